@@ -226,27 +226,45 @@ impl fmt::Display for RuntimeError {
         for frame in self.trace().iter() {
             let name = frame.module_name();
             let func_index = frame.func_index();
-            writeln!(f)?;
-            write!(f, "    at ")?;
-            match frame.function_name() {
-                Some(name) => match rustc_demangle::try_demangle(name) {
-                    Ok(name) => write!(f, "{}", name)?,
-                    Err(_) => write!(f, "{}", name)?,
-                },
-                None => write!(f, "<unnamed>")?,
+            let location = format!("{}[{}]:0x{:x}", name, func_index, frame.module_offset());
+            if frame.symbols().is_empty() {
+                writeln!(f)?;
+                write!(f, "    at ")?;
+                write_function_name(f, frame.function_name())?;
+                write!(f, " ({})", location)?;
+                continue;
+            }
+            // Functions inlined in the frame's function come first.
+            for symbol in frame.symbols() {
+                writeln!(f)?;
+                write!(f, "    at ")?;
+                write_function_name(f, symbol.name().or_else(|| frame.function_name()))?;
+                write!(f, " ({})", location)?;
+                if let Some(file) = symbol.file() {
+                    write!(f, "\n        at {}", file)?;
+                    if let Some(line) = symbol.line() {
+                        write!(f, ":{}", line)?;
+                        if let Some(column) = symbol.column() {
+                            write!(f, ":{}", column)?;
+                        }
+                    }
+                }
             }
-            write!(
-                f,
-                " ({}[{}]:0x{:x})",
-                name,
-                func_index,
-                frame.module_offset()
-            )?;
         }
         Ok(())
     }
 }
 
+fn write_function_name(f: &mut fmt::Formatter<'_>, name: Option<&str>) -> fmt::Result {
+    match name {
+        Some(name) => match rustc_demangle::try_demangle(name) {
+            Ok(name) => write!(f, "{}", name),
+            Err(_) => write!(f, "{}", name),
+        },
+        None => write!(f, "<unnamed>"),
+    }
+}
+
 impl std::error::Error for RuntimeError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         self.inner.source.source()
@@ -14,6 +14,18 @@ use crate::{
 use wasmer_types::RawValue;
 
 use crate::native_type::WasmTypeList;
+#[cfg(feature = "sys")]
+use std::{future::Future, pin::Pin};
+
+/// The future returned by an async host function, see
+/// [`Function::new_async_with_env`] and [`Function::new_typed_with_env_async`].
+///
+/// The future is polled on behalf of the [`Function::call_async`] that
+/// called the host function, and moves between threads together with it,
+/// which is why it must be `Send`.
+#[cfg(feature = "sys")]
+pub type AsyncFunctionFuture<'a, Rets = Vec<Value>> =
+    Pin<Box<dyn Future<Output = Result<Rets, RuntimeError>> + Send + 'a>>;
 
 /// The `HostFunction` trait represents the set of functions that
 /// can be used as host function. To uphold this statement, it is
@@ -156,6 +168,100 @@ impl Function {
         Self(function_impl::Function::new_with_env(store, env, ty, func))
     }
 
+    /// Creates a new async host `Function` (dynamic) with the provided
+    /// signature.
+    ///
+    /// The returned future is polled on the WebAssembly stack: whenever it is
+    /// pending, the running WebAssembly code is suspended until it can make
+    /// progress again. Async host functions can therefore only be called
+    /// through [`Function::call_async`] or [`TypedFunction::call_async`],
+    /// calling them synchronously results in a [`RuntimeError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let mut store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async(&mut store, &signature, |args| {
+    ///     let sum = args[0].unwrap_i32() + args[1].unwrap_i32();
+    ///     async move { Ok(vec![Value::I32(sum)]) }
+    /// });
+    /// ```
+    ///
+    /// The future may be resumed on another thread, so it can't hold on to
+    /// state that is not `Send`:
+    ///
+    /// ```compile_fail
+    /// # use std::rc::Rc;
+    /// # use wasmer::{Function, FunctionType, Store, Value};
+    /// # let mut store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![], vec![]);
+    ///
+    /// let f = Function::new_async(&mut store, &signature, |_args| {
+    ///     let state = Rc::new(());
+    ///     async move {
+    ///         let _state = state;
+    ///         Ok(vec![])
+    ///     }
+    /// });
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn new_async<FT, F, Fut>(store: &mut impl AsStoreMut, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Value]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Value>, RuntimeError>> + Send + 'static,
+    {
+        let env = FunctionEnv::new(&mut store.as_store_mut(), ());
+        Self::new_async_with_env(store, &env, ty, move |_env, args| Box::pin(func(args)))
+    }
+
+    /// Creates a new async host `Function` (dynamic) with the provided
+    /// signature and environment.
+    ///
+    /// The [`FunctionEnvMut`] can be held across `.await` points: the store
+    /// stays borrowed by the surrounding [`Function::call_async`] while the
+    /// WebAssembly code is suspended.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionEnv, FunctionType, Type, Store, Value};
+    /// # let mut store = Store::default();
+    /// # let env = FunctionEnv::new(&mut store, 1);
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async_with_env(&mut store, &env, &signature, |env, args| {
+    ///     Box::pin(async move {
+    ///         let offset = *env.data();
+    ///         Ok(vec![Value::I32(args[0].unwrap_i32() + offset)])
+    ///     })
+    /// });
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn new_async_with_env<FT, F, T: Send + 'static>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        ty: FT,
+        func: F,
+    ) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: for<'a> Fn(FunctionEnvMut<'a, T>, &'a [Value]) -> AsyncFunctionFuture<'a>
+            + 'static
+            + Send
+            + Sync,
+    {
+        Self(function_impl::Function::new_async_with_env(
+            store, env, ty, func,
+        ))
+    }
+
     /// Creates a new host `Function` from a native function.
     pub fn new_typed<F, Args, Rets>(store: &mut impl AsStoreMut, func: F) -> Self
     where
@@ -199,6 +305,69 @@ impl Function {
         ))
     }
 
+    /// Creates a new async host `Function` from a closure taking typed
+    /// arguments.
+    ///
+    /// This is the typed counterpart of [`Function::new_async`], the
+    /// function signature is retrieved from the argument and result types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, RuntimeError, Store};
+    /// # let mut store = Store::default();
+    /// #
+    /// let f = Function::new_typed_async(&mut store, |(a, b): (i32, i32)| async move {
+    ///     Ok::<i32, RuntimeError>(a + b)
+    /// });
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn new_typed_async<F, Args, Rets, Fut>(store: &mut impl AsStoreMut, func: F) -> Self
+    where
+        F: Fn(Args) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Rets, RuntimeError>> + Send + 'static,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        let env = FunctionEnv::new(&mut store.as_store_mut(), ());
+        Self::new_typed_with_env_async(store, &env, move |_env, args| Box::pin(func(args)))
+    }
+
+    /// Creates a new async host `Function` with an environment from a
+    /// closure taking typed arguments.
+    ///
+    /// This is the typed counterpart of [`Function::new_async_with_env`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionEnv, Store};
+    /// # let mut store = Store::default();
+    /// # let env = FunctionEnv::new(&mut store, 1);
+    /// #
+    /// let f = Function::new_typed_with_env_async(&mut store, &env, |env, a: i32| {
+    ///     Box::pin(async move { Ok(a + *env.data()) })
+    /// });
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn new_typed_with_env_async<T: Send + 'static, F, Args, Rets>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        func: F,
+    ) -> Self
+    where
+        F: for<'a> Fn(FunctionEnvMut<'a, T>, Args) -> AsyncFunctionFuture<'a, Rets>
+            + 'static
+            + Send
+            + Sync,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        Self(function_impl::Function::new_typed_with_env_async(
+            store, env, func,
+        ))
+    }
+
     /// Returns the [`FunctionType`] of the `Function`.
     ///
     /// # Example
@@ -302,6 +471,21 @@ impl Function {
         self.0.call(store, params)
     }
 
+    /// Call the `Function` function asynchronously.
+    ///
+    /// The WebAssembly code runs on a separate stack, which is suspended
+    /// whenever an async host function (see [`Function::new_async`]) is
+    /// waiting on its future. Dropping the returned future before it
+    /// completes cancels the call.
+    #[cfg(feature = "sys")]
+    pub async fn call_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+    ) -> Result<Box<[Value]>, RuntimeError> {
+        self.0.call_async(store, params).await
+    }
+
     #[doc(hidden)]
     #[allow(missing_docs)]
     pub fn call_raw(
@@ -4,6 +4,8 @@ pub(crate) mod memory;
 mod memory_view;
 mod table;
 
+#[cfg(feature = "sys")]
+pub use self::function::AsyncFunctionFuture;
 pub use self::function::{Function, HostFunction};
 pub use self::global::Global;
 pub use self::memory::{Memory, MemoryLocation, SharedMemory};
@@ -1,5 +1,9 @@
 use crate::engine::{AsEngineRef, Engine, EngineRef};
+#[cfg(feature = "sys")]
+use crate::RuntimeError;
 use derivative::Derivative;
+#[cfg(feature = "sys")]
+use std::sync::Arc;
 use std::{
     fmt,
     ops::{Deref, DerefMut},
@@ -10,7 +14,7 @@ pub use wasmer_types::{OnCalledAction, StoreId};
 #[cfg(feature = "sys")]
 use wasmer_vm::init_traps;
 #[cfg(feature = "sys")]
-pub use wasmer_vm::TrapHandlerFn;
+pub use wasmer_vm::{MemoryLimiter, TrapHandlerFn};
 
 #[cfg(feature = "sys")]
 pub use wasmer_vm::{StoreHandle, StoreObjects};
@@ -24,9 +28,31 @@ pub use crate::jsc::store::{StoreHandle, StoreObjects};
 /// Call handler for a store.
 // TODO: better documentation!
 pub type OnCalledHandler = Box<
-    dyn FnOnce(StoreMut<'_>) -> Result<OnCalledAction, Box<dyn std::error::Error + Send + Sync>>,
+    dyn FnOnce(StoreMut<'_>) -> Result<OnCalledAction, Box<dyn std::error::Error + Send + Sync>>
+        + Send,
 >;
 
+/// A transition between WebAssembly and the host, reported to the hook set
+/// with [`Store::set_call_hook`].
+#[cfg(feature = "sys")]
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum CallHook<'a> {
+    /// WebAssembly calls a host function.
+    CallingHost,
+    /// A host function returns to WebAssembly, successfully or not.
+    ReturningFromHost,
+    /// A trap unwound a call into WebAssembly, which returns this error.
+    Trapped(&'a RuntimeError),
+}
+
+/// A hook called on the transitions between WebAssembly and the host.
+///
+/// See [`Store::set_call_hook`].
+#[cfg(feature = "sys")]
+pub type CallHookFn =
+    dyn FnMut(StoreMut<'_>, CallHook<'_>) -> Result<(), RuntimeError> + Send + Sync;
+
 /// We require the context to have a fixed memory address for its lifetime since
 /// various bits of the VM have raw pointers that point back to it. Hence we
 /// wrap the actual context in a box.
@@ -39,6 +65,9 @@ pub(crate) struct StoreInner {
     #[cfg(feature = "sys")]
     #[derivative(Debug = "ignore")]
     pub(crate) trap_handler: Option<Box<TrapHandlerFn<'static>>>,
+    #[cfg(feature = "sys")]
+    #[derivative(Debug = "ignore")]
+    pub(crate) call_hook: Option<Box<CallHookFn>>,
     #[derivative(Debug = "ignore")]
     pub(crate) on_called: Option<OnCalledHandler>,
 }
@@ -70,6 +99,8 @@ impl Store {
                 engine: engine.into(),
                 #[cfg(feature = "sys")]
                 trap_handler: None,
+                #[cfg(feature = "sys")]
+                call_hook: None,
                 on_called: None,
             }),
         }
@@ -81,6 +112,55 @@ impl Store {
         self.inner.trap_handler = handler;
     }
 
+    #[cfg(feature = "sys")]
+    /// Set the hook called on the transitions between WebAssembly and the
+    /// host in this store.
+    ///
+    /// The hook is called with [`CallHook::CallingHost`] before any host
+    /// function is called by WebAssembly, and with
+    /// [`CallHook::ReturningFromHost`] once it returns. An error returned by
+    /// the hook on these transitions is raised as a trap in place of the
+    /// host function result.
+    ///
+    /// It's also called with [`CallHook::Trapped`] when a trap unwinds a
+    /// call made through [`Function::call`](crate::Function::call) or
+    /// [`TypedFunction::call`](crate::TypedFunction::call), or the start
+    /// function of an instance being created, in which case its result is
+    /// ignored.
+    ///
+    /// The hook isn't called for the calls it makes itself.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use wasmer::{CallHook, RuntimeError, Store};
+    ///
+    /// let host_calls = Arc::new(AtomicU64::new(0));
+    /// let mut store = Store::default();
+    /// let counter = host_calls.clone();
+    /// store.set_call_hook(Some(Box::new(move |_store, hook| {
+    ///     if let CallHook::CallingHost = hook {
+    ///         if counter.fetch_add(1, Ordering::SeqCst) >= 1000 {
+    ///             return Err(RuntimeError::new("too many host calls"));
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// })));
+    /// ```
+    pub fn set_call_hook(&mut self, hook: Option<Box<CallHookFn>>) {
+        self.inner.call_hook = hook;
+    }
+
+    #[cfg(feature = "sys")]
+    /// Set the limiter that decides whether the memories of this store may
+    /// grow, it's called whenever one of them grows (`memory.grow` or
+    /// [`Memory::grow`](crate::Memory::grow)).
+    pub fn set_memory_limiter(&mut self, limiter: Option<Arc<dyn MemoryLimiter>>) {
+        self.inner.objects.set_memory_limiter(limiter);
+    }
+
     /// Returns the [`Engine`].
     pub fn engine(&self) -> &Engine {
         &self.inner.engine
@@ -109,6 +189,14 @@ impl PartialEq for Store {
 unsafe impl Send for Store {}
 unsafe impl Sync for Store {}
 
+// A `Store` is only a box around its `StoreInner`, so this asserts nothing
+// that the `Send` impl of `Store` above doesn't already: the handlers of the
+// store are all `Send`, and its objects, which are only `!Send` because of
+// the raw pointers of the VM, are only ever accessed through the store.
+// This makes a `StoreMut` (and so a `FunctionEnvMut`) `Send`, which lets the
+// futures of async host functions hold it across `.await` points.
+unsafe impl Send for StoreInner {}
+
 impl Default for Store {
     fn default() -> Self {
         Self::new(Engine::default())
@@ -218,6 +306,13 @@ impl<'a> StoreMut<'a> {
         Self { inner: &mut *raw }
     }
 
+    #[cfg(feature = "sys")]
+    /// Set the limiter that decides whether the memories of this store may
+    /// grow, see [`Store::set_memory_limiter`].
+    pub fn set_memory_limiter(&mut self, limiter: Option<Arc<dyn MemoryLimiter>>) {
+        self.inner.objects.set_memory_limiter(limiter);
+    }
+
     // TODO: OnCalledAction is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
     /// Sets the unwind callback which will be invoked when the call finishes
     pub fn on_called<F>(&mut self, callback: F)
@@ -229,6 +324,28 @@ impl<'a> StoreMut<'a> {
     {
         self.inner.on_called.replace(Box::new(callback));
     }
+
+    /// Calls the call hook of the store, if any.
+    #[cfg(feature = "sys")]
+    pub(crate) fn call_hook(&mut self, hook: CallHook<'_>) -> Result<(), RuntimeError> {
+        let mut call_hook = match self.inner.call_hook.take() {
+            Some(call_hook) => call_hook,
+            None => return Ok(()),
+        };
+        // The hook is taken out of the store while it runs, so that it isn't
+        // called for the calls it makes itself.
+        let result = call_hook(self.as_store_mut(), hook);
+        self.inner.call_hook = Some(call_hook);
+        result
+    }
+
+    /// Reports a trap which unwound a call to the call hook of the store,
+    /// returning the error of the call.
+    #[cfg(feature = "sys")]
+    pub(crate) fn trapped(&mut self, error: RuntimeError) -> RuntimeError {
+        let _ = self.call_hook(CallHook::Trapped(&error));
+        error
+    }
 }
 
 /// Helper trait for a value that is convertible to a [`StoreRef`].
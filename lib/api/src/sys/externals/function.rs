@@ -1,16 +1,18 @@
-use crate::externals::function::{HostFunction, WithEnv, WithoutEnv};
+use crate::externals::function::{AsyncFunctionFuture, HostFunction, WithEnv, WithoutEnv};
 use crate::native_type::{FromToNativeWasmType, IntoResult, NativeWasmTypeInto, WasmTypeList};
-use crate::store::{AsStoreMut, AsStoreRef, StoreInner, StoreMut};
+use crate::store::{AsStoreMut, AsStoreRef, CallHook, StoreInner, StoreMut};
 use crate::sys::engine::NativeEngineExt;
 use crate::vm::{VMExternFunction, VMFunctionCallback};
 use crate::{FunctionEnv, FunctionEnvMut, FunctionType, RuntimeError, Value};
+use std::future::Future;
 use std::panic::{self, AssertUnwindSafe};
 use std::{cell::UnsafeCell, cmp::max, ffi::c_void};
 use wasmer_types::{NativeWasmType, RawValue};
 use wasmer_vm::{
-    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, MaybeInstanceOwned,
-    StoreHandle, VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef,
-    VMFunction, VMFunctionContext, VMFunctionKind, VMTrampoline,
+    await_on_wasm_stack, on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline,
+    wasmer_call_trampoline_async, MaybeInstanceOwned, StoreHandle, VMCallerCheckedAnyfunc,
+    VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef, VMFunction, VMFunctionContext,
+    VMFunctionKind, VMTrampoline,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -55,26 +57,126 @@ impl Function {
                     func_env: func_env.clone(),
                 };
                 let returns = func(env, &args)?;
+                write_dynamic_returns(&store, &func_ty, &returns, values_vec)
+            }
+        };
+        Self::new_dynamic(
+            store,
+            function_type,
+            DynamicFunction {
+                func: wrapper,
+                raw_store,
+                is_async: false,
+            },
+        )
+    }
 
-                // We need to dynamically check that the returns
-                // match the expected types, as well as expected length.
-                let return_types = returns.iter().map(|ret| ret.ty());
-                if return_types.ne(func_ty.results().iter().copied()) {
-                    return Err(RuntimeError::new(format!(
-                        "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
-                        func_ty.results(),
-                        returns.iter().map(|ret| ret.ty())
-                    )));
-                }
-                for (i, ret) in returns.iter().enumerate() {
-                    *values_vec.add(i) = ret.as_raw(&store);
+    pub fn new_async_with_env<FT, F, T: Send + 'static>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        ty: FT,
+        func: F,
+    ) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: for<'a> Fn(FunctionEnvMut<'a, T>, &'a [Value]) -> AsyncFunctionFuture<'a>
+            + 'static
+            + Send
+            + Sync,
+    {
+        let function_type = ty.into();
+        let func_ty = function_type.clone();
+        let func_env = env.clone();
+        let raw_store = store.as_store_mut().as_raw() as *mut u8;
+        let wrapper = move |values_vec: *mut RawValue| -> Result<(), RuntimeError> {
+            unsafe {
+                let args = {
+                    let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                    func_ty
+                        .params()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, ty)| Value::from_raw(&mut store, *ty, *values_vec.add(i)))
+                        .collect::<Vec<_>>()
+                };
+                let env = FunctionEnvMut {
+                    store_mut: StoreMut::from_raw(raw_store as *mut StoreInner),
+                    func_env: func_env.clone(),
+                };
+                let returns = await_host_future(func(env, &args))??;
+                let store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                write_dynamic_returns(&store, &func_ty, &returns, values_vec)
+            }
+        };
+        Self::new_dynamic(
+            store,
+            function_type,
+            DynamicFunction {
+                func: wrapper,
+                raw_store,
+                is_async: true,
+            },
+        )
+    }
+
+    pub fn new_typed_with_env_async<T: Send + 'static, F, Args, Rets>(
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<T>,
+        func: F,
+    ) -> Self
+    where
+        F: for<'a> Fn(FunctionEnvMut<'a, T>, Args) -> AsyncFunctionFuture<'a, Rets>
+            + 'static
+            + Send
+            + Sync,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        let function_type = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+        let func_env = env.clone();
+        let raw_store = store.as_store_mut().as_raw() as *mut u8;
+        let wrapper = move |values_vec: *mut RawValue| -> Result<(), RuntimeError> {
+            unsafe {
+                let args = {
+                    let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                    let args = std::slice::from_raw_parts(values_vec, Args::size() as usize);
+                    Args::from_slice(&mut store, args).unwrap()
+                };
+                let env = FunctionEnvMut {
+                    store_mut: StoreMut::from_raw(raw_store as *mut StoreInner),
+                    func_env: func_env.clone(),
+                };
+                let returns = await_host_future(func(env, args))??;
+                let mut store = StoreMut::from_raw(raw_store as *mut StoreInner);
+                let mut returns = returns.into_array(&mut store);
+                for (i, ret) in returns.as_mut().iter().enumerate() {
+                    *values_vec.add(i) = *ret;
                 }
+                Ok(())
             }
-            Ok(())
         };
+        Self::new_dynamic(
+            store,
+            function_type,
+            DynamicFunction {
+                func: wrapper,
+                raw_store,
+                is_async: true,
+            },
+        )
+    }
+
+    fn new_dynamic<F>(
+        store: &mut impl AsStoreMut,
+        function_type: FunctionType,
+        ctx: DynamicFunction<F>,
+    ) -> Self
+    where
+        F: Fn(*mut RawValue) -> Result<(), RuntimeError> + 'static,
+    {
         let mut host_data = Box::new(VMDynamicFunctionContext {
             address: std::ptr::null(),
-            ctx: DynamicFunction { func: wrapper },
+            ctx,
         });
         host_data.address = host_data.ctx.func_body_ptr();
 
@@ -213,6 +315,19 @@ impl Function {
         params: &[Value],
         results: &mut [Value],
     ) -> Result<(), RuntimeError> {
+        let values_vec = self.params_to_raw(store, params, results)?;
+
+        // Invoke the call
+        self.call_wasm_raw(store, trampoline, values_vec, results)?;
+        Ok(())
+    }
+
+    fn params_to_raw(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+        results: &[Value],
+    ) -> Result<Vec<RawValue>, RuntimeError> {
         let format_types_for_error_message = |items: &[Value]| {
             items
                 .iter()
@@ -255,9 +370,7 @@ impl Function {
             *slot = arg.as_raw(store);
         }
 
-        // Invoke the call
-        self.call_wasm_raw(store, trampoline, values_vec, results)?;
-        Ok(())
+        Ok(values_vec)
     }
 
     fn call_wasm_raw(
@@ -305,7 +418,7 @@ impl Function {
             r
         };
         if let Err(error) = result {
-            return Err(error.into());
+            return Err(store.as_store_mut().trapped(error.into()));
         }
 
         // Load the return values out of `values_vec`.
@@ -319,6 +432,76 @@ impl Function {
         Ok(())
     }
 
+    async fn call_wasm_raw_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        trampoline: VMTrampoline,
+        mut params: Vec<RawValue>,
+        results: &mut [Value],
+    ) -> Result<(), RuntimeError> {
+        self.call_trampoline_async(store, trampoline, &mut params)
+            .await?;
+
+        // Load the return values out of `values_vec`.
+        let signature = self.ty(store);
+        for (index, &value_type) in signature.results().iter().enumerate() {
+            unsafe {
+                results[index] = Value::from_raw(store, value_type, params[index]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls `trampoline` on a separate wasm stack with `args_rets` as its
+    /// arguments and results buffer, running the `on_called` handler of the
+    /// store after every call.
+    ///
+    /// This is shared by [`Function::call_async`] and
+    /// [`crate::TypedFunction::call_async`].
+    pub(crate) async fn call_trampoline_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        trampoline: VMTrampoline,
+        args_rets: &mut [RawValue],
+    ) -> Result<(), RuntimeError> {
+        // TODO: This loop is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
+        let result = loop {
+            let call = {
+                let storeref = store.as_store_ref();
+                let vm_function = self.handle.get(storeref.objects());
+                let config = storeref.engine().tunables().vmconfig();
+                unsafe {
+                    wasmer_call_trampoline_async(
+                        storeref.signal_handler(),
+                        config,
+                        vm_function.anyfunc.as_ptr().as_ref().vmctx,
+                        trampoline,
+                        vm_function.anyfunc.as_ptr().as_ref().func_ptr,
+                        args_rets.as_mut_ptr() as *mut u8,
+                    )
+                }
+            };
+            let result = call.await;
+            let store_mut = store.as_store_mut();
+            if let Some(callback) = store_mut.inner.on_called.take() {
+                match callback(store_mut) {
+                    Ok(wasmer_types::OnCalledAction::InvokeAgain) => continue,
+                    Ok(wasmer_types::OnCalledAction::Finish) => {}
+                    Ok(wasmer_types::OnCalledAction::Trap(trap)) => {
+                        return Err(RuntimeError::user(trap));
+                    }
+                    Err(trap) => return Err(RuntimeError::user(trap)),
+                }
+            }
+            break result;
+        };
+        if let Err(error) = result {
+            return Err(store.as_store_mut().trapped(error.into()));
+        }
+        Ok(())
+    }
+
     pub fn result_arity(&self, store: &impl AsStoreRef) -> usize {
         self.ty(store).results().len()
     }
@@ -341,6 +524,26 @@ impl Function {
         Ok(results.into_boxed_slice())
     }
 
+    pub async fn call_async(
+        &self,
+        store: &mut impl AsStoreMut,
+        params: &[Value],
+    ) -> Result<Box<[Value]>, RuntimeError> {
+        let trampoline = unsafe {
+            self.handle
+                .get(store.as_store_ref().objects())
+                .anyfunc
+                .as_ptr()
+                .as_ref()
+                .call_trampoline
+        };
+        let mut results = vec![Value::null(); self.result_arity(store)];
+        let values_vec = self.params_to_raw(store, params, &results)?;
+        self.call_wasm_raw_async(store, trampoline, values_vec, &mut results)
+            .await?;
+        Ok(results.into_boxed_slice())
+    }
+
     #[doc(hidden)]
     #[allow(missing_docs)]
     pub fn call_raw(
@@ -407,9 +610,46 @@ impl Function {
     }
 }
 
+/// Drives the future of an async host function to completion on the wasm
+/// stack of the surrounding `call_async`.
+fn await_host_future<F: Future + Send>(future: F) -> Result<F::Output, RuntimeError> {
+    await_on_wasm_stack(future).ok_or_else(|| {
+        RuntimeError::new("async host functions can only be called through `call_async`")
+    })
+}
+
+/// Checks the values returned by a dynamic host function against its
+/// signature and writes them into `values_vec`.
+unsafe fn write_dynamic_returns(
+    store: &StoreMut,
+    func_ty: &FunctionType,
+    returns: &[Value],
+    values_vec: *mut RawValue,
+) -> Result<(), RuntimeError> {
+    // We need to dynamically check that the returns
+    // match the expected types, as well as expected length.
+    let return_types = returns.iter().map(|ret| ret.ty());
+    if return_types.ne(func_ty.results().iter().copied()) {
+        return Err(RuntimeError::new(format!(
+            "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
+            func_ty.results(),
+            returns.iter().map(|ret| ret.ty())
+        )));
+    }
+    for (i, ret) in returns.iter().enumerate() {
+        *values_vec.add(i) = ret.as_raw(store);
+    }
+    Ok(())
+}
+
 /// Host state for a dynamic function.
 pub(crate) struct DynamicFunction<F> {
     func: F,
+    raw_store: *mut u8,
+    /// Async functions run on the wasm stack, so that the stack can be
+    /// suspended while their future is pending. The future itself is still
+    /// polled on the host stack, see [`await_on_wasm_stack`].
+    is_async: bool,
 }
 
 impl<F> DynamicFunction<F>
@@ -422,8 +662,20 @@ where
         this: &mut VMDynamicFunctionContext<Self>,
         values_vec: *mut RawValue,
     ) {
-        let result =
-            on_host_stack(|| panic::catch_unwind(AssertUnwindSafe(|| (this.ctx.func)(values_vec))));
+        // The store is only borrowed around the host function, which
+        // borrows it itself.
+        let raw_store = this.ctx.raw_store as *mut StoreInner;
+        let call = || {
+            StoreMut::from_raw(raw_store).call_hook(CallHook::CallingHost)?;
+            let result = (this.ctx.func)(values_vec);
+            let hook_result = StoreMut::from_raw(raw_store).call_hook(CallHook::ReturningFromHost);
+            result.and(hook_result)
+        };
+        let result = if this.ctx.is_async {
+            panic::catch_unwind(AssertUnwindSafe(call))
+        } else {
+            on_host_stack(|| panic::catch_unwind(AssertUnwindSafe(call)))
+        };
 
         match result {
             Ok(Ok(())) => {}
@@ -452,6 +704,18 @@ where
     }
 }
 
+/// Reports the return of a static host function to the call hook of the
+/// store, and returns its result, or the error of the hook.
+fn host_call_result<T, E: std::error::Error + Send + Sync + 'static>(
+    store: &mut StoreMut,
+    result: Result<T, E>,
+) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+    let hook_result = store.call_hook(CallHook::ReturningFromHost);
+    let value = result?;
+    hook_result?;
+    Ok(value)
+}
+
 /// Represents a low-level Wasm static host function. See
 /// [`crate::Function::new_typed`] and
 /// [`crate::Function::new_typed_with_env`] to learn more.
@@ -496,6 +760,7 @@ macro_rules! impl_host_function {
                         let result = on_host_stack(|| {
                             // println!("func wrapper1");
                             panic::catch_unwind(AssertUnwindSafe(|| {
+                                store.call_hook(CallHook::CallingHost)?;
                                 $(
                                     let $x = FromToNativeWasmType::from_native(NativeWasmTypeInto::from_abi(&mut store, $x));
                                 )*
@@ -506,13 +771,14 @@ macro_rules! impl_host_function {
                                     func_env: env.env.clone(),
                                 };
                                 // println!("func wrapper3");
-                                (env.func)(f_env, $($x),* ).into_result()
+                                let result = (env.func)(f_env, $($x),* ).into_result();
+                                host_call_result(&mut store, result)
                             }))
                         });
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(&mut store),
-                            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+                            Ok(Err(trap)) => raise_user_trap(trap),
                             Err(panic) => resume_panic(panic) ,
                         }
                     }
@@ -580,16 +846,18 @@ macro_rules! impl_host_function {
                         let result = on_host_stack(|| {
                             // println!("func wrapper1");
                             panic::catch_unwind(AssertUnwindSafe(|| {
+                                store.call_hook(CallHook::CallingHost)?;
                                 $(
                                     let $x = FromToNativeWasmType::from_native(NativeWasmTypeInto::from_abi(&mut store, $x));
                                 )*
-                                (env.func)($($x),* ).into_result()
+                                let result = (env.func)($($x),* ).into_result();
+                                host_call_result(&mut store, result)
                             }))
                         });
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(&mut store),
-                            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+                            Ok(Err(trap)) => raise_user_trap(trap),
                             Err(panic) => resume_panic(panic) ,
                         }
                     }
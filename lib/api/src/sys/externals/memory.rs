@@ -52,7 +52,13 @@ impl Memory {
     where
         IntoPages: Into<Pages>,
     {
-        self.handle.get_mut(store.objects_mut()).grow(delta.into())
+        let objects = store.objects_mut();
+        assert_eq!(
+            self.handle.store_id(),
+            objects.id(),
+            "object used with the wrong context"
+        );
+        objects.grow_memory(self.handle.internal_handle(), delta.into())
     }
 
     pub fn grow_at_least(
@@ -60,9 +66,13 @@ impl Memory {
         store: &mut impl AsStoreMut,
         min_size: u64,
     ) -> Result<(), MemoryError> {
-        self.handle
-            .get_mut(store.objects_mut())
-            .grow_at_least(min_size)
+        let objects = store.objects_mut();
+        assert_eq!(
+            self.handle.store_id(),
+            objects.id(),
+            "object used with the wrong context"
+        );
+        objects.grow_memory_at_least(self.handle.internal_handle(), min_size)
     }
 
     pub fn reset(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
@@ -10,11 +10,16 @@ pub(crate) mod typed_function;
 
 pub use crate::sys::engine::{get_default_compiler_config, NativeEngineExt};
 pub use crate::sys::tunables::BaseTunables;
+#[cfg(unix)]
+pub use crate::sys::tunables::{PoolingAllocator, PoolingLimits, PoolingTunables};
+#[cfg(unix)]
+pub use wasmer_compiler::GuestProfiler;
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware,
+    wasmparser, CompilerConfig, FileSystemFunctionCache, FunctionCache, FunctionCacheKey,
+    FunctionMiddleware, InMemoryFunctionCache, MiddlewareReaderState, ModuleMiddleware,
 };
-pub use wasmer_compiler::{Artifact, EngineBuilder, Features, Tunables};
+pub use wasmer_compiler::{Artifact, EngineBuilder, Features, ProfilingStrategy, Tunables};
 #[cfg(feature = "cranelift")]
 pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
 #[cfg(feature = "llvm")]
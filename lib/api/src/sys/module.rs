@@ -163,8 +163,14 @@ impl Module {
             // of this steps traps, we still need to keep the instance alive
             // as some of the Instance elements may have placed in other
             // instance tables.
-            self.artifact
-                .finish_instantiation(config, signal_handler, &mut instance_handle)?;
+            let result =
+                self.artifact
+                    .finish_instantiation(config, signal_handler, &mut instance_handle);
+            if let Err(wasmer_compiler::InstantiationError::Start(trap)) = result {
+                let error = store_mut.trapped(trap.into());
+                return Err(InstantiationError::Start(error));
+            }
+            result?;
 
             Ok(instance_handle)
         }
@@ -1,4 +1,8 @@
 pub use wasmer_compiler::BaseTunables;
+#[cfg(unix)]
+pub use wasmer_compiler::PoolingTunables;
+#[cfg(unix)]
+pub use wasmer_vm::{PoolingAllocator, PoolingLimits};
 
 // All BaseTunable definition now is in wasmer_compile crate
 // Tests are still here
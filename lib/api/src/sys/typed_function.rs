@@ -73,7 +73,9 @@ macro_rules! impl_native_traits {
                     }
                     break;
                 }
-                r?;
+                if let Err(error) = r {
+                    return Err(store.as_store_mut().trapped(error.into()));
+                }
 
                 let num_rets = rets_list.len();
                 if !using_rets_array && num_rets > 0 {
@@ -101,6 +103,60 @@ macro_rules! impl_native_traits {
                 // Ok(Rets::from_c_struct(results))
             }
 
+            /// Call the typed func asynchronously and return results.
+            ///
+            /// The WebAssembly code runs on a separate stack which is
+            /// suspended whenever an async host function is waiting on its
+            /// future. See [`crate::Function::new_async`].
+            #[allow(unused_mut)]
+            #[allow(clippy::too_many_arguments)]
+            pub async fn call_async(&self, store: &mut impl AsStoreMut, $( $x: $x, )* ) -> Result<Rets, RuntimeError> {
+                let call_trampoline = unsafe {
+                    self.func.0
+                        .handle
+                        .get(store.as_store_ref().objects())
+                        .anyfunc
+                        .as_ptr()
+                        .as_ref()
+                        .call_trampoline
+                };
+                // Ensure all parameters come from the same context.
+                if $(!FromToNativeWasmType::is_from_store(&$x, store) ||)* false {
+                    return Err(RuntimeError::new(
+                        "cross-`Store` values are not supported",
+                    ));
+                }
+                let mut params_list = [ $( $x.to_native().into_raw(store) ),* ];
+                let mut rets_list_array = Rets::empty_array();
+                let rets_list: &mut [RawValue] = rets_list_array.as_mut();
+                let using_rets_array;
+                let args_rets: &mut [RawValue] = if params_list.len() > rets_list.len() {
+                    using_rets_array = false;
+                    params_list.as_mut()
+                } else {
+                    using_rets_array = true;
+                    for (i, &arg) in params_list.iter().enumerate() {
+                        rets_list[i] = arg;
+                    }
+                    rets_list.as_mut()
+                };
+
+                self.func.0.call_trampoline_async(store, call_trampoline, args_rets).await?;
+
+                let num_rets = rets_list.len();
+                if !using_rets_array && num_rets > 0 {
+                    let src_pointer = params_list.as_ptr();
+                    let rets_list = &mut rets_list_array.as_mut()[0] as *mut RawValue;
+                    unsafe {
+                        // we know it's not overlapping because `using_rets_array` is false
+                        std::ptr::copy_nonoverlapping(src_pointer,
+                                                        rets_list,
+                                                        num_rets);
+                    }
+                }
+                Ok(unsafe { Rets::from_array(store, rets_list_array) })
+            }
+
             #[doc(hidden)]
             #[allow(missing_docs)]
             #[allow(unused_mut)]
@@ -156,7 +212,9 @@ macro_rules! impl_native_traits {
                     }
                     break;
                 }
-                r?;
+                if let Err(error) = r {
+                    return Err(store.as_store_mut().trapped(error.into()));
+                }
 
                 let num_rets = rets_list.len();
                 if !using_rets_array && num_rets > 0 {
@@ -0,0 +1,253 @@
+#[cfg(feature = "sys")]
+pub mod async_functions {
+    use anyhow::Result;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use std::time::Duration;
+    use wasmer::*;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// A future that is pending the first time it is polled.
+    #[derive(Default)]
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    const WAT: &str = r#"(module
+        (func $double (import "host" "double") (param i32) (result i32))
+        (func (export "run") (param i32) (result i32)
+            (i32.add (call $double (local.get 0)) (i32.const 1))
+        )
+    )"#;
+
+    fn instantiate(store: &mut Store) -> Result<Instance> {
+        let module = Module::new(store, WAT)?;
+        let env = FunctionEnv::new(store, 2);
+        let ty = FunctionType::new([Type::I32], [Type::I32]);
+        let double = Function::new_async_with_env(store, &env, ty, |env, args| {
+            Box::pin(async move {
+                YieldOnce::default().await;
+                Ok(vec![Value::I32(args[0].unwrap_i32() * *env.data())])
+            })
+        });
+        let imports = imports! {
+            "host" => {
+                "double" => double,
+            },
+        };
+        Ok(Instance::new(store, &module, &imports)?)
+    }
+
+    #[test]
+    fn typed_call_async_suspends_on_host_future() -> Result<()> {
+        let mut store = Store::default();
+        let instance = instantiate(&mut store)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        let result = block_on(run.call_async(&mut store, 20))?;
+        assert_eq!(result, 41);
+        Ok(())
+    }
+
+    #[test]
+    fn dynamic_call_async_suspends_on_host_future() -> Result<()> {
+        let mut store = Store::default();
+        let instance = instantiate(&mut store)?;
+        let run = instance.exports.get_function("run")?;
+
+        let result = block_on(run.call_async(&mut store, &[Value::I32(3)]))?;
+        assert_eq!(result.to_vec(), vec![Value::I32(7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn call_async_future_is_send() -> Result<()> {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let mut store = Store::default();
+        let instance = instantiate(&mut store)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+        let dynamic_run = instance.exports.get_function("run")?.clone();
+
+        let call = run.call_async(&mut store, 1);
+        assert_send(&call);
+        assert_eq!(block_on(call)?, 3);
+
+        let call = dynamic_run.call_async(&mut store, &[Value::I32(1)]);
+        assert_send(&call);
+        assert_eq!(block_on(call)?.to_vec(), vec![Value::I32(3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn typed_async_host_function() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let env = FunctionEnv::new(&mut store, 3);
+        let triple = Function::new_typed_with_env_async(&mut store, &env, |env, value: i32| {
+            Box::pin(async move {
+                YieldOnce::default().await;
+                Ok(value * *env.data())
+            })
+        });
+        let imports = imports! {
+            "host" => {
+                "double" => triple,
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        assert_eq!(block_on(run.call_async(&mut store, 5))?, 16);
+        Ok(())
+    }
+
+    #[test]
+    fn call_async_moves_between_tokio_workers() -> Result<()> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .enable_time()
+            .build()?;
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let env = FunctionEnv::new(&mut store, 2);
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let ty = FunctionType::new([Type::I32], [Type::I32]);
+        let double = Function::new_async_with_env(&mut store, &env, ty, {
+            let threads = threads.clone();
+            move |env, args| {
+                let threads = threads.clone();
+                Box::pin(async move {
+                    threads.lock().unwrap().push(thread::current().id());
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    threads.lock().unwrap().push(thread::current().id());
+                    Ok(vec![Value::I32(args[0].unwrap_i32() * *env.data())])
+                })
+            }
+        });
+        let imports = imports! {
+            "host" => {
+                "double" => double,
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        let results = runtime.block_on(async move {
+            let mut results = Vec::new();
+            for i in 0..32 {
+                let (run, mut moved_store) = (run.clone(), store);
+                let (result, returned_store) = tokio::spawn(async move {
+                    let result = run.call_async(&mut moved_store, i).await;
+                    (result, moved_store)
+                })
+                .await
+                .unwrap();
+                store = returned_store;
+                results.push(result.unwrap());
+            }
+            results
+        });
+
+        assert_eq!(results, (0..32).map(|i| i * 2 + 1).collect::<Vec<_>>());
+        assert_eq!(threads.lock().unwrap().len(), 64);
+        Ok(())
+    }
+
+    #[test]
+    fn dropping_call_async_drops_the_pending_host_future() -> Result<()> {
+        struct SetOnDrop(Arc<AtomicBool>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()?;
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let dropped = Arc::new(AtomicBool::new(false));
+        let ty = FunctionType::new([Type::I32], [Type::I32]);
+        let hang = Function::new_async(&mut store, ty, {
+            let dropped = dropped.clone();
+            move |_args| {
+                let guard = SetOnDrop(dropped.clone());
+                async move {
+                    let _guard = guard;
+                    std::future::pending::<()>().await;
+                    Ok(vec![Value::I32(0)])
+                }
+            }
+        });
+        let imports = imports! {
+            "host" => {
+                "double" => hang,
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        let timed_out = runtime.block_on(async {
+            let call = run.call_async(&mut store, 1);
+            tokio::time::timeout(Duration::from_millis(10), call).await
+        });
+        assert!(timed_out.is_err());
+        assert!(dropped.load(Ordering::SeqCst));
+
+        // The store can still be used after the call was cancelled
+        let memory = Memory::new(&mut store, MemoryType::new(1, None, false))?;
+        assert_eq!(memory.view(&store).size(), Pages(1));
+        Ok(())
+    }
+
+    #[test]
+    fn sync_call_into_async_function_traps() -> Result<()> {
+        let mut store = Store::default();
+        let instance = instantiate(&mut store)?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        let err = run.call(&mut store, 1).unwrap_err();
+        assert!(err.message().contains("call_async"));
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(feature = "sys")]
+pub mod call_hook {
+    use anyhow::Result;
+    use std::sync::{Arc, Mutex};
+    use wasmer::*;
+    use wasmer_types::TrapCode;
+
+    const WAT: &str = r#"(module
+        (import "env" "typed" (func $typed (param i32) (result i32)))
+        (import "env" "dynamic" (func $dynamic (param i32) (result i32)))
+        (func (export "run") (param i32) (result i32)
+            (call $dynamic (call $typed (local.get 0))))
+        (func (export "trap")
+            unreachable)
+    )"#;
+
+    /// The transitions reported to the hook, as strings.
+    type Events = Arc<Mutex<Vec<String>>>;
+
+    fn instantiate(
+        hook: impl Fn(&CallHook) -> Result<(), RuntimeError> + Send + Sync + 'static,
+    ) -> Result<(Store, Instance, Events)> {
+        let events: Events = Arc::new(Mutex::new(vec![]));
+        let mut store = Store::default();
+        let recorded = events.clone();
+        store.set_call_hook(Some(Box::new(move |_store, call_hook| {
+            let event = match call_hook {
+                CallHook::CallingHost => "calling host".to_string(),
+                CallHook::ReturningFromHost => "returning from host".to_string(),
+                CallHook::Trapped(error) => format!("trapped: {}", error.message()),
+                _ => unreachable!(),
+            };
+            recorded.lock().unwrap().push(event);
+            hook(&call_hook)
+        })));
+
+        let module = Module::new(&store, WAT)?;
+        let log = events.clone();
+        let typed = Function::new_typed(&mut store, move |x: i32| {
+            log.lock().unwrap().push("typed".to_string());
+            x + 1
+        });
+        let log = events.clone();
+        let dynamic = Function::new(
+            &mut store,
+            FunctionType::new([Type::I32], [Type::I32]),
+            move |args| {
+                log.lock().unwrap().push("dynamic".to_string());
+                Ok(vec![Value::I32(args[0].unwrap_i32() * 2)])
+            },
+        );
+        let imports = imports! {
+            "env" => {
+                "typed" => typed,
+                "dynamic" => dynamic,
+            },
+        };
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        Ok((store, instance, events))
+    }
+
+    #[test]
+    fn host_calls_are_reported() -> Result<()> {
+        let (mut store, instance, events) = instantiate(|_| Ok(()))?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        assert_eq!(run.call(&mut store, 1)?, 4);
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "calling host",
+                "typed",
+                "returning from host",
+                "calling host",
+                "dynamic",
+                "returning from host",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hook_errors_trap() -> Result<()> {
+        let (mut store, instance, events) = instantiate(|call_hook| match call_hook {
+            CallHook::CallingHost => Err(RuntimeError::new("no host calls")),
+            _ => Ok(()),
+        })?;
+        let run = instance.exports.get_function("run")?;
+
+        let error = run.call(&mut store, &[Value::I32(1)]).unwrap_err();
+        assert_eq!(error.message(), "no host calls");
+        assert_eq!(
+            *events.lock().unwrap(),
+            ["calling host", "trapped: no host calls"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn traps_are_reported() -> Result<()> {
+        let (mut store, instance, events) = instantiate(|_| Ok(()))?;
+        let trap: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "trap")?;
+
+        let error = trap.call(&mut store).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+        assert_eq!(*events.lock().unwrap(), ["trapped: unreachable"]);
+        Ok(())
+    }
+
+    #[test]
+    fn start_function_traps_are_reported() -> Result<()> {
+        let events: Events = Arc::new(Mutex::new(vec![]));
+        let mut store = Store::default();
+        let recorded = events.clone();
+        store.set_call_hook(Some(Box::new(move |_store, call_hook| {
+            if let CallHook::Trapped(error) = call_hook {
+                recorded.lock().unwrap().push(error.message());
+            }
+            Ok(())
+        })));
+        let module = Module::new(&store, "(module (func unreachable) (start 0))")?;
+
+        let error = Instance::new(&mut store, &module, &imports! {}).unwrap_err();
+        assert!(matches!(error, InstantiationError::Start(_)));
+        assert_eq!(*events.lock().unwrap(), ["unreachable"]);
+        Ok(())
+    }
+}
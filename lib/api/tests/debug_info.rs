@@ -0,0 +1,239 @@
+#[cfg(all(feature = "sys", feature = "cranelift", target_arch = "x86_64"))]
+pub mod debug_info {
+    use anyhow::Result;
+    use wasmer::sys::{Cranelift, EngineBuilder};
+    use wasmer::*;
+
+    #[repr(C)]
+    struct JitCodeEntry {
+        next_entry: *const JitCodeEntry,
+        prev_entry: *const JitCodeEntry,
+        symfile_addr: *const u8,
+        symfile_size: u64,
+    }
+
+    #[repr(C)]
+    struct JitDescriptor {
+        version: u32,
+        action_flag: u32,
+        relevant_entry: *const JitCodeEntry,
+        first_entry: *const JitCodeEntry,
+    }
+
+    extern "C" {
+        static __jit_debug_descriptor: JitDescriptor;
+    }
+
+    /// The images registered with debuggers.
+    fn registered_images() -> Vec<Vec<u8>> {
+        let mut images = vec![];
+        unsafe {
+            let mut entry = std::ptr::addr_of!(__jit_debug_descriptor)
+                .read_volatile()
+                .first_entry;
+            while !entry.is_null() {
+                let image =
+                    std::slice::from_raw_parts((*entry).symfile_addr, (*entry).symfile_size as _);
+                images.push(image.to_vec());
+                entry = (*entry).next_entry;
+            }
+        }
+        images
+    }
+
+    /// A function symbol of an image: its name, address and length.
+    struct Symbol {
+        name: String,
+        address: u64,
+        length: u64,
+    }
+
+    fn read<const N: usize>(image: &[u8], offset: u64) -> [u8; N] {
+        image[offset as usize..offset as usize + N]
+            .try_into()
+            .unwrap()
+    }
+
+    /// The range of the `.text` section and the symbols of an ELF image.
+    fn parse(image: &[u8]) -> (std::ops::Range<u64>, Vec<Symbol>) {
+        assert_eq!(&image[..4], b"\x7fELF");
+        let section_headers = u64::from_le_bytes(read(image, 0x28));
+        let num_sections = u16::from_le_bytes(read(image, 0x3c)) as u64;
+        let section = |index: u64| {
+            let header = section_headers + index * 64;
+            (
+                u32::from_le_bytes(read(image, header + 4)),
+                u64::from_le_bytes(read(image, header + 0x10)),
+                u64::from_le_bytes(read(image, header + 0x18)),
+                u64::from_le_bytes(read(image, header + 0x20)),
+                u32::from_le_bytes(read(image, header + 0x28)) as u64,
+            )
+        };
+        let mut text = 0..0;
+        let mut symbols = vec![];
+        for index in 1..num_sections {
+            let (kind, address, offset, size, link) = section(index);
+            // SHT_NOBITS, only used for `.text`
+            if kind == 8 {
+                text = address..address + size;
+            }
+            // SHT_SYMTAB
+            if kind == 2 {
+                let (_, _, strtab, _, _) = section(link);
+                // The first symbol is the null one.
+                for symbol in (offset + 24..offset + size).step_by(24) {
+                    let name = strtab + u32::from_le_bytes(read(image, symbol)) as u64;
+                    let name = &image[name as usize..];
+                    let name = &name[..name.iter().position(|b| *b == 0).unwrap()];
+                    symbols.push(Symbol {
+                        name: String::from_utf8(name.to_vec()).unwrap(),
+                        address: u64::from_le_bytes(read(image, symbol + 8)),
+                        length: u64::from_le_bytes(read(image, symbol + 16)),
+                    });
+                }
+            }
+        }
+        (text, symbols)
+    }
+
+    /// The symbols named `name` in the registered images, each with the
+    /// range of the `.text` section of its image.
+    fn registered_symbols(name: &str) -> Vec<(std::ops::Range<u64>, Symbol)> {
+        registered_images()
+            .iter()
+            .flat_map(|image| {
+                let (text, symbols) = parse(image);
+                symbols
+                    .into_iter()
+                    .filter(|symbol| symbol.name == name)
+                    .map(move |symbol| (text.clone(), symbol))
+            })
+            .collect()
+    }
+
+    /// The address of the code a function jumps to.
+    fn code_address(store: &Store, function: &Function) -> u64 {
+        let funcref = Value::FuncRef(Some(function.clone())).as_raw(store);
+        // The body is the first field of the func ref.
+        unsafe { *(funcref.funcref as *const u64) }
+    }
+
+    #[test]
+    fn functions_are_registered() -> Result<()> {
+        let engine = EngineBuilder::new(Cranelift::default())
+            .set_debug_info(true)
+            .engine();
+        let mut store = Store::new(engine);
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $registered_double (param i32) (result i32)
+                    (i32.mul (local.get 0) (i32.const 2)))
+                (func $registered_run (export "run") (param i32) (result i32)
+                    (call $registered_double (local.get 0)))
+            )"#,
+        )?;
+        let double = registered_symbols("registered_double");
+        assert_eq!(double.len(), 1, "the module is registered once");
+
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let run = instance.exports.get_function("run")?;
+        let (text, symbol) = registered_symbols("registered_run").pop().unwrap();
+        assert_eq!(symbol.address, code_address(&store, run));
+        assert!(symbol.length > 0);
+        assert!(text.start <= symbol.address && symbol.address + symbol.length <= text.end);
+        let (_, double) = &double[0];
+        assert!(
+            double.address + double.length <= symbol.address
+                || symbol.address + symbol.length <= double.address
+        );
+        let run: TypedFunction<i32, i32> = run.typed(&store)?;
+        assert_eq!(run.call(&mut store, 21)?, 42);
+
+        // The image is unregistered with the code.
+        drop(instance);
+        drop(module);
+        drop(store);
+        assert!(registered_symbols("registered_double").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_functions_are_registered_once_compiled() -> Result<()> {
+        let engine = EngineBuilder::new(Cranelift::default())
+            .set_debug_info(true)
+            .set_lazy_compilation(true)
+            .engine();
+        let mut store = Store::new(engine);
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $lazy_triple (param i32) (result i32)
+                    (i32.mul (local.get 0) (i32.const 3)))
+                (func $lazy_run (export "run") (param i32) (result i32)
+                    (call $lazy_triple (local.get 0)))
+            )"#,
+        )?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+        // Only the entry point is compiled, the stub isn't described.
+        assert_eq!(registered_symbols("lazy_run").len(), 1);
+        assert!(registered_symbols("lazy_triple").is_empty());
+
+        assert_eq!(run.call(&mut store, 14)?, 42);
+        let triple = registered_symbols("lazy_triple");
+        assert_eq!(triple.len(), 1);
+        let (text, symbol) = &triple[0];
+        assert!(symbol.length > 0);
+        assert_eq!(*text, symbol.address..symbol.address + symbol.length);
+        Ok(())
+    }
+
+    #[cfg(feature = "singlepass")]
+    #[test]
+    fn tiered_functions_are_registered() -> Result<()> {
+        use std::thread;
+        use std::time::{Duration, Instant};
+        use wasmer::sys::Singlepass;
+
+        let engine = EngineBuilder::new(Singlepass::default())
+            .set_tier_up(Cranelift::default(), 100)
+            .set_debug_info(true)
+            .engine();
+        let mut store = Store::new(engine.clone());
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $tiered_sum (export "sum") (param $n i32) (result i32)
+                    (local $acc i32)
+                    (block $done
+                        (loop $next
+                            (br_if $done (i32.eqz (local.get $n)))
+                            (local.set $acc (i32.add (local.get $acc) (local.get $n)))
+                            (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                            (br $next)))
+                    (local.get $acc))
+            )"#,
+        )?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+        let baseline = registered_symbols("tiered_sum");
+        assert_eq!(baseline.len(), 1);
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while engine.tiered_up_functions() == 0 {
+            assert_eq!(sum.call(&mut store, 100)?, 5050);
+            assert!(Instant::now() < deadline, "$tiered_sum was never tiered up");
+            thread::sleep(Duration::from_millis(1));
+        }
+        let tiered = registered_symbols("tiered_sum");
+        assert_eq!(tiered.len(), 2);
+        let (text, symbol) = tiered
+            .iter()
+            .find(|(_, symbol)| symbol.address != baseline[0].1.address)
+            .unwrap();
+        assert!(symbol.length > 0);
+        assert!(text.start <= symbol.address && symbol.address + symbol.length <= text.end);
+        Ok(())
+    }
+}
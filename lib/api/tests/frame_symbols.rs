@@ -0,0 +1,178 @@
+#[cfg(feature = "sys")]
+pub mod frame_symbols {
+    use anyhow::Result;
+    use std::ops::Range;
+    use wasmer::*;
+
+    /// Appends a custom section to a module.
+    fn push_custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut payload = vec![];
+        leb128(&mut payload, name.len() as u64);
+        payload.extend_from_slice(name.as_bytes());
+        payload.extend_from_slice(data);
+        wasm.push(0);
+        leb128(wasm, payload.len() as u64);
+        wasm.extend_from_slice(&payload);
+    }
+
+    fn leb128(out: &mut Vec<u8>, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    /// Appends minimal DWARF debug info to a module, describing the
+    /// `body` range of its code section as the function `trap_here`, at
+    /// line 7, column 3 of `main.c`.
+    fn push_debug_info(wasm: &mut Vec<u8>, body: Range<u32>) {
+        let low_pc = body.start;
+        let code_len = body.end - body.start;
+
+        #[rustfmt::skip]
+        let abbrev = [
+            // DW_TAG_compile_unit, with children.
+            1, 0x11, 1,
+            0x03, 0x08, // DW_AT_name, DW_FORM_string
+            0x10, 0x17, // DW_AT_stmt_list, DW_FORM_sec_offset
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x06, // DW_AT_high_pc, DW_FORM_data4
+            0, 0,
+            // DW_TAG_subprogram, without children.
+            2, 0x2e, 0,
+            0x03, 0x08, // DW_AT_name, DW_FORM_string
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x06, // DW_AT_high_pc, DW_FORM_data4
+            0, 0,
+            0,
+        ];
+
+        let mut dies = vec![1];
+        dies.extend_from_slice(b"main.c\0");
+        dies.extend_from_slice(&0u32.to_le_bytes());
+        dies.extend_from_slice(&low_pc.to_le_bytes());
+        dies.extend_from_slice(&code_len.to_le_bytes());
+        dies.push(2);
+        dies.extend_from_slice(b"trap_here\0");
+        dies.extend_from_slice(&low_pc.to_le_bytes());
+        dies.extend_from_slice(&code_len.to_le_bytes());
+        dies.push(0);
+        let mut info = vec![];
+        info.extend_from_slice(&(7 + dies.len() as u32).to_le_bytes());
+        info.extend_from_slice(&4u16.to_le_bytes());
+        info.extend_from_slice(&0u32.to_le_bytes());
+        info.push(4);
+        info.extend_from_slice(&dies);
+
+        #[rustfmt::skip]
+        let mut header = vec![
+            1, 1, 1, (-5i8) as u8, 14, 13,
+            0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1,
+            // No include directories.
+            0,
+        ];
+        header.extend_from_slice(b"main.c\0");
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        #[rustfmt::skip]
+        let mut program = vec![
+            0x03, 6, // DW_LNS_advance_line
+            0x05, 3, // DW_LNS_set_column
+            0x00, 5, 0x02, // DW_LNE_set_address
+        ];
+        program.extend_from_slice(&low_pc.to_le_bytes());
+        program.extend_from_slice(&[0x01, 0x02]); // DW_LNS_copy, DW_LNS_advance_pc
+        leb128(&mut program, code_len as u64);
+        program.extend_from_slice(&[0x00, 1, 0x01]); // DW_LNE_end_sequence
+        let mut line = vec![];
+        line.extend_from_slice(&(6 + header.len() as u32 + program.len() as u32).to_le_bytes());
+        line.extend_from_slice(&4u16.to_le_bytes());
+        line.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        line.extend_from_slice(&header);
+        line.extend_from_slice(&program);
+
+        push_custom_section(wasm, ".debug_abbrev", &abbrev);
+        push_custom_section(wasm, ".debug_info", &info);
+        push_custom_section(wasm, ".debug_line", &line);
+    }
+
+    const WAT: &str = r#"(module
+        (func $trap_here (export "run")
+            unreachable)
+    )"#;
+
+    /// `WAT` with the function count and the body size of its code
+    /// section encoded on 5 bytes, as some linkers do to patch them in
+    /// place. The body of `trap_here` is at `10..13` in the code section.
+    #[rustfmt::skip]
+    const PADDED_WASM: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+        // Type section: () -> ()
+        0x01, 4, 1, 0x60, 0, 0,
+        // Function section
+        0x03, 2, 1, 0,
+        // Export section: "run"
+        0x07, 7, 1, 3, b'r', b'u', b'n', 0x00, 0,
+        // Code section
+        0x0a, 13,
+        0x81, 0x80, 0x80, 0x80, 0x00,
+        0x83, 0x80, 0x80, 0x80, 0x00,
+        0, 0x00, 0x0b,
+    ];
+
+    fn assert_symbolicated(wasm: Vec<u8>) -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, wasm)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let run: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "run")?;
+
+        let error = run.call(&mut store).unwrap_err();
+        let frame = &error.trace()[0];
+        assert_eq!(
+            frame.symbols(),
+            [FrameSymbol::new(
+                Some("trap_here".to_string()),
+                Some("main.c".to_string()),
+                Some(7),
+                Some(3),
+            )]
+        );
+        assert!(
+            error.to_string().contains("\n        at main.c:7:3"),
+            "unexpected backtrace:\n{}",
+            error
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn trace_is_symbolicated() -> Result<()> {
+        let mut wasm = wat::parse_str(WAT)?;
+        // The body of `trap_here`, after the function count and its size.
+        push_debug_info(&mut wasm, 2..5);
+        assert_symbolicated(wasm)
+    }
+
+    #[test]
+    fn trace_is_symbolicated_with_padded_sizes() -> Result<()> {
+        let mut wasm = PADDED_WASM.to_vec();
+        push_debug_info(&mut wasm, 10..13);
+        assert_symbolicated(wasm)
+    }
+
+    #[test]
+    fn trace_without_debug_info() -> Result<()> {
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let run: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "run")?;
+
+        let error = run.call(&mut store).unwrap_err();
+        assert!(error.trace()[0].symbols().is_empty());
+        Ok(())
+    }
+}
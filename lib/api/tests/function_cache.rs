@@ -0,0 +1,130 @@
+#[cfg(all(feature = "sys", feature = "cranelift"))]
+pub mod function_cache {
+    use anyhow::Result;
+    use std::sync::Arc;
+    use wasmer::sys::{
+        Cranelift, EngineBuilder, FunctionMiddleware, InMemoryFunctionCache, ModuleMiddleware,
+    };
+    use wasmer::*;
+    use wasmer_types::LocalFunctionIndex;
+
+    fn module_wat_with(answer: i32, extra: &str) -> String {
+        format!(
+            r#"(module
+                (func $double (param i32) (result i32)
+                    (i32.mul (local.get 0) (i32.const 2)))
+                (func $answer (result i32)
+                    (i32.const {answer}))
+                (func (export "run") (param i32) (result i32)
+                    (i32.add (call $double (local.get 0)) (call $answer)))
+                {extra}
+            )"#
+        )
+    }
+
+    fn module_wat(answer: i32) -> String {
+        module_wat_with(answer, "")
+    }
+
+    fn cached_engine(cache: Arc<InMemoryFunctionCache>, config: Cranelift) -> Engine {
+        EngineBuilder::new(config)
+            .set_function_cache(cache)
+            .engine()
+            .into()
+    }
+
+    fn run(store: &mut Store, wat: &str, arg: i32) -> Result<i32> {
+        let module = Module::new(store, wat)?;
+        let instance = Instance::new(store, &module, &imports! {})?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(store, "run")?;
+        Ok(run.call(store, arg)?)
+    }
+
+    #[test]
+    fn unchanged_functions_are_reused() -> Result<()> {
+        let cache = Arc::new(InMemoryFunctionCache::new());
+        let mut store = Store::new(cached_engine(cache.clone(), Cranelift::default()));
+
+        assert_eq!(run(&mut store, &module_wat(1), 20)?, 41);
+        assert_eq!(cache.len(), 3);
+
+        // Only `$answer` changed, so a single function is added.
+        assert_eq!(run(&mut store, &module_wat(2), 20)?, 42);
+        assert_eq!(cache.len(), 4);
+
+        // Everything comes from the cache.
+        assert_eq!(run(&mut store, &module_wat(1), 5)?, 11);
+        assert_eq!(cache.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn functions_are_reused_when_others_are_added() -> Result<()> {
+        let cache = Arc::new(InMemoryFunctionCache::new());
+        let mut store = Store::new(cached_engine(cache.clone(), Cranelift::default()));
+
+        assert_eq!(run(&mut store, &module_wat(1), 20)?, 41);
+        assert_eq!(cache.len(), 3);
+
+        // A new function leaves the others untouched.
+        let extra = r#"(func $triple (param i32) (result i32)
+            (i32.mul (local.get 0) (i32.const 3)))"#;
+        assert_eq!(run(&mut store, &module_wat_with(1, extra), 20)?, 41);
+        assert_eq!(cache.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn functions_are_reused_when_they_move() -> Result<()> {
+        let cache = Arc::new(InMemoryFunctionCache::new());
+        let mut store = Store::new(cached_engine(cache.clone(), Cranelift::default()));
+
+        assert_eq!(run(&mut store, &module_wat(1), 20)?, 41);
+        assert_eq!(cache.len(), 3);
+
+        // A function is added first, and the callees of `run` are swapped,
+        // so every function moves, and `run` calls them at other indexes.
+        let moved = r#"(module
+            (func $triple (param i32) (result i32)
+                (i32.mul (local.get 0) (i32.const 3)))
+            (func $answer (result i32)
+                (i32.const 1))
+            (func $double (param i32) (result i32)
+                (i32.mul (local.get 0) (i32.const 2)))
+            (func (export "run") (param i32) (result i32)
+                (i32.add (call $double (local.get 0)) (call $answer)))
+        )"#;
+        assert_eq!(run(&mut store, moved, 20)?, 41);
+        assert_eq!(cache.len(), 4);
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct Passthrough;
+
+    impl ModuleMiddleware for Passthrough {
+        fn generate_function_middleware(
+            &self,
+            _: LocalFunctionIndex,
+        ) -> Box<dyn FunctionMiddleware> {
+            Box::new(FunctionPassthrough)
+        }
+    }
+
+    #[derive(Debug)]
+    struct FunctionPassthrough;
+
+    impl FunctionMiddleware for FunctionPassthrough {}
+
+    #[test]
+    fn middlewares_without_cache_key_disable_the_cache() -> Result<()> {
+        let cache = Arc::new(InMemoryFunctionCache::new());
+        let mut config = Cranelift::default();
+        config.push_middleware(Arc::new(Passthrough));
+        let mut store = Store::new(cached_engine(cache.clone(), config));
+
+        assert_eq!(run(&mut store, &module_wat(1), 20)?, 41);
+        assert!(cache.is_empty());
+        Ok(())
+    }
+}
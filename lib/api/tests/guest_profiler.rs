@@ -0,0 +1,67 @@
+#[cfg(all(feature = "sys", feature = "cranelift", unix))]
+pub mod guest_profiler {
+    use anyhow::Result;
+    use std::time::{Duration, Instant};
+    use wasmer::sys::{CompilerConfig, Cranelift, GuestProfiler};
+    use wasmer::*;
+
+    #[test]
+    fn samples_wasm_stacks() -> Result<()> {
+        // Leaf functions like `$busy` may omit their frame otherwise.
+        let mut compiler = Cranelift::default();
+        compiler.enable_frame_pointers();
+        let mut store = Store::new(compiler);
+        let module = Module::new(
+            &store,
+            r#"(module
+                (func $busy (param i32) (result i32)
+                    (loop $again
+                        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                        (br_if $again (i32.ne (local.get 0) (i32.const 0))))
+                    (local.get 0))
+                (func $outer (export "run") (param i32) (result i32)
+                    (call $busy (local.get 0)))
+            )"#,
+        )?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+
+        let profiler = GuestProfiler::start(Duration::from_millis(1));
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while profiler.sample_count() < 20 && Instant::now() < deadline {
+            run.call(&mut store, 10_000_000)?;
+        }
+
+        let mut collapsed = vec![];
+        profiler.write_collapsed(&mut collapsed)?;
+        let collapsed = String::from_utf8(collapsed)?;
+        assert!(
+            collapsed
+                .lines()
+                .any(|line| line.starts_with("outer;busy ")),
+            "unexpected profile:\n{}",
+            collapsed
+        );
+        for line in collapsed.lines() {
+            let (_, count) = line.rsplit_once(' ').unwrap();
+            assert!(count.parse::<u64>()? > 0);
+        }
+
+        // Nothing is sampled once the profiler is dropped.
+        drop(profiler);
+        run.call(&mut store, 10_000_000)?;
+        Ok(())
+    }
+
+    #[test]
+    fn frame_pointers_are_cached_apart() {
+        let mut compiler = Cranelift::default();
+        compiler.enable_frame_pointers();
+        let with_frame_pointers = Engine::from(compiler);
+        let without_frame_pointers = Engine::from(Cranelift::default());
+        assert_ne!(
+            with_frame_pointers.deterministic_id(),
+            without_frame_pointers.deterministic_id()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(all(feature = "sys", feature = "cranelift"))]
+pub mod lazy_compilation {
+    use anyhow::Result;
+    use std::fmt::Write;
+    use wasmer::sys::{Cranelift, EngineBuilder};
+    use wasmer::*;
+    use wasmer_types::TrapCode;
+
+    const WAT: &str = r#"(module
+        (type $binop (func (param i32 i32) (result i32)))
+        (table 2 funcref)
+        (elem (i32.const 0) $add $mul)
+        (func $add (type $binop) (i32.add (local.get 0) (local.get 1)))
+        (func $mul (type $binop) (i32.mul (local.get 0) (local.get 1)))
+        (func $square (param i32) (result i32)
+            (call $mul (local.get 0) (local.get 0)))
+        (func (export "square") (param i32) (result i32)
+            (call $square (local.get 0)))
+        (func (export "apply") (param i32 i32 i32) (result i32)
+            (call_indirect (type $binop) (local.get 1) (local.get 2) (local.get 0)))
+        (func $div (param i32 i32) (result i32)
+            (i32.div_s (local.get 0) (local.get 1)))
+        (func (export "div") (param i32 i32) (result i32)
+            (call $div (local.get 0) (local.get 1)))
+    )"#;
+
+    fn lazy_store() -> Store {
+        let engine = EngineBuilder::new(Cranelift::default())
+            .set_lazy_compilation(true)
+            .engine();
+        Store::new(engine)
+    }
+
+    #[test]
+    fn functions_are_compiled_on_first_call() -> Result<()> {
+        let mut store = lazy_store();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let square: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "square")?;
+        let apply: TypedFunction<(i32, i32, i32), i32> =
+            instance.exports.get_typed_function(&store, "apply")?;
+
+        assert_eq!(square.call(&mut store, 7)?, 49);
+        assert_eq!(square.call(&mut store, 8)?, 64);
+        assert_eq!(apply.call(&mut store, 0, 3, 4)?, 7);
+        assert_eq!(apply.call(&mut store, 1, 3, 4)?, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn traps_in_lazy_functions_have_a_trace() -> Result<()> {
+        let mut store = lazy_store();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let div: TypedFunction<(i32, i32), i32> =
+            instance.exports.get_typed_function(&store, "div")?;
+
+        assert_eq!(div.call(&mut store, 12, 4)?, 3);
+        let error = div.call(&mut store, 1, 0).unwrap_err();
+        assert_eq!(
+            error.clone().to_trap(),
+            Some(TrapCode::IntegerDivisionByZero)
+        );
+        let trace = error.trace();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].func_index(), 5);
+        assert_eq!(trace[1].func_index(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_modules_can_be_serialized() -> Result<()> {
+        let mut store = lazy_store();
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let square: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "square")?;
+        // Only part of the module is compiled at this point.
+        assert_eq!(square.call(&mut store, 3)?, 9);
+
+        let serialized = module.serialize()?;
+        let module = unsafe { Module::deserialize(&store, serialized)? };
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let apply: TypedFunction<(i32, i32, i32), i32> =
+            instance.exports.get_typed_function(&store, "apply")?;
+        assert_eq!(apply.call(&mut store, 0, 5, 6)?, 11);
+        assert_eq!(apply.call(&mut store, 1, 5, 6)?, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_functions_compile_concurrently() -> Result<()> {
+        let functions = (0..16).fold(String::new(), |mut wat, i| {
+            let _ = write!(
+                wat,
+                r#"(func $f{i} (result i32) (i32.const {i}))
+                   (func (export "f{i}") (result i32) (call $f{i}))"#
+            );
+            wat
+        });
+        let store = lazy_store();
+        let module = Module::new(&store, format!("(module {functions})"))?;
+
+        let threads = (0..16)
+            .map(|i| {
+                let engine = store.engine().clone();
+                let module = module.clone();
+                std::thread::spawn(move || -> Result<i32> {
+                    let mut store = Store::new(engine);
+                    let instance = Instance::new(&mut store, &module, &imports! {})?;
+                    let f: TypedFunction<(), i32> = instance
+                        .exports
+                        .get_typed_function(&store, &format!("f{i}"))?;
+                    Ok(f.call(&mut store)?)
+                })
+            })
+            .collect::<Vec<_>>();
+        for (i, thread) in threads.into_iter().enumerate() {
+            assert_eq!(thread.join().unwrap()?, i as i32);
+        }
+
+        // The module can still be serialized with all its functions
+        let serialized = module.serialize()?;
+        let mut store = Store::new(store.engine().clone());
+        let module = unsafe { Module::deserialize(&store, serialized)? };
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let f: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "f15")?;
+        assert_eq!(f.call(&mut store)?, 15);
+        Ok(())
+    }
+}
@@ -76,3 +76,53 @@ fn test_shared_memory_disable_atomics() {
     let err = mem.wait(MemoryLocation::new_32(1), None).unwrap_err();
     assert_eq!(err, AtomicsError::AtomicsDisabled);
 }
+
+#[cfg(feature = "sys")]
+#[test]
+fn test_memory_limiter() {
+    use std::sync::atomic::AtomicU32;
+    use wasmer::{vm::MemoryError, wat2wasm, MemoryLimiter, Pages, TypedFunction};
+
+    #[derive(Debug, Default)]
+    struct Limiter {
+        size: AtomicU32,
+    }
+
+    impl MemoryLimiter for Limiter {
+        fn memory_growing(&self, current: Pages, desired: Pages) -> Result<(), MemoryError> {
+            if desired > Pages(4) {
+                return Err(MemoryError::CouldNotGrow {
+                    current,
+                    attempted_delta: Pages(desired.0 - current.0),
+                });
+            }
+            Ok(())
+        }
+
+        fn memory_grown(&self, size: Pages) {
+            self.size.store(size.0, Ordering::SeqCst);
+        }
+    }
+
+    let wat = r#"(module
+        (memory (export "memory") 1)
+        (func (export "grow") (param i32) (result i32)
+            (memory.grow (local.get 0))))"#;
+    let mut store = Store::default();
+    let limiter = Arc::new(Limiter::default());
+    store.set_memory_limiter(Some(limiter.clone()));
+    let module = Module::new(&store, wat2wasm(wat.as_bytes()).unwrap()).unwrap();
+    let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+    let grow: TypedFunction<i32, i32> =
+        instance.exports.get_typed_function(&store, "grow").unwrap();
+
+    assert_eq!(grow.call(&mut store, 2).unwrap(), 1);
+    assert_eq!(limiter.size.load(Ordering::SeqCst), 3);
+    assert_eq!(grow.call(&mut store, 2).unwrap(), -1);
+
+    // The host grows the memory through the limiter as well
+    let memory = instance.exports.get_memory("memory").unwrap();
+    memory.grow(&mut store, 1).unwrap();
+    assert_eq!(limiter.size.load(Ordering::SeqCst), 4);
+    assert!(memory.grow(&mut store, 1).is_err());
+}
@@ -0,0 +1,102 @@
+#[cfg(all(feature = "sys", unix))]
+pub mod pooling {
+    use anyhow::Result;
+    use wasmer::sys::{NativeEngineExt, PoolingLimits, PoolingTunables};
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+        (memory (export "memory") 1 4)
+        (table 2 funcref)
+        (elem (i32.const 0) $get)
+        (func $get (result i32) (i32.load (i32.const 0)))
+        (func (export "get") (result i32) (call_indirect (result i32) (i32.const 0)))
+        (func (export "set") (param i32) (i32.store (i32.const 0) (local.get 0)))
+    )"#;
+
+    fn pooled_engine(limits: PoolingLimits) -> Engine {
+        let mut engine = Engine::default();
+        let tunables = PoolingTunables::new(engine.target(), limits).unwrap();
+        engine.set_tunables(tunables);
+        engine
+    }
+
+    #[test]
+    fn slots_are_recycled() -> Result<()> {
+        let engine = pooled_engine(PoolingLimits {
+            instances: 2,
+            memories: 2,
+            tables: 2,
+            ..Default::default()
+        });
+        let module = Module::new(&engine, WAT)?;
+
+        for _ in 0..10 {
+            let mut store = Store::new(engine.clone());
+            let instance = Instance::new(&mut store, &module, &imports! {})?;
+            let get: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "get")?;
+            let set: TypedFunction<i32, ()> = instance.exports.get_typed_function(&store, "set")?;
+
+            // Memories don't leak the data of the previous instance.
+            assert_eq!(get.call(&mut store)?, 0);
+            set.call(&mut store, 42)?;
+            assert_eq!(get.call(&mut store)?, 42);
+
+            let memory = instance.exports.get_memory("memory")?;
+            assert_eq!(memory.grow(&mut store, 3)?, Pages(1));
+            assert!(memory.grow(&mut store, 1).is_err());
+        }
+        Ok(())
+    }
+
+    const UNBOUNDED_WAT: &str = r#"(module
+        (memory (export "memory") 1)
+    )"#;
+
+    #[test]
+    fn unbounded_memories_are_rejected() -> Result<()> {
+        let engine = pooled_engine(PoolingLimits::default());
+        let module = Module::new(&engine, UNBOUNDED_WAT)?;
+        let mut store = Store::new(engine);
+        assert!(Instance::new(&mut store, &module, &imports! {}).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unbounded_memories_can_be_capped() -> Result<()> {
+        let engine = pooled_engine(PoolingLimits {
+            memory_pages: Pages(4),
+            cap_memories: true,
+            ..Default::default()
+        });
+        let module = Module::new(&engine, UNBOUNDED_WAT)?;
+        let mut store = Store::new(engine);
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        let memory = instance.exports.get_memory("memory")?;
+        assert_eq!(memory.ty(&store).maximum, Some(Pages(4)));
+        assert_eq!(memory.grow(&mut store, 3)?, Pages(1));
+        assert!(memory.grow(&mut store, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn instances_are_limited() -> Result<()> {
+        let engine = pooled_engine(PoolingLimits {
+            instances: 2,
+            ..Default::default()
+        });
+        let module = Module::new(&engine, WAT)?;
+        let mut store = Store::new(engine.clone());
+
+        let _first = Instance::new(&mut store, &module, &imports! {})?;
+        let _second = Instance::new(&mut store, &module, &imports! {})?;
+        assert!(Instance::new(&mut store, &module, &imports! {}).is_err());
+
+        // Instances live as long as their store, which gives their slots
+        // back when dropped.
+        drop(store);
+        let mut store = Store::new(engine);
+        Instance::new(&mut store, &module, &imports! {})?;
+        Ok(())
+    }
+}
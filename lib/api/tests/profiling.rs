@@ -0,0 +1,69 @@
+#[cfg(all(feature = "sys", feature = "cranelift", target_os = "linux"))]
+pub mod profiling {
+    use anyhow::Result;
+    use wasmer::sys::{Cranelift, EngineBuilder, ProfilingStrategy};
+    use wasmer::*;
+
+    fn compile(profiler: ProfilingStrategy, function_name: &str) -> Result<()> {
+        let engine = EngineBuilder::new(Cranelift::default())
+            .set_profiler(Some(profiler))
+            .engine();
+        let store = Store::new(engine);
+        Module::new(
+            &store,
+            format!(
+                r#"(module
+                    (func ${} (export "run") (param i32) (result i32)
+                        (i32.mul (local.get 0) (i32.const 2))))"#,
+                function_name
+            ),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn perf_map() -> Result<()> {
+        compile(ProfilingStrategy::PerfMap, "perf_map_double")?;
+        let path = format!("/tmp/perf-{}.map", std::process::id());
+        let perf_map = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+
+        let line = perf_map
+            .lines()
+            .find(|line| line.ends_with(" perf_map_double"))
+            .expect("the function is in the perf map");
+        let mut fields = line.split(' ');
+        let address = u64::from_str_radix(fields.next().unwrap(), 16)?;
+        let size = u64::from_str_radix(fields.next().unwrap(), 16)?;
+        assert_ne!(address, 0);
+        assert_ne!(size, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn jit_dump() -> Result<()> {
+        compile(ProfilingStrategy::JitDump, "jit_dump_double")?;
+        let path = format!("jit-{}.dump", std::process::id());
+        let jit_dump = std::fs::read(&path)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(&jit_dump[..4], &0x4A69_5444u32.to_ne_bytes());
+        let header_size = u32::from_ne_bytes(jit_dump[8..12].try_into()?) as usize;
+        let mut records = &jit_dump[header_size..];
+        let mut names = vec![];
+        while !records.is_empty() {
+            let id = u32::from_ne_bytes(records[..4].try_into()?);
+            let size = u32::from_ne_bytes(records[4..8].try_into()?) as usize;
+            if id == 0 {
+                // The name follows the header, the pid, the tid, the
+                // addresses, the size and the index.
+                let name = &records[56..];
+                let name = &name[..name.iter().position(|byte| *byte == 0).unwrap()];
+                names.push(String::from_utf8(name.to_vec())?);
+            }
+            records = &records[size..];
+        }
+        assert!(names.iter().any(|name| name == "jit_dump_double"));
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(all(feature = "sys", feature = "singlepass", feature = "cranelift"))]
+pub mod tiering {
+    use anyhow::Result;
+    use std::fmt::Write;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use wasmer::sys::{Cranelift, EngineBuilder, Singlepass};
+    use wasmer::*;
+
+    const WAT: &str = r#"(module
+        (type $sum_t (func (param i32) (result i32)))
+        (table 1 funcref)
+        (elem (i32.const 0) $sum)
+        (func $sum (type $sum_t) (param $n i32) (result i32)
+            (local $acc i32)
+            (block $done
+                (loop $next
+                    (br_if $done (i32.eqz (local.get $n)))
+                    (local.set $acc (i32.add (local.get $acc) (local.get $n)))
+                    (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                    (br $next)))
+            (local.get $acc))
+        (func (export "sum") (param i32) (result i32)
+            (call_indirect (type $sum_t) (local.get 0) (i32.const 0)))
+        (func (export "sum_direct") (param i32) (result i32)
+            (call $sum (local.get 0)))
+    )"#;
+
+    #[test]
+    fn hot_functions_keep_working_after_tier_up() -> Result<()> {
+        let engine = EngineBuilder::new(Singlepass::default())
+            .set_tier_up(Cranelift::default(), 100)
+            .engine();
+        let mut store = Store::new(engine.clone());
+        let module = Module::new(&store, WAT)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+        let sum_direct: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "sum_direct")?;
+
+        for _ in 0..10 {
+            assert_eq!(sum.call(&mut store, 100)?, 5050);
+        }
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while engine.tiered_up_functions() == 0 {
+            assert!(Instant::now() < deadline, "$sum was never tiered up");
+            thread::sleep(Duration::from_millis(1));
+        }
+        // Only `$sum` loops, so the exported functions stay cold.
+        assert_eq!(engine.tiered_up_functions(), 1);
+        for _ in 0..10 {
+            assert_eq!(sum.call(&mut store, 100)?, 5050);
+            assert_eq!(sum_direct.call(&mut store, 100)?, 5050);
+        }
+
+        // New instances pick up the optimized code right away.
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let sum: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "sum")?;
+        assert_eq!(sum.call(&mut store, 10)?, 55);
+        Ok(())
+    }
+
+    #[test]
+    fn modules_compiled_concurrently_keep_their_globals() -> Result<()> {
+        // The modules have a different number of globals, so that their
+        // hotness counters start at different indices.
+        fn wat(globals: usize) -> String {
+            let globals = (0..globals).fold(String::new(), |mut wat, i| {
+                let _ = write!(
+                    wat,
+                    "(global $g{i} (export \"g{i}\") (mut i64) (i64.const 7))"
+                );
+                wat
+            });
+            format!(
+                r#"(module
+                    {globals}
+                    (func (export "count") (param $n i32) (result i32)
+                        (local $acc i32)
+                        (block $done
+                            (loop $next
+                                (br_if $done (i32.eqz (local.get $n)))
+                                (local.set $acc (i32.add (local.get $acc) (i32.const 1)))
+                                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                                (br $next)))
+                        (local.get $acc)))"#
+            )
+        }
+
+        let engine = EngineBuilder::new(Singlepass::default())
+            .set_tier_up(Cranelift::default(), u64::MAX)
+            .engine();
+        let threads = (0..8)
+            .map(|i| {
+                let engine = engine.clone();
+                thread::spawn(move || -> Result<()> {
+                    let globals = i % 4;
+                    let mut store = Store::new(engine);
+                    let module = Module::new(&store, wat(globals))?;
+                    let instance = Instance::new(&mut store, &module, &imports! {})?;
+                    let count: TypedFunction<i32, i32> =
+                        instance.exports.get_typed_function(&store, "count")?;
+                    assert_eq!(count.call(&mut store, 1000)?, 1000);
+                    for g in 0..globals {
+                        let global = instance.exports.get_global(&format!("g{g}"))?;
+                        assert_eq!(global.get(&mut store), Value::I64(7));
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tiering_is_not_combined_with_lazy_compilation() {
+        let engine = EngineBuilder::new(Singlepass::default())
+            .set_tier_up(Cranelift::default(), 100)
+            .set_lazy_compilation(true)
+            .try_engine();
+        assert!(matches!(engine, Err(CompileError::UnsupportedFeature(_))));
+    }
+}
@@ -26,6 +26,7 @@ mod package;
 mod publish;
 mod run;
 mod self_update;
+mod snapshot_init;
 pub mod ssh;
 mod validate;
 #[cfg(feature = "wast")]
@@ -48,7 +49,7 @@ pub use {create_obj::*, gen_c_header::*};
 pub use self::journal::*;
 pub use self::{
     add::*, cache::*, config::*, container::*, init::*, inspect::*, login::*, package::*,
-    publish::*, run::Run, self_update::*, validate::*, whoami::*,
+    publish::*, run::Run, self_update::*, snapshot_init::*, validate::*, whoami::*,
 };
 use crate::error::PrettyError;
 
@@ -120,6 +121,7 @@ impl WasmerCmd {
             Some(Cmd::SelfUpdate(options)) => options.execute(),
             Some(Cmd::Cache(cache)) => cache.execute(),
             Some(Cmd::Validate(validate)) => validate.execute(),
+            Some(Cmd::SnapshotInit(snapshot_init)) => snapshot_init.execute(),
             #[cfg(feature = "compiler")]
             Some(Cmd::Compile(compile)) => compile.execute(),
             #[cfg(any(feature = "static-artifact-create", feature = "wasmer-artifact-create"))]
@@ -223,6 +225,11 @@ enum Cmd {
     /// Validate a WebAssembly binary
     Validate(Validate),
 
+    /// Pre-initialize a WebAssembly module, snapshotting its memory and
+    /// globals after running its initialization function
+    #[clap(name = "snapshot-init")]
+    SnapshotInit(SnapshotInit),
+
     /// Compile a WebAssembly binary
     #[cfg(feature = "compiler")]
     Compile(Compile),
@@ -0,0 +1,283 @@
+//! A debug endpoint (`--debug-listen`) that lets you inspect the processes
+//! of a guest while it runs, which helps to diagnose a hung guest.
+//!
+//! The endpoint reads one command per line and answers in plain text, so
+//! something like `nc localhost 7777` is all it takes to talk to it (each
+//! connection is served on a thread of its own):
+//! - `ps` lists the processes and what their threads are doing
+//! - `fds <pid>` lists the open file descriptors of a process
+//! - `kill <pid> [signal]` sends a signal to a process (`SIGTERM` by default)
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Error};
+use wasmer_wasix::{
+    os::task::{control_plane::WasiControlPlaneHandle, inspect::WasiProcessInfo},
+    types::Signal,
+    WasiControlPlane, WasiProcessId,
+};
+
+/// How long the listener waits between two attempts to accept a
+/// connection.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Serves the debug endpoint on a background thread for as long as the
+/// guest runs, only on the loopback interface unless `allow_remote` is set.
+pub(crate) fn serve(
+    addr: SocketAddr,
+    allow_remote: bool,
+    control_plane: WasiControlPlane,
+) -> Result<(), Error> {
+    if !addr.ip().is_loopback() && !allow_remote {
+        bail!(
+            "Serving the debug endpoint on \"{addr}\" exposes it to the network, this requires `--debug-listen-remote`"
+        );
+    }
+
+    let listener = TcpListener::bind(addr)
+        .with_context(|| format!("Unable to listen for debug connections on \"{addr}\""))?;
+    tracing::info!(%addr, "Serving the debug endpoint");
+
+    // The listener is polled, so that it is closed once the guest has
+    // exited even if nobody connects.
+    listener
+        .set_nonblocking(true)
+        .context("Unable to poll the debug endpoint")?;
+
+    // The endpoint only holds a weak reference so it does not keep the
+    // processes alive
+    let control_plane = control_plane.handle();
+    std::thread::Builder::new()
+        .name("wasmer-debug".to_string())
+        .spawn(move || {
+            while control_plane.upgrade().is_some() {
+                match listener.accept() {
+                    Ok((stream, _)) => serve_connection(stream, &control_plane),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(ACCEPT_INTERVAL);
+                    }
+                    Err(e) => {
+                        tracing::debug!(
+                            error = &e as &dyn std::error::Error,
+                            "Unable to accept a debug connection"
+                        );
+                        std::thread::sleep(ACCEPT_INTERVAL);
+                    }
+                }
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Serves a connection on a thread of its own.
+fn serve_connection(stream: TcpStream, control_plane: &WasiControlPlaneHandle) {
+    let control_plane = control_plane.clone();
+    let spawned = std::thread::Builder::new()
+        .name("wasmer-debug-connection".to_string())
+        .spawn(move || {
+            if let Err(e) = handle_connection(stream, &control_plane) {
+                tracing::debug!(
+                    error = &e as &dyn std::error::Error,
+                    "Debug connection failed"
+                );
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::debug!(
+            error = &e as &dyn std::error::Error,
+            "Unable to serve a debug connection"
+        );
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    control_plane: &WasiControlPlaneHandle,
+) -> Result<(), std::io::Error> {
+    // The accepted stream may inherit the non-blocking mode of the listener
+    stream.set_nonblocking(false)?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        // The connection ends once the guest has exited
+        let Some(control_plane) = control_plane.upgrade() else {
+            break;
+        };
+        let response = match execute(&control_plane, line.trim()) {
+            Ok(response) => response,
+            Err(e) => format!("error: {e}\n"),
+        };
+        writer.write_all(response.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Runs a command and returns its output.
+fn execute(control_plane: &WasiControlPlane, command: &str) -> Result<String, Error> {
+    let mut words = command.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (None, ..) => Ok(String::new()),
+        (Some("ps"), None, _) => Ok(format_processes(&control_plane.processes())),
+        (Some("fds"), Some(pid), None) => {
+            let pid = parse_pid(pid)?;
+            let process = control_plane
+                .get_process(pid)
+                .with_context(|| format!("No process with the ID {pid}"))?;
+            Ok(format_fds(&process.info()))
+        }
+        (Some("kill"), Some(pid), signal) => {
+            let signal = signal.map(parse_signal).transpose()?;
+            control_plane
+                .signal_process(parse_pid(pid)?, signal.unwrap_or(Signal::Sigterm))
+                .map_err(|e| anyhow::anyhow!("{e}"))?;
+            Ok(String::new())
+        }
+        (Some("help"), ..) => Ok("ps\nfds <pid>\nkill <pid> [signal]\n".to_string()),
+        _ => bail!("Unknown command \"{command}\" (try \"help\")"),
+    }
+}
+
+fn parse_pid(pid: &str) -> Result<WasiProcessId, Error> {
+    let pid: u32 = pid
+        .parse()
+        .with_context(|| format!("\"{pid}\" is not a process ID"))?;
+    Ok(pid.into())
+}
+
+/// Parses a signal from its number or name (e.g. `9`, `KILL` or `SIGKILL`).
+fn parse_signal(s: &str) -> Result<Signal, Error> {
+    if let Ok(number) = s.parse::<u8>() {
+        return Signal::try_from(number).with_context(|| format!("Unknown signal {number}"));
+    }
+
+    let name = s.to_ascii_uppercase();
+    let signal = match name.strip_prefix("SIG").unwrap_or(&name) {
+        "HUP" => Signal::Sighup,
+        "INT" => Signal::Sigint,
+        "QUIT" => Signal::Sigquit,
+        "ILL" => Signal::Sigill,
+        "TRAP" => Signal::Sigtrap,
+        "ABRT" => Signal::Sigabrt,
+        "BUS" => Signal::Sigbus,
+        "FPE" => Signal::Sigfpe,
+        "KILL" => Signal::Sigkill,
+        "USR1" => Signal::Sigusr1,
+        "SEGV" => Signal::Sigsegv,
+        "USR2" => Signal::Sigusr2,
+        "PIPE" => Signal::Sigpipe,
+        "ALRM" => Signal::Sigalrm,
+        "TERM" => Signal::Sigterm,
+        "STKFLT" => Signal::Sigstkflt,
+        "CHLD" => Signal::Sigchld,
+        "CONT" => Signal::Sigcont,
+        "STOP" => Signal::Sigstop,
+        "TSTP" => Signal::Sigtstp,
+        "TTIN" => Signal::Sigttin,
+        "TTOU" => Signal::Sigttou,
+        "URG" => Signal::Sigurg,
+        "XCPU" => Signal::Sigxcpu,
+        "XFSZ" => Signal::Sigxfsz,
+        "VTALRM" => Signal::Sigvtalrm,
+        "PROF" => Signal::Sigprof,
+        "WINCH" => Signal::Sigwinch,
+        "POLL" => Signal::Sigpoll,
+        "PWR" => Signal::Sigpwr,
+        "SYS" => Signal::Sigsys,
+        _ => bail!("Unknown signal \"{s}\""),
+    };
+    Ok(signal)
+}
+
+fn format_processes(processes: &[WasiProcessInfo]) -> String {
+    let mut out = format!(
+        "{:>6} {:>6} {:>6} {:>6} {:>10} {:>8} {:>8}  COMMAND\n",
+        "PID", "PPID", "PGID", "UID", "MEMORY", "UPTIME", "CPU"
+    );
+    for process in processes {
+        let command = if process.args.is_empty() {
+            process.name.clone()
+        } else {
+            process.args.join(" ")
+        };
+        let _ = writeln!(
+            out,
+            "{:>6} {:>6} {:>6} {:>6} {:>7} kB {:>8} {:>8}  {command}",
+            process.pid,
+            process.ppid,
+            process.pgid,
+            process.uid,
+            process.memory_size / 1024,
+            format_duration(process.uptime),
+            format_duration(process.cpu_time),
+        );
+        for thread in &process.threads {
+            let main = if thread.is_main { " (main)" } else { "" };
+            let _ = writeln!(
+                out,
+                "{:>13} thread {}{main}: {}",
+                "", thread.tid, thread.activity
+            );
+        }
+    }
+    out
+}
+
+fn format_fds(process: &WasiProcessInfo) -> String {
+    let mut out = format!("{:>6}  TARGET\n", "FD");
+    for fd in &process.fds {
+        let _ = writeln!(out, "{:>6}  {}", fd.fd, fd.target);
+    }
+    out
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_signal_names_and_numbers() {
+        assert_eq!(parse_signal("9").unwrap(), Signal::Sigkill);
+        assert_eq!(parse_signal("KILL").unwrap(), Signal::Sigkill);
+        assert_eq!(parse_signal("sigterm").unwrap(), Signal::Sigterm);
+        assert_eq!(parse_signal("SIGUSR1").unwrap(), Signal::Sigusr1);
+        assert!(parse_signal("SIGFOO").is_err());
+        assert!(parse_signal("64").is_err());
+    }
+
+    #[test]
+    fn the_endpoint_is_closed_once_the_guest_exits() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let control_plane = WasiControlPlane::default();
+        serve(addr, false, control_plane.clone()).unwrap();
+        TcpStream::connect(addr).unwrap();
+
+        // The listener is closed without another connection to wake it up.
+        drop(control_plane);
+        std::thread::sleep(ACCEPT_INTERVAL * 10);
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn commands_run_against_the_control_plane() {
+        let control_plane = WasiControlPlane::default();
+
+        let ps = execute(&control_plane, "ps").unwrap();
+        assert!(ps.starts_with("   PID"));
+        assert!(execute(&control_plane, "kill 1000 KILL").is_err());
+        assert!(execute(&control_plane, "fds nope").is_err());
+        assert!(execute(&control_plane, "reboot").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(missing_docs, unused)]
 
+mod debug;
 mod wasi;
 
 use std::{
@@ -26,13 +27,16 @@ use wasmer::{
     Value,
 };
 #[cfg(feature = "compiler")]
-use wasmer_compiler::ArtifactBuild;
+use wasmer_compiler::{ArtifactBuild, ModuleMiddleware};
+#[cfg(feature = "compiler")]
+use wasmer_middlewares::call_trace::{CallEvent, CallEventKind, CallTrace};
 use wasmer_registry::{wasmer_env::WasmerEnv, Package};
 #[cfg(feature = "journal")]
 use wasmer_wasix::journal::{LogFileJournal, SnapshotTrigger};
 use wasmer_wasix::{
     bin_factory::BinaryPackage,
     journal::CompactingLogFileJournal,
+    os::task::control_plane::ControlPlaneConfig,
     runners::{
         dcgi::{DcgiInstanceFactory, DcgiRunner},
         emscripten::EmscriptenRunner,
@@ -44,16 +48,21 @@ use wasmer_wasix::{
         module_cache::{CacheError, ModuleHash},
         package_loader::PackageLoader,
         resolver::{PackageSpecifier, QueryError},
-        task_manager::VirtualTaskManagerExt,
+        task_manager::{InlineWaker, VirtualTaskManagerExt},
     },
-    Runtime, WasiError,
+    ImportsFactory, Runtime, WasiControlPlane, WasiError,
 };
 use webc::{metadata::Manifest, Container};
 
-use crate::{commands::run::wasi::Wasi, error::PrettyError, logging::Output, store::StoreOptions};
+pub(crate) use self::wasi::Wasi;
+use crate::{error::PrettyError, logging::Output, store::StoreOptions};
 
 const TICK: Duration = Duration::from_millis(250);
 
+/// How often `--profile-out` samples the WebAssembly stacks.
+#[cfg(all(feature = "sys", unix))]
+const PROFILE_INTERVAL: Duration = Duration::from_millis(1);
+
 /// The unstable `wasmer run` subcommand.
 #[derive(Debug, Parser)]
 pub struct Run {
@@ -74,6 +83,26 @@ pub struct Run {
     /// Generate a coredump at this path if a WebAssembly trap occurs
     #[clap(name = "COREDUMP PATH", long)]
     coredump_on_trap: Option<PathBuf>,
+    /// Sample the WebAssembly stacks while running and write them to this
+    /// path as collapsed stacks, which flame graph tools take as input.
+    /// The code is compiled with frame pointers in every function, and
+    /// cached apart from the code compiled without them
+    #[cfg(all(feature = "sys", unix))]
+    #[clap(long = "profile-out")]
+    profile_out: Option<PathBuf>,
+    /// Print the calls of the WebAssembly functions, with their arguments
+    /// and results, to stderr. Only the functions whose names contain one of
+    /// the comma-separated values are traced, if any are given. This isn't
+    /// supported for packages
+    #[cfg(feature = "compiler")]
+    #[clap(
+        long = "trace-calls",
+        value_name = "FUNCTIONS",
+        num_args = 0..=1,
+        default_missing_value = "",
+        require_equals = true
+    )]
+    trace_calls: Option<String>,
     /// The file, URL, or package to run.
     #[clap(value_parser = PackageSource::infer)]
     input: PackageSource,
@@ -106,7 +135,24 @@ impl Run {
         }
 
         let _guard = handle.enter();
-        let (store, _) = self.store.get_store()?;
+        #[allow(unused_mut)]
+        let mut store_options = self.store.clone();
+        #[cfg(all(feature = "compiler", feature = "sys", unix))]
+        if self.profile_out.is_some() {
+            store_options.enable_frame_pointers();
+        }
+        #[cfg(feature = "compiler")]
+        let call_trace = self.call_trace();
+        #[cfg(feature = "compiler")]
+        let (store, _) = match &call_trace {
+            Some(call_trace) => {
+                let middleware: Arc<dyn ModuleMiddleware> = call_trace.clone();
+                store_options.get_store_with_middlewares([middleware])?
+            }
+            None => store_options.get_store()?,
+        };
+        #[cfg(not(feature = "compiler"))]
+        let (store, _) = store_options.get_store()?;
         let runtime = self
             .wasi
             .prepare_runtime(store.engine().clone(), &self.env, runtime)?;
@@ -117,20 +163,55 @@ impl Run {
         let runtime: Arc<dyn Runtime + Send + Sync> = monitoring_runtime.runtime.clone();
         let monitoring_runtime: Arc<dyn Runtime + Send + Sync> = monitoring_runtime;
 
+        #[cfg(feature = "compiler")]
+        let target = match (&call_trace, &self.trace_calls) {
+            (Some(call_trace), Some(functions)) => {
+                self.input
+                    .load_traced(&monitoring_runtime, call_trace, functions, &pb)?
+            }
+            _ => self.input.resolve_target(&monitoring_runtime, &pb)?,
+        };
+        #[cfg(not(feature = "compiler"))]
         let target = self.input.resolve_target(&monitoring_runtime, &pb)?;
 
+        // The imports of the middlewares are created in the store of every
+        // instance, including the ones of the threads, which then indent
+        // their calls separately
+        #[allow(unused_mut)]
+        let mut imports_factories = vec![];
+        #[cfg(feature = "compiler")]
+        if let Some(call_trace) = &call_trace {
+            let call_trace = call_trace.clone();
+            imports_factories.push(ImportsFactory::new(move |store| {
+                call_trace.imports(store, print_call_event(call_trace.clone()))
+            }));
+        }
+
         pb.finish_and_clear();
 
         // push the TTY state so we can restore it after the program finishes
         let tty = runtime.tty().map(|tty| tty.tty_get());
 
+        #[cfg(all(feature = "sys", unix))]
+        let profiler = self
+            .profile_out
+            .as_ref()
+            .map(|_| wasmer::sys::GuestProfiler::start(PROFILE_INTERVAL));
+
         let result = {
             match target {
                 ExecutableTarget::WebAssembly {
                     module,
                     module_hash,
                     path,
-                } => self.execute_wasm(&path, &module, module_hash, store, runtime.clone()),
+                } => self.execute_wasm(
+                    &path,
+                    &module,
+                    module_hash,
+                    store,
+                    runtime.clone(),
+                    &imports_factories,
+                ),
                 ExecutableTarget::Package(pkg) => self.execute_webc(&pkg, runtime.clone()),
             }
         };
@@ -142,6 +223,11 @@ impl Run {
             }
         }
 
+        #[cfg(all(feature = "sys", unix))]
+        if let (Some(profiler), Some(path)) = (profiler, &self.profile_out) {
+            self.save_profile(&profiler, path);
+        }
+
         if let Err(e) = &result {
             self.maybe_save_coredump(e);
         }
@@ -157,13 +243,14 @@ impl Run {
         module_hash: ModuleHash,
         mut store: Store,
         runtime: Arc<dyn Runtime + Send + Sync>,
+        imports_factories: &[ImportsFactory],
     ) -> Result<(), Error> {
         if wasmer_emscripten::is_emscripten_module(module) {
             self.execute_emscripten_module()
         } else if wasmer_wasix::is_wasi_module(module) || wasmer_wasix::is_wasix_module(module) {
-            self.execute_wasi_module(path, module, module_hash, runtime, store)
+            self.execute_wasi_module(path, module, module_hash, runtime, store, imports_factories)
         } else {
-            self.execute_pure_wasm_module(module, &mut store)
+            self.execute_pure_wasm_module(module, &mut store, imports_factories)
         }
     }
 
@@ -265,6 +352,21 @@ impl Run {
             config.forward_host_env();
         }
 
+        #[cfg(all(feature = "sys", unix))]
+        if let Some(instances) = self.wcgi.pool_instances {
+            let mut limits = wasmer::sys::PoolingLimits {
+                instances,
+                memories: instances,
+                tables: instances,
+                ..Default::default()
+            };
+            if let Some(pages) = self.wcgi.pool_memory_pages {
+                limits.memory_pages = wasmer::Pages(pages);
+                limits.cap_memories = true;
+            }
+            config.pooling(limits);
+        }
+
         #[cfg(feature = "journal")]
         {
             for trigger in self.wasi.snapshot_on.iter().cloned() {
@@ -315,8 +417,18 @@ impl Run {
     }
 
     #[tracing::instrument(skip_all)]
-    fn execute_pure_wasm_module(&self, module: &Module, store: &mut Store) -> Result<(), Error> {
-        let imports = Imports::default();
+    fn execute_pure_wasm_module(
+        &self,
+        module: &Module,
+        store: &mut Store,
+        imports_factories: &[ImportsFactory],
+    ) -> Result<(), Error> {
+        let mut imports = Imports::default();
+        for factory in imports_factories {
+            for ((namespace, name), value) in &factory.imports(store) {
+                imports.define(&namespace, &name, value);
+            }
+        }
         let instance = Instance::new(store, module, &imports)
             .context("Unable to instantiate the WebAssembly module")?;
 
@@ -363,6 +475,22 @@ impl Run {
             .with_forward_host_env(self.wasi.forward_host_env)
             .with_capabilities(self.wasi.capabilities());
 
+        if let Some((uid, gid)) = self.wasi.user {
+            runner.with_user(uid, gid);
+        }
+
+        let capabilities = self.wasi.capabilities();
+        let control_plane = WasiControlPlane::new(ControlPlaneConfig {
+            max_task_count: capabilities.threading.max_threads,
+            enable_asynchronous_threading: capabilities.threading.enable_asynchronous_threading,
+        });
+        if let Some(addr) = self.wasi.debug_listen {
+            debug::serve(addr, self.wasi.debug_listen_remote, control_plane.clone())?;
+        }
+        #[cfg(unix)]
+        forward_interrupts(&control_plane);
+        runner.with_control_plane(control_plane);
+
         #[cfg(feature = "journal")]
         {
             for trigger in self.wasi.snapshot_on.iter().cloned() {
@@ -395,10 +523,14 @@ impl Run {
         module_hash: ModuleHash,
         runtime: Arc<dyn Runtime + Send + Sync>,
         mut store: Store,
+        imports_factories: &[ImportsFactory],
     ) -> Result<(), Error> {
         let program_name = wasm_path.display().to_string();
 
-        let runner = self.build_wasi_runner(&runtime)?;
+        let mut runner = self.build_wasi_runner(&runtime)?;
+        for factory in imports_factories {
+            runner.with_imports_factory(factory.clone());
+        }
         runner.run_wasm(
             runtime,
             &program_name,
@@ -413,6 +545,30 @@ impl Run {
         bail!("Emscripten packages are not currently supported")
     }
 
+    /// The middleware tracing the calls requested with `--trace-calls`, if
+    /// any.
+    #[cfg(feature = "compiler")]
+    fn call_trace(&self) -> Option<Arc<CallTrace>> {
+        let functions: Vec<String> = self
+            .trace_calls
+            .as_deref()?
+            .split(',')
+            .filter(|function| !function.is_empty())
+            .map(String::from)
+            .collect();
+        let call_trace = CallTrace::new()
+            .with_filter(move |_, name| {
+                functions.is_empty()
+                    || name.map_or(false, |name| {
+                        functions
+                            .iter()
+                            .any(|function| name.contains(function.as_str()))
+                    })
+            })
+            .with_indirect_calls(true);
+        Some(Arc::new(call_trace))
+    }
+
     #[allow(unused_variables)]
     fn maybe_save_coredump(&self, e: &Error) {
         #[cfg(feature = "coredump")]
@@ -427,6 +583,18 @@ impl Run {
         }
     }
 
+    #[cfg(all(feature = "sys", unix))]
+    fn save_profile(&self, profiler: &wasmer::sys::GuestProfiler, path: &Path) {
+        let result = File::create(path).and_then(|mut file| profiler.write_collapsed(&mut file));
+        if let Err(e) = result {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                profile_path=%path.display(),
+                "Unable to write the profile",
+            );
+        }
+    }
+
     /// Create Run instance for arguments/env, assuming we're being run from a
     /// CFP binfmt interpreter.
     pub fn from_binfmt_args() -> Self {
@@ -458,6 +626,10 @@ impl Run {
             stack_size: None,
             entrypoint: Some(original_executable.to_string()),
             coredump_on_trap: None,
+            #[cfg(all(feature = "sys", unix))]
+            profile_out: None,
+            #[cfg(feature = "compiler")]
+            trace_calls: None,
             input: PackageSource::infer(executable)?,
             args: args.to_vec(),
         })
@@ -495,6 +667,43 @@ fn invoke_function(
     Ok(return_values)
 }
 
+/// Prints the events reported by `--trace-calls` to stderr, indenting the
+/// calls by their depth.
+#[cfg(feature = "compiler")]
+fn print_call_event(call_trace: Arc<CallTrace>) -> impl Fn(&CallEvent) + Send + Sync + 'static {
+    let depth = Mutex::new(0);
+    move |event| {
+        let name = call_trace
+            .function_name(event.function)
+            .unwrap_or_else(|| format!("<{}>", event.function.as_u32()));
+        let values = event
+            .values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut depth = depth.lock().unwrap();
+        match event.kind {
+            CallEventKind::Entry => {
+                eprintln!("{:indent$}-> {name}({values})", "", indent = *depth * 2);
+                *depth += 1;
+            }
+            CallEventKind::Exit => {
+                *depth = depth.saturating_sub(1);
+                eprintln!("{:indent$}<- {name} = [{values}]", "", indent = *depth * 2);
+            }
+            CallEventKind::IndirectCall => {
+                eprintln!(
+                    "{:indent$}call_indirect [{values}]",
+                    "",
+                    indent = *depth * 2
+                );
+            }
+        }
+    }
+}
+
 fn parse_value(s: &str, ty: wasmer_types::Type) -> Result<Value, Error> {
     let value = match ty {
         Type::I32 => Value::I32(s.parse()?),
@@ -555,6 +764,89 @@ impl PackageSource {
         ))
     }
 
+    /// Load the WebAssembly file of the [`PackageSource`], compiled with
+    /// `call_trace`.
+    ///
+    /// The module cache doesn't tell apart the artifacts compiled with
+    /// middlewares, so traced modules are cached under a key that also
+    /// covers the traced `functions`.
+    #[cfg(feature = "compiler")]
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn load_traced(
+        &self,
+        runtime: &Arc<dyn Runtime + Send + Sync>,
+        call_trace: &CallTrace,
+        functions: &str,
+        pb: &ProgressBar,
+    ) -> Result<ExecutableTarget, Error> {
+        let path = match self {
+            PackageSource::File(path) => path,
+            _ => bail!("--trace-calls is only supported when running WebAssembly files"),
+        };
+
+        match TargetOnDisk::from_file(path)? {
+            TargetOnDisk::WebAssemblyBinary | TargetOnDisk::Wat => {
+                pb.set_message(format!("Loading from \"{}\"", path.display()));
+                let wasm = std::fs::read(path)?;
+                let module_hash = ModuleHash::hash(&wasm);
+                let mut key = wasm.clone();
+                key.extend_from_slice(b"\0--trace-calls=");
+                key.extend_from_slice(functions.as_bytes());
+                let key = ModuleHash::hash(key);
+
+                let engine = runtime.engine();
+                let module_cache = runtime.module_cache();
+                let cached = match InlineWaker::block_on(module_cache.load(key, &engine)) {
+                    Ok(module) => match call_trace.use_compiled_module(&module) {
+                        Ok(()) => Some(module),
+                        Err(e) => {
+                            tracing::warn!(
+                                %key,
+                                error = &e as &dyn std::error::Error,
+                                "The cached module wasn't compiled with --trace-calls",
+                            );
+                            None
+                        }
+                    },
+                    Err(CacheError::NotFound) => None,
+                    Err(e) => {
+                        tracing::warn!(
+                            %key,
+                            error = &e as &dyn std::error::Error,
+                            "Unable to load the cached module",
+                        );
+                        None
+                    }
+                };
+                let module = match cached {
+                    Some(module) => module,
+                    None => {
+                        pb.set_message("Compiling to WebAssembly");
+                        let module = Module::new(&engine, &wasm)
+                            .with_context(|| format!("Unable to compile \"{}\"", path.display()))?;
+                        if let Err(e) =
+                            InlineWaker::block_on(module_cache.save(key, &engine, &module))
+                        {
+                            tracing::warn!(
+                                %key,
+                                error = &e as &dyn std::error::Error,
+                                "Unable to cache the compiled module",
+                            );
+                        }
+                        module
+                    }
+                };
+
+                Ok(ExecutableTarget::WebAssembly {
+                    module,
+                    module_hash,
+                    path: path.to_path_buf(),
+                })
+            }
+            _ => bail!("--trace-calls is only supported when running WebAssembly files"),
+        }
+    }
+
     /// Try to resolve the [`PackageSource`] to an executable artifact.
     ///
     /// This will try to automatically download and cache any resources from the
@@ -770,12 +1062,26 @@ pub(crate) struct WcgiOptions {
     /// The address to serve on.
     #[clap(long, short, env, default_value_t = ([127, 0, 0, 1], 8000).into())]
     pub(crate) addr: SocketAddr,
+    /// Allocate the instances handling the requests from a pool of this
+    /// many slots, recycled between requests
+    #[cfg(all(feature = "sys", unix))]
+    #[clap(long)]
+    pub(crate) pool_instances: Option<usize>,
+    /// The maximum size, in pages, of the memories of pooled instances.
+    /// Memories that could grow larger are capped at this size
+    #[cfg(all(feature = "sys", unix))]
+    #[clap(long, requires = "pool_instances")]
+    pub(crate) pool_memory_pages: Option<u32>,
 }
 
 impl Default for WcgiOptions {
     fn default() -> Self {
         Self {
             addr: ([127, 0, 0, 1], 8000).into(),
+            #[cfg(all(feature = "sys", unix))]
+            pool_instances: None,
+            #[cfg(all(feature = "sys", unix))]
+            pool_memory_pages: None,
         }
     }
 }
@@ -811,6 +1117,54 @@ impl wasmer_wasix::runners::wcgi::Callbacks for Callbacks {
     }
 }
 
+/// Forwards the Ctrl-C presses on the host terminal (`SIGINT`) to the
+/// foreground process group of the guest, like a terminal does, instead of
+/// killing `wasmer`.
+///
+/// Guests that ignore `SIGINT` would be impossible to interrupt, so pressing
+/// Ctrl-C a third time exits `wasmer` right away.
+#[cfg(unix)]
+fn forward_interrupts(control_plane: &WasiControlPlane) {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use wasmer_wasix::types::Signal;
+
+    static INTERRUPTS: AtomicU32 = AtomicU32::new(0);
+
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        // Only async-signal-safe calls are allowed in here
+        if INTERRUPTS.fetch_add(1, Ordering::SeqCst) >= 2 {
+            unsafe { libc::_exit(130) };
+        }
+    }
+
+    unsafe {
+        libc::signal(libc::SIGINT, on_interrupt as libc::sighandler_t);
+    }
+
+    let control_plane = control_plane.handle();
+    std::thread::spawn(move || {
+        let mut forwarded = 0;
+        while let Some(control_plane) = control_plane.upgrade() {
+            let interrupts = INTERRUPTS.load(Ordering::SeqCst);
+            // The session of the program that `wasmer` started
+            let session = control_plane
+                .running_processes()
+                .first()
+                .map(|process| process.session());
+            for _ in forwarded..interrupts {
+                if let Some(session) = session {
+                    control_plane
+                        .signal_foreground_group(session, Signal::Sigint)
+                        .ok();
+                }
+            }
+            forwarded = interrupts;
+            drop(control_plane);
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    });
+}
+
 /// Exit the current process, using the WASI exit code if the error contains
 /// one.
 fn exit_with_wasi_exit_code(result: Result<(), Error>) -> ! {
@@ -1,5 +1,6 @@
 use std::{
     collections::{BTreeSet, HashMap},
+    net::SocketAddr,
     path::{Path, PathBuf},
     str::FromStr,
     sync::{mpsc::Sender, Arc},
@@ -43,7 +44,7 @@ use wasmer_wasix::{
     WasiVersion,
 };
 
-use crate::utils::{parse_envvar, parse_mapdir};
+use crate::utils::{parse_debug_listen, parse_envvar, parse_mapdir, parse_unix_socket, parse_user};
 
 const WAPM_SOURCE_CACHE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
@@ -93,6 +94,41 @@ pub struct Wasi {
     #[clap(long = "net")]
     pub networking: bool,
 
+    /// Bridge a host UNIX socket to a path in the guest.
+    ///
+    /// Guest connections to the path are forwarded to the host socket, this
+    /// requires `--net`
+    #[clap(
+        long = "unix-socket",
+        name = "GUEST_PATH:HOST_PATH",
+        value_parser = parse_unix_socket,
+    )]
+    pub unix_sockets: Vec<(PathBuf, PathBuf)>,
+
+    /// Serve a debug endpoint on this port (or address), it lists the
+    /// processes of the guest and what their threads are doing (`ps`) and
+    /// sends them signals (`kill <pid> [signal]`)
+    ///
+    /// Connect with something like `nc`. A port on its own is served on
+    /// the loopback interface, other interfaces also need
+    /// `--debug-listen-remote`. This requires `--net` because the endpoint
+    /// opens a socket on the host and anyone who can connect to it may
+    /// signal the processes of the guest.
+    #[clap(long = "debug-listen", name = "[ADDR:]PORT", value_parser = parse_debug_listen)]
+    pub debug_listen: Option<SocketAddr>,
+
+    /// Allow the debug endpoint to listen on addresses other than the
+    /// loopback interface, which exposes it to the network
+    #[clap(long = "debug-listen-remote", requires = "[ADDR:]PORT")]
+    pub debug_listen_remote: bool,
+
+    /// Run the guest as a particular user (and group), in the form
+    /// `<uid>[:<gid>]`
+    ///
+    /// Guests run as root unless this is specified.
+    #[clap(long = "user", name = "UID[:GID]", value_parser = parse_user)]
+    pub user: Option<(u32, u32)>,
+
     /// Disables the TTY bridge
     #[clap(long = "no-tty")]
     pub no_tty: bool,
@@ -355,6 +391,10 @@ impl Wasi {
 
         *builder.capabilities_mut() = self.capabilities();
 
+        if let Some((uid, gid)) = self.user {
+            builder.set_user(uid, gid);
+        }
+
         #[cfg(feature = "journal")]
         {
             for trigger in self.snapshot_on.iter().cloned() {
@@ -517,6 +557,9 @@ impl Wasi {
         }
 
         caps.threading.enable_asynchronous_threading = self.enable_async_threads;
+        caps.unix_sockets
+            .host_bridges
+            .extend(self.unix_sockets.iter().cloned());
 
         caps
     }
@@ -535,6 +578,10 @@ impl Wasi {
 
         if self.networking {
             rt.set_networking_implementation(virtual_net::host::LocalNetworking::default());
+        } else if !self.unix_sockets.is_empty() {
+            bail!("Bridging host UNIX sockets with `--unix-socket` requires `--net`");
+        } else if self.debug_listen.is_some() {
+            bail!("Serving a debug endpoint with `--debug-listen` requires `--net`");
         } else {
             rt.set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default());
         }
@@ -600,6 +647,23 @@ impl Wasi {
         })
     }
 
+    /// The options of the environment in which `wasmer snapshot-init` runs
+    /// the initialization of a module. The host environment and TTY are left
+    /// out so that the snapshot only depends on the given options.
+    pub fn for_pre_initialization(
+        pre_opened_directories: Vec<PathBuf>,
+        mapped_dirs: Vec<MappedDirectory>,
+        env_vars: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            pre_opened_directories,
+            mapped_dirs,
+            env_vars,
+            no_tty: true,
+            ..Self::default()
+        }
+    }
+
     fn prepare_package_loader(
         &self,
         env: &WasmerEnv,
@@ -0,0 +1,115 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use wasmer::{is_wasm, Module};
+use wasmer_registry::wasmer_env::WasmerEnv;
+use wasmer_wasix::{runners::MappedDirectory, PreInitializer, Runtime};
+
+use crate::{
+    commands::run::Wasi,
+    store::StoreOptions,
+    utils::{parse_envvar, parse_mapdir},
+};
+
+#[derive(Debug, Parser)]
+/// The options for the `wasmer snapshot-init` subcommand
+pub struct SnapshotInit {
+    #[clap(flatten)]
+    env: WasmerEnv,
+
+    #[clap(flatten)]
+    store: StoreOptions,
+
+    /// WASI pre-opened directory. The pre-initialized module must be given
+    /// the same directories when it runs
+    #[clap(long = "dir", name = "DIR")]
+    pre_opened_directories: Vec<PathBuf>,
+
+    /// Map a host directory to a different location for the Wasm module
+    #[clap(
+        long = "mapdir",
+        name = "GUEST_DIR:HOST_DIR",
+        value_parser = parse_mapdir,
+    )]
+    mapped_dirs: Vec<MappedDirectory>,
+
+    /// Pass custom environment variables. The host environment variables
+    /// are never forwarded
+    #[clap(
+        long = "env",
+        name = "KEY=VALUE",
+        value_parser = parse_envvar,
+    )]
+    env_vars: Vec<(String, String)>,
+
+    /// The exported function initializing the module
+    #[clap(long = "init-func", default_value = "wizer.initialize")]
+    init_func: String,
+
+    /// Keep the initialization function exported by the pre-initialized
+    /// module
+    #[clap(long = "keep-init-func")]
+    keep_init_func: bool,
+
+    /// Output file
+    #[clap(name = "OUTPUT PATH", short = 'o')]
+    output: PathBuf,
+
+    /// Input file
+    #[clap(name = "FILE")]
+    path: PathBuf,
+
+    /// Command-line arguments passed to the module while it initializes
+    args: Vec<String>,
+}
+
+impl SnapshotInit {
+    /// Runs logic for the `snapshot-init` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute().context(format!(
+            "failed to pre-initialize `{}`",
+            self.path.display()
+        ))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let _guard = handle.enter();
+
+        let (mut store, _compiler_type) = self.store.get_store()?;
+        let wasm = std::fs::read(&self.path)?;
+        if !is_wasm(&wasm) {
+            bail!("`wasmer snapshot-init` only pre-initializes WebAssembly files");
+        }
+        let module = Module::new(&store, &wasm)?;
+
+        let wasi = Wasi::for_pre_initialization(
+            self.pre_opened_directories.clone(),
+            self.mapped_dirs.clone(),
+            self.env_vars.clone(),
+        );
+        let runtime: Arc<dyn Runtime + Send + Sync> =
+            Arc::new(wasi.prepare_runtime(store.engine().clone(), &self.env, runtime)?);
+        let program_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let builder = wasi.prepare(&module, program_name, self.args.clone(), runtime)?;
+
+        let pre_initialized = PreInitializer::new(&self.init_func)
+            .keep_init_func(self.keep_init_func)
+            .run(builder, &mut store, module, &wasm)?;
+        std::fs::write(&self.output, pre_initialized)?;
+
+        eprintln!(
+            "✔ Module pre-initialized successfully to `{}`.",
+            self.output.display(),
+        );
+        Ok(())
+    }
+}
@@ -15,9 +15,9 @@ use anyhow::{bail, Result};
 use wasmer::sys::Features;
 use wasmer::*;
 #[cfg(feature = "compiler")]
-use wasmer_compiler::CompilerConfig;
-#[cfg(feature = "compiler")]
 use wasmer_compiler::Engine;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{CompilerConfig, ModuleMiddleware};
 
 #[derive(Debug, Clone, clap::Parser, Default)]
 /// The compiler options
@@ -27,6 +27,27 @@ pub struct StoreOptions {
     compiler: CompilerOptions,
 }
 
+/// How the generated code is described to `perf`.
+#[cfg(feature = "compiler")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Profiler {
+    /// Write the name of every function to `/tmp/perf-<pid>.map`.
+    Perfmap,
+    /// Write every function with its code and source lines to
+    /// `jit-<pid>.dump`, for `perf inject --jit`.
+    Jitdump,
+}
+
+#[cfg(feature = "compiler")]
+impl From<Profiler> for wasmer_compiler::ProfilingStrategy {
+    fn from(profiler: Profiler) -> Self {
+        match profiler {
+            Profiler::Perfmap => Self::PerfMap,
+            Profiler::Jitdump => Self::JitDump,
+        }
+    }
+}
+
 #[derive(Debug, clap::Parser, Clone, Default)]
 /// The WebAssembly features that can be passed through the
 /// Command Line args.
@@ -88,6 +109,24 @@ pub struct CompilerOptions {
     #[clap(long)]
     llvm_debug_dir: Option<PathBuf>,
 
+    /// Register the generated code with native debuggers (GDB, LLDB).
+    ///
+    /// The DWARF debug info of the module, if any, is translated so that
+    /// breakpoints can be set on its source lines.
+    #[clap(long)]
+    debug_info: bool,
+
+    /// Describe the generated code to `perf`, so that profiles show the
+    /// names of the WebAssembly functions.
+    ///
+    /// Only available on Linux.
+    #[clap(long, value_enum)]
+    profile: Option<Profiler>,
+
+    /// Keep a frame pointer in every function, for the sampling profiler.
+    #[clap(skip)]
+    frame_pointers: bool,
+
     #[clap(flatten)]
     features: WasmFeatures,
 }
@@ -167,6 +206,8 @@ impl CompilerOptions {
         let engine: Engine = wasmer_compiler::EngineBuilder::new(compiler_config)
             .set_features(Some(features))
             .set_target(Some(target))
+            .set_debug_info(self.debug_info)
+            .set_profiler(self.profile.map(Into::into))
             .engine();
 
         Ok(engine)
@@ -184,6 +225,9 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.frame_pointers {
+                    config.enable_frame_pointers();
+                }
                 Box::new(config)
             }
             #[cfg(feature = "cranelift")]
@@ -192,6 +236,9 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.frame_pointers {
+                    config.enable_frame_pointers();
+                }
                 Box::new(config)
             }
             #[cfg(feature = "llvm")]
@@ -294,6 +341,9 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.frame_pointers {
+                    config.enable_frame_pointers();
+                }
                 Box::new(config)
             }
             #[cfg(not(all(feature = "singlepass", feature = "cranelift", feature = "llvm",)))]
@@ -353,6 +403,12 @@ impl ToString for CompilerType {
 
 #[cfg(feature = "compiler")]
 impl StoreOptions {
+    /// Keep a frame pointer in every function of the generated code, so
+    /// that the sampling profiler can walk the stacks of wasm code.
+    pub fn enable_frame_pointers(&mut self) {
+        self.compiler.frame_pointers = true;
+    }
+
     /// Gets the store for the host target, with the compiler name selected
     pub fn get_store(&self) -> Result<(Store, CompilerType)> {
         let target = Target::default();
@@ -367,6 +423,21 @@ impl StoreOptions {
         Ok((store, compiler_type))
     }
 
+    /// Gets the store for the host target, with the `middlewares` pushed
+    /// onto the compiler.
+    pub fn get_store_with_middlewares(
+        &self,
+        middlewares: impl IntoIterator<Item = Arc<dyn ModuleMiddleware>>,
+    ) -> Result<(Store, CompilerType)> {
+        let (mut compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
+        for middleware in middlewares {
+            compiler_config.push_middleware(middleware);
+        }
+        let engine = self.get_engine_with_compiler(Target::default(), compiler_config)?;
+        let store = Store::new(engine);
+        Ok((store, compiler_type))
+    }
+
     #[cfg(feature = "compiler")]
     fn get_engine_with_compiler(
         &self,
@@ -5,6 +5,7 @@ pub(crate) mod prompts;
 pub(crate) mod render;
 
 use std::{
+    net::{Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -72,6 +73,63 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     }
 }
 
+/// Parses a host UNIX socket that is bridged into the guest
+pub fn parse_unix_socket(entry: &str) -> Result<(PathBuf, PathBuf)> {
+    let (guest, host) = match entry.split_once("::") {
+        Some(paths) => paths,
+        None => match entry.split_once(':') {
+            Some(paths) => paths,
+            None => bail!(
+                "UNIX socket bridges must consist of two paths separated by a `::` or `:`. Found {}",
+                &entry
+            ),
+        },
+    };
+
+    if !guest.starts_with('/') {
+        bail!(
+            "The guest path of a UNIX socket must be absolute. Found {}",
+            guest
+        );
+    }
+    Ok((PathBuf::from(guest), PathBuf::from(host)))
+}
+
+/// Parses the address of the debug endpoint, a port on its own is served on
+/// the loopback interface
+pub fn parse_debug_listen(entry: &str) -> Result<SocketAddr> {
+    if let Ok(port) = entry.parse::<u16>() {
+        return Ok(SocketAddr::from((Ipv4Addr::LOCALHOST, port)));
+    }
+    entry.parse().map_err(|_| {
+        anyhow::anyhow!(
+            "The debug endpoint must listen on a port or an address with a port. Found {}",
+            entry
+        )
+    })
+}
+
+/// Parses the user (and optionally the group) that a guest runs as, in the
+/// form `<uid>[:<gid>]` where `root` stands for zero
+pub fn parse_user(entry: &str) -> Result<(u32, u32)> {
+    fn parse_id(id: &str) -> Result<u32> {
+        match id.trim() {
+            "root" => Ok(0),
+            id => id.parse().map_err(|_| {
+                anyhow::anyhow!("User and group IDs must be numbers or `root`. Found {}", id)
+            }),
+        }
+    }
+
+    match entry.split_once(':') {
+        Some((uid, gid)) => Ok((parse_id(uid)?, parse_id(gid)?)),
+        None => {
+            let uid = parse_id(entry)?;
+            Ok((uid, uid))
+        }
+    }
+}
+
 pub(crate) const DEFAULT_PACKAGE_MANIFEST_FILE: &str = "wasmer.toml";
 
 /// Load a package manifest from the manifest file.
@@ -456,6 +514,59 @@ c: c
         }
     }
 
+    #[test]
+    fn test_parse_unix_socket() {
+        assert_eq!(
+            parse_unix_socket("/run/docker.sock:/var/run/docker.sock").unwrap(),
+            (
+                PathBuf::from("/run/docker.sock"),
+                PathBuf::from("/var/run/docker.sock")
+            )
+        );
+        assert_eq!(
+            parse_unix_socket("/tmp/a.sock::relative.sock").unwrap(),
+            (PathBuf::from("/tmp/a.sock"), PathBuf::from("relative.sock"))
+        );
+        assert_eq!(
+            parse_unix_socket("/tmp/a.sock").unwrap_err().to_string(),
+            "UNIX socket bridges must consist of two paths separated by a `::` or `:`. Found /tmp/a.sock"
+        );
+        assert_eq!(
+            parse_unix_socket("a.sock:/tmp/a.sock")
+                .unwrap_err()
+                .to_string(),
+            "The guest path of a UNIX socket must be absolute. Found a.sock"
+        );
+    }
+
+    #[test]
+    fn test_parse_user() {
+        assert_eq!(parse_user("1000").unwrap(), (1000, 1000));
+        assert_eq!(parse_user("1000:100").unwrap(), (1000, 100));
+        assert_eq!(parse_user("root").unwrap(), (0, 0));
+        assert_eq!(parse_user("1000:root").unwrap(), (1000, 0));
+        assert_eq!(
+            parse_user("alice").unwrap_err().to_string(),
+            "User and group IDs must be numbers or `root`. Found alice"
+        );
+    }
+
+    #[test]
+    fn test_parse_debug_listen() {
+        assert_eq!(
+            parse_debug_listen("7777").unwrap(),
+            "127.0.0.1:7777".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_debug_listen("0.0.0.0:7777").unwrap(),
+            "0.0.0.0:7777".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_debug_listen("localhost").unwrap_err().to_string(),
+            "The debug endpoint must listen on a port or an address with a port. Found localhost"
+        );
+    }
+
     #[test]
     fn test_parse_envvar() {
         assert_eq!(
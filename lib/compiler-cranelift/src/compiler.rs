@@ -13,10 +13,11 @@ use crate::translator::{
     signature_to_cranelift_ir, CraneliftUnwindInfo, FuncTranslator,
 };
 use cranelift_codegen::ir::{ExternalName, UserFuncName};
+use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{ir, MachReloc};
 use cranelift_codegen::{Context, MachTrap};
 #[cfg(feature = "unwind")]
-use gimli::write::{Address, EhFrame, FrameTable};
+use gimli::write::{Address, CieId, EhFrame, FrameDescriptionEntry, FrameTable};
 #[cfg(feature = "rayon")]
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
@@ -27,11 +28,17 @@ use wasmer_compiler::{
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     CallingConvention, Compilation, CompileError, CompileModuleInfo, CompiledFunction,
-    CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo, Dwarf, FunctionBody, FunctionIndex,
-    LocalFunctionIndex, ModuleInfo, Relocation, RelocationTarget, SectionIndex, SignatureIndex,
+    CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo, CustomSections, Dwarf, FunctionBody,
+    FunctionIndex, LocalFunctionIndex, ModuleInfo, Relocation, RelocationTarget, SignatureIndex,
     Target, TrapCode, TrapInformation,
 };
 
+/// The unwind info of a function, that goes in the `eh_frame` section.
+#[cfg(feature = "unwind")]
+type Fde = FrameDescriptionEntry;
+#[cfg(not(feature = "unwind"))]
+type Fde = ();
+
 /// A compiler that compiles a WebAssembly module with Cranelift, translating the Wasm to Cranelift IR,
 /// optimizing it and then translating to assembly.
 pub struct CraneliftCompiler {
@@ -48,6 +55,177 @@ impl CraneliftCompiler {
     pub fn config(&self) -> &Cranelift {
         &self.config
     }
+
+    /// Translates the signatures of a module to Cranelift IR.
+    fn signatures(
+        isa: &dyn TargetIsa,
+        module: &ModuleInfo,
+    ) -> PrimaryMap<SignatureIndex, ir::Signature> {
+        module
+            .signatures
+            .iter()
+            .map(|(_sig_index, func_type)| {
+                signature_to_cranelift_ir(func_type, isa.frontend_config())
+            })
+            .collect()
+    }
+
+    /// Creates the frame table describing the unwind info of the compiled
+    /// functions, if the target uses DWARF unwind info.
+    #[cfg(feature = "unwind")]
+    fn dwarf_frametable(isa: &dyn TargetIsa, target: &Target) -> Option<(FrameTable, CieId)> {
+        match target.triple().default_calling_convention() {
+            Ok(CallingConvention::SystemV) => {
+                match isa.create_systemv_cie() {
+                    Some(cie) => {
+                        let mut dwarf_frametable = FrameTable::default();
+                        let cie_id = dwarf_frametable.add_cie(cie);
+                        Some((dwarf_frametable, cie_id))
+                    }
+                    // Even though we are in a SystemV system, Cranelift doesn't support it
+                    None => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes the FDEs of the compiled functions to an `eh_frame` custom
+    /// section.
+    #[cfg(feature = "unwind")]
+    fn write_eh_frame(
+        target: &Target,
+        mut dwarf_frametable: FrameTable,
+        cie_id: CieId,
+        fdes: Vec<Option<FrameDescriptionEntry>>,
+        custom_sections: &mut CustomSections,
+    ) -> Dwarf {
+        for fde in fdes.into_iter().flatten() {
+            dwarf_frametable.add_fde(cie_id, fde);
+        }
+        let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
+        dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
+
+        Dwarf::new(custom_sections.push(eh_frame.0.into_section()))
+    }
+
+    /// Compiles the body of a local function, and returns it along with the
+    /// FDE describing its unwind info if `emit_fde` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn compile_function_body(
+        &self,
+        isa: &dyn TargetIsa,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        signatures: &PrimaryMap<SignatureIndex, ir::Signature>,
+        func_translator: &mut FuncTranslator,
+        i: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+        emit_fde: bool,
+    ) -> Result<(CompiledFunction, Option<Fde>), CompileError> {
+        let module = &compile_info.module;
+        let func_index = module.func_index(i);
+        let mut context = Context::new();
+        let mut func_env = FuncEnvironment::new(
+            isa.frontend_config(),
+            module,
+            signatures,
+            &compile_info.memory_styles,
+            &compile_info.table_styles,
+        );
+        context.func.name = match get_function_name(func_index) {
+            ExternalName::User(nameref) => {
+                if context.func.params.user_named_funcs().is_valid(nameref) {
+                    let name = &context.func.params.user_named_funcs()[nameref];
+                    UserFuncName::User(name.clone())
+                } else {
+                    UserFuncName::default()
+                }
+            }
+            ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
+            _ => UserFuncName::default(),
+        };
+        context.func.signature = signatures[module.functions[func_index]].clone();
+        // if generate_debug_info {
+        //     context.func.collect_debug_info();
+        // }
+        let mut reader = MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
+        reader.set_middleware_chain(
+            self.config
+                .middlewares
+                .generate_function_middleware_chain_for_module(module, i),
+        );
+
+        func_translator.translate(
+            module_translation_state,
+            &mut reader,
+            &mut context.func,
+            &mut func_env,
+            i,
+        )?;
+
+        let mut code_buf: Vec<u8> = Vec::new();
+        context
+            .compile_and_emit(isa, &mut code_buf)
+            .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
+
+        let result = context.compiled_code().unwrap();
+        let func_relocs = result
+            .buffer
+            .relocs()
+            .iter()
+            .map(|r| mach_reloc_to_reloc(module, r))
+            .collect::<Vec<_>>();
+
+        let traps = result
+            .buffer
+            .traps()
+            .iter()
+            .map(mach_trap_to_trap)
+            .collect::<Vec<_>>();
+
+        let (unwind_info, fde) = match compiled_function_unwind_info(isa, &context)? {
+            #[cfg(feature = "unwind")]
+            CraneliftUnwindInfo::Fde(fde) => {
+                if emit_fde {
+                    let fde = fde.to_fde(Address::Symbol {
+                        // The symbol is the kind of relocation.
+                        // "0" is used for functions
+                        symbol: WriterRelocate::FUNCTION_SYMBOL,
+                        // We use the addend as a way to specify the
+                        // function index
+                        addend: i.index() as _,
+                    });
+                    // The unwind information is inserted into the dwarf section
+                    (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
+                } else {
+                    (None, None)
+                }
+            }
+            #[cfg(feature = "unwind")]
+            other => (other.maybe_into_to_windows_unwind(), None),
+
+            // This is a bit hacky, but necessary since gimli is not
+            // available when the "unwind" feature is disabled.
+            #[cfg(not(feature = "unwind"))]
+            other => (other.maybe_into_to_windows_unwind(), None::<()>),
+        };
+
+        let range = reader.range();
+        let address_map = get_function_address_map(&context, range, code_buf.len());
+
+        Ok((
+            CompiledFunction {
+                body: FunctionBody {
+                    body: code_buf,
+                    unwind_info,
+                },
+                relocations: func_relocs,
+                frame_info: CompiledFunctionFrameInfo { address_map, traps },
+            },
+            fde,
+        ))
+    }
 }
 
 impl Compiler for CraneliftCompiler {
@@ -60,6 +238,13 @@ impl Compiler for CraneliftCompiler {
         &self.config.middlewares
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        // The middlewares provide their own cache keys.
+        let mut config = self.config.clone();
+        config.middlewares.clear();
+        Some(format!("{}:{:?}", self.name(), config))
+    }
+
     /// Compile the module using Cranelift, producing a compilation result with
     /// associated relocations.
     fn compile_module(
@@ -74,14 +259,8 @@ impl Compiler for CraneliftCompiler {
             .isa(target)
             .map_err(|error| CompileError::Codegen(error.to_string()))?;
         let frontend_config = isa.frontend_config();
-        let memory_styles = &compile_info.memory_styles;
-        let table_styles = &compile_info.table_styles;
         let module = &compile_info.module;
-        let signatures = module
-            .signatures
-            .iter()
-            .map(|(_sig_index, func_type)| signature_to_cranelift_ir(func_type, frontend_config))
-            .collect::<PrimaryMap<SignatureIndex, ir::Signature>>();
+        let signatures = Self::signatures(&*isa, module);
 
         // Generate the frametable
         #[cfg(feature = "unwind")]
@@ -91,21 +270,12 @@ impl Compiler for CraneliftCompiler {
             // FDEs will cause some issues in Linux.
             None
         } else {
-            match target.triple().default_calling_convention() {
-                Ok(CallingConvention::SystemV) => {
-                    match isa.create_systemv_cie() {
-                        Some(cie) => {
-                            let mut dwarf_frametable = FrameTable::default();
-                            let cie_id = dwarf_frametable.add_cie(cie);
-                            Some((dwarf_frametable, cie_id))
-                        }
-                        // Even though we are in a SystemV system, Cranelift doesn't support it
-                        None => None,
-                    }
-                }
-                _ => None,
-            }
+            Self::dwarf_frametable(&*isa, target)
         };
+        #[cfg(feature = "unwind")]
+        let emit_fdes = dwarf_frametable.is_some();
+        #[cfg(not(feature = "unwind"))]
+        let emit_fdes = false;
 
         let mut custom_sections = PrimaryMap::new();
 
@@ -117,108 +287,16 @@ impl Compiler for CraneliftCompiler {
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_iter()
             .map(|(i, input)| {
-                let func_index = module.func_index(i);
-                let mut context = Context::new();
-                let mut func_env = FuncEnvironment::new(
-                    isa.frontend_config(),
-                    module,
-                    &signatures,
-                    &memory_styles,
-                    &table_styles,
-                );
-                context.func.name = match get_function_name(func_index) {
-                    ExternalName::User(nameref) => {
-                        if context.func.params.user_named_funcs().is_valid(nameref) {
-                            let name = &context.func.params.user_named_funcs()[nameref];
-                            UserFuncName::User(name.clone())
-                        } else {
-                            UserFuncName::default()
-                        }
-                    }
-                    ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
-                    _ => UserFuncName::default(),
-                };
-                context.func.signature = signatures[module.functions[func_index]].clone();
-                // if generate_debug_info {
-                //     context.func.collect_debug_info();
-                // }
-                let mut reader =
-                    MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
-                reader.set_middleware_chain(
-                    self.config
-                        .middlewares
-                        .generate_function_middleware_chain(i),
-                );
-
-                func_translator.translate(
+                self.compile_function_body(
+                    &*isa,
+                    compile_info,
                     module_translation_state,
-                    &mut reader,
-                    &mut context.func,
-                    &mut func_env,
+                    &signatures,
+                    &mut func_translator,
                     i,
-                )?;
-
-                let mut code_buf: Vec<u8> = Vec::new();
-                context
-                    .compile_and_emit(&*isa, &mut code_buf)
-                    .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
-
-                let result = context.compiled_code().unwrap();
-                let func_relocs = result
-                    .buffer
-                    .relocs()
-                    .into_iter()
-                    .map(|r| mach_reloc_to_reloc(module, r))
-                    .collect::<Vec<_>>();
-
-                let traps = result
-                    .buffer
-                    .traps()
-                    .into_iter()
-                    .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
-
-                let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
-                    #[cfg(feature = "unwind")]
-                    CraneliftUnwindInfo::Fde(fde) => {
-                        if dwarf_frametable.is_some() {
-                            let fde = fde.to_fde(Address::Symbol {
-                                // The symbol is the kind of relocation.
-                                // "0" is used for functions
-                                symbol: WriterRelocate::FUNCTION_SYMBOL,
-                                // We use the addend as a way to specify the
-                                // function index
-                                addend: i.index() as _,
-                            });
-                            // The unwind information is inserted into the dwarf section
-                            (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
-                        } else {
-                            (None, None)
-                        }
-                    }
-                    #[cfg(feature = "unwind")]
-                    other => (other.maybe_into_to_windows_unwind(), None),
-
-                    // This is a bit hacky, but necessary since gimli is not
-                    // available when the "unwind" feature is disabled.
-                    #[cfg(not(feature = "unwind"))]
-                    other => (other.maybe_into_to_windows_unwind(), None::<()>),
-                };
-
-                let range = reader.range();
-                let address_map = get_function_address_map(&context, range, code_buf.len());
-
-                Ok((
-                    CompiledFunction {
-                        body: FunctionBody {
-                            body: code_buf,
-                            unwind_info,
-                        },
-                        relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
-                    },
-                    fde,
-                ))
+                    input,
+                    emit_fdes,
+                )
             })
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
@@ -229,127 +307,25 @@ impl Compiler for CraneliftCompiler {
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .par_iter()
             .map_init(FuncTranslator::new, |func_translator, (i, input)| {
-                let func_index = module.func_index(*i);
-                let mut context = Context::new();
-                let mut func_env = FuncEnvironment::new(
-                    isa.frontend_config(),
-                    module,
-                    &signatures,
-                    memory_styles,
-                    table_styles,
-                );
-                context.func.name = match get_function_name(func_index) {
-                    ExternalName::User(nameref) => {
-                        if context.func.params.user_named_funcs().is_valid(nameref) {
-                            let name = &context.func.params.user_named_funcs()[nameref];
-                            UserFuncName::User(name.clone())
-                        } else {
-                            UserFuncName::default()
-                        }
-                    }
-                    ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
-                    _ => UserFuncName::default(),
-                };
-                context.func.signature = signatures[module.functions[func_index]].clone();
-                // if generate_debug_info {
-                //     context.func.collect_debug_info();
-                // }
-                let mut reader =
-                    MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
-                reader.set_middleware_chain(
-                    self.config
-                        .middlewares
-                        .generate_function_middleware_chain(*i),
-                );
-
-                func_translator.translate(
+                self.compile_function_body(
+                    &*isa,
+                    compile_info,
                     module_translation_state,
-                    &mut reader,
-                    &mut context.func,
-                    &mut func_env,
+                    &signatures,
+                    func_translator,
                     *i,
-                )?;
-
-                let mut code_buf: Vec<u8> = Vec::new();
-                context
-                    .compile_and_emit(&*isa, &mut code_buf)
-                    .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
-
-                let result = context.compiled_code().unwrap();
-                let func_relocs = result
-                    .buffer
-                    .relocs()
-                    .iter()
-                    .map(|r| mach_reloc_to_reloc(module, r))
-                    .collect::<Vec<_>>();
-
-                let traps = result
-                    .buffer
-                    .traps()
-                    .iter()
-                    .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
-
-                let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
-                    #[cfg(feature = "unwind")]
-                    CraneliftUnwindInfo::Fde(fde) => {
-                        if dwarf_frametable.is_some() {
-                            let fde = fde.to_fde(Address::Symbol {
-                                // The symbol is the kind of relocation.
-                                // "0" is used for functions
-                                symbol: WriterRelocate::FUNCTION_SYMBOL,
-                                // We use the addend as a way to specify the
-                                // function index
-                                addend: i.index() as _,
-                            });
-                            // The unwind information is inserted into the dwarf section
-                            (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
-                        } else {
-                            (None, None)
-                        }
-                    }
-                    #[cfg(feature = "unwind")]
-                    other => (other.maybe_into_to_windows_unwind(), None),
-
-                    // This is a bit hacky, but necessary since gimli is not
-                    // available when the "unwind" feature is disabled.
-                    #[cfg(not(feature = "unwind"))]
-                    other => (other.maybe_into_to_windows_unwind(), None::<()>),
-                };
-
-                let range = reader.range();
-                let address_map = get_function_address_map(&context, range, code_buf.len());
-
-                Ok((
-                    CompiledFunction {
-                        body: FunctionBody {
-                            body: code_buf,
-                            unwind_info,
-                        },
-                        relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
-                    },
-                    fde,
-                ))
+                    input,
+                    emit_fdes,
+                )
             })
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .unzip();
 
         #[cfg(feature = "unwind")]
-        let dwarf = if let Some((mut dwarf_frametable, cie_id)) = dwarf_frametable {
-            for fde in fdes.into_iter().flatten() {
-                dwarf_frametable.add_fde(cie_id, fde);
-            }
-            let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
-            dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
-
-            let eh_frame_section = eh_frame.0.into_section();
-            custom_sections.push(eh_frame_section);
-            Some(Dwarf::new(SectionIndex::new(custom_sections.len() - 1)))
-        } else {
-            None
-        };
+        let dwarf = dwarf_frametable.map(|(dwarf_frametable, cie_id)| {
+            Self::write_eh_frame(target, dwarf_frametable, cie_id, fdes, &mut custom_sections)
+        });
         #[cfg(not(feature = "unwind"))]
         let dwarf = None;
 
@@ -413,6 +389,58 @@ impl Compiler for CraneliftCompiler {
             debug: dwarf,
         })
     }
+
+    fn supports_compile_function(&self) -> bool {
+        true
+    }
+
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        function_body: &FunctionBodyData<'_>,
+    ) -> Result<(CompiledFunction, CustomSections, Option<Dwarf>), CompileError> {
+        let isa = self
+            .config()
+            .isa(target)
+            .map_err(|error| CompileError::Codegen(error.to_string()))?;
+        let signatures = Self::signatures(&*isa, &compile_info.module);
+        #[cfg(feature = "unwind")]
+        let dwarf_frametable = Self::dwarf_frametable(&*isa, target);
+        #[cfg(feature = "unwind")]
+        let emit_fdes = dwarf_frametable.is_some();
+        #[cfg(not(feature = "unwind"))]
+        let emit_fdes = false;
+
+        let (function, fde) = self.compile_function_body(
+            &*isa,
+            compile_info,
+            module_translation_state,
+            &signatures,
+            &mut FuncTranslator::new(),
+            index,
+            function_body,
+            emit_fdes,
+        )?;
+
+        let mut custom_sections = PrimaryMap::new();
+        #[cfg(feature = "unwind")]
+        let dwarf = dwarf_frametable.map(|(dwarf_frametable, cie_id)| {
+            Self::write_eh_frame(
+                target,
+                dwarf_frametable,
+                cie_id,
+                vec![fde],
+                &mut custom_sections,
+            )
+        });
+        #[cfg(not(feature = "unwind"))]
+        let dwarf = None;
+
+        Ok((function, custom_sections, dwarf))
+    }
 }
 
 fn mach_reloc_to_reloc(module: &ModuleInfo, reloc: &MachReloc) -> Relocation {
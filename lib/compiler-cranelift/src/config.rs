@@ -32,6 +32,7 @@ pub struct Cranelift {
     enable_nan_canonicalization: bool,
     enable_verifier: bool,
     enable_pic: bool,
+    enable_frame_pointers: bool,
     opt_level: CraneliftOptLevel,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
@@ -46,6 +47,7 @@ impl Cranelift {
             enable_verifier: false,
             opt_level: CraneliftOptLevel::Speed,
             enable_pic: false,
+            enable_frame_pointers: false,
             middlewares: vec![],
         }
     }
@@ -166,6 +168,14 @@ impl Cranelift {
             .set("enable_safepoints", "true")
             .expect("should be valid flag");
 
+        // Leaf functions keep a frame too, so that the sampling profiler
+        // can walk the frame pointer chain of wasm code.
+        if self.enable_frame_pointers {
+            flags
+                .enable("preserve_frame_pointers")
+                .expect("should be valid flag");
+        }
+
         flags
             .set(
                 "opt_level",
@@ -209,6 +219,14 @@ impl CompilerConfig for Cranelift {
         self.enable_verifier = true;
     }
 
+    fn enable_frame_pointers(&mut self) {
+        self.enable_frame_pointers = true;
+    }
+
+    fn frame_pointers_enabled(&self) -> bool {
+        self.enable_frame_pointers
+    }
+
     fn canonicalize_nans(&mut self, enable: bool) {
         self.enable_nan_canonicalization = enable;
     }
@@ -218,6 +236,10 @@ impl CompilerConfig for Cranelift {
         Box::new(CraneliftCompiler::new(*self))
     }
 
+    fn shared_compiler(&self) -> Option<Arc<dyn Compiler + Send + Sync>> {
+        Some(Arc::new(CraneliftCompiler::new(self.clone())))
+    }
+
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
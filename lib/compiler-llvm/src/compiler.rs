@@ -204,6 +204,13 @@ impl Compiler for LLVMCompiler {
         &self.config.middlewares
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        // The middlewares provide their own cache keys.
+        let mut config = self.config.clone();
+        config.middlewares.clear();
+        Some(format!("{}:{:?}", self.name(), config))
+    }
+
     fn experimental_native_compile_module(
         &self,
         target: &Target,
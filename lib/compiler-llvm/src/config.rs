@@ -41,6 +41,7 @@ pub trait LLVMCallbacks: Debug + Send + Sync {
 pub struct LLVM {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_verifier: bool,
+    pub(crate) enable_frame_pointers: bool,
     pub(crate) opt_level: LLVMOptLevel,
     is_pic: bool,
     pub(crate) callbacks: Option<Arc<dyn LLVMCallbacks>>,
@@ -55,6 +56,7 @@ impl LLVM {
         Self {
             enable_nan_canonicalization: false,
             enable_verifier: false,
+            enable_frame_pointers: false,
             opt_level: LLVMOptLevel::Aggressive,
             is_pic: false,
             callbacks: None,
@@ -259,6 +261,15 @@ impl CompilerConfig for LLVM {
         self.enable_verifier = true;
     }
 
+    /// Whether to keep frame pointers in leaf functions.
+    fn enable_frame_pointers(&mut self) {
+        self.enable_frame_pointers = true;
+    }
+
+    fn frame_pointers_enabled(&self) -> bool {
+        self.enable_frame_pointers
+    }
+
     fn canonicalize_nans(&mut self, enable: bool) {
         self.enable_nan_canonicalization = enable;
     }
@@ -268,6 +279,10 @@ impl CompilerConfig for LLVM {
         Box::new(LLVMCompiler::new(*self))
     }
 
+    fn shared_compiler(&self) -> Option<Arc<dyn Compiler + Send + Sync>> {
+        Some(Arc::new(LLVMCompiler::new(self.clone())))
+    }
+
     /// Pushes a middleware onto the back of the middleware chain.
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
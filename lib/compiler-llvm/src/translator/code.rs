@@ -104,6 +104,10 @@ impl FuncTranslator {
         }
 
         func.add_attribute(AttributeLoc::Function, intrinsics.stack_probe);
+        // Keep the frame pointer chain intact for the sampling profiler.
+        if config.enable_frame_pointers {
+            func.add_attribute(AttributeLoc::Function, intrinsics.frame_pointer);
+        }
         func.set_personality_function(intrinsics.personality);
         func.as_global_value().set_section(Some(FUNCTION_SECTION));
         func.set_linkage(Linkage::DLLExport);
@@ -139,7 +143,7 @@ impl FuncTranslator {
         reader.set_middleware_chain(
             config
                 .middlewares
-                .generate_function_middleware_chain(*local_func_index),
+                .generate_function_middleware_chain_for_module(wasm_module, *local_func_index),
         );
 
         let mut params = vec![];
@@ -158,6 +158,7 @@ pub struct Intrinsics<'ctx> {
     pub personality: FunctionValue<'ctx>,
     pub readonly: Attribute,
     pub stack_probe: Attribute,
+    pub frame_pointer: Attribute,
 
     pub void_ty: VoidType<'ctx>,
     pub i1_ty: IntType<'ctx>,
@@ -722,6 +723,7 @@ impl<'ctx> Intrinsics<'ctx> {
             readonly: context
                 .create_enum_attribute(Attribute::get_named_enum_kind_id("readonly"), 0),
             stack_probe: context.create_string_attribute("probe-stack", "inline-asm"),
+            frame_pointer: context.create_string_attribute("frame-pointer", "all"),
 
             void_ty,
             i1_ty,
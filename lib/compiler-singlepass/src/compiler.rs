@@ -13,10 +13,11 @@ use crate::machine::{
 use crate::machine_arm64::MachineARM64;
 use crate::machine_x64::MachineX86_64;
 #[cfg(feature = "unwind")]
-use crate::unwind::{create_systemv_cie, UnwindFrame};
+use crate::unwind::create_systemv_cie;
+use crate::unwind::UnwindFrame;
 use enumset::EnumSet;
 #[cfg(feature = "unwind")]
-use gimli::write::{EhFrame, FrameTable};
+use gimli::write::{CieId, EhFrame, FrameTable};
 #[cfg(feature = "rayon")]
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
@@ -27,7 +28,7 @@ use wasmer_compiler::{
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
-    CompiledFunction, CpuFeature, Dwarf, FunctionBody, FunctionIndex, FunctionType,
+    CompiledFunction, CpuFeature, CustomSections, Dwarf, FunctionBody, FunctionIndex, FunctionType,
     LocalFunctionIndex, MemoryIndex, ModuleInfo, OperatingSystem, SectionIndex, TableIndex, Target,
     TrapCode, TrapInformation, VMOffsets,
 };
@@ -48,6 +49,171 @@ impl SinglepassCompiler {
     fn config(&self) -> &Singlepass {
         &self.config
     }
+
+    /// Returns the calling convention of the target, or an error if the
+    /// target isn't supported.
+    fn calling_convention(target: &Target) -> Result<CallingConvention, CompileError> {
+        match target.triple().architecture {
+            Architecture::X86_64 => {}
+            Architecture::Aarch64(_) => {}
+            _ => {
+                return Err(CompileError::UnsupportedTarget(
+                    target.triple().architecture.to_string(),
+                ))
+            }
+        }
+
+        match target.triple().default_calling_convention() {
+            Ok(CallingConvention::WindowsFastcall) => Ok(CallingConvention::WindowsFastcall),
+            Ok(CallingConvention::SystemV) => Ok(CallingConvention::SystemV),
+            Ok(CallingConvention::AppleAarch64) => Ok(CallingConvention::AppleAarch64),
+            _ => Err(CompileError::UnsupportedTarget(
+                "Unsupported Calling convention for Singlepass compiler".to_string(),
+            )),
+        }
+    }
+
+    /// Generates the trampolines calling imported functions, which are the
+    /// first custom sections of a compilation.
+    fn import_call_trampolines(
+        target: &Target,
+        calling_convention: CallingConvention,
+        vmoffsets: &VMOffsets,
+        module: &ModuleInfo,
+    ) -> CustomSections {
+        (0..module.num_imported_functions)
+            .map(FunctionIndex::new)
+            .collect::<Vec<_>>()
+            .into_par_iter_if_rayon()
+            .map(|i| {
+                gen_import_call_trampoline(
+                    vmoffsets,
+                    i,
+                    &module.signatures[module.functions[i]],
+                    target,
+                    calling_convention,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Creates the frame table describing the unwind info of the compiled
+    /// functions, if the target uses DWARF unwind info.
+    #[cfg(feature = "unwind")]
+    fn dwarf_frametable(target: &Target) -> Option<(FrameTable, CieId)> {
+        match target.triple().default_calling_convention() {
+            Ok(CallingConvention::SystemV) => {
+                create_systemv_cie(target.triple().architecture).map(|cie| {
+                    let mut dwarf_frametable = FrameTable::default();
+                    let cie_id = dwarf_frametable.add_cie(cie);
+                    (dwarf_frametable, cie_id)
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes the FDEs of the compiled functions to an `eh_frame` custom
+    /// section.
+    #[cfg(feature = "unwind")]
+    fn write_eh_frame(
+        target: &Target,
+        mut dwarf_frametable: FrameTable,
+        cie_id: CieId,
+        fdes: Vec<Option<UnwindFrame>>,
+        custom_sections: &mut CustomSections,
+    ) -> Dwarf {
+        for fde in fdes.into_iter().flatten() {
+            match fde {
+                UnwindFrame::SystemV(fde) => dwarf_frametable.add_fde(cie_id, fde),
+            }
+        }
+        let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
+        dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
+
+        Dwarf::new(custom_sections.push(eh_frame.0.into_section()))
+    }
+
+    /// Compiles the body of a local function, and returns it along with its
+    /// unwind info.
+    fn compile_function_body(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        vmoffsets: &VMOffsets,
+        calling_convention: CallingConvention,
+        i: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+    ) -> Result<(CompiledFunction, Option<UnwindFrame>), CompileError> {
+        let module = &compile_info.module;
+        let memory_styles = &compile_info.memory_styles;
+        let table_styles = &compile_info.table_styles;
+        let middleware_chain = self
+            .config
+            .middlewares
+            .generate_function_middleware_chain_for_module(module, i);
+        let mut reader = MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
+        reader.set_middleware_chain(middleware_chain);
+
+        // This local list excludes arguments.
+        let mut locals = vec![];
+        let num_locals = reader.read_local_count()?;
+        for _ in 0..num_locals {
+            let (count, ty) = reader.read_local_decl()?;
+            for _ in 0..count {
+                locals.push(ty);
+            }
+        }
+
+        match target.triple().architecture {
+            Architecture::X86_64 => {
+                let machine = MachineX86_64::new(Some(target.clone()))?;
+                let mut generator = FuncGen::new(
+                    module,
+                    &self.config,
+                    vmoffsets,
+                    memory_styles,
+                    table_styles,
+                    i,
+                    &locals,
+                    machine,
+                    calling_convention,
+                )?;
+                while generator.has_control_frames() {
+                    generator.set_srcloc(reader.original_position() as u32);
+                    let op = reader.read_operator()?;
+                    generator.feed_operator(op)?;
+                }
+
+                generator.finalize(input)
+            }
+            Architecture::Aarch64(_) => {
+                let machine = MachineARM64::new();
+                let mut generator = FuncGen::new(
+                    module,
+                    &self.config,
+                    vmoffsets,
+                    memory_styles,
+                    table_styles,
+                    i,
+                    &locals,
+                    machine,
+                    calling_convention,
+                )?;
+                while generator.has_control_frames() {
+                    generator.set_srcloc(reader.original_position() as u32);
+                    let op = reader.read_operator()?;
+                    generator.feed_operator(op)?;
+                }
+
+                generator.finalize(input)
+            }
+            _ => unimplemented!(),
+        }
+    }
 }
 
 impl Compiler for SinglepassCompiler {
@@ -60,6 +226,13 @@ impl Compiler for SinglepassCompiler {
         &self.config.middlewares
     }
 
+    fn fingerprint(&self) -> Option<String> {
+        // The middlewares provide their own cache keys.
+        let mut config = self.config.clone();
+        config.middlewares.clear();
+        Some(format!("{}:{:?}", self.name(), config))
+    }
+
     /// Compile the module using Singlepass, producing a compilation result with
     /// associated relocations.
     fn compile_module(
@@ -69,26 +242,7 @@ impl Compiler for SinglepassCompiler {
         _module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError> {
-        match target.triple().architecture {
-            Architecture::X86_64 => {}
-            Architecture::Aarch64(_) => {}
-            _ => {
-                return Err(CompileError::UnsupportedTarget(
-                    target.triple().architecture.to_string(),
-                ))
-            }
-        }
-
-        let calling_convention = match target.triple().default_calling_convention() {
-            Ok(CallingConvention::WindowsFastcall) => CallingConvention::WindowsFastcall,
-            Ok(CallingConvention::SystemV) => CallingConvention::SystemV,
-            Ok(CallingConvention::AppleAarch64) => CallingConvention::AppleAarch64,
-            _ => {
-                return Err(CompileError::UnsupportedTarget(
-                    "Unsupported Calling convention for Singlepass compiler".to_string(),
-                ))
-            }
-        };
+        let calling_convention = Self::calling_convention(target)?;
 
         // Generate the frametable
         #[cfg(feature = "unwind")]
@@ -98,110 +252,26 @@ impl Compiler for SinglepassCompiler {
             // FDEs will cause some issues in Linux.
             None
         } else {
-            match target.triple().default_calling_convention() {
-                Ok(CallingConvention::SystemV) => {
-                    match create_systemv_cie(target.triple().architecture) {
-                        Some(cie) => {
-                            let mut dwarf_frametable = FrameTable::default();
-                            let cie_id = dwarf_frametable.add_cie(cie);
-                            Some((dwarf_frametable, cie_id))
-                        }
-                        None => None,
-                    }
-                }
-                _ => None,
-            }
+            Self::dwarf_frametable(target)
         };
 
-        let memory_styles = &compile_info.memory_styles;
-        let table_styles = &compile_info.table_styles;
         let vmoffsets = VMOffsets::new(8, &compile_info.module);
         let module = &compile_info.module;
-        let mut custom_sections: PrimaryMap<SectionIndex, _> = (0..module.num_imported_functions)
-            .map(FunctionIndex::new)
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|i| {
-                gen_import_call_trampoline(
-                    &vmoffsets,
-                    i,
-                    &module.signatures[module.functions[i]],
-                    target,
-                    calling_convention,
-                )
-                .unwrap()
-            })
-            .collect::<Vec<_>>()
-            .into_iter()
-            .collect();
+        let mut custom_sections =
+            Self::import_call_trampolines(target, calling_convention, &vmoffsets, module);
         let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_par_iter_if_rayon()
             .map(|(i, input)| {
-                let middleware_chain = self
-                    .config
-                    .middlewares
-                    .generate_function_middleware_chain(i);
-                let mut reader =
-                    MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
-                reader.set_middleware_chain(middleware_chain);
-
-                // This local list excludes arguments.
-                let mut locals = vec![];
-                let num_locals = reader.read_local_count()?;
-                for _ in 0..num_locals {
-                    let (count, ty) = reader.read_local_decl()?;
-                    for _ in 0..count {
-                        locals.push(ty);
-                    }
-                }
-
-                match target.triple().architecture {
-                    Architecture::X86_64 => {
-                        let machine = MachineX86_64::new(Some(target.clone()))?;
-                        let mut generator = FuncGen::new(
-                            module,
-                            &self.config,
-                            &vmoffsets,
-                            memory_styles,
-                            table_styles,
-                            i,
-                            &locals,
-                            machine,
-                            calling_convention,
-                        )?;
-                        while generator.has_control_frames() {
-                            generator.set_srcloc(reader.original_position() as u32);
-                            let op = reader.read_operator()?;
-                            generator.feed_operator(op)?;
-                        }
-
-                        generator.finalize(input)
-                    }
-                    Architecture::Aarch64(_) => {
-                        let machine = MachineARM64::new();
-                        let mut generator = FuncGen::new(
-                            module,
-                            &self.config,
-                            &vmoffsets,
-                            memory_styles,
-                            table_styles,
-                            i,
-                            &locals,
-                            machine,
-                            calling_convention,
-                        )?;
-                        while generator.has_control_frames() {
-                            generator.set_srcloc(reader.original_position() as u32);
-                            let op = reader.read_operator()?;
-                            generator.feed_operator(op)?;
-                        }
-
-                        generator.finalize(input)
-                    }
-                    _ => unimplemented!(),
-                }
+                self.compile_function_body(
+                    target,
+                    compile_info,
+                    &vmoffsets,
+                    calling_convention,
+                    i,
+                    input,
+                )
             })
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
@@ -235,21 +305,9 @@ impl Compiler for SinglepassCompiler {
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
 
         #[cfg(feature = "unwind")]
-        let dwarf = if let Some((mut dwarf_frametable, cie_id)) = dwarf_frametable {
-            for fde in fdes.into_iter().flatten() {
-                match fde {
-                    UnwindFrame::SystemV(fde) => dwarf_frametable.add_fde(cie_id, fde),
-                }
-            }
-            let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
-            dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
-
-            let eh_frame_section = eh_frame.0.into_section();
-            custom_sections.push(eh_frame_section);
-            Some(Dwarf::new(SectionIndex::new(custom_sections.len() - 1)))
-        } else {
-            None
-        };
+        let dwarf = dwarf_frametable.map(|(dwarf_frametable, cie_id)| {
+            Self::write_eh_frame(target, dwarf_frametable, cie_id, fdes, &mut custom_sections)
+        });
         #[cfg(not(feature = "unwind"))]
         let dwarf = None;
 
@@ -262,6 +320,52 @@ impl Compiler for SinglepassCompiler {
         })
     }
 
+    fn supports_compile_function(&self) -> bool {
+        true
+    }
+
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        function_body: &FunctionBodyData<'_>,
+    ) -> Result<(CompiledFunction, CustomSections, Option<Dwarf>), CompileError> {
+        let calling_convention = Self::calling_convention(target)?;
+        let vmoffsets = VMOffsets::new(8, &compile_info.module);
+        // Calls to imported functions go through the trampolines.
+        let mut custom_sections = Self::import_call_trampolines(
+            target,
+            calling_convention,
+            &vmoffsets,
+            &compile_info.module,
+        );
+        let (function, fde) = self.compile_function_body(
+            target,
+            compile_info,
+            &vmoffsets,
+            calling_convention,
+            index,
+            function_body,
+        )?;
+
+        #[cfg(feature = "unwind")]
+        let dwarf = Self::dwarf_frametable(target).map(|(dwarf_frametable, cie_id)| {
+            Self::write_eh_frame(
+                target,
+                dwarf_frametable,
+                cie_id,
+                vec![fde],
+                &mut custom_sections,
+            )
+        });
+        #[cfg(not(feature = "unwind"))]
+        let dwarf = None;
+
+        Ok((function, custom_sections, dwarf))
+    }
+
     fn get_cpu_features_used(&self, cpu_features: &EnumSet<CpuFeature>) -> EnumSet<CpuFeature> {
         let used = CpuFeature::AVX | CpuFeature::SSE42 | CpuFeature::LZCNT | CpuFeature::BMI1;
         cpu_features.intersection(used)
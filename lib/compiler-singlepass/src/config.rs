@@ -40,6 +40,10 @@ impl CompilerConfig for Singlepass {
         Box::new(SinglepassCompiler::new(*self))
     }
 
+    fn shared_compiler(&self) -> Option<Arc<dyn Compiler + Send + Sync>> {
+        Some(Arc::new(SinglepassCompiler::new(self.clone())))
+    }
+
     /// Gets the default features for this compiler in the given target
     fn default_features_for_target(&self, _target: &Target) -> Features {
         let mut features = Features::default();
@@ -2,10 +2,23 @@
 //! done as separate steps.
 
 #[cfg(feature = "compiler")]
-use super::trampoline::{libcall_trampoline_len, make_libcall_trampolines};
+use super::eh_frame::{self, retain_unwind_info};
+#[cfg(feature = "compiler")]
+use super::trampoline::{
+    lazy_function_stubs_supported, libcall_trampoline_len, make_lazy_function_slots,
+    make_lazy_function_stub, make_libcall_trampolines,
+};
+#[cfg(feature = "compiler")]
+use crate::compiler::STUB_FUNCTION_BODY;
+#[cfg(feature = "compiler")]
+use crate::function_cache::{
+    rebase_section_relocations, splice_cached_functions, ModuleFunctionCache,
+};
 use crate::ArtifactCreate;
 use crate::EngineInner;
 use crate::Features;
+#[cfg(feature = "compiler")]
+use crate::{FunctionBodyData, ModuleTranslationState};
 use crate::{ModuleEnvironment, ModuleMiddlewareChain};
 use core::mem::MaybeUninit;
 use enumset::EnumSet;
@@ -13,24 +26,58 @@ use rkyv::de::deserializers::SharedDeserializeMap;
 use rkyv::option::ArchivedOption;
 use self_cell::self_cell;
 use shared_buffer::OwnedBuffer;
+#[cfg(feature = "compiler")]
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "compiler")]
+use std::ops::Range;
 use std::sync::Arc;
+#[cfg(feature = "compiler")]
+use wasmer_types::entity::EntityRef;
 use wasmer_types::entity::{ArchivedPrimaryMap, PrimaryMap};
 use wasmer_types::ArchivedOwnedDataInitializer;
 use wasmer_types::ArchivedSerializableCompilation;
 use wasmer_types::ArchivedSerializableModule;
-#[cfg(feature = "compiler")]
-use wasmer_types::CompileModuleInfo;
 use wasmer_types::DeserializeError;
 use wasmer_types::{
     CompileError, CpuFeature, CustomSection, Dwarf, FunctionIndex, LocalFunctionIndex, MemoryIndex,
     MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, SectionIndex, SignatureIndex,
     TableIndex, TableStyle, Target,
 };
+#[cfg(feature = "compiler")]
+use wasmer_types::{CompileModuleInfo, CompiledFunction, CustomSections, ExportIndex};
 use wasmer_types::{
     CompiledFunctionFrameInfo, FunctionBody, SerializableCompilation, SerializableModule,
 };
 use wasmer_types::{MetadataHeader, SerializeError};
 
+/// What is needed to compile the local functions of an `ArtifactBuild`
+/// again, from the wasm binary it was built from.
+#[cfg(feature = "compiler")]
+pub(crate) struct FunctionBodies {
+    pub(crate) module_translation: ModuleTranslationState,
+    /// The location of the body of every local function in the wasm binary.
+    pub(crate) ranges: PrimaryMap<LocalFunctionIndex, Range<usize>>,
+}
+
+/// The functions of an `ArtifactBuild` that are compiled on their first
+/// call.
+#[cfg(feature = "compiler")]
+pub(crate) struct LazyFunctions {
+    /// The custom section holding the slots of the lazy functions.
+    pub(crate) slots: SectionIndex,
+    /// The functions that were stubbed out.
+    pub(crate) functions: BTreeSet<LocalFunctionIndex>,
+}
+
+/// Functions compiled apart from an `ArtifactBuild`, with the custom
+/// sections their relocations refer to, and their unwind info.
+#[cfg(feature = "compiler")]
+pub(crate) type FunctionBatch = (
+    Vec<(LocalFunctionIndex, CompiledFunction)>,
+    CustomSections,
+    Option<Dwarf>,
+);
+
 /// A compiled wasm module, ready to be instantiated.
 pub struct ArtifactBuild {
     serializable: SerializableModule,
@@ -54,6 +101,57 @@ impl ArtifactBuild {
         memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: PrimaryMap<TableIndex, TableStyle>,
     ) -> Result<Self, CompileError> {
+        let (artifact, _, _) = Self::build(
+            inner_engine,
+            data,
+            target,
+            memory_styles,
+            table_styles,
+            false,
+        )?;
+        Ok(artifact)
+    }
+
+    /// Compile a data buffer into a `ArtifactBuild`, and return what is
+    /// needed to compile its functions again.
+    ///
+    /// If `lazy`, only the entry points are compiled, and the other
+    /// functions are stubbed out so that they are compiled on their first
+    /// call. The entry points are the exported functions and the start
+    /// function. If the target doesn't support lazy stubs, if the compiler
+    /// can't compile single functions, or if there are only entry points,
+    /// the whole module is compiled and no `LazyFunctions` are returned.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn new_with_function_bodies(
+        inner_engine: &mut EngineInner,
+        data: &[u8],
+        target: &Target,
+        memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
+        table_styles: PrimaryMap<TableIndex, TableStyle>,
+        lazy: bool,
+    ) -> Result<(Self, FunctionBodies, Option<LazyFunctions>), CompileError> {
+        let lazy = lazy
+            && lazy_function_stubs_supported(target)
+            && inner_engine.compiler()?.supports_compile_function();
+        Self::build(
+            inner_engine,
+            data,
+            target,
+            memory_styles,
+            table_styles,
+            lazy,
+        )
+    }
+
+    #[cfg(feature = "compiler")]
+    fn build(
+        inner_engine: &mut EngineInner,
+        data: &[u8],
+        target: &Target,
+        memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
+        table_styles: PrimaryMap<TableIndex, TableStyle>,
+        lazy: bool,
+    ) -> Result<(Self, FunctionBodies, Option<LazyFunctions>), CompileError> {
         let environ = ModuleEnvironment::new();
         let features = inner_engine.features().clone();
 
@@ -61,11 +159,33 @@ impl ArtifactBuild {
 
         let compiler = inner_engine.compiler()?;
 
-        // We try to apply the middleware first
+        // We try to apply the middleware first, keeping the module the
+        // function bodies refer to for the function cache.
         let mut module = translation.module;
+        let translated_module = inner_engine.function_cache().map(|_| module.clone());
         let middlewares = compiler.get_middlewares();
         middlewares.apply_on_module_info(&mut module);
 
+        let lazy_functions = if lazy {
+            let entry_points = module
+                .exports
+                .values()
+                .filter_map(|export| match export {
+                    ExportIndex::Function(index) => Some(*index),
+                    _ => None,
+                })
+                .chain(module.start_function)
+                .filter_map(|index| module.local_func_index(index))
+                .collect::<BTreeSet<_>>();
+            translation
+                .function_body_inputs
+                .keys()
+                .filter(|index| !entry_points.contains(index))
+                .collect::<BTreeSet<_>>()
+        } else {
+            BTreeSet::new()
+        };
+
         let compile_info = CompileModuleInfo {
             module: Arc::new(module),
             features,
@@ -73,17 +193,100 @@ impl ArtifactBuild {
             table_styles,
         };
 
+        let function_body_ranges = translation
+            .function_body_inputs
+            .values()
+            .map(|body| body.module_offset..body.module_offset + body.data.len())
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        // Functions found in the cache are stubbed out like lazy ones, and
+        // spliced back in once the rest of the module is compiled.
+        let function_cache = inner_engine
+            .function_cache()
+            .zip(translated_module.as_ref())
+            .and_then(|(function_cache, translated_module)| {
+                ModuleFunctionCache::new(
+                    function_cache,
+                    compiler,
+                    target,
+                    &compile_info,
+                    translated_module,
+                )
+            });
+        let mut cached_functions = BTreeMap::new();
+        let mut uncached_functions = Vec::new();
+        let function_body_inputs = translation
+            .function_body_inputs
+            .into_iter()
+            .map(|(index, body)| {
+                let stub = FunctionBodyData {
+                    data: STUB_FUNCTION_BODY,
+                    module_offset: body.module_offset,
+                };
+                if lazy_functions.contains(&index) {
+                    return stub;
+                }
+                let key = function_cache
+                    .as_ref()
+                    .and_then(|function_cache| function_cache.key(index, &body));
+                if let (Some(function_cache), Some(key)) = (&function_cache, key) {
+                    if let Some(cached) = function_cache.load(&key, body.module_offset) {
+                        cached_functions.insert(index, cached);
+                        return stub;
+                    }
+                    uncached_functions.push((index, key, body.module_offset));
+                }
+                body
+            })
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+
         // Compile the Module
-        let compilation = compiler.compile_module(
+        let mut compilation = compiler.compile_module(
             target,
             &compile_info,
             // SAFETY: Calling `unwrap` is correct since
             // `environ.translate()` above will write some data into
             // `module_translation_state`.
             translation.module_translation_state.as_ref().unwrap(),
-            translation.function_body_inputs,
+            function_body_inputs,
         )?;
 
+        if let Some(function_cache) = &function_cache {
+            let function_eh_frames = compilation
+                .debug
+                .as_ref()
+                .and_then(|debug| {
+                    eh_frame::split_functions(&compilation.custom_sections[debug.eh_frame], target)
+                })
+                .unwrap_or_default();
+            for (index, key, module_offset) in uncached_functions {
+                function_cache.store(
+                    &key,
+                    module_offset,
+                    index,
+                    &compilation.functions[index],
+                    &compilation.custom_sections,
+                    function_eh_frames.get(&index),
+                );
+            }
+        }
+
+        // The unwind info of the stubbed out functions doesn't match their
+        // lazy stubs, nor the cached functions that replace them.
+        if !lazy_functions.is_empty() || !cached_functions.is_empty() {
+            retain_unwind_info(&mut compilation, target, |index| {
+                !lazy_functions.contains(&index) && !cached_functions.contains_key(&index)
+            });
+        }
+        if !cached_functions.is_empty() {
+            splice_cached_functions(
+                &mut compilation.functions,
+                &mut compilation.custom_sections,
+                &mut compilation.debug,
+                target,
+                cached_functions,
+            );
+        }
+
         let data_initializers = translation
             .data_initializers
             .iter()
@@ -91,27 +294,42 @@ impl ArtifactBuild {
             .collect::<Vec<_>>()
             .into_boxed_slice();
 
-        // Synthesize a custom section to hold the libcall trampolines.
-        let mut function_frame_info = PrimaryMap::with_capacity(compilation.functions.len());
-        let mut function_bodies = PrimaryMap::with_capacity(compilation.functions.len());
-        let mut function_relocations = PrimaryMap::with_capacity(compilation.functions.len());
-        for (_, func) in compilation.functions.into_iter() {
-            function_bodies.push(func.body);
-            function_relocations.push(func.relocations);
-            function_frame_info.push(func.frame_info);
-        }
         let mut custom_sections = compilation.custom_sections.clone();
         let mut custom_section_relocations = compilation
             .custom_sections
             .iter()
             .map(|(_, section)| section.relocations.clone())
             .collect::<PrimaryMap<SectionIndex, _>>();
+        // Synthesize a custom section to hold the slots of lazy functions.
+        let lazy_function_slots = if lazy_functions.is_empty() {
+            None
+        } else {
+            let slots_section = make_lazy_function_slots(function_body_ranges.len());
+            custom_section_relocations.push(slots_section.relocations.clone());
+            Some(custom_sections.push(slots_section))
+        };
+        // Synthesize a custom section to hold the libcall trampolines.
         let libcall_trampolines_section = make_libcall_trampolines(target);
         custom_section_relocations.push(libcall_trampolines_section.relocations.clone());
         let libcall_trampolines = custom_sections.push(libcall_trampolines_section);
         let libcall_trampoline_len = libcall_trampoline_len(target) as u32;
         let cpu_features = compiler.get_cpu_features_used(target.cpu_features());
 
+        let mut function_frame_info = PrimaryMap::with_capacity(compilation.functions.len());
+        let mut function_bodies = PrimaryMap::with_capacity(compilation.functions.len());
+        let mut function_relocations = PrimaryMap::with_capacity(compilation.functions.len());
+        for (index, func) in compilation.functions.into_iter() {
+            let func = match lazy_function_slots {
+                Some(slots) if lazy_functions.contains(&index) => {
+                    make_lazy_function_stub(target, index, slots)
+                }
+                _ => func,
+            };
+            function_bodies.push(func.body);
+            function_relocations.push(func.relocations);
+            function_frame_info.push(func.frame_info);
+        }
+
         let serializable_compilation = SerializableCompilation {
             function_bodies,
             function_relocations,
@@ -130,7 +348,85 @@ impl ArtifactBuild {
             data_initializers,
             cpu_features: cpu_features.as_u64(),
         };
-        Ok(Self { serializable })
+        let function_bodies = FunctionBodies {
+            // SAFETY: see above.
+            module_translation: translation.module_translation_state.unwrap(),
+            ranges: function_body_ranges,
+        };
+        let lazy_functions = lazy_function_slots.map(|slots| LazyFunctions {
+            slots,
+            functions: lazy_functions,
+        });
+        Ok((Self { serializable }, function_bodies, lazy_functions))
+    }
+
+    /// Serializes this artifact, with some of its functions replaced.
+    ///
+    /// Each batch holds compiled functions, the custom sections their
+    /// relocations refer to, and their unwind info among those sections.
+    /// This is used to serialize artifacts whose functions were compiled
+    /// lazily.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn serialize_with_functions(
+        &self,
+        target: &Target,
+        batches: Vec<FunctionBatch>,
+    ) -> Result<Vec<u8>, SerializeError> {
+        let compilation = &self.serializable.compilation;
+        let mut function_bodies = compilation.function_bodies.clone();
+        let mut function_relocations = compilation.function_relocations.clone();
+        let mut function_frame_info = compilation.function_frame_info.clone();
+        let mut custom_sections = compilation.custom_sections.clone();
+        let mut custom_section_relocations = compilation.custom_section_relocations.clone();
+        let mut debug = compilation.debug.clone();
+
+        for (functions, sections, batch_debug) in batches {
+            let first_section = custom_sections.len();
+            for (_, mut section) in sections {
+                section.relocations =
+                    rebase_section_relocations(section.relocations, first_section);
+                custom_section_relocations.push(section.relocations.clone());
+                custom_sections.push(section);
+            }
+            for (index, function) in functions {
+                function_bodies[index] = function.body;
+                function_relocations[index] =
+                    rebase_section_relocations(function.relocations, first_section);
+                function_frame_info[index] = function.frame_info;
+            }
+            if let Some(batch_debug) = batch_debug {
+                let eh_frame = SectionIndex::new(first_section + batch_debug.eh_frame.index());
+                match &debug {
+                    Some(debug) => {
+                        let batch_eh_frame = custom_sections[eh_frame].clone();
+                        let merged = &mut custom_sections[debug.eh_frame];
+                        if eh_frame::append(merged, &batch_eh_frame, target).is_some() {
+                            custom_section_relocations[debug.eh_frame] = merged.relocations.clone();
+                        }
+                    }
+                    None => debug = Some(Dwarf::new(eh_frame)),
+                }
+            }
+        }
+
+        let serializable = SerializableModule {
+            compilation: SerializableCompilation {
+                function_bodies,
+                function_relocations,
+                function_frame_info,
+                function_call_trampolines: compilation.function_call_trampolines.clone(),
+                dynamic_function_trampolines: compilation.dynamic_function_trampolines.clone(),
+                custom_sections,
+                custom_section_relocations,
+                debug,
+                libcall_trampolines: compilation.libcall_trampolines,
+                libcall_trampoline_len: compilation.libcall_trampoline_len,
+            },
+            compile_info: self.serializable.compile_info.clone(),
+            data_initializers: self.serializable.data_initializers.clone(),
+            cpu_features: self.serializable.cpu_features,
+        };
+        serialize_module(&serializable)
     }
 
     /// Compile a data buffer into a `ArtifactBuild`, which may then be instantiated.
@@ -0,0 +1,230 @@
+//! Editing of the `eh_frame` sections generated by the compilers.
+//!
+//! A compilation describes the unwind info of all its functions in a
+//! single `eh_frame` section, made of CIE and FDE records followed by a
+//! zero-length terminator. The initial location of each FDE is relocated
+//! to its function. When some functions of a compilation are replaced,
+//! by lazy stubs or by code compiled separately, their FDEs are dropped
+//! from the section, or carried over from another one.
+
+use std::collections::{BTreeMap, HashMap};
+use wasmer_types::{
+    Compilation, CustomSection, Endianness, LocalFunctionIndex, Relocation, RelocationTarget,
+    SectionBody, Target,
+};
+
+const TERMINATOR_LEN: usize = 4;
+
+/// A CIE or FDE record.
+struct Record {
+    start: usize,
+    end: usize,
+    /// The offset of the CIE of an FDE, `None` for a CIE.
+    cie: Option<usize>,
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32, big_endian: bool) {
+    let value = if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    bytes[offset..offset + 4].copy_from_slice(&value);
+}
+
+/// Splits an `eh_frame` section into its records. Returns `None` if it
+/// isn't made of 32-bit records followed by a terminator.
+fn records(bytes: &[u8], big_endian: bool) -> Option<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    loop {
+        let length = read_u32(bytes, start, big_endian)? as usize;
+        if length == 0 {
+            return (start + TERMINATOR_LEN == bytes.len()).then_some(records);
+        }
+        // 64-bit records are never generated by the compilers.
+        if length == 0xffff_ffff {
+            return None;
+        }
+        let end = start
+            .checked_add(4 + length)
+            .filter(|end| *end <= bytes.len())?;
+        let cie = match read_u32(bytes, start + 4, big_endian)? as usize {
+            0 => None,
+            pointer => Some((start + 4).checked_sub(pointer)?),
+        };
+        records.push(Record { start, end, cie });
+        start = end;
+    }
+}
+
+fn is_big_endian(target: &Target) -> bool {
+    matches!(target.triple().endianness(), Ok(Endianness::Big))
+}
+
+/// The relocations of `section` that apply to `record`, moved to a copy
+/// of the record starting at `start`.
+fn move_relocations<'a>(
+    section: &'a CustomSection,
+    record: &Record,
+    start: usize,
+) -> impl Iterator<Item = Relocation> + 'a {
+    let (record_start, record_end) = (record.start, record.end);
+    section
+        .relocations
+        .iter()
+        .filter(move |r| (record_start..record_end).contains(&(r.offset as usize)))
+        .map(move |r| Relocation {
+            offset: (r.offset as usize - record_start + start) as u32,
+            ..r.clone()
+        })
+}
+
+/// The function described by an FDE, given its relocations.
+fn described_function(relocations: &[Relocation]) -> Option<LocalFunctionIndex> {
+    relocations.iter().find_map(|r| match r.reloc_target {
+        RelocationTarget::LocalFunc(index) => Some(index),
+        _ => None,
+    })
+}
+
+/// Returns a copy of the `eh_frame` section without the FDEs of the
+/// functions for which `keep` returns false, or `None` if the section
+/// can't be parsed.
+pub(crate) fn retain_functions(
+    section: &CustomSection,
+    target: &Target,
+    mut keep: impl FnMut(LocalFunctionIndex) -> bool,
+) -> Option<CustomSection> {
+    let big_endian = is_big_endian(target);
+    let bytes = section.bytes.as_slice();
+    let mut retained = Vec::with_capacity(bytes.len());
+    let mut relocations = Vec::with_capacity(section.relocations.len());
+    let mut cie_offsets = HashMap::new();
+    for record in records(bytes, big_endian)? {
+        let start = retained.len();
+        let record_relocations = move_relocations(section, &record, start).collect::<Vec<_>>();
+        if matches!(described_function(&record_relocations), Some(function) if !keep(function)) {
+            continue;
+        }
+
+        retained.extend_from_slice(&bytes[record.start..record.end]);
+        match record.cie {
+            None => {
+                cie_offsets.insert(record.start, start);
+            }
+            Some(cie) => {
+                let pointer = start + 4 - *cie_offsets.get(&cie)?;
+                write_u32(&mut retained, start + 4, pointer as u32, big_endian);
+            }
+        }
+        relocations.extend(record_relocations);
+    }
+    retained.extend_from_slice(&[0; TERMINATOR_LEN]);
+
+    Some(CustomSection {
+        protection: section.protection.clone(),
+        bytes: SectionBody::new_with_vec(retained),
+        relocations,
+    })
+}
+
+/// Splits an `eh_frame` section into one section per function, holding
+/// its FDE and the CIE it refers to. Returns `None` if the section can't
+/// be parsed.
+pub(crate) fn split_functions(
+    section: &CustomSection,
+    target: &Target,
+) -> Option<BTreeMap<LocalFunctionIndex, CustomSection>> {
+    let big_endian = is_big_endian(target);
+    let bytes = section.bytes.as_slice();
+    let records = records(bytes, big_endian)?;
+    let cies = records
+        .iter()
+        .filter(|record| record.cie.is_none())
+        .map(|record| (record.start, record))
+        .collect::<HashMap<_, _>>();
+
+    let mut functions = BTreeMap::new();
+    for record in &records {
+        let cie = match record.cie {
+            Some(cie) => *cies.get(&cie)?,
+            None => continue,
+        };
+        // The FDE follows its CIE in the split section.
+        let cie_len = cie.end - cie.start;
+        let fde_relocations = move_relocations(section, record, cie_len).collect::<Vec<_>>();
+        let function = match described_function(&fde_relocations) {
+            Some(function) => function,
+            None => continue,
+        };
+
+        let mut split = bytes[cie.start..cie.end].to_vec();
+        split.extend_from_slice(&bytes[record.start..record.end]);
+        split.extend_from_slice(&[0; TERMINATOR_LEN]);
+        write_u32(&mut split, cie_len + 4, (cie_len + 4) as u32, big_endian);
+        let relocations = move_relocations(section, cie, 0)
+            .chain(fde_relocations)
+            .collect();
+
+        functions.insert(
+            function,
+            CustomSection {
+                protection: section.protection.clone(),
+                bytes: SectionBody::new_with_vec(split),
+                relocations,
+            },
+        );
+    }
+    Some(functions)
+}
+
+/// Appends the records of the `eh_frame` section `other` to `section`.
+/// Returns `None`, leaving `section` untouched, if either can't be parsed.
+pub(crate) fn append(
+    section: &mut CustomSection,
+    other: &CustomSection,
+    target: &Target,
+) -> Option<()> {
+    let big_endian = is_big_endian(target);
+    records(section.bytes.as_slice(), big_endian)?;
+    records(other.bytes.as_slice(), big_endian)?;
+
+    // The pointers from FDEs to their CIE are relative, so the records of
+    // `other` can be moved as a whole.
+    let base = section.bytes.len() - TERMINATOR_LEN;
+    let mut bytes = section.bytes.as_slice()[..base].to_vec();
+    bytes.extend_from_slice(other.bytes.as_slice());
+    section.bytes = SectionBody::new_with_vec(bytes);
+    section
+        .relocations
+        .extend(other.relocations.iter().map(|r| Relocation {
+            offset: r.offset + base as u32,
+            ..r.clone()
+        }));
+    Some(())
+}
+
+/// Drops the unwind info of the functions of `compilation` for which
+/// `keep` returns false. All of it is dropped if it can't be parsed.
+pub(crate) fn retain_unwind_info(
+    compilation: &mut Compilation,
+    target: &Target,
+    keep: impl FnMut(LocalFunctionIndex) -> bool,
+) {
+    if let Some(debug) = &compilation.debug {
+        match retain_functions(&compilation.custom_sections[debug.eh_frame], target, keep) {
+            Some(eh_frame) => compilation.custom_sections[debug.eh_frame] = eh_frame,
+            None => compilation.debug = None,
+        }
+    }
+}
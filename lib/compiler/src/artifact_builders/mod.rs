@@ -1,7 +1,13 @@
 //! Generic Artifact abstraction for Wasmer Engines.
 
 mod artifact_builder;
+#[cfg(feature = "compiler")]
+pub(crate) mod eh_frame;
 mod trampoline;
 
 pub use self::artifact_builder::{ArtifactBuild, ArtifactBuildFromArchive, ModuleFromArchive};
+#[cfg(feature = "compiler")]
+pub(crate) use self::artifact_builder::{FunctionBatch, FunctionBodies, LazyFunctions};
+#[cfg(feature = "compiler")]
+pub(crate) use self::eh_frame::retain_unwind_info;
 pub use self::trampoline::*;
@@ -1,12 +1,13 @@
-//! Trampolines for libcalls.
+//! Trampolines for libcalls, and stubs of lazily compiled and tiered functions.
 //!
 //! This is needed because the target of libcall relocations are not reachable
 //! through normal branch instructions.
 
 use enum_iterator::IntoEnumIterator;
 use wasmer_types::{
-    Architecture, CustomSection, CustomSectionProtection, LibCall, Relocation, RelocationKind,
-    RelocationTarget, SectionBody, Target,
+    Architecture, CallingConvention, CompiledFunction, CustomSection, CustomSectionProtection,
+    FunctionBody, LibCall, LocalFunctionIndex, Relocation, RelocationKind, RelocationTarget,
+    SectionBody, SectionIndex, Target,
 };
 
 // SystemV says that both x16 and x17 are available as intra-procedural scratch
@@ -106,3 +107,309 @@ pub fn get_libcall_trampoline(
 ) -> usize {
     libcall_trampolines + libcall as usize * libcall_trampoline_len
 }
+
+// Stubs of lazily compiled functions. The fast path jumps to the address
+// stored in the function's slot if it has been compiled already. Otherwise
+// the argument registers are saved and the lazy compilation hook found in
+// the header of the slots section is called with its context and the
+// index of the function, before jumping to the body it returns.
+//
+// MOVABS r11, SLOTADDR       49 bb 00 00 00 00 00 00 00 00
+// MOV rax, [r11]             49 8b 03
+// TEST rax, rax              48 85 c0
+// JE slow                    74 02
+// JMP rax                    ff e0
+// slow:
+// PUSH rbp                   55
+// MOV rbp, rsp               48 89 e5
+// PUSH rdi, rsi, rdx, rcx    57 56 52 51
+// PUSH r8, r9                41 50 41 51
+// SUB rsp, 128               48 81 ec 80 00 00 00
+// MOVDQU [rsp+16*n], xmmN    f3 0f 7f .. (xmm0 to xmm7)
+// MOVABS r11, SLOTSADDR      49 bb 00 00 00 00 00 00 00 00
+// MOV rdi, [r11+8]           49 8b 7b 08
+// MOV esi, INDEX             be 00 00 00 00
+// CALL [r11]                 41 ff 13
+// MOVDQU xmmN, [rsp+16*n]    f3 0f 6f .. (xmm0 to xmm7)
+// ADD rsp, 128               48 81 c4 80 00 00 00
+// POP r9, r8                 41 59 41 58
+// POP rcx, rdx, rsi, rdi     59 5a 5e 5f
+// POP rbp                    5d
+// JMP rax                    ff e0
+const X86_64_SYSTEMV_LAZY_STUB: [u8; 173] = [
+    0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x49, 0x8b, 0x03, 0x48, 0x85, 0xc0,
+    0x74, 0x02, 0xff, 0xe0, 0x55, 0x48, 0x89, 0xe5, 0x57, 0x56, 0x52, 0x51, 0x41, 0x50, 0x41, 0x51,
+    0x48, 0x81, 0xec, 0x80, 0x00, 0x00, 0x00, 0xf3, 0x0f, 0x7f, 0x04, 0x24, 0xf3, 0x0f, 0x7f, 0x4c,
+    0x24, 0x10, 0xf3, 0x0f, 0x7f, 0x54, 0x24, 0x20, 0xf3, 0x0f, 0x7f, 0x5c, 0x24, 0x30, 0xf3, 0x0f,
+    0x7f, 0x64, 0x24, 0x40, 0xf3, 0x0f, 0x7f, 0x6c, 0x24, 0x50, 0xf3, 0x0f, 0x7f, 0x74, 0x24, 0x60,
+    0xf3, 0x0f, 0x7f, 0x7c, 0x24, 0x70, 0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x49, 0x8b, 0x7b, 0x08, 0xbe, 0x00, 0x00, 0x00, 0x00, 0x41, 0xff, 0x13, 0xf3, 0x0f, 0x6f, 0x04,
+    0x24, 0xf3, 0x0f, 0x6f, 0x4c, 0x24, 0x10, 0xf3, 0x0f, 0x6f, 0x54, 0x24, 0x20, 0xf3, 0x0f, 0x6f,
+    0x5c, 0x24, 0x30, 0xf3, 0x0f, 0x6f, 0x64, 0x24, 0x40, 0xf3, 0x0f, 0x6f, 0x6c, 0x24, 0x50, 0xf3,
+    0x0f, 0x6f, 0x74, 0x24, 0x60, 0xf3, 0x0f, 0x6f, 0x7c, 0x24, 0x70, 0x48, 0x81, 0xc4, 0x80, 0x00,
+    0x00, 0x00, 0x41, 0x59, 0x41, 0x58, 0x59, 0x5a, 0x5e, 0x5f, 0x5d, 0xff, 0xe0,
+];
+const X86_64_SYSTEMV_LAZY_STUB_SLOTS_OFFSET: usize = 0x58;
+const X86_64_SYSTEMV_LAZY_STUB_INDEX_OFFSET: usize = 0x65;
+
+// Same as above for the Windows x64 calling convention, which only passes
+// arguments in rcx, rdx, r8, r9 and xmm0 to xmm3, and requires 32 bytes of
+// shadow space for the callee.
+//
+// MOVABS r11, SLOTADDR       49 bb 00 00 00 00 00 00 00 00
+// MOV rax, [r11]             49 8b 03
+// TEST rax, rax              48 85 c0
+// JE slow                    74 02
+// JMP rax                    ff e0
+// slow:
+// PUSH rbp                   55
+// MOV rbp, rsp               48 89 e5
+// PUSH rcx, rdx              51 52
+// PUSH r8, r9                41 50 41 51
+// SUB rsp, 96                48 83 ec 60
+// MOVDQU [rsp+32+16*n], xmmN f3 0f 7f .. (xmm0 to xmm3)
+// MOVABS r11, SLOTSADDR      49 bb 00 00 00 00 00 00 00 00
+// MOV rcx, [r11+8]           49 8b 4b 08
+// MOV edx, INDEX             ba 00 00 00 00
+// CALL [r11]                 41 ff 13
+// MOVDQU xmmN, [rsp+32+16*n] f3 0f 6f .. (xmm0 to xmm3)
+// ADD rsp, 96                48 83 c4 60
+// POP r9, r8                 41 59 41 58
+// POP rdx, rcx               5a 59
+// POP rbp                    5d
+// JMP rax                    ff e0
+const X86_64_WINDOWS_LAZY_STUB: [u8; 117] = [
+    0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x49, 0x8b, 0x03, 0x48, 0x85, 0xc0,
+    0x74, 0x02, 0xff, 0xe0, 0x55, 0x48, 0x89, 0xe5, 0x51, 0x52, 0x41, 0x50, 0x41, 0x51, 0x48, 0x83,
+    0xec, 0x60, 0xf3, 0x0f, 0x7f, 0x44, 0x24, 0x20, 0xf3, 0x0f, 0x7f, 0x4c, 0x24, 0x30, 0xf3, 0x0f,
+    0x7f, 0x54, 0x24, 0x40, 0xf3, 0x0f, 0x7f, 0x5c, 0x24, 0x50, 0x49, 0xbb, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x49, 0x8b, 0x4b, 0x08, 0xba, 0x00, 0x00, 0x00, 0x00, 0x41, 0xff, 0x13,
+    0xf3, 0x0f, 0x6f, 0x44, 0x24, 0x20, 0xf3, 0x0f, 0x6f, 0x4c, 0x24, 0x30, 0xf3, 0x0f, 0x6f, 0x54,
+    0x24, 0x40, 0xf3, 0x0f, 0x6f, 0x5c, 0x24, 0x50, 0x48, 0x83, 0xc4, 0x60, 0x41, 0x59, 0x41, 0x58,
+    0x5a, 0x59, 0x5d, 0xff, 0xe0,
+];
+const X86_64_WINDOWS_LAZY_STUB_SLOTS_OFFSET: usize = 0x3c;
+const X86_64_WINDOWS_LAZY_STUB_INDEX_OFFSET: usize = 0x49;
+
+// The slot is read with acquire semantics, as it is written by the thread
+// that compiled the function. x8 is saved as it holds the address of
+// indirect results.
+//
+// LDR x16, SLOTADDR          50 04 00 58
+// LDAR x17, [x16]            11 fe df c8
+// CBZ x17, slow              51 00 00 b4
+// BR x17                     20 02 1f d6
+// slow:
+// STP x29, x30, [sp, #-16]!  fd 7b bf a9
+// MOV x29, sp                fd 03 00 91
+// STP xN, xN+1, [sp, #-16]!  .. .. bf a9 (x0 to x9)
+// STP qN, qN+1, [sp, #-32]!  .. .. bf ad (q0 to q7)
+// LDR x16, SLOTSADDR         b0 02 00 58
+// LDR x0, [x16, #8]          00 06 40 f9
+// MOVZ w1, INDEX & 0xffff    01 00 80 52
+// MOVK w1, INDEX >> 16, LSL 16  01 00 a0 72
+// LDR x17, [x16]             11 02 40 f9
+// BLR x17                    20 02 3f d6
+// MOV x16, x0                f0 03 00 aa
+// LDP qN, qN+1, [sp], #32    .. .. c1 ac (q7 to q0)
+// LDP xN, xN+1, [sp], #16    .. .. c1 a8 (x9 to x0)
+// LDP x29, x30, [sp], #16    fd 7b c1 a8
+// BR x16                     00 02 1f d6
+// NOP                        1f 20 03 d5
+// SLOTADDR                   00 00 00 00 00 00 00 00
+// SLOTSADDR                  00 00 00 00 00 00 00 00
+const AARCH64_LAZY_STUB: [u8; 152] = [
+    0x50, 0x04, 0x00, 0x58, 0x11, 0xfe, 0xdf, 0xc8, 0x51, 0x00, 0x00, 0xb4, 0x20, 0x02, 0x1f, 0xd6,
+    0xfd, 0x7b, 0xbf, 0xa9, 0xfd, 0x03, 0x00, 0x91, 0xe0, 0x07, 0xbf, 0xa9, 0xe2, 0x0f, 0xbf, 0xa9,
+    0xe4, 0x17, 0xbf, 0xa9, 0xe6, 0x1f, 0xbf, 0xa9, 0xe8, 0x27, 0xbf, 0xa9, 0xe0, 0x07, 0xbf, 0xad,
+    0xe2, 0x0f, 0xbf, 0xad, 0xe4, 0x17, 0xbf, 0xad, 0xe6, 0x1f, 0xbf, 0xad, 0xb0, 0x02, 0x00, 0x58,
+    0x00, 0x06, 0x40, 0xf9, 0x01, 0x00, 0x80, 0x52, 0x01, 0x00, 0xa0, 0x72, 0x11, 0x02, 0x40, 0xf9,
+    0x20, 0x02, 0x3f, 0xd6, 0xf0, 0x03, 0x00, 0xaa, 0xe6, 0x1f, 0xc1, 0xac, 0xe4, 0x17, 0xc1, 0xac,
+    0xe2, 0x0f, 0xc1, 0xac, 0xe0, 0x07, 0xc1, 0xac, 0xe8, 0x27, 0xc1, 0xa8, 0xe6, 0x1f, 0xc1, 0xa8,
+    0xe4, 0x17, 0xc1, 0xa8, 0xe2, 0x0f, 0xc1, 0xa8, 0xe0, 0x07, 0xc1, 0xa8, 0xfd, 0x7b, 0xc1, 0xa8,
+    0x00, 0x02, 0x1f, 0xd6, 0x1f, 0x20, 0x03, 0xd5, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const AARCH64_LAZY_STUB_SLOTS_OFFSET: usize = 0x90;
+const AARCH64_LAZY_STUB_INDEX_OFFSET: usize = 0x44;
+
+/// Size in bytes of the header of the lazy function slots section.
+///
+/// The header holds the address of the lazy compilation hook, followed by
+/// the context it is called with. Both are set when the artifact is loaded.
+pub const LAZY_FUNCTION_SLOTS_HEADER_LEN: usize = 16;
+
+/// Creates the data section holding the slots of lazily compiled functions.
+///
+/// Each local function has a slot, holding the address of its body once it
+/// has been compiled.
+pub fn make_lazy_function_slots(num_local_functions: usize) -> CustomSection {
+    let len = LAZY_FUNCTION_SLOTS_HEADER_LEN + num_local_functions * 8;
+    CustomSection {
+        protection: CustomSectionProtection::Read,
+        bytes: SectionBody::new_with_vec(vec![0; len]),
+        relocations: vec![],
+    }
+}
+
+/// Returns whether lazy function stubs can be generated for this target.
+pub fn lazy_function_stubs_supported(target: &Target) -> bool {
+    matches!(
+        (
+            target.triple().architecture,
+            target.triple().default_calling_convention()
+        ),
+        (Architecture::X86_64, Ok(CallingConvention::SystemV))
+            | (Architecture::X86_64, Ok(CallingConvention::WindowsFastcall))
+            | (Architecture::Aarch64(_), Ok(CallingConvention::SystemV))
+            | (
+                Architecture::Aarch64(_),
+                Ok(CallingConvention::AppleAarch64)
+            )
+    )
+}
+
+/// Creates the stub of a function that is compiled on its first call.
+///
+/// `slots` is the section created by [`make_lazy_function_slots`]. The
+/// target must be supported, see [`lazy_function_stubs_supported`].
+pub fn make_lazy_function_stub(
+    target: &Target,
+    index: LocalFunctionIndex,
+    slots: SectionIndex,
+) -> CompiledFunction {
+    let (mut code, slots_offset, slot_offset) = match (
+        target.triple().architecture,
+        target.triple().default_calling_convention(),
+    ) {
+        (Architecture::X86_64, Ok(CallingConvention::WindowsFastcall)) => {
+            let mut code = X86_64_WINDOWS_LAZY_STUB.to_vec();
+            code[X86_64_WINDOWS_LAZY_STUB_INDEX_OFFSET..][..4]
+                .copy_from_slice(&index.as_u32().to_le_bytes());
+            (code, X86_64_WINDOWS_LAZY_STUB_SLOTS_OFFSET, 2)
+        }
+        (Architecture::X86_64, _) => {
+            let mut code = X86_64_SYSTEMV_LAZY_STUB.to_vec();
+            code[X86_64_SYSTEMV_LAZY_STUB_INDEX_OFFSET..][..4]
+                .copy_from_slice(&index.as_u32().to_le_bytes());
+            (code, X86_64_SYSTEMV_LAZY_STUB_SLOTS_OFFSET, 2)
+        }
+        (Architecture::Aarch64(_), _) => {
+            let mut code = AARCH64_LAZY_STUB.to_vec();
+            let index = index.as_u32();
+            // The immediate of MOVZ and MOVK is encoded in bits 5 to 20.
+            for (offset, imm16) in [(0, index & 0xffff), (4, index >> 16)] {
+                let at = AARCH64_LAZY_STUB_INDEX_OFFSET + offset;
+                let instruction = u32::from_le_bytes(code[at..][..4].try_into().unwrap());
+                code[at..][..4].copy_from_slice(&(instruction | (imm16 << 5)).to_le_bytes());
+            }
+            (code, AARCH64_LAZY_STUB_SLOTS_OFFSET, 0x88)
+        }
+        arch => panic!("Unsupported architecture for lazy compilation: {:?}", arch),
+    };
+    code.shrink_to_fit();
+
+    CompiledFunction {
+        body: FunctionBody {
+            body: code,
+            unwind_info: None,
+        },
+        relocations: vec![
+            Relocation {
+                kind: RelocationKind::Abs8,
+                reloc_target: RelocationTarget::CustomSection(slots),
+                offset: slot_offset,
+                addend: (LAZY_FUNCTION_SLOTS_HEADER_LEN + index.as_u32() as usize * 8) as _,
+            },
+            Relocation {
+                kind: RelocationKind::Abs8,
+                reloc_target: RelocationTarget::CustomSection(slots),
+                offset: slots_offset as u32,
+                addend: 0,
+            },
+        ],
+        frame_info: Default::default(),
+    }
+}
+
+// Stubs that direct calls to tiered functions go through. They jump to the
+// address stored in the function's slot, which starts out as the baseline
+// body and is switched to the optimized one once it is ready.
+//
+// MOVABS r11, SLOTADDR       49 bb 00 00 00 00 00 00 00 00
+// JMP [r11]                  41 ff 23
+// INT3 (padding)             cc cc cc
+const X86_64_TIER_UP_STUB: [u8; 16] = [
+    0x49, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x41, 0xff, 0x23, 0xcc, 0xcc, 0xcc,
+];
+const X86_64_TIER_UP_STUB_SLOT_OFFSET: usize = 2;
+
+// The slot is read with acquire semantics, as it is written by the tier-up
+// thread. Only x17 is used, see `AARCH64_TRAMPOLINE`.
+//
+// LDR x17, SLOTADDR          91 00 00 58
+// LDAR x17, [x17]            31 fe df c8
+// BR x17                     20 02 1f d6
+// NOP                        1f 20 03 d5
+// SLOTADDR                   00 00 00 00 00 00 00 00
+const AARCH64_TIER_UP_STUB: [u8; 24] = [
+    0x91, 0x00, 0x00, 0x58, 0x31, 0xfe, 0xdf, 0xc8, 0x20, 0x02, 0x1f, 0xd6, 0x1f, 0x20, 0x03, 0xd5,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+const AARCH64_TIER_UP_STUB_SLOT_OFFSET: usize = 16;
+
+/// Returns whether tier-up stubs can be generated for this target.
+pub fn tier_up_stubs_supported(target: &Target) -> bool {
+    matches!(
+        target.triple().architecture,
+        Architecture::X86_64 | Architecture::Aarch64(_)
+    )
+}
+
+/// Returns the length in bytes of a tier-up stub for this target.
+pub fn tier_up_stub_len(target: &Target) -> usize {
+    match target.triple().architecture {
+        Architecture::X86_64 => X86_64_TIER_UP_STUB.len(),
+        Architecture::Aarch64(_) => AARCH64_TIER_UP_STUB.len(),
+        arch => panic!(
+            "Unsupported architecture for tiered compilation: {:?}",
+            arch
+        ),
+    }
+}
+
+/// Creates the section holding the tier-up stubs of every local function,
+/// laid out every [`tier_up_stub_len`] bytes.
+///
+/// `slots` is the address of the slots of the functions, one 8-byte
+/// address each. Several stubs sections may share the same slots, so that
+/// every code allocation can reach them with near calls. The target must
+/// be supported, see [`tier_up_stubs_supported`].
+pub fn make_tier_up_stubs(
+    target: &Target,
+    num_local_functions: usize,
+    slots: usize,
+) -> CustomSection {
+    let (stub, slot_offset): (&[u8], usize) = match target.triple().architecture {
+        Architecture::X86_64 => (&X86_64_TIER_UP_STUB, X86_64_TIER_UP_STUB_SLOT_OFFSET),
+        Architecture::Aarch64(_) => (&AARCH64_TIER_UP_STUB, AARCH64_TIER_UP_STUB_SLOT_OFFSET),
+        arch => panic!(
+            "Unsupported architecture for tiered compilation: {:?}",
+            arch
+        ),
+    };
+
+    let mut code = Vec::with_capacity(stub.len() * num_local_functions);
+    for index in 0..num_local_functions {
+        let slot = (slots + index * 8) as u64;
+        let start = code.len();
+        code.extend_from_slice(stub);
+        code[start + slot_offset..start + slot_offset + 8].copy_from_slice(&slot.to_le_bytes());
+    }
+
+    CustomSection {
+        protection: CustomSectionProtection::ReadExecute,
+        bytes: SectionBody::new_with_vec(code),
+        relocations: vec![],
+    }
+}
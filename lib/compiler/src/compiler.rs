@@ -7,7 +7,7 @@ use crate::translator::ModuleMiddleware;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
 use enumset::EnumSet;
-use wasmer_types::compilation::function::Compilation;
+use wasmer_types::compilation::function::{Compilation, CompiledFunction, CustomSections, Dwarf};
 use wasmer_types::compilation::module::CompileModuleInfo;
 use wasmer_types::compilation::symbols::SymbolRegistry;
 use wasmer_types::compilation::target::Target;
@@ -16,6 +16,12 @@ use wasmer_types::error::CompileError;
 use wasmer_types::{CpuFeature, Features, LocalFunctionIndex};
 use wasmparser::{Validator, WasmFeatures};
 
+/// A function body with no locals that only contains `unreachable`.
+///
+/// It is valid for any signature, and is compiled in place of the functions
+/// that are not needed when only part of a module is compiled.
+pub(crate) const STUB_FUNCTION_BODY: &[u8] = &[0x00, 0x00, 0x0b];
+
 /// The compiler configuration options.
 pub trait CompilerConfig {
     /// Enable Position Independent Code (PIC).
@@ -37,6 +43,23 @@ pub trait CompilerConfig {
         // in case they create an IR that they can verify.
     }
 
+    /// Keep a frame pointer in every function, leaf ones included.
+    ///
+    /// This lets sampling profilers walk the stacks of wasm code through
+    /// the frame pointer chain.
+    fn enable_frame_pointers(&mut self) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they may omit frame pointers.
+    }
+
+    /// Whether [`Self::enable_frame_pointers`] changes the generated code.
+    ///
+    /// Engines use it to tell apart the artifacts compiled with frame
+    /// pointers in their deterministic id, which module caches key on.
+    fn frame_pointers_enabled(&self) -> bool {
+        false
+    }
+
     /// Enable NaN canonicalization.
     ///
     /// NaN canonicalization is useful when trying to run WebAssembly
@@ -49,6 +72,15 @@ pub trait CompilerConfig {
     /// Gets the custom compiler config
     fn compiler(self: Box<Self>) -> Box<dyn Compiler>;
 
+    /// Gets a compiler that can be shared between threads, which lazy
+    /// compilation needs to compile functions as they are first called.
+    ///
+    /// Lazy compilation is only enabled for compilers that return one, the
+    /// default implementation returns `None`.
+    fn shared_compiler(&self) -> Option<Arc<dyn Compiler + Send + Sync>> {
+        None
+    }
+
     /// Gets the default features for this compiler in the given target
     fn default_features_for_target(&self, _target: &Target) -> Features {
         Features::default()
@@ -122,6 +154,34 @@ pub trait Compiler: Send {
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError>;
 
+    /// Returns whether single functions can be compiled with
+    /// [`Compiler::compile_function`]. Lazy compilation is only enabled
+    /// for compilers that can.
+    fn supports_compile_function(&self) -> bool {
+        false
+    }
+
+    /// Compiles a single function of a parsed module, without compiling
+    /// the other ones.
+    ///
+    /// Relocations of the returned function that target custom sections
+    /// refer to the returned sections, and the returned [`Dwarf`] points to
+    /// the section holding the function's unwind info, if any. The default
+    /// implementation returns an error.
+    fn compile_function(
+        &self,
+        _target: &Target,
+        _module: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+        _index: LocalFunctionIndex,
+        _function_body: &FunctionBodyData<'_>,
+    ) -> Result<(CompiledFunction, CustomSections, Option<Dwarf>), CompileError> {
+        Err(CompileError::UnsupportedFeature(format!(
+            "the {} compiler can't compile single functions",
+            self.name()
+        )))
+    }
+
     /// Compiles a module into a native object file.
     ///
     /// It returns the bytes as a `&[u8]` or a [`CompileError`].
@@ -142,6 +202,16 @@ pub trait Compiler: Send {
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>];
 
+    /// Returns a fingerprint of this compiler and its configuration.
+    ///
+    /// It is part of the keys of the function cache, so it must change
+    /// whenever the generated code could, except for the middlewares which
+    /// provide their own keys. Compilers returning `None` don't use the
+    /// function cache.
+    fn fingerprint(&self) -> Option<String> {
+        None
+    }
+
     /// Get the CpuFeatues used by the compiler
     fn get_cpu_features_used(&self, cpu_features: &EnumSet<CpuFeature>) -> EnumSet<CpuFeature> {
         *cpu_features
@@ -1,7 +1,13 @@
 //! Define `Artifact`, based on `ArtifactBuild`
 //! to allow compiling and instantiating to be done as separate steps.
 
-use crate::engine::link::link_module;
+use crate::engine::debug_info::{register_debug_info, source_lines};
+#[cfg(feature = "compiler")]
+use crate::engine::lazy::LazyState;
+use crate::engine::link::{link_module, link_module_with_call_targets, tier_up_stub_extents};
+use crate::engine::profiling::{register_functions, ProfilingStrategy};
+#[cfg(feature = "compiler")]
+use crate::engine::tiering::{TierUpSlots, TieringState};
 use crate::lib::std::vec::IntoIter;
 use crate::ArtifactBuild;
 use crate::ArtifactBuildFromArchive;
@@ -17,6 +23,7 @@ use crate::{Compiler, FunctionBodyData, ModuleTranslationState};
 use crate::{Engine, EngineInner};
 use enumset::EnumSet;
 use shared_buffer::OwnedBuffer;
+use std::collections::BTreeSet;
 #[cfg(any(feature = "static-artifact-create", feature = "static-artifact-load"))]
 use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
@@ -35,12 +42,15 @@ use wasmer_types::DataInitializerLocation;
 use wasmer_types::DataInitializerLocationLike;
 use wasmer_types::MetadataHeader;
 use wasmer_types::{
-    CompileError, CpuFeature, DataInitializer, DeserializeError, FunctionIndex, LocalFunctionIndex,
-    MemoryIndex, ModuleInfo, OwnedDataInitializer, SignatureIndex, TableIndex, Target,
+    CompileError, CpuFeature, CustomSection, DataInitializer, DeserializeError, FunctionIndex,
+    LocalFunctionIndex, MemoryIndex, ModuleInfo, OwnedDataInitializer, SectionIndex,
+    SignatureIndex, TableIndex, Target,
 };
 use wasmer_types::{SerializableModule, SerializeError};
-use wasmer_vm::{FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex, VMTrampoline};
-use wasmer_vm::{InstanceAllocator, StoreObjects, TrapHandlerFn, VMConfig, VMExtern, VMInstance};
+use wasmer_vm::{
+    FunctionBodyPtr, MemoryStyle, SectionBodyPtr, TableStyle, VMSharedSignatureIndex, VMTrampoline,
+};
+use wasmer_vm::{StoreObjects, TrapHandlerFn, VMConfig, VMExtern, VMInstance};
 
 pub struct AllocatedArtifact {
     // This shows if the frame info has been regestered already or not.
@@ -95,6 +105,13 @@ pub struct Artifact {
     // The artifact will only be allocated in memory in case we can execute it
     // (that means, if the target != host then this will be None).
     allocated: Option<AllocatedArtifact>,
+    // The tiering state, if the artifact was compiled by a tiered engine.
+    #[cfg(feature = "compiler")]
+    tiering: Option<Arc<TieringState>>,
+    // The lazy compilation state, if some functions are compiled on
+    // their first call.
+    #[cfg(feature = "compiler")]
+    lazy: Option<Arc<LazyState>>,
 }
 
 /// Artifacts may be created as the result of the compilation of a wasm
@@ -128,18 +145,34 @@ impl Artifact {
             .map(|table_type| tunables.table_style(table_type))
             .collect();
 
-        let artifact = ArtifactBuild::new(
+        // Lazy stubs are only useful when the code is run on this host.
+        let lazy = engine.lazy_compiler().is_some() && engine.target().is_native();
+        let (artifact, function_bodies, lazy_functions) = ArtifactBuild::new_with_function_bodies(
             &mut inner_engine,
             data,
             engine.target(),
             memory_styles,
             table_styles,
+            lazy,
         )?;
 
-        Self::from_parts(
+        // Tiered functions are called through stubs, so that their callers
+        // pick up the optimized code once it is ready.
+        let tier_up_slots = match engine.tier_up() {
+            Some(_) if engine.target().is_native() => {
+                TierUpSlots::new(engine.target(), artifact.get_function_bodies_ref().len())
+            }
+            _ => None,
+        };
+        let tier_up_stubs = tier_up_slots
+            .as_ref()
+            .map(|slots| slots.make_stubs(engine.target()));
+
+        let (mut artifact, custom_sections) = Self::from_parts_with_sections(
             &mut inner_engine,
             ArtifactBuildVariant::Plain(artifact),
             engine.target(),
+            tier_up_stubs.as_ref(),
         )
         .map_err(|e| match e {
             DeserializeError::Compiler(c) => c,
@@ -153,7 +186,40 @@ impl Artifact {
             // hence this match block and the other cases being
             // unreachable.
             _ => unreachable!(),
-        })
+        })?;
+
+        artifact.describe_code(
+            &mut inner_engine,
+            lazy_functions.as_ref().map(|lazy| &lazy.functions),
+        );
+
+        if let (Some(lazy_functions), Some(custom_sections)) = (lazy_functions, &custom_sections) {
+            let slots = custom_sections[lazy_functions.slots];
+            artifact.lazy = Some(LazyState::start(
+                engine,
+                data,
+                &artifact,
+                function_bodies,
+                lazy_functions,
+                slots,
+            ));
+        } else if let (Some(tier_up), Some(slots), Some(custom_sections)) =
+            (engine.tier_up(), tier_up_slots, &custom_sections)
+        {
+            // The stubs are allocated after the sections of the artifact.
+            let stubs = *custom_sections.values().last().unwrap();
+            artifact.tiering = Some(TieringState::start(
+                engine,
+                tier_up,
+                data,
+                function_bodies,
+                &artifact,
+                slots,
+                stubs,
+            ));
+        }
+
+        Ok(artifact)
     }
 
     /// This indicates if the Artifact is allocated and can be run by the current
@@ -276,12 +342,32 @@ impl Artifact {
         artifact: ArtifactBuildVariant,
         target: &Target,
     ) -> Result<Self, DeserializeError> {
+        let (artifact, _) = Self::from_parts_with_sections(engine_inner, artifact, target, None)?;
+        artifact.describe_code(engine_inner, None);
+        Ok(artifact)
+    }
+
+    /// Same as `from_parts`, but also returns where the custom sections
+    /// were allocated, if the artifact was allocated. If `tier_up_stubs`
+    /// is given, it is allocated after the sections of a plain artifact,
+    /// and direct calls between its local functions go through it.
+    fn from_parts_with_sections(
+        engine_inner: &mut EngineInner,
+        artifact: ArtifactBuildVariant,
+        target: &Target,
+        tier_up_stubs: Option<&CustomSection>,
+    ) -> Result<(Self, Option<PrimaryMap<SectionIndex, SectionBodyPtr>>), DeserializeError> {
         if !target.is_native() {
-            return Ok(Self {
+            let artifact = Self {
                 id: Default::default(),
                 artifact,
                 allocated: None,
-            });
+                #[cfg(feature = "compiler")]
+                tiering: None,
+                #[cfg(feature = "compiler")]
+                lazy: None,
+            };
+            return Ok((artifact, None));
         } else {
             // check if cpu features are compatible before anything else
             let cpu_features = artifact.cpu_features();
@@ -304,7 +390,11 @@ impl Artifact {
                 p.get_function_bodies_ref().values(),
                 p.get_function_call_trampolines_ref().values(),
                 p.get_dynamic_function_trampolines_ref().values(),
-                p.get_custom_sections_ref().values(),
+                p.get_custom_sections_ref()
+                    .values()
+                    .chain(tier_up_stubs)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
             )?,
             ArtifactBuildVariant::Archived(a) => engine_inner.allocate(
                 module_info,
@@ -315,10 +405,15 @@ impl Artifact {
             )?,
         };
 
+        let tier_up_stubs = tier_up_stubs.map(|_| {
+            let stubs = *custom_sections.values().last().unwrap();
+            tier_up_stub_extents(target, stubs, finished_functions.len())
+        });
         match &artifact {
-            ArtifactBuildVariant::Plain(p) => link_module(
+            ArtifactBuildVariant::Plain(p) => link_module_with_call_targets(
                 module_info,
                 &finished_functions,
+                tier_up_stubs.as_ref().unwrap_or(&finished_functions),
                 p.get_function_relocations()
                     .iter()
                     .map(|(k, v)| (k, v.iter())),
@@ -409,6 +504,10 @@ impl Artifact {
                 signatures,
                 finished_function_lengths,
             }),
+            #[cfg(feature = "compiler")]
+            tiering: None,
+            #[cfg(feature = "compiler")]
+            lazy: None,
         };
 
         artifact
@@ -418,7 +517,7 @@ impl Artifact {
             engine_inner.register_frame_info(frame_info);
         }
 
-        Ok(artifact)
+        Ok((artifact, Some(custom_sections)))
     }
 
     /// Check if the provided bytes look like a serialized `ArtifactBuild`.
@@ -481,6 +580,10 @@ impl<'a> ArtifactCreate<'a> for Artifact {
     }
 
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        #[cfg(feature = "compiler")]
+        if let (Some(lazy), ArtifactBuildVariant::Plain(artifact)) = (&self.lazy, &self.artifact) {
+            return lazy.serialize(artifact);
+        }
         self.artifact.serialize()
     }
 }
@@ -651,24 +754,7 @@ impl Artifact {
             return Ok(()); // already done
         }
 
-        let finished_function_extents = self
-            .allocated
-            .as_ref()
-            .expect("It must be allocated")
-            .finished_functions
-            .values()
-            .copied()
-            .zip(
-                self.allocated
-                    .as_ref()
-                    .expect("It must be allocated")
-                    .finished_function_lengths
-                    .values()
-                    .copied(),
-            )
-            .map(|(ptr, length)| FunctionExtent { ptr, length })
-            .collect::<PrimaryMap<LocalFunctionIndex, _>>()
-            .into_boxed_slice();
+        let finished_function_extents = self.finished_function_extents().into_boxed_slice();
 
         let frame_info_registration = &mut self
             .allocated
@@ -693,6 +779,71 @@ impl Artifact {
         Ok(())
     }
 
+    /// Describes the code of the artifact to native debuggers and
+    /// profilers, if enabled in the engine.
+    ///
+    /// The `stubs` of a lazy artifact are left out, and described once
+    /// compiled.
+    fn describe_code(
+        &self,
+        engine_inner: &mut EngineInner,
+        stubs: Option<&BTreeSet<LocalFunctionIndex>>,
+    ) {
+        let profiler = engine_inner.profiler();
+        if !(engine_inner.debug_info() || profiler.is_some()) || !self.allocated() {
+            return;
+        }
+        let frame_infos = match &self.artifact {
+            ArtifactBuildVariant::Plain(p) => p.get_frame_info_ref().clone(),
+            ArtifactBuildVariant::Archived(a) => match a.deserialize_frame_info_ref() {
+                Ok(frame_infos) => frame_infos,
+                Err(_) => return,
+            },
+        };
+        let module_info = self.module_info();
+        let extents = self.finished_function_extents();
+        let compiled =
+            |index: &LocalFunctionIndex| stubs.map_or(true, |stubs| !stubs.contains(index));
+        if engine_inner.debug_info() {
+            let functions = extents
+                .iter()
+                .zip(frame_infos.values())
+                .filter(|((index, _), _)| compiled(index))
+                .map(|((index, extent), frame_info)| (index, extent, frame_info));
+            if let Some(debug_image) = register_debug_info(module_info, functions) {
+                engine_inner.register_debug_image(debug_image);
+            }
+        }
+        if let Some(profiler) = profiler {
+            let source_lines = match profiler {
+                ProfilingStrategy::JitDump => {
+                    Some(source_lines(module_info, &extents, &frame_infos))
+                }
+                ProfilingStrategy::PerfMap => None,
+            };
+            register_functions(
+                profiler,
+                module_info,
+                extents.iter().filter(|(index, _)| compiled(index)),
+                source_lines.as_ref(),
+            );
+        }
+    }
+
+    /// Returns the location and length of the functions allocated in memory.
+    pub(crate) fn finished_function_extents(
+        &self,
+    ) -> PrimaryMap<LocalFunctionIndex, FunctionExtent> {
+        let allocated = self.allocated.as_ref().expect("It must be allocated");
+        allocated
+            .finished_functions
+            .values()
+            .copied()
+            .zip(allocated.finished_function_lengths.values().copied())
+            .map(|(ptr, length)| FunctionExtent { ptr, length })
+            .collect()
+    }
+
     /// The GlobalFrameInfoRegistration needs to be transfered to EngineInner if
     /// register_frame_info has been used.
     #[deprecated(since = "4.0.0", note = "done automaticaly by Artifact::from_parts.")]
@@ -795,8 +946,9 @@ impl Artifact {
         // Get pointers to where metadata about local memories should live in VM memory.
         // Get pointers to where metadata about local tables should live in VM memory.
 
-        let (allocator, memory_definition_locations, table_definition_locations) =
-            InstanceAllocator::new(&module);
+        let (allocator, memory_definition_locations, table_definition_locations) = tunables
+            .allocate_instance(&module)
+            .map_err(InstantiationError::Link)?;
         let finished_memories = tunables
             .create_memories(
                 context,
@@ -820,7 +972,8 @@ impl Artifact {
             .map_err(InstantiationError::Link)?
             .into_boxed_slice();
 
-        let handle = VMInstance::new(
+        #[allow(unused_mut)]
+        let mut handle = VMInstance::new(
             allocator,
             module,
             context,
@@ -833,6 +986,15 @@ impl Artifact {
             self.signatures().clone(),
         )
         .map_err(InstantiationError::Start)?;
+        #[cfg(feature = "compiler")]
+        if let Some(tiering) = &self.tiering {
+            tiering.register(&mut handle);
+        }
+        #[cfg(feature = "compiler")]
+        if let Some(lazy) = &self.lazy {
+            // The stubs of the instance's functions refer to the state.
+            handle.push_guard(Box::new(lazy.clone()));
+        }
         Ok(handle)
     }
 
@@ -1190,6 +1352,10 @@ impl Artifact {
                 signatures: signatures.into_boxed_slice(),
                 finished_function_lengths,
             }),
+            #[cfg(feature = "compiler")]
+            tiering: None,
+            #[cfg(feature = "compiler")]
+            lazy: None,
         })
     }
 }
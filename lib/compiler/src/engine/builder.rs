@@ -1,5 +1,16 @@
 use super::Engine;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+use crate::engine::tiering::{HotnessCounters, TierUp};
 use crate::CompilerConfig;
+#[cfg(feature = "compiler")]
+use crate::FunctionCache;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ProfilingStrategy;
+#[cfg(feature = "compiler")]
+use std::sync::Arc;
+#[cfg(feature = "compiler")]
+use wasmer_types::CompileError;
 use wasmer_types::{Features, Target};
 
 /// The Builder contents of `Engine`
@@ -10,6 +21,18 @@ pub struct EngineBuilder {
     target: Option<Target>,
     /// The features to compile the Wasm module with
     features: Option<Features>,
+    /// The optimizing compiler and hotness threshold for tiered compilation
+    tier_up: Option<(Box<dyn CompilerConfig>, u64)>,
+    /// Whether functions are compiled on their first call
+    lazy_compilation: bool,
+    /// Whether the generated code is described to native debuggers
+    debug_info: bool,
+    /// How the generated code is described to profilers
+    #[cfg(not(target_arch = "wasm32"))]
+    profiler: Option<ProfilingStrategy>,
+    /// The store of compiled functions reused across modules
+    #[cfg(feature = "compiler")]
+    function_cache: Option<Arc<dyn FunctionCache>>,
 }
 
 impl EngineBuilder {
@@ -22,6 +45,13 @@ impl EngineBuilder {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            tier_up: None,
+            lazy_compilation: false,
+            debug_info: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            profiler: None,
+            #[cfg(feature = "compiler")]
+            function_cache: None,
         }
     }
 
@@ -31,6 +61,13 @@ impl EngineBuilder {
             compiler_config: None,
             target: None,
             features: None,
+            tier_up: None,
+            lazy_compilation: false,
+            debug_info: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            profiler: None,
+            #[cfg(feature = "compiler")]
+            function_cache: None,
         }
     }
 
@@ -46,24 +83,182 @@ impl EngineBuilder {
         self
     }
 
+    /// Enable tiered compilation.
+    ///
+    /// Modules are compiled with the main compiler first, which should be
+    /// a fast one like Singlepass. Functions that are entered or loop more
+    /// than `threshold` times are then recompiled in the background with
+    /// the given optimizing compiler, and swapped in once ready.
+    ///
+    /// Every call to a function goes through a stub, so both direct calls
+    /// and calls from the host or through tables reach the optimized code.
+    /// Only the hot functions are compiled again, so the optimizing
+    /// compiler must be able to compile single functions, as Cranelift
+    /// and Singlepass can. Otherwise, or on hosts other than x86_64 and
+    /// aarch64, the modules stay on the main compiler's code.
+    ///
+    /// Middlewares must be pushed identically on both compiler configs.
+    /// The optimized functions are compiled against the module as
+    /// transformed by the main compiler's middlewares, so the optimizing
+    /// compiler's ones are never bound to a module and serve all the
+    /// modules of the engine.
+    ///
+    /// Tiering can't be combined with lazy compilation: [`Self::try_engine`]
+    /// returns an error for that configuration, and [`Self::engine`]
+    /// panics.
+    pub fn set_tier_up<T>(mut self, compiler_config: T, threshold: u64) -> Self
+    where
+        T: Into<Box<dyn CompilerConfig>>,
+    {
+        self.tier_up = Some((compiler_config.into(), threshold));
+        self
+    }
+
+    /// Enable lazy compilation.
+    ///
+    /// Only the exported functions and the start function of a module are
+    /// compiled upfront. The other functions are compiled the first time
+    /// they are called, and serializing the module compiles the ones that
+    /// are left. This is ignored on targets without lazy stubs.
+    ///
+    /// Lazy compilation can't be combined with tiered compilation:
+    /// [`Self::try_engine`] returns an error for that configuration, and
+    /// [`Self::engine`] panics.
+    pub fn set_lazy_compilation(mut self, enable: bool) -> Self {
+        self.lazy_compilation = enable;
+        self
+    }
+
+    /// Describe the generated code to native debuggers.
+    ///
+    /// The code of every module loaded by the engine is registered with
+    /// GDB and LLDB through the GDB JIT interface, with a symbol for each
+    /// function. If the module carries DWARF debug info, its line tables
+    /// are translated, so breakpoints can be set on the source lines of
+    /// the guest. Functions compiled lazily or by the optimizing tier
+    /// are registered once compiled.
+    pub fn set_debug_info(mut self, enable: bool) -> Self {
+        self.debug_info = enable;
+        self
+    }
+
+    /// Describe the generated code to `perf`.
+    ///
+    /// Every function loaded by the engine is written to a perf map or a
+    /// jitdump file, named after the name section of its module. This is
+    /// only supported on Linux.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_profiler(mut self, profiler: Option<ProfilingStrategy>) -> Self {
+        self.profiler = profiler;
+        self
+    }
+
+    /// Set the store used to cache compiled functions.
+    ///
+    /// Each function is cached under a hash of its body and of everything
+    /// its code depends on, so that recompiling a slightly different
+    /// module only compiles the functions that changed.
+    ///
+    /// The cache is skipped for the modules compiled with a middleware
+    /// whose `ModuleMiddleware::cache_key` is `None`. This is the case of
+    /// the middlewares instrumenting each function differently, like the
+    /// hotness counters of tiered compilation, and of the middlewares
+    /// taking cost functions, like `Metering`, until their cost functions
+    /// are given a key.
+    #[cfg(feature = "compiler")]
+    pub fn set_function_cache(mut self, function_cache: Arc<dyn FunctionCache>) -> Self {
+        self.function_cache = Some(function_cache);
+        self
+    }
+
     /// Build the `Engine` for this configuration
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration is invalid, which is only the case when
+    /// tiered compilation is combined with lazy compilation. Use
+    /// [`Self::try_engine`] to handle that as an error.
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> Engine {
+        match self.try_engine() {
+            Ok(engine) => engine,
+            Err(e) => panic!("invalid engine configuration: {}", e),
+        }
+    }
+
+    /// Build the `Engine` for this configuration, or return an error if
+    /// some of its settings can't be combined.
+    #[cfg(feature = "compiler")]
+    pub fn try_engine(self) -> Result<Engine, CompileError> {
+        if self.tier_up.is_some() && self.lazy_compilation {
+            return Err(CompileError::UnsupportedFeature(
+                "tiered compilation can't be combined with lazy compilation".to_string(),
+            ));
+        }
         let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
+        if let Some(mut compiler_config) = self.compiler_config {
             let features = self
                 .features
                 .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
-            Engine::new(compiler_config, target, features)
+            // Optimizing compilers that can't compile single functions leave
+            // the modules on the baseline tier
+            #[cfg(not(target_arch = "wasm32"))]
+            let tier_up = self
+                .tier_up
+                .map(|(optimizing_config, threshold)| TierUp::new(optimizing_config, threshold))
+                .filter(TierUp::supported);
+            #[cfg(not(target_arch = "wasm32"))]
+            if tier_up.is_some() {
+                compiler_config.push_middleware(Arc::new(HotnessCounters::new(true)));
+            }
+            // Compilers that can't be shared between threads compile whole
+            // modules upfront
+            #[cfg(not(target_arch = "wasm32"))]
+            let lazy_compiler = self
+                .lazy_compilation
+                .then(|| compiler_config.shared_compiler())
+                .flatten();
+            #[allow(unused_mut)]
+            let mut engine = Engine::new(compiler_config, target, features);
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(tier_up) = tier_up {
+                engine.set_tier_up(tier_up);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(lazy_compiler) = lazy_compiler {
+                engine.set_lazy_compiler(lazy_compiler);
+            }
+            engine.inner_mut().set_function_cache(self.function_cache);
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::configure_code_tools(&engine, self.debug_info, self.profiler);
+            Ok(engine)
         } else {
-            Engine::headless()
+            let engine = Engine::headless();
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::configure_code_tools(&engine, self.debug_info, self.profiler);
+            Ok(engine)
         }
     }
 
     /// Build the `Engine` for this configuration
     #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> Engine {
-        Engine::headless()
+        let engine = Engine::headless();
+        #[cfg(not(target_arch = "wasm32"))]
+        Self::configure_code_tools(&engine, self.debug_info, self.profiler);
+        engine
+    }
+
+    /// Set up how the generated code is described to debuggers and profilers.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn configure_code_tools(
+        engine: &Engine,
+        debug_info: bool,
+        profiler: Option<ProfilingStrategy>,
+    ) {
+        let mut inner = engine.inner_mut();
+        inner.set_debug_info(debug_info);
+        inner.set_profiler(profiler);
     }
 
     /// The Wasm features
@@ -2,6 +2,7 @@
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
 //! Memory management for executable code.
+use super::gdb_jit::GdbJitImageRegistration;
 use super::unwind::UnwindRegistry;
 use crate::GlobalFrameInfoRegistration;
 use wasmer_types::{
@@ -25,6 +26,7 @@ const DATA_SECTION_ALIGNMENT: usize = 64;
 pub struct CodeMemory {
     // frame info is placed first, to ensure it's dropped before the mmap
     frame_info_registration: Option<GlobalFrameInfoRegistration>,
+    debug_image_registration: Option<GdbJitImageRegistration>,
     unwind_registry: UnwindRegistry,
     mmap: Mmap,
     start_of_nonexecutable_pages: usize,
@@ -38,6 +40,7 @@ impl CodeMemory {
             mmap: Mmap::new(),
             start_of_nonexecutable_pages: 0,
             frame_info_registration: None,
+            debug_image_registration: None,
         }
     }
 
@@ -231,6 +234,11 @@ impl CodeMemory {
     pub fn register_frame_info(&mut self, frame_info: GlobalFrameInfoRegistration) {
         self.frame_info_registration = Some(frame_info);
     }
+
+    /// Register the debug image, so debuggers forget it when the memory gets freed
+    pub(crate) fn register_debug_image(&mut self, debug_image: GdbJitImageRegistration) {
+        self.debug_image_registration = Some(debug_image);
+    }
 }
 
 fn round_up(size: usize, multiple: usize) -> usize {
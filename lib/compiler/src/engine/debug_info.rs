@@ -0,0 +1,794 @@
+//! Native debug info for the generated code.
+//!
+//! Each artifact is described to native debuggers by an in-memory ELF
+//! image, registered through the GDB JIT interface. The image has a
+//! symbol for every function of the module. If the module carries DWARF
+//! sections, as produced by `clang -g` or `cargo build` in debug mode,
+//! their line tables are translated to refer to the generated code, so
+//! that breakpoints can be set on source lines. Variables and types are
+//! not translated.
+
+use super::gdb_jit::GdbJitImageRegistration;
+use crate::FunctionExtent;
+use gimli::read::{AttributeValue as ReadAttributeValue, ColumnType, EndianSlice};
+use gimli::write::{
+    Address, AttributeValue, EndianVec, FileId, LineProgram, LineString, Range, RangeList,
+    Sections, Unit,
+};
+use gimli::{constants, Encoding, Format, LineEncoding, LittleEndian, SectionId};
+use std::collections::HashMap;
+use std::ops;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{CompiledFunctionFrameInfo, LocalFunctionIndex, ModuleInfo};
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// A function of the module, as allocated in memory.
+struct Function<'a> {
+    index: LocalFunctionIndex,
+    name: String,
+    address: u64,
+    length: u64,
+    frame_info: &'a CompiledFunctionFrameInfo,
+}
+
+/// Registers the debug image of some `functions` of `module`, with their
+/// allocation and frame info. Returns `None` on hosts without ELF
+/// debuggers support.
+pub(crate) fn register_debug_info<'a>(
+    module: &ModuleInfo,
+    functions: impl IntoIterator<
+        Item = (
+            LocalFunctionIndex,
+            &'a FunctionExtent,
+            &'a CompiledFunctionFrameInfo,
+        ),
+    >,
+) -> Option<GdbJitImageRegistration> {
+    let (machine, flags) = elf_machine()?;
+    let functions = functions
+        .into_iter()
+        .map(|(index, extent, frame_info)| Function {
+            index,
+            name: function_name(module, index),
+            address: extent.ptr.0 as u64,
+            length: extent.length as u64,
+            frame_info,
+        })
+        .collect::<Vec<_>>();
+    if functions.is_empty() {
+        return None;
+    }
+    let sections = module
+        .code_section_offset
+        .and_then(|offset| translate(module, &functions, offset))
+        .unwrap_or_default();
+    let image = write_elf(machine, flags, &functions, sections);
+    GdbJitImageRegistration::register(image)
+}
+
+/// A source line of the module, at an address of the generated code.
+pub(crate) struct SourceLine {
+    pub(crate) address: u64,
+    pub(crate) file: String,
+    pub(crate) line: u64,
+}
+
+/// Returns the source lines of the functions of `module` allocated at
+/// `extents`, as described to debuggers.
+pub(crate) fn source_lines(
+    module: &ModuleInfo,
+    extents: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    frame_infos: &PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> PrimaryMap<LocalFunctionIndex, Vec<SourceLine>> {
+    let mut lines = extents
+        .keys()
+        .map(|_| vec![])
+        .collect::<PrimaryMap<LocalFunctionIndex, Vec<SourceLine>>>();
+    let functions = allocated_functions(module, extents, frame_infos);
+    let sections = module
+        .code_section_offset
+        .and_then(|offset| translate(module, &functions, offset));
+    if let Some(sections) = sections {
+        let _ = read_source_lines(&sections, &functions, &mut lines);
+    }
+    lines
+}
+
+fn read_source_lines(
+    sections: &[(SectionId, Vec<u8>)],
+    functions: &[Function],
+    lines: &mut PrimaryMap<LocalFunctionIndex, Vec<SourceLine>>,
+) -> gimli::Result<()> {
+    let dwarf = gimli::read::Dwarf::load(|id| -> gimli::Result<Reader> {
+        let data = match sections.iter().find(|(section, _)| *section == id) {
+            Some((_, data)) => &data[..],
+            None => &[][..],
+        };
+        Ok(EndianSlice::new(data, LittleEndian))
+    })?;
+    let mut headers = dwarf.units();
+    while let Some(header) = headers.next()? {
+        let unit = dwarf.unit(header)?;
+        let program = match unit.line_program.clone() {
+            Some(program) => program,
+            None => continue,
+        };
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row()? {
+            if row.end_sequence() {
+                continue;
+            }
+            let address = row.address();
+            // Functions are allocated in order.
+            let index = match functions
+                .partition_point(|function| function.address <= address)
+                .checked_sub(1)
+            {
+                Some(index) if address < functions[index].address + functions[index].length => {
+                    index
+                }
+                _ => continue,
+            };
+            let mut file = String::new();
+            if let Some(entry) = row.file(header) {
+                if let Some(directory) = entry.directory(header) {
+                    let directory = dwarf.attr_string(&unit, directory)?;
+                    file.push_str(&directory.to_string_lossy());
+                    file.push('/');
+                }
+                let path = dwarf.attr_string(&unit, entry.path_name())?;
+                let path = path.to_string_lossy();
+                if path.starts_with('/') {
+                    file.clear();
+                }
+                file.push_str(&path);
+            }
+            lines[functions[index].index].push(SourceLine {
+                address,
+                file,
+                line: row.line().map_or(0, |line| line.get()),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The name of a function, from the name section of the module.
+pub(crate) fn function_name(module: &ModuleInfo, index: LocalFunctionIndex) -> String {
+    let func_index = module.func_index(index);
+    match module.function_names.get(&func_index) {
+        Some(name) => name.clone(),
+        None => format!("wasm-function[{}]", func_index.index()),
+    }
+}
+
+fn allocated_functions<'a>(
+    module: &ModuleInfo,
+    extents: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    frame_infos: &'a PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Vec<Function<'a>> {
+    extents
+        .iter()
+        .zip(frame_infos.values())
+        .map(|((index, extent), frame_info)| Function {
+            index,
+            name: function_name(module, index),
+            address: extent.ptr.0 as u64,
+            length: extent.length as u64,
+            frame_info,
+        })
+        .collect()
+}
+
+/// The ELF machine and flags of the host.
+fn elf_machine() -> Option<(u16, u32)> {
+    if cfg!(target_endian = "big") {
+        None
+    } else if cfg!(target_arch = "x86_64") {
+        Some((62, 0))
+    } else if cfg!(target_arch = "aarch64") {
+        Some((183, 0))
+    } else if cfg!(target_arch = "riscv64") {
+        // RVC, double-float ABI.
+        Some((243, 0x5))
+    } else {
+        None
+    }
+}
+
+/// Translates the DWARF sections of `module`, returning the sections
+/// that describe the generated code.
+fn translate(
+    module: &ModuleInfo,
+    functions: &[Function],
+    code_section_offset: u64,
+) -> Option<Vec<(SectionId, Vec<u8>)>> {
+    if !module.custom_sections.contains_key(".debug_info") {
+        return None;
+    }
+    let ranges = code_section_ranges(functions, code_section_offset);
+    let dwarf = gimli::read::Dwarf::load(|id| -> gimli::Result<Reader> {
+        let data = match module.custom_sections.get(id.name()) {
+            Some(index) => &module.custom_sections_data[*index][..],
+            None => &[][..],
+        };
+        Ok(EndianSlice::new(data, LittleEndian))
+    })
+    .ok()?;
+
+    let mut translated = gimli::write::Dwarf::new();
+    let mut assigned = vec![false; functions.len()];
+    let mut headers = dwarf.units();
+    while let Some(header) = headers.next().ok()? {
+        let unit = dwarf.unit(header).ok()?;
+        let translated_unit = translate_unit(
+            &dwarf,
+            &unit,
+            functions,
+            code_section_offset,
+            &ranges,
+            &mut assigned,
+        );
+        if let Some(translated_unit) = translated_unit {
+            translated.units.add(translated_unit);
+        }
+    }
+    if translated.units.count() == 0 {
+        return None;
+    }
+
+    let mut sections = Sections::new(EndianVec::new(LittleEndian));
+    translated.write(&mut sections).ok()?;
+    let mut result = vec![];
+    sections
+        .for_each(|id, data| {
+            if !data.slice().is_empty() {
+                result.push((id, data.slice().to_vec()));
+            }
+            Ok::<_, ()>(())
+        })
+        .ok()?;
+    Some(result)
+}
+
+/// Returns the range of each function in the code section, which starts
+/// at `code_section_offset` in the module.
+fn code_section_ranges(
+    functions: &[Function],
+    code_section_offset: u64,
+) -> Vec<Option<ops::Range<u64>>> {
+    functions
+        .iter()
+        .map(|function| {
+            let address_map = &function.frame_info.address_map;
+            if address_map.start_srcloc.is_default() || address_map.end_srcloc.is_default() {
+                return None;
+            }
+            let start =
+                (address_map.start_srcloc.bits() as u64).checked_sub(code_section_offset)?;
+            let end = (address_map.end_srcloc.bits() as u64).checked_sub(code_section_offset)?;
+            Some(start..end)
+        })
+        .collect()
+}
+
+/// A row of a line table of the module.
+struct Row {
+    address: u64,
+    file: u64,
+    line: u64,
+    column: u64,
+}
+
+/// A sequence of rows of a line table of the module, covering the
+/// addresses from `start` to `end`.
+struct Sequence {
+    start: u64,
+    end: u64,
+    rows: Vec<Row>,
+}
+
+/// Finds the row describing the code at `address`.
+fn find_row(sequences: &[Sequence], address: u64) -> Option<&Row> {
+    let sequence = sequences[..sequences.partition_point(|sequence| sequence.start <= address)]
+        .iter()
+        .rev()
+        .find(|sequence| address < sequence.end)?;
+    let index = sequence
+        .rows
+        .partition_point(|row| row.address <= address)
+        .checked_sub(1)?;
+    Some(&sequence.rows[index])
+}
+
+/// What the module tells about a function.
+#[derive(Default)]
+struct Subprogram {
+    name: Option<Vec<u8>>,
+    linkage_name: Option<Vec<u8>>,
+    decl_file: Option<u64>,
+    decl_line: Option<u64>,
+}
+
+/// Translates a compilation unit of the module, describing the
+/// functions that it covers and that aren't `assigned` to another one.
+fn translate_unit(
+    dwarf: &gimli::read::Dwarf<Reader>,
+    unit: &gimli::read::Unit<Reader>,
+    functions: &[Function],
+    code_section_offset: u64,
+    ranges: &[Option<ops::Range<u64>>],
+    assigned: &mut [bool],
+) -> Option<Unit> {
+    let program = unit.line_program.clone()?;
+    let header = program.header().clone();
+    let mut sequences = vec![];
+    let mut rows = vec![];
+    let mut program_rows = program.rows();
+    while let Some((_, row)) = program_rows.next_row().ok()? {
+        if row.end_sequence() {
+            // Sequences of functions removed by the linker start at 0,
+            // which is never the address of a function.
+            let start = rows.first().map_or(0, |row: &Row| row.address);
+            if start != 0 {
+                sequences.push(Sequence {
+                    start,
+                    end: row.address(),
+                    rows: std::mem::take(&mut rows),
+                });
+            }
+            rows.clear();
+        } else {
+            rows.push(Row {
+                address: row.address(),
+                file: row.file_index(),
+                line: row.line().map_or(0, |line| line.get()),
+                column: match row.column() {
+                    ColumnType::LeftEdge => 0,
+                    ColumnType::Column(column) => column.get(),
+                },
+            });
+        }
+    }
+    sequences.sort_by_key(|sequence| sequence.start);
+
+    let unit_functions = (0..functions.len())
+        .filter(|&index| {
+            !assigned[index]
+                && ranges[index].as_ref().map_or(false, |range| {
+                    sequences
+                        .iter()
+                        .any(|sequence| sequence.start < range.end && range.start < sequence.end)
+                })
+        })
+        .collect::<Vec<_>>();
+    if unit_functions.is_empty() {
+        return None;
+    }
+    for index in unit_functions.iter() {
+        assigned[*index] = true;
+    }
+
+    let to_vec = |value: Option<Reader>| {
+        value
+            .map(|value| value.to_vec())
+            .filter(|value| !value.is_empty())
+    };
+    let name = to_vec(unit.name).unwrap_or_else(|| b"<unknown>".to_vec());
+    let comp_dir = to_vec(unit.comp_dir).unwrap_or_else(|| b".".to_vec());
+    let encoding = Encoding {
+        format: Format::Dwarf32,
+        version: 4,
+        address_size: 8,
+    };
+    let line_program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(comp_dir.clone()),
+        LineString::String(name.clone()),
+        None,
+    );
+    let mut translated = Unit::new(encoding, line_program);
+    let mut files = HashMap::new();
+    let mut file_id = |line_program: &mut LineProgram, index: u64| -> Option<FileId> {
+        if let Some(id) = files.get(&index) {
+            return Some(*id);
+        }
+        let entry = header.file(index)?;
+        let path = dwarf.attr_string(unit, entry.path_name()).ok()?.to_vec();
+        if path.is_empty() {
+            return None;
+        }
+        let directory = match entry.directory(&header) {
+            Some(directory) => match dwarf.attr_string(unit, directory) {
+                Ok(directory) if !directory.is_empty() => {
+                    line_program.add_directory(LineString::String(directory.to_vec()))
+                }
+                _ => line_program.default_directory(),
+            },
+            None => line_program.default_directory(),
+        };
+        let id = line_program.add_file(LineString::String(path), directory, None);
+        files.insert(index, id);
+        Some(id)
+    };
+
+    // The line table maps each instruction of the generated code to the
+    // row of the wasm instruction it comes from, and the prologue to the
+    // start of the function.
+    for index in unit_functions.iter() {
+        let function = &functions[*index];
+        let mut instructions = function
+            .frame_info
+            .address_map
+            .instructions
+            .iter()
+            .filter(|instruction| !instruction.srcloc.is_default())
+            .map(|instruction| {
+                let address = (instruction.srcloc.bits() as u64).wrapping_sub(code_section_offset);
+                (instruction.code_offset as u64, address)
+            })
+            .collect::<Vec<_>>();
+        instructions.sort_by_key(|(code_offset, _)| *code_offset);
+        if let Some(range) = &ranges[*index] {
+            instructions.insert(0, (0, range.start));
+        }
+
+        let line_program = &mut translated.line_program;
+        line_program.begin_sequence(Some(Address::Constant(function.address)));
+        let mut last = None;
+        for (code_offset, address) in instructions {
+            let row = match find_row(&sequences, address) {
+                Some(row) => row,
+                None => continue,
+            };
+            let file = match file_id(line_program, row.file) {
+                Some(file) => file,
+                None => continue,
+            };
+            if last == Some((file, row.line, row.column)) {
+                continue;
+            }
+            last = Some((file, row.line, row.column));
+            let translated_row = line_program.row();
+            translated_row.address_offset = code_offset;
+            translated_row.file = file;
+            translated_row.line = row.line;
+            translated_row.column = row.column;
+            translated_row.is_statement = true;
+            line_program.generate_row();
+        }
+        line_program.end_sequence(function.length);
+    }
+
+    // Find the subprograms of the functions, and the attributes of the
+    // unit itself.
+    let mut subprograms = HashMap::new();
+    let mut producer = None;
+    let mut language = None;
+    let mut entries = unit.entries();
+    while let Some((_, entry)) = entries.next_dfs().ok()? {
+        if entry.tag() == constants::DW_TAG_compile_unit {
+            if let Ok(Some(value)) = entry.attr_value(constants::DW_AT_producer) {
+                producer = dwarf
+                    .attr_string(unit, value)
+                    .ok()
+                    .map(|value| value.to_vec());
+            }
+            if let Ok(Some(ReadAttributeValue::Language(value))) =
+                entry.attr_value(constants::DW_AT_language)
+            {
+                language = Some(value);
+            }
+            continue;
+        }
+        if entry.tag() != constants::DW_TAG_subprogram {
+            continue;
+        }
+        let low_pc = match entry.attr_value(constants::DW_AT_low_pc) {
+            Ok(Some(value)) => match dwarf.attr_address(unit, value) {
+                Ok(Some(low_pc)) => low_pc,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        // The address of a function may be the one of its body, or of
+        // the size that precedes it.
+        let index = unit_functions.iter().copied().find(|index| {
+            ranges[*index].as_ref().map_or(false, |range| {
+                range
+                    .start
+                    .saturating_sub(leb128_len(range.end - range.start))
+                    <= low_pc
+                    && low_pc < range.end
+            })
+        });
+        let index = match index {
+            Some(index) => index,
+            None => continue,
+        };
+        let string = |name| match entry.attr_value(name) {
+            Ok(Some(value)) => dwarf
+                .attr_string(unit, value)
+                .ok()
+                .map(|value| value.to_vec()),
+            _ => None,
+        };
+        let udata = |name| {
+            entry
+                .attr(name)
+                .ok()
+                .flatten()
+                .and_then(|attr| attr.udata_value())
+        };
+        subprograms.entry(index).or_insert(Subprogram {
+            name: string(constants::DW_AT_name),
+            linkage_name: string(constants::DW_AT_linkage_name),
+            decl_file: udata(constants::DW_AT_decl_file),
+            decl_line: udata(constants::DW_AT_decl_line),
+        });
+    }
+
+    let range_list = RangeList(
+        unit_functions
+            .iter()
+            .map(|index| Range::StartLength {
+                begin: Address::Constant(functions[*index].address),
+                length: functions[*index].length,
+            })
+            .collect(),
+    );
+    let range_list = translated.ranges.add(range_list);
+    let root = translated.root();
+    let root_entry = translated.get_mut(root);
+    root_entry.set(constants::DW_AT_name, AttributeValue::String(name));
+    root_entry.set(constants::DW_AT_comp_dir, AttributeValue::String(comp_dir));
+    if let Some(producer) = producer {
+        root_entry.set(constants::DW_AT_producer, AttributeValue::String(producer));
+    }
+    if let Some(language) = language {
+        root_entry.set(
+            constants::DW_AT_language,
+            AttributeValue::Language(language),
+        );
+    }
+    root_entry.set(constants::DW_AT_stmt_list, AttributeValue::LineProgramRef);
+    root_entry.set(
+        constants::DW_AT_low_pc,
+        AttributeValue::Address(Address::Constant(0)),
+    );
+    root_entry.set(
+        constants::DW_AT_ranges,
+        AttributeValue::RangeListRef(range_list),
+    );
+
+    for index in unit_functions.iter() {
+        let function = &functions[*index];
+        let subprogram = subprograms.remove(index).unwrap_or_default();
+        let decl_file = subprogram
+            .decl_file
+            .and_then(|file| file_id(&mut translated.line_program, file));
+        let id = translated.add(root, constants::DW_TAG_subprogram);
+        let entry = translated.get_mut(id);
+        let name = subprogram
+            .name
+            .unwrap_or_else(|| function.name.as_bytes().to_vec());
+        entry.set(constants::DW_AT_name, AttributeValue::String(name));
+        if let Some(linkage_name) = subprogram.linkage_name {
+            entry.set(
+                constants::DW_AT_linkage_name,
+                AttributeValue::String(linkage_name),
+            );
+        }
+        entry.set(
+            constants::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(function.address)),
+        );
+        entry.set(
+            constants::DW_AT_high_pc,
+            AttributeValue::Udata(function.length),
+        );
+        if let (Some(file), Some(line)) = (decl_file, subprogram.decl_line) {
+            entry.set(
+                constants::DW_AT_decl_file,
+                AttributeValue::FileIndex(Some(file)),
+            );
+            entry.set(constants::DW_AT_decl_line, AttributeValue::Udata(line));
+        }
+    }
+    Some(translated)
+}
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const ELF_HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+}
+
+/// A string table of an ELF file.
+struct StringTable(Vec<u8>);
+
+impl StringTable {
+    fn new() -> Self {
+        Self(vec![0])
+    }
+
+    fn add(&mut self, string: &str) -> u32 {
+        let offset = self.0.len() as u32;
+        self.0.extend_from_slice(string.as_bytes());
+        self.0.push(0);
+        offset
+    }
+}
+
+/// Writes a 64-bit little-endian ELF executable describing `functions`.
+///
+/// The `.text` section holds no data, but is placed where the functions
+/// are in memory, so that the symbols and debug sections can refer to
+/// their actual addresses.
+fn write_elf(
+    machine: u16,
+    flags: u32,
+    functions: &[Function],
+    debug_sections: Vec<(SectionId, Vec<u8>)>,
+) -> Vec<u8> {
+    let text_start = functions.iter().map(|f| f.address).min().unwrap_or(0);
+    let text_end = functions
+        .iter()
+        .map(|f| f.address + f.length)
+        .max()
+        .unwrap_or(0);
+
+    let mut data = vec![0; ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE];
+    let mut section_names = StringTable::new();
+    let mut headers = vec![SectionHeader::default()];
+    headers.push(SectionHeader {
+        name: section_names.add(".text"),
+        kind: SHT_NOBITS,
+        flags: SHF_ALLOC | SHF_EXECINSTR,
+        addr: text_start,
+        offset: data.len() as u64,
+        size: text_end - text_start,
+        align: 16,
+        ..Default::default()
+    });
+    for (id, bytes) in debug_sections {
+        headers.push(SectionHeader {
+            name: section_names.add(id.name()),
+            kind: SHT_PROGBITS,
+            offset: data.len() as u64,
+            size: bytes.len() as u64,
+            align: 1,
+            ..Default::default()
+        });
+        data.extend_from_slice(&bytes);
+    }
+
+    let mut symbol_names = StringTable::new();
+    align(&mut data, 8);
+    let symtab_offset = data.len();
+    data.extend_from_slice(&[0; SYMBOL_SIZE]);
+    for function in functions {
+        data.extend_from_slice(&symbol_names.add(&function.name).to_le_bytes());
+        // STB_GLOBAL, STT_FUNC
+        data.push(0x12);
+        data.push(0);
+        // The `.text` section
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&function.address.to_le_bytes());
+        data.extend_from_slice(&function.length.to_le_bytes());
+    }
+    let strtab_index = headers.len() as u32 + 1;
+    headers.push(SectionHeader {
+        name: section_names.add(".symtab"),
+        kind: SHT_SYMTAB,
+        offset: symtab_offset as u64,
+        size: (data.len() - symtab_offset) as u64,
+        link: strtab_index,
+        // The index of the first global symbol
+        info: 1,
+        align: 8,
+        entsize: SYMBOL_SIZE as u64,
+        ..Default::default()
+    });
+    headers.push(SectionHeader {
+        name: section_names.add(".strtab"),
+        kind: SHT_STRTAB,
+        offset: data.len() as u64,
+        size: symbol_names.0.len() as u64,
+        align: 1,
+        ..Default::default()
+    });
+    data.extend_from_slice(&symbol_names.0);
+    let shstrtab_index = headers.len() as u16;
+    let shstrtab_name = section_names.add(".shstrtab");
+    headers.push(SectionHeader {
+        name: shstrtab_name,
+        kind: SHT_STRTAB,
+        offset: data.len() as u64,
+        size: section_names.0.len() as u64,
+        align: 1,
+        ..Default::default()
+    });
+    data.extend_from_slice(&section_names.0);
+
+    align(&mut data, 8);
+    let section_headers_offset = data.len() as u64;
+    for header in headers.iter() {
+        data.extend_from_slice(&header.name.to_le_bytes());
+        data.extend_from_slice(&header.kind.to_le_bytes());
+        data.extend_from_slice(&header.flags.to_le_bytes());
+        data.extend_from_slice(&header.addr.to_le_bytes());
+        data.extend_from_slice(&header.offset.to_le_bytes());
+        data.extend_from_slice(&header.size.to_le_bytes());
+        data.extend_from_slice(&header.link.to_le_bytes());
+        data.extend_from_slice(&header.info.to_le_bytes());
+        data.extend_from_slice(&header.align.to_le_bytes());
+        data.extend_from_slice(&header.entsize.to_le_bytes());
+    }
+
+    let mut header = Vec::with_capacity(ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE);
+    // ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE
+    header.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    header.extend_from_slice(&[0; 8]);
+    // ET_EXEC, so that addresses are used as they are.
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&machine.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&(ELF_HEADER_SIZE as u64).to_le_bytes());
+    header.extend_from_slice(&section_headers_offset.to_le_bytes());
+    header.extend_from_slice(&flags.to_le_bytes());
+    header.extend_from_slice(&(ELF_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&(SECTION_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&(headers.len() as u16).to_le_bytes());
+    header.extend_from_slice(&shstrtab_index.to_le_bytes());
+    // A PT_LOAD segment for the code, which some debuggers look for.
+    header.extend_from_slice(&1u32.to_le_bytes());
+    // PF_R | PF_X
+    header.extend_from_slice(&5u32.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&text_start.to_le_bytes());
+    header.extend_from_slice(&text_start.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes());
+    header.extend_from_slice(&(text_end - text_start).to_le_bytes());
+    header.extend_from_slice(&1u64.to_le_bytes());
+    data[..header.len()].copy_from_slice(&header);
+    data
+}
+
+fn align(data: &mut Vec<u8>, alignment: usize) {
+    data.resize((data.len() + alignment - 1) & !(alignment - 1), 0);
+}
+
+/// Returns the length of `value` encoded as a minimal unsigned LEB128.
+fn leb128_len(mut value: u64) -> u64 {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
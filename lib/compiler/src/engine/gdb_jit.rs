@@ -0,0 +1,213 @@
+//! The GDB JIT interface.
+//!
+//! Debuggers put a breakpoint on `__jit_debug_register_code` and read
+//! the list of in-memory object files from `__jit_debug_descriptor`
+//! every time it is called. Both GDB and LLDB support it, see
+//! <https://sourceware.org/gdb/onlinedocs/gdb/JIT-Interface.html>.
+//!
+//! Other JIT engines linked in the same binary, like LLVM's, define the
+//! same symbols. Ours are weak, so that a single definition is kept and
+//! shared by all engines.
+
+use std::ptr;
+use std::sync::Mutex;
+
+#[repr(C)]
+struct JitCodeEntry {
+    next_entry: *mut JitCodeEntry,
+    prev_entry: *mut JitCodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64,
+}
+
+#[repr(C)]
+struct JitDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut JitCodeEntry,
+    first_entry: *mut JitCodeEntry,
+}
+
+const JIT_NOACTION: u32 = 0;
+const JIT_REGISTER_FN: u32 = 1;
+const JIT_UNREGISTER_FN: u32 = 2;
+
+// Rust can't define weak symbols, so they are defined in assembly. The
+// descriptor starts with version 1 and no entries, and the function only
+// returns, debuggers break on it.
+#[cfg(any(
+    all(
+        any(target_os = "linux", target_os = "android", target_os = "freebsd"),
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        ),
+    ),
+    all(
+        target_vendor = "apple",
+        any(target_arch = "x86_64", target_arch = "aarch64"),
+    ),
+))]
+mod symbols {
+    use super::JitDescriptor;
+
+    #[cfg(not(target_vendor = "apple"))]
+    std::arch::global_asm!(
+        ".pushsection .text.__jit_debug_register_code,\"ax\",%progbits",
+        ".weak __jit_debug_register_code",
+        ".type __jit_debug_register_code,%function",
+        ".p2align 2",
+        "__jit_debug_register_code:",
+        "ret",
+        ".size __jit_debug_register_code, . - __jit_debug_register_code",
+        ".popsection",
+        ".pushsection .data.__jit_debug_descriptor,\"aw\",%progbits",
+        ".weak __jit_debug_descriptor",
+        ".type __jit_debug_descriptor,%object",
+        ".p2align 3",
+        "__jit_debug_descriptor:",
+        ".4byte 1",
+        ".4byte 0",
+        ".8byte 0",
+        ".8byte 0",
+        ".size __jit_debug_descriptor, . - __jit_debug_descriptor",
+        ".popsection",
+    );
+
+    #[cfg(target_vendor = "apple")]
+    std::arch::global_asm!(
+        ".pushsection __TEXT,__text,regular,pure_instructions",
+        ".globl ___jit_debug_register_code",
+        ".weak_definition ___jit_debug_register_code",
+        ".p2align 2",
+        "___jit_debug_register_code:",
+        "ret",
+        ".popsection",
+        ".pushsection __DATA,__data",
+        ".globl ___jit_debug_descriptor",
+        ".weak_definition ___jit_debug_descriptor",
+        ".p2align 3",
+        "___jit_debug_descriptor:",
+        ".long 1",
+        ".long 0",
+        ".quad 0",
+        ".quad 0",
+        ".popsection",
+    );
+
+    extern "C" {
+        #[allow(non_upper_case_globals)]
+        pub(super) static mut __jit_debug_descriptor: JitDescriptor;
+        pub(super) fn __jit_debug_register_code();
+    }
+
+    pub(super) const SUPPORTED: bool = true;
+}
+
+// Stand-ins on other targets, where registration is skipped.
+#[cfg(not(any(
+    all(
+        any(target_os = "linux", target_os = "android", target_os = "freebsd"),
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        ),
+    ),
+    all(
+        target_vendor = "apple",
+        any(target_arch = "x86_64", target_arch = "aarch64"),
+    ),
+)))]
+mod symbols {
+    use super::{JitDescriptor, JIT_NOACTION};
+    use std::ptr;
+
+    #[allow(non_upper_case_globals)]
+    pub(super) static mut __jit_debug_descriptor: JitDescriptor = JitDescriptor {
+        version: 1,
+        action_flag: JIT_NOACTION,
+        relevant_entry: ptr::null_mut(),
+        first_entry: ptr::null_mut(),
+    };
+
+    pub(super) unsafe fn __jit_debug_register_code() {}
+
+    pub(super) const SUPPORTED: bool = false;
+}
+
+use symbols::{__jit_debug_descriptor, __jit_debug_register_code, SUPPORTED};
+
+/// Guards the list of entries against the other threads of the engine.
+static DESCRIPTOR_LOCK: Mutex<()> = Mutex::new(());
+
+/// An object file registered with the debugger, unregistered on drop.
+pub(crate) struct GdbJitImageRegistration {
+    entry: Box<JitCodeEntry>,
+    // The image must outlive the entry pointing to it.
+    _image: Box<[u8]>,
+}
+
+// The entry is only accessed under `DESCRIPTOR_LOCK`.
+unsafe impl Send for GdbJitImageRegistration {}
+unsafe impl Sync for GdbJitImageRegistration {}
+
+impl GdbJitImageRegistration {
+    /// Registers the object file `image` with the debugger. Returns `None`
+    /// on targets where the interface isn't available.
+    pub(crate) fn register(image: Vec<u8>) -> Option<Self> {
+        if !SUPPORTED {
+            return None;
+        }
+        let image = image.into_boxed_slice();
+        let mut entry = Box::new(JitCodeEntry {
+            next_entry: ptr::null_mut(),
+            prev_entry: ptr::null_mut(),
+            symfile_addr: image.as_ptr(),
+            symfile_size: image.len() as u64,
+        });
+        let _guard = DESCRIPTOR_LOCK.lock().unwrap();
+        unsafe {
+            let descriptor = ptr::addr_of_mut!(__jit_debug_descriptor);
+            let entry_ptr: *mut JitCodeEntry = &mut *entry;
+            entry.next_entry = (*descriptor).first_entry;
+            if !entry.next_entry.is_null() {
+                (*entry.next_entry).prev_entry = entry_ptr;
+            }
+            (*descriptor).first_entry = entry_ptr;
+            (*descriptor).relevant_entry = entry_ptr;
+            (*descriptor).action_flag = JIT_REGISTER_FN;
+            __jit_debug_register_code();
+            (*descriptor).action_flag = JIT_NOACTION;
+            (*descriptor).relevant_entry = ptr::null_mut();
+        }
+        Some(Self {
+            entry,
+            _image: image,
+        })
+    }
+}
+
+impl Drop for GdbJitImageRegistration {
+    fn drop(&mut self) {
+        let _guard = DESCRIPTOR_LOCK.lock().unwrap();
+        unsafe {
+            let descriptor = ptr::addr_of_mut!(__jit_debug_descriptor);
+            let entry_ptr: *mut JitCodeEntry = &mut *self.entry;
+            if self.entry.prev_entry.is_null() {
+                (*descriptor).first_entry = self.entry.next_entry;
+            } else {
+                (*self.entry.prev_entry).next_entry = self.entry.next_entry;
+            }
+            if !self.entry.next_entry.is_null() {
+                (*self.entry.next_entry).prev_entry = self.entry.prev_entry;
+            }
+            (*descriptor).relevant_entry = entry_ptr;
+            (*descriptor).action_flag = JIT_UNREGISTER_FN;
+            __jit_debug_register_code();
+            (*descriptor).action_flag = JIT_NOACTION;
+            (*descriptor).relevant_entry = ptr::null_mut();
+        }
+    }
+}
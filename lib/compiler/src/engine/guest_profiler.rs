@@ -0,0 +1,122 @@
+//! A sampling profiler of wasm code that doesn't need any external tool.
+
+use super::trap::{GlobalFrameInfo, FRAME_INFO};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wasmer_types::FrameInfo;
+use wasmer_vm::StackSampler;
+
+/// The name of the frames outside of wasm code.
+const HOST_FRAME: &str = "[host]";
+
+/// A sampling profiler of the wasm code run by the threads of the process.
+///
+/// A background thread periodically samples the stacks of the threads
+/// running wasm and names their frames with the name section of the
+/// modules. The time spent in host functions is attributed to a `[host]`
+/// frame. Only the calls into wasm started after the profiler are
+/// sampled.
+///
+/// The profiler stops sampling when dropped.
+pub struct GuestProfiler {
+    samples: Arc<Mutex<HashMap<Vec<String>, u64>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GuestProfiler {
+    /// Starts sampling every `interval`.
+    pub fn start(interval: Duration) -> Self {
+        let samples = Arc::new(Mutex::new(HashMap::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampler = StackSampler::new();
+        let thread = {
+            let samples = samples.clone();
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("wasmer-profiler".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Acquire) {
+                        thread::park_timeout(interval);
+                        let stacks = sampler.sample();
+                        if stacks.is_empty() {
+                            continue;
+                        }
+                        let info = FRAME_INFO.read().unwrap();
+                        let mut samples = samples.lock().unwrap();
+                        for stack in stacks {
+                            *samples.entry(symbolicate(&info, &stack)).or_insert(0) += 1;
+                        }
+                    }
+                })
+                .expect("failed to spawn the profiler thread")
+        };
+        Self {
+            samples,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns the number of samples taken so far.
+    pub fn sample_count(&self) -> u64 {
+        self.samples.lock().unwrap().values().sum()
+    }
+
+    /// Writes the samples taken so far as collapsed stacks.
+    ///
+    /// Every distinct stack is written on its own line, with the names of
+    /// its frames from the outermost one separated by `;`, followed by the
+    /// number of samples. Flame graphs are drawn from this format by
+    /// `flamegraph.pl`, `inferno-flamegraph` or speedscope.
+    pub fn write_collapsed(&self, out: &mut dyn Write) -> io::Result<()> {
+        let samples = self.samples.lock().unwrap();
+        let mut lines = samples
+            .iter()
+            .map(|(stack, count)| format!("{} {}\n", stack.join(";"), count))
+            .collect::<Vec<_>>();
+        lines.sort();
+        for line in lines {
+            out.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for GuestProfiler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Names the frames of a sampled stack, from the outermost one.
+fn symbolicate(info: &GlobalFrameInfo, stack: &[usize]) -> Vec<String> {
+    let mut frames = vec![];
+    for (i, &pc) in stack.iter().enumerate() {
+        // Return addresses point after the call instruction.
+        let pc = if i == 0 { pc } else { pc - 1 };
+        match info.lookup_frame_info_without_symbols(pc) {
+            Some(frame) => frames.push(frame_name(&frame)),
+            None if i == 0 => frames.push(HOST_FRAME.to_string()),
+            None => {}
+        }
+    }
+    frames.reverse();
+    frames
+}
+
+fn frame_name(frame: &FrameInfo) -> String {
+    match frame.function_name() {
+        // `;` separates the frames of collapsed stacks.
+        Some(name) => name.replace(';', ":"),
+        None => format!("wasm-function[{}]", frame.func_index()),
+    }
+}
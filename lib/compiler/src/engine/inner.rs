@@ -2,6 +2,11 @@
 
 use crate::engine::builder::EngineBuilder;
 #[cfg(not(target_arch = "wasm32"))]
+use crate::engine::gdb_jit::GdbJitImageRegistration;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+use crate::engine::tiering::TierUp;
+#[cfg(not(target_arch = "wasm32"))]
 use crate::Artifact;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::BaseTunables;
@@ -10,9 +15,9 @@ use crate::CodeMemory;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::GlobalFrameInfoRegistration;
 #[cfg(feature = "compiler")]
-use crate::{Compiler, CompilerConfig};
+use crate::{Compiler, CompilerConfig, FunctionCache};
 #[cfg(not(target_arch = "wasm32"))]
-use crate::{FunctionExtent, Tunables};
+use crate::{FunctionExtent, ProfilingStrategy, Tunables};
 #[cfg(not(target_arch = "wasm32"))]
 use shared_buffer::OwnedBuffer;
 #[cfg(not(target_arch = "wasm32"))]
@@ -42,6 +47,15 @@ pub struct Engine {
     engine_id: EngineId,
     #[cfg(not(target_arch = "wasm32"))]
     tunables: Arc<dyn Tunables + Send + Sync>,
+    /// The optimizing tier, if tiered compilation is enabled
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    tier_up: Option<Arc<TierUp>>,
+    /// The compiler of functions on their first call, if lazy compilation
+    /// is enabled
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    lazy_compiler: Option<Arc<dyn Compiler + Send + Sync>>,
     name: String,
 }
 
@@ -55,21 +69,35 @@ impl Engine {
     ) -> Self {
         #[cfg(not(target_arch = "wasm32"))]
         let tunables = BaseTunables::for_target(&target);
+        let frame_pointers = compiler_config.frame_pointers_enabled();
         let compiler = compiler_config.compiler();
-        let name = format!("engine-{}", compiler.name());
+        let name = if frame_pointers {
+            format!("engine-{}-frame-pointers", compiler.name())
+        } else {
+            format!("engine-{}", compiler.name())
+        };
         Self {
             inner: Arc::new(Mutex::new(EngineInner {
                 compiler: Some(compiler),
                 features,
+                function_cache: None,
                 #[cfg(not(target_arch = "wasm32"))]
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
+                debug_info: false,
+                #[cfg(not(target_arch = "wasm32"))]
+                profiler: None,
+                #[cfg(not(target_arch = "wasm32"))]
                 signatures: SignatureRegistry::new(),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
+            #[cfg(not(target_arch = "wasm32"))]
+            tier_up: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            lazy_compiler: None,
             name,
         }
     }
@@ -83,6 +111,45 @@ impl Engine {
         panic!("The engine is not compiled with any compiler support")
     }
 
+    /// Enable tiered compilation with the given optimizing tier.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_tier_up(&mut self, tier_up: TierUp) {
+        self.tier_up = Some(Arc::new(tier_up));
+    }
+
+    /// Returns the optimizing tier, if tiered compilation is enabled.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn tier_up(&self) -> Option<&Arc<TierUp>> {
+        self.tier_up.as_ref()
+    }
+
+    /// Returns how many functions were recompiled by the optimizing tier
+    /// so far, or 0 if tiered compilation is disabled.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tiered_up_functions(&self) -> usize {
+        self.tier_up
+            .as_ref()
+            .map_or(0, |tier_up| tier_up.tiered_up_functions())
+    }
+
+    /// Enable lazy compilation of functions with the given compiler.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_lazy_compiler(&mut self, compiler: Arc<dyn Compiler + Send + Sync>) {
+        self.lazy_compiler = Some(compiler);
+    }
+
+    /// Returns the compiler of functions on their first call, if lazy
+    /// compilation is enabled.
+    #[cfg(feature = "compiler")]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn lazy_compiler(&self) -> Option<&Arc<dyn Compiler + Send + Sync>> {
+        self.lazy_compiler.as_ref()
+    }
+
     /// Returns the name of this engine
     pub fn name(&self) -> &str {
         self.name.as_str()
@@ -120,15 +187,27 @@ impl Engine {
                 compiler: None,
                 #[cfg(feature = "compiler")]
                 features: Features::default(),
+                #[cfg(feature = "compiler")]
+                function_cache: None,
                 #[cfg(not(target_arch = "wasm32"))]
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
+                debug_info: false,
+                #[cfg(not(target_arch = "wasm32"))]
+                profiler: None,
+                #[cfg(not(target_arch = "wasm32"))]
                 signatures: SignatureRegistry::new(),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
+            #[cfg(feature = "compiler")]
+            #[cfg(not(target_arch = "wasm32"))]
+            tier_up: None,
+            #[cfg(feature = "compiler")]
+            #[cfg(not(target_arch = "wasm32"))]
+            lazy_compiler: None,
             name: "engine-headless".to_string(),
         }
     }
@@ -296,10 +375,19 @@ pub struct EngineInner {
     #[cfg(feature = "compiler")]
     /// The compiler and cpu features
     features: Features,
+    #[cfg(feature = "compiler")]
+    /// The store of compiled functions reused across modules
+    function_cache: Option<Arc<dyn FunctionCache>>,
     /// The code memory is responsible of publishing the compiled
     /// functions to memory.
     #[cfg(not(target_arch = "wasm32"))]
     code_memory: Vec<CodeMemory>,
+    /// Whether the generated code is described to native debuggers.
+    #[cfg(not(target_arch = "wasm32"))]
+    debug_info: bool,
+    /// How the generated code is described to profilers, if it is.
+    #[cfg(not(target_arch = "wasm32"))]
+    profiler: Option<ProfilingStrategy>,
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
     #[cfg(not(target_arch = "wasm32"))]
@@ -331,6 +419,42 @@ impl EngineInner {
         &self.features
     }
 
+    /// The store of compiled functions, if any.
+    #[cfg(feature = "compiler")]
+    pub fn function_cache(&self) -> Option<&dyn FunctionCache> {
+        self.function_cache.as_deref()
+    }
+
+    /// Sets the store of compiled functions.
+    #[cfg(feature = "compiler")]
+    pub fn set_function_cache(&mut self, function_cache: Option<Arc<dyn FunctionCache>>) {
+        self.function_cache = function_cache;
+    }
+
+    /// Whether the generated code is described to native debuggers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn debug_info(&self) -> bool {
+        self.debug_info
+    }
+
+    /// Enable or disable describing the generated code to native debuggers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_debug_info(&mut self, enable: bool) {
+        self.debug_info = enable;
+    }
+
+    /// How the generated code is described to profilers, if it is.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn profiler(&self) -> Option<ProfilingStrategy> {
+        self.profiler
+    }
+
+    /// Set how the generated code is described to profilers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_profiler(&mut self, profiler: Option<ProfilingStrategy>) {
+        self.profiler = profiler;
+    }
+
     /// Allocate compiled functions into memory
     #[cfg(not(target_arch = "wasm32"))]
     #[allow(clippy::type_complexity)]
@@ -436,6 +560,13 @@ impl EngineInner {
         self.code_memory.last_mut().unwrap().publish();
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Take ownership of the last allocated code memory, so that it is
+    /// freed with its new owner instead of the engine.
+    pub(crate) fn take_code_memory(&mut self) -> Option<CodeMemory> {
+        self.code_memory.pop()
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     /// Register DWARF-type exception handling information associated with the code.
     pub(crate) fn publish_eh_frame(&mut self, eh_frame: Option<&[u8]>) -> Result<(), CompileError> {
@@ -464,6 +595,15 @@ impl EngineInner {
             .unwrap()
             .register_frame_info(frame_info);
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Keep the debug image registered as long as the code memory
+    pub(crate) fn register_debug_image(&mut self, debug_image: GdbJitImageRegistration) {
+        self.code_memory
+            .last_mut()
+            .unwrap()
+            .register_debug_image(debug_image);
+    }
 }
 
 #[cfg(feature = "compiler")]
@@ -0,0 +1,337 @@
+//! Lazy compilation.
+//!
+//! When lazy compilation is enabled, only the entry points of a module
+//! are compiled upfront. Every other function is compiled to a small stub
+//! that jumps to the address stored in the function's slot. While the
+//! slot is empty, the stub calls back into the engine, which compiles the
+//! function, fills its slot, and returns the body to jump to.
+//!
+//! Filling a slot is a single aligned pointer store, so a thread calling
+//! a function concurrently either goes through the engine, where it waits
+//! for the compilation in progress, or jumps to the compiled body. Different
+//! functions are compiled in parallel.
+
+use crate::artifact_builders::{retain_unwind_info, FunctionBatch, FunctionBodies, LazyFunctions};
+use crate::compiler::STUB_FUNCTION_BODY;
+use crate::engine::debug_info::register_debug_info;
+use crate::engine::link::link_module;
+use crate::engine::profiling::register_functions;
+use crate::{
+    libcall_trampoline_len, make_libcall_trampolines, register_function_frame_info, Artifact,
+    ArtifactBuild, ArtifactCreate, Compiler, Engine, FunctionBodyData, FunctionExtent,
+    ModuleTranslationState, LAZY_FUNCTION_SLOTS_HEADER_LEN,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{
+    CompileError, CompileModuleInfo, CompiledFunction, CustomSections, Dwarf, FunctionBody,
+    LocalFunctionIndex, SectionIndex, SerializeError,
+};
+use wasmer_vm::{
+    on_host_stack, raise_user_trap, resume_panic, FunctionBodyPtr, SectionBodyPtr, VMFunctionBody,
+};
+
+/// A lazy function that was compiled, kept around for serialization.
+struct LazyFunction {
+    body: FunctionBodyPtr,
+    function: CompiledFunction,
+    custom_sections: CustomSections,
+    debug: Option<Dwarf>,
+}
+
+/// A lazy function, filled in by the first call that compiles it while the
+/// other calls wait on its lock.
+type LazySlot = Arc<Mutex<Option<LazyFunction>>>;
+
+/// The lazy compilation state of an `Artifact`.
+pub(crate) struct LazyState {
+    engine: Engine,
+    wasm: Box<[u8]>,
+    compile_info: CompileModuleInfo,
+    module_translation: ModuleTranslationState,
+    function_body_ranges: PrimaryMap<LocalFunctionIndex, Range<usize>>,
+    /// The functions compiled to a lazy stub.
+    functions: BTreeSet<LocalFunctionIndex>,
+    /// The functions of the artifact, where lazy functions are their stub.
+    base: PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    /// The start of the slots section, made of `AtomicUsize`s.
+    slots: *const AtomicUsize,
+    /// The lazy functions that were called, the map is only locked to look
+    /// up or insert their slot.
+    compiled: Mutex<BTreeMap<LocalFunctionIndex, LazySlot>>,
+}
+
+// The slots section lives in the engine's code memory, which outlives
+// the artifact, and it's only written through atomics.
+unsafe impl Send for LazyState {}
+unsafe impl Sync for LazyState {}
+
+impl LazyState {
+    /// Sets up the lazy functions of an `artifact` compiled from `wasm`,
+    /// whose slots section was allocated at `slots`.
+    pub(crate) fn start(
+        engine: &Engine,
+        wasm: &[u8],
+        artifact: &Artifact,
+        function_bodies: FunctionBodies,
+        lazy_functions: LazyFunctions,
+        slots: SectionBodyPtr,
+    ) -> Arc<Self> {
+        let state = Arc::new(Self {
+            engine: engine.clone(),
+            wasm: wasm.into(),
+            compile_info: CompileModuleInfo {
+                module: artifact.create_module_info(),
+                features: artifact.features().clone(),
+                memory_styles: artifact.memory_styles().clone(),
+                table_styles: artifact.table_styles().clone(),
+            },
+            module_translation: function_bodies.module_translation,
+            function_body_ranges: function_bodies.ranges,
+            functions: lazy_functions.functions,
+            base: artifact.finished_function_extents(),
+            slots: *slots as *const AtomicUsize,
+            compiled: Mutex::new(BTreeMap::new()),
+        });
+
+        // The state is kept alive by the artifact and its instances, which
+        // are the only ones that can call the stubs.
+        let header = state.slots;
+        unsafe {
+            (*header).store(compile_lazy_function as usize, Ordering::Release);
+            (*header.add(1)).store(Arc::as_ptr(&state) as usize, Ordering::Release);
+        }
+
+        state
+    }
+
+    fn slot(&self, index: LocalFunctionIndex) -> &AtomicUsize {
+        let header_len = LAZY_FUNCTION_SLOTS_HEADER_LEN / std::mem::size_of::<usize>();
+        unsafe { &*self.slots.add(header_len + index.index()) }
+    }
+
+    fn function_body(&self, index: LocalFunctionIndex) -> FunctionBodyData<'_> {
+        let range = &self.function_body_ranges[index];
+        FunctionBodyData {
+            data: &self.wasm[range.clone()],
+            module_offset: range.start,
+        }
+    }
+
+    fn lazy_compiler(&self) -> Result<&(dyn Compiler + Send + Sync), CompileError> {
+        self.engine
+            .lazy_compiler()
+            .map(|compiler| &**compiler)
+            .ok_or_else(|| CompileError::Codegen("lazy compilation is not enabled".to_string()))
+    }
+
+    /// Compiles the function `index` if needed, and returns its body.
+    fn compile(&self, index: LocalFunctionIndex) -> Result<FunctionBodyPtr, CompileError> {
+        let slot = self
+            .compiled
+            .lock()
+            .unwrap()
+            .entry(index)
+            .or_default()
+            .clone();
+        let mut compiled = slot.lock().unwrap();
+        if let Some(function) = compiled.as_ref() {
+            return Ok(function.body);
+        }
+
+        // Other threads may use the engine while the function compiles.
+        let target = self.engine.target();
+        let compiler = self.lazy_compiler()?;
+        let (function, custom_sections, debug) = compiler.compile_function(
+            target,
+            &self.compile_info,
+            &self.module_translation,
+            index,
+            &self.function_body(index),
+        )?;
+
+        let mut sections = custom_sections.clone();
+        let mut section_relocations = sections
+            .values()
+            .map(|section| section.relocations.clone())
+            .collect::<PrimaryMap<SectionIndex, _>>();
+        let libcall_trampolines_section = make_libcall_trampolines(target);
+        section_relocations.push(libcall_trampolines_section.relocations.clone());
+        let libcall_trampolines = sections.push(libcall_trampolines_section);
+
+        let mut engine_inner = self.engine.inner_mut();
+        let (allocated_functions, _, _, allocated_sections) = engine_inner.allocate(
+            &self.compile_info.module,
+            std::iter::once(&function.body),
+            std::iter::empty::<&FunctionBody>(),
+            std::iter::empty::<&FunctionBody>(),
+            sections.values(),
+        )?;
+        let allocated = &allocated_functions[LocalFunctionIndex::new(0)];
+        let extent = FunctionExtent {
+            ptr: allocated.ptr,
+            length: allocated.length,
+        };
+
+        // Calls to other lazy functions go through their stubs.
+        let call_targets = self
+            .base
+            .iter()
+            .map(|(i, base)| {
+                let target = if i == index { &extent } else { base };
+                FunctionExtent {
+                    ptr: target.ptr,
+                    length: target.length,
+                }
+            })
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        link_module(
+            &self.compile_info.module,
+            &call_targets,
+            std::iter::once((index, function.relocations.iter())),
+            &allocated_sections,
+            section_relocations
+                .iter()
+                .map(|(index, relocations)| (index, relocations.iter())),
+            libcall_trampolines,
+            libcall_trampoline_len(target),
+        );
+        let eh_frame = debug.as_ref().map(|debug| unsafe {
+            std::slice::from_raw_parts(
+                *allocated_sections[debug.eh_frame],
+                sections[debug.eh_frame].bytes.len(),
+            )
+        });
+        engine_inner.publish_compiled_code();
+        engine_inner.publish_eh_frame(eh_frame)?;
+
+        if let Some(registration) = register_function_frame_info(
+            self.compile_info.module.clone(),
+            index,
+            &extent,
+            function.frame_info.clone(),
+        ) {
+            engine_inner.register_frame_info(registration);
+        }
+        if engine_inner.debug_info() {
+            if let Some(debug_image) = register_debug_info(
+                &self.compile_info.module,
+                std::iter::once((index, &extent, &function.frame_info)),
+            ) {
+                engine_inner.register_debug_image(debug_image);
+            }
+        }
+        if let Some(profiler) = engine_inner.profiler() {
+            register_functions(
+                profiler,
+                &self.compile_info.module,
+                std::iter::once((index, &extent)),
+                None,
+            );
+        }
+        drop(engine_inner);
+
+        self.slot(index)
+            .store(*extent.ptr as usize, Ordering::Release);
+        *compiled = Some(LazyFunction {
+            body: extent.ptr,
+            function,
+            custom_sections,
+            debug,
+        });
+        Ok(extent.ptr)
+    }
+
+    /// Serializes `artifact` with the code of the lazy functions, compiling
+    /// the ones that were not called yet.
+    pub(crate) fn serialize(&self, artifact: &ArtifactBuild) -> Result<Vec<u8>, SerializeError> {
+        // Waits for the functions that are being compiled
+        let slots = self.compiled.lock().unwrap().clone();
+        let mut batches = slots
+            .iter()
+            .filter_map(|(index, slot)| {
+                let compiled = slot.lock().unwrap();
+                let function = compiled.as_ref()?;
+                Some((
+                    vec![(*index, function.function.clone())],
+                    function.custom_sections.clone(),
+                    function.debug.clone(),
+                ))
+            })
+            .collect::<Vec<FunctionBatch>>();
+
+        let compiled = batches
+            .iter()
+            .map(|(functions, ..)| functions[0].0)
+            .collect::<BTreeSet<_>>();
+        let pending = self
+            .functions
+            .iter()
+            .filter(|index| !compiled.contains(index))
+            .copied()
+            .collect::<BTreeSet<_>>();
+        if !pending.is_empty() {
+            let function_body_inputs = self
+                .function_body_ranges
+                .keys()
+                .map(|index| {
+                    let body = self.function_body(index);
+                    if pending.contains(&index) {
+                        body
+                    } else {
+                        FunctionBodyData {
+                            data: STUB_FUNCTION_BODY,
+                            module_offset: body.module_offset,
+                        }
+                    }
+                })
+                .collect();
+            let target = self.engine.target();
+            let mut compilation = self
+                .lazy_compiler()
+                .and_then(|compiler| {
+                    compiler.compile_module(
+                        target,
+                        &self.compile_info,
+                        &self.module_translation,
+                        function_body_inputs,
+                    )
+                })
+                .map_err(|e| SerializeError::Generic(e.to_string()))?;
+            retain_unwind_info(&mut compilation, target, |index| pending.contains(&index));
+            batches.push((
+                compilation
+                    .functions
+                    .into_iter()
+                    .filter(|(index, _)| pending.contains(index))
+                    .collect(),
+                compilation.custom_sections,
+                compilation.debug,
+            ));
+        }
+
+        artifact.serialize_with_functions(self.engine.target(), batches)
+    }
+}
+
+/// The hook called by lazy stubs, through the header of the slots section.
+unsafe extern "C" fn compile_lazy_function(
+    state: *const LazyState,
+    index: u32,
+) -> *const VMFunctionBody {
+    let state = &*state;
+    let result = on_host_stack(|| {
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            state.compile(LocalFunctionIndex::from_u32(index))
+        }))
+    });
+    match result {
+        Ok(Ok(body)) => *body,
+        Ok(Err(error)) => raise_user_trap(Box::new(error)),
+        Err(panic) => resume_panic(panic),
+    }
+}
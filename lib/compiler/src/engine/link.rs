@@ -1,27 +1,27 @@
 //! Linking for Universal-compiled code.
 
-use crate::get_libcall_trampoline;
 use crate::FunctionExtent;
+use crate::{get_libcall_trampoline, tier_up_stub_len};
 use std::collections::HashMap;
 use std::ptr::{read_unaligned, write_unaligned};
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::RelocationLike;
 use wasmer_types::{LocalFunctionIndex, ModuleInfo};
-use wasmer_types::{RelocationKind, RelocationTarget, SectionIndex};
+use wasmer_types::{RelocationKind, RelocationTarget, SectionIndex, Target};
 use wasmer_vm::libcalls::function_pointer;
-use wasmer_vm::SectionBodyPtr;
+use wasmer_vm::{FunctionBodyPtr, SectionBodyPtr};
 
 fn apply_relocation(
     body: usize,
     r: &impl RelocationLike,
-    allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    call_targets: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
     allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
     libcall_trampolines: SectionIndex,
     libcall_trampoline_len: usize,
     riscv_pcrel_hi20s: &mut HashMap<usize, u32>,
 ) {
     let target_func_address: usize = match r.reloc_target() {
-        RelocationTarget::LocalFunc(index) => *allocated_functions[index].ptr as usize,
+        RelocationTarget::LocalFunc(index) => *call_targets[index].ptr as usize,
         RelocationTarget::LibCall(libcall) => {
             // Use the direct target of the libcall if the relocation supports
             // a full 64-bit address. Otherwise use a trampoline.
@@ -132,8 +132,43 @@ fn apply_relocation(
 /// Links a module, patching the allocated functions with the
 /// required relocations and jump tables.
 pub fn link_module<'a>(
+    module: &ModuleInfo,
+    allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    function_relocations: impl Iterator<
+        Item = (
+            LocalFunctionIndex,
+            impl Iterator<Item = &'a (impl RelocationLike + 'a)>,
+        ),
+    >,
+    allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
+    section_relocations: impl Iterator<
+        Item = (
+            SectionIndex,
+            impl Iterator<Item = &'a (impl RelocationLike + 'a)>,
+        ),
+    >,
+    libcall_trampolines: SectionIndex,
+    trampoline_len: usize,
+) {
+    link_module_with_call_targets(
+        module,
+        allocated_functions,
+        allocated_functions,
+        function_relocations,
+        allocated_sections,
+        section_relocations,
+        libcall_trampolines,
+        trampoline_len,
+    )
+}
+
+/// Links a module like [`link_module`], except that calls to local
+/// functions are resolved to `call_targets` rather than to their bodies.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn link_module_with_call_targets<'a>(
     _module: &ModuleInfo,
     allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    call_targets: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
     function_relocations: impl Iterator<
         Item = (
             LocalFunctionIndex,
@@ -158,7 +193,7 @@ pub fn link_module<'a>(
             apply_relocation(
                 body,
                 r,
-                allocated_functions,
+                call_targets,
                 allocated_sections,
                 libcall_trampolines,
                 trampoline_len,
@@ -172,7 +207,7 @@ pub fn link_module<'a>(
             apply_relocation(
                 body,
                 r,
-                allocated_functions,
+                call_targets,
                 allocated_sections,
                 libcall_trampolines,
                 trampoline_len,
@@ -181,3 +216,19 @@ pub fn link_module<'a>(
         }
     }
 }
+
+/// Returns where the tier-up stub of each local function is, given the
+/// address `stubs` of a section created by `make_tier_up_stubs`.
+pub(crate) fn tier_up_stub_extents(
+    target: &Target,
+    stubs: SectionBodyPtr,
+    num_local_functions: usize,
+) -> PrimaryMap<LocalFunctionIndex, FunctionExtent> {
+    let stub_len = tier_up_stub_len(target);
+    (0..num_local_functions)
+        .map(|index| FunctionExtent {
+            ptr: FunctionBodyPtr(unsafe { stubs.0.add(index * stub_len) as *const _ }),
+            length: stub_len,
+        })
+        .collect::<PrimaryMap<LocalFunctionIndex, _>>()
+}
@@ -17,12 +17,30 @@ mod builder;
 #[cfg(not(target_arch = "wasm32"))]
 mod code_memory;
 #[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+mod debug_info;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+mod gdb_jit;
+#[cfg(unix)]
+#[cfg(not(target_arch = "wasm32"))]
+mod guest_profiler;
+#[cfg(feature = "translator")]
 mod inner;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+mod lazy;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 mod link;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
+mod profiling;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+mod tiering;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
 mod unwind;
 
 pub use self::error::{InstantiationError, LinkError};
@@ -30,6 +48,9 @@ pub use self::error::{InstantiationError, LinkError};
 pub use self::resolver::resolve_imports;
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::trap::*;
+#[cfg(unix)]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::tunables::PoolingTunables;
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::tunables::{BaseTunables, Tunables};
 
@@ -41,8 +62,14 @@ pub use self::builder::EngineBuilder;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::code_memory::CodeMemory;
+#[cfg(unix)]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::guest_profiler::GuestProfiler;
 #[cfg(feature = "translator")]
 pub use self::inner::{Engine, EngineInner};
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::link::link_module;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::profiling::ProfilingStrategy;
@@ -0,0 +1,211 @@
+//! Describing the generated code to `perf`.
+//!
+//! The perf map and jitdump formats are documented in the Linux sources,
+//! see `tools/perf/Documentation/jit-interface.txt` and
+//! `tools/perf/Documentation/jitdump-specification.txt`.
+
+use super::debug_info::SourceLine;
+use crate::FunctionExtent;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+
+/// How the code loaded by an engine is described to profilers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilingStrategy {
+    /// Append the location and name of every function to
+    /// `/tmp/perf-<pid>.map`, which `perf report` reads.
+    PerfMap,
+    /// Write the code, name and source lines of every function to
+    /// `jit-<pid>.dump` in the current directory. The records of a
+    /// `perf record -k mono` session are resolved with `perf inject --jit`.
+    JitDump,
+}
+
+/// Describes `functions` of `module` to the profiler, with the source
+/// lines of each function if known.
+///
+/// This does nothing on other systems than Linux.
+#[allow(unused_variables)]
+pub(crate) fn register_functions<'a>(
+    strategy: ProfilingStrategy,
+    module: &ModuleInfo,
+    functions: impl IntoIterator<Item = (LocalFunctionIndex, &'a FunctionExtent)>,
+    source_lines: Option<&PrimaryMap<LocalFunctionIndex, Vec<SourceLine>>>,
+) {
+    #[cfg(target_os = "linux")]
+    {
+        let functions = functions.into_iter().map(|(index, extent)| {
+            let name = super::debug_info::function_name(module, index);
+            let lines = source_lines.map_or(&[][..], |lines| &lines[index][..]);
+            (name, extent, lines)
+        });
+        // Failing to write a record only makes the profile less accurate.
+        let _ = match strategy {
+            ProfilingStrategy::PerfMap => linux::write_perf_map(functions),
+            ProfilingStrategy::JitDump => linux::write_jit_dump(functions),
+        };
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::SourceLine;
+    use crate::FunctionExtent;
+    use memmap2::{Mmap, MmapOptions};
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Write};
+    use std::sync::Mutex;
+
+    static PERF_MAP: Mutex<Option<File>> = Mutex::new(None);
+    static JIT_DUMP: Mutex<Option<JitDump>> = Mutex::new(None);
+
+    pub(super) fn write_perf_map<'a>(
+        functions: impl Iterator<Item = (String, &'a FunctionExtent, &'a [SourceLine])>,
+    ) -> io::Result<()> {
+        let mut perf_map = PERF_MAP.lock().unwrap();
+        if perf_map.is_none() {
+            let path = format!("/tmp/perf-{}.map", std::process::id());
+            *perf_map = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        }
+        let file = perf_map.as_mut().unwrap();
+        let mut records = String::new();
+        for (name, extent, _) in functions {
+            records.push_str(&format!(
+                "{:x} {:x} {}\n",
+                extent.ptr.0 as usize, extent.length, name
+            ));
+        }
+        file.write_all(records.as_bytes())
+    }
+
+    const JITDUMP_MAGIC: u32 = 0x4A69_5444;
+    const JITDUMP_VERSION: u32 = 1;
+    const JIT_CODE_LOAD: u32 = 0;
+    const JIT_CODE_DEBUG_INFO: u32 = 2;
+
+    /// The jitdump file of the process.
+    struct JitDump {
+        file: File,
+        // perf finds the file through this mapping.
+        _marker: Mmap,
+        code_index: u64,
+    }
+
+    pub(super) fn write_jit_dump<'a>(
+        functions: impl Iterator<Item = (String, &'a FunctionExtent, &'a [SourceLine])>,
+    ) -> io::Result<()> {
+        let mut jit_dump = JIT_DUMP.lock().unwrap();
+        if jit_dump.is_none() {
+            *jit_dump = Some(JitDump::create()?);
+        }
+        let jit_dump = jit_dump.as_mut().unwrap();
+        let pid = std::process::id();
+        let tid = unsafe { libc::syscall(libc::SYS_gettid) } as u32;
+        let mut records = vec![];
+        for (name, extent, lines) in functions {
+            let address = extent.ptr.0 as u64;
+            if !lines.is_empty() {
+                let mut record = vec![];
+                record.extend_from_slice(&address.to_ne_bytes());
+                record.extend_from_slice(&(lines.len() as u64).to_ne_bytes());
+                for line in lines {
+                    record.extend_from_slice(&line.address.to_ne_bytes());
+                    record.extend_from_slice(&(line.line as u32).to_ne_bytes());
+                    // The discriminator
+                    record.extend_from_slice(&0u32.to_ne_bytes());
+                    record.extend_from_slice(line.file.as_bytes());
+                    record.push(0);
+                }
+                push_record(&mut records, JIT_CODE_DEBUG_INFO, &record);
+            }
+
+            let code =
+                unsafe { std::slice::from_raw_parts(extent.ptr.0 as *const u8, extent.length) };
+            let mut record = vec![];
+            record.extend_from_slice(&pid.to_ne_bytes());
+            record.extend_from_slice(&tid.to_ne_bytes());
+            // The virtual address and the address of the code
+            record.extend_from_slice(&address.to_ne_bytes());
+            record.extend_from_slice(&address.to_ne_bytes());
+            record.extend_from_slice(&(code.len() as u64).to_ne_bytes());
+            record.extend_from_slice(&jit_dump.code_index.to_ne_bytes());
+            record.extend_from_slice(name.as_bytes());
+            record.push(0);
+            record.extend_from_slice(code);
+            push_record(&mut records, JIT_CODE_LOAD, &record);
+            jit_dump.code_index += 1;
+        }
+        jit_dump.file.write_all(&records)
+    }
+
+    impl JitDump {
+        fn create() -> io::Result<Self> {
+            let pid = std::process::id();
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(format!("jit-{}.dump", pid))?;
+
+            let mut header = vec![];
+            header.extend_from_slice(&JITDUMP_MAGIC.to_ne_bytes());
+            header.extend_from_slice(&JITDUMP_VERSION.to_ne_bytes());
+            // The size of the header
+            header.extend_from_slice(&40u32.to_ne_bytes());
+            header.extend_from_slice(&elf_machine().to_ne_bytes());
+            header.extend_from_slice(&0u32.to_ne_bytes());
+            header.extend_from_slice(&pid.to_ne_bytes());
+            header.extend_from_slice(&timestamp().to_ne_bytes());
+            // The flags
+            header.extend_from_slice(&0u64.to_ne_bytes());
+            file.write_all(&header)?;
+            let marker = unsafe { MmapOptions::new().len(page_size()).map_exec(&file)? };
+
+            Ok(Self {
+                file,
+                _marker: marker,
+                code_index: 0,
+            })
+        }
+    }
+
+    fn push_record(records: &mut Vec<u8>, id: u32, body: &[u8]) {
+        // The id, the total size and the timestamp
+        let size = 16 + body.len();
+        records.extend_from_slice(&id.to_ne_bytes());
+        records.extend_from_slice(&(size as u32).to_ne_bytes());
+        records.extend_from_slice(&timestamp().to_ne_bytes());
+        records.extend_from_slice(body);
+    }
+
+    /// The time in nanoseconds, from the clock used by `perf record -k mono`.
+    fn timestamp() -> u64 {
+        let mut time = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time) };
+        time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+    }
+
+    fn page_size() -> usize {
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    }
+
+    fn elf_machine() -> u32 {
+        if cfg!(target_arch = "x86_64") {
+            62
+        } else if cfg!(target_arch = "aarch64") {
+            183
+        } else if cfg!(target_arch = "riscv64") {
+            243
+        } else if cfg!(target_arch = "x86") {
+            3
+        } else if cfg!(target_arch = "arm") {
+            40
+        } else {
+            0
+        }
+    }
+}
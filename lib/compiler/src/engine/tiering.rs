@@ -0,0 +1,613 @@
+//! Tiered compilation.
+//!
+//! When tiering is enabled, modules are first compiled with the engine's
+//! baseline compiler (usually Singlepass), and the generated code bumps a
+//! per-function hotness counter on entry and on every loop iteration.
+//! A background thread shared by all the artifacts of the engine watches
+//! the counters of their live instances, and recompiles the functions that
+//! crossed the threshold with the optimizing compiler.
+//!
+//! Every call to a local function, be it a direct call or one through a
+//! func ref, goes through a small stub that jumps to the address stored in
+//! the function's slot. Once the optimized code of a function is ready,
+//! its slot is switched to it, so that all the callers pick it up.
+
+use crate::artifact_builders::eh_frame;
+use crate::artifact_builders::FunctionBodies;
+use crate::engine::code_memory::CodeMemory;
+use crate::engine::debug_info::register_debug_info;
+use crate::engine::link::{link_module_with_call_targets, tier_up_stub_extents};
+use crate::engine::profiling::register_functions;
+use crate::function_cache::rebase_section_relocations;
+use crate::{
+    libcall_trampoline_len, make_libcall_trampolines, make_tier_up_stubs,
+    register_function_frame_info, tier_up_stubs_supported, Artifact, ArtifactCreate, Compiler,
+    CompilerConfig, Engine, FunctionBodyData, FunctionExtent, FunctionMiddleware,
+    MiddlewareReaderState, ModuleMiddleware, ModuleTranslationState,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{
+    CompileError, CompileModuleInfo, CustomSection, CustomSections, Dwarf, FunctionBody,
+    GlobalIndex, GlobalInit, GlobalType, LocalFunctionIndex, LocalGlobalIndex, MiddlewareError,
+    ModuleInfo, Mutability, SectionIndex, Target, Type,
+};
+use wasmer_vm::{FunctionBodyPtr, SectionBodyPtr, VMGlobalDefinition, VMInstance};
+use wasmparser::Operator;
+
+/// How often the background thread looks at the hotness counters.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A middleware giving each local function an `i64` hotness counter.
+///
+/// The counters are appended as the last globals of the module (the
+/// middleware is pushed after all the others). The optimizing compiler
+/// compiles against the module info transformed by the baseline one, and
+/// uses a non-instrumenting variant.
+#[derive(Debug)]
+pub(crate) struct HotnessCounters {
+    instrument: bool,
+}
+
+impl HotnessCounters {
+    /// Creates the middleware. If `instrument` is false, the counters are
+    /// only declared, and the function bodies are left untouched.
+    pub(crate) fn new(instrument: bool) -> Self {
+        Self { instrument }
+    }
+
+    /// The index of the first hotness counter of a module whose info was
+    /// transformed by this middleware.
+    fn first_counter(module_info: &ModuleInfo) -> GlobalIndex {
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        GlobalIndex::new(module_info.globals.len() - num_local_functions)
+    }
+}
+
+impl ModuleMiddleware for HotnessCounters {
+    fn generate_function_middleware(
+        &self,
+        _local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        // The counters can't be located without the module, the compilers
+        // go through `generate_function_middleware_for_module`
+        assert!(
+            !self.instrument,
+            "the hotness counters need the info of the module"
+        );
+        Box::new(FunctionHotnessCounter {
+            counter: None,
+            entered: false,
+        })
+    }
+
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let counter = self.instrument.then(|| {
+            GlobalIndex::new(
+                Self::first_counter(module_info).index() + local_function_index.index(),
+            )
+        });
+        Box::new(FunctionHotnessCounter {
+            counter,
+            entered: false,
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        for _ in 0..num_local_functions {
+            module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+            module_info
+                .global_initializers
+                .push(GlobalInit::I64Const(0));
+        }
+    }
+
+    /// Each function bumps its own counter, so the instrumented functions
+    /// can't be cached.
+    fn cache_key(&self) -> Option<String> {
+        (!self.instrument).then(|| "hotness_counters".to_string())
+    }
+}
+
+#[derive(Debug)]
+struct FunctionHotnessCounter {
+    counter: Option<GlobalIndex>,
+    entered: bool,
+}
+
+impl FunctionHotnessCounter {
+    fn bump(counter: GlobalIndex, state: &mut MiddlewareReaderState<'_>) {
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: counter.as_u32(),
+            },
+            Operator::I64Const { value: 1 },
+            Operator::I64Add,
+            Operator::GlobalSet {
+                global_index: counter.as_u32(),
+            },
+        ]);
+    }
+}
+
+impl FunctionMiddleware for FunctionHotnessCounter {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let counter = match self.counter {
+            Some(counter) => counter,
+            None => {
+                state.push_operator(operator);
+                return Ok(());
+            }
+        };
+
+        if !self.entered {
+            self.entered = true;
+            Self::bump(counter, state);
+        }
+        let is_loop = matches!(operator, Operator::Loop { .. });
+        state.push_operator(operator);
+        if is_loop {
+            Self::bump(counter, state);
+        }
+
+        Ok(())
+    }
+}
+
+/// The optimizing tier of an engine, and the background thread that
+/// recompiles the hot functions of all its artifacts.
+pub(crate) struct TierUp {
+    compiler: Mutex<Box<dyn Compiler>>,
+    threshold: u64,
+    states: Mutex<Vec<Weak<TieringState>>>,
+    worker_started: AtomicBool,
+    tiered_up_functions: AtomicUsize,
+}
+
+impl TierUp {
+    /// Creates the optimizing tier from its compiler config.
+    pub(crate) fn new(mut compiler_config: Box<dyn CompilerConfig>, threshold: u64) -> Self {
+        compiler_config.push_middleware(Arc::new(HotnessCounters::new(false)));
+        Self {
+            compiler: Mutex::new(compiler_config.compiler()),
+            threshold,
+            states: Mutex::new(Vec::new()),
+            worker_started: AtomicBool::new(false),
+            tiered_up_functions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether the optimizing compiler can compile the hot functions
+    /// without the rest of their module.
+    pub(crate) fn supported(&self) -> bool {
+        self.compiler.lock().unwrap().supports_compile_function()
+    }
+
+    /// The number of functions whose optimized code was swapped in so far.
+    pub(crate) fn tiered_up_functions(&self) -> usize {
+        self.tiered_up_functions.load(Ordering::Acquire)
+    }
+
+    /// Starts watching the hotness counters of `state`, spawning the
+    /// background thread if it isn't running yet.
+    fn watch(self: &Arc<Self>, state: &Arc<TieringState>) {
+        self.states.lock().unwrap().push(Arc::downgrade(state));
+        if !self.worker_started.swap(true, Ordering::AcqRel) {
+            let weak = Arc::downgrade(self);
+            // If the thread can't be spawned, modules simply stay on the
+            // baseline tier.
+            let _ = thread::Builder::new()
+                .name("wasmer-tier-up".to_string())
+                .spawn(move || Self::run(weak));
+        }
+    }
+
+    fn run(tier_up: Weak<Self>) {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            match tier_up.upgrade() {
+                Some(tier_up) => tier_up.poll(),
+                None => return,
+            }
+        }
+    }
+
+    /// Recompiles the hot functions of every live artifact.
+    fn poll(&self) {
+        let states = {
+            let mut states = self.states.lock().unwrap();
+            states.retain(|state| state.strong_count() > 0);
+            states.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+        };
+        for state in states {
+            let hot = state.hot_functions(self.threshold);
+            if hot.is_empty() {
+                continue;
+            }
+            match state.tier_up(self, &hot) {
+                Ok(()) => {
+                    self.tiered_up_functions
+                        .fetch_add(hot.len(), Ordering::AcqRel);
+                }
+                // On failure, the module stays on the code it has so far.
+                Err(_) => {
+                    let failed = Arc::downgrade(&state);
+                    self.states
+                        .lock()
+                        .unwrap()
+                        .retain(|state| !state.ptr_eq(&failed));
+                }
+            }
+        }
+    }
+}
+
+/// The slots of the local functions of an artifact, holding the body that
+/// their tier-up stubs jump to.
+pub(crate) struct TierUpSlots(Box<[AtomicUsize]>);
+
+impl TierUpSlots {
+    /// Creates the slots of `num_local_functions` functions. Returns `None`
+    /// if the target has no tier-up stubs, in which case the module isn't
+    /// tiered.
+    pub(crate) fn new(target: &Target, num_local_functions: usize) -> Option<Self> {
+        if !tier_up_stubs_supported(target) {
+            return None;
+        }
+        Some(Self(
+            (0..num_local_functions)
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+        ))
+    }
+
+    /// Creates a section of tier-up stubs jumping through these slots.
+    pub(crate) fn make_stubs(&self, target: &Target) -> CustomSection {
+        make_tier_up_stubs(target, self.0.len(), self.0.as_ptr() as usize)
+    }
+
+    /// Points the stubs of `index` to `body`.
+    fn redirect(&self, index: LocalFunctionIndex, body: FunctionBodyPtr) {
+        self.0[index.index()].store(*body as usize, Ordering::Release);
+    }
+}
+
+/// The hotness counters of an instance.
+struct RegisteredInstance {
+    counters: Vec<NonNull<VMGlobalDefinition>>,
+}
+
+// The pointers stay valid until the instance is dropped, which
+// unregisters it while holding the lock of `TieringState::inner`.
+unsafe impl Send for RegisteredInstance {}
+
+impl RegisteredInstance {
+    /// Reads the counter of `index`, which the instance may be bumping
+    /// concurrently.
+    fn counter(&self, index: LocalFunctionIndex) -> u64 {
+        let counter = self.counters[index.index()].as_ptr();
+        unsafe {
+            let value = ptr::addr_of!((*counter).val) as *const AtomicU64;
+            (*value).load(Ordering::Relaxed)
+        }
+    }
+}
+
+/// The body of a function that is not optimized yet.
+struct PendingFunction {
+    body: Box<[u8]>,
+    module_offset: usize,
+}
+
+#[derive(Default)]
+struct TieringInner {
+    instances: HashMap<usize, RegisteredInstance>,
+    next_instance_id: usize,
+    /// The functions that are not optimized yet, which are the only ones
+    /// whose bodies are kept.
+    pending: BTreeMap<LocalFunctionIndex, PendingFunction>,
+    /// The optimized code, freed together with the artifact.
+    code: Vec<CodeMemory>,
+}
+
+/// The tiering state of an `Artifact`.
+pub(crate) struct TieringState {
+    engine: Engine,
+    compile_info: CompileModuleInfo,
+    module_translation: ModuleTranslationState,
+    first_counter: LocalGlobalIndex,
+    slots: TierUpSlots,
+    /// The stubs of the baseline code, that func refs point to.
+    stubs: PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    inner: Mutex<TieringInner>,
+}
+
+impl TieringState {
+    /// Starts watching the functions of a baseline `artifact` compiled
+    /// from `wasm`, whose calls were linked to the `stubs` jumping through
+    /// `slots`.
+    pub(crate) fn start(
+        engine: &Engine,
+        tier_up: &Arc<TierUp>,
+        wasm: &[u8],
+        function_bodies: FunctionBodies,
+        artifact: &Artifact,
+        slots: TierUpSlots,
+        stubs: SectionBodyPtr,
+    ) -> Arc<Self> {
+        let bodies = artifact.finished_function_extents();
+        for (index, extent) in bodies.iter() {
+            slots.redirect(index, extent.ptr);
+        }
+        let stubs = tier_up_stub_extents(engine.target(), stubs, bodies.len());
+
+        let module = artifact.create_module_info();
+        let first_counter = module
+            .local_global_index(HotnessCounters::first_counter(&module))
+            .expect("hotness counters are local globals");
+        let pending = function_bodies
+            .ranges
+            .into_iter()
+            .map(|(index, range)| {
+                let function = PendingFunction {
+                    module_offset: range.start,
+                    body: wasm[range].into(),
+                };
+                (index, function)
+            })
+            .collect();
+        let state = Arc::new(Self {
+            engine: engine.clone(),
+            compile_info: CompileModuleInfo {
+                module,
+                features: artifact.features().clone(),
+                memory_styles: artifact.memory_styles().clone(),
+                table_styles: artifact.table_styles().clone(),
+            },
+            module_translation: function_bodies.module_translation,
+            first_counter,
+            slots,
+            stubs,
+            inner: Mutex::new(TieringInner {
+                pending,
+                ..Default::default()
+            }),
+        });
+        tier_up.watch(&state);
+        state
+    }
+
+    /// Registers a new instance of the artifact, pointing its func refs to
+    /// the tier-up stubs.
+    pub(crate) fn register(self: &Arc<Self>, instance: &mut VMInstance) {
+        let num_local_functions = self.stubs.len();
+        for (index, stub) in self.stubs.iter() {
+            // The instance isn't shared with anyone yet.
+            unsafe { (*instance.local_funcref(index).as_ptr()).func_ptr = *stub.ptr };
+        }
+        let registered = RegisteredInstance {
+            counters: (0..num_local_functions)
+                .map(|index| {
+                    instance
+                        .local_global_ptr(LocalGlobalIndex::new(self.first_counter.index() + index))
+                })
+                .collect(),
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_instance_id;
+        inner.next_instance_id += 1;
+        inner.instances.insert(id, registered);
+        drop(inner);
+
+        instance.push_guard(Box::new(InstanceRegistration {
+            state: self.clone(),
+            id,
+        }));
+    }
+
+    /// Returns the functions that are not optimized yet, and whose
+    /// counters summed across all instances reached `threshold`.
+    fn hot_functions(&self, threshold: u64) -> BTreeSet<LocalFunctionIndex> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .pending
+            .keys()
+            .filter(|index| {
+                let count = inner
+                    .instances
+                    .values()
+                    .map(|instance| instance.counter(**index))
+                    .fold(0u64, u64::saturating_add);
+                count >= threshold
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Compiles the `hot` functions with the optimizing compiler, and
+    /// points their stubs to the new code. Their bodies are dropped, so
+    /// they are never compiled again, even if this fails.
+    fn tier_up(
+        &self,
+        tier_up: &TierUp,
+        hot: &BTreeSet<LocalFunctionIndex>,
+    ) -> Result<(), CompileError> {
+        let target = self.engine.target();
+        let pending = {
+            let mut inner = self.inner.lock().unwrap();
+            hot.iter()
+                .filter_map(|index| Some((*index, inner.pending.remove(index)?)))
+                .collect::<Vec<_>>()
+        };
+
+        // The optimized code shares the globals and imports of the baseline
+        // instances, so it's compiled against the module info transformed
+        // by the baseline middlewares. The middlewares of the optimizing
+        // compiler, which is shared by all the artifacts, only locate what
+        // they added to the module in it.
+        let compiler = tier_up.compiler.lock().unwrap();
+        let mut functions = Vec::with_capacity(pending.len());
+        let mut custom_sections = CustomSections::new();
+        let mut debug: Option<Dwarf> = None;
+        for (index, function) in &pending {
+            let (mut function, sections, function_debug) = compiler.compile_function(
+                target,
+                &self.compile_info,
+                &self.module_translation,
+                *index,
+                &FunctionBodyData {
+                    data: &function.body,
+                    module_offset: function.module_offset,
+                },
+            )?;
+            let first_section = custom_sections.len();
+            for (_, mut section) in sections {
+                section.relocations =
+                    rebase_section_relocations(section.relocations, first_section);
+                custom_sections.push(section);
+            }
+            function.relocations = rebase_section_relocations(function.relocations, first_section);
+            functions.push((*index, function));
+            if let Some(function_debug) = function_debug {
+                let eh_frame = SectionIndex::new(first_section + function_debug.eh_frame.index());
+                match &debug {
+                    Some(debug) => {
+                        let function_eh_frame = custom_sections[eh_frame].clone();
+                        eh_frame::append(
+                            &mut custom_sections[debug.eh_frame],
+                            &function_eh_frame,
+                            target,
+                        );
+                    }
+                    None => debug = Some(Dwarf::new(eh_frame)),
+                }
+            }
+        }
+        drop(compiler);
+
+        let mut custom_section_relocations = custom_sections
+            .values()
+            .map(|section| section.relocations.clone())
+            .collect::<PrimaryMap<SectionIndex, _>>();
+        let libcall_trampolines_section = make_libcall_trampolines(target);
+        custom_section_relocations.push(libcall_trampolines_section.relocations.clone());
+        let libcall_trampolines = custom_sections.push(libcall_trampolines_section);
+        // The optimized code gets stubs of its own, in case the baseline
+        // ones are out of reach of its calls.
+        custom_section_relocations.push(vec![]);
+        let stubs = custom_sections.push(self.slots.make_stubs(target));
+
+        let module = &self.compile_info.module;
+        let mut engine_inner = self.engine.inner_mut();
+        // The functions are allocated in the order of `functions`, which
+        // indexes `allocated_functions`.
+        let (allocated_functions, _, _, allocated_sections) = engine_inner.allocate(
+            module,
+            functions.iter().map(|(_, function)| &function.body),
+            std::iter::empty::<&FunctionBody>(),
+            std::iter::empty::<&FunctionBody>(),
+            custom_sections.values(),
+        )?;
+
+        // Calls from the optimized code go through the stubs as well, so
+        // that they reach the optimized code of functions tiered up later.
+        let call_targets =
+            tier_up_stub_extents(target, allocated_sections[stubs], self.stubs.len());
+        link_module_with_call_targets(
+            module,
+            &allocated_functions,
+            &call_targets,
+            functions
+                .iter()
+                .enumerate()
+                .map(|(position, (_, function))| {
+                    (
+                        LocalFunctionIndex::new(position),
+                        function.relocations.iter(),
+                    )
+                }),
+            &allocated_sections,
+            custom_section_relocations
+                .iter()
+                .map(|(index, relocations)| (index, relocations.iter())),
+            libcall_trampolines,
+            libcall_trampoline_len(target),
+        );
+
+        let eh_frame = debug.as_ref().map(|debug| unsafe {
+            std::slice::from_raw_parts(
+                *allocated_sections[debug.eh_frame],
+                custom_sections[debug.eh_frame].bytes.len(),
+            )
+        });
+        engine_inner.publish_compiled_code();
+        engine_inner.publish_eh_frame(eh_frame)?;
+
+        let optimized = functions
+            .iter()
+            .zip(allocated_functions.values())
+            .map(|((index, function), extent)| (*index, extent, &function.frame_info))
+            .collect::<Vec<_>>();
+        for (index, extent, frame_info) in &optimized {
+            if let Some(registration) =
+                register_function_frame_info(module.clone(), *index, extent, (*frame_info).clone())
+            {
+                engine_inner.register_frame_info(registration);
+            }
+        }
+        if engine_inner.debug_info() {
+            if let Some(debug_image) = register_debug_info(module, optimized.iter().copied()) {
+                engine_inner.register_debug_image(debug_image);
+            }
+        }
+        if let Some(profiler) = engine_inner.profiler() {
+            register_functions(
+                profiler,
+                module,
+                optimized.iter().map(|(index, extent, _)| (*index, *extent)),
+                None,
+            );
+        }
+        let code = engine_inner
+            .take_code_memory()
+            .expect("the optimized code was just allocated");
+        drop(engine_inner);
+
+        let mut inner = self.inner.lock().unwrap();
+        for (index, extent, _) in &optimized {
+            self.slots.redirect(*index, extent.ptr);
+        }
+        inner.code.push(code);
+        Ok(())
+    }
+}
+
+/// Unregisters an instance from its artifact's tiering state when the
+/// instance is dropped, and keeps the optimized code alive until then.
+struct InstanceRegistration {
+    state: Arc<TieringState>,
+    id: usize,
+}
+
+impl Drop for InstanceRegistration {
+    fn drop(&mut self) {
+        self.state.inner.lock().unwrap().instances.remove(&self.id);
+    }
+}
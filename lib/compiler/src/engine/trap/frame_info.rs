@@ -11,9 +11,10 @@
 //! let module: ModuleInfo = ...;
 //! FRAME_INFO.register(module, compiled_functions);
 //! ```
+use super::symbols::ModuleSymbols;
 use std::cmp;
 use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     CompiledFunctionFrameInfo, FrameInfo, LocalFunctionIndex, ModuleInfo, TrapInformation,
@@ -57,6 +58,8 @@ struct ModuleInfoFrameInfo {
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<ModuleInfo>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    /// The debug info of the module, loaded on the first lookup.
+    symbols: OnceLock<Option<ModuleSymbols>>,
 }
 
 impl ModuleInfoFrameInfo {
@@ -64,6 +67,13 @@ impl ModuleInfoFrameInfo {
         self.frame_infos.get(local_index).unwrap()
     }
 
+    /// Gets the debug info of the module, if it has any.
+    fn symbols(&self) -> Option<&ModuleSymbols> {
+        self.symbols
+            .get_or_init(|| ModuleSymbols::load(&self.module))
+            .as_ref()
+    }
+
     /// Gets a function given a pc
     fn function_info(&self, pc: usize) -> Option<&FunctionInfo> {
         let (end, func) = self.functions.range(pc..).next()?;
@@ -86,7 +96,25 @@ impl GlobalFrameInfo {
     ///
     /// Returns an object if this `pc` is known to some previously registered
     /// module, or returns `None` if no information can be found.
+    ///
+    /// The frame is symbolicated with the DWARF debug info of the module,
+    /// when it has some.
     pub fn lookup_frame_info(&self, pc: usize) -> Option<FrameInfo> {
+        let (module, frame) = self.lookup_frame(pc)?;
+        let symbols = match module.symbols() {
+            Some(symbols) => symbols.lookup(frame.module_offset() as u64),
+            None => Vec::new(),
+        };
+        Some(frame.with_symbols(symbols))
+    }
+
+    /// Fetches frame information about a program counter, without looking
+    /// up the debug info of the module, which is costly.
+    pub(crate) fn lookup_frame_info_without_symbols(&self, pc: usize) -> Option<FrameInfo> {
+        self.lookup_frame(pc).map(|(_, frame)| frame)
+    }
+
+    fn lookup_frame(&self, pc: usize) -> Option<(&ModuleInfoFrameInfo, FrameInfo)> {
         let module = self.module_info(pc)?;
         let func = module.function_info(pc)?;
 
@@ -130,13 +158,14 @@ impl GlobalFrameInfo {
             None => instr_map.start_srcloc,
         };
         let func_index = module.module.func_index(func.local_index);
-        Some(FrameInfo::new(
+        let frame = FrameInfo::new(
             module.module.name(),
             func_index.index() as u32,
             module.module.function_names.get(&func_index).cloned(),
             instr_map.start_srcloc,
             instr,
-        ))
+        );
+        Some((module, frame))
     }
 
     /// Fetches trap information about a program counter in a backtrace.
@@ -191,6 +220,32 @@ pub fn register(
     module: Arc<ModuleInfo>,
     finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionExtent>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Option<GlobalFrameInfoRegistration> {
+    register_extents(module, finished_functions.iter(), frame_infos)
+}
+
+/// Registers the frame information of a single function of `module`,
+/// compiled separately from the rest of the module.
+///
+/// The returned object unregisters it when dropped.
+pub fn register_function(
+    module: Arc<ModuleInfo>,
+    index: LocalFunctionIndex,
+    extent: &FunctionExtent,
+    frame_info: CompiledFunctionFrameInfo,
+) -> Option<GlobalFrameInfoRegistration> {
+    let mut frame_infos = PrimaryMap::with_capacity(index.index() + 1);
+    while frame_infos.len() < index.index() {
+        frame_infos.push(CompiledFunctionFrameInfo::default());
+    }
+    frame_infos.push(frame_info);
+    register_extents(module, std::iter::once((index, extent)), frame_infos)
+}
+
+fn register_extents<'a>(
+    module: Arc<ModuleInfo>,
+    finished_functions: impl Iterator<Item = (LocalFunctionIndex, &'a FunctionExtent)>,
+    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
 ) -> Option<GlobalFrameInfoRegistration> {
     let mut min = usize::max_value();
     let mut max = 0;
@@ -201,7 +256,7 @@ pub fn register(
             ptr: start,
             length: len,
         },
-    ) in finished_functions.iter()
+    ) in finished_functions
     {
         let start = **start as usize;
         // end is "last byte" of the function code
@@ -236,6 +291,7 @@ pub fn register(
             functions,
             module,
             frame_infos,
+            symbols: OnceLock::new(),
         },
     );
     assert!(prev.is_none());
@@ -1,6 +1,9 @@
 mod frame_info;
 mod stack;
+mod symbols;
+pub(crate) use frame_info::GlobalFrameInfo;
 pub use frame_info::{
-    register as register_frame_info, FunctionExtent, GlobalFrameInfoRegistration, FRAME_INFO,
+    register as register_frame_info, register_function as register_function_frame_info,
+    FunctionExtent, GlobalFrameInfoRegistration, FRAME_INFO,
 };
 pub use stack::get_trace_and_trapcode;
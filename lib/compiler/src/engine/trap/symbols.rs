@@ -0,0 +1,105 @@
+//! Source-level symbolication of wasm frames, through the DWARF debug info
+//! embedded by the toolchains in the custom sections of the modules.
+
+use addr2line::gimli::{Dwarf, EndianSlice, LittleEndian};
+use self_cell::self_cell;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use wasmer_types::{FrameSymbol, ModuleInfo};
+
+type Context<'a> = Mutex<addr2line::Context<EndianSlice<'a, LittleEndian>>>;
+
+self_cell!(
+    /// A module, with the context reading the debug info in its custom
+    /// sections.
+    struct ModuleContext {
+        owner: Arc<ModuleInfo>,
+
+        #[not_covariant]
+        dependent: Context,
+    }
+);
+
+/// The DWARF debug info of a module.
+pub(super) struct ModuleSymbols {
+    context: ModuleContext,
+    /// The offset of the code section in the module, which DWARF
+    /// addresses are relative to.
+    code_section_offset: u64,
+}
+
+impl ModuleSymbols {
+    /// Loads the debug info of `module`.
+    ///
+    /// Returns `None` if the module doesn't have any, or if it's malformed.
+    pub(super) fn load(module: &Arc<ModuleInfo>) -> Option<Self> {
+        if !module.custom_sections.contains_key(".debug_info") {
+            return None;
+        }
+        let code_section_offset = module.code_section_offset?;
+        let context = ModuleContext::try_new(module.clone(), |module| {
+            let dwarf = Dwarf::load(|id| -> Result<_, ()> {
+                let data = match module.custom_sections.get(id.name()) {
+                    Some(&index) => &module.custom_sections_data[index][..],
+                    None => &[],
+                };
+                Ok(EndianSlice::new(data, LittleEndian))
+            })?;
+            let context = addr2line::Context::from_dwarf(dwarf).map_err(|_| ())?;
+            Ok::<_, ()>(Mutex::new(context))
+        })
+        .ok()?;
+        Some(Self {
+            context,
+            code_section_offset,
+        })
+    }
+
+    /// Looks up the symbols of the instruction at `module_offset`, the
+    /// innermost inlined function first.
+    pub(super) fn lookup(&self, module_offset: u64) -> Vec<FrameSymbol> {
+        let probe = match module_offset.checked_sub(self.code_section_offset) {
+            Some(probe) => probe,
+            None => return Vec::new(),
+        };
+        self.context
+            .with_dependent(|_, context| Self::find_symbols(&context.lock().unwrap(), probe))
+    }
+
+    fn find_symbols(
+        context: &addr2line::Context<EndianSlice<'_, LittleEndian>>,
+        probe: u64,
+    ) -> Vec<FrameSymbol> {
+        let mut frames = match context.find_frames(probe).skip_all_loads() {
+            Ok(frames) => frames,
+            Err(_) => return Vec::new(),
+        };
+        let mut symbols = Vec::new();
+        while let Ok(Some(frame)) = frames.next() {
+            let name = frame
+                .function
+                .as_ref()
+                .and_then(|function| function.raw_name().ok())
+                .map(|name| name.into_owned());
+            let (file, line, column) = match frame.location {
+                Some(location) => (
+                    location.file.map(str::to_string),
+                    location.line,
+                    location.column,
+                ),
+                None => (None, None, None),
+            };
+            symbols.push(FrameSymbol::new(name, file, line, column));
+        }
+        symbols
+    }
+}
+
+impl fmt::Debug for ModuleSymbols {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModuleSymbols")
+            .field("code_section_offset", &self.code_section_offset)
+            .field("module", &self.context.borrow_owner().name())
+            .finish()
+    }
+}
@@ -5,8 +5,10 @@ use wasmer_types::{
     GlobalType, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType,
     ModuleInfo, Pages, PointerWidth, TableIndex, TableType, Target,
 };
-use wasmer_vm::{InternalStoreHandle, MemoryError, StoreObjects};
+use wasmer_vm::{InstanceAllocator, InternalStoreHandle, MemoryError, StoreObjects};
 use wasmer_vm::{MemoryStyle, TableStyle};
+#[cfg(unix)]
+use wasmer_vm::{PoolingAllocator, PoolingLimits};
 use wasmer_vm::{VMConfig, VMGlobal, VMMemory, VMTable};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -51,6 +53,25 @@ pub trait Tunables {
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<VMTable, String>;
 
+    /// Allocate the `Instance` of a module, with its `VMContext`.
+    ///
+    /// Returns the allocation, and the locations of the definitions of
+    /// the local memories and tables in the `VMContext`.
+    #[allow(clippy::result_large_err, clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        Ok(InstanceAllocator::new(module))
+    }
+
     /// Create a global with an unset value.
     fn create_global(&self, ty: GlobalType) -> Result<VMGlobal, String> {
         Ok(VMGlobal::new(ty))
@@ -283,6 +304,119 @@ impl Tunables for BaseTunables {
     }
 }
 
+/// Tunables allocating instances, and their memories and tables, from
+/// a [`PoolingAllocator`].
+///
+/// The pool bounds the number of instances alive at the same time, and
+/// recycles their allocations, which makes instantiation much cheaper
+/// when a module is instantiated over and over (once per request, for
+/// example). Memories and tables created by the host, and shared
+/// memories, are allocated like [`BaseTunables`] does.
+#[cfg(unix)]
+#[derive(Clone)]
+pub struct PoolingTunables {
+    base: BaseTunables,
+    pool: PoolingAllocator,
+}
+
+#[cfg(unix)]
+impl PoolingTunables {
+    /// Create tunables for `target`, reserving a pool with the given `limits`.
+    pub fn new(target: &Target, limits: PoolingLimits) -> Result<Self, String> {
+        Ok(Self {
+            base: BaseTunables::for_target(target),
+            pool: PoolingAllocator::new(limits)?,
+        })
+    }
+
+    /// The pool the instances are allocated from.
+    pub fn pool(&self) -> &PoolingAllocator {
+        &self.pool
+    }
+}
+
+#[cfg(unix)]
+impl Tunables for PoolingTunables {
+    /// Get a `MemoryStyle` for the provided `MemoryType`.
+    ///
+    /// Memories that can't grow beyond a pool slot, or that the pool
+    /// caps, are static, and span the whole slot. Shared memories, which
+    /// aren't allocated from the pool, get the style of [`BaseTunables`].
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        if memory.shared {
+            return self.base.memory_style(memory);
+        }
+        let limits = self.pool.limits();
+        let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
+        if maximum <= limits.memory_pages || limits.cap_memories {
+            MemoryStyle::Static {
+                bound: limits.memory_pages,
+                offset_guard_size: limits.memory_guard_size,
+            }
+        } else {
+            MemoryStyle::Dynamic {
+                offset_guard_size: limits.memory_guard_size,
+            }
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base.create_host_memory(ty, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        if ty.shared {
+            return self
+                .base
+                .create_vm_memory(ty, style, vm_definition_location);
+        }
+        self.pool
+            .allocate_memory(ty, style, Some(vm_definition_location))
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        self.pool
+            .allocate_table(ty, style, Some(vm_definition_location))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        InstanceAllocator::new_pooled(module, &self.pool).map_err(LinkError::Resource)
+    }
+}
+
 impl Tunables for Box<dyn Tunables + Send + Sync> {
     fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
         self.as_ref().memory_style(memory)
@@ -323,6 +457,21 @@ impl Tunables for Box<dyn Tunables + Send + Sync> {
         self.as_ref()
             .create_vm_table(ty, style, vm_definition_location)
     }
+
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.as_ref().allocate_instance(module)
+    }
 }
 
 impl Tunables for std::sync::Arc<dyn Tunables + Send + Sync> {
@@ -365,4 +514,19 @@ impl Tunables for std::sync::Arc<dyn Tunables + Send + Sync> {
         self.as_ref()
             .create_vm_table(ty, style, vm_definition_location)
     }
+
+    #[allow(clippy::type_complexity)]
+    fn allocate_instance(
+        &self,
+        module: &ModuleInfo,
+    ) -> Result<
+        (
+            InstanceAllocator,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        LinkError,
+    > {
+        self.as_ref().allocate_instance(module)
+    }
 }
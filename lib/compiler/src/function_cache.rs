@@ -0,0 +1,622 @@
+//! Caching of compiled functions.
+//!
+//! Whole module caches miss as soon as a single byte of the module
+//! changes. The function cache instead stores each compiled function
+//! under a key derived from its body and signature, the types of the
+//! functions, signatures and globals it refers to, and what all the code
+//! of a module depends on: the compiler and its configuration, the
+//! middlewares, the layout of the `VMContext`, and the memories and
+//! tables. When a module is recompiled after a small change, only the
+//! functions whose key changed are compiled again.
+//!
+//! The key doesn't depend on the position of the function, nor on the
+//! positions of the local functions it calls, which its code only refers
+//! to through relocations. The calls are numbered in the order of the
+//! first call of each function, which the relocations of the cached
+//! entry refer to, so that a function is reused when functions are added
+//! or removed before it.
+
+use crate::artifact_builders::eh_frame;
+use crate::{Compiler, FunctionBodyData};
+use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{
+    CompileModuleInfo, CompiledFunction, CustomSection, CustomSections, Dwarf, FunctionIndex,
+    GlobalIndex, LocalFunctionIndex, ModuleInfo, Relocation, RelocationTarget, SectionIndex,
+    SignatureIndex, SourceLoc, Target,
+};
+use wasmparser::{BlockType, Operator};
+
+/// The key of a compiled function in a [`FunctionCache`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FunctionCacheKey([u8; 32]);
+
+impl FunctionCacheKey {
+    /// The bytes of the key.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for FunctionCacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// A store for compiled functions, shared by all the modules compiled
+/// by an engine.
+///
+/// Entries are opaque bytes. Implementations are free to drop entries
+/// at any time, and failing to store one is not an error.
+pub trait FunctionCache: Send + Sync {
+    /// Loads the entry stored under `key`, if any.
+    fn load(&self, key: &FunctionCacheKey) -> Option<Vec<u8>>;
+
+    /// Stores an entry under `key`.
+    fn store(&self, key: &FunctionCacheKey, entry: &[u8]);
+}
+
+/// A [`FunctionCache`] that keeps its entries in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryFunctionCache {
+    entries: Mutex<HashMap<FunctionCacheKey, Vec<u8>>>,
+}
+
+impl InMemoryFunctionCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of functions in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FunctionCache for InMemoryFunctionCache {
+    fn load(&self, key: &FunctionCacheKey) -> Option<Vec<u8>> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn store(&self, key: &FunctionCacheKey, entry: &[u8]) {
+        self.entries.lock().unwrap().insert(*key, entry.to_vec());
+    }
+}
+
+/// A [`FunctionCache`] that keeps each entry in a file of a directory.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileSystemFunctionCache {
+    path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileSystemFunctionCache {
+    /// Creates a cache in the directory at `path`, creating it if needed.
+    pub fn new<P: Into<std::path::PathBuf>>(path: P) -> std::io::Result<Self> {
+        let path = path.into();
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FunctionCache for FileSystemFunctionCache {
+    fn load(&self, key: &FunctionCacheKey) -> Option<Vec<u8>> {
+        std::fs::read(self.path.join(key.to_string())).ok()
+    }
+
+    fn store(&self, key: &FunctionCacheKey, entry: &[u8]) {
+        // Write to a temporary file first, so that concurrent readers
+        // never see a partial entry.
+        let path = self.path.join(key.to_string());
+        let tmp_path = self
+            .path
+            .join(format!("{}.{}.tmp", key, std::process::id()));
+        if std::fs::write(&tmp_path, entry).is_ok() && std::fs::rename(&tmp_path, path).is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+    }
+}
+
+/// A compiled function, with the custom sections it refers to and its
+/// unwind info.
+///
+/// Source locations are relative to the start of the function body, so
+/// that the entry can be reused when the function moves in the module.
+#[derive(Archive, RkyvSerialize, RkyvDeserialize)]
+#[archive_attr(derive(rkyv::CheckBytes))]
+struct CachedFunction {
+    function: CompiledFunction,
+    custom_sections: Vec<CustomSection>,
+    /// An `eh_frame` section holding only the FDE of the function.
+    eh_frame: Option<CustomSection>,
+}
+
+/// A function loaded from the cache.
+pub(crate) struct LoadedFunction {
+    function: CompiledFunction,
+    custom_sections: CustomSections,
+    eh_frame: Option<CustomSection>,
+}
+
+/// Feeds values to a blake3 hasher through their `Hash` implementation.
+struct KeyHasher(blake3::Hasher);
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let hash = self.0.finalize();
+        u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap())
+    }
+}
+
+/// The functions, signatures and globals that the body of a function
+/// refers to.
+struct References {
+    /// The body, where the local functions called are numbered in the
+    /// order of `callees`, after the imported functions.
+    body: Vec<u8>,
+    /// The function itself, then the local functions it calls in the
+    /// order of their first call.
+    callees: Vec<LocalFunctionIndex>,
+    /// The imported functions called, and the functions referenced by
+    /// `ref.func`, whose indexes are part of the code.
+    functions: BTreeSet<u32>,
+    signatures: BTreeSet<u32>,
+    globals: BTreeSet<u32>,
+}
+
+impl References {
+    /// Returns `None` if the body of the function `index` can't be parsed.
+    fn of(
+        module: &ModuleInfo,
+        index: LocalFunctionIndex,
+        body: &FunctionBodyData<'_>,
+    ) -> Option<Self> {
+        let mut references = Self {
+            body: Vec::with_capacity(body.data.len()),
+            callees: vec![index],
+            functions: BTreeSet::new(),
+            signatures: BTreeSet::new(),
+            globals: BTreeSet::new(),
+        };
+        let mut reader = wasmparser::FunctionBody::new(body.module_offset, body.data)
+            .get_operators_reader()
+            .ok()?;
+        let mut copied = 0;
+        while !reader.eof() {
+            let start = reader.original_position() - body.module_offset;
+            match reader.read().ok()? {
+                Operator::Call { function_index } | Operator::ReturnCall { function_index }
+                    if !module.is_imported_function(FunctionIndex::from_u32(function_index)) =>
+                {
+                    let callee =
+                        module.local_func_index(FunctionIndex::from_u32(function_index))?;
+                    let number = match references.callees.iter().position(|c| *c == callee) {
+                        Some(number) => number,
+                        None => {
+                            references.callees.push(callee);
+                            references.callees.len() - 1
+                        }
+                    };
+                    // The opcode is a single byte, followed by the index.
+                    references
+                        .body
+                        .extend_from_slice(&body.data[copied..=start]);
+                    push_leb128(
+                        &mut references.body,
+                        (module.num_imported_functions + number) as u64,
+                    );
+                    copied = reader.original_position() - body.module_offset;
+                }
+                Operator::Call { function_index }
+                | Operator::ReturnCall { function_index }
+                | Operator::RefFunc { function_index } => {
+                    references.functions.insert(function_index);
+                }
+                Operator::CallIndirect { type_index, .. }
+                | Operator::ReturnCallIndirect { type_index, .. }
+                | Operator::Block {
+                    blockty: BlockType::FuncType(type_index),
+                }
+                | Operator::Loop {
+                    blockty: BlockType::FuncType(type_index),
+                }
+                | Operator::If {
+                    blockty: BlockType::FuncType(type_index),
+                }
+                | Operator::Try {
+                    blockty: BlockType::FuncType(type_index),
+                } => {
+                    references.signatures.insert(type_index);
+                }
+                Operator::GlobalGet { global_index } | Operator::GlobalSet { global_index } => {
+                    references.globals.insert(global_index);
+                }
+                _ => {}
+            }
+        }
+        references.body.extend_from_slice(&body.data[copied..]);
+        Some(references)
+    }
+}
+
+fn push_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// The key of a function, with the local functions that the relocations
+/// of its entry refer to by their number.
+pub(crate) struct FunctionKey {
+    key: FunctionCacheKey,
+    /// The function itself, then the local functions it calls in the
+    /// order of their first call.
+    callees: Vec<LocalFunctionIndex>,
+}
+
+impl FunctionKey {
+    /// Replaces the local functions targeted by `relocations` with their
+    /// number, or returns `None` if one of them isn't called.
+    fn number(&self, relocations: &[Relocation]) -> Option<Vec<Relocation>> {
+        relocations
+            .iter()
+            .cloned()
+            .map(|mut relocation| {
+                if let RelocationTarget::LocalFunc(index) = relocation.reloc_target {
+                    let number = self.callees.iter().position(|c| *c == index)?;
+                    relocation.reloc_target =
+                        RelocationTarget::LocalFunc(LocalFunctionIndex::new(number));
+                }
+                Some(relocation)
+            })
+            .collect()
+    }
+
+    /// Replaces the numbers of the local functions targeted by
+    /// `relocations` with their index, or returns `None` if one of them
+    /// isn't called.
+    fn resolve(&self, relocations: &mut [Relocation]) -> Option<()> {
+        for relocation in relocations {
+            if let RelocationTarget::LocalFunc(number) = relocation.reloc_target {
+                relocation.reloc_target =
+                    RelocationTarget::LocalFunc(*self.callees.get(number.index())?);
+            }
+        }
+        Some(())
+    }
+}
+
+/// The function cache, as seen while compiling a module.
+pub(crate) struct ModuleFunctionCache<'a> {
+    cache: &'a dyn FunctionCache,
+    /// The module as translated, which the function bodies refer to.
+    translated_module: &'a ModuleInfo,
+    module_key: blake3::Hash,
+}
+
+impl<'a> ModuleFunctionCache<'a> {
+    /// Returns `None` if `compiler` or one of its middlewares doesn't
+    /// support caching.
+    ///
+    /// `translated_module` is the module before the middlewares
+    /// transformed it into the one of `compile_info`. The function bodies
+    /// refer to its functions, which the middlewares may have shifted by
+    /// importing functions.
+    pub(crate) fn new(
+        cache: &'a dyn FunctionCache,
+        compiler: &dyn Compiler,
+        target: &Target,
+        compile_info: &'a CompileModuleInfo,
+        translated_module: &'a ModuleInfo,
+    ) -> Option<Self> {
+        let module = &*compile_info.module;
+        let mut hasher = KeyHasher(blake3::Hasher::new());
+        crate::VERSION.hash(&mut hasher);
+        compiler.fingerprint()?.hash(&mut hasher);
+        for middleware in compiler.get_middlewares() {
+            middleware.cache_key()?.hash(&mut hasher);
+        }
+        target.triple().to_string().hash(&mut hasher);
+        target.cpu_features().as_u64().hash(&mut hasher);
+        compile_info.features.hash(&mut hasher);
+        // The layout of the `VMContext`, which all the functions access.
+        for count in [
+            module.signatures.len(),
+            module.num_imported_functions,
+            module.num_imported_tables,
+            module.num_imported_memories,
+            module.num_imported_globals,
+            module.tables.len(),
+            module.memories.len(),
+            module.globals.len(),
+        ] {
+            count.hash(&mut hasher);
+        }
+        // Memories and tables are used by most functions, through too many
+        // operators to be worth tracking.
+        module.memories.values().for_each(|ty| ty.hash(&mut hasher));
+        compile_info
+            .memory_styles
+            .values()
+            .for_each(|style| style.hash(&mut hasher));
+        module.tables.values().for_each(|ty| ty.hash(&mut hasher));
+        compile_info
+            .table_styles
+            .values()
+            .for_each(|style| style.hash(&mut hasher));
+        Some(Self {
+            cache,
+            translated_module,
+            module_key: hasher.0.finalize(),
+        })
+    }
+
+    /// The key of the function `index`, or `None` if its body can't be
+    /// parsed.
+    pub(crate) fn key(
+        &self,
+        index: LocalFunctionIndex,
+        body: &FunctionBodyData<'_>,
+    ) -> Option<FunctionKey> {
+        let module = self.translated_module;
+        let signature = |index: SignatureIndex| module.signatures.get(index);
+        let function_signature = |index: FunctionIndex| signature(*module.functions.get(index)?);
+
+        let references = References::of(module, index, body)?;
+        let mut hasher = KeyHasher(blake3::Hasher::new());
+        hasher.write(self.module_key.as_bytes());
+        references.body.hash(&mut hasher);
+        // The signature of the function itself comes first.
+        for callee in &references.callees {
+            function_signature(module.func_index(*callee))?.hash(&mut hasher);
+        }
+        for function in references.functions {
+            function.hash(&mut hasher);
+            function_signature(FunctionIndex::from_u32(function))?.hash(&mut hasher);
+        }
+        for index in references.signatures {
+            index.hash(&mut hasher);
+            signature(SignatureIndex::from_u32(index))?.hash(&mut hasher);
+        }
+        for global in references.globals {
+            global.hash(&mut hasher);
+            module
+                .globals
+                .get(GlobalIndex::from_u32(global))?
+                .hash(&mut hasher);
+        }
+        Some(FunctionKey {
+            key: FunctionCacheKey(*hasher.0.finalize().as_bytes()),
+            callees: references.callees,
+        })
+    }
+
+    /// Loads the function stored under `key`, for a body starting at
+    /// `module_offset`. Relocations to custom sections refer to the
+    /// returned sections.
+    pub(crate) fn load(&self, key: &FunctionKey, module_offset: usize) -> Option<LoadedFunction> {
+        let entry = self.cache.load(&key.key)?;
+        let mut bytes = rkyv::AlignedVec::with_capacity(entry.len());
+        bytes.extend_from_slice(&entry);
+        let mut cached = rkyv::from_bytes::<CachedFunction>(&bytes).ok()?;
+        let mut function = cached.function;
+        key.resolve(&mut function.relocations)?;
+        for section in cached
+            .custom_sections
+            .iter_mut()
+            .chain(cached.eh_frame.as_mut())
+        {
+            key.resolve(&mut section.relocations)?;
+        }
+        rebase_source_locations(&mut function, module_offset, u32::wrapping_add);
+        Some(LoadedFunction {
+            function,
+            custom_sections: cached.custom_sections.into_iter().collect(),
+            eh_frame: cached.eh_frame,
+        })
+    }
+
+    /// Stores the function `index` of a compilation under `key`, with
+    /// its unwind info if it has any.
+    ///
+    /// Functions whose custom sections refer to other functions are not
+    /// stored, as their sections can't be extracted from the compilation,
+    /// nor the functions that refer to local functions they don't call.
+    pub(crate) fn store(
+        &self,
+        key: &FunctionKey,
+        module_offset: usize,
+        index: LocalFunctionIndex,
+        function: &CompiledFunction,
+        custom_sections: &CustomSections,
+        eh_frame: Option<&CustomSection>,
+    ) {
+        let sections = match referenced_sections(index, function, custom_sections) {
+            Some(sections) => sections,
+            None => return,
+        };
+        let renumbered = sections
+            .iter()
+            .enumerate()
+            .map(|(new, old)| (*old, SectionIndex::new(new)))
+            .collect::<BTreeMap<_, _>>();
+        let renumber = |relocations: &[Relocation]| {
+            let mut relocations = key.number(relocations)?;
+            for relocation in &mut relocations {
+                if let RelocationTarget::CustomSection(old) = relocation.reloc_target {
+                    relocation.reloc_target = RelocationTarget::CustomSection(renumbered[&old]);
+                }
+            }
+            Some(relocations)
+        };
+
+        let mut function = function.clone();
+        function.relocations = match renumber(&function.relocations) {
+            Some(relocations) => relocations,
+            None => return,
+        };
+        rebase_source_locations(&mut function, module_offset, u32::wrapping_sub);
+        let custom_sections = sections
+            .iter()
+            .map(|index| {
+                let mut section = custom_sections[*index].clone();
+                section.relocations = renumber(&section.relocations)?;
+                Some(section)
+            })
+            .collect::<Option<Vec<_>>>();
+        let custom_sections = match custom_sections {
+            Some(custom_sections) => custom_sections,
+            None => return,
+        };
+        let eh_frame = match eh_frame {
+            Some(eh_frame) => match key.number(&eh_frame.relocations) {
+                Some(relocations) => Some(CustomSection {
+                    relocations,
+                    ..eh_frame.clone()
+                }),
+                None => return,
+            },
+            None => None,
+        };
+
+        let cached = CachedFunction {
+            function,
+            custom_sections,
+            eh_frame,
+        };
+        if let Ok(bytes) = rkyv::to_bytes::<_, 4096>(&cached) {
+            self.cache.store(&key.key, &bytes);
+        }
+    }
+}
+
+/// Returns the custom sections that `function` refers to, directly or
+/// through other sections, or `None` if one of them refers to another
+/// function.
+fn referenced_sections(
+    index: LocalFunctionIndex,
+    function: &CompiledFunction,
+    custom_sections: &CustomSections,
+) -> Option<BTreeSet<SectionIndex>> {
+    let mut sections = BTreeSet::new();
+    let mut pending = function
+        .relocations
+        .iter()
+        .map(|relocation| (relocation.reloc_target, false))
+        .collect::<Vec<_>>();
+    while let Some((target, from_section)) = pending.pop() {
+        match target {
+            RelocationTarget::CustomSection(section) => {
+                if sections.insert(section) {
+                    pending.extend(
+                        custom_sections[section]
+                            .relocations
+                            .iter()
+                            .map(|relocation| (relocation.reloc_target, true)),
+                    );
+                }
+            }
+            RelocationTarget::LocalFunc(other) if from_section && other != index => return None,
+            _ => {}
+        }
+    }
+    Some(sections)
+}
+
+fn rebase_source_locations(
+    function: &mut CompiledFunction,
+    module_offset: usize,
+    op: fn(u32, u32) -> u32,
+) {
+    let rebase = |loc: &mut SourceLoc| {
+        if !loc.is_default() {
+            *loc = SourceLoc::new(op(loc.bits(), module_offset as u32));
+        }
+    };
+    let address_map = &mut function.frame_info.address_map;
+    address_map
+        .instructions
+        .iter_mut()
+        .for_each(|instruction| rebase(&mut instruction.srcloc));
+    rebase(&mut address_map.start_srcloc);
+    rebase(&mut address_map.end_srcloc);
+}
+
+/// Replaces functions of a compilation by cached ones, appending the
+/// custom sections they refer to, and their unwind info to the
+/// `eh_frame` section of the compilation.
+pub(crate) fn splice_cached_functions(
+    functions: &mut PrimaryMap<LocalFunctionIndex, CompiledFunction>,
+    custom_sections: &mut CustomSections,
+    debug: &mut Option<Dwarf>,
+    target: &Target,
+    cached: BTreeMap<LocalFunctionIndex, LoadedFunction>,
+) {
+    for (index, loaded) in cached {
+        let mut function = loaded.function;
+        let first_section = custom_sections.len();
+        for (_, mut section) in loaded.custom_sections {
+            section.relocations = rebase_section_relocations(section.relocations, first_section);
+            custom_sections.push(section);
+        }
+        function.relocations = rebase_section_relocations(function.relocations, first_section);
+        functions[index] = function;
+        if let Some(function_eh_frame) = loaded.eh_frame {
+            match debug {
+                Some(debug) => {
+                    eh_frame::append(
+                        &mut custom_sections[debug.eh_frame],
+                        &function_eh_frame,
+                        target,
+                    );
+                }
+                None => *debug = Some(Dwarf::new(custom_sections.push(function_eh_frame))),
+            }
+        }
+    }
+}
+
+/// Shifts the custom section targets of `relocations` by `first_section`,
+/// for sections appended to another compilation.
+pub(crate) fn rebase_section_relocations(
+    relocations: Vec<Relocation>,
+    first_section: usize,
+) -> Vec<Relocation> {
+    relocations
+        .into_iter()
+        .map(|mut relocation| {
+            if let RelocationTarget::CustomSection(index) = relocation.reloc_target {
+                relocation.reloc_target = RelocationTarget::CustomSection(SectionIndex::new(
+                    first_section + index.index(),
+                ));
+            }
+            relocation
+        })
+        .collect()
+}
@@ -60,6 +60,14 @@ pub use self::artifact_builders::*;
 #[cfg(feature = "translator")]
 mod compiler;
 
+#[cfg(feature = "compiler")]
+mod function_cache;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::function_cache::FileSystemFunctionCache;
+#[cfg(feature = "compiler")]
+pub use crate::function_cache::{FunctionCache, FunctionCacheKey, InMemoryFunctionCache};
+
 #[cfg(feature = "translator")]
 #[macro_use]
 mod translator;
@@ -318,6 +318,11 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_code_section_offset(&mut self, offset: u64) -> WasmResult<()> {
+        self.module.code_section_offset = Some(offset);
+        Ok(())
+    }
+
     pub(crate) fn reserve_table_initializers(&mut self, num: u32) -> WasmResult<()> {
         self.module
             .table_initializers
@@ -23,8 +23,33 @@ pub trait ModuleMiddleware: Debug + Send + Sync {
         local_function_index: LocalFunctionIndex,
     ) -> Box<dyn FunctionMiddleware>;
 
+    /// Generates a `FunctionMiddleware` for a function of the given module,
+    /// whose `ModuleInfo` was already transformed by the middleware chain.
+    ///
+    /// Middlewares that refer to what they added to the module (e.g. their
+    /// globals) override this, rather than keeping the `ModuleInfo` around,
+    /// since several modules may be compiled at the same time.
+    fn generate_function_middleware_for_module(
+        &self,
+        _module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        self.generate_function_middleware(local_function_index)
+    }
+
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
+
+    /// Returns a key identifying this middleware and its configuration.
+    ///
+    /// It is part of the keys of the function cache, so it must change
+    /// whenever the instrumentation of a function could. The keys don't
+    /// cover the index of the function, so middlewares instrumenting
+    /// functions depending on it must return `None`, which disables the
+    /// function cache.
+    fn cache_key(&self) -> Option<String> {
+        None
+    }
 }
 
 /// A function middleware specialized for a single function.
@@ -68,6 +93,16 @@ pub trait ModuleMiddlewareChain {
         local_function_index: LocalFunctionIndex,
     ) -> Vec<Box<dyn FunctionMiddleware>>;
 
+    /// Generates a function middleware chain for a function of the given
+    /// module.
+    fn generate_function_middleware_chain_for_module(
+        &self,
+        _module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>> {
+        self.generate_function_middleware_chain(local_function_index)
+    }
+
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
 }
@@ -83,6 +118,18 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
             .collect()
     }
 
+    /// Generates a function middleware chain for a function of the given
+    /// module.
+    fn generate_function_middleware_chain_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Vec<Box<dyn FunctionMiddleware>> {
+        self.iter()
+            .map(|x| x.generate_function_middleware_for_module(module_info, local_function_index))
+            .collect()
+    }
+
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo) {
         for item in self {
@@ -83,6 +83,15 @@ pub enum JournalEntryRecordType {
     SocketSetOptTimeV1 = 57,
     SocketShutdownV1 = 58,
     SnapshotV1 = 59,
+    ReceiveFileDescriptorV1 = 60,
+    SocketBindUnixV1 = 61,
+    SocketConnectedUnixV1 = 62,
+    SocketPairV1 = 63,
+    CreatePtyV1 = 64,
+    OpenPtySlaveV1 = 65,
+    CreateTimerFdV1 = 66,
+    SetTimerFdV1 = 67,
+    CreateSignalFdV1 = 68,
 }
 
 impl JournalEntryRecordType {
@@ -145,6 +154,11 @@ impl JournalEntryRecordType {
                     JournalEntryDuplicateFileDescriptorV1,
                 >(data))
             }
+            JournalEntryRecordType::ReceiveFileDescriptorV1 => {
+                ArchivedJournalEntry::ReceiveFileDescriptorV1(rkyv::archived_root::<
+                    JournalEntryReceiveFileDescriptorV1,
+                >(data))
+            }
             JournalEntryRecordType::CreateDirectoryV1 => {
                 ArchivedJournalEntry::CreateDirectoryV1(rkyv::archived_root::<
                     JournalEntryCreateDirectoryV1,
@@ -223,6 +237,27 @@ impl JournalEntryRecordType {
             JournalEntryRecordType::CreatePipeV1 => ArchivedJournalEntry::CreatePipeV1(
                 rkyv::archived_root::<JournalEntryCreatePipeV1>(data),
             ),
+            JournalEntryRecordType::CreatePtyV1 => ArchivedJournalEntry::CreatePtyV1(
+                rkyv::archived_root::<JournalEntryCreatePtyV1>(data),
+            ),
+            JournalEntryRecordType::OpenPtySlaveV1 => {
+                ArchivedJournalEntry::OpenPtySlaveV1(rkyv::archived_root::<
+                    JournalEntryOpenPtySlaveV1,
+                >(data))
+            }
+            JournalEntryRecordType::CreateTimerFdV1 => {
+                ArchivedJournalEntry::CreateTimerFdV1(rkyv::archived_root::<
+                    JournalEntryCreateTimerFdV1,
+                >(data))
+            }
+            JournalEntryRecordType::SetTimerFdV1 => ArchivedJournalEntry::SetTimerFdV1(
+                rkyv::archived_root::<JournalEntrySetTimerFdV1>(data),
+            ),
+            JournalEntryRecordType::CreateSignalFdV1 => {
+                ArchivedJournalEntry::CreateSignalFdV1(rkyv::archived_root::<
+                    JournalEntryCreateSignalFdV1,
+                >(data))
+            }
             JournalEntryRecordType::CreateEventV1 => ArchivedJournalEntry::CreateEventV1(
                 rkyv::archived_root::<JournalEntryCreateEventV1>(data),
             ),
@@ -272,6 +307,19 @@ impl JournalEntryRecordType {
                     JournalEntrySocketConnectedV1,
                 >(data))
             }
+            JournalEntryRecordType::SocketBindUnixV1 => {
+                ArchivedJournalEntry::SocketBindUnixV1(rkyv::archived_root::<
+                    JournalEntrySocketBindUnixV1,
+                >(data))
+            }
+            JournalEntryRecordType::SocketConnectedUnixV1 => {
+                ArchivedJournalEntry::SocketConnectedUnixV1(rkyv::archived_root::<
+                    JournalEntrySocketConnectedUnixV1,
+                >(data))
+            }
+            JournalEntryRecordType::SocketPairV1 => ArchivedJournalEntry::SocketPairV1(
+                rkyv::archived_root::<JournalEntrySocketPairV1>(data),
+            ),
             JournalEntryRecordType::SocketAcceptedV1 => {
                 ArchivedJournalEntry::SocketAcceptedV1(rkyv::archived_root::<
                     JournalEntrySocketAcceptedV1,
@@ -357,6 +405,7 @@ impl<'a> JournalEntry<'a> {
             Self::DuplicateFileDescriptorV1 { .. } => {
                 JournalEntryRecordType::DuplicateFileDescriptorV1
             }
+            Self::ReceiveFileDescriptorV1 { .. } => JournalEntryRecordType::ReceiveFileDescriptorV1,
             Self::CreateDirectoryV1 { .. } => JournalEntryRecordType::CreateDirectoryV1,
             Self::RemoveDirectoryV1 { .. } => JournalEntryRecordType::RemoveDirectoryV1,
             Self::PathSetTimesV1 { .. } => JournalEntryRecordType::PathSetTimesV1,
@@ -383,6 +432,11 @@ impl<'a> JournalEntry<'a> {
             Self::EpollCtlV1 { .. } => JournalEntryRecordType::EpollCtlV1,
             Self::TtySetV1 { .. } => JournalEntryRecordType::TtySetV1,
             Self::CreatePipeV1 { .. } => JournalEntryRecordType::CreatePipeV1,
+            Self::CreatePtyV1 { .. } => JournalEntryRecordType::CreatePtyV1,
+            Self::OpenPtySlaveV1 { .. } => JournalEntryRecordType::OpenPtySlaveV1,
+            Self::CreateTimerFdV1 { .. } => JournalEntryRecordType::CreateTimerFdV1,
+            Self::SetTimerFdV1 { .. } => JournalEntryRecordType::SetTimerFdV1,
+            Self::CreateSignalFdV1 { .. } => JournalEntryRecordType::CreateSignalFdV1,
             Self::CreateEventV1 { .. } => JournalEntryRecordType::CreateEventV1,
             Self::PortAddAddrV1 { .. } => JournalEntryRecordType::PortAddAddrV1,
             Self::PortDelAddrV1 { .. } => JournalEntryRecordType::PortDelAddrV1,
@@ -398,6 +452,9 @@ impl<'a> JournalEntry<'a> {
             Self::SocketListenV1 { .. } => JournalEntryRecordType::SocketListenV1,
             Self::SocketBindV1 { .. } => JournalEntryRecordType::SocketBindV1,
             Self::SocketConnectedV1 { .. } => JournalEntryRecordType::SocketConnectedV1,
+            Self::SocketBindUnixV1 { .. } => JournalEntryRecordType::SocketBindUnixV1,
+            Self::SocketConnectedUnixV1 { .. } => JournalEntryRecordType::SocketConnectedUnixV1,
+            Self::SocketPairV1 { .. } => JournalEntryRecordType::SocketPairV1,
             Self::SocketAcceptedV1 { .. } => JournalEntryRecordType::SocketAcceptedV1,
             Self::SocketJoinIpv4MulticastV1 { .. } => {
                 JournalEntryRecordType::SocketJoinIpv4MulticastV1
@@ -532,6 +589,17 @@ impl<'a> JournalEntry<'a> {
                 original_fd,
                 copied_fd,
             }),
+            JournalEntry::ReceiveFileDescriptorV1 {
+                fd,
+                fs_rights_base,
+                fs_rights_inheriting,
+                fs_flags,
+            } => serializer.serialize_value(&JournalEntryReceiveFileDescriptorV1 {
+                fd,
+                fs_rights_base: fs_rights_base.bits(),
+                fs_rights_inheriting: fs_rights_inheriting.bits(),
+                fs_flags: fs_flags.bits(),
+            }),
             JournalEntry::CreateDirectoryV1 { fd, path } => {
                 serializer.serialize_value(&JournalEntryCreateDirectoryV1 {
                     fd,
@@ -683,6 +751,39 @@ impl<'a> JournalEntry<'a> {
             JournalEntry::CreatePipeV1 { fd1, fd2 } => {
                 serializer.serialize_value(&JournalEntryCreatePipeV1 { fd1, fd2 })
             }
+            JournalEntry::CreatePtyV1 { master, slave } => {
+                serializer.serialize_value(&JournalEntryCreatePtyV1 { master, slave })
+            }
+            JournalEntry::OpenPtySlaveV1 { master, slave } => {
+                serializer.serialize_value(&JournalEntryOpenPtySlaveV1 { master, slave })
+            }
+            JournalEntry::CreateTimerFdV1 {
+                fd,
+                clock_id,
+                flags,
+            } => serializer.serialize_value(&JournalEntryCreateTimerFdV1 {
+                fd,
+                clock_id: clock_id.into(),
+                flags: flags.bits(),
+            }),
+            JournalEntry::SetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            } => serializer.serialize_value(&JournalEntrySetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            }),
+            JournalEntry::CreateSignalFdV1 { fd, mask, flags } => {
+                serializer.serialize_value(&JournalEntryCreateSignalFdV1 {
+                    fd,
+                    mask,
+                    flags: flags.bits(),
+                })
+            }
             JournalEntry::CreateEventV1 {
                 initial_val,
                 flags,
@@ -746,6 +847,27 @@ impl<'a> JournalEntry<'a> {
             JournalEntry::SocketConnectedV1 { fd, addr } => {
                 serializer.serialize_value(&JournalEntrySocketConnectedV1 { fd, addr })
             }
+            JournalEntry::SocketBindUnixV1 { fd, path } => {
+                serializer.serialize_value(&JournalEntrySocketBindUnixV1 {
+                    fd,
+                    _padding: padding(path.as_bytes().len()),
+                    path: path.into_owned(),
+                })
+            }
+            JournalEntry::SocketConnectedUnixV1 { fd, path } => {
+                serializer.serialize_value(&JournalEntrySocketConnectedUnixV1 {
+                    fd,
+                    _padding: padding(path.as_bytes().len()),
+                    path: path.into_owned(),
+                })
+            }
+            JournalEntry::SocketPairV1 { ty, fd1, fd2 } => {
+                serializer.serialize_value(&JournalEntrySocketPairV1 {
+                    ty: ty.into(),
+                    fd1,
+                    fd2,
+                })
+            }
             JournalEntry::SocketAcceptedV1 {
                 listen_fd,
                 fd,
@@ -899,6 +1021,7 @@ pub enum ArchivedJournalEntry<'a> {
     CloseFileDescriptorV1(&'a ArchivedJournalEntryCloseFileDescriptorV1),
     RenumberFileDescriptorV1(&'a ArchivedJournalEntryRenumberFileDescriptorV1),
     DuplicateFileDescriptorV1(&'a ArchivedJournalEntryDuplicateFileDescriptorV1),
+    ReceiveFileDescriptorV1(&'a ArchivedJournalEntryReceiveFileDescriptorV1),
     CreateDirectoryV1(&'a ArchivedJournalEntryCreateDirectoryV1),
     RemoveDirectoryV1(&'a ArchivedJournalEntryRemoveDirectoryV1),
     PathSetTimesV1(&'a ArchivedJournalEntryPathSetTimesV1),
@@ -917,6 +1040,11 @@ pub enum ArchivedJournalEntry<'a> {
     EpollCtlV1(&'a ArchivedJournalEntryEpollCtlV1),
     TtySetV1(&'a ArchivedJournalEntryTtySetV1),
     CreatePipeV1(&'a ArchivedJournalEntryCreatePipeV1),
+    CreatePtyV1(&'a ArchivedJournalEntryCreatePtyV1),
+    OpenPtySlaveV1(&'a ArchivedJournalEntryOpenPtySlaveV1),
+    CreateTimerFdV1(&'a ArchivedJournalEntryCreateTimerFdV1),
+    SetTimerFdV1(&'a ArchivedJournalEntrySetTimerFdV1),
+    CreateSignalFdV1(&'a ArchivedJournalEntryCreateSignalFdV1),
     CreateEventV1(&'a ArchivedJournalEntryCreateEventV1),
     PortAddAddrV1(&'a ArchivedJournalEntryPortAddAddrV1),
     PortDelAddrV1(&'a ArchivedJournalEntryPortDelAddrV1),
@@ -931,6 +1059,9 @@ pub enum ArchivedJournalEntry<'a> {
     SocketOpenV1(&'a ArchivedJournalEntrySocketOpenV1),
     SocketListenV1(&'a ArchivedJournalEntrySocketListenV1),
     SocketBindV1(&'a ArchivedJournalEntrySocketBindV1),
+    SocketBindUnixV1(&'a ArchivedJournalEntrySocketBindUnixV1),
+    SocketConnectedUnixV1(&'a ArchivedJournalEntrySocketConnectedUnixV1),
+    SocketPairV1(&'a ArchivedJournalEntrySocketPairV1),
     SocketConnectedV1(&'a ArchivedJournalEntrySocketConnectedV1),
     SocketAcceptedV1(&'a ArchivedJournalEntrySocketAcceptedV1),
     SocketJoinIpv4MulticastV1(&'a ArchivedJournalEntrySocketJoinIpv4MulticastV1),
@@ -1071,6 +1202,17 @@ pub struct JournalEntryDuplicateFileDescriptorV1 {
     pub copied_fd: u32,
 }
 
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntryReceiveFileDescriptorV1 {
+    pub fd: u32,
+    pub fs_rights_base: u64,
+    pub fs_rights_inheriting: u64,
+    pub fs_flags: u16,
+}
+
 #[repr(C)]
 #[repr(align(8))]
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
@@ -1265,6 +1407,55 @@ pub struct JournalEntryCreatePipeV1 {
     pub fd2: u32,
 }
 
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntryCreatePtyV1 {
+    pub master: u32,
+    pub slave: u32,
+}
+
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntryOpenPtySlaveV1 {
+    pub master: u32,
+    pub slave: u32,
+}
+
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntryCreateTimerFdV1 {
+    pub fd: u32,
+    pub clock_id: JournalSnapshot0ClockidV1,
+    pub flags: u16,
+}
+
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntrySetTimerFdV1 {
+    pub fd: u32,
+    pub value: u64,
+    pub interval: u64,
+    pub absolute: bool,
+}
+
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntryCreateSignalFdV1 {
+    pub fd: u32,
+    pub mask: u64,
+    pub flags: u16,
+}
+
 #[repr(C)]
 #[repr(align(8))]
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
@@ -1367,6 +1558,36 @@ pub struct JournalEntrySocketConnectedV1 {
     pub addr: SocketAddr,
 }
 
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntrySocketBindUnixV1 {
+    pub fd: u32,
+    pub path: String,
+    pub _padding: Vec<u8>,
+}
+
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntrySocketConnectedUnixV1 {
+    pub fd: u32,
+    pub path: String,
+    pub _padding: Vec<u8>,
+}
+
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct JournalEntrySocketPairV1 {
+    pub ty: JournalSocktypeV1,
+    pub fd1: u32,
+    pub fd2: u32,
+}
+
 #[repr(C)]
 #[repr(align(8))]
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
@@ -726,6 +726,19 @@ impl<'a> TryFrom<ArchivedJournalEntry<'a>> for JournalEntry<'a> {
                 original_fd: *old_fd,
                 copied_fd: *new_fd,
             },
+            ArchivedJournalEntry::ReceiveFileDescriptorV1(
+                ArchivedJournalEntryReceiveFileDescriptorV1 {
+                    fd,
+                    fs_rights_base,
+                    fs_rights_inheriting,
+                    fs_flags,
+                },
+            ) => Self::ReceiveFileDescriptorV1 {
+                fd: *fd,
+                fs_rights_base: wasi::Rights::from_bits_truncate(*fs_rights_base),
+                fs_rights_inheriting: wasi::Rights::from_bits_truncate(*fs_rights_inheriting),
+                fs_flags: wasi::Fdflags::from_bits_truncate(*fs_flags),
+            },
             ArchivedJournalEntry::CreateDirectoryV1(ArchivedJournalEntryCreateDirectoryV1 {
                 fd,
                 path,
@@ -883,6 +896,49 @@ impl<'a> TryFrom<ArchivedJournalEntry<'a>> for JournalEntry<'a> {
                     fd2: *fd2,
                 }
             }
+            ArchivedJournalEntry::CreatePtyV1(ArchivedJournalEntryCreatePtyV1 {
+                master,
+                slave,
+            }) => Self::CreatePtyV1 {
+                master: *master,
+                slave: *slave,
+            },
+            ArchivedJournalEntry::OpenPtySlaveV1(ArchivedJournalEntryOpenPtySlaveV1 {
+                master,
+                slave,
+            }) => Self::OpenPtySlaveV1 {
+                master: *master,
+                slave: *slave,
+            },
+            ArchivedJournalEntry::CreateTimerFdV1(ArchivedJournalEntryCreateTimerFdV1 {
+                fd,
+                ref clock_id,
+                flags,
+            }) => Self::CreateTimerFdV1 {
+                fd: *fd,
+                clock_id: clock_id.into(),
+                flags: wasi::Fdflags::from_bits_truncate(*flags),
+            },
+            ArchivedJournalEntry::SetTimerFdV1(ArchivedJournalEntrySetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            }) => Self::SetTimerFdV1 {
+                fd: *fd,
+                value: *value,
+                interval: *interval,
+                absolute: *absolute,
+            },
+            ArchivedJournalEntry::CreateSignalFdV1(ArchivedJournalEntryCreateSignalFdV1 {
+                fd,
+                mask,
+                flags,
+            }) => Self::CreateSignalFdV1 {
+                fd: *fd,
+                mask: *mask,
+                flags: wasi::Fdflags::from_bits_truncate(*flags),
+            },
             ArchivedJournalEntry::PortAddAddrV1(ArchivedJournalEntryPortAddAddrV1 { cidr }) => {
                 Self::PortAddAddrV1 {
                     cidr: JournalIpCidrV1 {
@@ -952,6 +1008,33 @@ impl<'a> TryFrom<ArchivedJournalEntry<'a>> for JournalEntry<'a> {
                 fd: *fd,
                 backlog: *backlog,
             },
+            ArchivedJournalEntry::SocketBindUnixV1(ArchivedJournalEntrySocketBindUnixV1 {
+                fd,
+                path,
+                _padding: _,
+            }) => Self::SocketBindUnixV1 {
+                fd: *fd,
+                path: path.as_ref().into(),
+            },
+            ArchivedJournalEntry::SocketConnectedUnixV1(
+                ArchivedJournalEntrySocketConnectedUnixV1 {
+                    fd,
+                    path,
+                    _padding: _,
+                },
+            ) => Self::SocketConnectedUnixV1 {
+                fd: *fd,
+                path: path.as_ref().into(),
+            },
+            ArchivedJournalEntry::SocketPairV1(ArchivedJournalEntrySocketPairV1 {
+                ref ty,
+                fd1,
+                fd2,
+            }) => Self::SocketPairV1 {
+                ty: ty.into(),
+                fd1: *fd1,
+                fd2: *fd2,
+            },
             ArchivedJournalEntry::SocketBindV1(ArchivedJournalEntrySocketBindV1 { fd, addr }) => {
                 Self::SocketBindV1 {
                     fd: *fd,
@@ -247,6 +247,7 @@ impl WritableJournal for FilteredJournalTx {
             | JournalEntry::DuplicateFileDescriptorV1 {
                 original_fd: fd, ..
             }
+            | JournalEntry::ReceiveFileDescriptorV1 { fd, .. }
             | JournalEntry::FileDescriptorSetFlagsV1 { fd, .. }
             | JournalEntry::FileDescriptorAdviseV1 { fd, .. }
             | JournalEntry::FileDescriptorAllocateV1 { fd, .. }
@@ -270,6 +271,11 @@ impl WritableJournal for FilteredJournalTx {
             | JournalEntry::CreateSymbolicLinkV1 { .. }
             | JournalEntry::ChangeDirectoryV1 { .. }
             | JournalEntry::CreatePipeV1 { .. }
+            | JournalEntry::CreatePtyV1 { .. }
+            | JournalEntry::OpenPtySlaveV1 { .. }
+            | JournalEntry::CreateTimerFdV1 { .. }
+            | JournalEntry::SetTimerFdV1 { .. }
+            | JournalEntry::CreateSignalFdV1 { .. }
             | JournalEntry::CreateEventV1 { .. } => {
                 if self.config.filter_fs {
                     return Ok(0);
@@ -296,6 +302,9 @@ impl WritableJournal for FilteredJournalTx {
             | JournalEntry::SocketListenV1 { .. }
             | JournalEntry::SocketBindV1 { .. }
             | JournalEntry::SocketConnectedV1 { .. }
+            | JournalEntry::SocketBindUnixV1 { .. }
+            | JournalEntry::SocketConnectedUnixV1 { .. }
+            | JournalEntry::SocketPairV1 { .. }
             | JournalEntry::SocketAcceptedV1 { .. }
             | JournalEntry::SocketJoinIpv4MulticastV1 { .. }
             | JournalEntry::SocketJoinIpv6MulticastV1 { .. }
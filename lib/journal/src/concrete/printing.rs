@@ -136,6 +136,9 @@ impl<'a> fmt::Display for JournalEntry<'a> {
                 "fd-duplicate (original={}, copied={})",
                 original_fd, copied_fd
             ),
+            JournalEntry::ReceiveFileDescriptorV1 { fd, .. } => {
+                write!(f, "fd-receive (fd={})", fd)
+            }
             JournalEntry::CreateDirectoryV1 { path, .. } => {
                 write!(f, "path-create-dir (path={})", path)
             }
@@ -210,6 +213,28 @@ impl<'a> fmt::Display for JournalEntry<'a> {
             JournalEntry::CreatePipeV1 { fd1, fd2 } => {
                 write!(f, "fd-pipe (fd1={}, fd2={})", fd1, fd2)
             }
+            JournalEntry::CreatePtyV1 { master, slave } => {
+                write!(f, "pty-open (master={}, slave={})", master, slave)
+            }
+            JournalEntry::OpenPtySlaveV1 { master, slave } => {
+                write!(f, "pty-open-slave (master={}, slave={})", master, slave)
+            }
+            JournalEntry::CreateTimerFdV1 { fd, clock_id, .. } => {
+                write!(f, "fd-timer (fd={}, clock={:?})", fd, clock_id)
+            }
+            JournalEntry::SetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            } => write!(
+                f,
+                "fd-timer-set (fd={}, value={}, interval={}, absolute={})",
+                fd, value, interval, absolute
+            ),
+            JournalEntry::CreateSignalFdV1 { fd, mask, .. } => {
+                write!(f, "fd-signal (fd={}, mask={:#x})", fd, mask)
+            }
             JournalEntry::CreateEventV1 {
                 initial_val, fd, ..
             } => write!(f, "fd-event (fd={}, initial={})", fd, initial_val),
@@ -249,6 +274,15 @@ impl<'a> fmt::Display for JournalEntry<'a> {
             JournalEntry::SocketConnectedV1 { fd, addr } => {
                 write!(f, "sock-connect (fd={}, addr={})", fd, addr)
             }
+            JournalEntry::SocketBindUnixV1 { fd, path } => {
+                write!(f, "sock-bind-unix (fd={}, path={})", fd, path)
+            }
+            JournalEntry::SocketConnectedUnixV1 { fd, path } => {
+                write!(f, "sock-connect-unix (fd={}, path={})", fd, path)
+            }
+            JournalEntry::SocketPairV1 { ty, fd1, fd2 } => {
+                write!(f, "sock-pair (ty={:?}, fd1={}, fd2={})", ty, fd1, fd2)
+            }
             JournalEntry::SocketAcceptedV1 {
                 listen_fd,
                 fd,
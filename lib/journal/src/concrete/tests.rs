@@ -160,6 +160,17 @@ pub fn test_record_duplicate_file_descriptor() {
     });
 }
 
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_receive_file_descriptor() {
+    run_test(JournalEntry::ReceiveFileDescriptorV1 {
+        fd: 4356345u32,
+        fs_rights_base: wasi::Rights::all(),
+        fs_rights_inheriting: wasi::Rights::FD_READ,
+        fs_flags: wasi::Fdflags::NONBLOCK,
+    });
+}
+
 #[tracing_test::traced_test]
 #[test]
 pub fn test_record_create_directory() {
@@ -352,6 +363,55 @@ pub fn test_record_create_pipe() {
     });
 }
 
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_create_pty() {
+    run_test(JournalEntry::CreatePtyV1 {
+        master: 23452,
+        slave: 23453,
+    });
+}
+
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_open_pty_slave() {
+    run_test(JournalEntry::OpenPtySlaveV1 {
+        master: 23452,
+        slave: 87634,
+    });
+}
+
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_create_timer_fd() {
+    run_test(JournalEntry::CreateTimerFdV1 {
+        fd: 5463,
+        clock_id: wasi::Snapshot0Clockid::Monotonic,
+        flags: wasi::Fdflags::NONBLOCK,
+    });
+}
+
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_set_timer_fd() {
+    run_test(JournalEntry::SetTimerFdV1 {
+        fd: 5463,
+        value: 1_000_000,
+        interval: 250_000,
+        absolute: true,
+    });
+}
+
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_create_signal_fd() {
+    run_test(JournalEntry::CreateSignalFdV1 {
+        fd: 4356,
+        mask: 0x4002,
+        flags: wasi::Fdflags::empty(),
+    });
+}
+
 #[tracing_test::traced_test]
 #[test]
 pub fn test_record_create_event() {
@@ -485,6 +545,34 @@ pub fn test_record_socket_connected() {
     });
 }
 
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_socket_bind_unix() {
+    run_test(JournalEntry::SocketBindUnixV1 {
+        fd: 2341234,
+        path: "/tmp/server.sock".into(),
+    });
+}
+
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_socket_connected_unix() {
+    run_test(JournalEntry::SocketConnectedUnixV1 {
+        fd: 12341,
+        path: "/tmp/server.sock".into(),
+    });
+}
+
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_socket_pair() {
+    run_test(JournalEntry::SocketPairV1 {
+        ty: wasi::Socktype::Dgram,
+        fd1: 345,
+        fd2: 346,
+    });
+}
+
 #[tracing_test::traced_test]
 #[test]
 pub fn test_record_socket_accepted() {
@@ -640,6 +728,10 @@ pub fn test_record_alignment() {
         std::mem::align_of::<JournalEntryDuplicateFileDescriptorV1>(),
         8
     );
+    assert_eq!(
+        std::mem::align_of::<JournalEntryReceiveFileDescriptorV1>(),
+        8
+    );
     assert_eq!(std::mem::align_of::<JournalEntryCreateDirectoryV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryRemoveDirectoryV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryPathSetTimesV1>(), 8);
@@ -676,6 +768,11 @@ pub fn test_record_alignment() {
     assert_eq!(std::mem::align_of::<JournalEntryEpollCtlV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryTtySetV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryCreatePipeV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntryCreatePtyV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntryOpenPtySlaveV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntryCreateTimerFdV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntrySetTimerFdV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntryCreateSignalFdV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryCreateEventV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryPortAddAddrV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryPortDelAddrV1>(), 8);
@@ -687,6 +784,9 @@ pub fn test_record_alignment() {
     assert_eq!(std::mem::align_of::<JournalEntrySocketListenV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketBindV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketConnectedV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntrySocketBindUnixV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntrySocketConnectedUnixV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntrySocketPairV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntrySocketAcceptedV1>(), 8);
     assert_eq!(
         std::mem::align_of::<JournalEntrySocketJoinIpv4MulticastV1>(),
@@ -151,6 +151,14 @@ pub enum JournalEntry<'a> {
         original_fd: Fd,
         copied_fd: Fd,
     },
+    /// Descriptor that was passed to the process by another process, its
+    /// file belongs to the other process
+    ReceiveFileDescriptorV1 {
+        fd: Fd,
+        fs_rights_base: Rights,
+        fs_rights_inheriting: Rights,
+        fs_flags: Fdflags,
+    },
     CreateDirectoryV1 {
         fd: Fd,
         path: Cow<'a, str>,
@@ -239,6 +247,35 @@ pub enum JournalEntry<'a> {
         fd1: Fd,
         fd2: Fd,
     },
+    /// Pseudo-terminal that was allocated along with both of its ends
+    CreatePtyV1 {
+        master: Fd,
+        slave: Fd,
+    },
+    /// Slave end of a pseudo-terminal that was opened from its master end
+    OpenPtySlaveV1 {
+        master: Fd,
+        slave: Fd,
+    },
+    CreateTimerFdV1 {
+        fd: Fd,
+        clock_id: Snapshot0Clockid,
+        flags: Fdflags,
+    },
+    /// Timer descriptor that was armed (or disarmed with a zero `value`)
+    SetTimerFdV1 {
+        fd: Fd,
+        value: Timestamp,
+        interval: Timestamp,
+        absolute: bool,
+    },
+    /// Signal descriptor that was created, or whose signals were changed
+    /// when it already exists
+    CreateSignalFdV1 {
+        fd: Fd,
+        mask: u64,
+        flags: Fdflags,
+    },
     CreateEventV1 {
         initial_val: u64,
         flags: EventFdFlags,
@@ -289,6 +326,21 @@ pub enum JournalEntry<'a> {
         fd: Fd,
         addr: SocketAddr,
     },
+    SocketBindUnixV1 {
+        fd: Fd,
+        path: Cow<'a, str>,
+    },
+    /// UNIX socket that was connected to the socket bound to a path, like
+    /// TCP connections the connection itself is not restored
+    SocketConnectedUnixV1 {
+        fd: Fd,
+        path: Cow<'a, str>,
+    },
+    SocketPairV1 {
+        ty: Socktype,
+        fd1: Fd,
+        fd2: Fd,
+    },
     SocketAcceptedV1 {
         listen_fd: Fd,
         fd: Fd,
@@ -435,6 +487,17 @@ impl<'a> JournalEntry<'a> {
                 original_fd,
                 copied_fd,
             },
+            Self::ReceiveFileDescriptorV1 {
+                fd,
+                fs_rights_base,
+                fs_rights_inheriting,
+                fs_flags,
+            } => JournalEntry::ReceiveFileDescriptorV1 {
+                fd,
+                fs_rights_base,
+                fs_rights_inheriting,
+                fs_flags,
+            },
             Self::CreateDirectoryV1 { fd, path } => JournalEntry::CreateDirectoryV1 {
                 fd,
                 path: path.into_owned().into(),
@@ -552,6 +615,33 @@ impl<'a> JournalEntry<'a> {
             },
             Self::TtySetV1 { tty, line_feeds } => JournalEntry::TtySetV1 { tty, line_feeds },
             Self::CreatePipeV1 { fd1, fd2 } => JournalEntry::CreatePipeV1 { fd1, fd2 },
+            Self::CreatePtyV1 { master, slave } => JournalEntry::CreatePtyV1 { master, slave },
+            Self::OpenPtySlaveV1 { master, slave } => {
+                JournalEntry::OpenPtySlaveV1 { master, slave }
+            }
+            Self::CreateTimerFdV1 {
+                fd,
+                clock_id,
+                flags,
+            } => JournalEntry::CreateTimerFdV1 {
+                fd,
+                clock_id,
+                flags,
+            },
+            Self::SetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            } => JournalEntry::SetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            },
+            Self::CreateSignalFdV1 { fd, mask, flags } => {
+                JournalEntry::CreateSignalFdV1 { fd, mask, flags }
+            }
             Self::CreateEventV1 {
                 initial_val,
                 flags,
@@ -593,6 +683,15 @@ impl<'a> JournalEntry<'a> {
             Self::SocketListenV1 { fd, backlog } => JournalEntry::SocketListenV1 { fd, backlog },
             Self::SocketBindV1 { fd, addr } => JournalEntry::SocketBindV1 { fd, addr },
             Self::SocketConnectedV1 { fd, addr } => JournalEntry::SocketConnectedV1 { fd, addr },
+            Self::SocketBindUnixV1 { fd, path } => JournalEntry::SocketBindUnixV1 {
+                fd,
+                path: path.into_owned().into(),
+            },
+            Self::SocketConnectedUnixV1 { fd, path } => JournalEntry::SocketConnectedUnixV1 {
+                fd,
+                path: path.into_owned().into(),
+            },
+            Self::SocketPairV1 { ty, fd1, fd2 } => JournalEntry::SocketPairV1 { ty, fd1, fd2 },
             Self::SocketAcceptedV1 {
                 listen_fd,
                 fd,
@@ -711,6 +810,7 @@ impl<'a> JournalEntry<'a> {
             JournalEntry::OpenFileDescriptorV1 { path, .. } => base_size + path.as_bytes().len(),
             JournalEntry::RenumberFileDescriptorV1 { .. } => base_size,
             JournalEntry::DuplicateFileDescriptorV1 { .. } => base_size,
+            JournalEntry::ReceiveFileDescriptorV1 { .. } => base_size,
             JournalEntry::CreateDirectoryV1 { path, .. } => base_size + path.as_bytes().len(),
             JournalEntry::RemoveDirectoryV1 { path, .. } => base_size + path.as_bytes().len(),
             JournalEntry::PathSetTimesV1 { path, .. } => base_size + path.as_bytes().len(),
@@ -735,6 +835,11 @@ impl<'a> JournalEntry<'a> {
             JournalEntry::EpollCtlV1 { .. } => base_size,
             JournalEntry::TtySetV1 { .. } => base_size,
             JournalEntry::CreatePipeV1 { .. } => base_size,
+            JournalEntry::CreatePtyV1 { .. } => base_size,
+            JournalEntry::OpenPtySlaveV1 { .. } => base_size,
+            JournalEntry::CreateTimerFdV1 { .. } => base_size,
+            JournalEntry::SetTimerFdV1 { .. } => base_size,
+            JournalEntry::CreateSignalFdV1 { .. } => base_size,
             JournalEntry::CreateEventV1 { .. } => base_size,
             JournalEntry::PortAddAddrV1 { .. } => base_size,
             JournalEntry::PortDelAddrV1 { .. } => base_size,
@@ -752,6 +857,9 @@ impl<'a> JournalEntry<'a> {
             JournalEntry::SocketListenV1 { .. } => base_size,
             JournalEntry::SocketBindV1 { .. } => base_size,
             JournalEntry::SocketConnectedV1 { .. } => base_size,
+            JournalEntry::SocketBindUnixV1 { path, .. } => base_size + path.as_bytes().len(),
+            JournalEntry::SocketConnectedUnixV1 { path, .. } => base_size + path.as_bytes().len(),
+            JournalEntry::SocketPairV1 { .. } => base_size,
             JournalEntry::SocketAcceptedV1 { .. } => base_size,
             JournalEntry::SocketJoinIpv4MulticastV1 { .. } => base_size,
             JournalEntry::SocketJoinIpv6MulticastV1 { .. } => base_size,
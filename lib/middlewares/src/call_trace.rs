@@ -0,0 +1,1168 @@
+//! `call_trace` is a middleware for tracing the calls of the functions
+//! of a WebAssembly module. It calls host functions, imported by the
+//! module, on the entry and exit of the traced functions, with their
+//! arguments and results, and optionally on indirect calls.
+//!
+//! This makes it possible to follow the logic of a module without
+//! rebuilding it with logging.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::{Arc, Mutex};
+//! use wasmer::sys::EngineBuilder;
+//! use wasmer::{wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store, Value};
+//! use wasmer_middlewares::call_trace::{CallEvent, CallEventKind, CallTrace};
+//!
+//! let wasm = wat2wasm(
+//!     br#"(module
+//!         (func $double (export "double") (param i32) (result i32)
+//!             (i32.mul (local.get 0) (i32.const 2))))"#,
+//! )
+//! .unwrap();
+//!
+//! let call_trace = Arc::new(CallTrace::new());
+//! let mut compiler_config = Cranelift::default();
+//! compiler_config.push_middleware(call_trace.clone());
+//! let mut store = Store::new(EngineBuilder::new(compiler_config));
+//! let module = Module::new(&store, &wasm).unwrap();
+//!
+//! let events = Arc::new(Mutex::new(vec![]));
+//! let imports = {
+//!     let events = events.clone();
+//!     call_trace.imports(&mut store, move |event: &CallEvent| {
+//!         events.lock().unwrap().push(event.clone());
+//!     })
+//! };
+//! let instance = Instance::new(&mut store, &module, &imports).unwrap();
+//! let double = instance.exports.get_function("double").unwrap();
+//! double.call(&mut store, &[Value::I32(21)]).unwrap();
+//!
+//! let events = events.lock().unwrap();
+//! assert_eq!(events[0].kind, CallEventKind::Entry);
+//! assert_eq!(events[0].values, [Value::I32(21)]);
+//! assert_eq!(events[1].kind, CallEventKind::Exit);
+//! assert_eq!(events[1].values, [Value::I32(42)]);
+//! ```
+
+use crate::hooks;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{BlockType, Operator};
+use wasmer::{
+    AsStoreMut, ExportIndex, Function, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    Imports, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, Module, ModuleMiddleware,
+    Mutability, RuntimeError, Type, Value,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, GlobalIndex, ImportIndex, ModuleInfo, V128};
+
+/// The namespace of the imported hooks.
+const HOOK_NAMESPACE: &str = "wasmer_call_trace";
+
+/// The name of the exported global locating the scratch globals, which is
+/// the first of them.
+const SCRATCH_GLOBAL: &str = "wasmer_call_trace_scratch";
+
+/// The kind of a [`CallEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallEventKind {
+    /// A traced function is entered. The values are its arguments.
+    Entry,
+    /// A traced function returns. The values are its results.
+    Exit,
+    /// A traced function makes an indirect call. The value is the index
+    /// of the called element of the table.
+    IndirectCall,
+}
+
+/// An event of a traced function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEvent {
+    /// The kind of event.
+    pub kind: CallEventKind,
+    /// The index of the traced function in the original module.
+    pub function: FunctionIndex,
+    /// The values of the event, depending on its kind.
+    pub values: Vec<Value>,
+}
+
+impl CallEventKind {
+    fn from_i32(kind: i32) -> Option<Self> {
+        match kind {
+            0 => Some(Self::Entry),
+            1 => Some(Self::Exit),
+            2 => Some(Self::IndirectCall),
+            _ => None,
+        }
+    }
+
+    fn as_i32(self) -> i32 {
+        match self {
+            Self::Entry => 0,
+            Self::Exit => 1,
+            Self::IndirectCall => 2,
+        }
+    }
+}
+
+type Filter = dyn Fn(FunctionIndex, Option<&str>) -> bool + Send + Sync;
+
+/// The module-level call tracing middleware.
+///
+/// The middleware adds the host functions it calls as imports of the
+/// module, after its own imported functions. They must be provided at
+/// instantiation by the imports of [`CallTrace::imports`].
+///
+/// The events report the indexes of the functions in the original
+/// module. The indexes of the local functions in the compiled module,
+/// as seen in traps for example, are shifted by the added imports.
+///
+/// Exits through a trap aren't reported. The tail calls of the traced
+/// functions are made as regular calls followed by a return, so that
+/// their exit can be reported.
+///
+/// The hooks are called with the index of each traced function, so the
+/// function cache of an engine is disabled for the modules instrumented
+/// by `CallTrace`.
+///
+/// # Panic
+///
+/// An instance of `CallTrace` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// indexes of the hooks. Attempts to use a `CallTrace` instance from
+/// multiple modules will result in a panic. The optimizing compiler of a
+/// tiered engine only locates the hooks and globals added to a module by
+/// the baseline compiler's `CallTrace`, so its `CallTrace` can be shared.
+///
+/// # Example
+///
+/// See the [module documentation][self].
+pub struct CallTrace {
+    /// Whether a function is traced, given its index and name.
+    filter: Box<Filter>,
+
+    /// Whether indirect calls are reported.
+    indirect_calls: bool,
+
+    /// The layout of the instrumentation in the module.
+    state: Mutex<Option<Arc<CallTraceState>>>,
+
+    /// The layout of the instrumentation of the last module that was
+    /// transformed by another `CallTrace`, keyed by the id of the module.
+    located: Mutex<Option<(String, Arc<CallTraceState>)>>,
+}
+
+/// The instrumentation of a module.
+#[derive(Debug)]
+struct CallTraceState {
+    /// The types of the imported hooks, starting at `first_hook`.
+    hooks: Vec<FunctionType>,
+
+    /// The function index of the first hook.
+    first_hook: usize,
+
+    /// The instrumentation of every local function, while compiling the
+    /// module.
+    functions: PrimaryMap<LocalFunctionIndex, TracedFunction>,
+
+    /// The names of the functions of the original module.
+    function_names: HashMap<FunctionIndex, String>,
+}
+
+/// The instrumentation of a local function.
+#[derive(Debug)]
+struct TracedFunction {
+    /// The index of the function in the original module.
+    index: u32,
+
+    /// Whether the function is traced.
+    traced: bool,
+
+    /// The number of parameters of the function.
+    num_params: u32,
+
+    /// The hooks called on entry, on exit and on indirect calls.
+    entry_hook: u32,
+    exit_hook: u32,
+    indirect_call_hook: Option<u32>,
+
+    /// The scratch globals holding the results of the function while
+    /// they are passed to the exit hook.
+    result_globals: Vec<u32>,
+
+    /// The scratch global holding the called element of indirect calls.
+    element_global: u32,
+
+    /// The scratch global holding the condition or the index of the
+    /// conditional branches, while checking if they leave the function.
+    branch_global: u32,
+}
+
+/// The function-level call tracing middleware.
+pub struct FunctionCallTrace {
+    state: Arc<CallTraceState>,
+
+    local_function_index: LocalFunctionIndex,
+
+    /// Whether the entry of the function was instrumented.
+    entered: bool,
+
+    /// The number of open blocks, including the body of the function.
+    depth: u32,
+}
+
+impl CallTrace {
+    /// Creates a `CallTrace` middleware tracing every function.
+    pub fn new() -> Self {
+        Self {
+            filter: Box::new(|_, _| true),
+            indirect_calls: false,
+            state: Mutex::new(None),
+            located: Mutex::new(None),
+        }
+    }
+
+    /// Only traces the functions for which `filter` returns `true`,
+    /// given their index and name.
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(FunctionIndex, Option<&str>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Box::new(filter);
+        self
+    }
+
+    /// Reports the indirect calls made by the traced functions.
+    pub fn with_indirect_calls(mut self, enable: bool) -> Self {
+        self.indirect_calls = enable;
+        self
+    }
+
+    /// Returns the name of a function of the module, given its index in
+    /// the original module.
+    ///
+    /// # Panic
+    ///
+    /// The module must have been compiled with this middleware.
+    pub fn function_name(&self, index: FunctionIndex) -> Option<String> {
+        self.state().function_names.get(&index).cloned()
+    }
+
+    /// Prepares the middleware for a module compiled with an equivalent
+    /// `CallTrace` instead of this one, for example when it was loaded
+    /// from a cache, so that its imports and function names are known.
+    ///
+    /// # Panic
+    ///
+    /// Like when compiling a module, a `CallTrace` can only be used for a
+    /// single module.
+    pub fn use_compiled_module(&self, module: &Module) -> Result<(), MiddlewareError> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("CallTrace::use_compiled_module: Attempting to use a `CallTrace` middleware from multiple modules.");
+        }
+
+        let info = module.info();
+        let (first_hook, hooks) = locate_hooks(info)?;
+
+        let shift = hooks.len();
+        let function_names = info
+            .function_names
+            .iter()
+            .filter_map(|(index, name)| {
+                let index = index.index();
+                if index < first_hook {
+                    Some((FunctionIndex::new(index), name.clone()))
+                } else if index >= first_hook + shift {
+                    Some((FunctionIndex::new(index - shift), name.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        *state = Some(Arc::new(CallTraceState {
+            hooks,
+            first_hook,
+            functions: PrimaryMap::new(),
+            function_names,
+        }));
+        Ok(())
+    }
+
+    /// Creates the host functions called by the module, which pass its
+    /// events to `handler`.
+    ///
+    /// The returned imports must be added to the imports of the module.
+    ///
+    /// # Panic
+    ///
+    /// The module must have been compiled with this middleware.
+    pub fn imports(
+        &self,
+        store: &mut impl AsStoreMut,
+        handler: impl Fn(&CallEvent) + Send + Sync + 'static,
+    ) -> Imports {
+        let handler = Arc::new(handler);
+        let mut imports = Imports::new();
+        for (index, ty) in self.state().hooks.iter().enumerate() {
+            let handler = handler.clone();
+            let hook = Function::new(store, ty, move |args: &[Value]| {
+                let (kind, function) = match args {
+                    [Value::I32(kind), Value::I32(function), ..] => (*kind, *function),
+                    _ => return Err(RuntimeError::new("invalid call trace event")),
+                };
+                let kind = CallEventKind::from_i32(kind)
+                    .ok_or_else(|| RuntimeError::new("invalid call trace event"))?;
+                handler(&CallEvent {
+                    kind,
+                    function: FunctionIndex::new(function as usize),
+                    values: args[2..].to_vec(),
+                });
+                Ok(vec![])
+            });
+            imports.define(HOOK_NAMESPACE, &hook_name(index), hook);
+        }
+        imports
+    }
+
+    fn state(&self) -> Arc<CallTraceState> {
+        self.state
+            .lock()
+            .unwrap()
+            .clone()
+            .expect("CallTrace: the module wasn't compiled with this middleware")
+    }
+
+    /// Plans the hooks and the scratch globals of the instrumentation of
+    /// the local `functions` of a module, given with their index in the
+    /// original module, their signature and their name.
+    ///
+    /// The hooks are imported at `first_hook`, and the returned types of
+    /// the scratch globals are appended at `first_scratch`.
+    fn plan(
+        &self,
+        first_hook: usize,
+        first_scratch: u32,
+        functions: &[(FunctionIndex, &FunctionType, Option<&str>)],
+    ) -> (
+        Vec<FunctionType>,
+        PrimaryMap<LocalFunctionIndex, TracedFunction>,
+        Vec<Type>,
+    ) {
+        let mut hooks = vec![];
+        let mut hook_index = |params: Vec<Type>| {
+            let ty = FunctionType::new(params, []);
+            let index = match hooks.iter().position(|hook| *hook == ty) {
+                Some(index) => index,
+                None => {
+                    hooks.push(ty);
+                    hooks.len() - 1
+                }
+            };
+            (first_hook + index) as u32
+        };
+
+        // Plan the hooks and scratch globals of the traced functions.
+        let mut traced_functions = PrimaryMap::new();
+        let mut scratch_types: HashMap<Type, usize> = HashMap::new();
+        for (index, ty, name) in functions {
+            let traced = (self.filter)(*index, *name);
+            let event_params = |values: &[Type]| {
+                let mut params = vec![Type::I32, Type::I32];
+                params.extend_from_slice(values);
+                params
+            };
+            let (entry_hook, exit_hook, indirect_call_hook) = if traced {
+                // The results, and the condition of the branches out of the
+                // function.
+                let mut counts: HashMap<Type, usize> = HashMap::new();
+                for result in ty.results() {
+                    *counts.entry(*result).or_insert(0) += 1;
+                }
+                *counts.entry(Type::I32).or_insert(0) += 1;
+                for (ty, count) in counts {
+                    let scratch = scratch_types.entry(ty).or_insert(0);
+                    *scratch = (*scratch).max(count);
+                }
+                let indirect_call_hook = if self.indirect_calls {
+                    let scratch = scratch_types.entry(Type::I32).or_insert(0);
+                    *scratch = (*scratch).max(1);
+                    Some(hook_index(event_params(&[Type::I32])))
+                } else {
+                    None
+                };
+                (
+                    hook_index(event_params(ty.params())),
+                    hook_index(event_params(ty.results())),
+                    indirect_call_hook,
+                )
+            } else {
+                (0, 0, None)
+            };
+            traced_functions.push(TracedFunction {
+                index: index.as_u32(),
+                traced,
+                num_params: ty.params().len() as u32,
+                entry_hook,
+                exit_hook,
+                indirect_call_hook,
+                result_globals: Vec::new(),
+                element_global: 0,
+                branch_global: 0,
+            });
+        }
+
+        // Lay out the scratch globals.
+        let mut scratch_globals: HashMap<Type, Vec<u32>> = HashMap::new();
+        let mut globals = vec![];
+        let mut types = scratch_types.into_iter().collect::<Vec<_>>();
+        types.sort_by_key(|(ty, _)| *ty as u8);
+        for (ty, count) in types {
+            for _ in 0..count {
+                scratch_globals
+                    .entry(ty)
+                    .or_default()
+                    .push(first_scratch + globals.len() as u32);
+                globals.push(ty);
+            }
+        }
+        for ((_, ty, _), function) in functions.iter().zip(traced_functions.values_mut()) {
+            if !function.traced {
+                continue;
+            }
+            let mut used: HashMap<Type, usize> = HashMap::new();
+            function.result_globals = ty
+                .results()
+                .iter()
+                .map(|ty| {
+                    let used = used.entry(*ty).or_insert(0);
+                    *used += 1;
+                    scratch_globals[ty][*used - 1]
+                })
+                .collect();
+            function.branch_global =
+                scratch_globals[&Type::I32][used.get(&Type::I32).copied().unwrap_or(0)];
+            if function.indirect_call_hook.is_some() {
+                function.element_global = scratch_globals[&Type::I32][0];
+            }
+        }
+
+        (hooks, traced_functions, globals)
+    }
+
+    /// Locates the instrumentation of a module that was transformed by
+    /// another `CallTrace`, configured like this one.
+    fn locate(&self, module_info: &ModuleInfo) -> Option<Arc<CallTraceState>> {
+        let mut located = self.located.lock().unwrap();
+        let id = module_info.id.id();
+        if let Some((located_id, state)) = &*located {
+            if *located_id == id {
+                return Some(state.clone());
+            }
+        }
+
+        let (first_hook, hooks) = locate_hooks(module_info).ok()?;
+        let first_scratch = match module_info.exports.get(SCRATCH_GLOBAL) {
+            Some(ExportIndex::Global(index)) => Some(index.as_u32()),
+            _ => None,
+        };
+        // The local functions were shifted by the hooks.
+        let local_functions = module_info
+            .functions
+            .iter()
+            .skip(module_info.num_imported_functions)
+            .enumerate()
+            .map(|(offset, (index, signature))| {
+                let name = module_info.function_names.get(&index).map(String::as_str);
+                (
+                    FunctionIndex::new(first_hook + offset),
+                    &module_info.signatures[*signature],
+                    name,
+                )
+            })
+            .collect::<Vec<_>>();
+        let (planned_hooks, functions, scratch_globals) =
+            self.plan(first_hook, first_scratch.unwrap_or(0), &local_functions);
+        if planned_hooks != hooks || (first_scratch.is_none() && !scratch_globals.is_empty()) {
+            return None;
+        }
+
+        let state = Arc::new(CallTraceState {
+            hooks,
+            first_hook,
+            functions,
+            // Only the names of the module transformed by this middleware
+            // are returned by `function_name`.
+            function_names: HashMap::new(),
+        });
+        *located = Some((id, state.clone()));
+        Some(state)
+    }
+}
+
+impl Default for CallTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CallTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallTrace")
+            .field("filter", &"<function>")
+            .field("indirect_calls", &self.indirect_calls)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for CallTrace {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionCallTrace {
+            state: self.state(),
+            local_function_index,
+            entered: false,
+            depth: 1,
+        })
+    }
+
+    /// Generates a `FunctionCallTrace` for a function of a module that a
+    /// `CallTrace` middleware, not necessarily this one, transformed.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let num_local_functions = module_info.functions.len() - module_info.num_imported_functions;
+        let state = match self.state.lock().unwrap().clone() {
+            Some(state) if state.functions.len() == num_local_functions => Some(state),
+            _ => self.locate(module_info),
+        };
+        match state {
+            Some(state) => Box::new(FunctionCallTrace {
+                state,
+                local_function_index,
+                entered: false,
+                depth: 1,
+            }),
+            None => Box::new(hooks::Uninstrumented("call_trace")),
+        }
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("CallTrace::transform_module_info: Attempting to use a `CallTrace` middleware from multiple modules.");
+        }
+
+        let first_hook = module_info.num_imported_functions;
+        let first_scratch = module_info.globals.len() as u32;
+        let local_functions = module_info
+            .functions
+            .iter()
+            .skip(first_hook)
+            .map(|(index, signature)| {
+                let name = module_info.function_names.get(&index).map(String::as_str);
+                (index, &module_info.signatures[*signature], name)
+            })
+            .collect::<Vec<_>>();
+        let (hooks, functions, scratch_globals) =
+            self.plan(first_hook, first_scratch, &local_functions);
+
+        // Append the scratch globals, exporting the first one so that they
+        // can be located in the transformed module.
+        if !scratch_globals.is_empty() {
+            module_info.exports.insert(
+                SCRATCH_GLOBAL.to_string(),
+                ExportIndex::Global(GlobalIndex::from_u32(first_scratch)),
+            );
+        }
+        for ty in scratch_globals {
+            module_info
+                .globals
+                .push(GlobalType::new(ty, Mutability::Var));
+            module_info.global_initializers.push(zero(ty));
+        }
+
+        // Import the hooks after the imported functions, which shifts the
+        // local functions.
+        let function_names = module_info.function_names.clone();
+        let hook_imports = hooks
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| (hook_name(index), ty.clone()))
+            .collect::<Vec<_>>();
+        hooks::import_functions(module_info, HOOK_NAMESPACE, &hook_imports);
+
+        *state = Some(Arc::new(CallTraceState {
+            hooks,
+            first_hook,
+            functions,
+            function_names,
+        }));
+    }
+
+    /// The hooks report the index of each function, so the instrumented
+    /// functions can't be cached.
+    fn cache_key(&self) -> Option<String> {
+        None
+    }
+}
+
+impl fmt::Debug for FunctionCallTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCallTrace")
+            .field("local_function_index", &self.local_function_index)
+            .field("entered", &self.entered)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl FunctionCallTrace {
+    /// Calls the exit hook with the results on top of the stack, leaving
+    /// them in place.
+    fn exit(&self, function: &TracedFunction, state: &mut MiddlewareReaderState<'_>) {
+        save_results(function, state);
+        restore_results(function, state);
+        call_exit_hook(function, state);
+    }
+
+    /// Calls the exit hook before a conditional branch, with the results
+    /// below its condition or index, if `leaves` computes a non-zero
+    /// condition from the condition or index in the branch global.
+    fn exit_if<'a>(
+        &self,
+        function: &TracedFunction,
+        leaves: &[Operator<'a>],
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let global_index = function.branch_global;
+        state.push_operator(Operator::GlobalSet { global_index });
+        save_results(function, state);
+        state.extend(leaves);
+        state.push_operator(Operator::If {
+            blockty: BlockType::Empty,
+        });
+        call_exit_hook(function, state);
+        state.push_operator(Operator::End);
+        restore_results(function, state);
+        state.push_operator(Operator::GlobalGet { global_index });
+    }
+}
+
+/// Moves the results on top of the stack to the scratch globals.
+fn save_results(function: &TracedFunction, state: &mut MiddlewareReaderState<'_>) {
+    for global_index in function.result_globals.iter().rev() {
+        state.push_operator(Operator::GlobalSet {
+            global_index: *global_index,
+        });
+    }
+}
+
+/// Pushes the results saved in the scratch globals back on the stack.
+fn restore_results(function: &TracedFunction, state: &mut MiddlewareReaderState<'_>) {
+    for global_index in &function.result_globals {
+        state.push_operator(Operator::GlobalGet {
+            global_index: *global_index,
+        });
+    }
+}
+
+/// Calls the exit hook with the results saved in the scratch globals.
+fn call_exit_hook(function: &TracedFunction, state: &mut MiddlewareReaderState<'_>) {
+    state.extend(&[
+        Operator::I32Const {
+            value: CallEventKind::Exit.as_i32(),
+        },
+        Operator::I32Const {
+            value: function.index as i32,
+        },
+    ]);
+    restore_results(function, state);
+    state.push_operator(Operator::Call {
+        function_index: function.exit_hook,
+    });
+}
+
+impl FunctionMiddleware for FunctionCallTrace {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let traced = self.state.clone();
+        let function = &traced.functions[self.local_function_index];
+
+        if function.traced && !self.entered {
+            state.extend(&[
+                Operator::I32Const {
+                    value: CallEventKind::Entry.as_i32(),
+                },
+                Operator::I32Const {
+                    value: function.index as i32,
+                },
+            ]);
+            for local_index in 0..function.num_params {
+                state.push_operator(Operator::LocalGet { local_index });
+            }
+            state.push_operator(Operator::Call {
+                function_index: function.entry_hook,
+            });
+        }
+        self.entered = true;
+
+        let operator =
+            hooks::remap_operator(operator, self.state.first_hook, self.state.hooks.len());
+
+        // The tail calls of traced functions become regular calls, whose
+        // results are reported before returning.
+        let (operator, tail_call) = match operator {
+            Operator::ReturnCall { function_index } if function.traced => {
+                (Operator::Call { function_index }, true)
+            }
+            Operator::ReturnCallIndirect {
+                type_index,
+                table_index,
+            } if function.traced => (
+                Operator::CallIndirect {
+                    type_index,
+                    table_index,
+                    table_byte: 0,
+                },
+                true,
+            ),
+            operator => (operator, false),
+        };
+
+        // The label of the function body.
+        let function_depth = self.depth - 1;
+        match operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. } => self.depth += 1,
+            Operator::End | Operator::Delegate { .. } => {
+                self.depth -= 1;
+                // The end of the function body.
+                if self.depth == 0 && function.traced {
+                    self.exit(function, state);
+                }
+            }
+            Operator::Return if function.traced => self.exit(function, state),
+            Operator::Br { relative_depth }
+                if relative_depth == function_depth && function.traced =>
+            {
+                self.exit(function, state)
+            }
+            Operator::BrIf { relative_depth }
+                if relative_depth == function_depth && function.traced =>
+            {
+                let leaves = [Operator::GlobalGet {
+                    global_index: function.branch_global,
+                }];
+                self.exit_if(function, &leaves, state);
+            }
+            Operator::BrTable { ref targets } if function.traced => {
+                let error = |error: wasmer::wasmparser::BinaryReaderError| {
+                    MiddlewareError::new("call_trace", error.to_string())
+                };
+                // Whether the index selects a target leaving the function.
+                let global_index = function.branch_global;
+                let mut leaves = vec![Operator::I32Const { value: 0 }];
+                for (index, target) in targets.targets().enumerate() {
+                    if target.map_err(error)? == function_depth {
+                        leaves.extend([
+                            Operator::GlobalGet { global_index },
+                            Operator::I32Const {
+                                value: index as i32,
+                            },
+                            Operator::I32Eq,
+                            Operator::I32Or,
+                        ]);
+                    }
+                }
+                if targets.default() == function_depth {
+                    leaves.extend([
+                        Operator::GlobalGet { global_index },
+                        Operator::I32Const {
+                            value: targets.len() as i32,
+                        },
+                        Operator::I32GeU,
+                        Operator::I32Or,
+                    ]);
+                }
+                if leaves.len() > 1 {
+                    self.exit_if(function, &leaves, state);
+                }
+            }
+            Operator::CallIndirect { .. } => {
+                if let (true, Some(hook)) = (function.traced, function.indirect_call_hook) {
+                    let global_index = function.element_global;
+                    state.extend(&[
+                        Operator::GlobalSet { global_index },
+                        Operator::GlobalGet { global_index },
+                        Operator::I32Const {
+                            value: CallEventKind::IndirectCall.as_i32(),
+                        },
+                        Operator::I32Const {
+                            value: function.index as i32,
+                        },
+                        Operator::GlobalGet { global_index },
+                        Operator::Call {
+                            function_index: hook,
+                        },
+                    ]);
+                }
+            }
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        if tail_call {
+            self.exit(function, state);
+            state.push_operator(Operator::Return);
+        }
+
+        Ok(())
+    }
+}
+
+fn hook_name(index: usize) -> String {
+    format!("hook{}", index)
+}
+
+/// Locates the hooks imported by a module compiled with `CallTrace`,
+/// returning the index of the first one and their types.
+fn locate_hooks(info: &ModuleInfo) -> Result<(usize, Vec<FunctionType>), MiddlewareError> {
+    // The hooks are imported after the functions of the original module.
+    let mut hook_imports = info
+        .imports
+        .iter()
+        .filter(|(key, _)| key.module == HOOK_NAMESPACE)
+        .map(|(key, index)| match index {
+            ImportIndex::Function(index) => Ok((*index, key.field.as_str())),
+            _ => Err(MiddlewareError::new(
+                "call_trace",
+                format!("`{}.{}` isn't a function", HOOK_NAMESPACE, key.field),
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    hook_imports.sort_by_key(|(index, _)| *index);
+    let first_hook = info.num_imported_functions - hook_imports.len();
+    let mut hooks = vec![];
+    for (offset, (index, name)) in hook_imports.into_iter().enumerate() {
+        if index.index() != first_hook + offset || name != hook_name(offset) {
+            return Err(MiddlewareError::new(
+                "call_trace",
+                "the module wasn't compiled with `CallTrace`",
+            ));
+        }
+        hooks.push(info.signatures[info.functions[index]].clone());
+    }
+    Ok((first_hook, hooks))
+}
+
+/// The initializer of a scratch global of type `ty`.
+fn zero(ty: Type) -> GlobalInit {
+    match ty {
+        Type::I32 => GlobalInit::I32Const(0),
+        Type::I64 => GlobalInit::I64Const(0),
+        Type::F32 => GlobalInit::F32Const(0.0),
+        Type::F64 => GlobalInit::F64Const(0.0),
+        Type::V128 => GlobalInit::V128Const(V128::from([0; 16])),
+        Type::ExternRef | Type::FuncRef => GlobalInit::RefNullConst,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use wasmer::sys::{EngineBuilder, Features};
+    use wasmer::{
+        wat2wasm, CompilerConfig, Cranelift, Instance, Module, Singlepass, Store, TypedFunction,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "env" "log" (func $log (param i32)))
+            (type $unary (func (param i32) (result i32)))
+            (table 2 funcref)
+            (elem (i32.const 0) $double $square)
+            (func $double (type $unary) (param $x i32) (result i32)
+                (i32.mul (local.get $x) (i32.const 2)))
+            (func $square (type $unary) (param $x i32) (result i32)
+                (if (i32.eqz (local.get $x))
+                    (then (return (i32.const 0))))
+                (i32.mul (local.get $x) (local.get $x)))
+            (func $apply (export "apply") (param $f i32) (param $x i32) (result i32)
+                (call $log (local.get $f))
+                (block $done (result i32)
+                    (br $done
+                        (call_indirect (type $unary) (local.get $x) (local.get $f)))))
+            (func $both (export "both") (param $x i32) (result i32 i64)
+                (call $double (local.get $x))
+                (i64.const 7))
+            (func $first_positive (export "first_positive") (param $a i32) (param $b i32) (result i32)
+                (drop (br_if 0 (local.get $a) (i32.gt_s (local.get $a) (i32.const 0))))
+                (local.get $b))
+            (func $pick (export "pick") (param $i i32) (result i32)
+                (drop
+                    (block $other (result i32)
+                        (br_table $other 1 $other (i32.const 7) (local.get $i))))
+                (i32.const 0))
+            (func $tail (export "tail") (param $x i32) (result i32)
+                (return_call $double (local.get $x))))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn compile(call_trace: Arc<CallTrace>) -> (Store, Module) {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(call_trace);
+        let mut features = Features::new();
+        features.tail_call(true);
+        let store = Store::new(EngineBuilder::new(compiler_config).set_features(Some(features)));
+        let module = Module::new(&store, bytecode()).unwrap();
+        (store, module)
+    }
+
+    fn instantiate(
+        call_trace: CallTrace,
+    ) -> (Arc<CallTrace>, Store, Instance, Arc<Mutex<Vec<CallEvent>>>) {
+        let call_trace = Arc::new(call_trace);
+        let (store, module) = compile(call_trace.clone());
+        let (store, instance, events) = instantiate_module(&call_trace, store, &module);
+        (call_trace, store, instance, events)
+    }
+
+    fn instantiate_module(
+        call_trace: &CallTrace,
+        mut store: Store,
+        module: &Module,
+    ) -> (Store, Instance, Arc<Mutex<Vec<CallEvent>>>) {
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut imports = {
+            let events = events.clone();
+            call_trace.imports(&mut store, move |event: &CallEvent| {
+                events.lock().unwrap().push(event.clone());
+            })
+        };
+        imports.define("env", "log", Function::new_typed(&mut store, |_: i32| {}));
+        let instance = Instance::new(&mut store, module, &imports).unwrap();
+        (store, instance, events)
+    }
+
+    fn event(kind: CallEventKind, function: u32, values: &[Value]) -> CallEvent {
+        CallEvent {
+            kind,
+            function: FunctionIndex::from_u32(function),
+            values: values.to_vec(),
+        }
+    }
+
+    #[test]
+    fn calls_are_traced() {
+        let (call_trace, mut store, instance, events) =
+            instantiate(CallTrace::new().with_indirect_calls(true));
+        assert_eq!(
+            call_trace
+                .function_name(FunctionIndex::from_u32(2))
+                .as_deref(),
+            Some("square")
+        );
+        let apply: TypedFunction<(i32, i32), i32> = instance
+            .exports
+            .get_typed_function(&store, "apply")
+            .unwrap();
+
+        assert_eq!(apply.call(&mut store, 0, 5).unwrap(), 10);
+        assert_eq!(apply.call(&mut store, 1, 0).unwrap(), 0);
+        use CallEventKind::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(Entry, 3, &[Value::I32(0), Value::I32(5)]),
+                event(IndirectCall, 3, &[Value::I32(0)]),
+                event(Entry, 1, &[Value::I32(5)]),
+                event(Exit, 1, &[Value::I32(10)]),
+                event(Exit, 3, &[Value::I32(10)]),
+                event(Entry, 3, &[Value::I32(1), Value::I32(0)]),
+                event(IndirectCall, 3, &[Value::I32(1)]),
+                event(Entry, 2, &[Value::I32(0)]),
+                event(Exit, 2, &[Value::I32(0)]),
+                event(Exit, 3, &[Value::I32(0)]),
+            ]
+        );
+
+        let both: TypedFunction<i32, (i32, i64)> =
+            instance.exports.get_typed_function(&store, "both").unwrap();
+        events.lock().unwrap().clear();
+        assert_eq!(both.call(&mut store, 4).unwrap(), (8, 7));
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(Entry, 4, &[Value::I32(4)]),
+                event(Entry, 1, &[Value::I32(4)]),
+                event(Exit, 1, &[Value::I32(8)]),
+                event(Exit, 4, &[Value::I32(8), Value::I64(7)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn calls_are_traced_across_tiers() {
+        // Both compilers trace the functions, and the hot ones are tiered
+        // up while the instance runs.
+        let call_trace = Arc::new(CallTrace::new().with_indirect_calls(true));
+        let mut baseline = Singlepass::default();
+        baseline.push_middleware(call_trace.clone());
+        let mut optimizing = Cranelift::default();
+        optimizing.push_middleware(Arc::new(CallTrace::new().with_indirect_calls(true)));
+        let mut features = Features::new();
+        features.tail_call(true);
+        let engine = EngineBuilder::new(baseline)
+            .set_features(Some(features))
+            .set_tier_up(optimizing, 10)
+            .engine();
+        let store = Store::new(engine.clone());
+        let module = Module::new(&store, bytecode()).unwrap();
+        let (mut store, instance, events) = instantiate_module(&call_trace, store, &module);
+        let apply: TypedFunction<(i32, i32), i32> = instance
+            .exports
+            .get_typed_function(&store, "apply")
+            .unwrap();
+
+        // `$apply` and `$double`
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while engine.tiered_up_functions() < 2 {
+            assert!(
+                Instant::now() < deadline,
+                "the functions were never tiered up"
+            );
+            assert_eq!(apply.call(&mut store, 0, 5).unwrap(), 10);
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        events.lock().unwrap().clear();
+        assert_eq!(apply.call(&mut store, 0, 5).unwrap(), 10);
+        use CallEventKind::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(Entry, 3, &[Value::I32(0), Value::I32(5)]),
+                event(IndirectCall, 3, &[Value::I32(0)]),
+                event(Entry, 1, &[Value::I32(5)]),
+                event(Exit, 1, &[Value::I32(10)]),
+                event(Exit, 3, &[Value::I32(10)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn branches_out_of_functions_are_traced() {
+        let (_, mut store, instance, events) = instantiate(CallTrace::new());
+        let first_positive: TypedFunction<(i32, i32), i32> = instance
+            .exports
+            .get_typed_function(&store, "first_positive")
+            .unwrap();
+        let pick: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "pick").unwrap();
+        let tail: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "tail").unwrap();
+
+        assert_eq!(first_positive.call(&mut store, 3, 4).unwrap(), 3);
+        assert_eq!(first_positive.call(&mut store, -3, 4).unwrap(), 4);
+        assert_eq!(pick.call(&mut store, 1).unwrap(), 7);
+        assert_eq!(pick.call(&mut store, 0).unwrap(), 0);
+        assert_eq!(pick.call(&mut store, 5).unwrap(), 0);
+        assert_eq!(tail.call(&mut store, 5).unwrap(), 10);
+        use CallEventKind::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(Entry, 5, &[Value::I32(3), Value::I32(4)]),
+                event(Exit, 5, &[Value::I32(3)]),
+                event(Entry, 5, &[Value::I32(-3), Value::I32(4)]),
+                event(Exit, 5, &[Value::I32(4)]),
+                event(Entry, 6, &[Value::I32(1)]),
+                event(Exit, 6, &[Value::I32(7)]),
+                event(Entry, 6, &[Value::I32(0)]),
+                event(Exit, 6, &[Value::I32(0)]),
+                event(Entry, 6, &[Value::I32(5)]),
+                event(Exit, 6, &[Value::I32(0)]),
+                event(Entry, 7, &[Value::I32(5)]),
+                event(Entry, 1, &[Value::I32(5)]),
+                event(Exit, 1, &[Value::I32(10)]),
+                event(Exit, 7, &[Value::I32(10)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn compiled_modules_can_be_traced() {
+        let (store, module) = compile(Arc::new(CallTrace::new()));
+        let serialized = module.serialize().unwrap();
+        let module = unsafe { Module::deserialize(&store, serialized) }.unwrap();
+
+        let call_trace = CallTrace::new();
+        call_trace.use_compiled_module(&module).unwrap();
+        assert_eq!(
+            call_trace
+                .function_name(FunctionIndex::from_u32(2))
+                .as_deref(),
+            Some("square")
+        );
+        let (mut store, instance, events) = instantiate_module(&call_trace, store, &module);
+        let both: TypedFunction<i32, (i32, i64)> =
+            instance.exports.get_typed_function(&store, "both").unwrap();
+        assert_eq!(both.call(&mut store, 4).unwrap(), (8, 7));
+        use CallEventKind::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(Entry, 4, &[Value::I32(4)]),
+                event(Entry, 1, &[Value::I32(4)]),
+                event(Exit, 1, &[Value::I32(8)]),
+                event(Exit, 4, &[Value::I32(8), Value::I64(7)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_works() {
+        // Cranelift doesn't support tail calls, which are only turned into
+        // regular calls in the traced functions.
+        let (_, mut store, instance, events) = instantiate(
+            CallTrace::new().with_filter(|_, name| matches!(name, Some("double" | "tail"))),
+        );
+        let both: TypedFunction<i32, (i32, i64)> =
+            instance.exports.get_typed_function(&store, "both").unwrap();
+        assert_eq!(both.call(&mut store, 4).unwrap(), (8, 7));
+        use CallEventKind::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(Entry, 1, &[Value::I32(4)]),
+                event(Exit, 1, &[Value::I32(8)]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,752 @@
+//! `coverage` is a middleware for measuring the code coverage of a
+//! WebAssembly module. It counts how many times each basic block of
+//! the module is executed, independently of the toolchain the module
+//! was built with.
+//!
+//! The counters can then be mapped back to the source lines of the
+//! module through its DWARF debug info, and written as an lcov report.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::sys::EngineBuilder;
+//! use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store};
+//! use wasmer_middlewares::coverage::{get_counters, Coverage};
+//!
+//! let wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+//!
+//! let coverage = Arc::new(Coverage::new(&wasm).unwrap());
+//! let mut compiler_config = Cranelift::default();
+//! compiler_config.push_middleware(coverage.clone());
+//! let mut store = Store::new(EngineBuilder::new(compiler_config));
+//!
+//! let module = Module::new(&store, &wasm).unwrap();
+//! let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+//! let run = instance.exports.get_function("run").unwrap();
+//! run.call(&mut store, &[]).unwrap();
+//!
+//! let counters = get_counters(&mut store, &instance);
+//! assert_eq!(counters, [1]);
+//!
+//! let mut report = vec![];
+//! coverage.write_lcov(&counters, &mut report).unwrap();
+//! ```
+
+use crate::hooks;
+use addr2line::gimli::{Dwarf, EndianSlice, LittleEndian, SectionId};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::sync::Mutex;
+use wasmer::wasmparser::{Operator, Parser, Payload};
+use wasmer::{
+    AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{GlobalIndex, ModuleInfo};
+
+/// The prefix of the names of the exported counters, one `i64` global
+/// per basic block, followed by the index of their block.
+const COUNTER_EXPORT_PREFIX: &str = "wasmer_coverage_counter_";
+
+/// The name of the exported global holding the number of basic blocks.
+const BLOCK_COUNT_EXPORT_NAME: &str = "wasmer_coverage_block_count";
+
+/// A basic block of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The function of the block.
+    pub function: LocalFunctionIndex,
+    /// The offset in the module of the first operator of the block.
+    pub start: u64,
+    /// The offset in the module after the last operator of the block.
+    pub end: u64,
+}
+
+/// The module-level coverage middleware.
+///
+/// # Panic
+///
+/// A `Coverage` is created for the binary of a given module and should
+/// _not_ be used with other modules. Attempts to use it for several
+/// modules will result in a panic. The optimizing compiler of a tiered
+/// engine only locates the counters added by the baseline compiler's
+/// `Coverage`, so both compilers can be given the same one.
+///
+/// It must be the first middleware of the chain, since operators
+/// added by earlier middlewares would be counted as basic blocks of
+/// the module.
+///
+/// The counters are specific to each function, so the function cache of
+/// an engine is disabled for the modules instrumented by `Coverage`.
+///
+/// # Example
+///
+/// See the [module documentation][self].
+pub struct Coverage {
+    /// The basic blocks of the module, in the order of the counters.
+    blocks: Vec<BasicBlock>,
+
+    /// The index of the first block of each local function.
+    first_blocks: PrimaryMap<LocalFunctionIndex, usize>,
+
+    /// The offset of the code section payload in the module, to which
+    /// the DWARF addresses are relative.
+    code_section_offset: u64,
+
+    /// The DWARF sections of the module.
+    debug_sections: HashMap<String, Vec<u8>>,
+
+    /// The global index of the first counter.
+    first_counter: Mutex<Option<GlobalIndex>>,
+}
+
+/// The function-level coverage middleware.
+pub struct FunctionCoverage {
+    /// The global indexes of the counters of the blocks of the function.
+    counters: Vec<u32>,
+
+    /// The index in `counters` of the counter of the next block.
+    next_counter: usize,
+
+    /// Whether the next operator starts a basic block.
+    block_start: bool,
+}
+
+impl Coverage {
+    /// Creates a `Coverage` middleware for the module in `wasm`, in the
+    /// binary format.
+    pub fn new(wasm: &[u8]) -> Result<Self, MiddlewareError> {
+        let error = |error: wasmer::wasmparser::BinaryReaderError| {
+            MiddlewareError::new("coverage", error.to_string())
+        };
+        let mut blocks = vec![];
+        let mut first_blocks = PrimaryMap::new();
+        let mut code_section_offset = 0;
+        let mut debug_sections = HashMap::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.map_err(error)? {
+                Payload::CodeSectionStart { range, .. } => {
+                    code_section_offset = range.start as u64;
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let function = first_blocks.push(blocks.len());
+                    let mut reader = body.get_operators_reader().map_err(error)?;
+                    let mut block_start = true;
+                    while !reader.eof() {
+                        let (operator, offset) = reader.read_with_offset().map_err(error)?;
+                        if block_start {
+                            blocks.push(BasicBlock {
+                                function,
+                                start: offset as u64,
+                                end: offset as u64,
+                            });
+                        }
+                        block_start = ends_basic_block(&operator);
+                        // The operator extends the current block up to the
+                        // next one.
+                        blocks.last_mut().unwrap().end = reader.original_position() as u64;
+                    }
+                }
+                Payload::CustomSection(reader) if reader.name().starts_with(".debug_") => {
+                    debug_sections.insert(reader.name().to_string(), reader.data().to_vec());
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            blocks,
+            first_blocks,
+            code_section_offset,
+            debug_sections,
+            first_counter: Mutex::new(None),
+        })
+    }
+
+    /// Returns the basic blocks of the module, in the order of their
+    /// counters.
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Writes the coverage of the source lines of the module as an lcov
+    /// tracefile, given the `counters` of an instance.
+    ///
+    /// The basic blocks are mapped to source lines through the DWARF
+    /// debug info of the module. Nothing is written for a module without
+    /// debug info.
+    pub fn write_lcov(&self, counters: &[u64], out: &mut dyn Write) -> io::Result<()> {
+        let invalid_data = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+        if !self.debug_sections.contains_key(".debug_info") {
+            return Ok(());
+        }
+        let dwarf = Dwarf::load(|id: SectionId| -> Result<_, ()> {
+            let data = self
+                .debug_sections
+                .get(id.name())
+                .map(|data| &data[..])
+                .unwrap_or(&[]);
+            Ok(EndianSlice::new(data, LittleEndian))
+        })
+        .unwrap();
+        let context = addr2line::Context::from_dwarf(dwarf).map_err(invalid_data)?;
+
+        // The execution count of each line of each file, and the functions
+        // of each file with their line and call count.
+        let mut lines: BTreeMap<String, BTreeMap<u32, u64>> = BTreeMap::new();
+        let mut functions: BTreeMap<String, BTreeMap<String, (u32, u64)>> = BTreeMap::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let count = counters.get(index).copied().unwrap_or(0);
+            let start = block.start - self.code_section_offset;
+            let end = block.end - self.code_section_offset;
+            let locations = context
+                .find_location_range(start, end)
+                .map_err(invalid_data)?;
+            for (_, _, location) in locations {
+                if let (Some(file), Some(line)) = (location.file, location.line) {
+                    let hits = lines.entry(file.to_string()).or_default();
+                    let hits = hits.entry(line).or_insert(0);
+                    *hits = (*hits).max(count);
+                }
+            }
+
+            // The entry block of a function counts its calls.
+            if self.first_blocks[block.function] != index {
+                continue;
+            }
+            let mut frames = context
+                .find_frames(start)
+                .skip_all_loads()
+                .map_err(invalid_data)?;
+            let mut outermost = None;
+            while let Some(frame) = frames.next().map_err(invalid_data)? {
+                outermost = Some(frame);
+            }
+            let frame = match outermost {
+                Some(frame) => frame,
+                None => continue,
+            };
+            let name = frame
+                .function
+                .as_ref()
+                .and_then(|function| function.raw_name().ok());
+            if let (Some(name), Some(location)) = (name, frame.location) {
+                if let (Some(file), Some(line)) = (location.file, location.line) {
+                    functions
+                        .entry(file.to_string())
+                        .or_default()
+                        .insert(name.into_owned(), (line, count));
+                }
+            }
+        }
+
+        for (file, lines) in &lines {
+            writeln!(out, "TN:")?;
+            writeln!(out, "SF:{}", file)?;
+            let functions = functions.remove(file).unwrap_or_default();
+            for (name, (line, _)) in &functions {
+                writeln!(out, "FN:{},{}", line, name)?;
+            }
+            for (name, (_, count)) in &functions {
+                writeln!(out, "FNDA:{},{}", count, name)?;
+            }
+            writeln!(out, "FNF:{}", functions.len())?;
+            writeln!(
+                out,
+                "FNH:{}",
+                functions.values().filter(|(_, count)| *count > 0).count()
+            )?;
+            for (line, count) in lines {
+                writeln!(out, "DA:{},{}", line, count)?;
+            }
+            writeln!(out, "LF:{}", lines.len())?;
+            writeln!(
+                out,
+                "LH:{}",
+                lines.values().filter(|count| **count > 0).count()
+            )?;
+            writeln!(out, "end_of_record")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("blocks", &self.blocks.len())
+            .field("code_section_offset", &self.code_section_offset)
+            .field("first_counter", &self.first_counter)
+            .finish()
+    }
+}
+
+impl Coverage {
+    /// The indexes of the blocks of a local function.
+    fn function_blocks(&self, local_function_index: LocalFunctionIndex) -> Range<usize> {
+        let first_block = self.first_blocks[local_function_index];
+        let end_block = self
+            .first_blocks
+            .get(LocalFunctionIndex::new(local_function_index.index() + 1))
+            .copied()
+            .unwrap_or(self.blocks.len());
+        first_block..end_block
+    }
+
+    fn function_coverage(&self, counters: Vec<u32>) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionCoverage {
+            counters,
+            next_counter: 0,
+            block_start: true,
+        })
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let first_counter = self.first_counter.lock().unwrap().unwrap().as_u32();
+        let counters = self
+            .function_blocks(local_function_index)
+            .map(|block| first_counter + block as u32)
+            .collect();
+        self.function_coverage(counters)
+    }
+
+    /// Generates a `FunctionCoverage` for a function of the module, once a
+    /// `Coverage` middleware, not necessarily this one, transformed it.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        // The counters are located through their exports, so that they are
+        // only used if they are the counters of this very function.
+        let counters = self
+            .function_blocks(local_function_index)
+            .map(|block| {
+                let name = format!("{}{}", COUNTER_EXPORT_PREFIX, block);
+                match module_info.exports.get(&name) {
+                    Some(ExportIndex::Global(index))
+                        if module_info.globals.get(*index)
+                            == Some(&GlobalType::new(Type::I64, Mutability::Var)) =>
+                    {
+                        Some(index.as_u32())
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>();
+        match counters {
+            Some(counters) => self.function_coverage(counters),
+            None => Box::new(hooks::Uninstrumented("coverage")),
+        }
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut first_counter = self.first_counter.lock().unwrap();
+
+        if first_counter.is_some() {
+            panic!("Coverage::transform_module_info: Attempting to use a `Coverage` middleware from multiple modules.");
+        }
+        if module_info.functions.len() - module_info.num_imported_functions
+            != self.first_blocks.len()
+        {
+            panic!("Coverage::transform_module_info: The `Coverage` middleware was created for another module.");
+        }
+
+        // Append an exported counter per basic block.
+        for index in 0..self.blocks.len() {
+            let global_index = module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+            module_info
+                .global_initializers
+                .push(GlobalInit::I64Const(0));
+            module_info.exports.insert(
+                format!("{}{}", COUNTER_EXPORT_PREFIX, index),
+                ExportIndex::Global(global_index),
+            );
+            if index == 0 {
+                *first_counter = Some(global_index);
+            }
+        }
+
+        // Append an exported global with the number of counters.
+        let global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Const));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(self.blocks.len() as i64));
+        module_info.exports.insert(
+            BLOCK_COUNT_EXPORT_NAME.to_string(),
+            ExportIndex::Global(global_index),
+        );
+    }
+
+    /// Each function counts its blocks in its own counters, so the
+    /// instrumented functions can't be cached.
+    fn cache_key(&self) -> Option<String> {
+        None
+    }
+}
+
+impl fmt::Debug for FunctionCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCoverage")
+            .field("counters", &self.counters)
+            .field("next_counter", &self.next_counter)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionCoverage {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if self.block_start {
+            let counter =
+                match self.counters.get(self.next_counter) {
+                    Some(counter) => *counter,
+                    None => return Err(MiddlewareError::new(
+                        "coverage",
+                        "more basic blocks than in the module; is `Coverage` the first middleware?",
+                    )),
+                };
+            state.extend(&[
+                // counter += 1;
+                Operator::GlobalGet {
+                    global_index: counter,
+                },
+                Operator::I64Const { value: 1 },
+                Operator::I64Add,
+                Operator::GlobalSet {
+                    global_index: counter,
+                },
+            ]);
+            self.next_counter += 1;
+        }
+        self.block_start = ends_basic_block(&operator);
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// Whether the operator following `operator` starts a new basic block.
+fn ends_basic_block(operator: &Operator) -> bool {
+    matches!(
+        operator,
+        Operator::Loop { .. } // loop bodies are branch targets
+            | Operator::If { .. } // "then" branches are conditionally run
+            | Operator::Else // "else" branches are conditionally run
+            | Operator::End // block ends are branch targets
+            | Operator::Br { .. } // branch source
+            | Operator::BrIf { .. } // branch source
+            | Operator::BrTable { .. } // branch source
+            | Operator::Call { .. } // the callee may not return
+            | Operator::CallIndirect { .. } // the callee may not return
+            | Operator::Return // end of function - branch source
+            | Operator::Unreachable // end of function - trap
+    )
+}
+
+/// Get the execution count of every basic block of an
+/// [`Instance`][wasmer::Instance], in the order of
+/// [`Coverage::blocks`].
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance.
+///
+/// # Panic
+///
+/// The counters of the [`Instance`][wasmer::Instance] must be
+/// processed with the [`Coverage`] middleware, otherwise this will
+/// panic.
+pub fn get_counters(ctx: &mut impl AsStoreMut, instance: &Instance) -> Vec<u64> {
+    let block_count: i64 = instance
+        .exports
+        .get_global(BLOCK_COUNT_EXPORT_NAME)
+        .expect("Can't get block count from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("block count from Instance has wrong type");
+    (0..block_count)
+        .map(|index| {
+            let count: i64 = instance
+                .exports
+                .get_global(&format!("{}{}", COUNTER_EXPORT_PREFIX, index))
+                .expect("Can't get counter from Instance")
+                .get(ctx)
+                .try_into()
+                .expect("counter from Instance has wrong type");
+            count as u64
+        })
+        .collect()
+}
+
+/// Reset the execution counts of every basic block of an
+/// [`Instance`][wasmer::Instance].
+///
+/// # Panic
+///
+/// The counters of the [`Instance`][wasmer::Instance] must be
+/// processed with the [`Coverage`] middleware, otherwise this will
+/// panic.
+pub fn reset_counters(ctx: &mut impl AsStoreMut, instance: &Instance) {
+    let block_count: i64 = instance
+        .exports
+        .get_global(BLOCK_COUNT_EXPORT_NAME)
+        .expect("Can't get block count from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("block count from Instance has wrong type");
+    for index in 0..block_count {
+        instance
+            .exports
+            .get_global(&format!("{}{}", COUNTER_EXPORT_PREFIX, index))
+            .expect("Can't get counter from Instance")
+            .set(ctx, 0i64.into())
+            .expect("Can't reset counter in Instance");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::sys::EngineBuilder;
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Module, Singlepass, Store, TypedFunction,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $count_down (export "count_down") (param $n i32) (result i32)
+                (local $steps i32)
+                (block $done
+                    (loop $again
+                        (br_if $done (i32.eqz (local.get $n)))
+                        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                        (local.set $steps (i32.add (local.get $steps) (i32.const 1)))
+                        (br $again)))
+                (local.get $steps)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn leb128(out: &mut Vec<u8>, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn push_custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut payload = vec![];
+        leb128(&mut payload, name.len() as u64);
+        payload.extend_from_slice(name.as_bytes());
+        payload.extend_from_slice(data);
+        wasm.push(0);
+        leb128(wasm, payload.len() as u64);
+        wasm.extend_from_slice(&payload);
+    }
+
+    /// Appends minimal DWARF debug info to a module, describing the
+    /// code before `split` as line 2 and the code after as line 3 of
+    /// `main.c`, in the function `count_down`.
+    fn push_debug_info(wasm: &mut Vec<u8>, split: u32) {
+        let code_len = 0x10000u32;
+
+        #[rustfmt::skip]
+        let abbrev = [
+            // DW_TAG_compile_unit, with children.
+            1, 0x11, 1,
+            0x03, 0x08, // DW_AT_name, DW_FORM_string
+            0x10, 0x17, // DW_AT_stmt_list, DW_FORM_sec_offset
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x06, // DW_AT_high_pc, DW_FORM_data4
+            0, 0,
+            // DW_TAG_subprogram, without children.
+            2, 0x2e, 0,
+            0x03, 0x08, // DW_AT_name, DW_FORM_string
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x06, // DW_AT_high_pc, DW_FORM_data4
+            0, 0,
+            0,
+        ];
+
+        let mut dies = vec![1];
+        dies.extend_from_slice(b"main.c\0");
+        dies.extend_from_slice(&0u32.to_le_bytes());
+        dies.extend_from_slice(&0u32.to_le_bytes());
+        dies.extend_from_slice(&code_len.to_le_bytes());
+        dies.push(2);
+        dies.extend_from_slice(b"count_down\0");
+        dies.extend_from_slice(&0u32.to_le_bytes());
+        dies.extend_from_slice(&code_len.to_le_bytes());
+        dies.push(0);
+        let mut info = vec![];
+        info.extend_from_slice(&(7 + dies.len() as u32).to_le_bytes());
+        info.extend_from_slice(&4u16.to_le_bytes());
+        info.extend_from_slice(&0u32.to_le_bytes());
+        info.push(4);
+        info.extend_from_slice(&dies);
+
+        #[rustfmt::skip]
+        let mut header = vec![
+            1, 1, 1, (-5i8) as u8, 14, 13,
+            0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1,
+            // No include directories.
+            0,
+        ];
+        header.extend_from_slice(b"main.c\0");
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        #[rustfmt::skip]
+        let mut program = vec![
+            0x03, 1, // DW_LNS_advance_line
+            0x00, 5, 0x02, 0, 0, 0, 0, // DW_LNE_set_address
+            0x01, // DW_LNS_copy
+            0x02, // DW_LNS_advance_pc
+        ];
+        leb128(&mut program, split as u64);
+        program.extend_from_slice(&[0x03, 1]); // DW_LNS_advance_line
+        program.push(0x01); // DW_LNS_copy
+        program.push(0x02); // DW_LNS_advance_pc
+        leb128(&mut program, (code_len - split) as u64);
+        program.extend_from_slice(&[0x00, 1, 0x01]); // DW_LNE_end_sequence
+        let mut line = vec![];
+        line.extend_from_slice(&(6 + header.len() as u32 + program.len() as u32).to_le_bytes());
+        line.extend_from_slice(&4u16.to_le_bytes());
+        line.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        line.extend_from_slice(&header);
+        line.extend_from_slice(&program);
+
+        push_custom_section(wasm, ".debug_abbrev", &abbrev);
+        push_custom_section(wasm, ".debug_info", &info);
+        push_custom_section(wasm, ".debug_line", &line);
+    }
+
+    fn instantiate(wasm: &[u8]) -> (Arc<Coverage>, Store, Instance) {
+        instantiate_with(wasm, Cranelift::default())
+    }
+
+    fn instantiate_with(
+        wasm: &[u8],
+        mut compiler_config: impl CompilerConfig + 'static,
+    ) -> (Arc<Coverage>, Store, Instance) {
+        let coverage = Arc::new(Coverage::new(wasm).unwrap());
+        compiler_config.push_middleware(coverage.clone());
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, wasm).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        (coverage, store, instance)
+    }
+
+    #[test]
+    fn counters_work() {
+        let (coverage, mut store, instance) = instantiate(&bytecode());
+        // The entry, the loop header, the loop body, the unreachable `end`
+        // of the loop, the `end` of the block when falling through, and
+        // the code after the block.
+        assert_eq!(coverage.blocks().len(), 6);
+        assert_eq!(get_counters(&mut store, &instance), [0; 6]);
+
+        let count_down: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("count_down")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+        assert_eq!(count_down.call(&mut store, 3).unwrap(), 3);
+        assert_eq!(get_counters(&mut store, &instance), [1, 4, 3, 0, 0, 1]);
+
+        count_down.call(&mut store, 0).unwrap();
+        assert_eq!(get_counters(&mut store, &instance), [2, 5, 3, 0, 0, 2]);
+
+        reset_counters(&mut store, &instance);
+        assert_eq!(get_counters(&mut store, &instance), [0; 6]);
+    }
+
+    #[test]
+    fn counters_work_with_singlepass() {
+        let (_, mut store, instance) = instantiate_with(&bytecode(), Singlepass::default());
+        let count_down: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("count_down")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+        assert_eq!(count_down.call(&mut store, 3).unwrap(), 3);
+        assert_eq!(get_counters(&mut store, &instance), [1, 4, 3, 0, 0, 1]);
+    }
+
+    #[test]
+    fn write_lcov_works() {
+        let mut wasm = bytecode();
+        // Without debug info, nothing is reported.
+        let (coverage, mut store, instance) = instantiate(&wasm);
+        let mut report = vec![];
+        coverage
+            .write_lcov(&get_counters(&mut store, &instance), &mut report)
+            .unwrap();
+        assert!(report.is_empty());
+
+        // Line 2 covers the entry block and the loop header, line 3 the
+        // rest of the function.
+        let blocks = coverage.blocks().to_vec();
+        push_debug_info(
+            &mut wasm,
+            (blocks[2].start - coverage.code_section_offset) as u32,
+        );
+        let (coverage, mut store, instance) = instantiate(&wasm);
+        assert_eq!(coverage.blocks(), blocks);
+        let count_down: TypedFunction<i32, i32> = instance
+            .exports
+            .get_function("count_down")
+            .unwrap()
+            .typed(&store)
+            .unwrap();
+        count_down.call(&mut store, 0).unwrap();
+
+        let mut report = vec![];
+        coverage
+            .write_lcov(&get_counters(&mut store, &instance), &mut report)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "TN:\n\
+             SF:main.c\n\
+             FN:2,count_down\n\
+             FNDA:1,count_down\n\
+             FNF:1\n\
+             FNH:1\n\
+             DA:2,1\n\
+             DA:3,1\n\
+             LF:2\n\
+             LH:2\n\
+             end_of_record\n"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Helpers for the middlewares calling host functions, which they add as
+//! imports of the modules they instrument, and for the middlewares
+//! locating what they added to a module.
+
+use wasmer::wasmparser::Operator;
+use wasmer::{
+    ExportIndex, FunctionMiddleware, FunctionType, GlobalInit, MiddlewareError,
+    MiddlewareReaderState,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, ImportIndex, ImportKey, ModuleInfo};
+
+/// Imports `functions` from the `namespace` module, after the functions
+/// already imported by the module.
+///
+/// This shifts the indexes of the local functions. They are updated
+/// everywhere in `module_info`, but the calls of the function bodies must
+/// be updated by [`remap_operator`].
+pub(crate) fn import_functions(
+    module_info: &mut ModuleInfo,
+    namespace: &str,
+    functions: &[(String, FunctionType)],
+) {
+    let first = module_info.num_imported_functions;
+    let shift = functions.len();
+    let remap = |index: &mut FunctionIndex| *index = shift_function(*index, first, shift);
+
+    let mut signatures = module_info.functions.values().copied().collect::<Vec<_>>();
+    let hook_signatures = functions
+        .iter()
+        .map(|(_, ty)| module_info.signatures.push(ty.clone()))
+        .collect::<Vec<_>>();
+    signatures.splice(first..first, hook_signatures);
+    module_info.functions = PrimaryMap::new();
+    for signature in signatures {
+        module_info.functions.push(signature);
+    }
+    for (index, (name, _)) in functions.iter().enumerate() {
+        let import_idx = module_info.imports.len() as u32;
+        module_info.imports.insert(
+            ImportKey {
+                module: namespace.to_string(),
+                field: name.clone(),
+                import_idx,
+            },
+            ImportIndex::Function(FunctionIndex::new(first + index)),
+        );
+    }
+    module_info.num_imported_functions += shift;
+
+    for export in module_info.exports.values_mut() {
+        if let ExportIndex::Function(index) = export {
+            remap(index);
+        }
+    }
+    if let Some(index) = &mut module_info.start_function {
+        remap(index);
+    }
+    for initializer in &mut module_info.table_initializers {
+        initializer.elements.iter_mut().for_each(remap);
+    }
+    for elements in module_info.passive_elements.values_mut() {
+        elements.iter_mut().for_each(remap);
+    }
+    for initializer in module_info.global_initializers.values_mut() {
+        if let GlobalInit::RefFunc(index) = initializer {
+            remap(index);
+        }
+    }
+    module_info.function_names = module_info
+        .function_names
+        .drain()
+        .map(|(index, name)| (shift_function(index, first, shift), name))
+        .collect();
+}
+
+/// Updates the function index of an operator once `shift` functions are
+/// imported at `first`.
+pub(crate) fn remap_operator(operator: Operator, first: usize, shift: usize) -> Operator {
+    let remap = |function_index: u32| {
+        shift_function(FunctionIndex::from_u32(function_index), first, shift).as_u32()
+    };
+    match operator {
+        Operator::Call { function_index } => Operator::Call {
+            function_index: remap(function_index),
+        },
+        Operator::ReturnCall { function_index } => Operator::ReturnCall {
+            function_index: remap(function_index),
+        },
+        Operator::RefFunc { function_index } => Operator::RefFunc {
+            function_index: remap(function_index),
+        },
+        operator => operator,
+    }
+}
+
+/// Returns the index of a function once `shift` functions are imported
+/// at `first`.
+fn shift_function(index: FunctionIndex, first: usize, shift: usize) -> FunctionIndex {
+    if index.index() >= first {
+        FunctionIndex::new(index.index() + shift)
+    } else {
+        index
+    }
+}
+
+/// The function middleware of a module that wasn't transformed by the
+/// middleware named `.0`, which fails the compilation of its functions.
+///
+/// This happens when a compiler that only instruments the functions of
+/// already transformed modules, like the optimizing compiler of a tiered
+/// engine, has a middleware that the baseline compiler doesn't.
+#[derive(Debug)]
+pub(crate) struct Uninstrumented(pub(crate) &'static str);
+
+impl FunctionMiddleware for Uninstrumented {
+    fn feed<'a>(
+        &mut self,
+        _: Operator<'a>,
+        _: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        Err(MiddlewareError::new(
+            self.0,
+            "the module wasn't transformed by this middleware",
+        ))
+    }
+}
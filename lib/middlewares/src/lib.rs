@@ -1,8 +1,15 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+pub mod call_trace;
+pub mod coverage;
+mod hooks;
 pub mod metering;
+pub mod stack_limit;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use call_trace::CallTrace;
+pub use coverage::Coverage;
 pub use metering::Metering;
+pub use stack_limit::StackLimit;
@@ -3,23 +3,34 @@
 //! operators executed. The WebAssembly instance execution is stopped
 //! when the limit is reached.
 //!
+//! The operators doing an amount of work given at runtime, like
+//! `memory.fill`, can also be charged in proportion to it with
+//! [`Metering::with_dynamic_cost_function`], and host functions can
+//! charge points with [`charge_points`].
+//!
 //! # Example
 //!
 //! [See the `metering` detailed and complete
 //! example](https://github.com/wasmerio/wasmer/blob/master/examples/metering.rs).
 
+use crate::hooks;
 use std::convert::TryInto;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use wasmer::wasmparser::{BlockType as WpTypeOrFuncType, Operator};
 use wasmer::{
     AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
-    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability,
+    RuntimeError, Type,
 };
 use wasmer_types::{GlobalIndex, ModuleInfo};
 
+/// Function that maps the operators to the cost in "points" of each unit
+/// of the work they do at runtime.
+type DynamicCostFunction = dyn Fn(&Operator) -> u64 + Send + Sync;
+
 #[derive(Clone)]
-struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex);
+struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex, Option<(GlobalIndex, GlobalIndex)>);
 
 impl MeteringGlobalIndexes {
     /// The global index in the current module for remaining points.
@@ -35,6 +46,44 @@ impl MeteringGlobalIndexes {
     fn points_exhausted(&self) -> GlobalIndex {
         self.1
     }
+
+    /// The global index in the current module for the operand of type `ty`
+    /// (`i32` or `i64`) holding the amount of work of an operator, while
+    /// it's charged.
+    ///
+    /// It only exists if there's a dynamic cost function.
+    fn dynamic_operand(&self, ty: Type) -> Option<GlobalIndex> {
+        self.2.map(|(i32_operand, i64_operand)| match ty {
+            Type::I64 => i64_operand,
+            _ => i32_operand,
+        })
+    }
+
+    /// Locates the globals of a module whose info was transformed by a
+    /// `Metering` middleware, from their exports, as long as they have the
+    /// types the middleware gave them.
+    fn locate(module_info: &ModuleInfo, dynamic: bool) -> Option<Self> {
+        let global = |name: &str, ty: Type| match module_info.exports.get(name) {
+            Some(ExportIndex::Global(index))
+                if module_info.globals.get(*index)
+                    == Some(&GlobalType::new(ty, Mutability::Var)) =>
+            {
+                Some(*index)
+            }
+            _ => None,
+        };
+        let remaining_points = global("wasmer_metering_remaining_points", Type::I64)?;
+        let points_exhausted = global("wasmer_metering_points_exhausted", Type::I32)?;
+        let dynamic_operand = if dynamic {
+            Some((
+                global("wasmer_metering_dynamic_operand_i32", Type::I32)?,
+                global("wasmer_metering_dynamic_operand_i64", Type::I64)?,
+            ))
+        } else {
+            None
+        };
+        Some(Self(remaining_points, points_exhausted, dynamic_operand))
+    }
 }
 
 impl fmt::Debug for MeteringGlobalIndexes {
@@ -42,6 +91,7 @@ impl fmt::Debug for MeteringGlobalIndexes {
         f.debug_struct("MeteringGlobalIndexes")
             .field("remaining_points", &self.remaining_points())
             .field("points_exhausted", &self.points_exhausted())
+            .field("dynamic_operand", &self.2)
             .finish()
     }
 }
@@ -53,7 +103,9 @@ impl fmt::Debug for MeteringGlobalIndexes {
 /// An instance of `Metering` should _not_ be shared among different
 /// modules, since it tracks module-specific information like the
 /// global index to store metering state. Attempts to use a `Metering`
-/// instance from multiple modules will result in a panic.
+/// instance from multiple modules will result in a panic. The optimizing
+/// compiler of a tiered engine only locates the globals added to a module
+/// by the baseline compiler's `Metering`, so its `Metering` can be shared.
 ///
 /// # Example
 ///
@@ -87,8 +139,19 @@ pub struct Metering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
+    /// Function that maps the operators to the cost of each unit of their
+    /// work at runtime.
+    dynamic_cost_function: Option<Arc<DynamicCostFunction>>,
+
+    /// The name of the cost functions, identifying them in the keys of the
+    /// function cache.
+    cache_key: Option<String>,
+
     /// The global indexes for metering points.
     global_indexes: Mutex<Option<MeteringGlobalIndexes>>,
+
+    /// The index type of each memory of the module.
+    memory_index_types: Mutex<Vec<Type>>,
 }
 
 /// The function-level metering middleware.
@@ -96,9 +159,16 @@ pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
+    /// Function that maps the operators to the cost of each unit of their
+    /// work at runtime.
+    dynamic_cost_function: Option<Arc<DynamicCostFunction>>,
+
     /// The global indexes for metering points.
     global_indexes: MeteringGlobalIndexes,
 
+    /// The index type of each memory of the module.
+    memory_index_types: Vec<Type>,
+
     /// Accumulated cost of the current basic block.
     accumulated_cost: u64,
 }
@@ -128,9 +198,69 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
         Self {
             initial_limit,
             cost_function: Arc::new(cost_function),
+            dynamic_cost_function: None,
+            cache_key: None,
             global_indexes: Mutex::new(None),
+            memory_index_types: Mutex::new(Vec::new()),
         }
     }
+
+    /// Charges the operators doing an amount of work given at runtime in
+    /// proportion to it.
+    ///
+    /// `dynamic_cost_function` maps these operators to the cost in
+    /// "points" of each unit of work, which is checked before the operator
+    /// is executed:
+    ///
+    /// * the pages of `memory.grow`, even if the memory can't grow;
+    /// * the bytes of `memory.copy`, `memory.fill` and `memory.init`;
+    /// * the elements of `table.grow`, `table.copy`, `table.fill` and
+    ///   `table.init`.
+    ///
+    /// The other operators are ignored, and are only charged by the cost
+    /// function given to [`Metering::new`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wasmer::wasmparser::Operator;
+    /// use wasmer_middlewares::Metering;
+    ///
+    /// // Every operator costs 1, and every byte filled costs 1 more.
+    /// let metering = Metering::new(1_000_000, |_: &Operator| -> u64 { 1 })
+    ///     .with_dynamic_cost_function(|operator| match operator {
+    ///         Operator::MemoryFill { .. } => 1,
+    ///         _ => 0,
+    ///     });
+    /// ```
+    pub fn with_dynamic_cost_function(
+        mut self,
+        dynamic_cost_function: impl Fn(&Operator) -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        self.dynamic_cost_function = Some(Arc::new(dynamic_cost_function));
+        self
+    }
+
+    /// Names the cost functions, so that the functions instrumented by
+    /// the middleware can be stored in the function cache of an engine.
+    ///
+    /// The cost functions can't be compared, so `cache_key` must change
+    /// whenever one of them does. Without it, the middleware disables the
+    /// function cache.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wasmer::wasmparser::Operator;
+    /// use wasmer_middlewares::Metering;
+    ///
+    /// let metering =
+    ///     Metering::new(1_000_000, |_: &Operator| -> u64 { 1 }).with_cache_key("unit-cost-v1");
+    /// ```
+    pub fn with_cache_key(mut self, cache_key: impl Into<String>) -> Self {
+        self.cache_key = Some(cache_key.into());
+        self
+    }
 }
 
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
@@ -138,6 +268,11 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
         f.debug_struct("Metering")
             .field("initial_limit", &self.initial_limit)
             .field("cost_function", &"<function>")
+            .field(
+                "dynamic_cost_function",
+                &self.dynamic_cost_function.as_ref().map(|_| "<function>"),
+            )
+            .field("cache_key", &self.cache_key)
             .field("global_indexes", &self.global_indexes)
             .finish()
     }
@@ -148,11 +283,32 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
     fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
         Box::new(FunctionMetering {
             cost_function: self.cost_function.clone(),
+            dynamic_cost_function: self.dynamic_cost_function.clone(),
             global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
+            memory_index_types: self.memory_index_types.lock().unwrap().clone(),
             accumulated_cost: 0,
         })
     }
 
+    /// Generates a `FunctionMetering` for a function of a module that a
+    /// `Metering` middleware, not necessarily this one, transformed.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        _: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        match MeteringGlobalIndexes::locate(module_info, self.dynamic_cost_function.is_some()) {
+            Some(global_indexes) => Box::new(FunctionMetering {
+                cost_function: self.cost_function.clone(),
+                dynamic_cost_function: self.dynamic_cost_function.clone(),
+                global_indexes,
+                memory_index_types: memory_index_types(module_info),
+                accumulated_cost: 0,
+            }),
+            None => Box::new(hooks::Uninstrumented("metering")),
+        }
+    }
+
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, module_info: &mut ModuleInfo) {
         let mut global_indexes = self.global_indexes.lock().unwrap();
@@ -189,22 +345,170 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
             ExportIndex::Global(points_exhausted_global_index),
         );
 
+        // Append a global of each index type for the operands of the
+        // operators with a dynamic cost.
+        let dynamic_operand_global_indexes = self.dynamic_cost_function.as_ref().map(|_| {
+            module_info
+                .global_initializers
+                .push(GlobalInit::I32Const(0));
+            let i32_operand = module_info
+                .globals
+                .push(GlobalType::new(Type::I32, Mutability::Var));
+            module_info.exports.insert(
+                "wasmer_metering_dynamic_operand_i32".to_string(),
+                ExportIndex::Global(i32_operand),
+            );
+            module_info
+                .global_initializers
+                .push(GlobalInit::I64Const(0));
+            let i64_operand = module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+            module_info.exports.insert(
+                "wasmer_metering_dynamic_operand_i64".to_string(),
+                ExportIndex::Global(i64_operand),
+            );
+            (i32_operand, i64_operand)
+        });
+
+        *self.memory_index_types.lock().unwrap() = memory_index_types(module_info);
+
         *global_indexes = Some(MeteringGlobalIndexes(
             remaining_points_global_index,
             points_exhausted_global_index,
+            dynamic_operand_global_indexes,
+        ))
+    }
+
+    /// The cost functions are identified by the key given to
+    /// [`Metering::with_cache_key`], the globals of the module by its
+    /// layout, which is part of the keys of the function cache.
+    fn cache_key(&self) -> Option<String> {
+        let cache_key = self.cache_key.as_ref()?;
+        Some(format!(
+            "metering:{}:{}",
+            self.dynamic_cost_function.is_some(),
+            cache_key
         ))
     }
 }
 
+/// The index type of each memory of a module.
+fn memory_index_types(module_info: &ModuleInfo) -> Vec<Type> {
+    // `MemoryType` has no index type, as the translator only accepts
+    // 32-bit memories for now.
+    module_info.memories.values().map(|_| Type::I32).collect()
+}
+
 impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionMetering<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FunctionMetering")
             .field("cost_function", &"<function>")
+            .field(
+                "dynamic_cost_function",
+                &self.dynamic_cost_function.as_ref().map(|_| "<function>"),
+            )
             .field("global_indexes", &self.global_indexes)
+            .field("memory_index_types", &self.memory_index_types)
             .finish()
     }
 }
 
+impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMetering<F> {
+    /// The type of the operand giving the work of an operator with a
+    /// dynamic cost.
+    fn dynamic_operand_type(&self, operator: &Operator) -> Type {
+        let memory_index_type = |memory: u32| {
+            self.memory_index_types
+                .get(memory as usize)
+                .copied()
+                .unwrap_or(Type::I32)
+        };
+        match *operator {
+            Operator::MemoryGrow { mem, .. } | Operator::MemoryFill { mem } => {
+                memory_index_type(mem)
+            }
+            // The length is an `i64` only when both memories are 64-bit.
+            Operator::MemoryCopy { dst_mem, src_mem } => {
+                match (memory_index_type(dst_mem), memory_index_type(src_mem)) {
+                    (Type::I64, Type::I64) => Type::I64,
+                    _ => Type::I32,
+                }
+            }
+            // Tables are indexed with `i32`s, and the lengths of
+            // `memory.init` and `table.init` are always `i32`s.
+            _ => Type::I32,
+        }
+    }
+
+    /// Charges the work of an operator, given by the operand of type
+    /// `operand_type` on top of the stack, at `unit_cost` points per unit.
+    fn charge_dynamic_cost(
+        &self,
+        unit_cost: u64,
+        operand_type: Type,
+        state: &mut MiddlewareReaderState<'_>,
+    ) {
+        let remaining_points = self.global_indexes.remaining_points().as_u32();
+        let points_exhausted = self.global_indexes.points_exhausted().as_u32();
+        let operand = self
+            .global_indexes
+            .dynamic_operand(operand_type)
+            .expect("the dynamic operand globals exist with a dynamic cost function")
+            .as_u32();
+        // The operand, as an `i64`.
+        let mut get_operand = vec![Operator::GlobalGet {
+            global_index: operand,
+        }];
+        if operand_type != Type::I64 {
+            get_operand.push(Operator::I64ExtendI32U);
+        }
+        state.extend(&[Operator::GlobalSet {
+            global_index: operand,
+        }]);
+        // if unsigned(operand) > unsigned(globals[remaining_points_index]) / unit_cost { throw(); }
+        state.extend(&get_operand);
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: remaining_points,
+            },
+            Operator::I64Const {
+                value: unit_cost as i64,
+            },
+            Operator::I64DivU,
+            Operator::I64GtU,
+            Operator::If {
+                blockty: WpTypeOrFuncType::Empty,
+            },
+            Operator::I32Const { value: 1 },
+            Operator::GlobalSet {
+                global_index: points_exhausted,
+            },
+            Operator::Unreachable,
+            Operator::End,
+            // globals[remaining_points_index] -= operand * unit_cost;
+            Operator::GlobalGet {
+                global_index: remaining_points,
+            },
+        ]);
+        state.extend(&get_operand);
+        state.extend(&[
+            Operator::I64Const {
+                value: unit_cost as i64,
+            },
+            Operator::I64Mul,
+            Operator::I64Sub,
+            Operator::GlobalSet {
+                global_index: remaining_points,
+            },
+            // Put the operand back for the operator.
+            Operator::GlobalGet {
+                global_index: operand,
+            },
+        ]);
+    }
+}
+
 impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMetering<F> {
     fn feed<'a>(
         &mut self,
@@ -252,6 +556,28 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
             }
             _ => {}
         }
+
+        // The operators whose work is given by their last operand.
+        if let Some(dynamic_cost_function) = &self.dynamic_cost_function {
+            match operator {
+                Operator::MemoryGrow { .. }
+                | Operator::MemoryCopy { .. }
+                | Operator::MemoryFill { .. }
+                | Operator::MemoryInit { .. }
+                | Operator::TableGrow { .. }
+                | Operator::TableCopy { .. }
+                | Operator::TableFill { .. }
+                | Operator::TableInit { .. } => {
+                    let unit_cost = dynamic_cost_function(&operator);
+                    if unit_cost > 0 {
+                        let operand_type = self.dynamic_operand_type(&operator);
+                        self.charge_dynamic_cost(unit_cost, operand_type, state);
+                    }
+                }
+                _ => {}
+            }
+        }
+
         state.push_operator(operator);
 
         Ok(())
@@ -348,13 +674,76 @@ pub fn set_remaining_points(ctx: &mut impl AsStoreMut, instance: &Instance, poin
         .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
 }
 
+/// Charge points in an [`Instance`][wasmer::Instance], typically from
+/// the host functions it calls, to account for their work.
+///
+/// If there aren't enough remaining points, the points are marked as
+/// exhausted, and an error is returned. Host functions can return it to
+/// stop the execution of the instance.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`Metering`] middleware at compile time, otherwise this
+/// will panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{FunctionEnvMut, Instance, RuntimeError};
+/// use wasmer_middlewares::metering::charge_points;
+///
+/// struct Env {
+///     instance: Option<Instance>,
+/// }
+///
+/// /// A host function charging 1 point per byte it processes.
+/// fn process(mut env: FunctionEnvMut<Env>, len: u32) -> Result<(), RuntimeError> {
+///     let instance = env.data().instance.clone().unwrap();
+///     charge_points(&mut env, &instance, len as u64)?;
+///     // ...
+///     Ok(())
+/// }
+/// ```
+pub fn charge_points(
+    ctx: &mut impl AsStoreMut,
+    instance: &Instance,
+    points: u64,
+) -> Result<(), RuntimeError> {
+    match get_remaining_points(ctx, instance) {
+        MeteringPoints::Remaining(remaining) if remaining >= points => {
+            instance
+                .exports
+                .get_global("wasmer_metering_remaining_points")
+                .expect("Can't get `wasmer_metering_remaining_points` from Instance")
+                .set(ctx, (remaining - points).into())
+                .expect("Can't set `wasmer_metering_remaining_points` in Instance");
+            Ok(())
+        }
+        _ => {
+            instance
+                .exports
+                .get_global("wasmer_metering_points_exhausted")
+                .expect("Can't get `wasmer_metering_points_exhausted` from Instance")
+                .set(ctx, 1i32.into())
+                .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
+            Err(RuntimeError::new("metering points exhausted"))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::sync::Arc;
-    use wasmer::sys::EngineBuilder;
-    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, TypedFunction};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use wasmer::sys::{EngineBuilder, InMemoryFunctionCache};
+    use wasmer::{
+        imports, wat2wasm, CompilerConfig, Cranelift, Function, FunctionEnv, FunctionEnvMut,
+        Module, Singlepass, Store, TypedFunction,
+    };
 
     fn cost_function(operator: &Operator) -> u64 {
         match operator {
@@ -484,4 +873,200 @@ mod tests {
             MeteringPoints::Remaining(4)
         );
     }
+
+    #[test]
+    fn metering_works_across_tiers() {
+        let wasm = wat2wasm(
+            br#"
+            (module
+            (func (export "add_one") (param $value i32) (result i32)
+                local.get $value
+                i32.const 1
+                i32.add)
+            (func (export "add_two") (param $value i32) (result i32)
+                local.get $value
+                i32.const 2
+                i32.add))
+            "#,
+        )
+        .unwrap();
+
+        // Both compilers meter the functions, and tier them up separately.
+        let mut baseline = Singlepass::default();
+        baseline.push_middleware(Arc::new(Metering::new(1_000_000, cost_function)));
+        let mut optimizing = Cranelift::default();
+        optimizing.push_middleware(Arc::new(Metering::new(1_000_000, cost_function)));
+        let engine = EngineBuilder::new(baseline)
+            .set_tier_up(optimizing, 10)
+            .engine();
+        let mut store = Store::new(engine.clone());
+        let module = Module::new(&store, wasm).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+
+        for (name, tiered_up_functions) in [("add_one", 1), ("add_two", 2)] {
+            let function: TypedFunction<i32, i32> =
+                instance.exports.get_typed_function(&store, name).unwrap();
+            let deadline = Instant::now() + Duration::from_secs(30);
+            while engine.tiered_up_functions() < tiered_up_functions {
+                assert!(Instant::now() < deadline, "{name} was never tiered up");
+                function.call(&mut store, 1).unwrap();
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        assert_eq!(engine.tiered_up_functions(), 2);
+
+        // The optimized code charges the globals of the baseline instance.
+        set_remaining_points(&mut store, &instance, 11);
+        for name in ["add_one", "add_two"] {
+            let function: TypedFunction<i32, i32> =
+                instance.exports.get_typed_function(&store, name).unwrap();
+            function.call(&mut store, 1).unwrap();
+        }
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(3)
+        );
+        let add_one: TypedFunction<i32, i32> = instance
+            .exports
+            .get_typed_function(&store, "add_one")
+            .unwrap();
+        assert!(add_one.call(&mut store, 1).is_err());
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    fn dynamic_bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "env" "charge" (func $charge (param i32)))
+            (memory 1)
+            (func (export "fill") (param $len i32)
+                i32.const 0
+                i32.const 0
+                local.get $len
+                memory.fill)
+            (func (export "work") (param $len i32)
+                local.get $len
+                call $charge))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn dynamic_cost_function(operator: &Operator) -> u64 {
+        match operator {
+            Operator::MemoryFill { .. } => 2,
+            _ => 0,
+        }
+    }
+
+    struct Env {
+        instance: Option<Instance>,
+    }
+
+    fn instantiate_dynamic(metering: Metering<fn(&Operator) -> u64>) -> (Store, Instance) {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(metering));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, dynamic_bytecode()).unwrap();
+
+        let env = FunctionEnv::new(&mut store, Env { instance: None });
+        let charge = Function::new_typed_with_env(
+            &mut store,
+            &env,
+            |mut env: FunctionEnvMut<Env>, points: u32| -> Result<(), RuntimeError> {
+                let instance = env.data().instance.clone().unwrap();
+                charge_points(&mut env, &instance, points as u64)
+            },
+        );
+        let imports = imports! { "env" => { "charge" => charge } };
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        env.as_mut(&mut store).instance = Some(instance.clone());
+        (store, instance)
+    }
+
+    #[test]
+    fn dynamic_cost_works() {
+        let metering = Metering::new(100, cost_function as fn(&Operator) -> u64)
+            .with_dynamic_cost_function(dynamic_cost_function);
+        let (mut store, instance) = instantiate_dynamic(metering);
+        let fill: TypedFunction<i32, ()> =
+            instance.exports.get_typed_function(&store, "fill").unwrap();
+
+        // Filling 10 bytes costs 20 points, on top of the 3 points of the
+        // operators.
+        fill.call(&mut store, 10).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(77)
+        );
+
+        // Filling 39 bytes would cost 78 points.
+        assert!(fill.call(&mut store, 39).is_err());
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    #[test]
+    fn charge_points_works() {
+        let (mut store, instance) =
+            instantiate_dynamic(Metering::new(100, cost_function as fn(&Operator) -> u64));
+        let work: TypedFunction<i32, ()> =
+            instance.exports.get_typed_function(&store, "work").unwrap();
+
+        work.call(&mut store, 50).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(49)
+        );
+
+        assert!(work.call(&mut store, 50).is_err());
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Exhausted
+        );
+    }
+
+    #[test]
+    fn cached_functions_are_metered() {
+        let cache = Arc::new(InMemoryFunctionCache::new());
+        let cost_function = cost_function as fn(&Operator) -> u64;
+        let instantiate = |metering: Metering<fn(&Operator) -> u64>| {
+            let mut compiler_config = Cranelift::default();
+            compiler_config.push_middleware(Arc::new(metering));
+            let engine = EngineBuilder::new(compiler_config).set_function_cache(cache.clone());
+            let mut store = Store::new(engine);
+            let module = Module::new(&store, bytecode()).unwrap();
+            let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+            (store, instance)
+        };
+
+        // Without a key, the cost function can't be told apart from
+        // another one, so nothing is cached.
+        instantiate(Metering::new(10, cost_function));
+        assert!(cache.is_empty());
+
+        instantiate(Metering::new(10, cost_function).with_cache_key("test"));
+        assert_eq!(cache.len(), 1);
+
+        // The function comes from the cache, and is still metered.
+        let (mut store, instance) =
+            instantiate(Metering::new(10, cost_function).with_cache_key("test"));
+        assert_eq!(cache.len(), 1);
+        let add_one: TypedFunction<i32, i32> = instance
+            .exports
+            .get_typed_function(&store, "add_one")
+            .unwrap();
+        add_one.call(&mut store, 1).unwrap();
+        assert_eq!(
+            get_remaining_points(&mut store, &instance),
+            MeteringPoints::Remaining(6)
+        );
+    }
 }
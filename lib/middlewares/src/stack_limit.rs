@@ -0,0 +1,829 @@
+//! `stack_limit` is a middleware for putting a deterministic limit on the
+//! depth of the WebAssembly call stack. Every call of a function adds the
+//! size of its frame, as estimated by a cost function, to the stack usage
+//! of the instance, and the instance traps with
+//! [`TrapCode::StackLimitExceeded`] when the usage would go over the limit.
+//!
+//! Unlike the native stack overflows, which depend on the host stack size
+//! and on the frames generated by the compiler, the limit is hit at the
+//! same depth with all the compilers.
+//!
+//! # Traps
+//!
+//! The frames unwound by a trap, including [`TrapCode::StackLimitExceeded`],
+//! aren't released by the instrumented code. Calls into an instance made
+//! through [`call`] restore its stack usage to the one before the call when
+//! they trap, so that the next calls aren't charged for the unwound frames,
+//! as in the example below. This includes the calls that host functions
+//! make back into the instance, each of them restoring the usage it started
+//! from. Otherwise, the usage must be reset with [`set_stack_usage`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::sys::EngineBuilder;
+//! use wasmer::{wat2wasm, CompilerConfig, Cranelift, Instance, Module, Store};
+//! use wasmer_middlewares::{stack_limit, StackLimit};
+//! use wasmer_types::TrapCode;
+//!
+//! let wasm = wat2wasm(
+//!     br#"(module
+//!         (func $recurse (export "recurse")
+//!             (call $recurse)))"#,
+//! )
+//! .unwrap();
+//!
+//! // Every frame costs 1, so the limit is on the call depth.
+//! let stack_limit = Arc::new(StackLimit::new(1000, |_| 1));
+//! let mut compiler_config = Cranelift::default();
+//! compiler_config.push_middleware(stack_limit);
+//! let mut store = Store::new(EngineBuilder::new(compiler_config));
+//! let module = Module::new(&store, &wasm).unwrap();
+//!
+//! let imports = stack_limit::imports(&mut store);
+//! let instance = Instance::new(&mut store, &module, &imports).unwrap();
+//! let recurse = instance.exports.get_function("recurse").unwrap();
+//! let error = stack_limit::call(&mut store, &instance, |store| recurse.call(store, &[]))
+//!     .unwrap_err();
+//! assert_eq!(error.to_trap(), Some(TrapCode::StackLimitExceeded));
+//!
+//! // The frames unwound by the trap were released.
+//! assert_eq!(stack_limit::get_stack_usage(&mut store, &instance), 0);
+//! ```
+
+use crate::hooks;
+use std::convert::TryInto;
+use std::fmt;
+use std::sync::Mutex;
+use wasmer::wasmparser::{BlockType as WpTypeOrFuncType, Operator};
+use wasmer::{
+    AsStoreMut, ExportIndex, Function, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    Imports, Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState,
+    ModuleMiddleware, Mutability, RuntimeError, Type,
+};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{GlobalIndex, ImportIndex, ModuleInfo, TrapCode};
+use wasmer_vm::Trap;
+
+/// The namespace of the imported hook.
+const HOOK_NAMESPACE: &str = "wasmer_stack_limit";
+
+/// The name of the imported hook, raising the trap.
+const HOOK_NAME: &str = "exceeded";
+
+/// The name of the exported global holding the stack usage.
+const USAGE_GLOBAL: &str = "wasmer_stack_limit_usage";
+
+/// The name of the exported scratch global of the branches.
+const BRANCH_GLOBAL: &str = "wasmer_stack_limit_branch";
+
+#[derive(Clone, Debug)]
+struct StackLimitState {
+    /// The global index of the stack usage.
+    usage: GlobalIndex,
+
+    /// The global index holding the condition, or the index, of the
+    /// branches leaving a function while they release its frame.
+    branch: GlobalIndex,
+
+    /// The function index of the imported hook raising the trap.
+    hook: u32,
+}
+
+impl StackLimitState {
+    /// Locates the globals and the hook of a module whose info was
+    /// transformed by a `StackLimit` middleware, from their exports and
+    /// imports, as long as they have the types the middleware gave them.
+    fn locate(module_info: &ModuleInfo) -> Option<Self> {
+        let global = |name: &str, ty: Type| match module_info.exports.get(name) {
+            Some(ExportIndex::Global(index))
+                if module_info.globals.get(*index)
+                    == Some(&GlobalType::new(ty, Mutability::Var)) =>
+            {
+                Some(*index)
+            }
+            _ => None,
+        };
+        let usage = global(USAGE_GLOBAL, Type::I64)?;
+        let branch = global(BRANCH_GLOBAL, Type::I32)?;
+        let hook = module_info
+            .imports
+            .iter()
+            .find_map(|(key, index)| match index {
+                ImportIndex::Function(index)
+                    if key.module == HOOK_NAMESPACE && key.field == HOOK_NAME =>
+                {
+                    Some(index.as_u32())
+                }
+                _ => None,
+            })?;
+        Some(Self {
+            usage,
+            branch,
+            hook,
+        })
+    }
+}
+
+/// The module-level stack limit middleware.
+///
+/// The middleware imports the host function raising the trap, after the
+/// functions imported by the module. It must be provided at instantiation
+/// by the imports of [`imports`].
+///
+/// The frames unwound by a trap are only released for the calls made
+/// through [`call`], see the [module documentation][self#traps].
+///
+/// # Panic
+///
+/// An instance of `StackLimit` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// global index to store the stack usage. Attempts to use a `StackLimit`
+/// instance from multiple modules will result in a panic. The optimizing
+/// compiler of a tiered engine only locates the globals added to a module
+/// by the baseline compiler's `StackLimit`, so its `StackLimit` can be shared.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::{CompilerConfig, FunctionType};
+/// use wasmer_middlewares::StackLimit;
+///
+/// fn create_stack_limit_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's estimate the size of the frames from their signatures.
+///     let frame_cost = |ty: &FunctionType| -> u64 {
+///         16 + 8 * (ty.params().len() + ty.results().len()) as u64
+///     };
+///
+///     // Let's allow 64KiB of frames.
+///     let limit = 64 * 1024;
+///
+///     // Let's create the stack limit middleware.
+///     let stack_limit = Arc::new(StackLimit::new(limit, frame_cost));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(stack_limit);
+/// }
+/// ```
+pub struct StackLimit<F: Fn(&FunctionType) -> u64 + Send + Sync> {
+    /// The limit of the stack usage.
+    limit: u64,
+
+    /// Function that maps the signature of each function to the cost of
+    /// its frames.
+    frame_cost: F,
+
+    /// The name of the frame cost function, identifying it in the keys of
+    /// the function cache.
+    cache_key: Option<String>,
+
+    /// The module-specific state of the middleware.
+    state: Mutex<Option<StackLimitState>>,
+
+    /// The frame costs of the local functions of the module.
+    frame_costs: Mutex<PrimaryMap<LocalFunctionIndex, u64>>,
+}
+
+/// The function-level stack limit middleware.
+pub struct FunctionStackLimit {
+    /// The limit of the stack usage.
+    limit: u64,
+
+    /// The cost of the frames of the function.
+    frame_cost: u64,
+
+    /// The module-specific state of the middleware.
+    state: StackLimitState,
+
+    /// The function index of the first local function, before the hook
+    /// is imported.
+    first_local_function: usize,
+
+    /// Whether the frame of the function was reserved.
+    entered: bool,
+
+    /// The number of open blocks, including the body of the function.
+    depth: u32,
+}
+
+impl<F: Fn(&FunctionType) -> u64 + Send + Sync> StackLimit<F> {
+    /// Creates a `StackLimit` middleware.
+    ///
+    /// A `frame_cost` of 1 for every function limits the call depth.
+    ///
+    /// The modules compiled with it import the hook raising the trap, so
+    /// their imports must include the ones created by [`imports`]. Calls
+    /// into their instances should be made through [`call`], which
+    /// releases the frames unwound by a trap.
+    pub fn new(limit: u64, frame_cost: F) -> Self {
+        Self {
+            limit,
+            frame_cost,
+            cache_key: None,
+            state: Mutex::new(None),
+            frame_costs: Mutex::new(PrimaryMap::new()),
+        }
+    }
+
+    /// Names the frame cost function, so that the functions instrumented
+    /// by the middleware can be stored in the function cache of an engine.
+    ///
+    /// The frame cost function can't be compared, so `cache_key` must
+    /// change whenever it does. Without it, the middleware disables the
+    /// function cache.
+    pub fn with_cache_key(mut self, cache_key: impl Into<String>) -> Self {
+        self.cache_key = Some(cache_key.into());
+        self
+    }
+}
+
+impl<F: Fn(&FunctionType) -> u64 + Send + Sync> fmt::Debug for StackLimit<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StackLimit")
+            .field("limit", &self.limit)
+            .field("frame_cost", &"<function>")
+            .field("cache_key", &self.cache_key)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl<F: Fn(&FunctionType) -> u64 + Send + Sync + 'static> ModuleMiddleware for StackLimit<F> {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        Box::new(FunctionStackLimit {
+            limit: self.limit,
+            frame_cost: self.frame_costs.lock().unwrap()[local_function_index],
+            first_local_function: state.hook as usize,
+            state,
+            entered: false,
+            depth: 1,
+        })
+    }
+
+    /// Generates a `FunctionStackLimit` for a function of a module that a
+    /// `StackLimit` middleware, not necessarily this one, transformed.
+    fn generate_function_middleware_for_module(
+        &self,
+        module_info: &ModuleInfo,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = match StackLimitState::locate(module_info) {
+            Some(state) => state,
+            None => return Box::new(hooks::Uninstrumented("stack_limit")),
+        };
+        let signature = module_info.functions[module_info.func_index(local_function_index)];
+        Box::new(FunctionStackLimit {
+            limit: self.limit,
+            frame_cost: (self.frame_cost)(&module_info.signatures[signature]),
+            first_local_function: state.hook as usize,
+            state,
+            entered: false,
+            depth: 1,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("StackLimit::transform_module_info: Attempting to use a `StackLimit` middleware from multiple modules.");
+        }
+
+        let frame_costs = module_info
+            .functions
+            .values()
+            .skip(module_info.num_imported_functions)
+            .map(|signature| (self.frame_cost)(&module_info.signatures[*signature]))
+            .collect();
+
+        // Append a global for the stack usage and initialize it.
+        let usage_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I64, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I64Const(0));
+
+        module_info.exports.insert(
+            USAGE_GLOBAL.to_string(),
+            ExportIndex::Global(usage_global_index),
+        );
+
+        // Append a scratch global for the branches.
+        let branch_global_index = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        module_info.exports.insert(
+            BRANCH_GLOBAL.to_string(),
+            ExportIndex::Global(branch_global_index),
+        );
+
+        // Import the hook after the imported functions, which shifts the
+        // local functions.
+        let hook = module_info.num_imported_functions as u32;
+        hooks::import_functions(
+            module_info,
+            HOOK_NAMESPACE,
+            &[(HOOK_NAME.to_string(), FunctionType::new([], []))],
+        );
+
+        *state = Some(StackLimitState {
+            usage: usage_global_index,
+            branch: branch_global_index,
+            hook,
+        });
+        *self.frame_costs.lock().unwrap() = frame_costs;
+    }
+
+    /// The frame cost function is identified by the key given to
+    /// [`StackLimit::with_cache_key`]. The frame cost of a function only
+    /// depends on its signature, and the globals and the hook of the module
+    /// on its layout, which are part of the keys of the function cache.
+    fn cache_key(&self) -> Option<String> {
+        let cache_key = self.cache_key.as_ref()?;
+        Some(format!("stack_limit:{}:{}", self.limit, cache_key))
+    }
+}
+
+impl FunctionStackLimit {
+    /// Releases the frame of the function.
+    fn release(&self, state: &mut MiddlewareReaderState<'_>) {
+        let usage = self.state.usage.as_u32();
+        state.extend(&[
+            // globals[usage] -= self.frame_cost;
+            Operator::GlobalGet {
+                global_index: usage,
+            },
+            Operator::I64Const {
+                value: self.frame_cost as i64,
+            },
+            Operator::I64Sub,
+            Operator::GlobalSet {
+                global_index: usage,
+            },
+        ]);
+    }
+
+    /// Releases the frame of the function if the condition on top of the
+    /// stack, computed from the `branch` global by `condition`, holds.
+    fn release_if(
+        &self,
+        state: &mut MiddlewareReaderState<'_>,
+        condition: impl FnOnce(&mut MiddlewareReaderState<'_>),
+    ) {
+        let branch = self.state.branch.as_u32();
+        state.push_operator(Operator::GlobalSet {
+            global_index: branch,
+        });
+        condition(state);
+        state.push_operator(Operator::If {
+            blockty: WpTypeOrFuncType::Empty,
+        });
+        self.release(state);
+        state.extend(&[
+            Operator::End,
+            Operator::GlobalGet {
+                global_index: branch,
+            },
+        ]);
+    }
+}
+
+impl fmt::Debug for FunctionStackLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionStackLimit")
+            .field("limit", &self.limit)
+            .field("frame_cost", &self.frame_cost)
+            .field("state", &self.state)
+            .field("entered", &self.entered)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionStackLimit {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let usage = self.state.usage.as_u32();
+        let branch = self.state.branch.as_u32();
+
+        // Reserve the frame on entry.
+        if !self.entered {
+            self.entered = true;
+            state.extend(&[
+                // if unsigned(globals[usage]) + self.frame_cost > unsigned(self.limit) { throw(); }
+                Operator::GlobalGet {
+                    global_index: usage,
+                },
+                Operator::I64Const {
+                    value: self.frame_cost as i64,
+                },
+                Operator::I64Add,
+                Operator::I64Const {
+                    value: self.limit as i64,
+                },
+                Operator::I64GtU,
+                Operator::If {
+                    blockty: WpTypeOrFuncType::Empty,
+                },
+                Operator::Call {
+                    function_index: self.state.hook,
+                },
+                Operator::End,
+                // globals[usage] += self.frame_cost;
+                Operator::GlobalGet {
+                    global_index: usage,
+                },
+                Operator::I64Const {
+                    value: self.frame_cost as i64,
+                },
+                Operator::I64Add,
+                Operator::GlobalSet {
+                    global_index: usage,
+                },
+            ]);
+        }
+
+        let operator = hooks::remap_operator(operator, self.first_local_function, 1);
+
+        // Release the frame wherever the function is left.
+        match &operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Try { .. } => self.depth += 1,
+            Operator::End | Operator::Delegate { .. } => {
+                self.depth -= 1;
+                // The end of the function body.
+                if self.depth == 0 {
+                    self.release(state);
+                }
+            }
+            Operator::Return
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+            | Operator::ReturnCallRef { .. } => self.release(state),
+            Operator::Br { relative_depth } if relative_depth + 1 == self.depth => {
+                self.release(state)
+            }
+            Operator::BrIf { relative_depth } if relative_depth + 1 == self.depth => self
+                .release_if(state, |state| {
+                    state.push_operator(Operator::GlobalGet {
+                        global_index: branch,
+                    })
+                }),
+            Operator::BrTable { targets } => {
+                let exits = targets
+                    .targets()
+                    .enumerate()
+                    .filter_map(|(index, target)| match target {
+                        Ok(target) if target + 1 == self.depth => Some(Ok(index as u32)),
+                        Ok(_) => None,
+                        Err(e) => Some(Err(e)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| MiddlewareError::new("StackLimit", e.to_string()))?;
+                let default_exits = targets.default() + 1 == self.depth;
+                if !exits.is_empty() || default_exits {
+                    let len = targets.len();
+                    self.release_if(state, |state| {
+                        state.push_operator(Operator::I32Const { value: 0 });
+                        for index in exits {
+                            state.extend(&[
+                                Operator::GlobalGet {
+                                    global_index: branch,
+                                },
+                                Operator::I32Const {
+                                    value: index as i32,
+                                },
+                                Operator::I32Eq,
+                                Operator::I32Or,
+                            ]);
+                        }
+                        if default_exits {
+                            state.extend(&[
+                                Operator::GlobalGet {
+                                    global_index: branch,
+                                },
+                                Operator::I32Const { value: len as i32 },
+                                Operator::I32GeU,
+                                Operator::I32Or,
+                            ]);
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// Creates the imports required by the modules processed with the
+/// [`StackLimit`] middleware.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance, InstantiationError, Module};
+/// use wasmer_middlewares::stack_limit;
+///
+/// fn instantiate(store: &mut impl AsStoreMut, module: &Module) -> Result<Instance, InstantiationError> {
+///     let imports = stack_limit::imports(store);
+///     Instance::new(store, module, &imports)
+/// }
+/// ```
+pub fn imports(store: &mut impl AsStoreMut) -> Imports {
+    let exceeded = Function::new_typed(store, || -> Result<(), RuntimeError> {
+        Err(Trap::lib(TrapCode::StackLimitExceeded).into())
+    });
+    let mut imports = Imports::new();
+    imports.define(HOOK_NAMESPACE, HOOK_NAME, exceeded);
+    imports
+}
+
+/// Makes a `call` into an [`Instance`][wasmer::Instance], and restores its
+/// stack usage to the one before the call if it traps, which releases the
+/// frames unwound by the trap.
+///
+/// Only the stack usage of `instance` is restored. If the call traps in
+/// the functions of other instances processed with the [`StackLimit`]
+/// middleware, which it called directly, their usage must be reset with
+/// [`set_stack_usage`].
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`StackLimit`] middleware at compile time, otherwise this will
+/// panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance, RuntimeError, Value};
+/// use wasmer_middlewares::stack_limit;
+///
+/// fn run(store: &mut impl AsStoreMut, instance: &Instance) -> Result<Box<[Value]>, RuntimeError> {
+///     let run = instance.exports.get_function("run").unwrap();
+///     stack_limit::call(store, instance, |store| run.call(store, &[]))
+/// }
+/// ```
+pub fn call<S: AsStoreMut, R>(
+    store: &mut S,
+    instance: &Instance,
+    call: impl FnOnce(&mut S) -> Result<R, RuntimeError>,
+) -> Result<R, RuntimeError> {
+    let usage = get_stack_usage(store, instance);
+    let result = call(store);
+    if result.is_err() {
+        set_stack_usage(store, instance, usage);
+    }
+    result
+}
+
+/// Get the stack usage of an [`Instance`][wasmer::Instance].
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`StackLimit`] middleware at compile time, otherwise this will
+/// panic.
+pub fn get_stack_usage(ctx: &mut impl AsStoreMut, instance: &Instance) -> u64 {
+    instance
+        .exports
+        .get_global(USAGE_GLOBAL)
+        .expect("Can't get `wasmer_stack_limit_usage` from Instance")
+        .get(ctx)
+        .try_into()
+        .expect("`wasmer_stack_limit_usage` from Instance has wrong type")
+}
+
+/// Set the stack usage of an [`Instance`][wasmer::Instance].
+///
+/// # Traps
+///
+/// **This must be called after the traps of the calls that weren't made
+/// through [`call`].** The frames unwound by these traps aren't released,
+/// so until its usage is reset, the instance keeps counting them and hits
+/// the limit earlier, until every call fails.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`StackLimit`] middleware at compile time, otherwise this
+/// will panic.
+pub fn set_stack_usage(ctx: &mut impl AsStoreMut, instance: &Instance, usage: u64) {
+    instance
+        .exports
+        .get_global(USAGE_GLOBAL)
+        .expect("Can't get `wasmer_stack_limit_usage` from Instance")
+        .set(ctx, usage.into())
+        .expect("Can't set `wasmer_stack_limit_usage` in Instance");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::sys::{EngineBuilder, InMemoryFunctionCache};
+    use wasmer::{
+        wat2wasm, CompilerConfig, Cranelift, FunctionEnv, FunctionEnvMut, Module, Singlepass,
+        Store, TypedFunction,
+    };
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (type $unary (func (param i32) (result i32)))
+            (table 1 funcref)
+            (elem (i32.const 0) $down)
+            (func $down (export "down") (type $unary) (param $n i32) (result i32)
+                (if (i32.eqz (local.get $n))
+                    (then (return (i32.const 0))))
+                (i32.add
+                    (call_indirect (type $unary)
+                        (i32.sub (local.get $n) (i32.const 1))
+                        (i32.const 0))
+                    (i32.const 1)))
+            (func $br_if (export "br_if") (param $n i32) (result i32)
+                (local.get $n)
+                (br_if 0 (local.get $n))
+                (drop)
+                (i32.const -1))
+            (func $br_table (export "br_table") (param $n i32)
+                (block $continue
+                    (br_table $continue 1 (local.get $n)))
+                (br_table 0 (local.get $n))))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate(compiler_config: impl CompilerConfig + 'static) -> (Store, Instance) {
+        let mut compiler_config = compiler_config;
+        compiler_config.push_middleware(Arc::new(StackLimit::new(100, |_| 1)));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(&store, bytecode()).unwrap();
+        let imports = imports(&mut store);
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        (store, instance)
+    }
+
+    fn limit_works(compiler_config: impl CompilerConfig + 'static) {
+        let (mut store, instance) = instantiate(compiler_config);
+        let down: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "down").unwrap();
+
+        // The frames are released on every exit of the functions.
+        let br_if: TypedFunction<i32, i32> = instance
+            .exports
+            .get_typed_function(&store, "br_if")
+            .unwrap();
+        let br_table: TypedFunction<i32, ()> = instance
+            .exports
+            .get_typed_function(&store, "br_table")
+            .unwrap();
+        assert_eq!(down.call(&mut store, 99).unwrap(), 99);
+        assert_eq!(br_if.call(&mut store, 3).unwrap(), 3);
+        assert_eq!(br_if.call(&mut store, 0).unwrap(), -1);
+        br_table.call(&mut store, 0).unwrap();
+        br_table.call(&mut store, 1).unwrap();
+        br_table.call(&mut store, 2).unwrap();
+        assert_eq!(get_stack_usage(&mut store, &instance), 0);
+
+        // The 101st frame exceeds the limit.
+        let error = down.call(&mut store, 100).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::StackLimitExceeded));
+        assert_eq!(get_stack_usage(&mut store, &instance), 100);
+
+        set_stack_usage(&mut store, &instance, 0);
+        assert_eq!(down.call(&mut store, 99).unwrap(), 99);
+
+        // The frames unwound by a trap are released by `call`.
+        let error = call(&mut store, &instance, |store| down.call(store, 100)).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::StackLimitExceeded));
+        assert_eq!(get_stack_usage(&mut store, &instance), 0);
+        assert_eq!(down.call(&mut store, 99).unwrap(), 99);
+    }
+
+    struct Env {
+        instance: Option<Instance>,
+    }
+
+    fn reentrant_traps_restore_their_usage(compiler_config: impl CompilerConfig + 'static) {
+        let mut compiler_config = compiler_config;
+        compiler_config.push_middleware(Arc::new(StackLimit::new(100, |_| 1)));
+        let mut store = Store::new(EngineBuilder::new(compiler_config));
+        let module = Module::new(
+            &store,
+            r#"(module
+                (import "env" "reenter" (func $reenter (result i32)))
+                (func $down (export "down") (param $n i32) (result i32)
+                    (if (i32.eqz (local.get $n))
+                        (then (return (call $reenter))))
+                    (i32.add
+                        (call $down (i32.sub (local.get $n) (i32.const 1)))
+                        (i32.const 1)))
+                (func $recurse (export "recurse")
+                    (call $recurse)))"#,
+        )
+        .unwrap();
+
+        // The host function makes a call that traps, and returns the usage
+        // once it's restored.
+        let env = FunctionEnv::new(&mut store, Env { instance: None });
+        let reenter =
+            Function::new_typed_with_env(&mut store, &env, |mut env: FunctionEnvMut<Env>| -> i32 {
+                let (env, mut store) = env.data_and_store_mut();
+                let instance = env.instance.clone().unwrap();
+                let recurse = instance.exports.get_function("recurse").unwrap();
+                let error =
+                    call(&mut store, &instance, |store| recurse.call(store, &[])).unwrap_err();
+                assert_eq!(error.to_trap(), Some(TrapCode::StackLimitExceeded));
+                get_stack_usage(&mut store, &instance) as i32
+            });
+        let mut imports = imports(&mut store);
+        imports.define("env", "reenter", reenter);
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        env.as_mut(&mut store).instance = Some(instance.clone());
+        let down: TypedFunction<i32, i32> =
+            instance.exports.get_typed_function(&store, "down").unwrap();
+
+        // The 4 frames of `down` are still reserved while `reenter` runs.
+        assert_eq!(down.call(&mut store, 3).unwrap(), 4 + 3);
+        assert_eq!(get_stack_usage(&mut store, &instance), 0);
+    }
+
+    #[test]
+    fn limit_works_with_cranelift() {
+        limit_works(Cranelift::default());
+    }
+
+    #[test]
+    fn limit_works_with_singlepass() {
+        limit_works(Singlepass::default());
+    }
+
+    #[test]
+    fn reentrant_traps_restore_their_usage_with_cranelift() {
+        reentrant_traps_restore_their_usage(Cranelift::default());
+    }
+
+    #[test]
+    fn reentrant_traps_restore_their_usage_with_singlepass() {
+        reentrant_traps_restore_their_usage(Singlepass::default());
+    }
+
+    #[test]
+    fn cached_functions_are_limited() {
+        let cache = Arc::new(InMemoryFunctionCache::new());
+        let instantiate = |wat: &str| {
+            let mut compiler_config = Cranelift::default();
+            compiler_config
+                .push_middleware(Arc::new(StackLimit::new(100, |_| 1).with_cache_key("test")));
+            let engine = EngineBuilder::new(compiler_config).set_function_cache(cache.clone());
+            let mut store = Store::new(engine);
+            let module = Module::new(&store, wat).unwrap();
+            let imports = imports(&mut store);
+            let instance = Instance::new(&mut store, &module, &imports).unwrap();
+            (store, instance)
+        };
+        let recurse = r#"(func $recurse (export "recurse") (call $recurse))"#;
+
+        instantiate(&format!("(module {recurse})"));
+        assert_eq!(cache.len(), 1);
+
+        // Only `nop` is compiled: `recurse` comes from the cache after
+        // another function, and still calls itself rather than `nop` or the
+        // hook.
+        let (mut store, instance) =
+            instantiate(&format!("(module (func $nop (export \"nop\")) {recurse})"));
+        assert_eq!(cache.len(), 2);
+        let recurse = instance.exports.get_function("recurse").unwrap();
+        let error = call(&mut store, &instance, |store| recurse.call(store, &[])).unwrap_err();
+        assert_eq!(error.to_trap(), Some(TrapCode::StackLimitExceeded));
+        assert_eq!(get_stack_usage(&mut store, &instance), 0);
+    }
+}
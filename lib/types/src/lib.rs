@@ -135,7 +135,7 @@ pub use crate::compilation::unwind::{
     CompiledFunctionUnwindInfoReference,
 };
 
-pub use crate::stack::{FrameInfo, SourceLoc, TrapInformation};
+pub use crate::stack::{FrameInfo, FrameSymbol, SourceLoc, TrapInformation};
 pub use crate::store_id::StoreId;
 
 /// Offset in bytes from the beginning of the function.
@@ -16,6 +16,8 @@ pub struct FrameInfo {
     func_start: SourceLoc,
     /// The source location of the instruction
     instr: SourceLoc,
+    /// The symbols from the debug info of the module
+    symbols: Vec<FrameSymbol>,
 }
 
 impl FrameInfo {
@@ -33,9 +35,17 @@ impl FrameInfo {
             function_name,
             func_start,
             instr,
+            symbols: Vec::new(),
         }
     }
 
+    /// Attaches the `symbols` of the instruction, found in the debug info
+    /// of the module, to this frame.
+    pub fn with_symbols(mut self, symbols: Vec<FrameSymbol>) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
     /// Returns the WebAssembly function index for this frame.
     ///
     /// This function index is the index in the function index space of the
@@ -93,4 +103,70 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+
+    /// Returns the source-level symbols of this frame's instruction, from
+    /// the DWARF debug info of the module.
+    ///
+    /// There are several symbols when functions were inlined into the one
+    /// of this frame: the innermost inlined function comes first, followed
+    /// by the functions it was inlined into.
+    ///
+    /// This is empty when the module has no debug info.
+    pub fn symbols(&self) -> &[FrameSymbol] {
+        &self.symbols
+    }
+}
+
+/// A source-level symbol of a frame, from the DWARF debug info of the
+/// module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSymbol {
+    /// The name of the function, mangled if it was by the source language
+    name: Option<String>,
+    /// The source file
+    file: Option<String>,
+    /// The line in the source file
+    line: Option<u32>,
+    /// The column in the line
+    column: Option<u32>,
+}
+
+impl FrameSymbol {
+    /// Creates a new [FrameSymbol].
+    pub fn new(
+        name: Option<String>,
+        file: Option<String>,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> Self {
+        Self {
+            name,
+            file,
+            line,
+            column,
+        }
+    }
+
+    /// Returns the name of the function, if known.
+    ///
+    /// The name is the one of the symbol in the source language, which may
+    /// be mangled.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the path of the source file, if known.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Returns the line in the source file, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Returns the column in the source line, if known.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
 }
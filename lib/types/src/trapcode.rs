@@ -69,6 +69,9 @@ pub enum TrapCode {
 
     /// An atomic memory access was attempted with an unaligned pointer.
     UnalignedAtomic = 10,
+
+    /// The deterministic stack limit enforced by a middleware was exceeded.
+    StackLimitExceeded = 11,
 }
 
 impl TrapCode {
@@ -86,6 +89,7 @@ impl TrapCode {
             Self::BadConversionToInteger => "invalid conversion to integer",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unaligned atomic access",
+            Self::StackLimitExceeded => "stack limit exceeded",
         }
     }
 }
@@ -104,6 +108,7 @@ impl Display for TrapCode {
             Self::BadConversionToInteger => "bad_toint",
             Self::UnreachableCodeReached => "unreachable",
             Self::UnalignedAtomic => "unalign_atom",
+            Self::StackLimitExceeded => "stk_limit",
         };
         f.write_str(identifier)
     }
@@ -125,6 +130,7 @@ impl FromStr for TrapCode {
             "bad_toint" => Ok(Self::BadConversionToInteger),
             "unreachable" => Ok(Self::UnreachableCodeReached),
             "unalign_atom" => Ok(Self::UnalignedAtomic),
+            "stk_limit" => Ok(Self::StackLimitExceeded),
             _ => Err(()),
         }
     }
@@ -148,7 +154,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 11] = [
+    const CODES: [TrapCode; 12] = [
         TrapCode::StackOverflow,
         TrapCode::HeapAccessOutOfBounds,
         TrapCode::HeapMisaligned,
@@ -160,6 +166,7 @@ mod tests {
         TrapCode::BadConversionToInteger,
         TrapCode::UnreachableCodeReached,
         TrapCode::UnalignedAtomic,
+        TrapCode::StackLimitExceeded,
     ];
 
     #[test]
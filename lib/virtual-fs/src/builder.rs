@@ -104,6 +104,11 @@ impl RootFileSystemBuilder {
                 PathBuf::from("/dev/tty"),
                 self.tty.unwrap_or_else(|| Box::<NullFile>::default()),
             );
+            // The runtime allocates a new pseudo-terminal whenever this file
+            // is opened, so it only needs to exist
+            let _ = tmp
+                .new_open_options_ext()
+                .insert_device_file(PathBuf::from("/dev/ptmx"), Box::<NullFile>::default());
         }
         tmp
     }
@@ -4,7 +4,7 @@ use crate::{io_err_into_net_error, VirtualIoSource};
 use crate::{
     IpCidr, IpRoute, NetworkError, Result, SocketStatus, StreamSecurity, VirtualConnectedSocket,
     VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket, VirtualUnixSocket,
 };
 use bytes::{Buf, BytesMut};
 use derivative::Derivative;
@@ -16,6 +16,8 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
 use std::os::fd::AsRawFd;
 #[cfg(not(target_os = "windows"))]
 use std::os::fd::RawFd;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
@@ -126,6 +128,19 @@ impl VirtualNetworking for LocalNetworking {
         Ok(socket)
     }
 
+    #[cfg(unix)]
+    async fn connect_unix(&self, path: &Path) -> Result<Box<dyn VirtualUnixSocket + Sync>> {
+        let stream = mio::net::UnixStream::connect(path).map_err(io_err_into_net_error)?;
+        let socket = Box::new(LocalUnixStream {
+            stream,
+            path: path.to_path_buf(),
+            selector: self.selector.clone(),
+            handler_guard: HandlerGuardState::None,
+            buffer: BytesMut::new(),
+        });
+        Ok(socket)
+    }
+
     async fn resolve(
         &self,
         host: &str,
@@ -644,6 +659,164 @@ fn libc_poll(fd: RawFd, events: libc::c_short) -> Option<libc::c_short> {
     }
 }
 
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct LocalUnixStream {
+    stream: mio::net::UnixStream,
+    path: PathBuf,
+    selector: Arc<Selector>,
+    handler_guard: HandlerGuardState,
+    buffer: BytesMut,
+}
+
+#[cfg(unix)]
+impl VirtualUnixSocket for LocalUnixStream {
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        let ret = self.stream.write(data).map_err(io_err_into_net_error);
+        match &ret {
+            Ok(0) | Err(NetworkError::WouldBlock) => {
+                if let HandlerGuardState::WakerMap(_, map) = &mut self.handler_guard {
+                    map.pop(InterestType::Writable);
+                }
+            }
+            _ => {}
+        }
+        ret
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        let buf: &mut [u8] = unsafe { std::mem::transmute(buf) };
+        if !self.buffer.is_empty() {
+            let amt = buf.len().min(self.buffer.len());
+            buf[..amt].copy_from_slice(&self.buffer[..amt]);
+            self.buffer.advance(amt);
+            return Ok(amt);
+        }
+
+        self.stream.read(buf).map_err(io_err_into_net_error)
+    }
+
+    fn set_handler(&mut self, mut handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        if let HandlerGuardState::ExternalHandler(guard) = &mut self.handler_guard {
+            match guard.replace_handler(handler) {
+                Ok(()) => return Ok(()),
+                Err(h) => handler = h,
+            }
+
+            // the handler could not be replaced so we need to build a new handler instead
+            if let Err(err) = guard.unregister(&mut self.stream) {
+                tracing::debug!("failed to unregister previous token - {}", err);
+            }
+        }
+
+        let guard = InterestGuard::new(
+            &self.selector,
+            handler,
+            &mut self.stream,
+            mio::Interest::READABLE.add(mio::Interest::WRITABLE),
+        )
+        .map_err(io_err_into_net_error)?;
+
+        self.handler_guard = HandlerGuardState::ExternalHandler(guard);
+
+        Ok(())
+    }
+
+    fn addr_peer(&self) -> Result<Option<PathBuf>> {
+        Ok(Some(self.path.clone()))
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.stream.shutdown(how).map_err(io_err_into_net_error)
+    }
+
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(unix)]
+impl LocalUnixStream {
+    fn split_borrow(
+        &mut self,
+    ) -> (
+        &mut HandlerGuardState,
+        &Arc<Selector>,
+        &mut mio::net::UnixStream,
+        &mut BytesMut,
+    ) {
+        (
+            &mut self.handler_guard,
+            &self.selector,
+            &mut self.stream,
+            &mut self.buffer,
+        )
+    }
+}
+
+#[cfg(unix)]
+impl VirtualIoSource for LocalUnixStream {
+    fn remove_handler(&mut self) {
+        let mut guard = HandlerGuardState::None;
+        std::mem::swap(&mut guard, &mut self.handler_guard);
+        match guard {
+            HandlerGuardState::ExternalHandler(mut guard) => {
+                guard.unregister(&mut self.stream).ok();
+            }
+            HandlerGuardState::WakerMap(mut guard, _) => {
+                guard.unregister(&mut self.stream).ok();
+            }
+            HandlerGuardState::None => {}
+        }
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<usize>> {
+        if !self.buffer.is_empty() {
+            return Poll::Ready(Ok(self.buffer.len()));
+        }
+
+        let (state, selector, stream, buffer) = self.split_borrow();
+        let map = state_as_waker_map(state, selector, stream).map_err(io_err_into_net_error)?;
+        map.pop(InterestType::Readable);
+        map.add(InterestType::Readable, cx.waker());
+
+        buffer.reserve(buffer.len() + 10240);
+        let uninit: &mut [MaybeUninit<u8>] = buffer.spare_capacity_mut();
+        let uninit_unsafe: &mut [u8] = unsafe { std::mem::transmute(uninit) };
+
+        match stream.read(uninit_unsafe) {
+            Ok(0) => Poll::Ready(Ok(0)),
+            Ok(amt) => {
+                unsafe {
+                    buffer.set_len(buffer.len() + amt);
+                }
+                Poll::Ready(Ok(amt))
+            }
+            Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => Poll::Ready(Ok(0)),
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => Poll::Ready(Ok(0)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            Err(err) => Poll::Ready(Err(io_err_into_net_error(err))),
+        }
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<usize>> {
+        let (state, selector, stream, _) = self.split_borrow();
+        let map = state_as_waker_map(state, selector, stream).map_err(io_err_into_net_error)?;
+        map.pop(InterestType::Writable);
+        map.add(InterestType::Writable, cx.waker());
+        map.add(InterestType::Closed, cx.waker());
+        if map.has_interest(InterestType::Closed) {
+            return Poll::Ready(Ok(0));
+        }
+
+        match libc_poll(stream.as_raw_fd(), libc::POLLOUT | libc::POLLHUP) {
+            Some(val) if (val & libc::POLLHUP) != 0 => Poll::Ready(Ok(0)),
+            Some(val) if (val & libc::POLLOUT) != 0 => Poll::Ready(Ok(10240)),
+            _ => Poll::Pending,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LocalUdpSocket {
     socket: mio::net::UdpSocket,
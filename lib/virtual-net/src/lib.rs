@@ -27,6 +27,8 @@ use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use std::net::Shutdown;
 use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Context;
@@ -206,6 +208,11 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
         Err(NetworkError::Unsupported)
     }
 
+    /// Opens a connection to a UNIX domain socket at a particular path
+    async fn connect_unix(&self, path: &Path) -> Result<Box<dyn VirtualUnixSocket + Sync>> {
+        Err(NetworkError::Unsupported)
+    }
+
     /// Performs DNS resolution for a specific hostname
     async fn resolve(
         &self,
@@ -680,6 +687,30 @@ impl<'a> AsyncWrite for Box<dyn VirtualTcpSocket + Sync + 'a> {
     }
 }
 
+pub trait VirtualUnixSocket: VirtualIoSource + fmt::Debug + Send + Sync + 'static {
+    /// Tries to send out a stream of bytes on this socket
+    fn try_send(&mut self, data: &[u8]) -> Result<usize>;
+
+    /// Tries to read a stream of bytes from the socket
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize>;
+
+    /// Registers a waker for when this connection is ready to receive
+    /// more data. Uses a stack machine which means more than one waker
+    /// can be registered
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()>;
+
+    /// Returns the path of the peer socket that this is connected to,
+    /// unnamed peers (such as the ends of a socket pair) have no path
+    fn addr_peer(&self) -> Result<Option<PathBuf>>;
+
+    /// Shuts down either the READER or WRITER sides of the socket
+    /// connection.
+    fn shutdown(&mut self, how: Shutdown) -> Result<()>;
+
+    /// Return true if the socket is closed
+    fn is_closed(&self) -> bool;
+}
+
 pub trait VirtualUdpSocket:
     VirtualConnectionlessSocket + fmt::Debug + Send + Sync + 'static
 {
@@ -1,4 +1,6 @@
 use super::{Instance, VMInstance};
+#[cfg(unix)]
+use crate::pool::{InstanceSlot, PoolingAllocator};
 use crate::vmcontext::VMTableDefinition;
 use crate::VMMemoryDefinition;
 use std::alloc::{self, Layout};
@@ -42,10 +44,20 @@ pub struct InstanceAllocator {
     /// `instance_ptr` buffer. If it has not when being dropped,
     /// the buffer should be freed.
     consumed: bool,
+
+    /// The pool slot of the `instance_ptr` buffer, if it was allocated
+    /// from a pool rather than the global allocator.
+    #[cfg(unix)]
+    pooled: Option<InstanceSlot>,
 }
 
 impl Drop for InstanceAllocator {
     fn drop(&mut self) {
+        #[cfg(unix)]
+        if self.pooled.is_some() {
+            // The slot goes back to its pool when dropped.
+            return;
+        }
         if !self.consumed {
             // If `consumed` has not been set, then we still have ownership
             // over the buffer and must free it.
@@ -87,11 +99,64 @@ impl InstanceAllocator {
             alloc::handle_alloc_error(instance_layout);
         };
 
+        Self::with_buffer(
+            instance_ptr,
+            instance_layout,
+            offsets,
+            #[cfg(unix)]
+            None,
+        )
+    }
+
+    /// Like [`InstanceAllocator::new`], but allocates the instance data
+    /// in a slot of `pool`, which is given back when the instance is
+    /// dropped.
+    #[cfg(unix)]
+    #[allow(clippy::type_complexity)]
+    pub fn new_pooled(
+        module: &ModuleInfo,
+        pool: &PoolingAllocator,
+    ) -> Result<
+        (
+            Self,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        let instance_layout = Self::instance_layout(&offsets);
+        let slot = pool.allocate_instance(instance_layout.size(), instance_layout.align())?;
+
+        // Slots are page-aligned, so the buffer is properly aligned.
+        #[allow(clippy::cast_ptr_alignment)]
+        let instance_ptr = NonNull::new(slot.as_ptr() as *mut Instance).unwrap();
+
+        Ok(Self::with_buffer(
+            instance_ptr,
+            instance_layout,
+            offsets,
+            Some(slot),
+        ))
+    }
+
+    fn with_buffer(
+        instance_ptr: NonNull<Instance>,
+        instance_layout: Layout,
+        offsets: VMOffsets,
+        #[cfg(unix)] pooled: Option<InstanceSlot>,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         let allocator = Self {
             instance_ptr,
             instance_layout,
             offsets,
             consumed: false,
+            #[cfg(unix)]
+            pooled,
         };
 
         // # Safety
@@ -208,6 +273,8 @@ impl InstanceAllocator {
         VMInstance {
             instance,
             instance_layout,
+            #[cfg(unix)]
+            pooled: self.pooled.take(),
         }
     }
 
@@ -26,6 +26,7 @@ pub use allocator::InstanceAllocator;
 use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::alloc::Layout;
+use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -82,6 +83,10 @@ pub(crate) struct Instance {
     /// get removed. A missing entry is considered equivalent to an empty slice.
     passive_data: RefCell<HashMap<DataIndex, Arc<[u8]>>>,
 
+    /// Objects whose lifetime is tied to this instance. They are dropped
+    /// before the func refs below, so they may safely hold pointers into them.
+    guards: Vec<Box<dyn Any + Send>>,
+
     /// Mapping of function indices to their func ref backing data. `VMFuncRef`s
     /// will point to elements here for functions defined by this instance.
     funcrefs: BoxedSlice<LocalFunctionIndex, VMCallerCheckedAnyfunc>,
@@ -411,7 +416,7 @@ impl Instance {
             .memories
             .get(memory_index)
             .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
-        mem.get_mut(self.context_mut()).grow(delta.into())
+        self.context_mut().grow_memory(mem, delta.into())
     }
 
     /// Grow imported memory by the specified amount of pages.
@@ -432,7 +437,7 @@ impl Instance {
     {
         let import = self.imported_memory(memory_index);
         let mem = import.handle;
-        mem.get_mut(self.context_mut()).grow(delta.into())
+        self.context_mut().grow_memory(mem, delta.into())
     }
 
     /// Returns the number of allocated wasm pages.
@@ -972,6 +977,10 @@ pub struct VMInstance {
     /// No one in the code has a copy of the `Instance`'s
     /// pointer. `Self` is the only one.
     instance: NonNull<Instance>,
+
+    /// The pool slot of the `Instance`, if it was allocated from a pool.
+    #[cfg(unix)]
+    pooled: Option<crate::pool::InstanceSlot>,
 }
 
 /// VMInstance are created with an InstanceAllocator
@@ -984,7 +993,12 @@ impl Drop for VMInstance {
         unsafe {
             // Need to drop all the actual Instance members
             instance_ptr.drop_in_place();
-            // And then free the memory allocated for the Instance itself
+            // And then free the memory allocated for the Instance itself,
+            // unless it lives in a pool slot, which is given back on drop.
+            #[cfg(unix)]
+            if self.pooled.is_some() {
+                return;
+            }
             std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
         }
     }
@@ -1056,6 +1070,7 @@ impl VMInstance {
                 function_call_trampolines: finished_function_call_trampolines,
                 passive_elements: Default::default(),
                 passive_data,
+                guards: Vec::new(),
                 funcrefs,
                 imported_funcrefs,
                 vmctx: VMContext {},
@@ -1188,6 +1203,27 @@ impl VMInstance {
         self.instance().module_ref()
     }
 
+    /// Return a pointer to the func ref backing the given local function.
+    ///
+    /// Tables and exported functions of this instance point to this
+    /// func ref, so updating its `func_ptr` redirects indirect calls
+    /// and host calls to the new function body.
+    pub fn local_funcref(&self, index: LocalFunctionIndex) -> NonNull<VMCallerCheckedAnyfunc> {
+        NonNull::from(&self.instance().funcrefs[index])
+    }
+
+    /// Return a pointer to the `VMGlobalDefinition` of the given local global.
+    pub fn local_global_ptr(&self, index: LocalGlobalIndex) -> NonNull<VMGlobalDefinition> {
+        self.instance().global_ptr(index)
+    }
+
+    /// Attach an object to this instance, to be dropped together with it.
+    ///
+    /// Guards are dropped before the instance's func refs are freed.
+    pub fn push_guard(&mut self, guard: Box<dyn Any + Send>) {
+        self.instance_mut().guards.push(guard);
+    }
+
     /// Lookup an export with the given name.
     pub fn lookup(&mut self, field: &str) -> Option<VMExtern> {
         let export = *self.module_ref().exports.get(field)?;
@@ -23,6 +23,8 @@ mod imports;
 mod instance;
 mod memory;
 mod mmap;
+#[cfg(unix)]
+mod pool;
 mod probestack;
 mod sig_registry;
 mod store;
@@ -42,10 +44,12 @@ pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{InstanceAllocator, VMInstance};
 pub use crate::memory::{
-    initialize_memory_with_data, LinearMemory, NotifyLocation, VMMemory, VMOwnedMemory,
-    VMSharedMemory,
+    initialize_memory_with_data, LinearMemory, MemoryLimiter, NotifyLocation, VMMemory,
+    VMOwnedMemory, VMSharedMemory,
 };
 pub use crate::mmap::Mmap;
+#[cfg(unix)]
+pub use crate::pool::{PoolingAllocator, PoolingLimits};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::store::{InternalStoreHandle, MaybeInstanceOwned, StoreHandle, StoreObjects};
@@ -679,6 +679,22 @@ pub unsafe fn initialize_memory_with_data(
     Ok(())
 }
 
+/// Decides whether the memories of a store may grow, it is attached to the
+/// store with [`StoreObjects::set_memory_limiter`](crate::StoreObjects::set_memory_limiter)
+/// and called whenever one of its memories grows (from WebAssembly or from
+/// the host)
+pub trait MemoryLimiter
+where
+    Self: std::fmt::Debug + Send + Sync,
+{
+    /// Called before a memory grows from `current` to `desired` pages, the
+    /// memory does not grow if an error is returned
+    fn memory_growing(&self, current: Pages, desired: Pages) -> Result<(), MemoryError>;
+
+    /// Called after a memory grew to `size` pages
+    fn memory_grown(&self, _size: Pages) {}
+}
+
 /// Represents memory that is used by the WebAsssembly module
 pub trait LinearMemory
 where
@@ -0,0 +1,646 @@
+//! Pooling allocation of instances, linear memories and tables.
+//!
+//! By default, every instantiation allocates its `Instance` on the heap,
+//! maps fresh memory for each linear memory and allocates each table.
+//! A [`PoolingAllocator`] instead reserves slots for all of them upfront,
+//! for a fixed number of live instances, memories and tables, and
+//! recycles a slot as soon as its owner is dropped.
+//!
+//! Slots are reset when they are released: the pages of linear memories
+//! and instances are handed back to the OS where it allows it (`madvise`
+//! on Linux, `VirtualFree` on Windows), so that they read as zeros and
+//! only the pages that are touched again cost anything, and the elements
+//! of tables are cleared.
+
+use crate::memory::{LinearMemory, VMOwnedMemory};
+use crate::store::MaybeInstanceOwned;
+use crate::table::RawTableElement;
+use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
+use crate::{Mmap, VMMemory, VMTable};
+use std::cell::UnsafeCell;
+use std::io;
+use std::ptr::{self, NonNull};
+use std::sync::{Arc, Mutex};
+use wasmer_types::{
+    MemoryError, MemoryStyle, MemoryType, Pages, TableStyle, TableType, WASM_PAGE_SIZE,
+};
+
+/// The limits of a [`PoolingAllocator`].
+#[derive(Debug, Clone)]
+pub struct PoolingLimits {
+    /// The maximum number of live instances.
+    pub instances: usize,
+    /// The maximum size in bytes of an instance, including its `VMContext`.
+    pub instance_size: usize,
+    /// The maximum number of live linear memories.
+    pub memories: usize,
+    /// The maximum size of a linear memory.
+    pub memory_pages: Pages,
+    /// Whether linear memories without a maximum, or with a maximum above
+    /// `memory_pages`, are capped at `memory_pages`. Otherwise, they can't
+    /// be allocated from the pool.
+    pub cap_memories: bool,
+    /// The size in bytes of the guard region after each linear memory.
+    pub memory_guard_size: u64,
+    /// The maximum number of live tables.
+    pub tables: usize,
+    /// The maximum number of elements of a table.
+    pub table_elements: u32,
+}
+
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            instances: 1000,
+            instance_size: 0x10_0000,
+            memories: 1000,
+            memory_pages: Pages(160),
+            cap_memories: false,
+            memory_guard_size: 0x1_0000,
+            tables: 1000,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// An allocator recycling the instances, linear memories and tables
+/// of the modules it instantiates.
+///
+/// Cloning a `PoolingAllocator` gives another handle to the same pool.
+/// The reserved address space is released once every handle and every
+/// allocation from the pool is dropped.
+#[derive(Debug, Clone)]
+pub struct PoolingAllocator {
+    pool: Arc<Pool>,
+}
+
+#[derive(Debug)]
+struct Pool {
+    limits: PoolingLimits,
+    instances: Slots,
+    instance_slot_size: usize,
+    memories: Slots,
+    memory_slot_size: usize,
+    tables: Mutex<TableSlots>,
+}
+
+/// A region divided in slots of the same size.
+#[derive(Debug)]
+struct Slots {
+    mmap: Mmap,
+    free: Mutex<Vec<usize>>,
+}
+
+#[derive(Debug)]
+struct TableSlots {
+    in_use: usize,
+    /// Allocations of tables that were dropped, to reuse.
+    free: Vec<Vec<RawTableElement>>,
+}
+
+// The pool only hands out raw slots, whose users are responsible for
+// synchronizing their accesses. Recycled table allocations hold no live
+// references.
+unsafe impl Send for Pool {}
+unsafe impl Sync for Pool {}
+
+impl Slots {
+    fn new(count: usize, slot_size: usize, accessible: bool) -> Result<Self, String> {
+        let size = count
+            .checked_mul(slot_size)
+            .ok_or_else(|| "the pool is too large".to_string())?;
+        let mmap = Mmap::accessible_reserved(if accessible { size } else { 0 }, size)?;
+        Ok(Self {
+            mmap,
+            // Hand out the lowest slots first.
+            free: Mutex::new((0..count).rev().collect()),
+        })
+    }
+
+    fn acquire(&self) -> Option<usize> {
+        self.free.lock().unwrap().pop()
+    }
+
+    fn release(&self, index: usize) {
+        self.free.lock().unwrap().push(index);
+    }
+}
+
+impl PoolingAllocator {
+    /// Reserves the address space of all the slots allowed by `limits`.
+    pub fn new(limits: PoolingLimits) -> Result<Self, String> {
+        let page_size = region::page::size();
+        let instance_slot_size = round_up(limits.instance_size, page_size);
+        let memory_slot_size = limits
+            .memory_pages
+            .bytes()
+            .0
+            .checked_add(limits.memory_guard_size as usize)
+            .map(|size| round_up(size, page_size))
+            .ok_or_else(|| "the memory slots are too large".to_string())?;
+        Ok(Self {
+            pool: Arc::new(Pool {
+                instances: Slots::new(limits.instances, instance_slot_size, true)?,
+                instance_slot_size,
+                memories: Slots::new(limits.memories, memory_slot_size, false)?,
+                memory_slot_size,
+                tables: Mutex::new(TableSlots {
+                    in_use: 0,
+                    free: Vec::new(),
+                }),
+                limits,
+            }),
+        })
+    }
+
+    /// The limits of the pool.
+    pub fn limits(&self) -> &PoolingLimits {
+        &self.pool.limits
+    }
+
+    /// Allocates a slot for an instance of `size` bytes, aligned to `align`.
+    pub(crate) fn allocate_instance(
+        &self,
+        size: usize,
+        align: usize,
+    ) -> Result<InstanceSlot, String> {
+        if size > self.pool.instance_slot_size || align > region::page::size() {
+            return Err(format!(
+                "the instance needs {} bytes, but the pool only has slots of {} bytes",
+                size, self.pool.instance_slot_size
+            ));
+        }
+        let index = self.pool.instances.acquire().ok_or_else(|| {
+            format!(
+                "all the {} instance slots of the pool are in use",
+                self.pool.limits.instances
+            )
+        })?;
+        Ok(InstanceSlot {
+            pool: self.pool.clone(),
+            index,
+        })
+    }
+
+    /// Allocates a linear memory from the pool.
+    ///
+    /// Shared memories can't be allocated from the pool, nor memories that
+    /// could grow beyond the `memory_pages` limit of the pool, unless the
+    /// pool caps them. The maximum of a capped memory is `memory_pages`.
+    ///
+    /// # Safety
+    /// - `vm_memory_location`, if any, must point to a valid location in VM memory.
+    pub unsafe fn allocate_memory(
+        &self,
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
+    ) -> Result<VMMemory, MemoryError> {
+        let limits = &self.pool.limits;
+        if memory.shared {
+            return Err(MemoryError::InvalidMemory {
+                reason: "shared memories can't be allocated from a pool".to_string(),
+            });
+        }
+        if memory.minimum > limits.memory_pages {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: memory.minimum,
+                max_allowed: limits.memory_pages,
+            });
+        }
+        if let Some(max) = memory.maximum {
+            if max < memory.minimum {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the maximum ({} pages) is less than the minimum ({} pages)",
+                        max.0, memory.minimum.0
+                    ),
+                });
+            }
+        }
+        let maximum = match memory.maximum {
+            Some(max) if max <= limits.memory_pages => max,
+            _ if limits.cap_memories => limits.memory_pages,
+            _ => {
+                return Err(MemoryError::InvalidMemory {
+                    reason: format!(
+                        "the memory can grow beyond the {} pages of a pool slot",
+                        limits.memory_pages.0
+                    ),
+                })
+            }
+        };
+        let fits = match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } => *bound <= limits.memory_pages && *offset_guard_size <= limits.memory_guard_size,
+            MemoryStyle::Dynamic { offset_guard_size } => {
+                *offset_guard_size <= limits.memory_guard_size
+            }
+        };
+        if !fits {
+            return Err(MemoryError::InvalidMemory {
+                reason: format!("the memory style {:?} doesn't fit in the pool", style),
+            });
+        }
+
+        let index = self.pool.memories.acquire().ok_or_else(|| {
+            MemoryError::Generic(format!(
+                "all the {} memory slots of the pool are in use",
+                limits.memories
+            ))
+        })?;
+        let slot = MemorySlot {
+            pool: self.pool.clone(),
+            index,
+            accessible: 0,
+        };
+        let base = slot.base();
+        let memory_definition = VMMemoryDefinition {
+            base,
+            current_length: 0,
+        };
+        let vm_memory_definition = if let Some(mut mem_loc) = vm_memory_location {
+            *mem_loc.as_mut() = memory_definition;
+            MaybeInstanceOwned::Instance(mem_loc)
+        } else {
+            MaybeInstanceOwned::Host(Box::new(UnsafeCell::new(memory_definition)))
+        };
+        let mut pooled = PooledMemory {
+            slot,
+            size: Pages(0),
+            memory: MemoryType {
+                maximum: Some(maximum),
+                ..*memory
+            },
+            style: *style,
+            vm_memory_definition,
+        };
+        pooled.grow(memory.minimum)?;
+        Ok(VMMemory(Box::new(pooled)))
+    }
+
+    /// Allocates a table from the pool.
+    ///
+    /// The table can't grow beyond the `table_elements` limit of the pool.
+    ///
+    /// # Safety
+    /// - `vm_table_location`, if any, must point to a valid location in VM memory.
+    pub unsafe fn allocate_table(
+        &self,
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: Option<NonNull<VMTableDefinition>>,
+    ) -> Result<VMTable, String> {
+        let limits = &self.pool.limits;
+        if table.minimum > limits.table_elements {
+            return Err(format!(
+                "Table minimum ({}) is larger than the pool allows ({})",
+                table.minimum, limits.table_elements
+            ));
+        }
+        let vec = {
+            let mut tables = self.pool.tables.lock().unwrap();
+            if tables.in_use == limits.tables {
+                return Err(format!(
+                    "all the {} table slots of the pool are in use",
+                    limits.tables
+                ));
+            }
+            tables.in_use += 1;
+            tables
+                .free
+                .pop()
+                .unwrap_or_else(|| Vec::with_capacity(limits.table_elements as usize))
+        };
+        let slot = TableSlot {
+            pool: self.pool.clone(),
+        };
+        VMTable::from_pool(table, style, vm_table_location, vec, slot)
+    }
+}
+
+fn round_up(size: usize, page_size: usize) -> usize {
+    (size + (page_size - 1)) & !(page_size - 1)
+}
+
+/// Hands the pages of `len` bytes at `base`, in a private anonymous
+/// mapping, back to the OS, so that they read as zeros.
+///
+/// # Safety
+/// - The range must be accessible, and not be in use.
+unsafe fn decommit(base: *mut u8, len: usize) -> io::Result<()> {
+    // On Linux, dropping the pages of a private mapping makes them read
+    // as zeros. Other systems may keep their content, so they are
+    // zeroed instead.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if libc::madvise(base as *mut libc::c_void, len, libc::MADV_DONTNEED) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    ptr::write_bytes(base, 0, len);
+    Ok(())
+}
+
+/// The slot of an `Instance` and its `VMContext`.
+#[derive(Debug)]
+pub(crate) struct InstanceSlot {
+    pool: Arc<Pool>,
+    index: usize,
+}
+
+impl InstanceSlot {
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        unsafe {
+            (self.pool.instances.mmap.as_ptr() as *mut u8)
+                .add(self.index * self.pool.instance_slot_size)
+        }
+    }
+}
+
+impl PartialEq for InstanceSlot {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pool, &other.pool) && self.index == other.index
+    }
+}
+
+impl Eq for InstanceSlot {}
+
+impl Drop for InstanceSlot {
+    fn drop(&mut self) {
+        // A slot that can't be reset is leaked rather than handed out
+        // with the content of its previous owner.
+        let base = self.as_ptr();
+        if unsafe { decommit(base, self.pool.instance_slot_size) }.is_ok() {
+            self.pool.instances.release(self.index);
+        }
+    }
+}
+
+/// The slot of a table, whose allocation is recycled by the pool.
+pub(crate) struct TableSlot {
+    pool: Arc<Pool>,
+}
+
+impl TableSlot {
+    pub(crate) fn max_elements(&self) -> u32 {
+        self.pool.limits.table_elements
+    }
+
+    /// Gives the allocation of a dropped table back to the pool, with its
+    /// elements cleared.
+    pub(crate) fn recycle(&self, mut vec: Vec<RawTableElement>) {
+        vec.fill(RawTableElement::default());
+        vec.clear();
+        self.pool.tables.lock().unwrap().free.push(vec);
+    }
+}
+
+impl Drop for TableSlot {
+    fn drop(&mut self) {
+        self.pool.tables.lock().unwrap().in_use -= 1;
+    }
+}
+
+/// The slot of a linear memory. The first `accessible` bytes are
+/// readable and writable, the rest of the slot is inaccessible.
+#[derive(Debug)]
+struct MemorySlot {
+    pool: Arc<Pool>,
+    index: usize,
+    accessible: usize,
+}
+
+impl MemorySlot {
+    fn base(&self) -> *mut u8 {
+        unsafe {
+            (self.pool.memories.mmap.as_ptr() as *mut u8)
+                .add(self.index * self.pool.memory_slot_size)
+        }
+    }
+
+    /// Makes the first `len` bytes of the slot accessible.
+    fn make_accessible(&mut self, len: usize) -> Result<(), MemoryError> {
+        if len > self.accessible {
+            let start = unsafe { self.base().add(self.accessible) };
+            unsafe { commit(start, len - self.accessible) }
+                .map_err(|e| MemoryError::Region(e.to_string()))?;
+            self.accessible = len;
+        }
+        Ok(())
+    }
+
+    /// Zeroes the accessible bytes of the slot and makes them inaccessible.
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        if self.accessible == 0 {
+            return Ok(());
+        }
+        unsafe { uncommit(self.base(), self.accessible) }
+            .map_err(|e| MemoryError::Region(e.to_string()))?;
+        self.accessible = 0;
+        Ok(())
+    }
+}
+
+/// Makes the `len` bytes at `start`, in the reserved region of a slot,
+/// readable and writable.
+///
+/// # Safety
+/// - The range must be in the reserved region of a slot.
+#[cfg(not(target_os = "windows"))]
+unsafe fn commit(start: *mut u8, len: usize) -> io::Result<()> {
+    if libc::mprotect(
+        start as *mut libc::c_void,
+        len,
+        libc::PROT_READ | libc::PROT_WRITE,
+    ) != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Makes the `len` bytes at `start`, in the reserved region of a slot,
+/// readable and writable.
+///
+/// # Safety
+/// - The range must be in the reserved region of a slot.
+#[cfg(target_os = "windows")]
+unsafe fn commit(start: *mut u8, len: usize) -> io::Result<()> {
+    use winapi::ctypes::c_void;
+    use winapi::um::memoryapi::VirtualAlloc;
+    use winapi::um::winnt::{MEM_COMMIT, PAGE_READWRITE};
+    if VirtualAlloc(start as *mut c_void, len, MEM_COMMIT, PAGE_READWRITE).is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Zeroes the `len` bytes at `base`, made accessible by [`commit`], and
+/// makes them inaccessible again.
+///
+/// # Safety
+/// - The range must not be in use.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn uncommit(base: *mut u8, len: usize) -> io::Result<()> {
+    decommit(base, len)?;
+    if libc::mprotect(base as *mut libc::c_void, len, libc::PROT_NONE) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Zeroes the `len` bytes at `base`, made accessible by [`commit`], and
+/// makes them inaccessible again.
+///
+/// # Safety
+/// - The range must not be in use.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+unsafe fn uncommit(base: *mut u8, len: usize) -> io::Result<()> {
+    // Other systems may keep the content of dropped pages, so the range
+    // is mapped again instead.
+    let ptr = libc::mmap(
+        base as *mut libc::c_void,
+        len,
+        libc::PROT_NONE,
+        libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+        -1,
+        0,
+    );
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Zeroes the `len` bytes at `base`, made accessible by [`commit`], and
+/// makes them inaccessible again.
+///
+/// # Safety
+/// - The range must not be in use.
+#[cfg(target_os = "windows")]
+unsafe fn uncommit(base: *mut u8, len: usize) -> io::Result<()> {
+    // Decommitted pages are inaccessible, and read as zeros once they are
+    // committed again.
+    use winapi::ctypes::c_void;
+    use winapi::um::memoryapi::VirtualFree;
+    use winapi::um::winnt::MEM_DECOMMIT;
+    if VirtualFree(base as *mut c_void, len, MEM_DECOMMIT) == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Drop for MemorySlot {
+    fn drop(&mut self) {
+        // A slot that can't be reset is leaked rather than handed out
+        // with the content of its previous owner.
+        if self.reset().is_ok() {
+            self.pool.memories.release(self.index);
+        }
+    }
+}
+
+/// A linear memory living in a slot of a [`PoolingAllocator`].
+#[derive(Debug)]
+struct PooledMemory {
+    slot: MemorySlot,
+    size: Pages,
+    memory: MemoryType,
+    style: MemoryStyle,
+    vm_memory_definition: MaybeInstanceOwned<VMMemoryDefinition>,
+}
+
+unsafe impl Send for PooledMemory {}
+unsafe impl Sync for PooledMemory {}
+
+impl PooledMemory {
+    fn update_definition(&mut self) {
+        unsafe {
+            let mut md_ptr = self.vm_memory_definition.as_ptr();
+            let md = md_ptr.as_mut();
+            md.current_length = self.size.bytes().0;
+            md.base = self.slot.base();
+        }
+    }
+}
+
+impl LinearMemory for PooledMemory {
+    fn ty(&self) -> MemoryType {
+        let mut ty = self.memory;
+        ty.minimum = self.size;
+        ty
+    }
+
+    fn size(&self) -> Pages {
+        self.size
+    }
+
+    fn style(&self) -> MemoryStyle {
+        self.style
+    }
+
+    fn grow(&mut self, delta: Pages) -> Result<Pages, MemoryError> {
+        let could_not_grow = MemoryError::CouldNotGrow {
+            current: self.size,
+            attempted_delta: delta,
+        };
+        if delta.0 == 0 {
+            return Ok(self.size);
+        }
+        let new_pages = self
+            .size
+            .checked_add(delta)
+            .ok_or_else(|| could_not_grow.clone())?;
+        if self.memory.maximum.map_or(false, |max| new_pages > max)
+            || new_pages > self.slot.pool.limits.memory_pages
+        {
+            return Err(could_not_grow);
+        }
+
+        self.slot.make_accessible(new_pages.bytes().0)?;
+        let prev_pages = self.size;
+        self.size = new_pages;
+        self.update_definition();
+        Ok(prev_pages)
+    }
+
+    fn grow_at_least(&mut self, min_size: u64) -> Result<(), MemoryError> {
+        let cur_size = self.size.bytes().0 as u64;
+        if cur_size < min_size {
+            let growth = min_size - cur_size;
+            let growth_pages = ((growth - 1) / WASM_PAGE_SIZE as u64) + 1;
+            self.grow(Pages(growth_pages as u32))?;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), MemoryError> {
+        self.slot.reset()?;
+        self.size = Pages(0);
+        self.update_definition();
+        Ok(())
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.vm_memory_definition.as_ptr()
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        Err(MemoryError::MemoryNotShared)
+    }
+
+    /// Copies the memory out of the pool.
+    fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        let copy = VMOwnedMemory::new(&self.ty(), &self.style)?;
+        unsafe {
+            ptr::copy_nonoverlapping(
+                self.slot.base(),
+                copy.vmmemory().as_ref().base,
+                self.size.bytes().0,
+            );
+        }
+        Ok(Box::new(copy))
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
-    VMExternObj, VMFunction, VMFunctionEnvironment, VMGlobal, VMInstance, VMMemory, VMTable,
+    LinearMemory, MemoryLimiter, VMExternObj, VMFunction, VMFunctionEnvironment, VMGlobal,
+    VMInstance, VMMemory, VMTable,
 };
 use core::slice::Iter;
-use std::{cell::UnsafeCell, fmt, marker::PhantomData, num::NonZeroUsize, ptr::NonNull};
-use wasmer_types::StoreId;
+use std::{cell::UnsafeCell, fmt, marker::PhantomData, num::NonZeroUsize, ptr::NonNull, sync::Arc};
+use wasmer_types::{MemoryError, Pages, StoreId, WASM_PAGE_SIZE};
 
 /// Trait to represent an object managed by a context. This is implemented on
 /// the VM types managed by the context.
@@ -46,6 +47,7 @@ pub struct StoreObjects {
     instances: Vec<VMInstance>,
     extern_objs: Vec<VMExternObj>,
     function_environments: Vec<VMFunctionEnvironment>,
+    memory_limiter: Option<Arc<dyn MemoryLimiter>>,
 }
 
 impl StoreObjects {
@@ -78,6 +80,73 @@ impl StoreObjects {
         }
     }
 
+    /// Sets the limiter that decides whether the memories of this store may
+    /// grow (none lets them grow up to their maximum)
+    pub fn set_memory_limiter(&mut self, limiter: Option<Arc<dyn MemoryLimiter>>) {
+        self.memory_limiter = limiter;
+    }
+
+    /// Returns the limiter that decides whether the memories of this store
+    /// may grow
+    pub fn memory_limiter(&self) -> Option<&Arc<dyn MemoryLimiter>> {
+        self.memory_limiter.as_ref()
+    }
+
+    /// Grows a memory of this store by `delta` pages if its memory limiter
+    /// allows it, returns the previous number of pages
+    pub fn grow_memory(
+        &mut self,
+        handle: InternalStoreHandle<VMMemory>,
+        delta: Pages,
+    ) -> Result<Pages, MemoryError> {
+        let limiter = self.memory_limiter.clone();
+        let memory = handle.get_mut(self);
+        let limiter = match limiter {
+            Some(limiter) => limiter,
+            None => return memory.grow(delta),
+        };
+
+        let current = memory.size();
+        let desired = current
+            .checked_add(delta)
+            .ok_or(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: delta,
+            })?;
+        limiter.memory_growing(current, desired)?;
+        let previous = memory.grow(delta)?;
+        limiter.memory_grown(memory.size());
+        Ok(previous)
+    }
+
+    /// Grows a memory of this store to at least `min_size` bytes if its
+    /// memory limiter allows it
+    pub fn grow_memory_at_least(
+        &mut self,
+        handle: InternalStoreHandle<VMMemory>,
+        min_size: u64,
+    ) -> Result<(), MemoryError> {
+        let limiter = self.memory_limiter.clone();
+        let memory = handle.get_mut(self);
+        let limiter = match limiter {
+            Some(limiter) => limiter,
+            None => return memory.grow_at_least(min_size),
+        };
+
+        let current = memory.size();
+        let desired = Pages(
+            min_size
+                .div_ceil(WASM_PAGE_SIZE as u64)
+                .min(u32::MAX as u64) as u32,
+        );
+        if desired > current {
+            limiter.memory_growing(current, desired)?;
+        }
+        memory.grow_at_least(min_size)?;
+        limiter.memory_grown(memory.size());
+        Ok(())
+    }
+
     /// Return an immutable iterator over all globals
     pub fn iter_globals(&self) -> Iter<VMGlobal> {
         self.globals.iter()
@@ -5,6 +5,8 @@
 //!
 //! `Table` is to WebAssembly tables what `Memory` is to WebAssembly linear memories.
 
+#[cfg(unix)]
+use crate::pool::TableSlot;
 use crate::store::MaybeInstanceOwned;
 use crate::vmcontext::VMTableDefinition;
 use crate::Trap;
@@ -82,6 +84,19 @@ pub struct VMTable {
     style: TableStyle,
     #[derivative(Debug = "ignore")]
     vm_table_definition: MaybeInstanceOwned<VMTableDefinition>,
+    /// The pool slot this table lives in, if any.
+    #[cfg(unix)]
+    #[derivative(Debug = "ignore")]
+    pooled: Option<TableSlot>,
+}
+
+#[cfg(unix)]
+impl Drop for VMTable {
+    fn drop(&mut self) {
+        if let Some(slot) = self.pooled.take() {
+            slot.recycle(std::mem::take(&mut self.vec));
+        }
+    }
 }
 
 impl VMTable {
@@ -113,11 +128,43 @@ impl VMTable {
         Self::new_inner(table, style, Some(vm_table_location))
     }
 
+    /// Create a new `Table` in a slot of a pool, reusing the allocation `vec`.
+    ///
+    /// The table can't grow beyond the capacity of the slot, so that `vec`
+    /// is never reallocated.
+    #[cfg(unix)]
+    pub(crate) unsafe fn from_pool(
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: Option<NonNull<VMTableDefinition>>,
+        vec: Vec<RawTableElement>,
+        slot: TableSlot,
+    ) -> Result<Self, String> {
+        let mut ret = Self::new_in(table, style, vm_table_location, vec)?;
+        let max_elements = slot.max_elements();
+        ret.maximum = Some(
+            ret.maximum
+                .map_or(max_elements, |max| max.min(max_elements)),
+        );
+        ret.pooled = Some(slot);
+        Ok(ret)
+    }
+
     /// Create a new `Table` with either self-owned or VM owned metadata.
     unsafe fn new_inner(
         table: &TableType,
         style: &TableStyle,
         vm_table_location: Option<NonNull<VMTableDefinition>>,
+    ) -> Result<Self, String> {
+        Self::new_in(table, style, vm_table_location, Vec::new())
+    }
+
+    /// Create a new `Table` whose elements are stored in `vec`.
+    unsafe fn new_in(
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: Option<NonNull<VMTableDefinition>>,
+        mut vec: Vec<RawTableElement>,
     ) -> Result<Self, String> {
         match table.ty {
             ValType::FuncRef | ValType::ExternRef => (),
@@ -138,7 +185,8 @@ impl VMTable {
         }
         let table_minimum = usize::try_from(table.minimum)
             .map_err(|_| "Table minimum is bigger than usize".to_string())?;
-        let mut vec = vec![RawTableElement::default(); table_minimum];
+        vec.clear();
+        vec.resize(table_minimum, RawTableElement::default());
         let base = vec.as_mut_ptr();
         match style {
             TableStyle::CallerChecksSignature => Ok(Self {
@@ -160,6 +208,8 @@ impl VMTable {
                         current_elements: table_minimum as _,
                     })))
                 },
+                #[cfg(unix)]
+                pooled: None,
             }),
         }
     }
@@ -4,14 +4,19 @@
 //! This is the module that facilitates the usage of Traps
 //! in Wasmer Runtime
 
+#[cfg(unix)]
+mod sampler;
 #[allow(clippy::module_inception)]
 mod trap;
 mod traphandlers;
 
+#[cfg(unix)]
+pub use sampler::StackSampler;
 pub use trap::Trap;
 pub use traphandlers::{
-    catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, set_stack_size,
-    wasmer_call_trampoline, TrapHandlerFn, VMConfig,
+    await_on_wasm_stack, catch_traps, catch_traps_async, on_host_stack, raise_lib_trap,
+    raise_user_trap, set_stack_size, wasmer_call_trampoline, wasmer_call_trampoline_async,
+    TrapHandlerFn, VMConfig, WasmCallFuture,
 };
 pub use traphandlers::{init_traps, resume_panic};
 pub use wasmer_types::TrapCode;
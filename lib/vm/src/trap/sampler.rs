@@ -0,0 +1,264 @@
+//! Sampling the stacks of the threads running wasm code.
+//!
+//! While a [`StackSampler`] exists, the threads entering wasm register
+//! themselves. Each of them is then sampled by sending it `SIGPROF`, whose
+//! handler walks the frame pointers of the interrupted wasm stack. The
+//! handler is installed with the first sampler, and the previous one is
+//! restored once the last sampler is dropped.
+
+use super::traphandlers::walk_interrupted_wasm_stack;
+use std::cell::{Cell, UnsafeCell};
+use std::io;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The maximum number of frames of a sampled stack.
+const MAX_FRAMES: usize = 256;
+
+/// How long a thread is given to handle the signal. A thread blocking
+/// `SIGPROF` is skipped after that.
+const SIGNAL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The number of live samplers.
+static SAMPLERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the handler is installed. The lock is held while samplers are
+/// created and dropped.
+static INSTALLED: Mutex<bool> = Mutex::new(false);
+
+/// Whether a signal may still be delivered to a thread that didn't handle
+/// it in time. The handler is then kept installed, as the previous one
+/// could be the default one, which kills the process.
+static STALE_SIGNALS: AtomicBool = AtomicBool::new(false);
+
+/// The threads running wasm while samplers are live. Sampling holds the
+/// lock, so that the threads can't exit while they are signalled.
+static THREADS: Mutex<Vec<libc::pthread_t>> = Mutex::new(Vec::new());
+
+static mut PREV_SIGPROF: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+
+thread_local! {
+    static REGISTERED: Cell<bool> = Cell::new(false);
+}
+
+/// The stack requested from a thread, filled by its signal handler.
+struct Request {
+    /// The requested thread, as a `usize`.
+    thread: AtomicUsize,
+    /// Whether the request is waiting for a signal handler to take it.
+    pending: AtomicBool,
+    /// Whether the signal handler filled in the frames.
+    done: AtomicBool,
+    frames: UnsafeCell<[usize; MAX_FRAMES]>,
+    len: AtomicUsize,
+}
+
+// The frames are only written by the signal handler taking the pending
+// request, and only read once it is done.
+unsafe impl Sync for Request {}
+
+static REQUEST: Request = Request {
+    thread: AtomicUsize::new(0),
+    pending: AtomicBool::new(false),
+    done: AtomicBool::new(false),
+    frames: UnsafeCell::new([0; MAX_FRAMES]),
+    len: AtomicUsize::new(0),
+};
+
+/// Samples the stacks of the threads running wasm code.
+///
+/// Only the threads entering wasm after the sampler is created are
+/// sampled.
+pub struct StackSampler {
+    _private: (),
+}
+
+impl StackSampler {
+    /// Creates a sampler.
+    pub fn new() -> Self {
+        let mut installed = INSTALLED.lock().unwrap();
+        if !*installed {
+            unsafe { install_handler() };
+            *installed = true;
+        }
+        SAMPLERS.fetch_add(1, Ordering::SeqCst);
+        Self { _private: () }
+    }
+
+    /// Samples the stack of every thread running wasm.
+    ///
+    /// Each stack starts with the program counter of the thread, followed
+    /// by the return addresses of its callers. The walk stops at the
+    /// first frame without a frame pointer.
+    pub fn sample(&self) -> Vec<Vec<usize>> {
+        let threads = THREADS.lock().unwrap();
+        threads
+            .iter()
+            .filter_map(|&thread| sample_thread(thread))
+            .filter(|frames| !frames.is_empty())
+            .collect()
+    }
+}
+
+impl Default for StackSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for StackSampler {
+    fn drop(&mut self) {
+        let mut installed = INSTALLED.lock().unwrap();
+        if SAMPLERS.fetch_sub(1, Ordering::SeqCst) == 1 && !STALE_SIGNALS.load(Ordering::SeqCst) {
+            unsafe { uninstall_handler() };
+            *installed = false;
+        }
+    }
+}
+
+/// Keeps the current thread registered for sampling.
+pub(super) struct ThreadRegistration {
+    thread: libc::pthread_t,
+}
+
+/// Registers the current thread for sampling if any sampler is live and
+/// the thread isn't registered yet.
+pub(super) fn register_thread() -> Option<ThreadRegistration> {
+    if SAMPLERS.load(Ordering::Relaxed) == 0 || REGISTERED.with(|cell| cell.replace(true)) {
+        return None;
+    }
+    let thread = unsafe { libc::pthread_self() };
+    THREADS.lock().unwrap().push(thread);
+    Some(ThreadRegistration { thread })
+}
+
+impl Drop for ThreadRegistration {
+    fn drop(&mut self) {
+        let mut threads = THREADS.lock().unwrap();
+        if let Some(pos) = threads.iter().position(|&thread| thread == self.thread) {
+            threads.swap_remove(pos);
+        }
+        REGISTERED.with(|cell| cell.set(false));
+    }
+}
+
+/// Asks `thread` for its stack and waits for it.
+fn sample_thread(thread: libc::pthread_t) -> Option<Vec<usize>> {
+    REQUEST.thread.store(thread as usize, Ordering::Relaxed);
+    REQUEST.done.store(false, Ordering::Relaxed);
+    REQUEST.pending.store(true, Ordering::Release);
+    if unsafe { libc::pthread_kill(thread, libc::SIGPROF) } != 0 {
+        REQUEST.pending.store(false, Ordering::Relaxed);
+        return None;
+    }
+
+    let deadline = Instant::now() + SIGNAL_TIMEOUT;
+    while !REQUEST.done.load(Ordering::Acquire) {
+        // Once taken by the handler, the request is always completed.
+        if Instant::now() >= deadline && REQUEST.pending.swap(false, Ordering::AcqRel) {
+            STALE_SIGNALS.store(true, Ordering::SeqCst);
+            return None;
+        }
+        thread::yield_now();
+    }
+    let len = REQUEST.len.load(Ordering::Relaxed);
+    let frames = unsafe { &*REQUEST.frames.get() };
+    Some(frames[..len].to_vec())
+}
+
+unsafe fn install_handler() {
+    let mut handler: libc::sigaction = mem::zeroed();
+    // SA_ONSTACK runs the handler on the signal stack of the thread, as the
+    // wasm stack may be close to overflowing.
+    //
+    // SA_RESTART keeps the system calls made by host functions from failing
+    // with EINTR.
+    handler.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK | libc::SA_RESTART;
+    handler.sa_sigaction = sigprof_handler as usize;
+    libc::sigemptyset(&mut handler.sa_mask);
+    if libc::sigaction(
+        libc::SIGPROF,
+        &handler,
+        ptr::addr_of_mut!(PREV_SIGPROF).cast(),
+    ) != 0
+    {
+        panic!(
+            "unable to install signal handler: {}",
+            io::Error::last_os_error(),
+        );
+    }
+}
+
+/// Restores the previous handler, unless another one replaced ours.
+unsafe fn uninstall_handler() {
+    let mut current: libc::sigaction = mem::zeroed();
+    if libc::sigaction(libc::SIGPROF, ptr::null(), &mut current) != 0
+        || current.sa_sigaction != sigprof_handler as usize
+    {
+        return;
+    }
+    libc::sigaction(
+        libc::SIGPROF,
+        ptr::addr_of!(PREV_SIGPROF).cast(),
+        ptr::null_mut(),
+    );
+}
+
+unsafe extern "C" fn sigprof_handler(
+    signum: libc::c_int,
+    siginfo: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    if REQUEST.pending.load(Ordering::Acquire)
+        && REQUEST.thread.load(Ordering::Relaxed) == libc::pthread_self() as usize
+        && REQUEST
+            .pending
+            .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+    {
+        let len = walk_interrupted_wasm_stack(context, &mut *REQUEST.frames.get());
+        REQUEST.len.store(len, Ordering::Relaxed);
+        REQUEST.done.store(true, Ordering::Release);
+        return;
+    }
+
+    // This signal wasn't sent by a sampler, or arrived too late. Forward
+    // it to the previous handler, unless that would kill the process.
+    let previous = &*ptr::addr_of!(PREV_SIGPROF).cast::<libc::sigaction>();
+    if previous.sa_flags & libc::SA_SIGINFO != 0 {
+        mem::transmute::<usize, extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void)>(
+            previous.sa_sigaction,
+        )(signum, siginfo, context)
+    } else if previous.sa_sigaction != libc::SIG_DFL && previous.sa_sigaction != libc::SIG_IGN {
+        mem::transmute::<usize, extern "C" fn(libc::c_int)>(previous.sa_sigaction)(signum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current_handler() -> usize {
+        unsafe {
+            let mut current: libc::sigaction = mem::zeroed();
+            assert_eq!(libc::sigaction(libc::SIGPROF, ptr::null(), &mut current), 0);
+            current.sa_sigaction
+        }
+    }
+
+    #[test]
+    fn handler_is_uninstalled_with_the_last_sampler() {
+        let previous = current_handler();
+        let first = StackSampler::new();
+        let second = StackSampler::new();
+        assert_eq!(current_handler(), sigprof_handler as usize);
+        drop(first);
+        assert_eq!(current_handler(), sigprof_handler as usize);
+        drop(second);
+        assert_eq!(current_handler(), previous);
+    }
+}
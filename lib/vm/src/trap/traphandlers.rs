@@ -15,13 +15,16 @@ use scopeguard::defer;
 use std::any::Any;
 use std::cell::Cell;
 use std::error::Error;
+use std::future::Future;
 use std::io;
 use std::mem;
 #[cfg(unix)]
 use std::mem::MaybeUninit;
+use std::pin::Pin;
 use std::ptr::{self, NonNull};
 use std::sync::atomic::{compiler_fence, AtomicPtr, AtomicUsize, Ordering};
 use std::sync::Once;
+use std::task::{Context, Poll};
 use wasmer_types::TrapCode;
 
 /// Configuration for the the runtime VM
@@ -130,6 +133,7 @@ unsafe fn process_illegal_op(addr: usize) -> Option<TrapCode> {
             8 => Some(TrapCode::BadConversionToInteger),
             9 => Some(TrapCode::UnreachableCodeReached),
             10 => Some(TrapCode::UnalignedAtomic),
+            11 => Some(TrapCode::StackLimitExceeded),
             _ => None,
         },
     }
@@ -346,6 +350,92 @@ cfg_if::cfg_if! {
             (pc, sp)
         }
 
+        /// Returns the frame pointer if frames are chained through it on
+        /// this platform, with the caller's frame pointer and the return
+        /// address stored next to each other.
+        unsafe fn get_fp(context: &ucontext_t) -> Option<usize> {
+            cfg_if::cfg_if! {
+                if #[cfg(all(
+                    any(target_os = "linux", target_os = "android"),
+                    target_arch = "x86_64",
+                ))] {
+                    Some(context.uc_mcontext.gregs[libc::REG_RBP as usize] as usize)
+                } else if #[cfg(all(target_os = "freebsd", target_arch = "x86_64"))] {
+                    Some(context.uc_mcontext.mc_rbp as usize)
+                } else if #[cfg(all(target_vendor = "apple", target_arch = "x86_64"))] {
+                    Some((*context.uc_mcontext).__ss.__rbp as usize)
+                } else if #[cfg(all(
+                    any(target_os = "linux", target_os = "android"),
+                    target_arch = "aarch64",
+                ))] {
+                    Some(context.uc_mcontext.regs[29] as usize)
+                } else if #[cfg(all(target_vendor = "apple", target_arch = "aarch64"))] {
+                    Some((*context.uc_mcontext).__ss.__fp as usize)
+                } else if #[cfg(all(target_os = "freebsd", target_arch = "aarch64"))] {
+                    Some(context.uc_mcontext.mc_gpregs.gp_x[29] as usize)
+                } else {
+                    let _ = context;
+                    None
+                }
+            }
+        }
+
+        /// Walks the wasm stack interrupted by a signal, storing the
+        /// interrupted program counter followed by the return addresses
+        /// found through the frame pointers in `frames`.
+        ///
+        /// Returns the number of frames stored, 0 if the thread was not
+        /// running wasm. Only the program counter is stored when the thread
+        /// was running a host function on the host stack. Only the live part
+        /// of the wasm stack is read, so a frame without a frame pointer
+        /// (such as the one of a host function) ends the walk early rather
+        /// than crashing it.
+        pub(super) unsafe fn walk_interrupted_wasm_stack(
+            context: *mut libc::c_void,
+            frames: &mut [usize],
+        ) -> usize {
+            let ptr = TRAP_HANDLER.with(|ptr| ptr.load(Ordering::Relaxed));
+            if ptr.is_null() || frames.is_empty() {
+                return 0;
+            }
+            let ctx = &*ptr;
+            let in_bounds = |addr: usize| (ctx.stack_ptr_in_bounds)(ctx.inner, addr);
+
+            let ucontext = &*(context as *const ucontext_t);
+            let (pc, sp) = get_pc_sp(ucontext);
+            frames[0] = pc;
+            let mut len = 1;
+            if !in_bounds(sp) {
+                return len;
+            }
+
+            let word = mem::size_of::<usize>();
+            let mut fp = match get_fp(ucontext) {
+                Some(fp) => fp,
+                None => return len,
+            };
+            while len < frames.len()
+                && fp >= sp
+                && fp % word == 0
+                && in_bounds(fp)
+                && in_bounds(fp + 2 * word - 1)
+            {
+                let next = *(fp as *const usize);
+                let ret = *((fp + word) as *const usize);
+                if ret == 0 {
+                    break;
+                }
+                frames[len] = ret;
+                len += 1;
+                // Callers live higher up the stack.
+                if next <= fp {
+                    break;
+                }
+                fp = next;
+            }
+            len
+        }
+
         unsafe fn update_context(context: &mut ucontext_t, regs: TrapHandlerRegs) {
             cfg_if::cfg_if! {
                 if #[cfg(all(
@@ -669,6 +759,33 @@ pub unsafe fn wasmer_call_trampoline(
     })
 }
 
+/// Asynchronous version of [`wasmer_call_trampoline`].
+///
+/// The returned future runs the wasm function on its own stack. Whenever an
+/// async host function called from wasm (see [`await_on_wasm_stack`]) is
+/// waiting on a future that is not ready yet, the wasm stack is suspended and
+/// the returned future yields `Poll::Pending`.
+///
+/// # Safety
+///
+/// Same requirements as [`wasmer_call_trampoline`]. In addition the pointers
+/// passed in must stay valid until the returned future completes or is
+/// dropped.
+pub unsafe fn wasmer_call_trampoline_async<'a>(
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+    config: &VMConfig,
+    vmctx: VMFunctionContext,
+    trampoline: VMTrampoline,
+    callee: *const VMFunctionBody,
+    values_vec: *mut u8,
+) -> WasmCallFuture<'a, ()> {
+    catch_traps_async(trap_handler, config, move || {
+        mem::transmute::<_, extern "C" fn(VMFunctionContext, *const VMFunctionBody, *mut u8)>(
+            trampoline,
+        )(vmctx, callee, values_vec);
+    })
+}
+
 /// Catches any wasm traps that happen within the execution of `closure`,
 /// returning them as a `Result`.
 ///
@@ -691,6 +808,39 @@ where
     on_wasm_stack(stack_size, trap_handler, closure).map_err(UnwindReason::into_trap)
 }
 
+/// Asynchronous version of [`catch_traps`].
+///
+/// `closure` is run on a separate wasm stack which can be suspended by async
+/// host functions. Traps are returned as the output of the future.
+///
+/// # Safety
+///
+/// Highly unsafe since `closure` won't have any dtors run.
+pub unsafe fn catch_traps_async<'a, F, R>(
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+    config: &VMConfig,
+    closure: F,
+) -> WasmCallFuture<'a, R>
+where
+    F: FnOnce() -> R + 'a,
+{
+    let stack_size = config
+        .wasm_stack_size
+        .unwrap_or_else(|| DEFAULT_STACK_SIZE.load(Ordering::Relaxed));
+    let stack = STACK_POOL
+        .pop()
+        .unwrap_or_else(|| DefaultStack::new(stack_size).unwrap());
+    let coro = ScopedCoroutine::with_stack(stack, move |yielder, ()| {
+        set_yielder(Some(yielder.into()));
+
+        Ok(closure())
+    });
+    WasmCallFuture {
+        coro: Some(coro),
+        trap_handler,
+    }
+}
+
 // We need two separate thread-local variables here:
 // - YIELDER is set within the new stack and is used to unwind back to the root
 //   of the stack from inside it.
@@ -699,9 +849,54 @@ where
 //
 // We also do per-thread signal stack initialization on the first time
 // TRAP_HANDLER is accessed.
+//
+// ASYNC_CX holds the task context of the future currently driving the wasm
+// stack. It is null when the wasm stack was entered synchronously, in which
+// case async host functions can't suspend it.
 thread_local! {
-    static YIELDER: Cell<Option<NonNull<Yielder<(), UnwindReason>>>> = Cell::new(None);
+    static YIELDER: Cell<Option<YielderPtr>> = Cell::new(None);
     static TRAP_HANDLER: AtomicPtr<TrapHandlerContext> = AtomicPtr::new(ptr::null_mut());
+    static ASYNC_CX: Cell<*mut Context<'static>> = Cell::new(ptr::null_mut());
+}
+
+// The wasm stack can be suspended on one thread and resumed on another, so
+// YIELDER and ASYNC_CX are only accessed through these functions, which are
+// never inlined. Otherwise, the address of a thread-local variable computed
+// before the stack is suspended could be reused once it is resumed, and
+// point to the variable of the previous thread.
+type YielderPtr = NonNull<Yielder<(), CoroutineYield>>;
+
+#[inline(never)]
+fn yielder() -> Option<YielderPtr> {
+    YIELDER.with(|cell| cell.get())
+}
+
+#[inline(never)]
+fn set_yielder(yielder: Option<YielderPtr>) {
+    YIELDER.with(|cell| cell.set(yielder))
+}
+
+#[inline(never)]
+fn replace_yielder(yielder: Option<YielderPtr>) -> Option<YielderPtr> {
+    YIELDER.with(|cell| cell.replace(yielder))
+}
+
+#[inline(never)]
+fn async_cx() -> *mut Context<'static> {
+    ASYNC_CX.with(|cell| cell.get())
+}
+
+#[inline(never)]
+fn replace_async_cx(cx: *mut Context<'static>) -> *mut Context<'static> {
+    ASYNC_CX.with(|cell| cell.replace(cx))
+}
+
+// Allocating a new stack is pretty expensive since it involves several
+// system calls. We therefore keep a cache of pre-allocated stacks which
+// allows them to be reused multiple times.
+// FIXME(Amanieu): We should refactor this to avoid the lock.
+lazy_static::lazy_static! {
+    static ref STACK_POOL: crossbeam_queue::SegQueue<DefaultStack> = crossbeam_queue::SegQueue::new();
 }
 
 /// Read-only information that is used by signal handlers to handle and recover
@@ -717,6 +912,8 @@ struct TrapHandlerContext {
         Option<TrapCode>,
         &mut dyn FnMut(TrapHandlerRegs),
     ) -> bool,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    stack_ptr_in_bounds: fn(*const u8, usize) -> bool,
     custom_trap: Option<*const TrapHandlerFn<'static>>,
 }
 struct TrapHandlerContextInner<T> {
@@ -752,13 +949,25 @@ impl TrapHandlerContext {
                 )
             }
         }
+        fn stack_ptr_in_bounds<T>(ptr: *const u8, sp: usize) -> bool {
+            unsafe {
+                (*(ptr as *const TrapHandlerContextInner<T>))
+                    .coro_trap_handler
+                    .stack_ptr_in_bounds(sp)
+            }
+        }
         let inner = TrapHandlerContextInner { coro_trap_handler };
         let ctx = Self {
             inner: &inner as *const _ as *const u8,
             handle_trap: func::<T>,
+            stack_ptr_in_bounds: stack_ptr_in_bounds::<T>,
             custom_trap,
         };
 
+        // Let the stack sampler find this thread while it runs wasm.
+        #[cfg(unix)]
+        let _sampled = super::sampler::register_thread();
+
         compiler_fence(Ordering::Release);
         let prev = TRAP_HANDLER.with(|ptr| {
             let prev = ptr.load(Ordering::Relaxed);
@@ -860,6 +1069,15 @@ impl<T> TrapHandlerContextInner<T> {
     }
 }
 
+/// Value passed from the wasm stack back to its root when the coroutine is
+/// suspended.
+enum CoroutineYield {
+    /// The wasm stack must be unwound.
+    Unwind(UnwindReason),
+    /// An async host function is waiting on a future that is not ready yet.
+    Suspend,
+}
+
 enum UnwindReason {
     /// A panic caused by the host
     Panic(Box<dyn Any + Send>),
@@ -891,11 +1109,9 @@ impl UnwindReason {
 }
 
 unsafe fn unwind_with(reason: UnwindReason) -> ! {
-    let yielder = YIELDER
-        .with(|cell| cell.replace(None))
-        .expect("not running on Wasm stack");
+    let yielder = replace_yielder(None).expect("not running on Wasm stack");
 
-    yielder.as_ref().suspend(reason);
+    yielder.as_ref().suspend(CoroutineYield::Unwind(reason));
 
     // on_wasm_stack will forcibly reset the coroutine stack after yielding.
     unreachable!();
@@ -909,13 +1125,6 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
     trap_handler: Option<*const TrapHandlerFn<'static>>,
     f: F,
 ) -> Result<T, UnwindReason> {
-    // Allocating a new stack is pretty expensive since it involves several
-    // system calls. We therefore keep a cache of pre-allocated stacks which
-    // allows them to be reused multiple times.
-    // FIXME(Amanieu): We should refactor this to avoid the lock.
-    lazy_static::lazy_static! {
-        static ref STACK_POOL: crossbeam_queue::SegQueue<DefaultStack> = crossbeam_queue::SegQueue::new();
-    }
     let stack = STACK_POOL
         .pop()
         .unwrap_or_else(|| DefaultStack::new(stack_size).unwrap());
@@ -924,21 +1133,28 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
     // Create a coroutine with a new stack to run the function on.
     let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, ()| {
         // Save the yielder to TLS so that it can be used later.
-        YIELDER.with(|cell| cell.set(Some(yielder.into())));
+        set_yielder(Some(yielder.into()));
 
         Ok(f())
     });
 
     // Ensure that YIELDER is reset on exit even if the coroutine panics,
     defer! {
-        YIELDER.with(|cell| cell.set(None));
+        set_yielder(None);
+    }
+
+    // A synchronous call can't be suspended, even if it is nested inside an
+    // async one: hide the async context from the functions it calls.
+    let async_cx = replace_async_cx(ptr::null_mut());
+    defer! {
+        replace_async_cx(async_cx);
     }
 
     // Set up metadata for the trap handler for the duration of the coroutine
     // execution. This is restored to its previous value afterwards.
     TrapHandlerContext::install(trap_handler, coro.trap_handler(), || {
         match coro.resume(()) {
-            CoroutineResult::Yield(trap) => {
+            CoroutineResult::Yield(CoroutineYield::Unwind(trap)) => {
                 // This came from unwind_with which requires that there be only
                 // Wasm code on the stack.
                 unsafe {
@@ -946,11 +1162,161 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
                 }
                 Err(trap)
             }
+            CoroutineResult::Yield(CoroutineYield::Suspend) => {
+                unreachable!("synchronous wasm calls can't be suspended")
+            }
             CoroutineResult::Return(result) => result,
         }
     })
 }
 
+/// Future returned by [`catch_traps_async`] and
+/// [`wasmer_call_trampoline_async`].
+///
+/// Dropping the future before it completes cancels the call: the wasm stack
+/// is resumed one last time so that the pending host future gets dropped,
+/// after which the call is unwound with a trap.
+pub struct WasmCallFuture<'a, T> {
+    #[allow(clippy::type_complexity)]
+    coro: Option<ScopedCoroutine<'a, (), CoroutineYield, Result<T, UnwindReason>, DefaultStack>>,
+    trap_handler: Option<*const TrapHandlerFn<'static>>,
+}
+
+// SAFETY: the wasm stack is only ever resumed from `poll` and `drop`, which
+// take `&mut self`, and all the thread-local state used by the coroutine is
+// restored on every resume. Everything living on the wasm stack belongs to
+// the call that owns this future: the wasm frames only refer to the store
+// that the call borrows exclusively, and the pending futures of async host
+// functions are required to be `Send` by the `wasmer` API (see
+// `AsyncFunctionFuture`), which is the only way to suspend the stack.
+unsafe impl<'a, T: Send> Send for WasmCallFuture<'a, T> {}
+
+impl<'a, T> WasmCallFuture<'a, T> {
+    /// Resumes the wasm stack with the given async context, returning `None`
+    /// if it was suspended again.
+    fn resume(&mut self, cx: *mut Context<'static>) -> Option<Result<T, UnwindReason>> {
+        let coro = self
+            .coro
+            .as_mut()
+            .expect("`WasmCallFuture` polled after completion");
+
+        defer! {
+            set_yielder(None);
+        }
+        let prev_cx = replace_async_cx(cx);
+        defer! {
+            replace_async_cx(prev_cx);
+        }
+
+        let result = TrapHandlerContext::install(self.trap_handler, coro.trap_handler(), || {
+            match coro.resume(()) {
+                CoroutineResult::Yield(CoroutineYield::Unwind(trap)) => {
+                    // This came from unwind_with which requires that there be
+                    // only Wasm code on the stack.
+                    unsafe {
+                        coro.force_reset();
+                    }
+                    Some(Err(trap))
+                }
+                CoroutineResult::Yield(CoroutineYield::Suspend) => None,
+                CoroutineResult::Return(result) => Some(result),
+            }
+        })?;
+
+        let coro = self.coro.take().unwrap();
+        STACK_POOL.push(coro.into_stack());
+        Some(result)
+    }
+}
+
+impl<'a, T> Future for WasmCallFuture<'a, T> {
+    type Output = Result<T, Trap>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Signal handling has to be set up on whichever thread we are polled.
+        if let Err(trap) = lazy_per_thread_init() {
+            return Poll::Ready(Err(trap));
+        }
+
+        let this = self.get_mut();
+        let cx = (cx as *mut Context<'_>).cast::<Context<'static>>();
+        match this.resume(cx) {
+            Some(result) => Poll::Ready(result.map_err(UnwindReason::into_trap)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<'a, T> Drop for WasmCallFuture<'a, T> {
+    fn drop(&mut self) {
+        let started = match self.coro.as_ref() {
+            Some(coro) => coro.started(),
+            None => return,
+        };
+        if !started {
+            // Nothing is running on the stack yet, so it can be dropped as is.
+            return;
+        }
+
+        // Resume the stack without an async context: the pending host
+        // function drops its future and raises a trap, which unwinds the
+        // stack back to its root.
+        if lazy_per_thread_init().is_ok() {
+            if let Some(Err(UnwindReason::Panic(panic))) = self.resume(ptr::null_mut()) {
+                if !std::thread::panicking() {
+                    std::panic::resume_unwind(panic);
+                }
+            }
+        }
+
+        // If the stack could not be unwound, leak it rather than reusing
+        // memory that may still be referenced.
+        if let Some(coro) = self.coro.take() {
+            mem::forget(coro);
+        }
+    }
+}
+
+/// Drives `future` to completion from an async host function.
+///
+/// If the current wasm call was started with [`catch_traps_async`], the wasm
+/// stack is suspended every time `future` is pending and resumed once the
+/// outer future is polled again.
+///
+/// Returns `None` if the wasm stack can't be suspended, either because the
+/// call was started synchronously or because it was cancelled. `future` is
+/// dropped in that case and the caller is expected to raise a trap.
+///
+/// `future` must be `Send` since it lives on the wasm stack, which moves
+/// between threads together with the [`WasmCallFuture`] that owns it.
+pub fn await_on_wasm_stack<F: Future + Send>(future: F) -> Option<F::Output> {
+    let mut future = std::pin::pin!(future);
+    loop {
+        let cx = async_cx();
+        let yielder = yielder();
+        let yielder = match yielder {
+            Some(yielder) if !cx.is_null() => yielder,
+            _ => return None,
+        };
+
+        // The future is arbitrary host code, so it is polled on the host
+        // stack rather than on the wasm stack whose usage is under the
+        // control of the guest.
+        let poll = on_host_stack(|| future.as_mut().poll(unsafe { &mut *cx }));
+        if let Poll::Ready(output) = poll {
+            return Some(output);
+        }
+
+        unsafe {
+            yielder.as_ref().suspend(CoroutineYield::Suspend);
+        }
+
+        // We may have been resumed from another thread, so restore the
+        // thread-local state of the wasm stack.
+        set_yielder(Some(yielder));
+    }
+}
+
 /// When executing on the Wasm stack, temporarily switch back to the host stack
 /// to perform an operation that should not be constrainted by the Wasm stack
 /// limits.
@@ -962,7 +1328,7 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
 pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
     // Reset YIEDER to None for the duration of this call to indicate that we
     // are no longer on the Wasm stack.
-    let yielder_ptr = YIELDER.with(|cell| cell.replace(None));
+    let yielder_ptr = replace_yielder(None);
 
     // If we are already on the host stack, execute the function directly. This
     // happens if a host function is called directly from the API.
@@ -973,7 +1339,7 @@ pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
 
     // Restore YIELDER upon exiting normally or unwinding.
     defer! {
-        YIELDER.with(|cell| cell.set(yielder_ptr));
+        set_yielder(yielder_ptr);
     }
 
     // on_parent_stack requires the closure to be Send so that the Yielder
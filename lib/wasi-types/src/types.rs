@@ -282,6 +282,7 @@ pub mod net {
     pub const __WASI_SOCK_RECV_INPUT_DATA_TRUNCATED: RiFlags = 1 << 2;
 
     pub const __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED: RoFlags = 1 << 0;
+    pub const __WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED: RoFlags = 1 << 1;
 
     pub const __WASI_SHUT_RD: SdFlags = 1 << 0;
     pub const __WASI_SHUT_WR: SdFlags = 1 << 1;
@@ -2415,6 +2415,9 @@ wai_bindgen_rust::bitflags::bitflags! {
         const NON_BLOCKING = 1 << 0 ;
         #[doc = " Return if a process is stopped"]
         const WAKE_STOPPED = 1 << 1 ;
+        #[doc = " Join on any child that is a member of the process group"]
+        #[doc = " (the caller's group when no ID is given)"]
+        const PROCESS_GROUP = 1 << 2 ;
     }
 }
 impl JoinFlags {
@@ -211,6 +211,23 @@ unsafe impl wasmer::FromToNativeWasmType for Fstflags {
     }
 }
 
+// TODO: if necessary, must be implemented in wit-bindgen
+unsafe impl wasmer::FromToNativeWasmType for Subclockflags {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.bits() as i32
+    }
+    fn from_native(n: Self::Native) -> Self {
+        Self::from_bits_truncate(n as u16)
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        // TODO: find correct implementation
+        false
+    }
+}
+
 // TODO: if necessary, must be implemented in wit-bindgen
 unsafe impl wasmer::FromToNativeWasmType for Oflags {
     type Native = i32;
@@ -1,3 +1,5 @@
+use std::{collections::HashMap, path::PathBuf};
+
 use crate::http::HttpClientCapabilityV1;
 
 /// Defines capabilities for a Wasi environment.
@@ -6,6 +8,8 @@ pub struct Capabilities {
     pub insecure_allow_all: bool,
     pub http_client: HttpClientCapabilityV1,
     pub threading: CapabilityThreadingV1,
+    pub unix_sockets: CapabilityUnixSocketsV1,
+    pub limits: CapabilityLimitsV1,
 }
 
 impl Capabilities {
@@ -14,6 +18,8 @@ impl Capabilities {
             insecure_allow_all: false,
             http_client: Default::default(),
             threading: Default::default(),
+            unix_sockets: Default::default(),
+            limits: Default::default(),
         }
     }
 
@@ -24,10 +30,14 @@ impl Capabilities {
             insecure_allow_all,
             http_client,
             threading,
+            unix_sockets,
+            limits,
         } = other;
         self.insecure_allow_all |= insecure_allow_all;
         self.http_client.update(http_client);
         self.threading.update(threading);
+        self.unix_sockets.update(unix_sockets);
+        self.limits.update(limits);
     }
 }
 
@@ -60,3 +70,73 @@ impl CapabilityThreadingV1 {
         self.max_threads = max_threads.or(self.max_threads);
     }
 }
+
+/// Defines UNIX socket related permissions.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilityUnixSocketsV1 {
+    /// UNIX sockets of the host that can be connected to, keyed by the path
+    /// the guest connects to.
+    ///
+    /// Connecting goes through the [`virtual_net::VirtualNetworking`] of the
+    /// runtime, which must support [`virtual_net::VirtualNetworking::connect_unix`].
+    pub host_bridges: HashMap<PathBuf, PathBuf>,
+}
+
+impl CapabilityUnixSocketsV1 {
+    pub fn update(&mut self, other: CapabilityUnixSocketsV1) {
+        let CapabilityUnixSocketsV1 { host_bridges } = other;
+        self.host_bridges.extend(host_bridges);
+    }
+}
+
+/// Defines the default resource limits of processes (see `setrlimit`).
+///
+/// Processes start with these as both their soft and hard limits, they can
+/// lower them but only privileged processes can raise them again.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilityLimitsV1 {
+    /// Maximum number of file descriptors that a process can have open.
+    ///
+    /// [`None`] means no limit.
+    pub max_open_files: Option<u64>,
+
+    /// Maximum size in bytes that a process can extend files to.
+    ///
+    /// [`None`] means no limit.
+    pub max_file_size: Option<u64>,
+
+    /// Maximum number of pages of linear memory of a process, this caps
+    /// the memories that are created for processes (e.g. the shared memory
+    /// of multi-threaded modules)
+    ///
+    /// [`None`] means no limit.
+    pub max_memory_pages: Option<u64>,
+
+    /// Maximum number of seconds of CPU time that a process can consume
+    /// before it is killed.
+    ///
+    /// [`None`] means no limit.
+    pub max_cpu_seconds: Option<u64>,
+
+    /// Maximum number of child processes that a process can have.
+    ///
+    /// [`None`] means no limit.
+    pub max_processes: Option<u64>,
+}
+
+impl CapabilityLimitsV1 {
+    pub fn update(&mut self, other: CapabilityLimitsV1) {
+        let CapabilityLimitsV1 {
+            max_open_files,
+            max_file_size,
+            max_memory_pages,
+            max_cpu_seconds,
+            max_processes,
+        } = other;
+        self.max_open_files = max_open_files.or(self.max_open_files);
+        self.max_file_size = max_file_size.or(self.max_file_size);
+        self.max_memory_pages = max_memory_pages.or(self.max_memory_pages);
+        self.max_cpu_seconds = max_cpu_seconds.or(self.max_cpu_seconds);
+        self.max_processes = max_processes.or(self.max_processes);
+    }
+}
@@ -18,7 +18,7 @@ use crate::{net::socket::InodeSocket, syscalls::EpollJoinWaker};
 
 use super::{
     InodeGuard, InodeValFilePollGuard, InodeValFilePollGuardJoin, InodeValFilePollGuardMode,
-    InodeWeakGuard, NotificationInner,
+    InodeWeakGuard, NotificationInner, SignalFdInner, TimerFdInner,
 };
 
 #[derive(Debug, Clone)]
@@ -54,6 +54,10 @@ impl Fd {
     ///
     /// This permission is currently unused when deserializing.
     pub const CREATE: u16 = 16;
+    /// The permissions of the file were checked when this [`Fd`] was opened,
+    /// the other descriptors (e.g. the ones the process was started with)
+    /// are checked when they are written to.
+    pub const ACCESS_CHECKED: u16 = 32;
 }
 
 /// A file that Wasi knows about that may or may not be open
@@ -222,4 +226,18 @@ pub enum Kind {
     EventNotifications {
         inner: Arc<NotificationInner>,
     },
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    TimerFd {
+        /// Counts the expirations of the timer that have not been read yet
+        inner: Arc<NotificationInner>,
+        /// The timer that is counted
+        timer: Arc<TimerFdInner>,
+    },
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    SignalFd {
+        /// Counts the signals that have not been read yet
+        inner: Arc<NotificationInner>,
+        /// The signals that are received by the descriptor
+        signals: Arc<SignalFdInner>,
+    },
 }
@@ -28,8 +28,15 @@ use crate::{
 pub(crate) enum InodeValFilePollGuardMode {
     File(Arc<RwLock<Box<dyn VirtualFile + Send + Sync + 'static>>>),
     EventNotifications(Arc<NotificationInner>),
-    Socket { inner: Arc<InodeSocketInner> },
-    Pipe { pipe: Arc<RwLock<Box<VirtualPipe>>> },
+    /// Timer and signal descriptors, which are readable while their counter
+    /// is non-zero and are never writable
+    Counter(Arc<NotificationInner>),
+    Socket {
+        inner: Arc<InodeSocketInner>,
+    },
+    Pipe {
+        pipe: Arc<RwLock<Box<VirtualPipe>>>,
+    },
 }
 
 pub struct InodeValFilePollGuard {
@@ -50,6 +57,9 @@ impl InodeValFilePollGuard {
             Kind::EventNotifications { inner, .. } => {
                 InodeValFilePollGuardMode::EventNotifications(inner.clone())
             }
+            Kind::TimerFd { inner, .. } | Kind::SignalFd { inner, .. } => {
+                InodeValFilePollGuardMode::Counter(inner.clone())
+            }
             Kind::Socket { socket, .. } => InodeValFilePollGuardMode::Socket {
                 inner: socket.inner.clone(),
             },
@@ -82,6 +92,9 @@ impl std::fmt::Debug for InodeValFilePollGuard {
             InodeValFilePollGuardMode::EventNotifications { .. } => {
                 write!(f, "guard-notifications(fd={}, peb={})", self.fd, self.peb)
             }
+            InodeValFilePollGuardMode::Counter(..) => {
+                write!(f, "guard-counter(fd={}, peb={})", self.fd, self.peb)
+            }
             InodeValFilePollGuardMode::Socket { inner } => {
                 let inner = inner.protected.read().unwrap();
                 match inner.kind {
@@ -149,6 +162,7 @@ impl InodeValFilePollGuardJoin {
             InodeValFilePollGuardMode::EventNotifications(inner) => {
                 inner.reset();
             }
+            InodeValFilePollGuardMode::Counter(_) => {}
             InodeValFilePollGuardMode::Socket { .. } => {}
             InodeValFilePollGuardMode::Pipe { .. } => {}
         }
@@ -197,6 +211,7 @@ impl Future for InodeValFilePollGuardJoin {
                     file.poll_read_ready(cx)
                 }
                 InodeValFilePollGuardMode::EventNotifications(inner) => inner.poll(waker).map(Ok),
+                InodeValFilePollGuardMode::Counter(inner) => inner.poll_nonzero(waker).map(Ok),
                 InodeValFilePollGuardMode::Socket { ref inner } => {
                     let mut guard = inner.protected.write().unwrap();
                     guard.poll_read_ready(cx)
@@ -287,6 +302,7 @@ impl Future for InodeValFilePollGuardJoin {
                     file.poll_write_ready(cx)
                 }
                 InodeValFilePollGuardMode::EventNotifications(inner) => inner.poll(waker).map(Ok),
+                InodeValFilePollGuardMode::Counter(_) => Poll::Pending,
                 InodeValFilePollGuardMode::Socket { ref inner } => {
                     let mut guard = inner.protected.write().unwrap();
                     guard.poll_write_ready(cx)
@@ -1,6 +1,10 @@
 mod fd;
 mod inode_guard;
 mod notification;
+pub(crate) mod proc_fs;
+mod pts_fs;
+mod signal_fd;
+mod timer_fd;
 
 use std::{
     borrow::{Borrow, Cow},
@@ -17,14 +21,17 @@ use std::{
 
 use crate::{
     net::socket::InodeSocketKind,
+    os::task::{control_plane::WasiControlPlane, credentials::WasiCredentials},
     state::{Stderr, Stdin, Stdout},
+    WasiProcessId,
 };
 use futures::{future::BoxFuture, Future, TryStreamExt};
 #[cfg(feature = "enable-serde")]
 use serde_derive::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, trace};
-use virtual_fs::{copy_reference, FileSystem, FsError, OpenOptions, VirtualFile};
+use virtual_fs::{copy_reference, FileSystem, FsError, OpenOptions, TmpFileSystem, VirtualFile};
+use virtual_mio::InlineWaker;
 use wasmer_wasix_types::{
     types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO},
     wasi::{
@@ -39,6 +46,11 @@ pub(crate) use self::inode_guard::{
     InodeValFileReadGuard, InodeValFileWriteGuard, WasiStateFileGuard, POLL_GUARD_MAX_RET,
 };
 pub use self::notification::NotificationInner;
+pub use self::proc_fs::ProcFileSystem;
+use self::proc_fs::ProcMountFileSystem;
+pub use self::pts_fs::PtsFileSystem;
+pub use self::signal_fd::{signal_fd_siginfo, SignalFdInner, SIGNALFD_SIGINFO_SIZE};
+pub use self::timer_fd::TimerFdInner;
 use crate::syscalls::map_io_err;
 use crate::{bin_factory::BinaryPackage, state::PreopenedDir, ALL_RIGHTS};
 
@@ -292,6 +304,39 @@ impl WasiFsRoot {
             }
         }
     }
+
+    /// Exposes the processes of a control plane at `/proc`
+    pub(crate) fn mount_proc(self, control_plane: &WasiControlPlane) -> Self {
+        let proc = ProcFileSystem::new(control_plane);
+        match self {
+            WasiFsRoot::Sandbox(fs) => {
+                let proc: Arc<dyn FileSystem + Send + Sync> = Arc::new(proc);
+                if let Err(err) = fs.mount("/proc".into(), &proc, "/".into()) {
+                    debug!("failed to mount /proc - {err}");
+                }
+                WasiFsRoot::Sandbox(fs)
+            }
+            WasiFsRoot::Backing(fs) => {
+                WasiFsRoot::Backing(Arc::new(Box::new(ProcMountFileSystem::new(fs, proc))))
+            }
+        }
+    }
+
+    /// Exposes the pseudo-terminals of a control plane at `/dev/pts`, next
+    /// to the `/dev/ptmx` that allocates them (which only the sandboxed
+    /// roots have)
+    pub(crate) fn mount_pts(self, control_plane: &WasiControlPlane) -> Self {
+        if let WasiFsRoot::Sandbox(fs) = &self {
+            if fs.metadata(Path::new("/dev/ptmx")).is_ok() {
+                let pts: Arc<dyn FileSystem + Send + Sync> =
+                    Arc::new(PtsFileSystem::new(control_plane));
+                if let Err(err) = fs.mount("/dev/pts".into(), &pts, "/".into()) {
+                    debug!("failed to mount /dev/pts - {err}");
+                }
+            }
+        }
+        self
+    }
 }
 
 impl FileSystem for WasiFsRoot {
@@ -411,6 +456,92 @@ fn create_dir_all(fs: &dyn FileSystem, path: &Path) -> Result<(), virtual_fs::Fs
     Ok(())
 }
 
+/// Writes an `/etc/passwd` and `/etc/group` that describe the user of the
+/// credentials into a file system, files that already exist are kept.
+///
+/// Returns the paths that were created.
+pub(crate) fn populate_user_database(
+    fs: &TmpFileSystem,
+    credentials: &WasiCredentials,
+) -> Vec<&'static str> {
+    let mut created = Vec::new();
+    if fs.metadata(Path::new("/etc")).is_err() {
+        if let Err(err) = fs.create_dir(Path::new("/etc")) {
+            debug!("failed to create /etc - {err}");
+            return created;
+        }
+        created.push("/etc");
+    }
+
+    let mut passwd = String::from("root:x:0:0:root:/root:/bin/sh\n");
+    if credentials.uid != 0 {
+        passwd.push_str(&format!(
+            "user:x:{}:{}:user:/home/user:/bin/sh\n",
+            credentials.uid, credentials.gid
+        ));
+    }
+    let mut group = String::from("root:x:0:\n");
+    let mut gids = vec![credentials.gid];
+    gids.extend(credentials.groups.iter().copied());
+    gids.sort_unstable();
+    gids.dedup();
+    let members = if credentials.uid != 0 { "user" } else { "" };
+    for gid in gids.into_iter().filter(|gid| *gid != 0) {
+        group.push_str(&format!("group{gid}:x:{gid}:{members}\n"));
+    }
+
+    for (path, contents) in [("/etc/passwd", passwd), ("/etc/group", group)] {
+        if fs.metadata(Path::new(path)).is_ok() {
+            continue;
+        }
+        let ret = fs
+            .new_open_options()
+            .create_new(true)
+            .write(true)
+            .open(Path::new(path))
+            .and_then(|mut file| {
+                InlineWaker::block_on(file.write_all(contents.as_bytes())).map_err(Into::into)
+            });
+        match ret {
+            Ok(()) => created.push(path),
+            Err(err) => debug!("failed to create {path} - {err}"),
+        }
+    }
+    created
+}
+
+/// The owner and permission bits of a file or directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct FileOwnership {
+    pub uid: u32,
+    pub gid: u32,
+    /// Permission bits (e.g. `0o644`)
+    pub mode: u32,
+}
+
+impl FileOwnership {
+    pub fn new(uid: u32, gid: u32, mode: u32) -> Self {
+        Self { uid, gid, mode }
+    }
+
+    /// Returns true if the permission bits let a user read and/or write
+    /// the file, privileged users may do anything
+    pub fn permits(&self, credentials: &WasiCredentials, read: bool, write: bool) -> bool {
+        if credentials.is_privileged() {
+            return true;
+        }
+        let bits = if credentials.euid == self.uid {
+            self.mode >> 6
+        } else if credentials.in_group(self.gid) {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        (!read || bits & 0o4 != 0) && (!write || bits & 0o2 != 0)
+    }
+}
+
 /// Warning, modifying these fields directly may cause invariants to break and
 /// should be considered unsafe.  These fields may be made private in a future release
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -435,6 +566,16 @@ pub struct WasiFs {
     pub(crate) init_preopens: Vec<PreopenedDir>,
     // The virtual file system preopens when this was initialized
     pub(crate) init_vfs_preopens: Vec<String>,
+    // The process that owns this file system, `/proc/self` refers to it
+    // (zero when it is not known yet)
+    pid: AtomicU32,
+    // Owners and permissions of the files that have them (the ones created
+    // by the processes), the files of the underlying file systems have none
+    // so everything else is unrestricted
+    ownership: Arc<RwLock<HashMap<PathBuf, FileOwnership>>>,
+    // Maximum number of file descriptors that can be open at the same time
+    // (`u64::MAX` when there is no limit)
+    max_open_files: AtomicU64,
 }
 
 impl WasiFs {
@@ -448,6 +589,11 @@ impl WasiFs {
         self.is_wasix.store(is_wasix, Ordering::SeqCst);
     }
 
+    /// Sets the process that `/proc/self` refers to
+    pub(crate) fn set_pid(&self, pid: WasiProcessId) {
+        self.pid.store(pid.raw(), Ordering::SeqCst);
+    }
+
     /// Forking the WasiState is used when either fork or vfork is called
     pub fn fork(&self) -> Self {
         let fd_map = self.fd_map.read().unwrap().clone();
@@ -462,6 +608,9 @@ impl WasiFs {
             has_unioned: Arc::new(Mutex::new(HashSet::new())),
             init_preopens: self.init_preopens.clone(),
             init_vfs_preopens: self.init_vfs_preopens.clone(),
+            pid: AtomicU32::new(self.pid.load(Ordering::SeqCst)),
+            ownership: self.ownership.clone(),
+            max_open_files: AtomicU64::new(self.max_open_files.load(Ordering::SeqCst)),
         }
     }
 
@@ -523,6 +672,124 @@ impl WasiFs {
         Ok(wasi_fs)
     }
 
+    /// Limits the number of file descriptors that can be open at the same time
+    pub(crate) fn set_max_open_files(&self, max: Option<u64>) {
+        self.max_open_files
+            .store(max.unwrap_or(u64::MAX), Ordering::SeqCst);
+    }
+
+    /// Fails with `Errno::Mfile` when no more file descriptors can be opened
+    fn check_open_files(&self) -> Result<(), Errno> {
+        let open = self.fd_map.read().unwrap().len() as u64;
+        if open >= self.max_open_files.load(Ordering::SeqCst) {
+            return Err(Errno::Mfile);
+        }
+        Ok(())
+    }
+
+    /// Sets the owner and permissions of a file or directory
+    pub fn set_ownership(&self, path: impl AsRef<Path>, ownership: FileOwnership) {
+        let path = Path::new("/").join(path);
+        self.ownership.write().unwrap().insert(path, ownership);
+    }
+
+    /// Returns the owner and permissions of a file or directory, if it has any
+    pub fn ownership(&self, path: impl AsRef<Path>) -> Option<FileOwnership> {
+        let path = Path::new("/").join(path);
+        self.ownership.read().unwrap().get(&path).copied()
+    }
+
+    /// Records the user that created a file or directory as its owner
+    pub(crate) fn set_creator(
+        &self,
+        path: impl AsRef<Path>,
+        credentials: &WasiCredentials,
+        mode: u32,
+    ) {
+        let ownership = FileOwnership::new(credentials.euid, credentials.egid, mode);
+        self.set_ownership(path, ownership);
+    }
+
+    /// Moves the owners of a file or directory (and of everything in it)
+    /// to the path it was renamed to
+    pub(crate) fn rename_ownership(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) {
+        let from = Path::new("/").join(from);
+        let to = Path::new("/").join(to);
+        let mut ownership = self.ownership.write().unwrap();
+        ownership.retain(|path, _| !path.starts_with(&to));
+        let moved = ownership
+            .keys()
+            .filter(|path| path.starts_with(&from))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in moved {
+            if let Some(owner) = ownership.remove(&path) {
+                let suffix = path.strip_prefix(&from).unwrap();
+                ownership.insert(to.join(suffix), owner);
+            }
+        }
+    }
+
+    /// Forgets the owner of a file or directory that was removed, so that
+    /// it doesn't apply to the next one created at the same path
+    pub(crate) fn remove_ownership(&self, path: impl AsRef<Path>) {
+        let path = Path::new("/").join(path);
+        self.ownership.write().unwrap().remove(&path);
+    }
+
+    /// Checks that a user may read and/or write a file or directory
+    pub(crate) fn check_access(
+        &self,
+        path: &Path,
+        credentials: &WasiCredentials,
+        read: bool,
+        write: bool,
+    ) -> Result<(), Errno> {
+        match self.ownership(path) {
+            Some(ownership) if !ownership.permits(credentials, read, write) => Err(Errno::Access),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a user may read and/or write the file or directory
+    /// behind an inode
+    pub(crate) fn check_inode_access(
+        &self,
+        inode: &InodeGuard,
+        credentials: &WasiCredentials,
+        read: bool,
+        write: bool,
+    ) -> Result<(), Errno> {
+        let path = {
+            let guard = inode.read();
+            match guard.deref() {
+                Kind::File { path, .. } | Kind::Dir { path, .. } => path.clone(),
+                Kind::Root { .. } => PathBuf::from("/"),
+                _ => return Ok(()),
+            }
+        };
+        self.check_access(&path, credentials, read, write)
+    }
+
+    /// Synthesizes `/etc/passwd` and `/etc/group` describing the user the
+    /// process runs as (unless the sandbox already has them), the files
+    /// that are created are owned by root
+    pub(crate) fn populate_user_database(&self, credentials: &WasiCredentials) {
+        if let WasiFsRoot::Sandbox(fs) = &self.root_fs {
+            let created = populate_user_database(fs, credentials);
+            self.set_user_database_ownership(&created);
+        }
+    }
+
+    /// Makes root the owner of the paths that [`populate_user_database`]
+    /// created, the files that were already there keep their owner
+    pub(crate) fn set_user_database_ownership(&self, created: &[&str]) {
+        for path in created {
+            let mode = if *path == "/etc" { 0o755 } else { 0o644 };
+            self.set_ownership(path, FileOwnership::new(0, 0, mode));
+        }
+    }
+
     /// Converts a relative path into an absolute path
     pub(crate) fn relative_path_to_absolute(&self, mut path: String) -> String {
         if path.starts_with("./") {
@@ -565,6 +832,9 @@ impl WasiFs {
             has_unioned: Arc::new(Mutex::new(HashSet::new())),
             init_preopens: Default::default(),
             init_vfs_preopens: Default::default(),
+            pid: AtomicU32::new(0),
+            ownership: Default::default(),
+            max_open_files: AtomicU64::new(u64::MAX),
         };
         wasi_fs.create_stdin(inodes);
         wasi_fs.create_stdout(inodes);
@@ -884,6 +1154,19 @@ impl WasiFs {
                             "." => continue 'path_iter,
                             _ => (),
                         }
+                        // `/proc/self` is the directory of the process that owns this
+                        // file system
+                        let self_pid;
+                        let component = match self.pid.load(Ordering::SeqCst) {
+                            pid if pid != 0
+                                && component.as_os_str() == "self"
+                                && proc_fs::is_proc_dir(path) =>
+                            {
+                                self_pid = pid.to_string();
+                                Component::Normal(std::ffi::OsStr::new(&self_pid))
+                            }
+                            _ => component,
+                        };
                         // used for full resolution of symlinks
                         let mut loop_for_symlink = false;
                         if let Some(entry) =
@@ -1046,6 +1329,8 @@ impl WasiFs {
                     | Kind::Socket { .. }
                     | Kind::Pipe { .. }
                     | Kind::EventNotifications { .. }
+                    | Kind::TimerFd { .. }
+                    | Kind::SignalFd { .. }
                     | Kind::Epoll { .. } => {
                         return Err(Errno::Notdir);
                     }
@@ -1308,6 +1593,9 @@ impl WasiFs {
                 Kind::Socket { socket } => match socket.inner.protected.read().unwrap().kind {
                     InodeSocketKind::TcpStream { .. } => Filetype::SocketStream,
                     InodeSocketKind::Raw { .. } => Filetype::SocketRaw,
+                    InodeSocketKind::UnixListener { .. } => Filetype::SocketStream,
+                    InodeSocketKind::UnixStream { .. } => Filetype::SocketStream,
+                    InodeSocketKind::UnixDatagram { .. } => Filetype::SocketDgram,
                     InodeSocketKind::PreSocket { ty, .. } => match ty {
                         Socktype::Stream => Filetype::SocketStream,
                         Socktype::Dgram => Filetype::SocketDgram,
@@ -1466,6 +1754,7 @@ impl WasiFs {
         open_flags: u16,
         inode: InodeGuard,
     ) -> Result<WasiFd, Errno> {
+        self.check_open_files()?;
         let idx = self.next_fd.fetch_add(1, Ordering::SeqCst);
         self.create_fd_ext(rights, rights_inheriting, flags, open_flags, inode, idx)?;
         Ok(idx)
@@ -1517,19 +1806,16 @@ impl WasiFs {
 
     pub fn clone_fd(&self, fd: WasiFd) -> Result<WasiFd, Errno> {
         let fd = self.get_fd(fd)?;
+        self.insert_fd(fd)
+    }
+
+    /// Adds a descriptor that shares its file and offset with a descriptor
+    /// of this process or of another one, which is how descriptors are
+    /// duplicated and passed around
+    pub fn insert_fd(&self, fd: Fd) -> Result<WasiFd, Errno> {
+        self.check_open_files()?;
         let idx = self.next_fd.fetch_add(1, Ordering::SeqCst);
-        self.fd_map.write().unwrap().insert(
-            idx,
-            Fd {
-                rights: fd.rights,
-                rights_inheriting: fd.rights_inheriting,
-                flags: fd.flags,
-                offset: fd.offset.clone(),
-                open_flags: fd.open_flags,
-                inode: fd.inode,
-                is_stdio: fd.is_stdio,
-            },
-        );
+        self.fd_map.write().unwrap().insert(idx, fd);
         Ok(idx)
     }
 
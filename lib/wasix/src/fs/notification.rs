@@ -85,6 +85,18 @@ impl NotificationInner {
         }
     }
 
+    /// Polls for the counter to be non-zero, unlike [`Self::poll`] this
+    /// keeps reporting a count until it has been read
+    pub fn poll_nonzero(&self, waker: &Waker) -> Poll<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.add_waker(waker);
+
+        match state.counter {
+            0 => Poll::Pending,
+            counter => Poll::Ready(counter as usize),
+        }
+    }
+
     pub fn write(&self, val: u64) {
         let mut state = self.state.lock().unwrap();
         state.inc(val);
@@ -0,0 +1,592 @@
+use std::{
+    ffi::OsStr,
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use futures::future::BoxFuture;
+use tokio::io::AsyncReadExt;
+use virtual_fs::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, StaticFile, VirtualFile,
+};
+use virtual_mio::InlineWaker;
+use wasmer_wasix_types::wasi::{Errno, Fd as WasiFd};
+
+use super::{fs_error_into_wasi_err, Fd, Kind};
+use crate::{
+    os::task::{
+        control_plane::{WasiControlPlane, WasiControlPlaneHandle},
+        inspect::WasiThreadActivity,
+        thread::WasiThreadWait,
+    },
+    WasiProcess, WasiThread, WasiThreadId,
+};
+
+/// Size of the address space of a 32-bit memory, which is reported as the
+/// total memory in `/proc/meminfo`
+const MEMORY_TOTAL: u64 = 1 << 32;
+
+/// State of a thread that finished (or of a process whose threads all did)
+const ZOMBIE: &str = "Z (zombie)";
+
+/// State of the threads of a process that was stopped by a signal
+const STOPPED: &str = "T (stopped)";
+
+/// Returns the state of a thread the way Linux reports it in `status`
+fn thread_state(activity: WasiThreadActivity) -> &'static str {
+    match activity {
+        WasiThreadActivity::Running => "R (running)",
+        WasiThreadActivity::Idle
+        | WasiThreadActivity::Waiting(WasiThreadWait::Poll | WasiThreadWait::Sleep) => {
+            "S (sleeping)"
+        }
+        WasiThreadActivity::Waiting(WasiThreadWait::Fd { .. } | WasiThreadWait::Futex { .. }) => {
+            "D (disk sleep)"
+        }
+        WasiThreadActivity::Finished => ZOMBIE,
+    }
+}
+
+/// Returns the state of a process, which is running if any of its threads
+/// is and otherwise the state of its main thread
+fn process_state<'a>(threads: impl Iterator<Item = &'a WasiThread>) -> &'static str {
+    let mut state = ZOMBIE;
+    for thread in threads {
+        let activity = thread.activity();
+        if activity == WasiThreadActivity::Running {
+            return thread_state(activity);
+        }
+        if thread.is_main() {
+            state = thread_state(activity);
+        }
+    }
+    state
+}
+
+/// Reports the threads of a stopped process as stopped until they finish
+fn stopped_state(process: &WasiProcess, state: &'static str) -> &'static str {
+    match process.stopped() {
+        Some(_) if state != ZOMBIE => STOPPED,
+        _ => state,
+    }
+}
+
+/// Read-only file system that exposes the processes of a control plane
+/// in the same layout as the `/proc` file system of Linux
+///
+/// The content is generated whenever a file is opened, it holds:
+/// - `/cpuinfo` and `/meminfo`
+/// - `/<pid>/cmdline`, `/<pid>/cwd`, `/<pid>/exe` and `/<pid>/status`
+/// - `/<pid>/fd/<fd>` for every open file descriptor of a process
+/// - `/<pid>/task/<tid>/status` for every thread of a process
+///
+/// Symbolic links are not supported by the virtual file systems, so `cwd`,
+/// `exe` and the file descriptors are regular files that contain the path
+/// they link to (which `path_readlink` returns for them). `/proc/self` is
+/// resolved by [`WasiFs`](super::WasiFs) as it depends on the process that
+/// looks it up.
+#[derive(Debug, Clone)]
+pub struct ProcFileSystem {
+    control_plane: WasiControlPlaneHandle,
+}
+
+/// Entry of the file system
+enum Node {
+    Root,
+    CpuInfo,
+    MemInfo,
+    Process(WasiProcess),
+    Cmdline(WasiProcess),
+    Cwd(WasiProcess),
+    Exe(WasiProcess),
+    Status(WasiProcess),
+    Fds(WasiProcess),
+    Fd(WasiProcess, WasiFd),
+    Tasks(WasiProcess),
+    Task(WasiProcess, WasiThreadId),
+    TaskStatus(WasiProcess, WasiThreadId),
+}
+
+impl ProcFileSystem {
+    pub fn new(control_plane: &WasiControlPlane) -> Self {
+        Self {
+            control_plane: control_plane.handle(),
+        }
+    }
+
+    fn control_plane(&self) -> Result<WasiControlPlane, FsError> {
+        self.control_plane.upgrade().ok_or(FsError::EntryNotFound)
+    }
+
+    fn process(&self, pid: &str) -> Result<WasiProcess, FsError> {
+        let pid: u32 = pid.parse().map_err(|_| FsError::EntryNotFound)?;
+        self.control_plane()?
+            .get_process(pid.into())
+            .filter(|process| process.try_join().is_none())
+            .ok_or(FsError::EntryNotFound)
+    }
+
+    fn node(&self, path: &Path) -> Result<Node, FsError> {
+        let mut names = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::RootDir | Component::CurDir => {}
+                Component::Normal(name) => {
+                    names.push(name.to_str().ok_or(FsError::EntryNotFound)?);
+                }
+                Component::Prefix(_) | Component::ParentDir => return Err(FsError::InvalidInput),
+            }
+        }
+
+        let node = match names.as_slice() {
+            [] => Node::Root,
+            ["cpuinfo"] => Node::CpuInfo,
+            ["meminfo"] => Node::MemInfo,
+            [pid] => Node::Process(self.process(pid)?),
+            [pid, "cmdline"] => Node::Cmdline(self.process(pid)?),
+            [pid, "cwd"] => Node::Cwd(self.process(pid)?),
+            [pid, "exe"] => Node::Exe(self.process(pid)?),
+            [pid, "status"] => Node::Status(self.process(pid)?),
+            [pid, "fd"] => Node::Fds(self.process(pid)?),
+            [pid, "fd", fd] => {
+                let process = self.process(pid)?;
+                let fd = fd.parse().map_err(|_| FsError::EntryNotFound)?;
+                process.fd(fd).map_err(|_| FsError::EntryNotFound)?;
+                Node::Fd(process, fd)
+            }
+            [pid, "task"] => Node::Tasks(self.process(pid)?),
+            [pid, "task", tid, rest @ ..] => {
+                let process = self.process(pid)?;
+                let tid: WasiThreadId = tid
+                    .parse::<u32>()
+                    .map_err(|_| FsError::EntryNotFound)?
+                    .into();
+                process.get_thread(&tid).ok_or(FsError::EntryNotFound)?;
+                match rest {
+                    [] => Node::Task(process, tid),
+                    ["status"] => Node::TaskStatus(process, tid),
+                    _ => return Err(FsError::EntryNotFound),
+                }
+            }
+            _ => return Err(FsError::EntryNotFound),
+        };
+        Ok(node)
+    }
+}
+
+impl Node {
+    fn is_dir(&self) -> bool {
+        matches!(
+            self,
+            Node::Root | Node::Process(_) | Node::Fds(_) | Node::Tasks(_) | Node::Task(..)
+        )
+    }
+
+    /// Names of the entries of a directory
+    fn entries(&self, fs: &ProcFileSystem) -> Result<Vec<String>, FsError> {
+        let entries = match self {
+            Node::Root => {
+                let mut entries = vec!["cpuinfo".to_string(), "meminfo".to_string()];
+                entries.extend(
+                    fs.control_plane()?
+                        .running_processes()
+                        .iter()
+                        .map(|process| process.pid().to_string()),
+                );
+                entries
+            }
+            Node::Process(_) => ["cmdline", "cwd", "exe", "fd", "status", "task"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            Node::Fds(process) => {
+                let state = process.state().map_err(|_| FsError::EntryNotFound)?;
+                let mut fds = state
+                    .fs
+                    .fd_map
+                    .read()
+                    .unwrap()
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                fds.sort_unstable();
+                fds.iter().map(|fd| fd.to_string()).collect()
+            }
+            Node::Tasks(process) => {
+                let mut tids = process.lock().threads.keys().copied().collect::<Vec<_>>();
+                tids.sort_unstable();
+                tids.iter().map(|tid| tid.to_string()).collect()
+            }
+            Node::Task(..) => vec!["status".to_string()],
+            _ => return Err(FsError::BaseNotDirectory),
+        };
+        Ok(entries)
+    }
+
+    /// Generates the content of a file
+    fn contents(&self, fs: &ProcFileSystem) -> Result<String, FsError> {
+        let contents = match self {
+            Node::CpuInfo => {
+                let cpus = std::thread::available_parallelism()
+                    .map(|cpus| cpus.get())
+                    .unwrap_or(1);
+                (0..cpus).fold(String::new(), |mut contents, cpu| {
+                    let _ = write!(
+                        contents,
+                        "processor\t: {cpu}\n\
+                         vendor_id\t: wasmer\n\
+                         model name\t: WebAssembly\n\
+                         cpu cores\t: {cpus}\n\n"
+                    );
+                    contents
+                })
+            }
+            Node::MemInfo => {
+                let used: u64 = fs
+                    .control_plane()?
+                    .running_processes()
+                    .iter()
+                    .map(|process| process.memory_size())
+                    .sum();
+                let free = MEMORY_TOTAL.saturating_sub(used);
+                format!(
+                    "MemTotal:       {:>8} kB\n\
+                     MemFree:        {:>8} kB\n\
+                     MemAvailable:   {:>8} kB\n",
+                    MEMORY_TOTAL / 1024,
+                    free / 1024,
+                    free / 1024
+                )
+            }
+            Node::Cmdline(process) => {
+                let state = process.state().map_err(|_| FsError::EntryNotFound)?;
+                state.args.iter().fold(String::new(), |mut contents, arg| {
+                    contents.push_str(arg);
+                    contents.push('\0');
+                    contents
+                })
+            }
+            Node::Cwd(process) => {
+                let state = process.state().map_err(|_| FsError::EntryNotFound)?;
+                let cwd = state.fs.current_dir.lock().unwrap();
+                cwd.clone()
+            }
+            Node::Exe(process) => {
+                let state = process.state().map_err(|_| FsError::EntryNotFound)?;
+                state.args.first().cloned().unwrap_or_default()
+            }
+            Node::Status(process) => {
+                let (threads, state) = {
+                    let inner = process.lock();
+                    (inner.threads.len(), process_state(inner.threads.values()))
+                };
+                let state = stopped_state(process, state);
+                let memory = process.memory_size() / 1024;
+                format!(
+                    "Name:\t{}\n\
+                     State:\t{state}\n\
+                     Tgid:\t{}\n\
+                     Pid:\t{}\n\
+                     PPid:\t{}\n\
+                     Threads:\t{threads}\n\
+                     VmSize:\t{memory:>8} kB\n\
+                     VmRSS:\t{memory:>8} kB\n",
+                    process.name(),
+                    process.pid(),
+                    process.pid(),
+                    process.ppid(),
+                )
+            }
+            Node::TaskStatus(process, tid) => {
+                let state = process
+                    .get_thread(tid)
+                    .map_or(ZOMBIE, |thread| thread_state(thread.activity()));
+                let state = stopped_state(process, state);
+                format!(
+                    "Name:\t{}\n\
+                     State:\t{state}\n\
+                     Tgid:\t{}\n\
+                     Pid:\t{tid}\n\
+                     PPid:\t{}\n",
+                    process.name(),
+                    process.pid(),
+                    process.ppid(),
+                )
+            }
+            Node::Fd(process, fd) => {
+                let fd = process.fd(*fd).map_err(|_| FsError::EntryNotFound)?;
+                fd_target(&fd)
+            }
+            _ => return Err(FsError::NotAFile),
+        };
+        Ok(contents)
+    }
+}
+
+/// Path that a file descriptor links to, following the conventions of
+/// Linux for the descriptors that are not backed by a file
+pub(crate) fn fd_target(fd: &Fd) -> String {
+    let ino = fd.inode.ino().as_u64();
+    // the inode is locked when the descriptor refers to the entry that is
+    // being opened, or to one of its parent directories
+    let guard = match fd.inode.kind.try_read() {
+        Ok(guard) => guard,
+        Err(_) => return fd.inode.name.to_string(),
+    };
+    match &*guard {
+        Kind::File { path, .. } if path.as_os_str().is_empty() => {
+            format!("/dev/{}", fd.inode.name)
+        }
+        Kind::File { path, .. } | Kind::Dir { path, .. } => {
+            Path::new("/").join(path).to_string_lossy().into_owned()
+        }
+        Kind::Root { .. } => "/".to_string(),
+        Kind::Socket { .. } => format!("socket:[{ino}]"),
+        Kind::Pipe { .. } => format!("pipe:[{ino}]"),
+        Kind::Epoll { .. } => "anon_inode:[eventpoll]".to_string(),
+        Kind::EventNotifications { .. } => "anon_inode:[eventfd]".to_string(),
+        Kind::TimerFd { .. } => "anon_inode:[timerfd]".to_string(),
+        Kind::SignalFd { .. } => "anon_inode:[signalfd]".to_string(),
+        Kind::Symlink { .. } | Kind::Buffer { .. } => fd.inode.name.to_string(),
+    }
+}
+
+/// Returns true if a path is the `/proc` directory
+pub(crate) fn is_proc_dir(path: &Path) -> bool {
+    path.components()
+        .filter(|component| !matches!(component, Component::RootDir))
+        .eq([Component::Normal(OsStr::new("proc"))])
+}
+
+/// Returns true if a path is an entry of `/proc` that behaves as a
+/// symbolic link, i.e. `/proc/<pid>/{cwd,exe}` or `/proc/<pid>/fd/<fd>`
+pub(crate) fn is_link(path: &Path) -> bool {
+    let names = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    match names.as_slice() {
+        ["proc", pid, "cwd" | "exe"] => pid.parse::<u32>().is_ok(),
+        ["proc", pid, "fd", fd] => pid.parse::<u32>().is_ok() && fd.parse::<u32>().is_ok(),
+        _ => false,
+    }
+}
+
+/// Reads the path that an entry of `/proc` links to
+pub(crate) fn read_link(fs: &dyn FileSystem, path: &Path) -> Result<String, Errno> {
+    let mut file = fs
+        .new_open_options()
+        .read(true)
+        .open(path)
+        .map_err(fs_error_into_wasi_err)?;
+    let mut target = String::new();
+    InlineWaker::block_on(file.read_to_string(&mut target)).map_err(|_| Errno::Io)?;
+    Ok(target)
+}
+
+fn dir_metadata() -> Metadata {
+    Metadata {
+        ft: FileType::new_dir(),
+        ..Default::default()
+    }
+}
+
+fn file_metadata() -> Metadata {
+    Metadata {
+        ft: FileType::new_file(),
+        ..Default::default()
+    }
+}
+
+impl FileSystem for ProcFileSystem {
+    fn read_dir(&self, path: &Path) -> virtual_fs::Result<ReadDir> {
+        let node = self.node(path)?;
+        let entries = node
+            .entries(self)?
+            .into_iter()
+            .map(|name| {
+                let path = path.join(name);
+                let metadata = self.metadata(&path);
+                DirEntry { path, metadata }
+            })
+            .collect();
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename<'a>(
+        &'a self,
+        _from: &'a Path,
+        _to: &'a Path,
+    ) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        Box::pin(async { Err(FsError::PermissionDenied) })
+    }
+
+    fn metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        // like on Linux the files are empty until they are opened
+        if self.node(path)?.is_dir() {
+            Ok(dir_metadata())
+        } else {
+            Ok(file_metadata())
+        }
+    }
+
+    fn remove_file(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl FileOpener for ProcFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        _conf: &OpenOptionsConfig,
+    ) -> virtual_fs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        // the files are opened with write access whenever their directory
+        // allows it, writing to them fails instead
+        let node = self.node(path)?;
+        if node.is_dir() {
+            return Err(FsError::NotAFile);
+        }
+        let contents = node.contents(self)?;
+        Ok(Box::new(StaticFile::new(contents.into_bytes())))
+    }
+}
+
+/// File system that exposes a [`ProcFileSystem`] at `/proc` in front of
+/// another file system, for the roots that can not mount it
+#[derive(Debug)]
+pub(crate) struct ProcMountFileSystem<F> {
+    inner: F,
+    proc: ProcFileSystem,
+}
+
+impl<F> ProcMountFileSystem<F>
+where
+    F: FileSystem,
+{
+    pub(crate) fn new(inner: F, proc: ProcFileSystem) -> Self {
+        Self { inner, proc }
+    }
+
+    /// Path of an entry of `/proc` within the [`ProcFileSystem`]
+    fn proc_path(path: &Path) -> Option<PathBuf> {
+        let rest = path.strip_prefix("/proc").ok()?;
+        Some(Path::new("/").join(rest))
+    }
+}
+
+impl<F> FileSystem for ProcMountFileSystem<F>
+where
+    F: FileSystem,
+{
+    fn read_dir(&self, path: &Path) -> virtual_fs::Result<ReadDir> {
+        if let Some(path) = Self::proc_path(path) {
+            let entries = self
+                .proc
+                .read_dir(&path)?
+                .filter_map(Result::ok)
+                .map(|entry| DirEntry {
+                    path: Path::new("/proc")
+                        .join(entry.path.strip_prefix("/").unwrap_or(&entry.path)),
+                    metadata: entry.metadata,
+                })
+                .collect();
+            return Ok(ReadDir::new(entries));
+        }
+
+        let mut entries = self
+            .inner
+            .read_dir(path)?
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+        if path == Path::new("/") && !entries.iter().any(|entry| entry.path == Path::new("/proc")) {
+            entries.push(DirEntry {
+                path: PathBuf::from("/proc"),
+                metadata: Ok(dir_metadata()),
+            });
+        }
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> virtual_fs::Result<()> {
+        match Self::proc_path(path) {
+            Some(path) => self.proc.create_dir(&path),
+            None => self.inner.create_dir(path),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> virtual_fs::Result<()> {
+        match Self::proc_path(path) {
+            Some(path) => self.proc.remove_dir(&path),
+            None => self.inner.remove_dir(path),
+        }
+    }
+
+    fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        if Self::proc_path(from).is_some() || Self::proc_path(to).is_some() {
+            return Box::pin(async { Err(FsError::PermissionDenied) });
+        }
+        self.inner.rename(from, to)
+    }
+
+    fn metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        match Self::proc_path(path) {
+            Some(path) => self.proc.metadata(&path),
+            None => self.inner.metadata(path),
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        match Self::proc_path(path) {
+            Some(path) => self.proc.symlink_metadata(&path),
+            None => self.inner.symlink_metadata(path),
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> virtual_fs::Result<()> {
+        match Self::proc_path(path) {
+            Some(path) => self.proc.remove_file(&path),
+            None => self.inner.remove_file(path),
+        }
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl<F> FileOpener for ProcMountFileSystem<F>
+where
+    F: FileSystem,
+{
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> virtual_fs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        match Self::proc_path(path) {
+            Some(path) => self.proc.open(&path, conf),
+            None => self
+                .inner
+                .new_open_options()
+                .options(conf.clone())
+                .open(path),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::{Component, Path};
+
+use futures::future::BoxFuture;
+use virtual_fs::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, VirtualFile,
+};
+
+use crate::os::task::control_plane::{WasiControlPlane, WasiControlPlaneHandle};
+
+/// Read-only file system that exposes the pseudo-terminals of a control
+/// plane in the same layout as the `/dev/pts` file system of Linux
+///
+/// Every pseudo-terminal whose master end is open has an entry named after
+/// its number, opening it opens another slave end of the pseudo-terminal.
+#[derive(Debug, Clone)]
+pub struct PtsFileSystem {
+    control_plane: WasiControlPlaneHandle,
+}
+
+impl PtsFileSystem {
+    pub fn new(control_plane: &WasiControlPlane) -> Self {
+        Self {
+            control_plane: control_plane.handle(),
+        }
+    }
+
+    fn control_plane(&self) -> Result<WasiControlPlane, FsError> {
+        self.control_plane.upgrade().ok_or(FsError::EntryNotFound)
+    }
+
+    /// Returns the number of the pseudo-terminal that a path refers to, or
+    /// `None` for the root of the file system
+    fn index(&self, path: &Path) -> Result<Option<u32>, FsError> {
+        let mut names = path
+            .components()
+            .filter(|component| !matches!(component, Component::RootDir | Component::CurDir));
+        let index = match names.next() {
+            None => return Ok(None),
+            Some(Component::Normal(name)) => name
+                .to_str()
+                .and_then(|name| name.parse::<u32>().ok())
+                .ok_or(FsError::EntryNotFound)?,
+            Some(_) => return Err(FsError::EntryNotFound),
+        };
+        if names.next().is_some() {
+            return Err(FsError::EntryNotFound);
+        }
+        if !self.control_plane()?.ptys().indexes().contains(&index) {
+            return Err(FsError::EntryNotFound);
+        }
+        Ok(Some(index))
+    }
+}
+
+impl FileSystem for PtsFileSystem {
+    fn read_dir(&self, path: &Path) -> virtual_fs::Result<ReadDir> {
+        if self.index(path)?.is_some() {
+            return Err(FsError::BaseNotDirectory);
+        }
+        let entries = self
+            .control_plane()?
+            .ptys()
+            .indexes()
+            .into_iter()
+            .map(|index| {
+                let path = path.join(index.to_string());
+                let metadata = self.metadata(&path);
+                DirEntry { path, metadata }
+            })
+            .collect();
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename<'a>(
+        &'a self,
+        _from: &'a Path,
+        _to: &'a Path,
+    ) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        Box::pin(async { Err(FsError::PermissionDenied) })
+    }
+
+    fn metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        let ft = match self.index(path)? {
+            Some(_) => FileType {
+                char_device: true,
+                ..Default::default()
+            },
+            None => FileType::new_dir(),
+        };
+        Ok(Metadata {
+            ft,
+            ..Default::default()
+        })
+    }
+
+    fn remove_file(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+}
+
+impl FileOpener for PtsFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        _conf: &OpenOptionsConfig,
+    ) -> virtual_fs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let index = self.index(path)?.ok_or(FsError::NotAFile)?;
+        let slave = self.control_plane()?.ptys().open_slave(index)?;
+        Ok(Box::new(slave))
+    }
+}
@@ -0,0 +1,91 @@
+//! Signal descriptors (`signalfd`) that receive the signals of a process
+//! in place of its threads, the signals are read and polled like event
+//! notifications
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Poll, Waker},
+};
+
+use wasmer_wasix_types::types::Signal;
+
+use super::NotificationInner;
+
+/// Size of the record that is read for every signal (`signalfd_siginfo`)
+pub const SIGNALFD_SIGINFO_SIZE: usize = 128;
+
+#[derive(Debug)]
+pub struct SignalFdInner {
+    /// Signals that are received by the descriptor, bit `n - 1` is set for
+    /// signal `n` (like a `sigset_t`)
+    mask: AtomicU64,
+    /// Signals that were received and have not been read yet
+    pending: Mutex<VecDeque<Signal>>,
+    /// Counts the pending signals (which must be a semaphore)
+    notifications: Arc<NotificationInner>,
+}
+
+impl SignalFdInner {
+    pub fn new(mask: u64, notifications: Arc<NotificationInner>) -> Self {
+        Self {
+            mask: AtomicU64::new(mask),
+            pending: Default::default(),
+            notifications,
+        }
+    }
+
+    /// Changes the signals that are received by the descriptor
+    pub fn set_mask(&self, mask: u64) {
+        self.mask.store(mask, Ordering::Release);
+    }
+
+    /// Returns true if the descriptor receives a signal, `SIGKILL` and
+    /// `SIGSTOP` are never received
+    pub fn watches(&self, signal: Signal) -> bool {
+        if matches!(signal, Signal::Sigkill | Signal::Sigstop) {
+            return false;
+        }
+        match (signal as u64).checked_sub(1) {
+            Some(bit) if bit < 64 => self.mask.load(Ordering::Acquire) & (1 << bit) != 0,
+            _ => false,
+        }
+    }
+
+    /// Queues a signal on the descriptor, a signal that is already pending
+    /// is not queued again
+    pub fn push(&self, signal: Signal) {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.contains(&signal) {
+            pending.push_back(signal);
+            self.notifications.write(1);
+        }
+    }
+
+    /// Takes the next pending signal, if there is one
+    pub fn try_pop(&self) -> Option<Signal> {
+        let mut pending = self.pending.lock().unwrap();
+        self.notifications.try_read()?;
+        pending.pop_front()
+    }
+
+    /// Takes the next pending signal or registers the waker to be woken
+    /// when a signal is queued
+    pub fn pop(&self, waker: &Waker) -> Poll<Signal> {
+        let mut pending = self.pending.lock().unwrap();
+        match self.notifications.read(waker) {
+            Poll::Ready(_) => pending.pop_front().map_or(Poll::Pending, Poll::Ready),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Returns the record that is read from a signal descriptor for a signal
+pub fn signal_fd_siginfo(signal: Signal) -> [u8; SIGNALFD_SIGINFO_SIZE] {
+    let mut siginfo = [0u8; SIGNALFD_SIGINFO_SIZE];
+    siginfo[0..4].copy_from_slice(&(signal as u32).to_le_bytes());
+    siginfo
+}
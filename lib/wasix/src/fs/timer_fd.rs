@@ -0,0 +1,174 @@
+//! Timer descriptors (`timerfd_create`) that count the expirations of a
+//! timer, the count is read and polled like an event notification
+
+use std::{
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid};
+
+use super::NotificationInner;
+use crate::{syscalls::platform_clock_time_get, VirtualTaskManager};
+
+#[derive(Debug, Default)]
+struct TimerFdState {
+    /// Time on the clock when the timer expires next (none when disarmed)
+    deadline: Option<Duration>,
+    /// Time between the expirations of a periodic timer (zero for one-shot timers)
+    interval: Duration,
+    /// Incremented every time the timer is armed so that the task driving
+    /// a previous arming of the timer stops
+    generation: u64,
+}
+
+#[derive(Debug)]
+pub struct TimerFdInner {
+    /// Clock that the timer measures its expiration against
+    clock_id: Snapshot0Clockid,
+    /// Counts the expirations that have not been read yet
+    notifications: Arc<NotificationInner>,
+    state: Mutex<TimerFdState>,
+}
+
+/// What the task driving a timer descriptor does next
+enum TimerFdStep {
+    /// The timer expired and its expirations were counted
+    Expire,
+    /// The timer expires after this time on its clock
+    Wait(Duration),
+    /// The timer was disarmed or re-armed, or the descriptor was closed
+    Stop,
+}
+
+impl TimerFdInner {
+    /// Creates a disarmed timer that counts its expirations on the
+    /// notifications (which must not be a semaphore)
+    pub fn new(clock_id: Snapshot0Clockid, notifications: Arc<NotificationInner>) -> Self {
+        Self {
+            clock_id,
+            notifications,
+            state: Mutex::new(TimerFdState::default()),
+        }
+    }
+
+    fn now(&self) -> Result<Duration, Errno> {
+        let now = platform_clock_time_get(self.clock_id, 1)?;
+        Ok(Duration::from_nanos(now as u64))
+    }
+
+    /// Arms the timer so that it expires after `value` (or at `value` on its
+    /// clock when `absolute` is set) and then every `interval`, a zero
+    /// `value` disarms the timer instead. Expirations that were not read
+    /// yet are discarded.
+    ///
+    /// Returns the time that was left on the timer and its interval
+    pub fn set(
+        self: &Arc<Self>,
+        tasks: &Arc<dyn VirtualTaskManager>,
+        value: Duration,
+        interval: Duration,
+        absolute: bool,
+    ) -> Result<(Duration, Duration), Errno> {
+        let now = self.now()?;
+
+        let (old, generation) = {
+            let mut state = self.state.lock().unwrap();
+            let old = (
+                state
+                    .deadline
+                    .map(|deadline| deadline.saturating_sub(now))
+                    .unwrap_or_default(),
+                state.interval,
+            );
+
+            self.notifications.try_read();
+            state.generation += 1;
+            state.interval = interval;
+            state.deadline = if value.is_zero() {
+                None
+            } else if absolute {
+                Some(value)
+            } else {
+                Some(now + value)
+            };
+            if state.deadline.is_none() {
+                return Ok(old);
+            }
+            (old, state.generation)
+        };
+
+        let timer = Arc::downgrade(self);
+        let tasks_inner = tasks.clone();
+        tasks
+            .task_shared(Box::new(move || {
+                Box::pin(run_timer_fd(timer, tasks_inner, generation))
+            }))
+            .map_err(Errno::from)?;
+        Ok(old)
+    }
+
+    /// Returns the time left on the timer and its interval
+    pub fn get(&self) -> Result<(Duration, Duration), Errno> {
+        let now = self.now()?;
+        let state = self.state.lock().unwrap();
+        let left = state
+            .deadline
+            .map(|deadline| deadline.saturating_sub(now))
+            .unwrap_or_default();
+        Ok((left, state.interval))
+    }
+
+    fn step(&self, generation: u64, now: Duration) -> TimerFdStep {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return TimerFdStep::Stop;
+        }
+        let deadline = match state.deadline {
+            Some(deadline) => deadline,
+            None => return TimerFdStep::Stop,
+        };
+        if now < deadline {
+            return TimerFdStep::Wait(deadline - now);
+        }
+
+        // The expirations are counted while the state is locked so that
+        // they can not be counted after the timer was re-armed
+        let count = if state.interval.is_zero() {
+            state.deadline = None;
+            1
+        } else {
+            let missed = (now - deadline).as_nanos() / state.interval.as_nanos();
+            let missed = missed.min(u32::MAX as u128 - 1) as u32;
+            state.deadline = Some(deadline + state.interval * (missed + 1));
+            missed as u64 + 1
+        };
+        tracing::trace!(count, "timer descriptor expired");
+        self.notifications.write(count);
+        TimerFdStep::Expire
+    }
+}
+
+/// Drives an armed timer descriptor until it is disarmed or re-armed, or
+/// until the descriptor is closed
+async fn run_timer_fd(
+    timer: Weak<TimerFdInner>,
+    tasks: Arc<dyn VirtualTaskManager>,
+    generation: u64,
+) {
+    loop {
+        let step = match timer.upgrade() {
+            Some(timer) => match timer.now() {
+                Ok(now) => timer.step(generation, now),
+                Err(_) => return,
+            },
+            None => return,
+        };
+
+        match step {
+            TimerFdStep::Expire => {}
+            TimerFdStep::Wait(wait) => tasks.sleep_now(wait).await,
+            TimerFdStep::Stop => return,
+        }
+    }
+}
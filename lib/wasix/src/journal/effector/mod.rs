@@ -37,6 +37,7 @@ mod syscalls {
     mod fd_duplicate;
     mod fd_event;
     mod fd_pipe;
+    mod fd_receive;
     mod fd_renumber;
     mod fd_seek;
     mod fd_set_flags;
@@ -62,15 +63,21 @@ mod syscalls {
     mod port_route_clear;
     mod port_route_remove;
     mod port_unbridge;
+    mod pty_open;
+    mod pty_open_slave;
+    mod signalfd;
     mod sock_accept;
     mod sock_bind;
+    mod sock_bind_unix;
     mod sock_connect;
+    mod sock_connect_unix;
     mod sock_join_ipv4_multicast;
     mod sock_join_ipv6_multicast;
     mod sock_leave_ipv4_multicast;
     mod sock_leave_ipv6_multicast;
     mod sock_listen;
     mod sock_open;
+    mod sock_pair;
     mod sock_send;
     mod sock_send_file;
     mod sock_send_to;
@@ -78,6 +85,8 @@ mod syscalls {
     mod sock_set_opt_size;
     mod sock_set_opt_time;
     mod sock_shutdown;
+    mod timerfd_create;
+    mod timerfd_settime;
     mod tty_set;
 }
 #[cfg(feature = "journal")]
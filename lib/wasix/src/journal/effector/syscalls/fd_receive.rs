@@ -0,0 +1,74 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use virtual_fs::NullFile;
+
+use crate::fs::{Fd as WasiFdEntry, Kind};
+
+use super::*;
+
+impl JournalEffector {
+    pub fn save_fd_receive(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        entry: &WasiFdEntry,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::ReceiveFileDescriptorV1 {
+                fd,
+                fs_rights_base: entry.rights,
+                fs_rights_inheriting: entry.rights_inheriting,
+                fs_flags: entry.flags,
+            },
+        )
+    }
+
+    /// The file of a received descriptor belongs to the process that passed
+    /// it and can not be restored, the descriptor is restored with a file
+    /// that reads nothing and discards what is written to it so that the
+    /// events that follow still find it
+    pub fn apply_fd_receive(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        fs_rights_base: Rights,
+        fs_rights_inheriting: Rights,
+        fs_flags: Fdflags,
+    ) -> anyhow::Result<()> {
+        let env = ctx.data();
+        let state = env.state();
+        let inodes = &state.inodes;
+        let kind = Kind::File {
+            handle: Some(Arc::new(RwLock::new(Box::<NullFile>::default()))),
+            path: PathBuf::new(),
+            fd: None,
+        };
+        let inode = state
+            .fs
+            .create_inode_with_default_stat(inodes, kind, false, "received".into());
+
+        let ret_fd = state
+            .fs
+            .create_fd(fs_rights_base, fs_rights_inheriting, fs_flags, 0, inode)
+            .map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to create received file descriptor - {}",
+                    err
+                )
+            })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_fd, fd);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after receiving it (from={}, to={}) - {}",
+                ret_fd,
+                fd,
+                ret
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use super::*;
+
+impl JournalEffector {
+    pub fn save_pty_open(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        master: Fd,
+        slave: Fd,
+    ) -> anyhow::Result<()> {
+        Self::save_event(ctx, JournalEntry::CreatePtyV1 { master, slave })
+    }
+
+    pub fn apply_pty_open(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        master: Fd,
+        slave: Fd,
+    ) -> anyhow::Result<()> {
+        let (ret_master, ret_slave) = crate::syscalls::pty_open_internal(ctx).map_err(|err| {
+            anyhow::format_err!(
+                "journal restore error: failed to open pseudo-terminal - {}",
+                err
+            )
+        })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_master, master);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after open pseudo-terminal (from={}, to={}) - {}",
+                ret_master,
+                master,
+                ret
+            );
+        }
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_slave, slave);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after open pseudo-terminal (from={}, to={}) - {}",
+                ret_slave,
+                slave,
+                ret
+            );
+        }
+
+        Ok(())
+    }
+}
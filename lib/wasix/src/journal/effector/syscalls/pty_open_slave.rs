@@ -0,0 +1,37 @@
+use super::*;
+
+impl JournalEffector {
+    pub fn save_pty_open_slave(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        master: Fd,
+        slave: Fd,
+    ) -> anyhow::Result<()> {
+        Self::save_event(ctx, JournalEntry::OpenPtySlaveV1 { master, slave })
+    }
+
+    pub fn apply_pty_open_slave(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        master: Fd,
+        slave: Fd,
+    ) -> anyhow::Result<()> {
+        let ret_slave = crate::syscalls::pty_open_slave_internal(ctx, master).map_err(|err| {
+            anyhow::format_err!(
+                "journal restore error: failed to open pseudo-terminal slave (master={}) - {}",
+                master,
+                err
+            )
+        })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_slave, slave);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after open pseudo-terminal slave (from={}, to={}) - {}",
+                ret_slave,
+                slave,
+                ret
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use std::ops::Deref;
+
+use super::*;
+use crate::fs::Kind;
+
+impl JournalEffector {
+    pub fn save_signalfd(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        mask: u64,
+        flags: Fdflags,
+    ) -> anyhow::Result<()> {
+        Self::save_event(ctx, JournalEntry::CreateSignalFdV1 { fd, mask, flags })
+    }
+
+    /// Changes the signals of the descriptor when it was already restored,
+    /// otherwise creates it
+    pub fn apply_signalfd(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        mask: u64,
+        flags: Fdflags,
+    ) -> anyhow::Result<()> {
+        let exists = ctx
+            .data()
+            .state
+            .fs
+            .get_fd(fd)
+            .map(|fd| matches!(fd.inode.read().deref(), Kind::SignalFd { .. }))
+            .unwrap_or(false);
+        if exists {
+            crate::syscalls::signalfd_internal(ctx, fd, mask, flags).map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to change the signal descriptor (fd={}) - {}",
+                    fd,
+                    err
+                )
+            })?;
+            return Ok(());
+        }
+
+        let ret_fd =
+            crate::syscalls::signalfd_internal(ctx, u32::MAX, mask, flags).map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to create signal descriptor - {}",
+                    err
+                )
+            })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_fd, fd);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after create signal descriptor (from={}, to={}) - {}",
+                ret_fd,
+                fd,
+                ret
+            );
+        }
+
+        Ok(())
+    }
+}
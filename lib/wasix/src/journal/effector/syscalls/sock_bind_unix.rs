@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+use super::*;
+
+impl JournalEffector {
+    pub fn save_sock_bind_unix(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        path: PathBuf,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::SocketBindUnixV1 {
+                fd,
+                path: path.to_string_lossy().into_owned().into(),
+            },
+        )
+    }
+
+    pub fn apply_sock_bind_unix(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        path: &str,
+    ) -> anyhow::Result<()> {
+        // The file that the socket created when it was bound may have
+        // survived in the file system, it no longer belongs to any socket
+        ctx.data().state.fs_remove_file(Path::new(path)).ok();
+
+        crate::syscalls::sock_bind_unix_internal(ctx, fd, PathBuf::from(path))
+            .map(|r| r.map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| Err(err.to_string()))
+            .map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to bind socket to path (fd={}, path={}) - {}",
+                    fd,
+                    path,
+                    err
+                )
+            })?;
+        Ok(())
+    }
+}
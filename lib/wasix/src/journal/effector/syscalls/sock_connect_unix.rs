@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use crate::{
+    fs::Kind,
+    net::{
+        socket::{InodeSocket, InodeSocketKind},
+        unix::UnixPipeSocket,
+    },
+};
+
+use super::*;
+
+impl JournalEffector {
+    pub fn save_sock_connect_unix(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        path: PathBuf,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::SocketConnectedUnixV1 {
+                fd,
+                path: path.to_string_lossy().into_owned().into(),
+            },
+        )
+    }
+
+    /// The socket that was connected to is gone, the socket is restored as
+    /// a stream whose other end was closed (reads end and writes fail)
+    pub fn apply_sock_connect_unix(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        path: &str,
+    ) -> anyhow::Result<()> {
+        let (socket, _) = UnixPipeSocket::pair(Some(PathBuf::from(path)), None);
+        let kind = Kind::Socket {
+            socket: InodeSocket::new(InodeSocketKind::UnixStream {
+                socket: Box::new(socket),
+                local: None,
+                fds: None,
+                write_timeout: None,
+                read_timeout: None,
+            }),
+        };
+
+        let env = ctx.data();
+        let state = env.state();
+        let inodes = &state.inodes;
+        let inode = state
+            .fs
+            .create_inode_with_default_stat(inodes, kind, false, "socket".into());
+
+        let rights = Rights::all_socket();
+        let ret_fd = state
+            .fs
+            .create_fd(rights, rights, Fdflags::empty(), 0, inode)
+            .map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to create connected unix socket - {}",
+                    err
+                )
+            })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_fd, fd);
+        if ret != Errno::Success {
+            bail!(
+                    "journal restore error: failed renumber file descriptor after connecting the socket (from={}, to={}) - {}",
+                    ret_fd,
+                    fd,
+                    ret
+                );
+        }
+
+        Ok(())
+    }
+}
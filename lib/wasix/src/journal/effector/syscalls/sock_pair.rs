@@ -0,0 +1,50 @@
+use wasmer_wasix_types::wasi::Socktype;
+
+use super::*;
+
+impl JournalEffector {
+    pub fn save_sock_pair(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        ty: Socktype,
+        fd1: Fd,
+        fd2: Fd,
+    ) -> anyhow::Result<()> {
+        Self::save_event(ctx, JournalEntry::SocketPairV1 { ty, fd1, fd2 })
+    }
+
+    pub fn apply_sock_pair(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        ty: Socktype,
+        fd1: Fd,
+        fd2: Fd,
+    ) -> anyhow::Result<()> {
+        let (ret_fd1, ret_fd2) = crate::syscalls::sock_pair_internal(ctx, ty).map_err(|err| {
+            anyhow::format_err!(
+                "journal restore error: failed to create socket pair - {}",
+                err
+            )
+        })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_fd1, fd1);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after create socket pair (from={}, to={}) - {}",
+                ret_fd1,
+                fd1,
+                ret
+            );
+        }
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_fd2, fd2);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after create socket pair (from={}, to={}) - {}",
+                ret_fd2,
+                fd2,
+                ret
+            );
+        }
+
+        Ok(())
+    }
+}
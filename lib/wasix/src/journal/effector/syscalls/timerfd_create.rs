@@ -0,0 +1,46 @@
+use super::*;
+
+impl JournalEffector {
+    pub fn save_timerfd_create(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        clock_id: Snapshot0Clockid,
+        flags: Fdflags,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::CreateTimerFdV1 {
+                fd,
+                clock_id,
+                flags,
+            },
+        )
+    }
+
+    pub fn apply_timerfd_create(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        clock_id: Snapshot0Clockid,
+        flags: Fdflags,
+    ) -> anyhow::Result<()> {
+        let ret_fd =
+            crate::syscalls::timerfd_create_internal(ctx, clock_id, flags).map_err(|err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to create timer descriptor - {}",
+                    err
+                )
+            })?;
+
+        let ret = crate::syscalls::fd_renumber_internal(ctx, ret_fd, fd);
+        if ret != Errno::Success {
+            bail!(
+                "journal restore error: failed renumber file descriptor after create timer descriptor (from={}, to={}) - {}",
+                ret_fd,
+                fd,
+                ret
+            );
+        }
+
+        Ok(())
+    }
+}
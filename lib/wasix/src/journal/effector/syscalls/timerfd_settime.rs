@@ -0,0 +1,42 @@
+use super::*;
+
+impl JournalEffector {
+    pub fn save_timerfd_settime(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        value: Timestamp,
+        interval: Timestamp,
+        absolute: bool,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::SetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            },
+        )
+    }
+
+    /// Arms the timer again, a relative timer then expires relative to the
+    /// time that the journal is restored at
+    pub fn apply_timerfd_settime(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        value: Timestamp,
+        interval: Timestamp,
+        absolute: bool,
+    ) -> anyhow::Result<()> {
+        crate::syscalls::timerfd_settime_internal(ctx, fd, value, interval, absolute).map_err(
+            |err| {
+                anyhow::format_err!(
+                    "journal restore error: failed to set the timer descriptor (fd={}) - {}",
+                    fd,
+                    err
+                )
+            },
+        )?;
+        Ok(())
+    }
+}
@@ -96,8 +96,8 @@ pub use crate::{
     rewind::*,
     runtime::{task_manager::VirtualTaskManager, PluggableRuntime, Runtime},
     state::{
-        WasiEnv, WasiEnvBuilder, WasiEnvInit, WasiFunctionEnv, WasiInstanceHandles,
-        WasiStateCreationError, ALL_RIGHTS,
+        ImportsFactory, PreInitError, PreInitializer, WasiEnv, WasiEnvBuilder, WasiEnvInit,
+        WasiFunctionEnv, WasiInstanceHandles, WasiStateCreationError, ALL_RIGHTS,
     },
     syscalls::{rewind, rewind_ext, types, unwind},
     utils::is_wasix_module,
@@ -436,6 +436,10 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "epoll_create" => Function::new_typed_with_env(&mut store, env, epoll_create::<Memory32>),
         "epoll_ctl" => Function::new_typed_with_env(&mut store, env, epoll_ctl::<Memory32>),
         "epoll_wait" => Function::new_typed_with_env(&mut store, env, epoll_wait::<Memory32>),
+        "timerfd_create" => Function::new_typed_with_env(&mut store, env, timerfd_create::<Memory32>),
+        "timerfd_settime" => Function::new_typed_with_env(&mut store, env, timerfd_settime::<Memory32>),
+        "timerfd_gettime" => Function::new_typed_with_env(&mut store, env, timerfd_gettime::<Memory32>),
+        "signalfd" => Function::new_typed_with_env(&mut store, env, signalfd::<Memory32>),
         "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
         "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
         "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
@@ -478,12 +482,43 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory32>),
         "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
         "proc_raise_interval" => Function::new_typed_with_env(&mut store, env, proc_raise_interval),
+        "proc_timer_create" => Function::new_typed_with_env(&mut store, env, proc_timer_create::<Memory32>),
+        "proc_timer_settime" => Function::new_typed_with_env(&mut store, env, proc_timer_settime::<Memory32>),
+        "proc_timer_gettime" => Function::new_typed_with_env(&mut store, env, proc_timer_gettime::<Memory32>),
+        "proc_timer_getoverrun" => Function::new_typed_with_env(&mut store, env, proc_timer_getoverrun::<Memory32>),
+        "proc_timer_delete" => Function::new_typed_with_env(&mut store, env, proc_timer_delete),
+        "proc_rusage" => Function::new_typed_with_env(&mut store, env, proc_rusage::<Memory32>),
         "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory32>),
         "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory32>),
         "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory32>),
+        "proc_getpgid" => Function::new_typed_with_env(&mut store, env, proc_getpgid::<Memory32>),
+        "proc_setpgid" => Function::new_typed_with_env(&mut store, env, proc_setpgid),
+        "proc_getsid" => Function::new_typed_with_env(&mut store, env, proc_getsid::<Memory32>),
+        "proc_setsid" => Function::new_typed_with_env(&mut store, env, proc_setsid::<Memory32>),
+        "proc_getuid" => Function::new_typed_with_env(&mut store, env, proc_getuid::<Memory32>),
+        "proc_geteuid" => Function::new_typed_with_env(&mut store, env, proc_geteuid::<Memory32>),
+        "proc_getgid" => Function::new_typed_with_env(&mut store, env, proc_getgid::<Memory32>),
+        "proc_getegid" => Function::new_typed_with_env(&mut store, env, proc_getegid::<Memory32>),
+        "proc_getgroups" => Function::new_typed_with_env(&mut store, env, proc_getgroups::<Memory32>),
+        "proc_setuid" => Function::new_typed_with_env(&mut store, env, proc_setuid),
+        "proc_seteuid" => Function::new_typed_with_env(&mut store, env, proc_seteuid),
+        "proc_setgid" => Function::new_typed_with_env(&mut store, env, proc_setgid),
+        "proc_setegid" => Function::new_typed_with_env(&mut store, env, proc_setegid),
+        "proc_setgroups" => Function::new_typed_with_env(&mut store, env, proc_setgroups::<Memory32>),
+        "proc_getrlimit" => Function::new_typed_with_env(&mut store, env, proc_getrlimit::<Memory32>),
+        "proc_setrlimit" => Function::new_typed_with_env(&mut store, env, proc_setrlimit),
+        "proc_signal_group" => Function::new_typed_with_env(&mut store, env, proc_signal_group),
+        "proc_dup_fd" => Function::new_typed_with_env(&mut store, env, proc_dup_fd::<Memory32>),
         "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
         "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory32>),
         "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory32>),
+        "tty_get_pgrp" => Function::new_typed_with_env(&mut store, env, tty_get_pgrp::<Memory32>),
+        "tty_set_pgrp" => Function::new_typed_with_env(&mut store, env, tty_set_pgrp),
+        "pty_open" => Function::new_typed_with_env(&mut store, env, pty_open::<Memory32>),
+        "pty_open_slave" => Function::new_typed_with_env(&mut store, env, pty_open_slave::<Memory32>),
+        "pty_get" => Function::new_typed_with_env(&mut store, env, pty_get::<Memory32>),
+        "pty_set" => Function::new_typed_with_env(&mut store, env, pty_set::<Memory32>),
+        "pty_set_ctty" => Function::new_typed_with_env(&mut store, env, pty_set_ctty),
         "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory32>),
         "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory32>),
         "callback_signal" => Function::new_typed_with_env(&mut store, env, callback_signal::<Memory32>),
@@ -539,6 +574,15 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory32>),
         "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory32>),
         "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
+        "sock_pair" => Function::new_typed_with_env(&mut store, env, sock_pair::<Memory32>),
+        "sock_bind_unix" => Function::new_typed_with_env(&mut store, env, sock_bind_unix::<Memory32>),
+        "sock_connect_unix" => Function::new_typed_with_env(&mut store, env, sock_connect_unix::<Memory32>),
+        "sock_addr_local_unix" => Function::new_typed_with_env(&mut store, env, sock_addr_local_unix::<Memory32>),
+        "sock_addr_peer_unix" => Function::new_typed_with_env(&mut store, env, sock_addr_peer_unix::<Memory32>),
+        "sock_send_to_unix" => Function::new_typed_with_env(&mut store, env, sock_send_to_unix::<Memory32>),
+        "sock_recv_from_unix" => Function::new_typed_with_env(&mut store, env, sock_recv_from_unix::<Memory32>),
+        "sock_send_fds" => Function::new_typed_with_env(&mut store, env, sock_send_fds::<Memory32>),
+        "sock_recv_fds" => Function::new_typed_with_env(&mut store, env, sock_recv_fds::<Memory32>),
         "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory32>),
     };
     namespace
@@ -557,6 +601,10 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "epoll_create" => Function::new_typed_with_env(&mut store, env, epoll_create::<Memory64>),
         "epoll_ctl" => Function::new_typed_with_env(&mut store, env, epoll_ctl::<Memory64>),
         "epoll_wait" => Function::new_typed_with_env(&mut store, env, epoll_wait::<Memory64>),
+        "timerfd_create" => Function::new_typed_with_env(&mut store, env, timerfd_create::<Memory64>),
+        "timerfd_settime" => Function::new_typed_with_env(&mut store, env, timerfd_settime::<Memory64>),
+        "timerfd_gettime" => Function::new_typed_with_env(&mut store, env, timerfd_gettime::<Memory64>),
+        "signalfd" => Function::new_typed_with_env(&mut store, env, signalfd::<Memory64>),
         "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
         "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
         "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
@@ -599,12 +647,43 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory64>),
         "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
         "proc_raise_interval" => Function::new_typed_with_env(&mut store, env, proc_raise_interval),
+        "proc_timer_create" => Function::new_typed_with_env(&mut store, env, proc_timer_create::<Memory64>),
+        "proc_timer_settime" => Function::new_typed_with_env(&mut store, env, proc_timer_settime::<Memory64>),
+        "proc_timer_gettime" => Function::new_typed_with_env(&mut store, env, proc_timer_gettime::<Memory64>),
+        "proc_timer_getoverrun" => Function::new_typed_with_env(&mut store, env, proc_timer_getoverrun::<Memory64>),
+        "proc_timer_delete" => Function::new_typed_with_env(&mut store, env, proc_timer_delete),
+        "proc_rusage" => Function::new_typed_with_env(&mut store, env, proc_rusage::<Memory64>),
         "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory64>),
         "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory64>),
         "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory64>),
+        "proc_getpgid" => Function::new_typed_with_env(&mut store, env, proc_getpgid::<Memory64>),
+        "proc_setpgid" => Function::new_typed_with_env(&mut store, env, proc_setpgid),
+        "proc_getsid" => Function::new_typed_with_env(&mut store, env, proc_getsid::<Memory64>),
+        "proc_setsid" => Function::new_typed_with_env(&mut store, env, proc_setsid::<Memory64>),
+        "proc_getuid" => Function::new_typed_with_env(&mut store, env, proc_getuid::<Memory64>),
+        "proc_geteuid" => Function::new_typed_with_env(&mut store, env, proc_geteuid::<Memory64>),
+        "proc_getgid" => Function::new_typed_with_env(&mut store, env, proc_getgid::<Memory64>),
+        "proc_getegid" => Function::new_typed_with_env(&mut store, env, proc_getegid::<Memory64>),
+        "proc_getgroups" => Function::new_typed_with_env(&mut store, env, proc_getgroups::<Memory64>),
+        "proc_setuid" => Function::new_typed_with_env(&mut store, env, proc_setuid),
+        "proc_seteuid" => Function::new_typed_with_env(&mut store, env, proc_seteuid),
+        "proc_setgid" => Function::new_typed_with_env(&mut store, env, proc_setgid),
+        "proc_setegid" => Function::new_typed_with_env(&mut store, env, proc_setegid),
+        "proc_setgroups" => Function::new_typed_with_env(&mut store, env, proc_setgroups::<Memory64>),
+        "proc_getrlimit" => Function::new_typed_with_env(&mut store, env, proc_getrlimit::<Memory64>),
+        "proc_setrlimit" => Function::new_typed_with_env(&mut store, env, proc_setrlimit),
+        "proc_signal_group" => Function::new_typed_with_env(&mut store, env, proc_signal_group),
+        "proc_dup_fd" => Function::new_typed_with_env(&mut store, env, proc_dup_fd::<Memory64>),
         "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory64>),
         "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory64>),
         "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory64>),
+        "tty_get_pgrp" => Function::new_typed_with_env(&mut store, env, tty_get_pgrp::<Memory64>),
+        "tty_set_pgrp" => Function::new_typed_with_env(&mut store, env, tty_set_pgrp),
+        "pty_open" => Function::new_typed_with_env(&mut store, env, pty_open::<Memory64>),
+        "pty_open_slave" => Function::new_typed_with_env(&mut store, env, pty_open_slave::<Memory64>),
+        "pty_get" => Function::new_typed_with_env(&mut store, env, pty_get::<Memory64>),
+        "pty_set" => Function::new_typed_with_env(&mut store, env, pty_set::<Memory64>),
+        "pty_set_ctty" => Function::new_typed_with_env(&mut store, env, pty_set_ctty),
         "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory64>),
         "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory64>),
         "callback_signal" => Function::new_typed_with_env(&mut store, env, callback_signal::<Memory64>),
@@ -660,6 +739,15 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory64>),
         "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory64>),
         "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
+        "sock_pair" => Function::new_typed_with_env(&mut store, env, sock_pair::<Memory64>),
+        "sock_bind_unix" => Function::new_typed_with_env(&mut store, env, sock_bind_unix::<Memory64>),
+        "sock_connect_unix" => Function::new_typed_with_env(&mut store, env, sock_connect_unix::<Memory64>),
+        "sock_addr_local_unix" => Function::new_typed_with_env(&mut store, env, sock_addr_local_unix::<Memory64>),
+        "sock_addr_peer_unix" => Function::new_typed_with_env(&mut store, env, sock_addr_peer_unix::<Memory64>),
+        "sock_send_to_unix" => Function::new_typed_with_env(&mut store, env, sock_send_to_unix::<Memory64>),
+        "sock_recv_from_unix" => Function::new_typed_with_env(&mut store, env, sock_recv_from_unix::<Memory64>),
+        "sock_send_fds" => Function::new_typed_with_env(&mut store, env, sock_send_fds::<Memory64>),
+        "sock_recv_fds" => Function::new_typed_with_env(&mut store, env, sock_recv_fds::<Memory64>),
         "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory64>),
     };
     namespace
@@ -1,6 +1,7 @@
 use std::{
     intrinsics::transmute,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::{Path, PathBuf},
     time::Duration,
 };
 
@@ -17,6 +18,7 @@ use wasmer_wasix_types::{
 };
 
 pub mod socket;
+pub mod unix;
 
 #[allow(dead_code)]
 pub(crate) fn read_ip<M: MemorySize>(
@@ -228,6 +230,65 @@ pub(crate) fn write_ip_port<M: MemorySize>(
     Ok(())
 }
 
+/// Writes the address of a UNIX socket, paths do not fit in the address
+/// so they are returned by the `_unix` variants of the syscalls instead
+pub(crate) fn write_unix_port<M: MemorySize>(
+    memory: &MemoryView,
+    ptr: WasmPtr<__wasi_addr_port_t, M>,
+) -> Result<(), Errno> {
+    let addr = __wasi_addr_port_t {
+        tag: Addressfamily::Unix,
+        _padding: 0,
+        u: __wasi_addr_port_u { octs: [0; 18] },
+    };
+
+    let addr_ptr = ptr.deref(memory);
+    addr_ptr.write(addr).map_err(crate::mem_error_to_wasi)?;
+    Ok(())
+}
+
+/// Reads the path of a UNIX socket and resolves it against the current
+/// directory of the process
+pub(crate) fn read_unix_path<M: MemorySize>(
+    memory: &MemoryView,
+    ptr: WasmPtr<u8, M>,
+    len: M::Offset,
+    current_dir: &str,
+) -> Result<PathBuf, Errno> {
+    let path = ptr
+        .read_utf8_string(memory, len)
+        .map_err(crate::mem_error_to_wasi)?;
+    let path = path.trim_end_matches('\0');
+    unix::resolve_path(current_dir, path).ok_or(Errno::Inval)
+}
+
+/// Writes the path of a UNIX socket, the length is read as the size of the
+/// buffer and then set to the length of the path (which is zero for unnamed
+/// sockets). Paths that exceed the size of the buffer return ERANGE
+pub(crate) fn write_unix_path<M: MemorySize>(
+    memory: &MemoryView,
+    ptr: WasmPtr<u8, M>,
+    len: WasmPtr<M::Offset, M>,
+    path: Option<&Path>,
+) -> Result<(), Errno> {
+    let path = path
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let max_len: u64 = len.read(memory).map_err(crate::mem_error_to_wasi)?.into();
+
+    let path_len: M::Offset = path.len().try_into().map_err(|_| Errno::Overflow)?;
+    len.write(memory, path_len)
+        .map_err(crate::mem_error_to_wasi)?;
+    if path.len() as u64 > max_len {
+        return Err(Errno::Range);
+    }
+
+    ptr.slice(memory, path_len)
+        .and_then(|slice| slice.write_slice(path.as_bytes()))
+        .map_err(crate::mem_error_to_wasi)?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub(crate) fn read_route<M: MemorySize>(
     memory: &MemoryView,
@@ -3,6 +3,8 @@ use std::{
     io,
     mem::MaybeUninit,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::DerefMut,
+    path::{Path, PathBuf},
     pin::Pin,
     sync::{Arc, RwLock},
     task::{Context, Poll},
@@ -14,13 +16,14 @@ use derivative::Derivative;
 use serde_derive::{Deserialize, Serialize};
 use virtual_mio::InterestHandler;
 use virtual_net::{
-    net_error_into_io_err, NetworkError, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    net_error_into_io_err, NetworkError, VirtualIcmpSocket, VirtualIoSource, VirtualNetworking,
+    VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket, VirtualUnixSocket,
 };
 use wasmer_types::MemorySize;
 use wasmer_wasix_types::wasi::{Addressfamily, Errno, Rights, SockProto, Sockoption, Socktype};
 
-use crate::{net::net_error_into_wasi_err, VirtualTaskManager};
+use super::unix::{UnixDatagramSocket, UnixFdChannel, UnixListener, UnixSocketRegistry};
+use crate::{fs::Fd, net::net_error_into_wasi_err, VirtualTaskManager};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -42,6 +45,8 @@ pub enum InodeSocketKind {
         ty: Socktype,
         pt: SockProto,
         addr: Option<SocketAddr>,
+        /// Path that a UNIX socket is bound to
+        unix_addr: Option<PathBuf>,
         only_v6: bool,
         reuse_port: bool,
         reuse_addr: bool,
@@ -75,6 +80,23 @@ pub enum InodeSocketKind {
     RemoteTcpStream {
         peer_addr: SocketAddr,
     },
+    UnixListener {
+        listener: UnixListener,
+        accept_timeout: Option<Duration>,
+    },
+    UnixStream {
+        socket: Box<dyn VirtualUnixSocket + Sync>,
+        /// Path that this end of the stream is bound to
+        local: Option<PathBuf>,
+        /// Carries file descriptors to the other end, streams bridged to
+        /// the host can not pass them
+        fds: Option<UnixFdChannel>,
+        write_timeout: Option<Duration>,
+        read_timeout: Option<Duration>,
+    },
+    UnixDatagram {
+        socket: UnixDatagramSocket,
+    },
 }
 
 pub enum WasiSocketOption {
@@ -303,11 +325,36 @@ impl InodeSocket {
         }
     }
 
+    pub fn bind_unix(&self, unix: &UnixSocketRegistry, path: PathBuf) -> Result<(), Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        match &mut inner.kind {
+            InodeSocketKind::PreSocket {
+                family: Addressfamily::Unix,
+                ty: Socktype::Stream,
+                unix_addr,
+                ..
+            } => {
+                if unix_addr.is_some() {
+                    return Err(Errno::Inval);
+                }
+                // the path is only registered once the socket listens
+                unix_addr.replace(path);
+                Ok(())
+            }
+            InodeSocketKind::UnixDatagram { socket } => {
+                socket.bind(unix, path).map_err(net_error_into_wasi_err)
+            }
+            InodeSocketKind::PreSocket { .. } => Err(Errno::Inval),
+            _ => Err(Errno::Notsup),
+        }
+    }
+
     pub async fn listen(
         &self,
         tasks: &dyn VirtualTaskManager,
         net: &dyn VirtualNetworking,
-        _backlog: usize,
+        unix: &UnixSocketRegistry,
+        backlog: usize,
     ) -> Result<Option<InodeSocket>, Errno> {
         let timeout = self
             .opt_time(TimeType::AcceptTimeout)
@@ -319,13 +366,27 @@ impl InodeSocket {
             let inner = self.inner.protected.read().unwrap();
             match &inner.kind {
                 InodeSocketKind::PreSocket {
+                    family,
                     ty,
                     addr,
+                    unix_addr,
                     only_v6,
                     reuse_port,
                     reuse_addr,
                     ..
                 } => match *ty {
+                    Socktype::Stream if *family == Addressfamily::Unix => {
+                        let path = unix_addr.clone().ok_or(Errno::Inval)?;
+                        drop(inner);
+
+                        let listener = unix
+                            .listen(path, backlog)
+                            .map_err(net_error_into_wasi_err)?;
+                        return Ok(Some(InodeSocket::new(InodeSocketKind::UnixListener {
+                            listener,
+                            accept_timeout: Some(timeout),
+                        })));
+                    }
                     Socktype::Stream => {
                         if addr.is_none() {
                             tracing::warn!("wasi[?]::sock_listen - failed - address not set");
@@ -368,7 +429,7 @@ impl InodeSocket {
         tasks: &dyn VirtualTaskManager,
         nonblocking: bool,
         timeout: Option<Duration>,
-    ) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr), Errno> {
+    ) -> Result<(InodeSocketKind, Option<SocketAddr>), Errno> {
         struct SocketAccepter<'a> {
             sock: &'a InodeSocket,
             nonblocking: bool,
@@ -383,33 +444,58 @@ impl InodeSocket {
             }
         }
         impl<'a> Future for SocketAccepter<'a> {
-            type Output = Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr), Errno>;
+            type Output = Result<(InodeSocketKind, Option<SocketAddr>), Errno>;
             fn poll(
                 mut self: Pin<&mut Self>,
                 cx: &mut std::task::Context<'_>,
             ) -> std::task::Poll<Self::Output> {
                 loop {
                     let mut inner = self.sock.inner.protected.write().unwrap();
-                    return match &mut inner.kind {
-                        InodeSocketKind::TcpListener { socket, .. } => match socket.try_accept() {
-                            Ok((child, addr)) => Poll::Ready(Ok((child, addr))),
-                            Err(NetworkError::WouldBlock) if self.nonblocking => {
-                                Poll::Ready(Err(Errno::Again))
-                            }
-                            Err(NetworkError::WouldBlock) if !self.handler_registered => {
-                                let res = socket.set_handler(cx.waker().into());
-                                if let Err(err) = res {
-                                    return Poll::Ready(Err(net_error_into_wasi_err(err)));
-                                }
-                                drop(inner);
-                                self.handler_registered = true;
-                                continue;
+                    let res = match &mut inner.kind {
+                        InodeSocketKind::TcpListener { socket, .. } => {
+                            socket.try_accept().map(|(child, addr)| {
+                                let child = InodeSocketKind::TcpStream {
+                                    socket: child,
+                                    write_timeout: None,
+                                    read_timeout: None,
+                                };
+                                (child, Some(addr))
+                            })
+                        }
+                        InodeSocketKind::UnixListener { listener, .. } => {
+                            let local = listener.addr_local().to_path_buf();
+                            listener.try_accept().map(|child| {
+                                let child = InodeSocketKind::UnixStream {
+                                    fds: Some(child.fd_channel()),
+                                    socket: Box::new(child),
+                                    local: Some(local),
+                                    write_timeout: None,
+                                    read_timeout: None,
+                                };
+                                (child, None)
+                            })
+                        }
+                        InodeSocketKind::PreSocket { .. } => {
+                            return Poll::Ready(Err(Errno::Notconn))
+                        }
+                        _ => return Poll::Ready(Err(Errno::Notsup)),
+                    };
+                    return match res {
+                        Ok(ret) => Poll::Ready(Ok(ret)),
+                        Err(NetworkError::WouldBlock) if self.nonblocking => {
+                            Poll::Ready(Err(Errno::Again))
+                        }
+                        Err(NetworkError::WouldBlock) if !self.handler_registered => {
+                            let res = inner.set_handler(cx.waker().into());
+                            if let Err(err) = res {
+                                return Poll::Ready(Err(net_error_into_wasi_err(err)));
                             }
-                            Err(NetworkError::WouldBlock) => Poll::Pending,
-                            Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
-                        },
-                        InodeSocketKind::PreSocket { .. } => Poll::Ready(Err(Errno::Notconn)),
-                        _ => Poll::Ready(Err(Errno::Notsup)),
+                            drop(inner);
+                            self.handler_registered = true;
+                            continue;
+                        }
+                        Err(NetworkError::WouldBlock) => Poll::Pending,
+                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
                     };
                 }
             }
@@ -442,6 +528,9 @@ impl InodeSocket {
             InodeSocketKind::Raw(_) => {}
             InodeSocketKind::PreSocket { .. } => return Err(Errno::Notconn),
             InodeSocketKind::RemoteTcpStream { .. } => {}
+            InodeSocketKind::UnixListener { .. } => {}
+            InodeSocketKind::UnixStream { .. } => {}
+            InodeSocketKind::UnixDatagram { .. } => {}
         };
         Ok(())
     }
@@ -539,6 +628,80 @@ impl InodeSocket {
         Ok(Some(socket))
     }
 
+    /// Connects to the UNIX socket bound to a path, sockets of other guest
+    /// processes take precedence over the host socket the path is bridged to
+    pub async fn connect_unix(
+        &mut self,
+        tasks: &dyn VirtualTaskManager,
+        net: &dyn VirtualNetworking,
+        unix: &UnixSocketRegistry,
+        peer: PathBuf,
+        bridge: Option<PathBuf>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Option<InodeSocket>, Errno> {
+        let timeout = timeout.unwrap_or(Duration::from_secs(30));
+
+        let (local, write_timeout, read_timeout, handler) = {
+            let mut inner = self.inner.protected.write().unwrap();
+            match &mut inner.kind {
+                InodeSocketKind::PreSocket {
+                    family: Addressfamily::Unix,
+                    ty: Socktype::Stream,
+                    unix_addr,
+                    write_timeout,
+                    read_timeout,
+                    handler,
+                    ..
+                } => (
+                    unix_addr.clone(),
+                    *write_timeout,
+                    *read_timeout,
+                    handler.take(),
+                ),
+                InodeSocketKind::UnixDatagram { socket } => {
+                    socket
+                        .connect(unix, peer)
+                        .map_err(net_error_into_wasi_err)?;
+                    return Ok(None);
+                }
+                InodeSocketKind::PreSocket { .. } => return Err(Errno::Inval),
+                _ => return Err(Errno::Notsup),
+            }
+        };
+
+        let (mut socket, fds): (Box<dyn VirtualUnixSocket + Sync>, _) =
+            match (unix.connect(&peer, local.clone()), bridge) {
+                (Ok(socket), _) => {
+                    let fds = socket.fd_channel();
+                    (Box::new(socket), Some(fds))
+                }
+                (Err(NetworkError::AddressNotAvailable), Some(host_path)) => {
+                    let socket = tokio::select! {
+                        res = net.connect_unix(&host_path) => res.map_err(net_error_into_wasi_err)?,
+                        _ = tasks.sleep_now(timeout) => return Err(Errno::Timedout)
+                    };
+                    (socket, None)
+                }
+                (Err(err), _) => return Err(net_error_into_wasi_err(err)),
+            };
+
+        if let Some(handler) = handler {
+            socket
+                .set_handler(handler)
+                .map_err(net_error_into_wasi_err)?;
+        }
+
+        let socket = InodeSocket::new(InodeSocketKind::UnixStream {
+            socket,
+            local,
+            fds,
+            write_timeout,
+            read_timeout,
+        });
+
+        Ok(Some(socket))
+    }
+
     pub fn status(&self) -> Result<WasiSocketStatus, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
@@ -546,10 +709,55 @@ impl InodeSocket {
             InodeSocketKind::TcpListener { .. } => WasiSocketStatus::Opened,
             InodeSocketKind::TcpStream { .. } => WasiSocketStatus::Opened,
             InodeSocketKind::UdpSocket { .. } => WasiSocketStatus::Opened,
+            InodeSocketKind::UnixListener { .. } => WasiSocketStatus::Opened,
+            InodeSocketKind::UnixStream { socket, .. } => match socket.is_closed() {
+                true => WasiSocketStatus::Closed,
+                false => WasiSocketStatus::Opened,
+            },
+            InodeSocketKind::UnixDatagram { .. } => WasiSocketStatus::Opened,
             _ => WasiSocketStatus::Failed,
         })
     }
 
+    /// Returns the path that a UNIX socket is bound to, unnamed sockets
+    /// return [`None`]
+    pub fn addr_local_unix(&self) -> Result<Option<PathBuf>, Errno> {
+        let inner = self.inner.protected.read().unwrap();
+        Ok(match &inner.kind {
+            InodeSocketKind::PreSocket {
+                family: Addressfamily::Unix,
+                unix_addr,
+                ..
+            } => unix_addr.clone(),
+            InodeSocketKind::UnixListener { listener, .. } => {
+                Some(listener.addr_local().to_path_buf())
+            }
+            InodeSocketKind::UnixStream { local, .. } => local.clone(),
+            InodeSocketKind::UnixDatagram { socket } => socket.addr_local(),
+            _ => return Err(Errno::Notsup),
+        })
+    }
+
+    /// Returns the path of the UNIX socket this socket is connected to,
+    /// unnamed peers return [`None`]
+    pub fn addr_peer_unix(&self) -> Result<Option<PathBuf>, Errno> {
+        let inner = self.inner.protected.read().unwrap();
+        match &inner.kind {
+            InodeSocketKind::PreSocket {
+                family: Addressfamily::Unix,
+                ..
+            } => Err(Errno::Notconn),
+            InodeSocketKind::UnixListener { .. } => Err(Errno::Notconn),
+            InodeSocketKind::UnixStream { socket, .. } => {
+                socket.addr_peer().map_err(net_error_into_wasi_err)
+            }
+            InodeSocketKind::UnixDatagram { socket } => {
+                socket.addr_peer().map_err(net_error_into_wasi_err)
+            }
+            _ => Err(Errno::Notsup),
+        }
+    }
+
     pub fn addr_local(&self) -> Result<SocketAddr, Errno> {
         let inner = self.inner.protected.read().unwrap();
         Ok(match &inner.kind {
@@ -831,6 +1039,25 @@ impl InodeSocket {
                 }
                 Ok(())
             }
+            InodeSocketKind::UnixStream {
+                write_timeout,
+                read_timeout,
+                ..
+            } => {
+                match ty {
+                    TimeType::WriteTimeout => *write_timeout = timeout,
+                    TimeType::ReadTimeout => *read_timeout = timeout,
+                    _ => return Err(Errno::Inval),
+                }
+                Ok(())
+            }
+            InodeSocketKind::UnixListener { accept_timeout, .. } => {
+                match ty {
+                    TimeType::AcceptTimeout => *accept_timeout = timeout,
+                    _ => return Err(Errno::Inval),
+                }
+                Ok(())
+            }
             InodeSocketKind::PreSocket {
                 read_timeout,
                 write_timeout,
@@ -867,6 +1094,19 @@ impl InodeSocket {
                 TimeType::AcceptTimeout => *accept_timeout,
                 _ => return Err(Errno::Inval),
             }),
+            InodeSocketKind::UnixStream {
+                read_timeout,
+                write_timeout,
+                ..
+            } => Ok(match ty {
+                TimeType::ReadTimeout => *read_timeout,
+                TimeType::WriteTimeout => *write_timeout,
+                _ => return Err(Errno::Inval),
+            }),
+            InodeSocketKind::UnixListener { accept_timeout, .. } => Ok(match ty {
+                TimeType::AcceptTimeout => *accept_timeout,
+                _ => return Err(Errno::Inval),
+            }),
             InodeSocketKind::PreSocket {
                 read_timeout,
                 write_timeout,
@@ -1017,6 +1257,8 @@ impl InodeSocket {
                                 Err(NetworkError::NotConnected)
                             }
                         }
+                        InodeSocketKind::UnixStream { socket, .. } => socket.try_send(self.data),
+                        InodeSocketKind::UnixDatagram { socket } => socket.try_send(self.data),
                         InodeSocketKind::PreSocket { .. } => {
                             return Poll::Ready(Err(Errno::Notconn))
                         }
@@ -1188,6 +1430,10 @@ impl InodeSocket {
                                 }
                             }
                         }
+                        InodeSocketKind::UnixStream { socket, .. } => socket.try_recv(self.data),
+                        InodeSocketKind::UnixDatagram { socket } => {
+                            socket.try_recv_from(self.data).map(|(amt, _)| amt)
+                        }
                         InodeSocketKind::PreSocket { .. } => {
                             return Poll::Ready(Err(Errno::Notconn))
                         }
@@ -1304,12 +1550,346 @@ impl InodeSocket {
         }
     }
 
+    /// Sends a datagram to the UNIX socket bound to a path
+    pub async fn send_to_unix(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        unix: &UnixSocketRegistry,
+        buf: &[u8],
+        path: &Path,
+        timeout: Option<Duration>,
+        nonblocking: bool,
+    ) -> Result<usize, Errno> {
+        struct SocketSender<'a, 'b> {
+            inner: &'a InodeSocketInner,
+            unix: &'a UnixSocketRegistry,
+            data: &'b [u8],
+            path: &'b Path,
+            nonblocking: bool,
+            handler_registered: bool,
+        }
+        impl<'a, 'b> Drop for SocketSender<'a, 'b> {
+            fn drop(&mut self) {
+                if self.handler_registered {
+                    let mut inner = self.inner.protected.write().unwrap();
+                    inner.remove_handler();
+                }
+            }
+        }
+        impl<'a, 'b> Future for SocketSender<'a, 'b> {
+            type Output = Result<usize, Errno>;
+            fn poll(
+                mut self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> Poll<Self::Output> {
+                loop {
+                    let mut inner = self.inner.protected.write().unwrap();
+                    let res = match &mut inner.kind {
+                        InodeSocketKind::UnixDatagram { socket } => {
+                            socket.try_send_to(self.data, self.unix, self.path)
+                        }
+                        InodeSocketKind::UnixStream { .. } => {
+                            return Poll::Ready(Err(Errno::Isconn))
+                        }
+                        InodeSocketKind::PreSocket { .. } => {
+                            return Poll::Ready(Err(Errno::Notconn))
+                        }
+                        _ => return Poll::Ready(Err(Errno::Notsup)),
+                    };
+                    return match res {
+                        Ok(amt) => Poll::Ready(Ok(amt)),
+                        Err(NetworkError::WouldBlock) if self.nonblocking => {
+                            Poll::Ready(Err(Errno::Again))
+                        }
+                        Err(NetworkError::WouldBlock) if !self.handler_registered => {
+                            inner
+                                .set_handler(cx.waker().into())
+                                .map_err(net_error_into_wasi_err)?;
+                            self.handler_registered = true;
+                            drop(inner);
+                            continue;
+                        }
+                        Err(NetworkError::WouldBlock) => Poll::Pending,
+                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
+                    };
+                }
+            }
+        }
+
+        let poller = SocketSender {
+            inner: &self.inner,
+            unix,
+            data: buf,
+            path,
+            nonblocking,
+            handler_registered: false,
+        };
+        if let Some(timeout) = timeout {
+            tokio::select! {
+                res = poller => res,
+                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+            }
+        } else {
+            poller.await
+        }
+    }
+
+    /// Receives a datagram along with the path of the UNIX socket that
+    /// sent it, unnamed senders return [`None`]
+    pub async fn recv_from_unix(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        buf: &mut [MaybeUninit<u8>],
+        timeout: Option<Duration>,
+        nonblocking: bool,
+    ) -> Result<(usize, Option<PathBuf>), Errno> {
+        struct SocketReceiver<'a, 'b> {
+            inner: &'a InodeSocketInner,
+            data: &'b mut [MaybeUninit<u8>],
+            nonblocking: bool,
+            handler_registered: bool,
+        }
+        impl<'a, 'b> Drop for SocketReceiver<'a, 'b> {
+            fn drop(&mut self) {
+                if self.handler_registered {
+                    let mut inner = self.inner.protected.write().unwrap();
+                    inner.remove_handler();
+                }
+            }
+        }
+        impl<'a, 'b> Future for SocketReceiver<'a, 'b> {
+            type Output = Result<(usize, Option<PathBuf>), Errno>;
+            fn poll(
+                mut self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> Poll<Self::Output> {
+                let mut inner = self.inner.protected.write().unwrap();
+                loop {
+                    let res = match &mut inner.kind {
+                        InodeSocketKind::UnixDatagram { socket } => socket.try_recv_from(self.data),
+                        InodeSocketKind::UnixStream { socket, .. } => {
+                            let peer = socket.addr_peer().ok().flatten();
+                            socket.try_recv(self.data).map(|amt| (amt, peer))
+                        }
+                        InodeSocketKind::PreSocket { .. } => {
+                            return Poll::Ready(Err(Errno::Notconn))
+                        }
+                        _ => return Poll::Ready(Err(Errno::Notsup)),
+                    };
+                    return match res {
+                        Ok(ret) => Poll::Ready(Ok(ret)),
+                        Err(NetworkError::WouldBlock) if self.nonblocking => {
+                            Poll::Ready(Err(Errno::Again))
+                        }
+                        Err(NetworkError::WouldBlock) if !self.handler_registered => {
+                            inner
+                                .set_handler(cx.waker().into())
+                                .map_err(net_error_into_wasi_err)?;
+                            self.handler_registered = true;
+                            continue;
+                        }
+                        Err(NetworkError::WouldBlock) => Poll::Pending,
+                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
+                    };
+                }
+            }
+        }
+
+        let poller = SocketReceiver {
+            inner: &self.inner,
+            data: buf,
+            nonblocking,
+            handler_registered: false,
+        };
+        if let Some(timeout) = timeout {
+            tokio::select! {
+                res = poller => res,
+                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+            }
+        } else {
+            poller.await
+        }
+    }
+
+    /// Sends data over a UNIX socket with file descriptors attached to it,
+    /// the descriptors are handed over once some of the data was sent
+    pub async fn send_fds(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        buf: &[u8],
+        fds: Vec<Fd>,
+        timeout: Option<Duration>,
+        nonblocking: bool,
+    ) -> Result<usize, Errno> {
+        struct SocketSender<'a, 'b> {
+            inner: &'a InodeSocketInner,
+            data: &'b [u8],
+            fds: Vec<Fd>,
+            nonblocking: bool,
+            handler_registered: bool,
+        }
+        impl<'a, 'b> Drop for SocketSender<'a, 'b> {
+            fn drop(&mut self) {
+                if self.handler_registered {
+                    let mut inner = self.inner.protected.write().unwrap();
+                    inner.remove_handler();
+                }
+            }
+        }
+        impl<'a, 'b> Future for SocketSender<'a, 'b> {
+            type Output = Result<usize, Errno>;
+            fn poll(
+                mut self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> Poll<Self::Output> {
+                let this = self.deref_mut();
+                loop {
+                    let mut inner = this.inner.protected.write().unwrap();
+                    let res = match &mut inner.kind {
+                        InodeSocketKind::UnixStream {
+                            fds: Some(channel), ..
+                        } => channel.try_send_fds(this.data, &mut this.fds),
+                        InodeSocketKind::UnixStream { fds: None, .. } => {
+                            return Poll::Ready(Err(Errno::Notsup))
+                        }
+                        InodeSocketKind::UnixDatagram { socket } => {
+                            socket.try_send_fds(this.data, &mut this.fds)
+                        }
+                        InodeSocketKind::PreSocket { .. } => {
+                            return Poll::Ready(Err(Errno::Notconn))
+                        }
+                        _ => return Poll::Ready(Err(Errno::Notsup)),
+                    };
+                    return match res {
+                        Ok(amt) => Poll::Ready(Ok(amt)),
+                        Err(NetworkError::WouldBlock) if this.nonblocking => {
+                            Poll::Ready(Err(Errno::Again))
+                        }
+                        Err(NetworkError::WouldBlock) if !this.handler_registered => {
+                            inner
+                                .set_handler(cx.waker().into())
+                                .map_err(net_error_into_wasi_err)?;
+                            drop(inner);
+                            this.handler_registered = true;
+                            continue;
+                        }
+                        Err(NetworkError::WouldBlock) => Poll::Pending,
+                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
+                    };
+                }
+            }
+        }
+
+        let poller = SocketSender {
+            inner: &self.inner,
+            data: buf,
+            fds,
+            nonblocking,
+            handler_registered: false,
+        };
+        if let Some(timeout) = timeout {
+            tokio::select! {
+                res = poller => res,
+                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+            }
+        } else {
+            poller.await
+        }
+    }
+
+    /// Receives data from a UNIX socket along with the file descriptors
+    /// attached to it
+    pub async fn recv_fds(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        buf: &mut [MaybeUninit<u8>],
+        timeout: Option<Duration>,
+        nonblocking: bool,
+    ) -> Result<(usize, Vec<Fd>), Errno> {
+        struct SocketReceiver<'a, 'b> {
+            inner: &'a InodeSocketInner,
+            data: &'b mut [MaybeUninit<u8>],
+            nonblocking: bool,
+            handler_registered: bool,
+        }
+        impl<'a, 'b> Drop for SocketReceiver<'a, 'b> {
+            fn drop(&mut self) {
+                if self.handler_registered {
+                    let mut inner = self.inner.protected.write().unwrap();
+                    inner.remove_handler();
+                }
+            }
+        }
+        impl<'a, 'b> Future for SocketReceiver<'a, 'b> {
+            type Output = Result<(usize, Vec<Fd>), Errno>;
+            fn poll(
+                mut self: Pin<&mut Self>,
+                cx: &mut std::task::Context<'_>,
+            ) -> Poll<Self::Output> {
+                let this = self.deref_mut();
+                loop {
+                    let mut inner = this.inner.protected.write().unwrap();
+                    let mut fds = Vec::new();
+                    let res = match &mut inner.kind {
+                        InodeSocketKind::UnixStream {
+                            fds: Some(channel), ..
+                        } => channel.try_recv_fds(this.data, &mut fds),
+                        InodeSocketKind::UnixStream {
+                            socket, fds: None, ..
+                        } => socket.try_recv(this.data),
+                        InodeSocketKind::UnixDatagram { socket } => {
+                            socket.try_recv_fds(this.data, &mut fds).map(|(amt, _)| amt)
+                        }
+                        InodeSocketKind::PreSocket { .. } => {
+                            return Poll::Ready(Err(Errno::Notconn))
+                        }
+                        _ => return Poll::Ready(Err(Errno::Notsup)),
+                    };
+                    return match res {
+                        Ok(amt) => Poll::Ready(Ok((amt, fds))),
+                        Err(NetworkError::WouldBlock) if this.nonblocking => {
+                            Poll::Ready(Err(Errno::Again))
+                        }
+                        Err(NetworkError::WouldBlock) if !this.handler_registered => {
+                            inner
+                                .set_handler(cx.waker().into())
+                                .map_err(net_error_into_wasi_err)?;
+                            drop(inner);
+                            this.handler_registered = true;
+                            continue;
+                        }
+                        Err(NetworkError::WouldBlock) => Poll::Pending,
+                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
+                    };
+                }
+            }
+        }
+
+        let poller = SocketReceiver {
+            inner: &self.inner,
+            data: buf,
+            nonblocking,
+            handler_registered: false,
+        };
+        if let Some(timeout) = timeout {
+            tokio::select! {
+                res = poller => res,
+                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+            }
+        } else {
+            poller.await
+        }
+    }
+
     pub fn shutdown(&mut self, how: std::net::Shutdown) -> Result<(), Errno> {
         let mut inner = self.inner.protected.write().unwrap();
         match &mut inner.kind {
             InodeSocketKind::TcpStream { socket, .. } => {
                 socket.shutdown(how).map_err(net_error_into_wasi_err)?;
             }
+            InodeSocketKind::UnixStream { socket, .. } => {
+                socket.shutdown(how).map_err(net_error_into_wasi_err)?;
+            }
             InodeSocketKind::PreSocket { .. } => return Err(Errno::Notconn),
             _ => return Err(Errno::Notsup),
         }
@@ -1322,7 +1902,9 @@ impl InodeSocket {
             match &mut guard.kind {
                 InodeSocketKind::TcpStream { .. }
                 | InodeSocketKind::UdpSocket { .. }
-                | InodeSocketKind::Raw(..) => true,
+                | InodeSocketKind::Raw(..)
+                | InodeSocketKind::UnixStream { .. }
+                | InodeSocketKind::UnixDatagram { .. } => true,
                 _ => false,
             }
         } else {
@@ -1343,6 +1925,9 @@ impl InodeSocketProtected {
                 handler.take();
             }
             InodeSocketKind::RemoteTcpStream { .. } => {}
+            InodeSocketKind::UnixListener { listener, .. } => listener.remove_handler(),
+            InodeSocketKind::UnixStream { socket, .. } => socket.remove_handler(),
+            InodeSocketKind::UnixDatagram { socket } => socket.remove_handler(),
         }
     }
 
@@ -1355,6 +1940,9 @@ impl InodeSocketProtected {
             InodeSocketKind::Icmp(socket) => socket.poll_read_ready(cx),
             InodeSocketKind::PreSocket { .. } => Poll::Pending,
             InodeSocketKind::RemoteTcpStream { .. } => Poll::Pending,
+            InodeSocketKind::UnixListener { listener, .. } => listener.poll_read_ready(cx),
+            InodeSocketKind::UnixStream { socket, .. } => socket.poll_read_ready(cx),
+            InodeSocketKind::UnixDatagram { socket } => socket.poll_read_ready(cx),
         }
         .map_err(net_error_into_io_err)
     }
@@ -1368,6 +1956,9 @@ impl InodeSocketProtected {
             InodeSocketKind::Icmp(socket) => socket.poll_write_ready(cx),
             InodeSocketKind::PreSocket { .. } => Poll::Pending,
             InodeSocketKind::RemoteTcpStream { .. } => Poll::Pending,
+            InodeSocketKind::UnixListener { listener, .. } => listener.poll_write_ready(cx),
+            InodeSocketKind::UnixStream { socket, .. } => socket.poll_write_ready(cx),
+            InodeSocketKind::UnixDatagram { socket } => socket.poll_write_ready(cx),
         }
         .map_err(net_error_into_io_err)
     }
@@ -1387,6 +1978,9 @@ impl InodeSocketProtected {
                 Ok(())
             }
             InodeSocketKind::RemoteTcpStream { .. } => Ok(()),
+            InodeSocketKind::UnixListener { listener, .. } => listener.set_handler(handler),
+            InodeSocketKind::UnixStream { socket, .. } => socket.set_handler(handler),
+            InodeSocketKind::UnixDatagram { socket } => socket.set_handler(handler),
         }
     }
 }
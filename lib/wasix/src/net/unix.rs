@@ -0,0 +1,809 @@
+//! UNIX domain sockets that connect the processes of a
+//! [`WasiControlPlane`](crate::WasiControlPlane) with each other
+//!
+//! Sockets are bound to paths of the guest file system, the registry of
+//! the control plane resolves those paths to the listening (or receiving)
+//! socket without ever touching the host.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    mem::MaybeUninit,
+    net::Shutdown,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+    task::{Context, Poll, Waker},
+};
+
+use derivative::Derivative;
+use virtual_mio::{InterestHandler, InterestType};
+use virtual_net::{NetworkError, VirtualIoSource, VirtualUnixSocket};
+
+use crate::fs::Fd;
+
+/// Maximum length of a socket path, including the terminating nul
+/// byte that guests add (this matches `sun_path` on Linux)
+pub const UNIX_PATH_MAX: usize = 108;
+
+/// Number of bytes that can be buffered in each direction of a
+/// stream before the writer has to wait for the reader
+const STREAM_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Number of datagrams that can be queued on a socket before the
+/// senders have to wait for the receiver
+const DATAGRAM_QUEUE_SIZE: usize = 256;
+
+/// Largest datagram that can be sent
+const DATAGRAM_MAX_SIZE: usize = 64 * 1024;
+
+/// Maximum number of file descriptors that can be attached to a
+/// single message (this matches `SCM_MAX_FD` on Linux)
+pub const UNIX_MAX_FDS: usize = 253;
+
+/// Resolves the path a guest binds or connects to against its current
+/// directory so that every process agrees on the name of the socket
+pub(crate) fn resolve_path(current_dir: &str, path: &str) -> Option<PathBuf> {
+    if path.is_empty() || path.len() >= UNIX_PATH_MAX {
+        return None;
+    }
+
+    let mut ret = PathBuf::from("/");
+    let path = Path::new(path);
+    let components = if path.is_absolute() {
+        path.components().collect::<Vec<_>>()
+    } else {
+        Path::new(current_dir)
+            .components()
+            .chain(path.components())
+            .collect()
+    };
+    for component in components {
+        match component {
+            Component::Normal(name) => ret.push(name),
+            Component::ParentDir => {
+                ret.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    Some(ret)
+}
+
+/// Paths of the guest file system that UNIX sockets are bound to
+#[derive(Debug, Default)]
+pub struct UnixSocketRegistry {
+    bindings: Mutex<HashMap<PathBuf, UnixBinding>>,
+}
+
+#[derive(Debug)]
+enum UnixBinding {
+    Stream(Weak<ListenerShared>),
+    Datagram(Weak<DatagramShared>),
+}
+
+impl UnixBinding {
+    fn is_alive(&self) -> bool {
+        match self {
+            UnixBinding::Stream(listener) => listener.strong_count() > 0,
+            UnixBinding::Datagram(inbox) => inbox.strong_count() > 0,
+        }
+    }
+}
+
+impl UnixSocketRegistry {
+    /// Starts listening for stream connections on a path
+    pub(crate) fn listen(
+        &self,
+        path: PathBuf,
+        backlog: usize,
+    ) -> virtual_net::Result<UnixListener> {
+        let mut bindings = self.bindings.lock().unwrap();
+        if bindings
+            .get(&path)
+            .map(|b| b.is_alive())
+            .unwrap_or_default()
+        {
+            return Err(NetworkError::AddressInUse);
+        }
+
+        let shared = Arc::new(ListenerShared {
+            path: path.clone(),
+            backlog: Mutex::new(VecDeque::new()),
+            max_backlog: backlog.max(1),
+            notifier: Default::default(),
+        });
+        bindings.insert(path, UnixBinding::Stream(Arc::downgrade(&shared)));
+        Ok(UnixListener { shared })
+    }
+
+    /// Connects to the stream socket that listens on a path
+    ///
+    /// Returns [`NetworkError::AddressNotAvailable`] when nothing is bound
+    /// to the path.
+    pub(crate) fn connect(
+        &self,
+        path: &Path,
+        local: Option<PathBuf>,
+    ) -> virtual_net::Result<UnixPipeSocket> {
+        let listener = {
+            let mut bindings = self.bindings.lock().unwrap();
+            match bindings.get(path) {
+                Some(UnixBinding::Stream(listener)) => match listener.upgrade() {
+                    Some(listener) => listener,
+                    None => {
+                        bindings.remove(path);
+                        return Err(NetworkError::AddressNotAvailable);
+                    }
+                },
+                Some(UnixBinding::Datagram(..)) => return Err(NetworkError::ConnectionRefused),
+                None => return Err(NetworkError::AddressNotAvailable),
+            }
+        };
+
+        let (client, server) = UnixPipeSocket::pair(Some(path.to_path_buf()), local);
+        {
+            let mut backlog = listener.backlog.lock().unwrap();
+            if backlog.len() >= listener.max_backlog {
+                return Err(NetworkError::ConnectionRefused);
+            }
+            backlog.push_back(server);
+        }
+        listener.notifier.notify(InterestType::Readable);
+        Ok(client)
+    }
+
+    /// Forgets the socket bound to a path once the path was removed from
+    /// the file system, the socket keeps working for the connections it
+    /// already has but can no longer be found and the path can be bound
+    /// again
+    pub(crate) fn unbind(&self, path: &Path) {
+        self.bindings.lock().unwrap().remove(path);
+    }
+
+    fn bind_datagram(&self, path: PathBuf, inbox: &Arc<DatagramShared>) -> virtual_net::Result<()> {
+        let mut bindings = self.bindings.lock().unwrap();
+        if bindings
+            .get(&path)
+            .map(|b| b.is_alive())
+            .unwrap_or_default()
+        {
+            return Err(NetworkError::AddressInUse);
+        }
+        bindings.insert(path, UnixBinding::Datagram(Arc::downgrade(inbox)));
+        Ok(())
+    }
+
+    fn datagram(&self, path: &Path) -> virtual_net::Result<Arc<DatagramShared>> {
+        let bindings = self.bindings.lock().unwrap();
+        match bindings.get(path) {
+            Some(UnixBinding::Datagram(inbox)) => {
+                inbox.upgrade().ok_or(NetworkError::ConnectionRefused)
+            }
+            Some(UnixBinding::Stream(..)) => Err(NetworkError::ConnectionRefused),
+            None => Err(NetworkError::AddressNotAvailable),
+        }
+    }
+}
+
+/// Wakes up whoever waits on one end of a UNIX socket
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+struct UnixNotifier {
+    #[derivative(Debug = "ignore")]
+    state: Mutex<UnixNotifierState>,
+}
+
+#[derive(Default)]
+struct UnixNotifierState {
+    handler: Option<Box<dyn InterestHandler + Send + Sync>>,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl UnixNotifier {
+    fn notify(&self, interest: InterestType) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(handler) = state.handler.as_mut() {
+            handler.push_interest(interest);
+        }
+        let wakers = match interest {
+            InterestType::Readable => [state.read_waker.take(), None],
+            InterestType::Writable => [state.write_waker.take(), None],
+            InterestType::Closed | InterestType::Error => {
+                [state.read_waker.take(), state.write_waker.take()]
+            }
+        };
+        drop(state);
+        wakers.into_iter().flatten().for_each(Waker::wake);
+    }
+
+    fn set_handler(&self, handler: Box<dyn InterestHandler + Send + Sync>) {
+        self.state.lock().unwrap().handler.replace(handler);
+    }
+
+    fn remove_handler(&self) {
+        self.state.lock().unwrap().handler.take();
+    }
+
+    fn register_read(&self, waker: &Waker) {
+        self.state.lock().unwrap().read_waker.replace(waker.clone());
+    }
+
+    fn register_write(&self, waker: &Waker) {
+        self.state
+            .lock()
+            .unwrap()
+            .write_waker
+            .replace(waker.clone());
+    }
+}
+
+/// Bytes flowing in one direction of a connected stream
+#[derive(Debug)]
+struct StreamBuffer {
+    state: Mutex<StreamBufferState>,
+    reader: Arc<UnixNotifier>,
+    writer: Arc<UnixNotifier>,
+}
+
+#[derive(Debug, Default)]
+struct StreamBufferState {
+    data: VecDeque<u8>,
+    /// Total number of bytes written to and read from the stream
+    written: u64,
+    consumed: u64,
+    /// File descriptors attached to the byte at a position of the stream
+    fds: VecDeque<(u64, Vec<Fd>)>,
+    /// The writer shut down its side, readers see the end of the stream
+    /// once the remaining data is consumed
+    write_closed: bool,
+    /// The reader shut down its side, writes fail with a broken pipe
+    read_closed: bool,
+}
+
+impl StreamBuffer {
+    /// Writes as much data as fits in the buffer, the file descriptors are
+    /// taken out of `fds` and attached to the first byte once anything
+    /// was written
+    fn send(&self, data: &[u8], fds: Option<&mut Vec<Fd>>) -> virtual_net::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.read_closed || state.write_closed {
+            return Err(NetworkError::BrokenPipe);
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let amt = data.len().min(STREAM_BUFFER_SIZE - state.data.len());
+        if amt == 0 {
+            return Err(NetworkError::WouldBlock);
+        }
+        if let Some(fds) = fds.filter(|fds| !fds.is_empty()) {
+            let position = state.written;
+            state.fds.push_back((position, std::mem::take(fds)));
+        }
+        state.data.extend(&data[..amt]);
+        state.written += amt as u64;
+        drop(state);
+
+        self.reader.notify(InterestType::Readable);
+        Ok(amt)
+    }
+
+    /// Reads the buffered data, the file descriptors attached to it are
+    /// moved to `fds` when given and closed otherwise
+    ///
+    /// A read that collects file descriptors stops before the next bytes
+    /// that carry descriptors so that they are never merged.
+    fn recv(
+        &self,
+        buf: &mut [MaybeUninit<u8>],
+        mut fds: Option<&mut Vec<Fd>>,
+    ) -> virtual_net::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.data.is_empty() {
+            return match state.write_closed || state.read_closed {
+                true => Ok(0),
+                false => Err(NetworkError::WouldBlock),
+            };
+        }
+        let mut amt = buf.len().min(state.data.len());
+        if fds.is_some() {
+            let consumed = state.consumed;
+            if let Some((position, _)) = state.fds.iter().find(|(pos, _)| *pos > consumed) {
+                amt = amt.min((position - consumed) as usize);
+            }
+        }
+        for (dst, src) in buf.iter_mut().zip(state.data.drain(..amt)) {
+            dst.write(src);
+        }
+        state.consumed += amt as u64;
+
+        let mut dropped = Vec::new();
+        while let Some((position, _)) = state.fds.front() {
+            if *position >= state.consumed {
+                break;
+            }
+            let (_, attached) = state.fds.pop_front().unwrap();
+            match fds.as_mut() {
+                Some(fds) => fds.extend(attached),
+                None => dropped.extend(attached),
+            }
+        }
+        drop(state);
+        // the descriptors are closed outside of the lock as closing a
+        // socket may shut down this very buffer
+        drop(dropped);
+
+        self.writer.notify(InterestType::Writable);
+        Ok(amt)
+    }
+}
+
+/// One end of a connected UNIX stream socket (or of a socket pair)
+#[derive(Debug)]
+pub struct UnixPipeSocket {
+    peer: Option<PathBuf>,
+    rx: Arc<StreamBuffer>,
+    tx: Arc<StreamBuffer>,
+    notifier: Arc<UnixNotifier>,
+}
+
+impl UnixPipeSocket {
+    /// Creates two connected ends, each one is given the path of the
+    /// other end
+    pub(crate) fn pair(a_peer: Option<PathBuf>, b_peer: Option<PathBuf>) -> (Self, Self) {
+        let a_notifier = Arc::new(UnixNotifier::default());
+        let b_notifier = Arc::new(UnixNotifier::default());
+        let a_to_b = Arc::new(StreamBuffer {
+            state: Default::default(),
+            reader: b_notifier.clone(),
+            writer: a_notifier.clone(),
+        });
+        let b_to_a = Arc::new(StreamBuffer {
+            state: Default::default(),
+            reader: a_notifier.clone(),
+            writer: b_notifier.clone(),
+        });
+
+        let a = Self {
+            peer: a_peer,
+            rx: b_to_a.clone(),
+            tx: a_to_b.clone(),
+            notifier: a_notifier,
+        };
+        let b = Self {
+            peer: b_peer,
+            rx: a_to_b,
+            tx: b_to_a,
+            notifier: b_notifier,
+        };
+        (a, b)
+    }
+
+    /// Returns the channel that carries file descriptors alongside the
+    /// data of this stream
+    pub(crate) fn fd_channel(&self) -> UnixFdChannel {
+        UnixFdChannel {
+            rx: self.rx.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+
+    fn shutdown_read(&self) {
+        let mut state = self.rx.state.lock().unwrap();
+        state.read_closed = true;
+        state.data.clear();
+        let dropped = std::mem::take(&mut state.fds);
+        drop(state);
+        drop(dropped);
+        self.rx.writer.notify(InterestType::Closed);
+    }
+
+    fn shutdown_write(&self) {
+        self.tx.state.lock().unwrap().write_closed = true;
+        self.tx.reader.notify(InterestType::Readable);
+    }
+}
+
+impl Drop for UnixPipeSocket {
+    fn drop(&mut self) {
+        self.shutdown_read();
+        self.shutdown_write();
+    }
+}
+
+impl VirtualUnixSocket for UnixPipeSocket {
+    fn try_send(&mut self, data: &[u8]) -> virtual_net::Result<usize> {
+        self.tx.send(data, None)
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> virtual_net::Result<usize> {
+        self.rx.recv(buf, None)
+    }
+
+    fn set_handler(
+        &mut self,
+        handler: Box<dyn InterestHandler + Send + Sync>,
+    ) -> virtual_net::Result<()> {
+        self.notifier.set_handler(handler);
+        Ok(())
+    }
+
+    fn addr_peer(&self) -> virtual_net::Result<Option<PathBuf>> {
+        Ok(self.peer.clone())
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> virtual_net::Result<()> {
+        match how {
+            Shutdown::Read => self.shutdown_read(),
+            Shutdown::Write => self.shutdown_write(),
+            Shutdown::Both => {
+                self.shutdown_read();
+                self.shutdown_write();
+            }
+        }
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        // the locks are taken one after the other as the peer takes them in
+        // the opposite order
+        let peer_closed_write = self.rx.state.lock().unwrap().write_closed;
+        peer_closed_write && self.tx.state.lock().unwrap().read_closed
+    }
+}
+
+impl VirtualIoSource for UnixPipeSocket {
+    fn remove_handler(&mut self) {
+        self.notifier.remove_handler();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        let state = self.rx.state.lock().unwrap();
+        if !state.data.is_empty() {
+            return Poll::Ready(Ok(state.data.len()));
+        }
+        if state.write_closed || state.read_closed {
+            return Poll::Ready(Ok(0));
+        }
+        self.notifier.register_read(cx.waker());
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        let state = self.tx.state.lock().unwrap();
+        if state.read_closed || state.write_closed {
+            return Poll::Ready(Ok(0));
+        }
+        if state.data.len() < STREAM_BUFFER_SIZE {
+            return Poll::Ready(Ok(STREAM_BUFFER_SIZE - state.data.len()));
+        }
+        self.notifier.register_write(cx.waker());
+        Poll::Pending
+    }
+}
+
+/// Handle on both directions of a stream socket that passes file
+/// descriptors along with the data
+///
+/// Only streams whose ends both live in this runtime have one, the
+/// descriptors of a process mean nothing to the host. The descriptors
+/// are attached to the first byte of the data they were sent with, they
+/// are received along with that byte.
+#[derive(Debug, Clone)]
+pub struct UnixFdChannel {
+    rx: Arc<StreamBuffer>,
+    tx: Arc<StreamBuffer>,
+}
+
+impl UnixFdChannel {
+    /// Tries to send data with file descriptors attached to its first
+    /// byte, the descriptors are only taken out of `fds` when some data
+    /// was sent
+    pub fn try_send_fds(&self, data: &[u8], fds: &mut Vec<Fd>) -> virtual_net::Result<usize> {
+        if data.is_empty() && !fds.is_empty() {
+            return Err(NetworkError::InvalidInput);
+        }
+        self.tx.send(data, Some(fds))
+    }
+
+    /// Tries to receive data along with the file descriptors attached
+    /// to it
+    pub fn try_recv_fds(
+        &self,
+        buf: &mut [MaybeUninit<u8>],
+        fds: &mut Vec<Fd>,
+    ) -> virtual_net::Result<usize> {
+        self.rx.recv(buf, Some(fds))
+    }
+}
+
+#[derive(Debug)]
+struct ListenerShared {
+    path: PathBuf,
+    backlog: Mutex<VecDeque<UnixPipeSocket>>,
+    max_backlog: usize,
+    notifier: UnixNotifier,
+}
+
+/// UNIX stream socket listening for connections on a path
+#[derive(Debug)]
+pub struct UnixListener {
+    shared: Arc<ListenerShared>,
+}
+
+impl UnixListener {
+    /// Tries to accept a new connection
+    pub fn try_accept(&mut self) -> virtual_net::Result<UnixPipeSocket> {
+        let mut backlog = self.shared.backlog.lock().unwrap();
+        backlog.pop_front().ok_or(NetworkError::WouldBlock)
+    }
+
+    /// Returns the path this listener is bound to
+    pub fn addr_local(&self) -> &Path {
+        &self.shared.path
+    }
+
+    /// Registers a waker for when a new connection has arrived
+    pub fn set_handler(
+        &mut self,
+        handler: Box<dyn InterestHandler + Send + Sync>,
+    ) -> virtual_net::Result<()> {
+        self.shared.notifier.set_handler(handler);
+        Ok(())
+    }
+}
+
+impl VirtualIoSource for UnixListener {
+    fn remove_handler(&mut self) {
+        self.shared.notifier.remove_handler();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        let backlog = self.shared.backlog.lock().unwrap();
+        if !backlog.is_empty() {
+            return Poll::Ready(Ok(backlog.len()));
+        }
+        self.shared.notifier.register_read(cx.waker());
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, _cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        Poll::Pending
+    }
+}
+
+/// Datagrams waiting to be received by a socket
+#[derive(Debug, Default)]
+struct DatagramShared {
+    state: Mutex<DatagramState>,
+    notifier: Arc<UnixNotifier>,
+}
+
+#[derive(Debug)]
+struct Datagram {
+    data: Vec<u8>,
+    from: Option<PathBuf>,
+    fds: Vec<Fd>,
+}
+
+#[derive(Debug, Default)]
+struct DatagramState {
+    queue: VecDeque<Datagram>,
+    /// Senders waiting for the queue to drain
+    blocked: Vec<Weak<UnixNotifier>>,
+}
+
+impl DatagramShared {
+    fn deliver(
+        &self,
+        data: &[u8],
+        from: Option<PathBuf>,
+        fds: &mut Vec<Fd>,
+        sender: &Arc<UnixNotifier>,
+    ) -> virtual_net::Result<usize> {
+        if data.len() > DATAGRAM_MAX_SIZE {
+            return Err(NetworkError::InvalidInput);
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.queue.len() >= DATAGRAM_QUEUE_SIZE {
+            state.blocked.push(Arc::downgrade(sender));
+            return Err(NetworkError::WouldBlock);
+        }
+        state.queue.push_back(Datagram {
+            data: data.to_vec(),
+            from,
+            fds: std::mem::take(fds),
+        });
+        drop(state);
+
+        self.notifier.notify(InterestType::Readable);
+        Ok(data.len())
+    }
+}
+
+#[derive(Debug)]
+struct DatagramPeer {
+    path: Option<PathBuf>,
+    inbox: Weak<DatagramShared>,
+}
+
+/// UNIX datagram socket, which may be bound to a path and connected
+/// to another datagram socket
+#[derive(Debug)]
+pub struct UnixDatagramSocket {
+    inbox: Arc<DatagramShared>,
+    local: Option<PathBuf>,
+    peer: Option<DatagramPeer>,
+}
+
+impl Default for UnixDatagramSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnixDatagramSocket {
+    /// Creates an unnamed datagram socket
+    pub fn new() -> Self {
+        Self {
+            inbox: Default::default(),
+            local: None,
+            peer: None,
+        }
+    }
+
+    /// Creates two unnamed datagram sockets connected to each other
+    pub fn pair() -> (Self, Self) {
+        let mut a = Self::new();
+        let mut b = Self::new();
+        a.peer = Some(DatagramPeer {
+            path: None,
+            inbox: Arc::downgrade(&b.inbox),
+        });
+        b.peer = Some(DatagramPeer {
+            path: None,
+            inbox: Arc::downgrade(&a.inbox),
+        });
+        (a, b)
+    }
+
+    /// Binds this socket to a path so that others can send to it
+    pub(crate) fn bind(
+        &mut self,
+        registry: &UnixSocketRegistry,
+        path: PathBuf,
+    ) -> virtual_net::Result<()> {
+        if self.local.is_some() {
+            return Err(NetworkError::InvalidInput);
+        }
+        registry.bind_datagram(path.clone(), &self.inbox)?;
+        self.local.replace(path);
+        Ok(())
+    }
+
+    /// Sets the default destination of the datagrams sent by this socket
+    pub(crate) fn connect(
+        &mut self,
+        registry: &UnixSocketRegistry,
+        path: PathBuf,
+    ) -> virtual_net::Result<()> {
+        let inbox = registry.datagram(&path)?;
+        self.peer.replace(DatagramPeer {
+            path: Some(path),
+            inbox: Arc::downgrade(&inbox),
+        });
+        Ok(())
+    }
+
+    /// Returns the path this socket is bound to
+    pub fn addr_local(&self) -> Option<PathBuf> {
+        self.local.clone()
+    }
+
+    /// Returns the path of the socket this is connected to
+    pub fn addr_peer(&self) -> virtual_net::Result<Option<PathBuf>> {
+        match &self.peer {
+            Some(peer) => Ok(peer.path.clone()),
+            None => Err(NetworkError::NotConnected),
+        }
+    }
+
+    /// Tries to send a datagram to the connected socket
+    pub fn try_send(&mut self, data: &[u8]) -> virtual_net::Result<usize> {
+        self.try_send_fds(data, &mut Vec::new())
+    }
+
+    /// Tries to send a datagram with file descriptors attached to the
+    /// connected socket, the descriptors are only taken out of `fds`
+    /// when the datagram was sent
+    pub(crate) fn try_send_fds(
+        &mut self,
+        data: &[u8],
+        fds: &mut Vec<Fd>,
+    ) -> virtual_net::Result<usize> {
+        let peer = self.peer.as_ref().ok_or(NetworkError::NotConnected)?;
+        let inbox = peer
+            .inbox
+            .upgrade()
+            .ok_or(NetworkError::ConnectionRefused)?;
+        inbox.deliver(data, self.local.clone(), fds, &self.inbox.notifier)
+    }
+
+    /// Tries to send a datagram to the socket bound to a path
+    pub(crate) fn try_send_to(
+        &mut self,
+        data: &[u8],
+        registry: &UnixSocketRegistry,
+        path: &Path,
+    ) -> virtual_net::Result<usize> {
+        let inbox = registry.datagram(path)?;
+        inbox.deliver(
+            data,
+            self.local.clone(),
+            &mut Vec::new(),
+            &self.inbox.notifier,
+        )
+    }
+
+    /// Tries to receive a datagram along with the path of its sender,
+    /// the part of the datagram that does not fit in the buffer is lost
+    pub fn try_recv_from(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> virtual_net::Result<(usize, Option<PathBuf>)> {
+        self.try_recv_fds(buf, &mut Vec::new())
+    }
+
+    /// Tries to receive a datagram along with the path of its sender and
+    /// the file descriptors attached to it
+    pub(crate) fn try_recv_fds(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+        fds: &mut Vec<Fd>,
+    ) -> virtual_net::Result<(usize, Option<PathBuf>)> {
+        let mut state = self.inbox.state.lock().unwrap();
+        let datagram = state.queue.pop_front().ok_or(NetworkError::WouldBlock)?;
+        let blocked = std::mem::take(&mut state.blocked);
+        drop(state);
+
+        for sender in blocked.iter().filter_map(Weak::upgrade) {
+            sender.notify(InterestType::Writable);
+        }
+
+        let amt = buf.len().min(datagram.data.len());
+        for (dst, src) in buf.iter_mut().zip(datagram.data[..amt].iter()) {
+            dst.write(*src);
+        }
+        fds.extend(datagram.fds);
+        Ok((amt, datagram.from))
+    }
+
+    /// Registers a waker for when a datagram arrives or when a
+    /// destination is ready to receive more datagrams
+    pub fn set_handler(
+        &mut self,
+        handler: Box<dyn InterestHandler + Send + Sync>,
+    ) -> virtual_net::Result<()> {
+        self.inbox.notifier.set_handler(handler);
+        Ok(())
+    }
+}
+
+impl VirtualIoSource for UnixDatagramSocket {
+    fn remove_handler(&mut self) {
+        self.inbox.notifier.remove_handler();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        let state = self.inbox.state.lock().unwrap();
+        if let Some(datagram) = state.queue.front() {
+            return Poll::Ready(Ok(datagram.data.len()));
+        }
+        self.inbox.notifier.register_read(cx.waker());
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, _cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        Poll::Ready(Ok(DATAGRAM_MAX_SIZE))
+    }
+}
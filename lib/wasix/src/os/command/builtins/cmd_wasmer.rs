@@ -70,7 +70,7 @@ impl CmdWasmer {
             let mut state = env.state.fork();
             args.insert(0, what.clone());
             state.args = args;
-            env.state = Arc::new(state);
+            env.set_state(state);
 
             if let Ok(binary) = self.get_package(&what).await {
                 // Now run the module
@@ -1,12 +1,18 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc, RwLock,
     },
 };
 
-use crate::{runtime::module_cache::ModuleHash, WasiProcess, WasiProcessId};
+use wasmer_wasix_types::wasi::{Errno, Signal};
+
+use super::{inspect::WasiProcessInfo, process::WasiJobIds};
+use crate::{
+    net::unix::UnixSocketRegistry, os::tty::pty::PtyRegistry, runtime::module_cache::ModuleHash,
+    WasiProcess, WasiProcessId,
+};
 
 #[derive(Debug, Clone)]
 pub struct WasiControlPlane {
@@ -65,6 +71,12 @@ struct State {
     /// Total number of active tasks (threads) across all processes.
     task_count: Arc<AtomicUsize>,
 
+    /// UNIX sockets bound by the processes running on this machine
+    unix_sockets: UnixSocketRegistry,
+
+    /// Pseudo-terminals that the processes allocated.
+    ptys: PtyRegistry,
+
     /// Mutable state.
     mutable: RwLock<MutableState>,
 }
@@ -75,6 +87,9 @@ struct MutableState {
     process_seed: u32,
     /// The processes running on this machine
     processes: HashMap<WasiProcessId, WasiProcess>,
+    /// The foreground process group of each session (keyed by the session ID),
+    /// sessions without an entry have the group of their leader in the foreground
+    foreground: HashMap<WasiProcessId, WasiProcessId>,
     // TODO: keep a queue of terminated process ids for id reuse.
 }
 
@@ -84,9 +99,12 @@ impl WasiControlPlane {
             state: Arc::new(State {
                 config,
                 task_count: Arc::new(AtomicUsize::new(0)),
+                unix_sockets: Default::default(),
+                ptys: Default::default(),
                 mutable: RwLock::new(MutableState {
                     process_seed: 0,
                     processes: Default::default(),
+                    foreground: Default::default(),
                 }),
             }),
         }
@@ -106,6 +124,16 @@ impl WasiControlPlane {
         &self.state.config
     }
 
+    /// Returns the UNIX sockets shared by the processes of this control plane
+    pub(crate) fn unix_sockets(&self) -> &UnixSocketRegistry {
+        &self.state.unix_sockets
+    }
+
+    /// Pseudo-terminals that the processes allocated.
+    pub(crate) fn ptys(&self) -> &PtyRegistry {
+        &self.state.ptys
+    }
+
     /// Register a new task.
     ///
     // Currently just increments the task counter.
@@ -138,11 +166,20 @@ impl WasiControlPlane {
         let mut mutable = self.state.mutable.write().unwrap();
 
         let pid = mutable.next_process_id()?;
+        // New processes lead their own session until they are moved
+        // into the session of their parent (see `inherit_job`)
         proc.set_pid(pid);
         mutable.processes.insert(pid, proc.clone());
         Ok(proc)
     }
 
+    /// Places a newly forked process in the process group and session
+    /// of its parent
+    pub(crate) fn inherit_job(&self, parent: &WasiProcess, child: &WasiProcess) {
+        let _mutable = self.state.mutable.write().unwrap();
+        child.set_job(parent.job());
+    }
+
     /// Generates a new process ID
     pub fn generate_id(&self) -> Result<WasiProcessId, ControlPlaneError> {
         let mut mutable = self.state.mutable.write().unwrap();
@@ -159,9 +196,179 @@ impl WasiControlPlane {
             .get(&pid)
             .cloned()
     }
+
+    /// Returns all the processes that are still running, ordered by their ID
+    pub fn running_processes(&self) -> Vec<WasiProcess> {
+        let mut processes = self
+            .state
+            .mutable
+            .read()
+            .unwrap()
+            .processes
+            .values()
+            .filter(|process| process.try_join().is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        processes.sort_by_key(|process| process.pid());
+        processes
+    }
+
+    /// Takes a snapshot of all the processes that are still running, which
+    /// is how hosts inspect their guests (`ps`)
+    pub fn processes(&self) -> Vec<WasiProcessInfo> {
+        self.running_processes()
+            .iter()
+            .map(WasiProcess::info)
+            .collect()
+    }
+
+    /// Sends a signal to a running process (`kill`)
+    pub fn signal_process(&self, pid: WasiProcessId, signal: Signal) -> Result<(), Errno> {
+        let process = self
+            .get_process(pid)
+            .filter(|process| process.try_join().is_none())
+            .ok_or(Errno::Srch)?;
+        process.signal_process(signal);
+        Ok(())
+    }
+
+    /// Returns the process group that a running process belongs to
+    pub fn process_group(&self, pid: WasiProcessId) -> Option<WasiProcessId> {
+        let mutable = self.state.mutable.read().unwrap();
+        mutable.running(pid).map(WasiProcess::process_group)
+    }
+
+    /// Returns the session that a running process belongs to
+    pub fn session(&self, pid: WasiProcessId) -> Option<WasiProcessId> {
+        let mutable = self.state.mutable.read().unwrap();
+        mutable.running(pid).map(WasiProcess::session)
+    }
+
+    /// Moves a process into a process group, creating the group when
+    /// `pgid` is the ID of the process itself (the same rules as `setpgid`)
+    pub fn set_process_group(&self, pid: WasiProcessId, pgid: WasiProcessId) -> Result<(), Errno> {
+        let mutable = self.state.mutable.write().unwrap();
+        let process = mutable.running(pid).ok_or(Errno::Srch)?;
+        let job = process.job();
+
+        // Session leaders can not leave their group
+        if job.sid == pid {
+            return if job.pgid == pgid {
+                Ok(())
+            } else {
+                Err(Errno::Perm)
+            };
+        }
+        // Joining a group requires it to exist in the same session
+        if pgid != pid && !mutable.any_running(|other| other.pgid == pgid && other.sid == job.sid) {
+            return Err(Errno::Perm);
+        }
+
+        process.set_job(WasiJobIds { pgid, sid: job.sid });
+        Ok(())
+    }
+
+    /// Creates a new session (and process group) led by a process
+    /// and returns its ID (the same rules as `setsid`)
+    pub fn new_session(&self, pid: WasiProcessId) -> Result<WasiProcessId, Errno> {
+        let mut mutable = self.state.mutable.write().unwrap();
+        let process = mutable.running(pid).ok_or(Errno::Srch)?.clone();
+        // Process group leaders can not create a new session as
+        // the other members of the group would end up in another one
+        if mutable.any_running(|job| job.pgid == pid) {
+            return Err(Errno::Perm);
+        }
+
+        process.set_job(WasiJobIds {
+            pgid: pid,
+            sid: pid,
+        });
+        mutable.foreground.remove(&pid);
+        mutable.prune_foreground();
+        Ok(pid)
+    }
+
+    /// Returns the running processes that are members of a process group
+    pub fn process_group_members(&self, pgid: WasiProcessId) -> Vec<WasiProcess> {
+        let mut processes = self
+            .running_processes()
+            .into_iter()
+            .filter(|process| process.process_group() == pgid)
+            .collect::<Vec<_>>();
+        processes.sort_by_key(|process| process.pid());
+        processes
+    }
+
+    /// Sends a signal to all the running processes of a process group
+    pub fn signal_process_group(&self, pgid: WasiProcessId, signal: Signal) -> Result<(), Errno> {
+        let members = self.process_group_members(pgid);
+        if members.is_empty() {
+            return Err(Errno::Srch);
+        }
+        for process in members {
+            process.deliver_signal(signal);
+        }
+        Ok(())
+    }
+
+    /// Sends a signal raised by the terminal of a session (e.g. `SIGINT`
+    /// for Ctrl-C) to its foreground process group
+    pub fn signal_foreground_group(&self, sid: WasiProcessId, signal: Signal) -> Result<(), Errno> {
+        self.signal_process_group(self.foreground_group(sid), signal)
+    }
+
+    /// Returns the process group in the foreground of a session, this
+    /// is the group that receives the signals raised by its terminal
+    pub fn foreground_group(&self, sid: WasiProcessId) -> WasiProcessId {
+        let mutable = self.state.mutable.read().unwrap();
+        mutable.foreground.get(&sid).copied().unwrap_or(sid)
+    }
+
+    /// Moves a process group of a session into the foreground
+    /// (the same rules as `tcsetpgrp`)
+    pub fn set_foreground_group(
+        &self,
+        sid: WasiProcessId,
+        pgid: WasiProcessId,
+    ) -> Result<(), Errno> {
+        let mut mutable = self.state.mutable.write().unwrap();
+        if !mutable.any_running(|job| job.pgid == pgid && job.sid == sid) {
+            return Err(Errno::Perm);
+        }
+        mutable.prune_foreground();
+        mutable.foreground.insert(sid, pgid);
+        Ok(())
+    }
 }
 
 impl MutableState {
+    /// Gets a process that has not finished yet
+    fn running(&self, pid: WasiProcessId) -> Option<&WasiProcess> {
+        self.processes
+            .get(&pid)
+            .filter(|process| process.try_join().is_none())
+    }
+
+    /// Checks whether the process group and session of any process that
+    /// has not finished yet match, finished processes leave their group
+    fn any_running(&self, matches: impl Fn(&WasiJobIds) -> bool) -> bool {
+        self.processes
+            .values()
+            .filter(|process| process.try_join().is_none())
+            .any(|process| matches(&process.job()))
+    }
+
+    /// Forgets the foreground process groups of sessions that ended
+    fn prune_foreground(&mut self) {
+        let sessions = self
+            .processes
+            .values()
+            .filter(|process| process.try_join().is_none())
+            .map(WasiProcess::session)
+            .collect::<HashSet<_>>();
+        self.foreground.retain(|sid, _| sessions.contains(sid));
+    }
+
     fn next_process_id(&mut self) -> Result<WasiProcessId, ControlPlaneError> {
         // TODO: reuse terminated ids, handle wrap-around, ...
         let id = self.process_seed.checked_add(1).ok_or({
@@ -199,11 +406,20 @@ pub enum ControlPlaneError {
         /// The maximum number of tasks.
         max: usize,
     },
+    /// The process has reached its limit of child processes.
+    #[error("The maximum number of child processes has been reached ({max})")]
+    ProcessLimitReached {
+        /// The maximum number of child processes.
+        max: u64,
+    },
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::os::task::thread::WasiMemoryLayout;
+    use crate::os::task::{
+        inspect::WasiThreadActivity,
+        thread::{WasiMemoryLayout, WasiThreadWait},
+    };
 
     use super::*;
 
@@ -247,4 +463,88 @@ mod tests {
             ControlPlaneError::TaskLimitReached { max: 2 }
         );
     }
+
+    /// Ensures that hosts see what the threads of a process are waiting on.
+    #[test]
+    fn test_control_plane_process_snapshots() {
+        let p = WasiControlPlane::default();
+
+        let process = p.new_process(ModuleHash::random()).unwrap();
+        let handle = process.new_thread(WasiMemoryLayout::default()).unwrap();
+        let thread = handle.as_thread();
+        {
+            let _idle = thread.cpu_clock().idle();
+            let _wait = thread.wait_on(WasiThreadWait::Futex { address: 64 });
+
+            let processes = p.processes();
+            assert_eq!(processes.len(), 1);
+            assert_eq!(processes[0].pid, process.pid());
+            assert_eq!(processes[0].threads.len(), 1);
+            assert_eq!(
+                processes[0].threads[0].activity,
+                WasiThreadActivity::Waiting(WasiThreadWait::Futex { address: 64 })
+            );
+        }
+        assert_eq!(
+            p.processes()[0].threads[0].activity,
+            WasiThreadActivity::Running
+        );
+
+        assert_eq!(
+            p.signal_process(WasiProcessId::from(1000), Signal::Sigterm),
+            Err(Errno::Srch)
+        );
+    }
+
+    /// Ensures that process groups follow the rules of `setpgid` and `setsid`.
+    #[test]
+    fn test_control_plane_process_groups() {
+        let p = WasiControlPlane::default();
+
+        let shell = p.new_process(ModuleHash::random()).unwrap();
+        let job = p.new_process(ModuleHash::random()).unwrap();
+        p.inherit_job(&shell, &job);
+        let other = p.new_process(ModuleHash::random()).unwrap();
+
+        // Forked processes start in the group and session of their parent
+        assert_eq!(job.process_group(), shell.pid());
+        assert_eq!(p.session(job.pid()), Some(shell.pid()));
+
+        // Session leaders can not move, nor can processes join another session
+        assert_eq!(
+            p.set_process_group(shell.pid(), job.pid()),
+            Err(Errno::Perm)
+        );
+        assert_eq!(
+            p.set_process_group(job.pid(), other.pid()),
+            Err(Errno::Perm)
+        );
+
+        p.set_process_group(job.pid(), job.pid()).unwrap();
+        assert_eq!(p.process_group(job.pid()), Some(job.pid()));
+        assert_eq!(p.process_group_members(job.pid()).len(), 1);
+
+        // Group leaders can not start a new session
+        assert_eq!(p.new_session(job.pid()), Err(Errno::Perm));
+        assert_eq!(p.new_session(WasiProcessId::from(1000)), Err(Errno::Srch));
+
+        assert_eq!(p.foreground_group(shell.pid()), shell.pid());
+        p.set_foreground_group(shell.pid(), job.pid()).unwrap();
+        assert_eq!(p.foreground_group(shell.pid()), job.pid());
+        assert_eq!(
+            p.set_foreground_group(shell.pid(), other.pid()),
+            Err(Errno::Perm)
+        );
+
+        // Processes leave their group when they finish
+        let handle = job.new_thread(WasiMemoryLayout::default()).unwrap();
+        job.terminate(wasmer_wasix_types::wasi::ExitCode::Other(0));
+        drop(handle);
+        assert_eq!(p.process_group(job.pid()), None);
+        assert!(p.process_group_members(job.pid()).is_empty());
+        assert_eq!(
+            p.set_foreground_group(shell.pid(), job.pid()),
+            Err(Errno::Perm)
+        );
+    }
 }
@@ -0,0 +1,148 @@
+//! User and group identity of processes, which is emulated as WASIX
+//! has no users of its own (the host user is never exposed)
+
+use wasmer_wasix_types::wasi::Errno;
+
+/// The user and group identity of a process (like the credentials of
+/// a POSIX process), processes start as root unless configured otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasiCredentials {
+    /// Real user ID
+    pub uid: u32,
+    /// Effective user ID, which is used for permission checks
+    pub euid: u32,
+    /// Saved user ID, which an unprivileged process can switch back to
+    pub suid: u32,
+    /// Real group ID
+    pub gid: u32,
+    /// Effective group ID, which is used for permission checks
+    pub egid: u32,
+    /// Saved group ID, which an unprivileged process can switch back to
+    pub sgid: u32,
+    /// Supplementary groups
+    pub groups: Vec<u32>,
+}
+
+impl WasiCredentials {
+    /// Creates the credentials of a user with a primary group
+    pub fn new(uid: u32, gid: u32) -> Self {
+        Self {
+            uid,
+            euid: uid,
+            suid: uid,
+            gid,
+            egid: gid,
+            sgid: gid,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Returns the credentials of the root user
+    pub fn root() -> Self {
+        Self::new(0, 0)
+    }
+
+    /// Privileged processes (an effective user of root) can change their
+    /// identity at will and pass all permission checks
+    pub fn is_privileged(&self) -> bool {
+        self.euid == 0
+    }
+
+    /// Returns true if the effective group or one of the supplementary
+    /// groups is `gid`
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.egid == gid || self.groups.contains(&gid)
+    }
+
+    /// Changes the user the same way as `setuid` does, privileged processes
+    /// change all the user IDs while the others only change the effective
+    /// user ID back to their real or saved user ID
+    pub fn set_uid(&mut self, uid: u32) -> Result<(), Errno> {
+        if self.is_privileged() {
+            self.uid = uid;
+            self.euid = uid;
+            self.suid = uid;
+            Ok(())
+        } else {
+            self.set_euid(uid)
+        }
+    }
+
+    /// Changes the effective user the same way as `seteuid` does
+    pub fn set_euid(&mut self, euid: u32) -> Result<(), Errno> {
+        if !self.is_privileged() && euid != self.uid && euid != self.suid {
+            return Err(Errno::Perm);
+        }
+        self.euid = euid;
+        Ok(())
+    }
+
+    /// Changes the group the same way as `setgid` does
+    pub fn set_gid(&mut self, gid: u32) -> Result<(), Errno> {
+        if self.is_privileged() {
+            self.gid = gid;
+            self.egid = gid;
+            self.sgid = gid;
+            Ok(())
+        } else {
+            self.set_egid(gid)
+        }
+    }
+
+    /// Changes the effective group the same way as `setegid` does
+    pub fn set_egid(&mut self, egid: u32) -> Result<(), Errno> {
+        if !self.is_privileged() && egid != self.gid && egid != self.sgid {
+            return Err(Errno::Perm);
+        }
+        self.egid = egid;
+        Ok(())
+    }
+
+    /// Replaces the supplementary groups, which only privileged processes can do
+    pub fn set_groups(&mut self, groups: Vec<u32>) -> Result<(), Errno> {
+        if !self.is_privileged() {
+            return Err(Errno::Perm);
+        }
+        self.groups = groups;
+        Ok(())
+    }
+}
+
+impl Default for WasiCredentials {
+    fn default() -> Self {
+        Self::root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unprivileged_users_can_only_switch_to_their_own_ids() {
+        let mut creds = WasiCredentials::new(1000, 100);
+        assert_eq!(creds.set_uid(0), Err(Errno::Perm));
+        assert_eq!(creds.set_gid(0), Err(Errno::Perm));
+        assert_eq!(creds.set_groups(vec![0]), Err(Errno::Perm));
+        creds.set_uid(1000).unwrap();
+        assert_eq!(creds, WasiCredentials::new(1000, 100));
+    }
+
+    #[test]
+    fn test_root_can_drop_and_regain_its_privileges() {
+        let mut creds = WasiCredentials::root();
+        creds.set_groups(vec![10, 20]).unwrap();
+        assert!(creds.in_group(20));
+
+        // Dropping the effective user keeps the saved one...
+        creds.set_euid(1000).unwrap();
+        assert!(!creds.is_privileged());
+        assert_eq!(creds.set_euid(2000), Err(Errno::Perm));
+        creds.set_euid(0).unwrap();
+
+        // ...while setuid as root drops the privileges for good
+        creds.set_uid(1000).unwrap();
+        assert_eq!((creds.uid, creds.euid, creds.suid), (1000, 1000, 1000));
+        assert_eq!(creds.set_euid(0), Err(Errno::Perm));
+    }
+}
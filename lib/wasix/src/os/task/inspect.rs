@@ -0,0 +1,150 @@
+//! Snapshots of processes and threads that let hosts inspect what their
+//! guests are doing (see [`WasiControlPlane::processes`])
+//!
+//! [`WasiControlPlane::processes`]: super::control_plane::WasiControlPlane::processes
+
+use std::time::Duration;
+
+use wasmer_wasix_types::wasi::{ExitCode, Fd as WasiFd, Fdflags, Rights};
+
+use super::{
+    process::{WasiProcess, WasiProcessId},
+    thread::{WasiThread, WasiThreadId, WasiThreadWait},
+};
+use crate::fs::proc_fs::fd_target;
+
+/// Snapshot of a process
+#[derive(Debug, Clone)]
+pub struct WasiProcessInfo {
+    pub pid: WasiProcessId,
+    pub ppid: WasiProcessId,
+    /// Process group of the process
+    pub pgid: WasiProcessId,
+    /// Session of the process
+    pub sid: WasiProcessId,
+    /// Name of the program that the process runs
+    pub name: String,
+    pub args: Vec<String>,
+    /// Effective user ID of the process
+    pub uid: u32,
+    pub threads: Vec<WasiThreadInfo>,
+    pub fds: Vec<WasiFdInfo>,
+    /// Size of the linear memory in bytes
+    pub memory_size: u64,
+    /// Time since the process started
+    pub uptime: Duration,
+    /// CPU time consumed by the threads of the process
+    pub cpu_time: Duration,
+    /// Exit code of a process that has finished but was not joined yet
+    pub exit_code: Option<ExitCode>,
+}
+
+/// Snapshot of a thread
+#[derive(Debug, Clone)]
+pub struct WasiThreadInfo {
+    pub tid: WasiThreadId,
+    pub is_main: bool,
+    pub activity: WasiThreadActivity,
+    /// CPU time consumed by the thread
+    pub cpu_time: Duration,
+}
+
+/// What a thread is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasiThreadActivity {
+    /// Running guest code (or a syscall that does not block)
+    Running,
+    /// Blocked in a syscall
+    Idle,
+    /// Blocked in a syscall that waits on something in particular
+    Waiting(WasiThreadWait),
+    /// Finished but not joined yet
+    Finished,
+}
+
+impl std::fmt::Display for WasiThreadActivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Idle => write!(f, "idle"),
+            Self::Waiting(WasiThreadWait::Futex { address }) => write!(f, "futex {address:#x}"),
+            Self::Waiting(WasiThreadWait::Fd { fd }) => write!(f, "fd {fd}"),
+            Self::Waiting(WasiThreadWait::Poll) => write!(f, "poll"),
+            Self::Waiting(WasiThreadWait::Sleep) => write!(f, "sleep"),
+            Self::Finished => write!(f, "finished"),
+        }
+    }
+}
+
+/// Snapshot of an open file descriptor
+#[derive(Debug, Clone)]
+pub struct WasiFdInfo {
+    pub fd: WasiFd,
+    /// What the descriptor refers to, in the format of `/proc/<pid>/fd`
+    pub target: String,
+    pub rights: Rights,
+    pub flags: Fdflags,
+}
+
+impl WasiProcessInfo {
+    pub(crate) fn new(process: &WasiProcess) -> Self {
+        let pid = process.pid();
+        let job = process.job();
+
+        let mut threads = process
+            .lock()
+            .threads
+            .values()
+            .map(WasiThreadInfo::new)
+            .collect::<Vec<_>>();
+        threads.sort_by_key(|thread| thread.tid);
+
+        let (args, fds) = match process.state() {
+            Ok(state) => {
+                let mut fds = state
+                    .fs
+                    .fd_map
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|(fd, entry)| WasiFdInfo {
+                        fd: *fd,
+                        target: fd_target(entry),
+                        rights: entry.rights,
+                        flags: entry.flags,
+                    })
+                    .collect::<Vec<_>>();
+                fds.sort_by_key(|fd| fd.fd);
+                (state.args.clone(), fds)
+            }
+            Err(_) => Default::default(),
+        };
+
+        Self {
+            pid,
+            ppid: process.ppid(),
+            pgid: job.pgid,
+            sid: job.sid,
+            name: process.name(),
+            args,
+            uid: process.credentials().euid,
+            threads,
+            fds,
+            memory_size: process.memory_size(),
+            uptime: process.uptime(),
+            cpu_time: process.cpu_time(),
+            exit_code: process.try_join().and_then(|res| res.ok()),
+        }
+    }
+}
+
+impl WasiThreadInfo {
+    fn new(thread: &WasiThread) -> Self {
+        Self {
+            tid: thread.tid(),
+            is_main: thread.is_main(),
+            activity: thread.activity(),
+            cpu_time: thread.cpu_clock().elapsed(),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Limits on the resources that a process may consume (see `setrlimit`),
+//! the host sets the defaults through [`crate::capabilities::CapabilityLimitsV1`]
+
+use std::{sync::Arc, time::Duration};
+
+use wasmer_wasix_types::{types::Signal, wasi::Errno};
+
+use crate::{capabilities::CapabilityLimitsV1, os::task::process::WasiProcess, VirtualTaskManager};
+
+/// A resource whose consumption is limited per process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WasiResource {
+    /// Number of file descriptors that are open at the same time
+    OpenFiles,
+    /// Size in bytes that files may be extended to
+    FileSize,
+    /// Number of pages of linear memory
+    MemoryPages,
+    /// CPU time in seconds
+    CpuTime,
+    /// Number of child processes that exist at the same time
+    Processes,
+}
+
+impl TryFrom<u32> for WasiResource {
+    type Error = Errno;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::OpenFiles),
+            1 => Ok(Self::FileSize),
+            2 => Ok(Self::MemoryPages),
+            3 => Ok(Self::CpuTime),
+            4 => Ok(Self::Processes),
+            _ => Err(Errno::Inval),
+        }
+    }
+}
+
+/// The limit of a resource, [`None`] means that it is unlimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WasiResourceLimit {
+    /// Limit that is enforced, the process may raise it up to the hard limit
+    pub soft: Option<u64>,
+    /// Ceiling of the soft limit, only privileged processes may raise it
+    pub hard: Option<u64>,
+}
+
+impl WasiResourceLimit {
+    /// Creates a limit whose soft and hard limits are the same
+    pub fn new(limit: Option<u64>) -> Self {
+        Self {
+            soft: limit,
+            hard: limit,
+        }
+    }
+
+    /// Returns true if `value` is over the (soft) limit
+    pub fn exceeded_by(&self, value: u64) -> bool {
+        self.soft.map_or(false, |soft| value > soft)
+    }
+}
+
+/// Returns true if `new` is a higher limit than `old`
+fn raises(new: Option<u64>, old: Option<u64>) -> bool {
+    match (new, old) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(new), Some(old)) => new > old,
+    }
+}
+
+/// The limits of all the resources of a process
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WasiResourceLimits {
+    pub open_files: WasiResourceLimit,
+    pub file_size: WasiResourceLimit,
+    pub memory_pages: WasiResourceLimit,
+    pub cpu_time: WasiResourceLimit,
+    pub processes: WasiResourceLimit,
+}
+
+impl WasiResourceLimits {
+    /// Returns the limit of a resource
+    pub fn get(&self, resource: WasiResource) -> WasiResourceLimit {
+        match resource {
+            WasiResource::OpenFiles => self.open_files,
+            WasiResource::FileSize => self.file_size,
+            WasiResource::MemoryPages => self.memory_pages,
+            WasiResource::CpuTime => self.cpu_time,
+            WasiResource::Processes => self.processes,
+        }
+    }
+
+    /// Changes the limit of a resource the same way as `setrlimit` does,
+    /// the soft limit may not be above the hard limit and only privileged
+    /// processes may raise the hard limit
+    pub fn set(
+        &mut self,
+        resource: WasiResource,
+        limit: WasiResourceLimit,
+        privileged: bool,
+    ) -> Result<(), Errno> {
+        if raises(limit.soft, limit.hard) {
+            return Err(Errno::Inval);
+        }
+        let current = match resource {
+            WasiResource::OpenFiles => &mut self.open_files,
+            WasiResource::FileSize => &mut self.file_size,
+            WasiResource::MemoryPages => &mut self.memory_pages,
+            WasiResource::CpuTime => &mut self.cpu_time,
+            WasiResource::Processes => &mut self.processes,
+        };
+        if !privileged && raises(limit.hard, current.hard) {
+            return Err(Errno::Perm);
+        }
+        *current = limit;
+        Ok(())
+    }
+}
+
+impl From<&CapabilityLimitsV1> for WasiResourceLimits {
+    fn from(caps: &CapabilityLimitsV1) -> Self {
+        Self {
+            open_files: WasiResourceLimit::new(caps.max_open_files),
+            file_size: WasiResourceLimit::new(caps.max_file_size),
+            memory_pages: WasiResourceLimit::new(caps.max_memory_pages),
+            cpu_time: WasiResourceLimit::new(caps.max_cpu_seconds),
+            processes: WasiResourceLimit::new(caps.max_processes),
+        }
+    }
+}
+
+/// What the task enforcing the CPU time limit does next
+#[derive(Debug, PartialEq, Eq)]
+enum CpuLimitStep {
+    /// The hard limit was reached so the process is killed
+    Kill,
+    /// The soft limit was passed so the process is warned, after which
+    /// the next limit is reached after this much CPU time
+    Warn(Duration),
+    /// The next limit is reached after this much CPU time
+    Wait(Duration),
+    /// There is no limit
+    Stop,
+}
+
+/// Raises `SIGXCPU` on a process once it has used up the soft limit of its
+/// CPU time (and every second after that) and kills it at the hard limit,
+/// until the limit changes or the process exits
+pub(crate) async fn run_cpu_limit(
+    process: WasiProcess,
+    tasks: Arc<dyn VirtualTaskManager>,
+    generation: u64,
+) {
+    let mut next_warning = None;
+    loop {
+        let current = process.lock().cpu_limit_generation;
+        if current != generation {
+            return;
+        }
+        let limit = process.resource_limit(WasiResource::CpuTime);
+
+        let wait = match step_cpu_limit(limit, process.cpu_time(), &mut next_warning) {
+            CpuLimitStep::Kill => {
+                tracing::debug!(pid = %process.pid(), "CPU time limit reached");
+                process.signal_process(Signal::Sigkill);
+                return;
+            }
+            CpuLimitStep::Warn(wait) => {
+                process.signal_process(Signal::Sigxcpu);
+                wait
+            }
+            CpuLimitStep::Wait(wait) => wait,
+            CpuLimitStep::Stop => return,
+        };
+
+        // The CPU time of a process advances at most as fast as the
+        // number of threads that it has running
+        tasks
+            .sleep_now(wait / process.active_threads().max(1))
+            .await;
+        if process.try_join().is_some() {
+            return;
+        }
+    }
+}
+
+fn step_cpu_limit(
+    limit: WasiResourceLimit,
+    used: Duration,
+    next_warning: &mut Option<Duration>,
+) -> CpuLimitStep {
+    let soft = limit.soft.map(Duration::from_secs);
+    let hard = limit.hard.map(Duration::from_secs);
+    if hard.map_or(false, |hard| used >= hard) {
+        return CpuLimitStep::Kill;
+    }
+
+    let mut warn = false;
+    if let Some(warning) = next_warning.or(soft) {
+        if used >= warning {
+            warn = true;
+            *next_warning = Some(used + Duration::from_secs(1));
+        }
+    }
+
+    let deadline = [next_warning.or(soft), hard].into_iter().flatten().min();
+    match deadline {
+        Some(deadline) if warn => CpuLimitStep::Warn(deadline.saturating_sub(used)),
+        Some(deadline) => CpuLimitStep::Wait(deadline.saturating_sub(used)),
+        None => CpuLimitStep::Stop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_privileged_processes_raise_hard_limits() {
+        let mut limits = WasiResourceLimits::from(&CapabilityLimitsV1 {
+            max_open_files: Some(64),
+            ..Default::default()
+        });
+
+        let lower = WasiResourceLimit {
+            soft: Some(16),
+            hard: Some(32),
+        };
+        limits.set(WasiResource::OpenFiles, lower, false).unwrap();
+        assert_eq!(limits.get(WasiResource::OpenFiles), lower);
+        assert!(limits.get(WasiResource::OpenFiles).exceeded_by(17));
+
+        // The soft limit can go back up to the hard limit but no further
+        let raised = WasiResourceLimit::new(Some(32));
+        limits.set(WasiResource::OpenFiles, raised, false).unwrap();
+        assert_eq!(
+            limits.set(WasiResource::OpenFiles, WasiResourceLimit::new(None), false),
+            Err(Errno::Perm)
+        );
+        assert_eq!(
+            limits.set(
+                WasiResource::OpenFiles,
+                WasiResourceLimit {
+                    soft: None,
+                    hard: Some(8)
+                },
+                true
+            ),
+            Err(Errno::Inval)
+        );
+        limits
+            .set(WasiResource::OpenFiles, WasiResourceLimit::new(None), true)
+            .unwrap();
+        assert!(!limits.get(WasiResource::OpenFiles).exceeded_by(u64::MAX));
+    }
+
+    #[test]
+    fn test_cpu_limit_warns_every_second_and_then_kills() {
+        let limit = WasiResourceLimit {
+            soft: Some(2),
+            hard: Some(4),
+        };
+        let secs = Duration::from_secs;
+        let mut next_warning = None;
+
+        assert_eq!(
+            step_cpu_limit(limit, secs(1), &mut next_warning),
+            CpuLimitStep::Wait(secs(1))
+        );
+        assert_eq!(
+            step_cpu_limit(limit, secs(2), &mut next_warning),
+            CpuLimitStep::Warn(secs(1))
+        );
+        assert_eq!(
+            step_cpu_limit(limit, secs(3), &mut next_warning),
+            CpuLimitStep::Warn(secs(1))
+        );
+        assert_eq!(
+            step_cpu_limit(limit, secs(4), &mut next_warning),
+            CpuLimitStep::Kill
+        );
+        assert_eq!(
+            step_cpu_limit(WasiResourceLimit::default(), secs(4), &mut None),
+            CpuLimitStep::Stop
+        );
+    }
+}
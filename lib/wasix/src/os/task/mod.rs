@@ -1,10 +1,14 @@
 //! OS task management for processes and threads.
 
 pub mod control_plane;
+pub mod credentials;
+pub mod inspect;
+pub mod limits;
 pub mod process;
 pub mod signal;
 mod task_join_handle;
 pub mod thread;
+pub mod timer;
 
 pub use task_join_handle::{
     OwnedTaskStatus, TaskJoinHandle, TaskStatus, TaskTerminatedError, VirtualTaskHandle,
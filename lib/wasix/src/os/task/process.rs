@@ -1,35 +1,48 @@
-#[cfg(feature = "journal")]
-use crate::{journal::JournalEffector, unwind, WasiResult};
 use crate::{
-    journal::SnapshotTrigger, runtime::module_cache::ModuleHash, WasiEnv, WasiRuntimeError,
+    fs::{Fd, Kind, SignalFdInner},
+    journal::SnapshotTrigger,
+    runtime::{module_cache::ModuleHash, SpawnMemoryType},
+    state::WasiState,
+    VirtualTaskManager, WasiEnv, WasiRuntimeError,
 };
+#[cfg(feature = "journal")]
+use crate::{journal::JournalEffector, unwind, WasiResult};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     convert::TryInto,
+    path::Path,
     sync::{
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicU32, AtomicU64, Ordering},
         Arc, Condvar, Mutex, MutexGuard, RwLock, Weak,
     },
     time::Duration,
 };
+use tokio::sync::watch;
 use tracing::trace;
-use wasmer::FunctionEnvMut;
+#[cfg(feature = "sys")]
+use wasmer::{Bytes, MemoryLimiter};
+use wasmer::{FunctionEnvMut, MemoryError, Pages};
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode, Snapshot0Clockid},
+    wasi::{Errno, ExitCode, Fd as WasiFd, Fdflags, Rights, Snapshot0Clockid},
 };
 
 use crate::{
-    os::task::signal::WasiSignalInterval, syscalls::platform_clock_time_get, WasiThread,
-    WasiThreadHandle, WasiThreadId,
+    os::task::{signal::WasiSignalInterval, thread::WasiThreadError},
+    syscalls::platform_clock_time_get,
+    WasiThread, WasiThreadHandle, WasiThreadId,
 };
 
 use super::{
     control_plane::{ControlPlaneError, WasiControlPlaneHandle},
+    credentials::WasiCredentials,
+    inspect::WasiProcessInfo,
+    limits::{run_cpu_limit, WasiResource, WasiResourceLimit, WasiResourceLimits},
     signal::{SignalDeliveryError, SignalHandlerAbi},
     task_join_handle::OwnedTaskStatus,
     thread::WasiMemoryLayout,
+    timer::{run_timer, WasiTimer, WasiTimerClock, WasiTimerId},
 };
 
 /// Represents the ID of a sub-process
@@ -80,6 +93,52 @@ impl std::fmt::Debug for WasiProcessId {
 
 pub type LockableWasiProcessInner = Arc<(Mutex<WasiProcessInner>, Condvar)>;
 
+/// The process group and session that a process belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WasiJobIds {
+    pub pgid: WasiProcessId,
+    pub sid: WasiProcessId,
+}
+
+/// Whether a process is stopped by a signal (e.g. `SIGSTOP`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct WasiStopState {
+    /// The signal that stopped the process, until it is continued
+    pub signal: Option<Signal>,
+    /// Whether the parent was told that the process stopped (see
+    /// `JoinFlags::WAKE_STOPPED`)
+    pub reported: bool,
+}
+
+/// Keeps track of the size of the memories of a process as they grow and
+/// stops them from growing past its limit on memory pages, it is attached
+/// to the stores that run the process
+#[cfg(feature = "sys")]
+#[derive(Debug)]
+struct WasiProcessMemoryLimiter {
+    memory_size: Arc<AtomicU64>,
+    limits: Arc<RwLock<WasiResourceLimits>>,
+}
+
+#[cfg(feature = "sys")]
+impl MemoryLimiter for WasiProcessMemoryLimiter {
+    fn memory_growing(&self, current: Pages, desired: Pages) -> Result<(), MemoryError> {
+        let limit = self.limits.read().unwrap().memory_pages.soft;
+        match limit {
+            Some(limit) if desired.0 as u64 > limit => Err(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: Pages(desired.0.saturating_sub(current.0)),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn memory_grown(&self, size: Pages) {
+        self.memory_size
+            .store(Bytes::from(size).0 as u64, Ordering::Relaxed);
+    }
+}
+
 /// Represents a process running within the compute state
 /// TODO: fields should be private and only accessed via methods.
 #[derive(Debug, Clone)]
@@ -101,6 +160,18 @@ pub struct WasiProcess {
     pub(crate) finished: Arc<OwnedTaskStatus>,
     /// Number of threads waiting for children to exit
     pub(crate) waiting: Arc<AtomicU32>,
+    /// Size of the linear memory in bytes, updated as the memory grows
+    pub(crate) memory_size: Arc<AtomicU64>,
+    /// User and group identity of the process
+    pub(crate) credentials: Arc<RwLock<WasiCredentials>>,
+    /// Limits on the resources that the process may consume
+    pub(crate) limits: Arc<RwLock<WasiResourceLimits>>,
+    /// Monotonic time (in nanoseconds) at which the process started
+    pub(crate) started: u128,
+    /// The process group and session of the process
+    pub(crate) job: Arc<RwLock<WasiJobIds>>,
+    /// Whether the process is stopped, its threads wait for it to change
+    pub(crate) stop: Arc<watch::Sender<WasiStopState>>,
 }
 
 /// Represents a freeze of all threads to perform some action
@@ -133,6 +204,22 @@ pub struct WasiProcessInner {
     /// Represents a checkpoint which blocks all the threads
     /// and then executes some maintenance action
     pub checkpoint: WasiProcessCheckpoint,
+    /// State of the environment running this process, which holds
+    /// its file descriptors
+    pub(crate) state: Weak<WasiState>,
+    /// CPU time consumed by the threads of this process that have exited
+    pub(crate) finished_cpu_time: Duration,
+    /// CPU time consumed by the children of this process that have been joined
+    pub(crate) children_cpu_time: Duration,
+    /// Timers created by this process (see `timer_create`)
+    pub(crate) timers: HashMap<WasiTimerId, WasiTimer>,
+    /// Seed used to generate timer IDs
+    pub(crate) timer_seed: WasiTimerId,
+    /// Signal descriptors created by this process (see `signalfd`)
+    pub(crate) signal_fds: Vec<Weak<SignalFdInner>>,
+    /// Incremented every time the CPU time limit changes so that the task
+    /// enforcing the previous limit stops
+    pub(crate) cpu_limit_generation: u64,
 }
 
 pub enum MaybeCheckpointResult<'a> {
@@ -296,16 +383,38 @@ impl WasiProcess {
                     signal_intervals: Default::default(),
                     children: Default::default(),
                     checkpoint: WasiProcessCheckpoint::Execute,
+                    state: Weak::new(),
+                    finished_cpu_time: Duration::ZERO,
+                    children_cpu_time: Duration::ZERO,
+                    timers: Default::default(),
+                    timer_seed: 0,
+                    signal_fds: Default::default(),
+                    cpu_limit_generation: 0,
                 }),
                 Condvar::new(),
             )),
             finished: Arc::new(OwnedTaskStatus::default()),
             waiting: Arc::new(AtomicU32::new(0)),
+            memory_size: Arc::new(AtomicU64::new(0)),
+            credentials: Default::default(),
+            limits: Default::default(),
+            started: platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap() as u128,
+            job: Arc::new(RwLock::new(WasiJobIds {
+                pgid: pid,
+                sid: pid,
+            })),
+            stop: Arc::new(watch::channel(WasiStopState::default()).0),
         }
     }
 
+    /// Sets the ID of this process, which leads its own process group and
+    /// session until it joins others
     pub(super) fn set_pid(&mut self, pid: WasiProcessId) {
         self.pid = pid;
+        self.set_job(WasiJobIds {
+            pgid: pid,
+            sid: pid,
+        });
     }
 
     /// Gets the process ID of this process
@@ -323,6 +432,258 @@ impl WasiProcess {
             .unwrap_or(WasiProcessId(0))
     }
 
+    /// Returns the process group that this process belongs to
+    pub fn process_group(&self) -> WasiProcessId {
+        self.job.read().unwrap().pgid
+    }
+
+    /// Returns the session that this process belongs to
+    pub fn session(&self) -> WasiProcessId {
+        self.job.read().unwrap().sid
+    }
+
+    pub(crate) fn job(&self) -> WasiJobIds {
+        *self.job.read().unwrap()
+    }
+
+    pub(crate) fn set_job(&self, job: WasiJobIds) {
+        *self.job.write().unwrap() = job;
+    }
+
+    /// Returns the signal that stopped this process, while it is stopped
+    pub fn stopped(&self) -> Option<Signal> {
+        self.stop.borrow().signal
+    }
+
+    /// Stops this process, its threads wait at their next syscall until it
+    /// is continued with `SIGCONT` (or killed)
+    pub(crate) fn stop(&self, signal: Signal) {
+        self.stop.send_if_modified(|stop| {
+            if stop.signal.is_some() {
+                return false;
+            }
+            *stop = WasiStopState {
+                signal: Some(signal),
+                reported: false,
+            };
+            true
+        });
+    }
+
+    /// Continues this process if it was stopped
+    pub(crate) fn resume(&self) {
+        self.stop
+            .send_if_modified(|stop| stop.signal.take().is_some());
+    }
+
+    /// Waits until this process is no longer stopped
+    pub(crate) async fn wait_until_resumed(&self) {
+        let mut stop = self.stop.subscribe();
+        // The sender is held by this process so it can't be dropped
+        stop.wait_for(|stop| stop.signal.is_none()).await.ok();
+    }
+
+    /// Attaches the state of the environment that runs this process
+    pub(crate) fn set_state(&self, state: &Arc<WasiState>) {
+        state.fs.set_pid(self.pid);
+        state
+            .fs
+            .set_max_open_files(self.resource_limit(WasiResource::OpenFiles).soft);
+        self.inner.0.lock().unwrap().state = Arc::downgrade(state);
+    }
+
+    /// Returns the user and group identity of this process
+    pub fn credentials(&self) -> WasiCredentials {
+        self.credentials.read().unwrap().clone()
+    }
+
+    /// Replaces the user and group identity of this process
+    pub fn set_credentials(&self, credentials: WasiCredentials) {
+        *self.credentials.write().unwrap() = credentials;
+    }
+
+    /// Returns the limits on the resources that this process may consume
+    pub fn resource_limits(&self) -> WasiResourceLimits {
+        self.limits.read().unwrap().clone()
+    }
+
+    /// Returns the limit on a resource that this process may consume
+    pub fn resource_limit(&self, resource: WasiResource) -> WasiResourceLimit {
+        self.limits.read().unwrap().get(resource)
+    }
+
+    /// Replaces all the limits on the resources of this process (without
+    /// the checks that `setrlimit` makes) and starts enforcing them
+    pub fn set_resource_limits(
+        &self,
+        tasks: &Arc<dyn VirtualTaskManager>,
+        limits: WasiResourceLimits,
+    ) -> Result<(), Errno> {
+        *self.limits.write().unwrap() = limits;
+        self.enforce_limit(tasks, WasiResource::OpenFiles)?;
+        self.enforce_limit(tasks, WasiResource::CpuTime)
+    }
+
+    /// Changes the limit on a resource the same way as `setrlimit` does,
+    /// only privileged processes may raise their hard limits
+    pub fn set_resource_limit(
+        &self,
+        tasks: &Arc<dyn VirtualTaskManager>,
+        resource: WasiResource,
+        limit: WasiResourceLimit,
+    ) -> Result<(), Errno> {
+        let privileged = self.credentials.read().unwrap().is_privileged();
+        self.limits
+            .write()
+            .unwrap()
+            .set(resource, limit, privileged)?;
+        self.enforce_limit(tasks, resource)
+    }
+
+    /// Applies a limit that is not checked as the resource is consumed
+    fn enforce_limit(
+        &self,
+        tasks: &Arc<dyn VirtualTaskManager>,
+        resource: WasiResource,
+    ) -> Result<(), Errno> {
+        let limit = self.resource_limit(resource);
+        match resource {
+            WasiResource::OpenFiles => {
+                if let Ok(state) = self.state() {
+                    state.fs.set_max_open_files(limit.soft);
+                }
+            }
+            WasiResource::CpuTime => {
+                let generation = {
+                    let mut inner = self.inner.0.lock().unwrap();
+                    inner.cpu_limit_generation += 1;
+                    inner.cpu_limit_generation
+                };
+                if limit != WasiResourceLimit::default() {
+                    let process = self.clone();
+                    let tasks_inner = tasks.clone();
+                    tasks
+                        .task_shared(Box::new(move || {
+                            Box::pin(run_cpu_limit(process, tasks_inner, generation))
+                        }))
+                        .map_err(Errno::from)?;
+                }
+            }
+            WasiResource::FileSize | WasiResource::MemoryPages | WasiResource::Processes => {}
+        }
+        Ok(())
+    }
+
+    /// Attaches the memories of a store to this process, which keeps track
+    /// of their size as they grow
+    #[cfg(feature = "sys")]
+    pub(crate) fn attach_store(&self, store: &mut impl wasmer::AsStoreMut) {
+        let limiter = WasiProcessMemoryLimiter {
+            memory_size: self.memory_size.clone(),
+            limits: self.limits.clone(),
+        };
+        store
+            .as_store_mut()
+            .set_memory_limiter(Some(Arc::new(limiter)));
+    }
+
+    /// Caps the memory that is about to be created for this process at its
+    /// limit on memory pages (memories that the module creates itself can
+    /// not be capped)
+    pub(crate) fn limit_memory<'a>(
+        &self,
+        spawn_type: SpawnMemoryType<'a>,
+    ) -> Result<SpawnMemoryType<'a>, WasiThreadError> {
+        let limit = match self.resource_limit(WasiResource::MemoryPages).soft {
+            Some(limit) => Pages(limit.min(Pages::max_value().0 as u64) as u32),
+            None => return Ok(spawn_type),
+        };
+        match spawn_type {
+            SpawnMemoryType::CreateMemoryOfType(mut ty) => {
+                if ty.minimum > limit {
+                    return Err(WasiThreadError::MemoryCreateFailed(
+                        MemoryError::MinimumMemoryTooLarge {
+                            min_requested: ty.minimum,
+                            max_allowed: limit,
+                        },
+                    ));
+                }
+                ty.maximum = Some(ty.maximum.map_or(limit, |max| max.min(limit)));
+                Ok(SpawnMemoryType::CreateMemoryOfType(ty))
+            }
+            spawn_type => Ok(spawn_type),
+        }
+    }
+
+    pub(crate) fn state(&self) -> Result<Arc<WasiState>, Errno> {
+        self.inner
+            .0
+            .lock()
+            .unwrap()
+            .state
+            .upgrade()
+            .ok_or(Errno::Srch)
+    }
+
+    /// Name of the program that this process runs
+    pub fn name(&self) -> String {
+        self.state()
+            .ok()
+            .and_then(|state| {
+                let program = state.args.first()?;
+                let name = Path::new(program).file_name()?;
+                Some(name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the time since this process started
+    pub fn uptime(&self) -> Duration {
+        let now = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap() as u128;
+        Duration::from_nanos(now.saturating_sub(self.started) as u64)
+    }
+
+    /// Returns the size of the linear memory of this process in bytes
+    pub fn memory_size(&self) -> u64 {
+        self.memory_size.load(Ordering::Relaxed)
+    }
+
+    /// Takes a snapshot of this process, its threads and its open file
+    /// descriptors
+    pub fn info(&self) -> WasiProcessInfo {
+        WasiProcessInfo::new(self)
+    }
+
+    /// Gets a file descriptor of this process, it can be inserted into
+    /// another process with [`WasiProcess::insert_fd`]
+    pub fn fd(&self, fd: WasiFd) -> Result<Fd, Errno> {
+        self.state()?.fs.get_fd(fd)
+    }
+
+    /// Inserts a duplicate of a file descriptor (of this or of another
+    /// process) into this running process and returns its number
+    ///
+    /// Both descriptors share the same file and offset.
+    pub fn insert_fd(&self, fd: Fd) -> Result<WasiFd, Errno> {
+        self.state()?.fs.insert_fd(fd)
+    }
+
+    /// Opens a new file descriptor in this running process, for instance
+    /// for a connection that the host accepted on its behalf, and returns
+    /// its number
+    pub fn inject_fd(&self, kind: Kind, rights: Rights, flags: Fdflags) -> Result<WasiFd, Errno> {
+        let state = self.state()?;
+        let inode = state.fs.create_inode_with_default_stat(
+            &state.inodes,
+            kind,
+            false,
+            "injected".to_string().into(),
+        );
+        state
+            .fs
+            .create_fd(rights, rights, flags, Fd::READ | Fd::WRITE, inode)
+    }
+
     /// Gains access to the process internals
     // TODO: Make this private, all inner access should be exposed with methods.
     pub fn lock(&self) -> MutexGuard<'_, WasiProcessInner> {
@@ -386,6 +747,10 @@ impl WasiProcess {
         let pid = self.pid();
         tracing::trace!(%pid, %tid, "signal-thread({:?})", signal);
 
+        if self.queue_on_signal_fds(signal) {
+            return;
+        }
+
         let inner = self.inner.0.lock().unwrap();
         if let Some(thread) = inner.threads.get(&tid) {
             thread.signal(signal);
@@ -404,6 +769,10 @@ impl WasiProcess {
         let pid = self.pid();
         tracing::trace!(%pid, "signal-process({:?})", signal);
 
+        if self.intercept_signal(signal) {
+            return;
+        }
+
         {
             let inner = self.inner.0.lock().unwrap();
             if self.waiting.load(Ordering::Acquire) > 0 {
@@ -417,12 +786,101 @@ impl WasiProcess {
                 }
             }
         }
+        self.signal_threads(signal);
+    }
+
+    /// Signals all the threads in this process, without handing the signal
+    /// to the children it waits on like [`WasiProcess::signal_process`] does
+    pub(crate) fn deliver_signal(&self, signal: Signal) {
+        tracing::trace!(pid = %self.pid(), "deliver-signal({:?})", signal);
+        if !self.intercept_signal(signal) {
+            self.signal_threads(signal);
+        }
+    }
+
+    /// Stops or continues the process for the job control signals, and
+    /// queues the signal on a signal descriptor that watches it
+    ///
+    /// Returns true if the threads are not to be signaled.
+    fn intercept_signal(&self, signal: Signal) -> bool {
+        match signal {
+            // `SIGSTOP` can't be caught
+            Signal::Sigstop => {
+                self.stop(signal);
+                return true;
+            }
+            // Killed processes have to run to exit
+            Signal::Sigcont | Signal::Sigkill => self.resume(),
+            _ => {}
+        }
+        self.queue_on_signal_fds(signal)
+    }
+
+    fn signal_threads(&self, signal: Signal) {
         let inner = self.inner.0.lock().unwrap();
         for thread in inner.threads.values() {
             thread.signal(signal);
         }
     }
 
+    /// Registers a signal descriptor that receives the signals of this
+    /// process that it watches in place of its threads
+    pub fn add_signal_fd(&self, signal_fd: &Arc<SignalFdInner>) {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner
+            .signal_fds
+            .retain(|signal_fd| signal_fd.strong_count() > 0);
+        inner.signal_fds.push(Arc::downgrade(signal_fd));
+    }
+
+    /// Queues a signal on the first signal descriptor of this process that
+    /// watches it, returns false if there is no such descriptor
+    fn queue_on_signal_fds(&self, signal: Signal) -> bool {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner
+            .signal_fds
+            .retain(|signal_fd| signal_fd.strong_count() > 0);
+        let signal_fd = inner
+            .signal_fds
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|signal_fd| signal_fd.watches(signal));
+        match signal_fd {
+            Some(signal_fd) => {
+                trace!(pid = %self.pid(), ?signal, "signal queued on a signal descriptor");
+                signal_fd.push(signal);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the signals whose interval elapsed since they were last
+    /// raised, the ones that a signal descriptor watches are queued on it
+    /// instead of being returned
+    pub(crate) fn pop_interval_signals(&self) -> Vec<Signal> {
+        let due = {
+            let mut inner = self.inner.0.lock().unwrap();
+            if inner.signal_intervals.is_empty() {
+                return Vec::new();
+            }
+            let now =
+                platform_clock_time_get(Snapshot0Clockid::Monotonic, 1_000_000).unwrap() as u128;
+            inner
+                .signal_intervals
+                .values_mut()
+                .filter(|signal| now - signal.last_signal >= signal.interval.as_nanos())
+                .map(|signal| {
+                    signal.last_signal = now;
+                    signal.signal
+                })
+                .collect::<Vec<_>>()
+        };
+        due.into_iter()
+            .filter(|signal| !self.queue_on_signal_fds(*signal))
+            .collect()
+    }
+
     /// Signals one of the threads every interval
     pub fn signal_interval(&self, signal: Signal, interval: Option<Duration>, repeat: bool) {
         let mut inner = self.inner.0.lock().unwrap();
@@ -447,6 +905,104 @@ impl WasiProcess {
         );
     }
 
+    /// Creates a disarmed timer that raises a signal on this process
+    /// when it expires on a clock
+    pub fn create_timer(
+        &self,
+        clock: WasiTimerClock,
+        signal: Signal,
+    ) -> Result<WasiTimerId, Errno> {
+        let mut inner = self.inner.0.lock().unwrap();
+        let id = inner.timer_seed.checked_add(1).ok_or(Errno::Again)?;
+        inner.timer_seed = id;
+        inner.timers.insert(id, WasiTimer::new(clock, signal));
+        Ok(id)
+    }
+
+    /// Arms a timer so that it expires after `value` (or at `value` on its
+    /// clock when `absolute` is set) and then every `interval`, a zero
+    /// `value` disarms the timer instead
+    ///
+    /// Returns the time that was left on the timer and its interval
+    pub fn set_timer(
+        &self,
+        tasks: &Arc<dyn VirtualTaskManager>,
+        id: WasiTimerId,
+        value: Duration,
+        interval: Duration,
+        absolute: bool,
+    ) -> Result<(Duration, Duration), Errno> {
+        let clock = self.timer_clock(id)?;
+        let now = clock.now(self)?;
+
+        let (old, generation) = {
+            let mut inner = self.inner.0.lock().unwrap();
+            let timer = inner.timers.get_mut(&id).ok_or(Errno::Inval)?;
+            let old = (
+                timer
+                    .deadline
+                    .map(|deadline| deadline.saturating_sub(now))
+                    .unwrap_or_default(),
+                timer.interval,
+            );
+
+            timer.generation += 1;
+            timer.overrun = 0;
+            timer.interval = interval;
+            timer.deadline = if value.is_zero() {
+                None
+            } else if absolute {
+                Some(value)
+            } else {
+                Some(now + value)
+            };
+            if timer.deadline.is_none() {
+                return Ok(old);
+            }
+            (old, timer.generation)
+        };
+
+        let process = self.clone();
+        let tasks_inner = tasks.clone();
+        tasks
+            .task_shared(Box::new(move || {
+                Box::pin(run_timer(process, tasks_inner, id, generation))
+            }))
+            .map_err(Errno::from)?;
+        Ok(old)
+    }
+
+    /// Returns the time left on a timer and its interval
+    pub fn get_timer(&self, id: WasiTimerId) -> Result<(Duration, Duration), Errno> {
+        let now = self.timer_clock(id)?.now(self)?;
+        let inner = self.inner.0.lock().unwrap();
+        let timer = inner.timers.get(&id).ok_or(Errno::Inval)?;
+        let left = timer
+            .deadline
+            .map(|deadline| deadline.saturating_sub(now))
+            .unwrap_or_default();
+        Ok((left, timer.interval))
+    }
+
+    /// Returns the number of expirations of a timer that were missed
+    pub fn timer_overrun(&self, id: WasiTimerId) -> Result<u32, Errno> {
+        let inner = self.inner.0.lock().unwrap();
+        let timer = inner.timers.get(&id).ok_or(Errno::Inval)?;
+        Ok(timer.overrun)
+    }
+
+    /// Deletes a timer, disarming it
+    pub fn delete_timer(&self, id: WasiTimerId) -> Result<(), Errno> {
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.timers.remove(&id).map(|_| ()).ok_or(Errno::Inval)
+    }
+
+    fn timer_clock(&self, id: WasiTimerId) -> Result<WasiTimerClock, Errno> {
+        let inner = self.inner.0.lock().unwrap();
+        let timer = inner.timers.get(&id).ok_or(Errno::Inval)?;
+        Ok(timer.clock.clone())
+    }
+
     /// Returns the number of active threads for this process
     pub fn active_threads(&self) -> u32 {
         let inner = self.inner.0.lock().unwrap();
@@ -480,8 +1036,10 @@ impl WasiProcess {
                 let inner = self.inner.clone();
                 waits.push(async move {
                     let join = process.join().await;
+                    let cpu_time = process.total_cpu_time();
                     let mut inner = inner.0.lock().unwrap();
                     inner.children.retain(|a| a.pid != child.pid);
+                    inner.children_cpu_time += cpu_time;
                     join
                 })
             }
@@ -494,11 +1052,45 @@ impl WasiProcess {
 
     /// Waits for any of the children to finished
     pub async fn join_any_child(&mut self) -> Result<Option<(WasiProcessId, ExitCode)>, Errno> {
+        self.join_any_child_where(|_| true).await
+    }
+
+    /// Waits for any of the children that are members of a process group to finish
+    pub async fn join_any_child_in_group(
+        &mut self,
+        pgid: WasiProcessId,
+    ) -> Result<Option<(WasiProcessId, ExitCode)>, Errno> {
+        self.join_any_child_where(|child| child.process_group() == pgid)
+            .await
+    }
+
+    /// Joins a child of a process group that has already finished, if there is one
+    pub fn try_join_child_in_group(
+        &self,
+        pgid: WasiProcessId,
+    ) -> Result<Option<(WasiProcessId, ExitCode)>, Errno> {
+        let children = self.children_where(|child| child.process_group() == pgid);
+        if children.is_empty() {
+            return Err(Errno::Child);
+        }
+
+        for child in children {
+            if let Some(res) = child.try_join() {
+                self.reap_child(&child);
+                let code = res
+                    .unwrap_or_else(|e| e.as_exit_code().unwrap_or_else(|| Errno::Canceled.into()));
+                return Ok(Some((child.pid, code)));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn join_any_child_where(
+        &mut self,
+        filter: impl Fn(&WasiProcess) -> bool,
+    ) -> Result<Option<(WasiProcessId, ExitCode)>, Errno> {
         let _guard = WasiProcessWait::new(self);
-        let children: Vec<_> = {
-            let inner = self.inner.0.lock().unwrap();
-            inner.children.clone()
-        };
+        let children = self.children_where(filter);
         if children.is_empty() {
             return Err(Errno::Child);
         }
@@ -509,8 +1101,10 @@ impl WasiProcess {
                 let inner = self.inner.clone();
                 waits.push(async move {
                     let join = process.join().await;
+                    let cpu_time = process.total_cpu_time();
                     let mut inner = inner.0.lock().unwrap();
                     inner.children.retain(|a| a.pid != child.pid);
+                    inner.children_cpu_time += cpu_time;
                     (child, join)
                 })
             }
@@ -525,6 +1119,96 @@ impl WasiProcess {
         Ok(Some((child.pid, code)))
     }
 
+    fn children_where(&self, filter: impl Fn(&WasiProcess) -> bool) -> Vec<WasiProcess> {
+        let inner = self.inner.0.lock().unwrap();
+        inner
+            .children
+            .iter()
+            .filter(|child| filter(child))
+            .cloned()
+            .collect()
+    }
+
+    /// Reports a child accepted by `filter` that stopped since the last
+    /// time it was reported, if there is one
+    pub fn try_join_stopped_child(
+        &self,
+        filter: impl Fn(&WasiProcess) -> bool,
+    ) -> Option<(WasiProcessId, Signal)> {
+        self.children_where(filter).into_iter().find_map(|child| {
+            let mut report = None;
+            // Reporting a stop doesn't wake the threads of the child
+            child.stop.send_if_modified(|stop| {
+                if let (Some(signal), false) = (stop.signal, stop.reported) {
+                    stop.reported = true;
+                    report = Some((child.pid, signal));
+                }
+                false
+            });
+            report
+        })
+    }
+
+    /// Waits until a child accepted by `filter` is stopped by a signal and
+    /// reports it, it waits forever if there is no such child
+    pub async fn join_stopped_child(
+        &self,
+        filter: impl Fn(&WasiProcess) -> bool,
+    ) -> (WasiProcessId, Signal) {
+        loop {
+            // Subscribing first so that no stop is missed
+            let children = self.children_where(&filter);
+            let mut stops = children
+                .iter()
+                .map(|child| child.stop.subscribe())
+                .collect::<Vec<_>>();
+            if let Some(stopped) = self.try_join_stopped_child(&filter) {
+                return stopped;
+            }
+            if stops.is_empty() {
+                return futures::future::pending().await;
+            }
+            // The children hold the senders, so the receivers can't fail
+            let (changed, ..) =
+                futures::future::select_all(stops.iter_mut().map(|stop| Box::pin(stop.changed())))
+                    .await;
+            changed.ok();
+        }
+    }
+
+    /// Removes a child that has finished and adds the CPU time that it
+    /// consumed to the usage of the children of this process
+    pub(crate) fn reap_child(&self, child: &WasiProcess) {
+        let cpu_time = child.total_cpu_time();
+        let mut inner = self.inner.0.lock().unwrap();
+        inner.children.retain(|a| a.pid != child.pid);
+        inner.children_cpu_time += cpu_time;
+    }
+
+    /// Returns the CPU time consumed by the threads of this process
+    pub fn cpu_time(&self) -> Duration {
+        let inner = self.inner.0.lock().unwrap();
+        inner.finished_cpu_time
+            + inner
+                .threads
+                .values()
+                .map(|thread| thread.cpu_clock().elapsed())
+                .sum::<Duration>()
+    }
+
+    /// Returns the CPU time consumed by the children of this process
+    /// that have finished and were joined
+    pub fn children_cpu_time(&self) -> Duration {
+        let inner = self.inner.0.lock().unwrap();
+        inner.children_cpu_time
+    }
+
+    /// CPU time of this process including the children it joined, which
+    /// is what its parent accounts for when it joins on it
+    fn total_cpu_time(&self) -> Duration {
+        self.cpu_time() + self.children_cpu_time()
+    }
+
     /// Terminate the process and all its threads
     pub fn terminate(&self, exit_code: ExitCode) {
         // FIXME: this is wrong, threads might still be running!
@@ -533,16 +1217,26 @@ impl WasiProcess {
         for thread in guard.threads.values() {
             thread.set_status_finished(Ok(exit_code))
         }
+        drop(guard);
+        self.resume();
     }
 }
 
+/// The signals raised through a process on behalf of its terminal (see
+/// [`Tty::set_signaler`](crate::os::Tty::set_signaler)) are delivered to the
+/// foreground process group of its session, like
+/// [`TtyJobControl`](crate::os::TtyJobControl) does
 impl SignalHandlerAbi for WasiProcess {
     fn signal(&self, sig: u8) -> Result<(), SignalDeliveryError> {
-        if let Ok(sig) = sig.try_into() {
-            self.signal_process(sig);
-            Ok(())
-        } else {
-            Err(SignalDeliveryError)
+        let sig: Signal = sig.try_into().map_err(|_| SignalDeliveryError)?;
+        match self.compute.upgrade() {
+            Some(control_plane) => control_plane
+                .signal_foreground_group(self.session(), sig)
+                .map_err(|_| SignalDeliveryError),
+            None => {
+                self.signal_process(sig);
+                Ok(())
+            }
         }
     }
 }
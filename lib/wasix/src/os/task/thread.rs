@@ -6,23 +6,25 @@ use std::{
     ops::{Deref, DerefMut},
     sync::{Arc, Condvar, Mutex, Weak},
     task::Waker,
+    time::Duration,
 };
 
 use bytes::{Bytes, BytesMut};
 use wasmer::{ExportError, InstantiationError, MemoryError};
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode},
+    wasi::{Errno, ExitCode, Fd as WasiFd, Snapshot0Clockid},
 };
 
 use crate::{
     os::task::process::{WasiProcessId, WasiProcessInner},
-    syscalls::HandleRewindType,
+    syscalls::{platform_clock_time_get, HandleRewindType},
     WasiRuntimeError,
 };
 
 use super::{
     control_plane::TaskCountGuard,
+    inspect::WasiThreadActivity,
     task_join_handle::{OwnedTaskStatus, TaskJoinHandle},
 };
 
@@ -205,6 +207,203 @@ pub(crate) struct RewindResult {
     pub rewind_result: Option<Bytes>,
 }
 
+/// What a thread is blocked on while it waits in a syscall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasiThreadWait {
+    /// Waiting on the futex at this address of the linear memory
+    Futex { address: u64 },
+    /// Waiting to read from or write to a file descriptor
+    Fd { fd: WasiFd },
+    /// Waiting for events on a set of subscriptions (`poll_oneoff`, `epoll_wait`)
+    Poll,
+    /// Sleeping for some time
+    Sleep,
+}
+
+/// Clears what a thread is blocked on when dropped, see [`WasiThread::wait_on`]
+#[derive(Debug)]
+pub(crate) struct WasiThreadWaitGuard {
+    state: Arc<WasiThreadState>,
+    previous: Option<WasiThreadWait>,
+}
+
+impl Drop for WasiThreadWaitGuard {
+    fn drop(&mut self) {
+        *self.state.waiting_on.lock().unwrap() = self.previous.take();
+    }
+}
+
+/// Measures the CPU time of a thread, which is the CPU time of the host
+/// threads that ran it less the time it spent idle (waiting on IO,
+/// sleeping, ...)
+#[derive(Debug, Clone)]
+pub struct WasiCpuClock {
+    state: Arc<Mutex<CpuClockState>>,
+}
+
+#[derive(Debug)]
+struct CpuClockState {
+    /// CPU time consumed in the running periods that ended
+    consumed: u128,
+    /// The host clock of the current running period and its time when the
+    /// period began, there is none while the thread is idle or finished
+    running: Option<(HostCpuClock, u128)>,
+    /// Number of nested idle periods
+    idle_depth: u32,
+    stopped: bool,
+}
+
+/// Clock that measures the CPU time of a host thread, it falls back to the
+/// monotonic clock on the hosts that can't measure it (which then also
+/// counts the time the host thread was descheduled)
+#[derive(Debug, Clone, Copy)]
+enum HostCpuClock {
+    /// `CLOCK_THREAD_CPUTIME_ID` of a host thread, which can be read from
+    /// any other thread
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    Thread(libc::clockid_t),
+    Monotonic,
+}
+
+impl HostCpuClock {
+    /// Returns the clock of the host thread that calls it
+    fn current() -> Self {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let mut id: libc::clockid_t = 0;
+            if unsafe { libc::pthread_getcpuclockid(libc::pthread_self(), &mut id) } == 0 {
+                return Self::Thread(id);
+            }
+        }
+        Self::Monotonic
+    }
+
+    /// Reads the clock (in nanoseconds), which fails once its host thread
+    /// exited
+    fn now(&self) -> Option<u128> {
+        match self {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Self::Thread(id) => {
+                let mut time = libc::timespec {
+                    tv_sec: 0,
+                    tv_nsec: 0,
+                };
+                match unsafe { libc::clock_gettime(*id, &mut time) } {
+                    0 => Some(time.tv_sec as u128 * 1_000_000_000 + time.tv_nsec as u128),
+                    _ => None,
+                }
+            }
+            Self::Monotonic => platform_clock_time_get(Snapshot0Clockid::Monotonic, 1)
+                .ok()
+                .map(|time| time as u128),
+        }
+    }
+}
+
+impl CpuClockState {
+    /// Starts a running period on the host thread that calls it
+    fn run_on_current_thread(&mut self) {
+        let clock = HostCpuClock::current();
+        self.running = clock.now().map(|now| (clock, now));
+    }
+
+    /// CPU time consumed by the current running period
+    fn running_time(&self) -> u128 {
+        self.running
+            .and_then(|(clock, since)| Some(clock.now()?.saturating_sub(since)))
+            .unwrap_or_default()
+    }
+
+    fn end_running_period(&mut self) {
+        self.consumed += self.running_time();
+        self.running = None;
+    }
+}
+
+impl WasiCpuClock {
+    /// Creates the clock of a thread that runs on the host thread that
+    /// creates it (see [`WasiCpuClock::run_on_current_thread`])
+    fn new() -> Self {
+        let mut state = CpuClockState {
+            consumed: 0,
+            running: None,
+            idle_depth: 0,
+            stopped: false,
+        };
+        state.run_on_current_thread();
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Measures the CPU time of the host thread that calls it from now
+    /// on, as threads are created by (the host thread of) another thread
+    /// and run elsewhere
+    pub(crate) fn run_on_current_thread(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.running.is_some() {
+            state.run_on_current_thread();
+        }
+    }
+
+    /// Marks the thread as idle (or running again), the idle periods nest
+    ///
+    /// It has to be called by the host thread that runs the thread.
+    pub(crate) fn set_idle(&self, idle: bool) {
+        let mut state = self.state.lock().unwrap();
+        if idle {
+            if state.idle_depth == 0 {
+                state.end_running_period();
+            }
+            state.idle_depth += 1;
+        } else if state.idle_depth > 0 {
+            state.idle_depth -= 1;
+            if state.idle_depth == 0 && !state.stopped {
+                state.run_on_current_thread();
+            }
+        }
+    }
+
+    /// Returns true if the thread is idle at the moment
+    pub fn is_idle(&self) -> bool {
+        self.state.lock().unwrap().idle_depth > 0
+    }
+
+    /// Marks the thread as idle until the returned guard is dropped
+    pub(crate) fn idle(&self) -> WasiCpuIdleGuard {
+        self.set_idle(true);
+        WasiCpuIdleGuard {
+            clock: self.clone(),
+        }
+    }
+
+    /// Stops the clock, which happens when the thread finishes
+    fn stop(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.end_running_period();
+        state.stopped = true;
+    }
+
+    /// Returns the CPU time consumed so far
+    pub fn elapsed(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        let nanos = state.consumed + state.running_time();
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// Marks a thread as running again when dropped, see [`WasiCpuClock::idle`]
+#[derive(Debug)]
+pub(crate) struct WasiCpuIdleGuard {
+    clock: WasiCpuClock,
+}
+
+impl Drop for WasiCpuIdleGuard {
+    fn drop(&mut self) {
+        self.clock.set_idle(false);
+    }
+}
+
 #[derive(Debug)]
 struct WasiThreadState {
     is_main: bool,
@@ -213,6 +412,8 @@ struct WasiThreadState {
     signals: Mutex<(Vec<Signal>, Vec<Waker>)>,
     stack: Mutex<ThreadStack>,
     status: Arc<OwnedTaskStatus>,
+    cpu_clock: WasiCpuClock,
+    waiting_on: Mutex<Option<WasiThreadWait>>,
     #[cfg(feature = "journal")]
     check_pointing: AtomicBool,
 
@@ -238,6 +439,8 @@ impl WasiThread {
                 pid,
                 id,
                 status,
+                cpu_clock: WasiCpuClock::new(),
+                waiting_on: Mutex::new(None),
                 signals: Mutex::new((Vec::new(), Vec::new())),
                 stack: Mutex::new(ThreadStack::default()),
                 #[cfg(feature = "journal")]
@@ -296,9 +499,42 @@ impl WasiThread {
     /// Marks the thread as finished (which will cause anyone that
     /// joined on it to wake up)
     pub fn set_status_finished(&self, res: Result<ExitCode, WasiRuntimeError>) {
+        self.state.cpu_clock.stop();
         self.state.status.set_finished(res.map_err(Arc::new));
     }
 
+    /// Returns the clock that measures the CPU time of this thread
+    pub fn cpu_clock(&self) -> &WasiCpuClock {
+        &self.state.cpu_clock
+    }
+
+    /// Returns what the thread is doing at the moment
+    pub fn activity(&self) -> WasiThreadActivity {
+        if self.try_join().is_some() {
+            WasiThreadActivity::Finished
+        } else if self.state.cpu_clock.is_idle() {
+            self.waiting_on()
+                .map_or(WasiThreadActivity::Idle, WasiThreadActivity::Waiting)
+        } else {
+            WasiThreadActivity::Running
+        }
+    }
+
+    /// Records what the thread is blocked on until the returned guard is
+    /// dropped, which is what hosts see when they inspect the thread
+    pub(crate) fn wait_on(&self, wait: WasiThreadWait) -> WasiThreadWaitGuard {
+        let previous = self.state.waiting_on.lock().unwrap().replace(wait);
+        WasiThreadWaitGuard {
+            state: self.state.clone(),
+            previous,
+        }
+    }
+
+    /// Returns what the thread is blocked on (if anything)
+    pub fn waiting_on(&self) -> Option<WasiThreadWait> {
+        *self.state.waiting_on.lock().unwrap()
+    }
+
     /// Waits until the thread is finished or the timeout is reached
     pub async fn join(&self) -> Result<ExitCode, Arc<WasiRuntimeError>> {
         self.state.status.await_termination().await
@@ -531,6 +767,7 @@ impl Drop for WasiThreadHandleProtected {
             let mut inner = inner.0.lock().unwrap();
             if let Some(ctrl) = inner.threads.remove(&id) {
                 ctrl.set_status_finished(Ok(Errno::Success.into()));
+                inner.finished_cpu_time += ctrl.cpu_clock().elapsed();
             }
             inner.thread_count -= 1;
         }
@@ -0,0 +1,152 @@
+//! POSIX style timers (`timer_create`) that raise a signal on a process
+//! when they expire, driven by tasks on the [`VirtualTaskManager`]
+
+use std::{sync::Arc, time::Duration};
+
+use wasmer_wasix_types::{
+    types::Signal,
+    wasi::{Errno, Snapshot0Clockid},
+};
+
+use crate::{
+    os::task::{process::WasiProcess, thread::WasiCpuClock},
+    syscalls::platform_clock_time_get,
+    VirtualTaskManager,
+};
+
+/// Represents the ID of a timer (unique within a process)
+pub type WasiTimerId = u32;
+
+/// Clock that a timer measures its expiration against
+#[derive(Debug, Clone)]
+pub enum WasiTimerClock {
+    /// One of the system clocks (realtime or monotonic)
+    System(Snapshot0Clockid),
+    /// CPU time consumed by the process
+    Process,
+    /// CPU time consumed by a thread
+    Thread(WasiCpuClock),
+}
+
+impl WasiTimerClock {
+    /// Returns the current time of the clock
+    pub fn now(&self, process: &WasiProcess) -> Result<Duration, Errno> {
+        match self {
+            Self::System(clock_id) => {
+                let now = platform_clock_time_get(*clock_id, 1)?;
+                Ok(Duration::from_nanos(now as u64))
+            }
+            Self::Process => Ok(process.cpu_time()),
+            Self::Thread(clock) => Ok(clock.elapsed()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WasiTimer {
+    /// Clock that the timer runs on
+    pub clock: WasiTimerClock,
+    /// Signal raised when the timer expires
+    pub signal: Signal,
+    /// Time on the clock when the timer expires next (none when disarmed)
+    pub deadline: Option<Duration>,
+    /// Time between the expirations of a periodic timer (zero for one-shot timers)
+    pub interval: Duration,
+    /// Number of expirations that were missed the last time the timer expired
+    pub overrun: u32,
+    /// Incremented every time the timer is armed so that the task driving
+    /// a previous arming of the timer stops
+    pub(crate) generation: u64,
+}
+
+impl WasiTimer {
+    pub fn new(clock: WasiTimerClock, signal: Signal) -> Self {
+        Self {
+            clock,
+            signal,
+            deadline: None,
+            interval: Duration::ZERO,
+            overrun: 0,
+            generation: 0,
+        }
+    }
+}
+
+/// What the task driving a timer does next
+enum TimerStep {
+    /// The timer expired and raises its signal
+    Expire(Signal),
+    /// The timer expires after this time on its clock
+    Wait(Duration),
+    /// The timer was disarmed, re-armed or deleted
+    Stop,
+}
+
+/// Drives an armed timer until it is disarmed, re-armed or deleted,
+/// or until the process exits
+pub(crate) async fn run_timer(
+    process: WasiProcess,
+    tasks: Arc<dyn VirtualTaskManager>,
+    id: WasiTimerId,
+    generation: u64,
+) {
+    loop {
+        let clock = {
+            let inner = process.lock();
+            match inner.timers.get(&id) {
+                Some(timer) if timer.generation == generation => timer.clock.clone(),
+                _ => return,
+            }
+        };
+        let now = match clock.now(&process) {
+            Ok(now) => now,
+            Err(_) => return,
+        };
+
+        match step_timer(&process, id, generation, now) {
+            TimerStep::Expire(signal) => {
+                tracing::trace!(pid = %process.pid(), %id, ?signal, "timer expired");
+                process.signal_process(signal);
+            }
+            TimerStep::Wait(wait) => {
+                // The CPU time of a process advances at most as fast as the
+                // number of threads that it has running
+                let wait = match clock {
+                    WasiTimerClock::Process => wait / process.active_threads().max(1),
+                    _ => wait,
+                };
+                tasks.sleep_now(wait).await;
+
+                if process.try_join().is_some() {
+                    return;
+                }
+            }
+            TimerStep::Stop => return,
+        }
+    }
+}
+
+fn step_timer(process: &WasiProcess, id: WasiTimerId, generation: u64, now: Duration) -> TimerStep {
+    let mut inner = process.lock();
+    let timer = match inner.timers.get_mut(&id) {
+        Some(timer) if timer.generation == generation => timer,
+        _ => return TimerStep::Stop,
+    };
+    let deadline = match timer.deadline {
+        Some(deadline) => deadline,
+        None => return TimerStep::Stop,
+    };
+    if now < deadline {
+        return TimerStep::Wait(deadline - now);
+    }
+
+    if timer.interval.is_zero() {
+        timer.deadline = None;
+    } else {
+        let missed = (now - deadline).as_nanos() / timer.interval.as_nanos();
+        let missed = missed.min(u32::MAX as u128 - 1) as u32;
+        timer.overrun = missed;
+        timer.deadline = Some(deadline + timer.interval * (missed + 1));
+    }
+    TimerStep::Expire(timer.signal)
+}
@@ -8,12 +8,20 @@ use futures::future::BoxFuture;
 use virtual_fs::{AsyncWriteExt, NullFile, VirtualFile};
 use wasmer_wasix_types::wasi::{Signal, Snapshot0Clockid};
 
-use crate::syscalls::platform_clock_time_get;
+use crate::{
+    os::task::{
+        control_plane::{WasiControlPlane, WasiControlPlaneHandle},
+        signal::SignalDeliveryError,
+    },
+    syscalls::platform_clock_time_get,
+    WasiProcessId,
+};
 
 use super::task::signal::SignalHandlerAbi;
 
 const TTY_MOBILE_PAUSE: u128 = std::time::Duration::from_millis(200).as_nanos();
 
+pub mod pty;
 pub mod tty_sys;
 
 #[derive(Debug)]
@@ -226,10 +234,23 @@ impl Tty {
         })
     }
 
-    fn on_ctrl_c(mut self, _data: Cow<'static, [u8]>) -> BoxFuture<'static, Self> {
+    fn on_ctrl_c(self, _data: Cow<'static, [u8]>) -> BoxFuture<'static, Self> {
+        self.on_signal(Signal::Sigint)
+    }
+
+    fn on_ctrl_z(self, _data: Cow<'static, [u8]>) -> BoxFuture<'static, Self> {
+        self.on_signal(Signal::Sigtstp)
+    }
+
+    fn on_ctrl_backslash(self, _data: Cow<'static, [u8]>) -> BoxFuture<'static, Self> {
+        self.on_signal(Signal::Sigquit)
+    }
+
+    /// Raises a signal on behalf of the terminal and discards the current line
+    fn on_signal(mut self, signal: Signal) -> BoxFuture<'static, Self> {
         Box::pin(async move {
             if let Some(signaler) = self.signaler.as_ref() {
-                signaler.signal(Signal::Sigint as u8).ok();
+                signaler.signal(signal as u8).ok();
 
                 let (echo, _line_buffering) = {
                     let options = self.options.inner.lock().unwrap();
@@ -364,6 +385,8 @@ impl Tty {
             return match String::from_utf8_lossy(data.as_ref()).as_ref() {
                 "\r" | "\u{000A}" => self.on_enter(data),
                 "\u{0003}" => self.on_ctrl_c(data),
+                "\u{001A}" => self.on_ctrl_z(data),
+                "\u{001C}" => self.on_ctrl_backslash(data),
                 "\u{007F}" => self.on_backspace(data),
                 "\u{0009}" => self.on_tab(data),
                 "\u{001B}\u{005B}\u{0044}" => self.on_cursor_left(data),
@@ -412,6 +435,38 @@ impl Tty {
     }
 }
 
+/// Delivers the signals raised by a terminal (e.g. Ctrl-C) to the
+/// foreground process group of the session that the terminal controls,
+/// which is how job control shells expect them to be delivered
+///
+/// It is attached to a [`Tty`] with [`Tty::set_signaler`]
+#[derive(Debug, Clone)]
+pub struct TtyJobControl {
+    control_plane: WasiControlPlaneHandle,
+    sid: WasiProcessId,
+}
+
+impl TtyJobControl {
+    /// Creates the job control for the session led by a process,
+    /// usually the shell that was started on the terminal
+    pub fn new(control_plane: &WasiControlPlane, sid: WasiProcessId) -> Self {
+        Self {
+            control_plane: control_plane.handle(),
+            sid,
+        }
+    }
+}
+
+impl SignalHandlerAbi for TtyJobControl {
+    fn signal(&self, signal: u8) -> Result<(), SignalDeliveryError> {
+        let signal: Signal = signal.try_into().map_err(|_| SignalDeliveryError)?;
+        let control_plane = self.control_plane.upgrade().ok_or(SignalDeliveryError)?;
+        control_plane
+            .signal_foreground_group(self.sid, signal)
+            .map_err(|_| SignalDeliveryError)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct WasiTtyState {
     pub cols: u32,
@@ -0,0 +1,659 @@
+//! Pseudo-terminals (`/dev/ptmx`, `openpty`) that guests can allocate
+//! themselves, e.g. to run a shell behind a terminal multiplexer or an
+//! SSH server
+//!
+//! A pseudo-terminal is a pair of connected files: whatever is written to
+//! the master end passes through the line discipline of a [`Tty`] (echo,
+//! line editing and signal characters) before the slave end can read it,
+//! and whatever is written to the slave end can be read from the master end
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    io,
+    path::{Component, Path},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    task::{Context, Poll},
+};
+
+use derivative::Derivative;
+use futures::{future::BoxFuture, FutureExt};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use virtual_fs::{FsError, Pipe, VirtualFile};
+use wasmer_wasix_types::wasi::Signal;
+
+use super::{InputEvent, Tty, TtyJobControl, TtyOptions, WasiTtyState};
+use crate::{
+    os::task::{control_plane::WasiControlPlane, signal::SignalHandlerAbi},
+    WasiProcessId,
+};
+
+/// Returns true if a path is `/dev/ptmx`, every time it is opened a new
+/// pseudo-terminal is allocated and its master end is returned
+pub(crate) fn is_ptmx(path: &Path) -> bool {
+    path.components()
+        .filter(|component| !matches!(component, Component::RootDir))
+        .eq([
+            Component::Normal(OsStr::new("dev")),
+            Component::Normal(OsStr::new("ptmx")),
+        ])
+}
+
+/// Pseudo-terminals that were allocated on a control plane, they are
+/// numbered like the entries of `/dev/pts`
+#[derive(Debug, Default)]
+pub struct PtyRegistry {
+    ptys: Mutex<BTreeMap<u32, Weak<PtyShared>>>,
+}
+
+impl PtyRegistry {
+    /// Allocates a new pseudo-terminal and returns its master end, it
+    /// reuses the lowest number that is no longer in use
+    pub fn allocate(&self) -> PtyMaster {
+        let mut ptys = self.ptys.lock().unwrap();
+        ptys.retain(|_, shared| shared.strong_count() > 0);
+        let index = (0..)
+            .zip(ptys.keys())
+            .find(|(index, used)| index != *used)
+            .map(|(index, _)| index)
+            .unwrap_or(ptys.len() as u32);
+
+        let master = PtyMaster::new(index);
+        ptys.insert(index, Arc::downgrade(&master.shared));
+        master
+    }
+
+    /// Returns the numbers of the pseudo-terminals whose master end is
+    /// still open
+    pub fn indexes(&self) -> Vec<u32> {
+        self.ptys
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, shared)| {
+                shared
+                    .upgrade()
+                    .map(|shared| !shared.master_closed.load(Ordering::SeqCst))
+                    .unwrap_or(false)
+            })
+            .map(|(index, _)| *index)
+            .collect()
+    }
+
+    /// Opens the slave end of a pseudo-terminal (`/dev/pts/N`)
+    pub fn open_slave(&self, index: u32) -> Result<PtySlave, FsError> {
+        let shared = self
+            .ptys
+            .lock()
+            .unwrap()
+            .get(&index)
+            .and_then(|shared| shared.upgrade())
+            .ok_or(FsError::EntryNotFound)?;
+        if shared.master_closed.load(Ordering::SeqCst) {
+            return Err(FsError::EntryNotFound);
+        }
+        PtyShared::open_slave(&shared)
+    }
+}
+
+/// State that is shared by both ends of a pseudo-terminal
+#[derive(Debug)]
+struct PtyShared {
+    index: u32,
+    /// Line discipline that processes the input written to the master end,
+    /// it is taken out while the input is processed
+    tty: Mutex<Option<Tty>>,
+    options: TtyOptions,
+    state: Mutex<WasiTtyState>,
+    /// Slave end of the pipe, each opened slave end uses a copy of it
+    slave: Pipe,
+    /// Delivers the signals to the session that the terminal controls
+    job_control: Mutex<Option<TtyJobControl>>,
+    /// Number of slave ends that are open
+    slaves: AtomicUsize,
+    /// Set once the last slave end was closed, the master end then reads
+    /// the end of the file
+    hung_up: AtomicBool,
+    /// Set once the master end was closed, the slave end can then no
+    /// longer be opened
+    master_closed: AtomicBool,
+}
+
+impl PtyShared {
+    /// Opens the slave end of the pseudo-terminal, which fails once all the
+    /// slave ends were closed as the terminal hung up
+    fn open_slave(shared: &Arc<Self>) -> Result<PtySlave, FsError> {
+        if shared.hung_up.load(Ordering::SeqCst) {
+            return Err(FsError::BrokenPipe);
+        }
+        shared.slaves.fetch_add(1, Ordering::SeqCst);
+        Ok(PtySlave {
+            shared: shared.clone(),
+            pipe: shared.slave.clone(),
+        })
+    }
+}
+
+/// Handle to a pseudo-terminal that is used to change its settings
+#[derive(Debug, Clone)]
+pub struct Pty {
+    shared: Arc<PtyShared>,
+}
+
+impl Pty {
+    /// Returns the pseudo-terminal that a file is one of the ends of
+    pub fn of(file: &(dyn VirtualFile + Send + Sync + 'static)) -> Option<Self> {
+        let file = file.upcast_any_ref();
+        if let Some(master) = file.downcast_ref::<PtyMaster>() {
+            return Some(master.pty());
+        }
+        file.downcast_ref::<PtySlave>().map(|slave| slave.pty())
+    }
+
+    /// Number of the pseudo-terminal (`N` in `/dev/pts/N`)
+    pub fn index(&self) -> u32 {
+        self.shared.index
+    }
+
+    /// Returns the window size and the line discipline settings
+    pub fn state(&self) -> WasiTtyState {
+        self.shared.state.lock().unwrap().clone()
+    }
+
+    /// Changes the window size and the line discipline settings, resizing
+    /// the window raises `SIGWINCH` in the foreground process group of the
+    /// session that the terminal controls
+    pub fn set_state(&self, state: WasiTtyState) {
+        let resized = {
+            let mut current = self.shared.state.lock().unwrap();
+            let resized = (current.cols, current.rows, current.width, current.height)
+                != (state.cols, state.rows, state.width, state.height);
+            *current = state.clone();
+            resized
+        };
+
+        let options = &self.shared.options;
+        options.set_cols(state.cols);
+        options.set_rows(state.rows);
+        options.set_echo(state.echo);
+        options.set_line_buffering(state.line_buffered);
+        options.set_line_feeds(state.line_feeds);
+
+        if resized {
+            let job_control = self.shared.job_control.lock().unwrap().clone();
+            if let Some(job_control) = job_control {
+                job_control.signal(Signal::Sigwinch as u8).ok();
+            }
+        }
+    }
+
+    /// Makes the pseudo-terminal the controlling terminal of a session,
+    /// the signal characters (e.g. Ctrl-C) are then delivered to the
+    /// foreground process group of that session
+    pub fn set_controlling_session(&self, control_plane: &WasiControlPlane, sid: WasiProcessId) {
+        let job_control = TtyJobControl::new(control_plane, sid);
+        self.shared
+            .job_control
+            .lock()
+            .unwrap()
+            .replace(job_control.clone());
+        if let Some(tty) = self.shared.tty.lock().unwrap().as_mut() {
+            tty.set_signaler(Box::new(job_control));
+        }
+    }
+}
+
+/// Master end of a pseudo-terminal, it is held by the program that emulates
+/// the terminal (e.g. a terminal multiplexer or an SSH server)
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct PtyMaster {
+    shared: Arc<PtyShared>,
+    /// Reads the output of the slave end, writing to it sends the input
+    /// to the slave end (which is what the line discipline does)
+    pipe: Pipe,
+    /// Output that was read from the pipe but not yet from the master end
+    pending: Vec<u8>,
+    /// Input that the line discipline is still processing, it hands the
+    /// terminal back once it is done
+    #[derivative(Debug = "ignore")]
+    processing: Mutex<Option<BoxFuture<'static, Tty>>>,
+}
+
+/// Slave end of a pseudo-terminal, it is the terminal of the programs that
+/// run on the pseudo-terminal
+#[derive(Debug)]
+pub struct PtySlave {
+    shared: Arc<PtyShared>,
+    pipe: Pipe,
+}
+
+impl PtyMaster {
+    /// Creates a new pseudo-terminal and returns its master end, they are
+    /// allocated with [`PtyRegistry::allocate`]
+    fn new(index: u32) -> Self {
+        let (master, slave) = Pipe::channel();
+
+        let options = TtyOptions::default();
+        let state = WasiTtyState {
+            cols: options.cols(),
+            rows: options.rows(),
+            width: 0,
+            height: 0,
+            stdin_tty: true,
+            stdout_tty: true,
+            stderr_tty: true,
+            echo: options.echo(),
+            line_buffered: options.line_buffering(),
+            line_feeds: options.line_feeds(),
+        };
+        // The line discipline sends the input to the slave end and echoes
+        // it back to the master end
+        let tty = Tty::new(
+            Box::new(master.clone()),
+            Box::new(slave.clone()),
+            false,
+            options.clone(),
+        );
+
+        Self {
+            shared: Arc::new(PtyShared {
+                index,
+                tty: Mutex::new(Some(tty)),
+                options,
+                state: Mutex::new(state),
+                slave,
+                job_control: Mutex::new(None),
+                slaves: AtomicUsize::new(0),
+                hung_up: AtomicBool::new(false),
+                master_closed: AtomicBool::new(false),
+            }),
+            pipe: master,
+            pending: Vec::new(),
+            processing: Mutex::new(None),
+        }
+    }
+
+    pub fn pty(&self) -> Pty {
+        Pty {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Opens the slave end of the pseudo-terminal, which fails once all the
+    /// slave ends were closed as the terminal hung up
+    pub fn open_slave(&self) -> Result<PtySlave, FsError> {
+        PtyShared::open_slave(&self.shared)
+    }
+
+    /// Waits for the line discipline to finish processing the previous
+    /// input and hands the terminal back
+    fn poll_processing(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut processing = self.processing.lock().unwrap();
+        if let Some(future) = processing.as_mut() {
+            let tty = futures::ready!(future.poll_unpin(cx));
+            processing.take();
+
+            // The signals may have been redirected while the input was
+            // being processed
+            let mut tty = tty;
+            if let Some(job_control) = self.shared.job_control.lock().unwrap().clone() {
+                tty.set_signaler(Box::new(job_control));
+            }
+            self.shared.tty.lock().unwrap().replace(tty);
+        }
+        Poll::Ready(())
+    }
+
+    /// Runs the input through the line discipline, the input is accepted
+    /// right away while the line discipline processes it in the background
+    /// of the following calls
+    fn poll_write_input(&self, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+        futures::ready!(self.poll_processing(cx));
+
+        let tty = self
+            .shared
+            .tty
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        let keys = if self.shared.options.line_buffering() {
+            split_keys(data).map(|key| key.to_vec()).collect()
+        } else {
+            vec![data.to_vec()]
+        };
+        self.processing
+            .lock()
+            .unwrap()
+            .replace(Box::pin(async move {
+                let mut tty = tty;
+                for key in keys {
+                    tty = tty.on_event(InputEvent::Raw(key)).await;
+                }
+                tty
+            }));
+
+        // Usually the line discipline finishes right away
+        let _ = self.poll_processing(cx);
+        Poll::Ready(Ok(data.len()))
+    }
+}
+
+impl PtySlave {
+    pub fn pty(&self) -> Pty {
+        Pty {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for PtyMaster {
+    fn drop(&mut self) {
+        // The slave ends read the end of the file once the master end is gone
+        self.shared.master_closed.store(true, Ordering::SeqCst);
+        self.pipe.close();
+    }
+}
+
+impl Drop for PtySlave {
+    fn drop(&mut self) {
+        if self.shared.slaves.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shared.hung_up.store(true, Ordering::SeqCst);
+            self.pipe.close();
+        }
+    }
+}
+
+/// Splits input into the keys that the line discipline expects, the escape
+/// sequences of special keys (e.g. the cursor keys) are kept together
+fn split_keys(mut data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    std::iter::from_fn(move || {
+        if data.is_empty() {
+            return None;
+        }
+        let len = match data {
+            // Control sequences end with a byte in the range `@` to `~`
+            [0x1b, b'[', rest @ ..] => rest
+                .iter()
+                .position(|b| (0x40..=0x7e).contains(b))
+                .map(|pos| pos + 3)
+                .unwrap_or(data.len()),
+            [0x1b, b'O', _, ..] => 3,
+            [0xc0..=0xdf, ..] => 2,
+            [0xe0..=0xef, ..] => 3,
+            [0xf0..=0xf7, ..] => 4,
+            _ => 1,
+        }
+        .min(data.len());
+        let (key, rest) = data.split_at(len);
+        data = rest;
+        Some(key)
+    })
+}
+
+/// Translates line feeds into carriage returns followed by line feeds,
+/// which is what terminals need to move to the start of the next line
+fn translate_line_feeds(data: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(data.len());
+    for byte in data {
+        if *byte == b'\n' {
+            ret.push(b'\r');
+        }
+        ret.push(*byte);
+    }
+    ret
+}
+
+impl AsyncRead for PtyMaster {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.pending.is_empty() {
+            let mut chunk = [0u8; 4096];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.pipe).poll_read(cx, &mut chunk_buf) {
+                Poll::Ready(Ok(())) => {}
+                other => return other,
+            }
+            let data = chunk_buf.filled();
+            this.pending = if this.shared.options.line_feeds() {
+                translate_line_feeds(data)
+            } else {
+                data.to_vec()
+            };
+        }
+
+        let read = this.pending.len().min(buf.remaining());
+        buf.put_slice(&this.pending[..read]);
+        this.pending.drain(..read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for PtyMaster {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_input(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_processing(cx).map(Ok)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for PtyMaster {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl VirtualFile for PtyMaster {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> BoxFuture<'static, virtual_fs::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if !this.pending.is_empty() {
+            return Poll::Ready(Ok(this.pending.len()));
+        }
+        Pin::new(&mut this.pipe).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        self.poll_processing(cx).map(|()| Ok(8192))
+    }
+}
+
+impl AsyncRead for PtySlave {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().pipe).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for PtySlave {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().pipe).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for PtySlave {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl VirtualFile for PtySlave {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> BoxFuture<'static, virtual_fs::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().pipe).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().pipe).poll_write_ready(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// Reads as many bytes as the expected output has
+    async fn read_output(file: &mut (impl AsyncRead + Unpin), expected: &str) -> String {
+        let mut buf = vec![0u8; expected.len()];
+        file.read_exact(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+
+    #[test]
+    fn test_split_keys() {
+        let keys = split_keys(b"ab\x1b[A\x1b[15~\x1bOP\xc3\xa9\r").collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                &b"a"[..],
+                b"b",
+                b"\x1b[A",
+                b"\x1b[15~",
+                b"\x1bOP",
+                "\u{e9}".as_bytes(),
+                b"\r"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pty_canonical_mode() {
+        let mut master = PtyRegistry::default().allocate();
+        let mut slave = master.open_slave().unwrap();
+
+        // The input is echoed and only reaches the slave end per line
+        master.write_all(b"ls -l\x7f\x7fa\r").await.unwrap();
+        assert_eq!(read_output(&mut slave, "ls a\n").await, "ls a\n");
+        assert_eq!(
+            read_output(&mut master, "ls -l\u{8} \u{8}\u{8} \u{8}a\r\n").await,
+            "ls -l\u{8} \u{8}\u{8} \u{8}a\r\n"
+        );
+
+        // Line feeds written to the slave end come out as new lines
+        slave.write_all(b"hello\n").await.unwrap();
+        assert_eq!(read_output(&mut master, "hello\r\n").await, "hello\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_pty_raw_mode() {
+        let mut master = PtyRegistry::default().allocate();
+        let mut slave = master.open_slave().unwrap();
+
+        let pty = master.pty();
+        pty.set_state(WasiTtyState {
+            echo: false,
+            line_buffered: false,
+            line_feeds: false,
+            ..pty.state()
+        });
+
+        master.write_all(b"q\x03").await.unwrap();
+        assert_eq!(read_output(&mut slave, "q\x03").await, "q\x03");
+
+        slave.write_all(b"done\n").await.unwrap();
+        assert_eq!(read_output(&mut master, "done\n").await, "done\n");
+    }
+
+    #[tokio::test]
+    async fn test_pty_hang_up() {
+        let mut master = PtyRegistry::default().allocate();
+        let slave = master.open_slave().unwrap();
+        assert_eq!(Pty::of(&slave).unwrap().index(), master.pty().index());
+
+        drop(slave);
+        let mut buf = Vec::new();
+        master.read_to_end(&mut buf).await.unwrap();
+        assert!(buf.is_empty());
+        assert!(master.open_slave().is_err());
+    }
+}
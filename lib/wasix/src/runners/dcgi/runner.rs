@@ -90,7 +90,10 @@ impl crate::runners::Runner for DcgiRunner {
             })
             .collect::<Vec<_>>();
         let runtime = OverriddenRuntime::new(runtime).with_journals(journals);
-        let runtime = Arc::new(runtime) as Arc<DynRuntime>;
+        let runtime = self
+            .config
+            .inner
+            .pooled_runtime(Arc::new(runtime) as Arc<DynRuntime>)?;
 
         //We now pass the runtime to the the handlers
         let handler = self.prepare_handler(command_name, pkg, Arc::clone(&runtime))?;
@@ -12,9 +12,10 @@ use crate::{
     bin_factory::BinaryPackage,
     capabilities::Capabilities,
     journal::{DynJournal, SnapshotTrigger},
+    os::task::credentials::WasiCredentials,
     runners::{wasi_common::CommonWasiOptions, MappedDirectory, MountedDirectory},
     runtime::{module_cache::ModuleHash, task_manager::VirtualTaskManagerExt},
-    Runtime, WasiEnvBuilder, WasiError, WasiRuntimeError,
+    ImportsFactory, Runtime, WasiControlPlane, WasiEnvBuilder, WasiError, WasiRuntimeError,
 };
 
 use super::wasi_common::MappedCommand;
@@ -100,6 +101,30 @@ impl WasiRunner {
         self
     }
 
+    /// Run the WASIX instance as a particular user and primary group
+    /// (root by default).
+    pub fn with_user(&mut self, uid: u32, gid: u32) -> &mut Self {
+        let groups = std::mem::take(&mut self.wasi.credentials.groups);
+        self.wasi.credentials = WasiCredentials {
+            groups,
+            ..WasiCredentials::new(uid, gid)
+        };
+        self
+    }
+
+    /// Set the supplementary groups of the user the WASIX instance runs as.
+    pub fn with_groups(&mut self, groups: impl IntoIterator<Item = u32>) -> &mut Self {
+        self.wasi.credentials.groups = groups.into_iter().collect();
+        self
+    }
+
+    /// Register the processes of the WASIX instance with a control plane
+    /// that the host keeps, so it can inspect and signal them.
+    pub fn with_control_plane(&mut self, control_plane: WasiControlPlane) -> &mut Self {
+        self.wasi.control_plane = Some(control_plane);
+        self
+    }
+
     /// Add a package that should be available to the instance at runtime.
     pub fn with_injected_package(&mut self, pkg: BinaryPackage) -> &mut Self {
         self.wasi.injected_packages.push(pkg);
@@ -217,6 +242,13 @@ impl WasiRunner {
         self
     }
 
+    /// Add imports created in the store of every instance of the module,
+    /// including the instances of the threads it spawns.
+    pub fn with_imports_factory(&mut self, factory: ImportsFactory) -> &mut Self {
+        self.wasi.imports_factories.push(factory);
+        self
+    }
+
     #[tracing::instrument(level = "debug", skip_all)]
     pub(crate) fn prepare_webc_env(
         &self,
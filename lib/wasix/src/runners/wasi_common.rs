@@ -14,8 +14,10 @@ use webc::metadata::annotations::Wasi as WasiAnnotation;
 use crate::{
     bin_factory::BinaryPackage,
     capabilities::Capabilities,
+    fs::populate_user_database,
     journal::{DynJournal, SnapshotTrigger},
-    WasiEnvBuilder,
+    os::task::credentials::WasiCredentials,
+    ImportsFactory, WasiControlPlane, WasiEnvBuilder,
 };
 
 #[derive(Debug, Clone)]
@@ -41,7 +43,10 @@ pub(crate) struct CommonWasiOptions {
     pub(crate) snapshot_on: Vec<SnapshotTrigger>,
     pub(crate) snapshot_interval: Option<std::time::Duration>,
     pub(crate) current_dir: Option<PathBuf>,
+    pub(crate) credentials: WasiCredentials,
+    pub(crate) control_plane: Option<WasiControlPlane>,
     pub(crate) additional_imports: Imports,
+    pub(crate) imports_factories: Vec<ImportsFactory>,
 }
 
 impl CommonWasiOptions {
@@ -53,6 +58,8 @@ impl CommonWasiOptions {
         root_fs: Option<TmpFileSystem>,
     ) -> Result<(), anyhow::Error> {
         let root_fs = root_fs.unwrap_or_else(|| RootFileSystemBuilder::default().build());
+        let user_database = populate_user_database(&root_fs, &self.credentials);
+        builder.set_user_database(user_database);
         let fs = prepare_filesystem(root_fs, &self.mounts, container_fs)?;
 
         builder.add_preopen_dir("/")?;
@@ -79,7 +86,17 @@ impl CommonWasiOptions {
 
         *builder.capabilities_mut() = self.capabilities.clone();
 
+        builder.set_user(self.credentials.uid, self.credentials.gid);
+        builder.set_groups(self.credentials.groups.iter().copied());
+
+        if let Some(control_plane) = &self.control_plane {
+            builder.set_control_plane(control_plane.clone());
+        }
+
         builder.add_imports(&self.additional_imports);
+        for factory in &self.imports_factories {
+            builder.add_imports_factory(factory.clone());
+        }
 
         Ok(())
     }
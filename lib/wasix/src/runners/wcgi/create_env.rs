@@ -35,6 +35,8 @@ pub(crate) async fn default_create_env(conf: CreateEnvConfig) -> anyhow::Result<
             insecure_allow_all: true,
             http_client: HttpClientCapabilityV1::new_allow_all(),
             threading: Default::default(),
+            unix_sockets: Default::default(),
+            limits: Default::default(),
         });
     let env = builder.build()?;
 
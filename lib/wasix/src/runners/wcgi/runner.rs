@@ -6,6 +6,8 @@ use hyper::Body;
 use tower::{make::Shared, Service, ServiceBuilder};
 use tower_http::{catch_panic::CatchPanicLayer, cors::CorsLayer, trace::TraceLayer};
 use tracing::Span;
+#[cfg(unix)]
+use wasmer::sys::{NativeEngineExt, PoolingLimits, PoolingTunables};
 use wcgi_host::CgiDialect;
 use webc::metadata::{
     annotations::{Wasi, Wcgi},
@@ -20,7 +22,7 @@ use crate::{
         wcgi::handler::{Handler, SharedState},
         MappedDirectory,
     },
-    runtime::task_manager::VirtualTaskManagerExt,
+    runtime::{task_manager::VirtualTaskManagerExt, DynRuntime},
     Runtime, WasiEnvBuilder,
 };
 
@@ -169,6 +171,7 @@ impl crate::runners::Runner for WcgiRunner {
         pkg: &BinaryPackage,
         runtime: Arc<dyn Runtime + Send + Sync>,
     ) -> Result<(), Error> {
+        let runtime = self.config.pooled_runtime(runtime)?;
         let handler = self.prepare_handler(
             command_name,
             pkg,
@@ -187,6 +190,8 @@ pub struct Config {
     pub(crate) addr: SocketAddr,
     #[derivative(Debug = "ignore")]
     pub(crate) callbacks: Arc<dyn Callbacks>,
+    #[cfg(unix)]
+    pub(crate) pooling: Option<PoolingLimits>,
 }
 
 impl Config {
@@ -311,6 +316,42 @@ impl Config {
         self.wasi.journals.push(journal);
         self
     }
+
+    /// Allocate the instances handling the requests, and their memories
+    /// and tables, from a pool with the given limits.
+    ///
+    /// The pool bounds the number of requests handled at the same time,
+    /// and recycles the allocations of the instances between requests.
+    #[cfg(unix)]
+    pub fn pooling(&mut self, limits: PoolingLimits) -> &mut Self {
+        self.pooling = Some(limits);
+        self
+    }
+
+    /// Returns a runtime whose engine allocates from the pool, if one was
+    /// configured.
+    pub(crate) fn pooled_runtime(
+        &self,
+        runtime: Arc<DynRuntime>,
+    ) -> Result<Arc<DynRuntime>, Error> {
+        #[cfg(unix)]
+        if let Some(limits) = self.pooling.clone() {
+            let mut engine = runtime.engine();
+            let tunables = PoolingTunables::new(engine.target(), limits)
+                .map_err(|e| anyhow::anyhow!(e))
+                .context("Unable to reserve the instance pool")?;
+            engine.set_tunables(tunables);
+            // Modules are compiled for the memory styles of the pool, so they
+            // are kept apart from the modules of the other engines.
+            let runtime = crate::runtime::OverriddenRuntime::new(runtime)
+                .with_engine(engine)
+                .with_module_cache(Arc::new(
+                    crate::runtime::module_cache::SharedCache::default(),
+                ));
+            return Ok(Arc::new(runtime));
+        }
+        Ok(runtime)
+    }
 }
 
 impl Config {
@@ -322,6 +363,8 @@ impl Config {
             addr: ([127, 0, 0, 1], 8000).into(),
             wasi: CommonWasiOptions::default(),
             callbacks: Arc::new(callbacks),
+            #[cfg(unix)]
+            pooling: None,
         }
     }
 }
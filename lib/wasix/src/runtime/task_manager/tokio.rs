@@ -167,6 +167,9 @@ impl VirtualTaskManager for TokioTaskManager {
                 let result = trigger.await;
                 // Build the task that will go on the callback
                 pool.execute(move || {
+                    // The thread runs on this host thread from now on
+                    ctx.data(&store).thread.cpu_clock().run_on_current_thread();
+
                     // Invoke the callback
                     run(TaskWasmRunProperties {
                         ctx,
@@ -183,6 +186,9 @@ impl VirtualTaskManager for TokioTaskManager {
             self.pool.execute(move || {
                 tracing::trace!("task_wasm started in blocking thread");
 
+                // The thread runs on this host thread from now on
+                ctx.data(&store).thread.cpu_clock().run_on_current_thread();
+
                 // Invoke the callback
                 run(TaskWasmRunProperties {
                     ctx,
@@ -17,7 +17,10 @@ use crate::{
     bin_factory::{BinFactory, BinaryPackage},
     capabilities::Capabilities,
     fs::{WasiFs, WasiFsRoot, WasiInodes},
-    os::task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+    os::task::{
+        control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+        credentials::WasiCredentials,
+    },
     runtime::module_cache::ModuleHash,
     state::WasiState,
     syscalls::{
@@ -27,7 +30,7 @@ use crate::{
     Runtime, WasiEnv, WasiError, WasiFunctionEnv, WasiRuntimeError,
 };
 
-use super::env::WasiEnvInit;
+use super::env::{ImportsFactory, WasiEnvInit};
 
 /// Builder API for configuring a [`WasiEnv`] environment needed to run WASI modules.
 ///
@@ -62,8 +65,14 @@ pub struct WasiEnvBuilder {
     pub(super) stderr: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stdin: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) fs: Option<WasiFsRoot>,
+    pub(super) disable_proc_fs: bool,
+    pub(super) control_plane: Option<WasiControlPlane>,
     pub(super) runtime: Option<Arc<dyn crate::Runtime + Send + Sync + 'static>>,
     pub(super) current_dir: Option<PathBuf>,
+    pub(super) credentials: WasiCredentials,
+    /// The paths of the user database that were synthesized before the
+    /// file system was handed to the builder.
+    pub(super) user_database: Vec<&'static str>,
 
     /// List of webc dependencies to be injected.
     pub(super) uses: Vec<BinaryPackage>,
@@ -75,6 +84,7 @@ pub struct WasiEnvBuilder {
 
     pub(super) capabilites: Capabilities,
     pub(super) additional_imports: Imports,
+    pub(super) imports_factories: Vec<ImportsFactory>,
 
     #[cfg(feature = "journal")]
     pub(super) snapshot_on: Vec<SnapshotTrigger>,
@@ -540,6 +550,32 @@ impl WasiEnvBuilder {
         self
     }
 
+    /// Sets the user and primary group that the process runs as (root by default).
+    pub fn user(mut self, uid: u32, gid: u32) -> Self {
+        self.set_user(uid, gid);
+        self
+    }
+
+    /// Sets the user and primary group that the process runs as (root by default).
+    pub fn set_user(&mut self, uid: u32, gid: u32) {
+        let groups = std::mem::take(&mut self.credentials.groups);
+        self.credentials = WasiCredentials {
+            groups,
+            ..WasiCredentials::new(uid, gid)
+        };
+    }
+
+    /// Sets the supplementary groups of the user that the process runs as.
+    pub fn groups(mut self, groups: impl IntoIterator<Item = u32>) -> Self {
+        self.set_groups(groups);
+        self
+    }
+
+    /// Sets the supplementary groups of the user that the process runs as.
+    pub fn set_groups(&mut self, groups: impl IntoIterator<Item = u32>) {
+        self.credentials.groups = groups.into_iter().collect();
+    }
+
     /// Overwrite the default WASI `stdout`, if you want to hold on to the
     /// original `stdout` use [`WasiFs::swap_file`] after building.
     pub fn stdout(mut self, new_file: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
@@ -601,6 +637,39 @@ impl WasiEnvBuilder {
         self
     }
 
+    /// Enables or disables the `/proc` file system, which describes the
+    /// processes of the instance (enabled by default).
+    pub fn proc_fs(mut self, enabled: bool) -> Self {
+        self.set_proc_fs(enabled);
+        self
+    }
+
+    pub fn set_proc_fs(&mut self, enabled: bool) {
+        self.disable_proc_fs = !enabled;
+    }
+
+    /// Sets the control plane that the processes of the instance are
+    /// registered with, which lets the host inspect and signal them
+    /// (a new one is created by default).
+    ///
+    /// The threading capabilities do not apply to a control plane that
+    /// is passed in, its own configuration does.
+    pub fn control_plane(mut self, control_plane: WasiControlPlane) -> Self {
+        self.set_control_plane(control_plane);
+        self
+    }
+
+    pub fn set_control_plane(&mut self, control_plane: WasiControlPlane) {
+        self.control_plane = Some(control_plane);
+    }
+
+    /// Records the paths of a user database that was synthesized into the
+    /// file system with [`crate::fs::populate_user_database`], so that
+    /// they are owned by root.
+    pub(crate) fn set_user_database(&mut self, created: Vec<&'static str>) {
+        self.user_database = created;
+    }
+
     /// Configure the WASI filesystem before running.
     // TODO: improve ergonomics on this function
     pub fn setup_fs(mut self, setup_fs_fn: SetupFsFn) -> Self {
@@ -677,6 +746,12 @@ impl WasiEnvBuilder {
         self.additional_imports.extend(imports);
     }
 
+    /// Add imports created by `factory` in the store of every instance of
+    /// the process, including the instances of its threads.
+    pub fn add_imports_factory(&mut self, factory: ImportsFactory) {
+        self.imports_factories.push(factory);
+    }
+
     pub fn imports<I, S1, S2, E>(mut self, imports: I) -> Self
     where
         I: IntoIterator<Item = ((S1, S2), E)>,
@@ -761,10 +836,23 @@ impl WasiEnvBuilder {
             .take()
             .unwrap_or_else(|| Box::new(ArcFile::new(Box::<super::Stdin>::default())));
 
-        let fs_backing = self
+        let plane_config = ControlPlaneConfig {
+            max_task_count: self.capabilites.threading.max_threads,
+            enable_asynchronous_threading: self.capabilites.threading.enable_asynchronous_threading,
+        };
+        let control_plane = self
+            .control_plane
+            .take()
+            .unwrap_or_else(|| WasiControlPlane::new(plane_config));
+
+        let mut fs_backing = self
             .fs
             .take()
             .unwrap_or_else(|| WasiFsRoot::Sandbox(Arc::new(TmpFileSystem::new())));
+        if !self.disable_proc_fs {
+            fs_backing = fs_backing.mount_proc(&control_plane);
+        }
+        fs_backing = fs_backing.mount_pts(&control_plane);
 
         if let Some(dir) = &self.current_dir {
             match fs_backing.read_dir(dir) {
@@ -795,6 +883,8 @@ impl WasiEnvBuilder {
             let mut wasi_fs =
                 WasiFs::new_with_preopen(&inodes, &self.preopens, &self.vfs_preopens, fs_backing)
                     .map_err(WasiStateCreationError::WasiFsCreationError)?;
+            wasi_fs.populate_user_database(&self.credentials);
+            wasi_fs.set_user_database_ownership(&self.user_database);
 
             // set up the file system, overriding base files and calling the setup function
             wasi_fs
@@ -865,12 +955,6 @@ impl WasiEnvBuilder {
 
         let capabilities = self.capabilites;
 
-        let plane_config = ControlPlaneConfig {
-            max_task_count: capabilities.threading.max_threads,
-            enable_asynchronous_threading: capabilities.threading.enable_asynchronous_threading,
-        };
-        let control_plane = WasiControlPlane::new(plane_config);
-
         let init = WasiEnvInit {
             state,
             runtime,
@@ -891,6 +975,8 @@ impl WasiEnvBuilder {
             #[cfg(feature = "journal")]
             snapshot_on: self.snapshot_on,
             additional_imports: self.additional_imports,
+            imports_factories: self.imports_factories,
+            credentials: self.credentials,
         };
 
         Ok(init)
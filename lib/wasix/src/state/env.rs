@@ -15,11 +15,11 @@ use virtual_fs::{FileSystem, FsError, StaticFile, VirtualFile};
 use virtual_net::DynVirtualNetworking;
 use wasmer::{
     AsStoreMut, AsStoreRef, FunctionEnvMut, Global, Imports, Instance, Memory, MemoryType,
-    MemoryView, Module, TypedFunction,
+    MemoryView, Module, StoreMut, TypedFunction,
 };
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode, Snapshot0Clockid},
+    wasi::{Errno, ExitCode},
 };
 
 #[cfg(feature = "journal")]
@@ -27,10 +27,12 @@ use crate::journal::{DynJournal, JournalEffector, SnapshotTrigger};
 use crate::{
     bin_factory::{BinFactory, BinaryPackage},
     capabilities::Capabilities,
-    fs::{WasiFsRoot, WasiInodes},
+    fs::{Kind, WasiFsRoot, WasiInodes},
     import_object_for_all_wasi_versions,
     os::task::{
         control_plane::ControlPlaneError,
+        credentials::WasiCredentials,
+        limits::WasiResourceLimits,
         process::{WasiProcess, WasiProcessId},
         thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
     },
@@ -38,7 +40,6 @@ use crate::{
         module_cache::ModuleHash, resolver::PackageSpecifier, task_manager::InlineWaker,
         SpawnMemoryType,
     },
-    syscalls::platform_clock_time_get,
     Runtime, VirtualTaskManager, WasiControlPlane, WasiEnvBuilder, WasiError, WasiFunctionEnv,
     WasiResult, WasiRuntimeError, WasiStateCreationError, WasiVFork,
 };
@@ -212,6 +213,47 @@ impl WasiInstanceHandles {
     }
 }
 
+/// Creates imports in a given store.
+///
+/// Unlike the additional imports, which belong to the store of the main
+/// instance, they are created for every instance of the process,
+/// including its threads and forks which live in stores of their own.
+#[derive(Clone)]
+pub struct ImportsFactory(Arc<dyn Fn(&mut StoreMut<'_>) -> Imports + Send + Sync>);
+
+impl ImportsFactory {
+    pub fn new(factory: impl Fn(&mut StoreMut<'_>) -> Imports + Send + Sync + 'static) -> Self {
+        Self(Arc::new(factory))
+    }
+
+    /// Creates the imports in `store`.
+    pub fn imports(&self, store: &mut impl AsStoreMut) -> Imports {
+        (self.0)(&mut store.as_store_mut())
+    }
+}
+
+/// Defines the imports created by `factories` in `store`, without
+/// overriding the existing ones.
+pub(crate) fn define_factory_imports(
+    factories: &[ImportsFactory],
+    store: &mut impl AsStoreMut,
+    import_object: &mut Imports,
+) {
+    for factory in factories {
+        for ((namespace, name), value) in &factory.imports(store) {
+            if !import_object.exists(&namespace, &name) {
+                import_object.define(&namespace, &name, value);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for ImportsFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImportsFactory").finish_non_exhaustive()
+    }
+}
+
 /// Data required to construct a [`WasiEnv`].
 #[derive(Debug)]
 pub struct WasiEnvInit {
@@ -227,6 +269,9 @@ pub struct WasiEnvInit {
     pub process: Option<WasiProcess>,
     pub thread: Option<WasiThreadHandle>,
 
+    /// User and group identity of the process (when it is created)
+    pub credentials: WasiCredentials,
+
     /// Whether to call the `_initialize` function in the WASI module.
     /// Will be true for regular new instances, but false for threads.
     pub call_initialize: bool,
@@ -241,6 +286,10 @@ pub struct WasiEnvInit {
     /// normal WASIX syscalls.
     pub additional_imports: Imports,
 
+    /// Additional functionality provided to every instance of the process,
+    /// including its threads.
+    pub imports_factories: Vec<ImportsFactory>,
+
     /// Indicates triggers that will cause a snapshot to be taken
     #[cfg(feature = "journal")]
     pub snapshot_on: Vec<SnapshotTrigger>,
@@ -277,12 +326,14 @@ impl WasiEnvInit {
             memory_ty: None,
             process: None,
             thread: None,
+            credentials: self.credentials.clone(),
             call_initialize: self.call_initialize,
             can_deep_sleep: self.can_deep_sleep,
             extra_tracing: false,
             #[cfg(feature = "journal")]
             snapshot_on: self.snapshot_on.clone(),
             additional_imports: self.additional_imports.clone(),
+            imports_factories: self.imports_factories.clone(),
         }
     }
 }
@@ -327,6 +378,9 @@ pub struct WasiEnv {
     /// (this is normally used so that the instance can be reused later on)
     pub(crate) disable_fs_cleanup: bool,
 
+    /// Creates the additional imports of every instance of the process
+    pub(crate) imports_factories: Vec<ImportsFactory>,
+
     /// List of situations that the process will checkpoint on
     #[cfg(feature = "journal")]
     snapshot_on: HashSet<SnapshotTrigger>,
@@ -365,6 +419,7 @@ impl Clone for WasiEnv {
             #[cfg(feature = "journal")]
             snapshot_on: self.snapshot_on.clone(),
             disable_fs_cleanup: self.disable_fs_cleanup,
+            imports_factories: self.imports_factories.clone(),
         }
     }
 }
@@ -377,7 +432,24 @@ impl WasiEnv {
 
     /// Forking the WasiState is used when either fork or vfork is called
     pub fn fork(&self) -> Result<(Self, WasiThreadHandle), ControlPlaneError> {
+        let limits = self.process.resource_limits();
+        let children = self
+            .process
+            .lock()
+            .children
+            .iter()
+            .filter(|child| child.try_join().is_none())
+            .count() as u64;
+        if let Some(max) = limits.processes.soft.filter(|max| children >= *max) {
+            return Err(ControlPlaneError::ProcessLimitReached { max });
+        }
+
         let process = self.control_plane.new_process(self.process.module_hash)?;
+        self.control_plane.inherit_job(&self.process, &process);
+        process.set_credentials(self.process.credentials());
+        if let Err(err) = process.set_resource_limits(self.tasks(), limits) {
+            tracing::warn!("failed to apply the resource limits of the child process - {err}");
+        }
         let handle = process.new_thread(self.layout.clone())?;
 
         let thread = handle.as_thread();
@@ -385,6 +457,13 @@ impl WasiEnv {
 
         let state = Arc::new(self.state.fork());
 
+        // The signal descriptors that the child inherits receive its signals
+        for fd in state.fs.fd_map.read().unwrap().values() {
+            if let Kind::SignalFd { signals, .. } = fd.inode.read().deref() {
+                process.add_signal_fd(signals);
+            }
+        }
+
         let bin_factory = self.bin_factory.clone();
 
         let new_env = Self {
@@ -406,10 +485,19 @@ impl WasiEnv {
             #[cfg(feature = "journal")]
             snapshot_on: self.snapshot_on.clone(),
             disable_fs_cleanup: self.disable_fs_cleanup,
+            imports_factories: self.imports_factories.clone(),
         };
+        new_env.process.set_state(&new_env.state);
         Ok((new_env, handle))
     }
 
+    /// Replaces the state of the environment, for instance to change the
+    /// arguments of a process before it starts
+    pub(crate) fn set_state(&mut self, state: WasiState) {
+        self.state = Arc::new(state);
+        self.process.set_state(&self.state);
+    }
+
     pub fn pid(&self) -> WasiProcessId {
         self.process.pid()
     }
@@ -498,7 +586,13 @@ impl WasiEnv {
         let process = if let Some(p) = init.process {
             p
         } else {
-            init.control_plane.new_process(module_hash)?
+            let process = init.control_plane.new_process(module_hash)?;
+            process.set_credentials(init.credentials);
+            let limits = WasiResourceLimits::from(&init.capabilities.limits);
+            if let Err(err) = process.set_resource_limits(init.runtime.task_manager(), limits) {
+                tracing::warn!("failed to apply the resource limits of the process - {err}");
+            }
+            process
         };
 
         let layout = WasiMemoryLayout::default();
@@ -530,8 +624,10 @@ impl WasiEnv {
             #[cfg(feature = "journal")]
             snapshot_on: init.snapshot_on.into_iter().collect(),
             disable_fs_cleanup: false,
+            imports_factories: init.imports_factories,
         };
         env.owned_handles.push(thread);
+        env.process.set_state(&env.state);
 
         // TODO: should not be here - should be callers responsibility!
         for pkg in &init.webc_dependencies {
@@ -565,8 +661,11 @@ impl WasiEnv {
 
         let env = Self::from_init(init, module_hash)?;
         let pid = env.process.pid();
+        let process = env.process.clone();
 
         let mut store = store.as_store_mut();
+        #[cfg(feature = "sys")]
+        process.attach_store(&mut store);
 
         let tasks = env.runtime.task_manager().clone();
         let mut func_env = WasiFunctionEnv::new(&mut store, env);
@@ -583,6 +682,7 @@ impl WasiEnv {
                 None => SpawnMemoryType::CreateMemory,
             }
         };
+        let spawn_type = process.limit_memory(spawn_type)?;
         let memory = tasks.build_memory(&mut store, spawn_type)?;
 
         // Let's instantiate the module with the imports.
@@ -596,6 +696,8 @@ impl WasiEnv {
                 import_object.define(&namespace, &name, value);
             }
         }
+        let imports_factories = func_env.data(&store).imports_factories.clone();
+        define_factory_imports(&imports_factories, &mut store, &mut import_object);
 
         let imported_memory = if let Some(memory) = memory {
             import_object.define("env", "memory", memory.clone());
@@ -682,6 +784,11 @@ impl WasiEnv {
 
     /// Porcesses any signals that are batched up or any forced exit codes
     pub(crate) fn process_signals_and_exit(ctx: &mut FunctionEnvMut<'_, Self>) -> WasiResult<bool> {
+        // Stopped processes only carry on once they are continued
+        if Self::wait_while_stopped(ctx.data()) {
+            return Self::process_signals_and_exit(ctx);
+        }
+
         // If a signal handler has never been set then we need to handle signals
         // differently
         let env = ctx.data();
@@ -699,10 +806,18 @@ impl WasiEnv {
                     {
                         let exit_code = env.thread.set_or_get_exit_code_for_signal(sig);
                         return Err(WasiError::Exit(exit_code));
+                    } else if sig == Signal::Sigtstp
+                        || sig == Signal::Sigttin
+                        || sig == Signal::Sigttou
+                    {
+                        env.process.stop(sig);
                     } else {
                         tracing::trace!(pid=%env.pid(), ?sig, "Signal ignored");
                     }
                 }
+                if Self::wait_while_stopped(env) {
+                    return Self::process_signals_and_exit(ctx);
+                }
                 return Ok(Ok(true));
             }
         }
@@ -715,6 +830,19 @@ impl WasiEnv {
         Self::process_signals(ctx)
     }
 
+    /// Blocks the calling thread while the process is stopped (e.g. by
+    /// `SIGSTOP`), returns true if it had to wait
+    fn wait_while_stopped(env: &WasiEnv) -> bool {
+        if env.process.stopped().is_none() {
+            return false;
+        }
+        tracing::trace!(pid=%env.pid(), "waiting for the process to be continued");
+        // Stopped processes don't use any CPU time
+        let _idle = env.thread.cpu_clock().idle();
+        InlineWaker::block_on(env.process.wait_until_resumed());
+        true
+    }
+
     /// Porcesses any signals that are batched up
     pub(crate) fn process_signals(ctx: &mut FunctionEnvMut<'_, Self>) -> WasiResult<bool> {
         // If a signal handler has never been set then we need to handle signals
@@ -749,32 +877,7 @@ impl WasiEnv {
             .ok_or_else(|| WasiError::Exit(Errno::Fault.into()))?;
         if let Some(handler) = inner.signal.clone() {
             // We might also have signals that trigger on timers
-            let mut now = 0;
-            {
-                let mut has_signal_interval = false;
-                let inner = env.process.inner.0.lock().unwrap();
-                if !inner.signal_intervals.is_empty() {
-                    now = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1_000_000).unwrap()
-                        as u128;
-                    for signal in inner.signal_intervals.values() {
-                        let elapsed = now - signal.last_signal;
-                        if elapsed >= signal.interval.as_nanos() {
-                            has_signal_interval = true;
-                            break;
-                        }
-                    }
-                }
-                if has_signal_interval {
-                    let mut inner = env.process.inner.0.lock().unwrap();
-                    for signal in inner.signal_intervals.values_mut() {
-                        let elapsed = now - signal.last_signal;
-                        if elapsed >= signal.interval.as_nanos() {
-                            signal.last_signal = now;
-                            signals.push(signal.signal);
-                        }
-                    }
-                }
-            }
+            signals.extend(env.process.pop_interval_signals());
 
             for signal in signals {
                 tracing::trace!(
@@ -11,7 +11,7 @@ use crate::syscalls::restore_snapshot;
 use crate::{
     import_object_for_all_wasi_versions,
     runtime::SpawnMemoryType,
-    state::WasiInstanceHandles,
+    state::{env::define_factory_imports, WasiInstanceHandles},
     utils::{get_wasi_version, get_wasi_versions, store::restore_instance_snapshot},
     InstanceSnapshot, RewindStateOption, WasiEnv, WasiError, WasiRuntimeError, WasiThreadError,
 };
@@ -46,14 +46,19 @@ impl WasiFunctionEnv {
         // Create a new store and put the memory object in it
         // (but only if it has imported memory)
         let mut store = env.runtime.new_store();
+        #[cfg(feature = "sys")]
+        env.process.attach_store(&mut store);
+        let spawn_type = env.process.limit_memory(spawn_type)?;
         let memory = env
             .tasks()
             .build_memory(&mut store.as_store_mut(), spawn_type)?;
 
         // Build the context object and import the memory
+        let imports_factories = env.imports_factories.clone();
         let mut ctx = WasiFunctionEnv::new(&mut store, env);
         let (mut import_object, init) =
             import_object_for_all_wasi_versions(&module, &mut store, &ctx.env);
+        define_factory_imports(&imports_factories, &mut store, &mut import_object);
         if let Some(memory) = memory.clone() {
             import_object.define("env", "memory", memory);
         }
@@ -146,11 +151,17 @@ impl WasiFunctionEnv {
             |v| Ok(v.clone()),
         )?;
 
+        // the memory is measured as it grows from here on (see
+        // `WasiProcess::attach_store`)
+        let memory_size = memory.view(store).data_size();
         let new_inner = WasiInstanceHandles::new(memory, store, instance);
         let stack_pointer = new_inner.stack_pointer.clone();
 
         let env = self.data_mut(store);
         env.set_inner(new_inner);
+        env.process
+            .memory_size
+            .store(memory_size, std::sync::atomic::Ordering::Relaxed);
 
         env.state.fs.set_is_wasix(is_wasix_module);
 
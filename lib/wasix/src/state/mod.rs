@@ -19,6 +19,7 @@ mod builder;
 mod env;
 mod func_env;
 mod handles;
+mod pre_init;
 mod run;
 mod types;
 
@@ -38,8 +39,9 @@ use wasmer_wasix_types::wasi::{Errno, Fd as WasiFd, Rights, Snapshot0Clockid};
 
 pub use self::{
     builder::*,
-    env::{WasiEnv, WasiEnvInit, WasiInstanceHandles},
+    env::{ImportsFactory, WasiEnv, WasiEnvInit, WasiInstanceHandles},
     func_env::WasiFunctionEnv,
+    pre_init::{PreInitError, PreInitializer},
     types::*,
 };
 pub use crate::fs::{InodeGuard, InodeWeakGuard};
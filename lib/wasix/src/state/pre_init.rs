@@ -0,0 +1,514 @@
+//! Pre-initialization of modules: the initialization function of a module
+//! runs once in a WASI environment, and the resulting state of the instance
+//! is written into a new module whose instances start already initialized.
+//!
+//! This cuts the cold starts of the interpreters (Python, QuickJS, ...)
+//! compiled to WebAssembly, which spend most of their startup in
+//! initialization code.
+
+use std::ops::Range;
+
+use thiserror::Error;
+use wasmer::{AsStoreMut, Instance, Memory, MemoryAccessError, Module, Store};
+use wasmparser::{
+    BinaryReaderError, DataKind, Encoding, ExternalKind, MemoryType, Parser, Payload, TypeRef,
+    ValType,
+};
+
+use super::run::wasi_exit_code;
+use crate::{capture_instance_snapshot, WasiEnvBuilder, WasiFunctionEnv, WasiRuntimeError};
+
+/// The memory is captured in regions of this size, like the memory of the
+/// journal snapshots, and the regions that are zeroed are left out.
+const MEMORY_REGION_SIZE: u64 = 65536;
+
+/// The size of a WebAssembly page.
+const WASM_PAGE_SIZE: u64 = 65536;
+
+/// The function that WASI reactors export to initialize themselves, which
+/// runs as the module is instantiated.
+const WASI_INITIALIZE: &str = "_initialize";
+
+const IMPORT_SECTION: u8 = 2;
+const MEMORY_SECTION: u8 = 5;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const DATA_SECTION: u8 = 11;
+const DATA_COUNT_SECTION: u8 = 12;
+
+/// An error raised while pre-initializing a module.
+#[derive(Error, Debug)]
+pub enum PreInitError {
+    #[error("the module is not valid")]
+    InvalidModule(#[from] BinaryReaderError),
+    #[error("the module doesn't export an initialization function named \"{0}\"")]
+    MissingInitFunction(String),
+    #[error("the initialization function failed")]
+    Runtime(#[from] WasiRuntimeError),
+    #[error("reading the memory failed")]
+    Memory(#[from] MemoryAccessError),
+    #[error("the store holds {captured} globals but the module defines {expected}")]
+    GlobalsMismatch { captured: usize, expected: usize },
+    #[error("{0} can't be pre-initialized")]
+    Unsupported(&'static str),
+}
+
+/// Pre-initializes modules, the way [Wizer](https://github.com/bytecodealliance/wizer) does.
+///
+/// The module is instantiated in the WASI environment of a [`WasiEnvBuilder`]
+/// and its initialization function is called. The memory and the mutable
+/// globals of the instance are then captured in a new module: its data
+/// segments hold the non-zero regions of the memory, and its globals are
+/// initialized with the captured values.
+///
+/// The initialization only sees the arguments, environment variables and
+/// pre-opened directories of the builder, which are set up in the order they
+/// were added, so a given builder always produces the same module as long as
+/// the initialization doesn't read the clocks or random numbers. The state of
+/// the WASI environment itself (open files, ...) isn't captured: instances of
+/// the new module should be given the same pre-opened directories, which
+/// libraries like `wasi-libc` record while initializing.
+///
+/// The tables aren't captured either, and the start function and the
+/// `_initialize` export are removed since their effects are part of the
+/// snapshot.
+#[derive(Debug, Clone)]
+pub struct PreInitializer {
+    init_func: String,
+    keep_init_func: bool,
+}
+
+impl PreInitializer {
+    /// Creates a pre-initializer calling the `init_func` export of the
+    /// modules.
+    pub fn new(init_func: impl Into<String>) -> Self {
+        PreInitializer {
+            init_func: init_func.into(),
+            keep_init_func: false,
+        }
+    }
+
+    /// Keeps the initialization function exported by the pre-initialized
+    /// modules, instead of removing its export.
+    pub fn keep_init_func(mut self, keep: bool) -> Self {
+        self.keep_init_func = keep;
+        self
+    }
+
+    /// Instantiates `module` in the environment of `builder`, calls its
+    /// initialization function and returns the pre-initialized module.
+    ///
+    /// The module must be compiled from `wasm`.
+    #[allow(clippy::result_large_err)]
+    pub fn run(
+        &self,
+        builder: WasiEnvBuilder,
+        store: &mut Store,
+        module: Module,
+        wasm: &[u8],
+    ) -> Result<Vec<u8>, PreInitError> {
+        // If no handle or runtime exists then create one
+        #[cfg(feature = "sys-thread")]
+        let _guard = if tokio::runtime::Handle::try_current().is_err() {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            Some(runtime)
+        } else {
+            None
+        };
+        #[cfg(feature = "sys-thread")]
+        let _guard = _guard.as_ref().map(|r| r.enter());
+
+        let (instance, env) = builder.instantiate(module, store)?;
+
+        let init = match instance.exports.get_function(&self.init_func) {
+            Ok(init) => init,
+            Err(_) => {
+                env.on_exit(store, None);
+                return Err(PreInitError::MissingInitFunction(self.init_func.clone()));
+            }
+        };
+        // The `_initialize` function of reactors already ran while the
+        // module was instantiated
+        let result = if self.init_func == WASI_INITIALIZE {
+            Ok(())
+        } else {
+            env.data(&store).thread.set_status_running();
+            crate::run_wasi_func_start(init, store)
+        };
+        let (result, exit_code) = wasi_exit_code(result);
+
+        let snapshot = result
+            .map_err(PreInitError::from)
+            .and_then(|()| self.snapshot(wasm, store, &instance, &env));
+        env.on_exit(store, Some(exit_code));
+        snapshot
+    }
+
+    /// Returns a module whose instances start in the state of the initialized
+    /// `instance` of `wasm` held by `store`.
+    ///
+    /// The store must not hold the objects of other instances, because the
+    /// globals of the instance are captured as an [`InstanceSnapshot`](crate::InstanceSnapshot).
+    /// The memory of the instance has to be exported, or imported as the
+    /// memory of the WASI environment `env`.
+    #[allow(clippy::result_large_err)]
+    pub fn snapshot(
+        &self,
+        wasm: &[u8],
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+        env: &WasiFunctionEnv,
+    ) -> Result<Vec<u8>, PreInitError> {
+        let globals = capture_instance_snapshot(store).globals;
+
+        let mut memory = CapturedMemory::default();
+        if let Some(instance_memory) = instance_memory(&*store, instance, env) {
+            memory.captured = true;
+            let view = instance_memory.view(&store);
+            let size = view.data_size();
+            memory.pages = size / WASM_PAGE_SIZE;
+
+            let mut cur = 0u64;
+            while cur < size {
+                let end = size.min(cur + MEMORY_REGION_SIZE);
+                memory.add_region(cur, &view.copy_range_to_vec(cur..end)?);
+                cur = end;
+            }
+        }
+
+        self.write_module(wasm, &globals, &memory)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn write_module(
+        &self,
+        wasm: &[u8],
+        globals: &[u128],
+        memory: &CapturedMemory,
+    ) -> Result<Vec<u8>, PreInitError> {
+        let mut output = Vec::with_capacity(wasm.len() + memory.len());
+        let mut num_globals = 0;
+        let mut num_memories = 0;
+        let mut memory64 = false;
+        let mut has_data_section = false;
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding, range, ..
+                } => {
+                    if encoding != Encoding::Module {
+                        return Err(PreInitError::Unsupported("components"));
+                    }
+                    output.extend_from_slice(&wasm[range]);
+                }
+                Payload::ImportSection(reader) => {
+                    let range = reader.range();
+                    let mut section = Vec::new();
+                    write_u32(&mut section, reader.count());
+                    let imports = reader
+                        .into_iter_with_offsets()
+                        .collect::<Result<Vec<_>, _>>()?;
+                    for (index, (offset, import)) in imports.iter().enumerate() {
+                        let end = imports.get(index + 1).map_or(range.end, |(end, _)| *end);
+                        match import.ty {
+                            TypeRef::Memory(ty) => {
+                                num_memories += 1;
+                                memory64 = ty.memory64;
+                                write_name(&mut section, import.module);
+                                write_name(&mut section, import.name);
+                                section.push(0x02);
+                                write_memory_type(&mut section, ty, memory.pages);
+                            }
+                            TypeRef::Global(_) => {
+                                num_globals += 1;
+                                section.extend_from_slice(&wasm[*offset..end]);
+                            }
+                            _ => section.extend_from_slice(&wasm[*offset..end]),
+                        }
+                    }
+                    write_section(&mut output, IMPORT_SECTION, &section);
+                }
+                Payload::MemorySection(reader) => {
+                    let mut section = Vec::new();
+                    write_u32(&mut section, reader.count());
+                    for ty in reader {
+                        let ty = ty?;
+                        num_memories += 1;
+                        memory64 = ty.memory64;
+                        write_memory_type(&mut section, ty, memory.pages);
+                    }
+                    write_section(&mut output, MEMORY_SECTION, &section);
+                }
+                Payload::GlobalSection(reader) => {
+                    let range = reader.range();
+                    let mut section = Vec::new();
+                    write_u32(&mut section, reader.count());
+                    let defined = reader
+                        .into_iter_with_offsets()
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let expected = num_globals + defined.len();
+                    if globals.len() != expected {
+                        return Err(PreInitError::GlobalsMismatch {
+                            captured: globals.len(),
+                            expected,
+                        });
+                    }
+                    for (index, (offset, global)) in defined.iter().enumerate() {
+                        let end = defined.get(index + 1).map_or(range.end, |(end, _)| *end);
+                        if global.ty.mutable {
+                            let init_expr =
+                                global.init_expr.get_binary_reader().original_position();
+                            section.extend_from_slice(&wasm[*offset..init_expr]);
+                            write_const(
+                                &mut section,
+                                global.ty.content_type,
+                                globals[num_globals + index],
+                            )?;
+                        } else {
+                            section.extend_from_slice(&wasm[*offset..end]);
+                        }
+                    }
+                    write_section(&mut output, GLOBAL_SECTION, &section);
+                }
+                Payload::ExportSection(reader) => {
+                    let range = reader.range();
+                    let exports = reader
+                        .into_iter_with_offsets()
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let mut kept = Vec::new();
+                    for (index, (offset, export)) in exports.iter().enumerate() {
+                        let end = exports.get(index + 1).map_or(range.end, |(end, _)| *end);
+                        let removed = export.kind == ExternalKind::Func
+                            && (export.name == WASI_INITIALIZE
+                                || (export.name == self.init_func && !self.keep_init_func));
+                        if !removed {
+                            kept.push(&wasm[*offset..end]);
+                        }
+                    }
+                    let mut section = Vec::new();
+                    write_u32(&mut section, kept.len() as u32);
+                    kept.iter()
+                        .for_each(|export| section.extend_from_slice(export));
+                    write_section(&mut output, EXPORT_SECTION, &section);
+                }
+                // The start function already ran
+                Payload::StartSection { .. } => {}
+                Payload::DataCountSection { count, .. } => {
+                    let mut section = Vec::new();
+                    write_u32(&mut section, count + memory.regions.len() as u32);
+                    write_section(&mut output, DATA_COUNT_SECTION, &section);
+                }
+                Payload::DataSection(reader) => {
+                    has_data_section = true;
+                    let mut section = Vec::new();
+                    write_u32(&mut section, reader.count() + memory.regions.len() as u32);
+                    for data in reader {
+                        let data = data?;
+                        match data.kind {
+                            // The active segments were copied into the memory,
+                            // they are kept empty so the indexes of the passive
+                            // ones don't change
+                            DataKind::Active { .. } => write_data(&mut section, memory64, 0, &[]),
+                            DataKind::Passive => section.extend_from_slice(&wasm[data.range]),
+                        }
+                    }
+                    memory.write_segments(&mut section, memory64);
+                    write_section(&mut output, DATA_SECTION, &section);
+                }
+                Payload::End(_) => {}
+                payload => {
+                    if let Some((id, range)) = payload.as_section() {
+                        write_section(&mut output, id, &wasm[range]);
+                    }
+                }
+            }
+        }
+
+        if num_memories > 1 {
+            return Err(PreInitError::Unsupported("modules with multiple memories"));
+        }
+        if num_memories == 1 && !memory.captured {
+            return Err(PreInitError::Unsupported(
+                "modules which neither export nor import their memory",
+            ));
+        }
+        if !has_data_section && !memory.regions.is_empty() {
+            let mut section = Vec::new();
+            write_u32(&mut section, memory.regions.len() as u32);
+            memory.write_segments(&mut section, memory64);
+            write_section(&mut output, DATA_SECTION, &section);
+        }
+
+        Ok(output)
+    }
+}
+
+/// The memory of an initialized instance.
+#[derive(Debug, Default)]
+struct CapturedMemory {
+    /// Whether the memory of the instance was found and captured.
+    captured: bool,
+    /// The size of the memory, in pages.
+    pages: u64,
+    /// The non-zero regions of the memory, in order.
+    regions: Vec<(u64, Vec<u8>)>,
+}
+
+impl CapturedMemory {
+    /// Adds the non-zero part of the region of `data` at `offset`, merging it
+    /// with the previous region when they are contiguous.
+    fn add_region(&mut self, offset: u64, data: &[u8]) {
+        let Some(range) = non_zero_range(data) else {
+            return;
+        };
+        let start = offset + range.start as u64;
+        match self.regions.last_mut() {
+            Some((last, bytes)) if *last + bytes.len() as u64 == start => {
+                bytes.extend_from_slice(&data[range]);
+            }
+            _ => self.regions.push((start, data[range].to_vec())),
+        }
+    }
+
+    /// The number of bytes captured.
+    fn len(&self) -> usize {
+        self.regions.iter().map(|(_, bytes)| bytes.len()).sum()
+    }
+
+    fn write_segments(&self, section: &mut Vec<u8>, memory64: bool) {
+        for (offset, bytes) in &self.regions {
+            write_data(section, memory64, *offset, bytes);
+        }
+    }
+}
+
+/// Returns the memory of `instance`: its exported memory, or else the
+/// imported memory which the WASI environment uses.
+fn instance_memory(
+    store: &impl AsStoreMut,
+    instance: &Instance,
+    env: &WasiFunctionEnv,
+) -> Option<Memory> {
+    let module = instance.module();
+    if let Some(export) = module.exports().memories().next() {
+        return instance.exports.get_memory(export.name()).ok().cloned();
+    }
+    if module.imports().memories().next().is_some() {
+        return env.data(store).try_memory().cloned();
+    }
+    None
+}
+
+/// Returns the range of `data` between its first and last non-zero bytes.
+fn non_zero_range(data: &[u8]) -> Option<Range<usize>> {
+    let start = data.iter().position(|byte| *byte != 0)?;
+    let end = data.iter().rposition(|byte| *byte != 0)? + 1;
+    Some(start..end)
+}
+
+fn write_section(output: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    output.push(id);
+    write_u32(output, contents.len() as u32);
+    output.extend_from_slice(contents);
+}
+
+fn write_name(output: &mut Vec<u8>, name: &str) {
+    write_u32(output, name.len() as u32);
+    output.extend_from_slice(name.as_bytes());
+}
+
+/// Writes a memory type, growing its minimum to `pages`.
+fn write_memory_type(output: &mut Vec<u8>, ty: MemoryType, pages: u64) {
+    let mut flags = 0;
+    if ty.maximum.is_some() {
+        flags |= 0x01;
+    }
+    if ty.shared {
+        flags |= 0x02;
+    }
+    if ty.memory64 {
+        flags |= 0x04;
+    }
+    output.push(flags);
+    write_u64(output, ty.initial.max(pages));
+    if let Some(maximum) = ty.maximum {
+        write_u64(output, maximum);
+    }
+}
+
+/// Writes an active segment of the first memory.
+fn write_data(output: &mut Vec<u8>, memory64: bool, offset: u64, bytes: &[u8]) {
+    output.push(0x00);
+    if memory64 {
+        output.push(0x42);
+        write_i64(output, offset as i64);
+    } else {
+        output.push(0x41);
+        write_i64(output, offset as u32 as i32 as i64);
+    }
+    output.push(0x0b);
+    write_u32(output, bytes.len() as u32);
+    output.extend_from_slice(bytes);
+}
+
+/// Writes the constant expression of a global holding the raw `value`.
+#[allow(clippy::result_large_err)]
+fn write_const(output: &mut Vec<u8>, ty: ValType, value: u128) -> Result<(), PreInitError> {
+    match ty {
+        ValType::I32 => {
+            output.push(0x41);
+            write_i64(output, value as u32 as i32 as i64);
+        }
+        ValType::I64 => {
+            output.push(0x42);
+            write_i64(output, value as u64 as i64);
+        }
+        ValType::F32 => {
+            output.push(0x43);
+            output.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        ValType::F64 => {
+            output.push(0x44);
+            output.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+        ValType::V128 => {
+            output.extend_from_slice(&[0xfd, 0x0c]);
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+        ValType::Ref(_) => return Err(PreInitError::Unsupported("mutable reference globals")),
+    }
+    output.push(0x0b);
+    Ok(())
+}
+
+fn write_u32(output: &mut Vec<u8>, value: u32) {
+    write_u64(output, value as u64)
+}
+
+fn write_u64(output: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            output.push(byte);
+            return;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+fn write_i64(output: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            output.push(byte);
+            return;
+        }
+        output.push(byte | 0x80);
+    }
+}
@@ -395,10 +395,52 @@ pub unsafe fn restore_snapshot(
             crate::journal::JournalEntry::ChangeDirectoryV1 { path } => {
                 JournalEffector::apply_chdir(&mut ctx, &path).map_err(anyhow_err_to_runtime_err)?;
             }
+            crate::journal::JournalEntry::ReceiveFileDescriptorV1 {
+                fd,
+                fs_rights_base,
+                fs_rights_inheriting,
+                fs_flags,
+            } => JournalEffector::apply_fd_receive(
+                &mut ctx,
+                fd,
+                fs_rights_base,
+                fs_rights_inheriting,
+                fs_flags,
+            )
+            .map_err(anyhow_err_to_runtime_err)?,
             crate::journal::JournalEntry::CreatePipeV1 { fd1, fd2 } => {
                 JournalEffector::apply_fd_pipe(&mut ctx, fd1, fd2)
                     .map_err(anyhow_err_to_runtime_err)?;
             }
+            crate::journal::JournalEntry::CreatePtyV1 { master, slave } => {
+                JournalEffector::apply_pty_open(&mut ctx, master, slave)
+                    .map_err(anyhow_err_to_runtime_err)?;
+            }
+            crate::journal::JournalEntry::OpenPtySlaveV1 { master, slave } => {
+                JournalEffector::apply_pty_open_slave(&mut ctx, master, slave)
+                    .map_err(anyhow_err_to_runtime_err)?;
+            }
+            crate::journal::JournalEntry::CreateTimerFdV1 {
+                fd,
+                clock_id,
+                flags,
+            } => {
+                JournalEffector::apply_timerfd_create(&mut ctx, fd, clock_id, flags)
+                    .map_err(anyhow_err_to_runtime_err)?;
+            }
+            crate::journal::JournalEntry::SetTimerFdV1 {
+                fd,
+                value,
+                interval,
+                absolute,
+            } => {
+                JournalEffector::apply_timerfd_settime(&mut ctx, fd, value, interval, absolute)
+                    .map_err(anyhow_err_to_runtime_err)?;
+            }
+            crate::journal::JournalEntry::CreateSignalFdV1 { fd, mask, flags } => {
+                JournalEffector::apply_signalfd(&mut ctx, fd, mask, flags)
+                    .map_err(anyhow_err_to_runtime_err)?;
+            }
             crate::journal::JournalEntry::EpollCreateV1 { fd } => {
                 JournalEffector::apply_epoll_create(&mut ctx, fd)
                     .map_err(anyhow_err_to_runtime_err)?;
@@ -499,6 +541,18 @@ pub unsafe fn restore_snapshot(
                 JournalEffector::apply_sock_connect(&mut ctx, fd, addr)
                     .map_err(anyhow_err_to_runtime_err)?
             }
+            crate::journal::JournalEntry::SocketBindUnixV1 { fd, path } => {
+                JournalEffector::apply_sock_bind_unix(&mut ctx, fd, &path)
+                    .map_err(anyhow_err_to_runtime_err)?
+            }
+            crate::journal::JournalEntry::SocketConnectedUnixV1 { fd, path } => {
+                JournalEffector::apply_sock_connect_unix(&mut ctx, fd, &path)
+                    .map_err(anyhow_err_to_runtime_err)?
+            }
+            crate::journal::JournalEntry::SocketPairV1 { ty, fd1, fd2 } => {
+                JournalEffector::apply_sock_pair(&mut ctx, ty, fd1, fd2)
+                    .map_err(anyhow_err_to_runtime_err)?
+            }
             crate::journal::JournalEntry::SocketAcceptedV1 {
                 listen_fd,
                 fd,
@@ -41,7 +41,7 @@ pub(crate) use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     num::NonZeroU64,
     ops::{Deref, DerefMut},
-    path::Path,
+    path::{Path, PathBuf},
     pin::Pin,
     sync::{
         atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
@@ -95,7 +95,7 @@ pub(crate) use self::types::{
 use self::{state::WasiInstanceGuardMemory, utils::WasiDummyWaker};
 pub(crate) use crate::os::task::{
     process::{WasiProcessId, WasiProcessWait},
-    thread::{WasiThread, WasiThreadId},
+    thread::{WasiThread, WasiThreadId, WasiThreadWait},
 };
 pub(crate) use crate::{
     bin_factory::spawn_exec_module,
@@ -103,7 +103,8 @@ pub(crate) use crate::{
     net::{
         read_ip_port,
         socket::{InodeHttpSocketType, InodeSocket, InodeSocketKind},
-        write_ip_port,
+        unix::{UnixDatagramSocket, UnixPipeSocket},
+        write_ip_port, write_unix_port,
     },
     runtime::SpawnMemoryType,
     state::{
@@ -336,9 +337,10 @@ where
         }
     }
 
-    // Block on the work
+    // Block on the work (the thread is idle meanwhile)
     let mut pinned_work = Box::pin(work);
     let tasks = env.tasks().clone();
+    let _idle = env.thread.cpu_clock().idle();
     let poller = Poller { ctx, pinned_work };
     block_on_with_timeout(&tasks, timeout, poller)
 }
@@ -426,6 +428,10 @@ where
         .map(|i| !i.signal_set)
         .unwrap_or(true);
 
+    // The thread is idle while it waits on the work
+    let cpu_clock = ctx.data().thread.cpu_clock().clone();
+    cpu_clock.set_idle(true);
+
     // Box up the trigger
     let mut trigger = Box::pin(work);
 
@@ -474,7 +480,13 @@ where
 
     // Block until the work is finished or until we
     // unload the thread using asyncify
-    InlineWaker::block_on(work)
+    let ret = InlineWaker::block_on(work);
+
+    // Threads that went into a deep sleep stay idle until they are rewound
+    if !matches!(ret, Ok(AsyncifyAction::Unwind)) {
+        cpu_clock.set_idle(false);
+    }
+    ret
 }
 
 /// Asyncify takes the current thread and blocks on the async runtime associated with it
@@ -519,6 +531,7 @@ where
 
     // Block until the work is finished or until we
     // unload the thread using asyncify
+    let _idle = env.thread.cpu_clock().idle();
     Ok(InlineWaker::block_on(work))
 }
 
@@ -1288,6 +1301,9 @@ where
 
     // If the stack has been restored
     if let Some(result) = ctx.data_mut().thread.take_rewind() {
+        // The thread is running again after its deep sleep
+        ctx.data().thread.cpu_clock().set_idle(false);
+
         // Deserialize the result
         let memory_stack = result.memory_stack;
 
@@ -1322,7 +1338,7 @@ pub(crate) fn _prepare_wasi(wasi_env: &mut WasiEnv, args: Option<Vec<String>>) {
     if let Some(args) = args {
         let mut wasi_state = wasi_env.state.fork();
         wasi_state.args = args;
-        wasi_env.state = Arc::new(wasi_state);
+        wasi_env.set_state(wasi_state);
     }
 
     // Close any files after the STDERR that are not preopened
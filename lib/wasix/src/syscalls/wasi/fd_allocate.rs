@@ -63,7 +63,10 @@ pub(crate) fn fd_allocate_internal(
                 buffer.resize(new_size as usize, 0);
             }
             Kind::Symlink { .. } => return Err(Errno::Badf),
-            Kind::EventNotifications { .. } | Kind::Epoll { .. } => return Err(Errno::Badf),
+            Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
+            | Kind::Epoll { .. } => return Err(Errno::Badf),
             Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
         }
     }
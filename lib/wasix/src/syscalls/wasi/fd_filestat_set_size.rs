@@ -1,5 +1,5 @@
 use super::*;
-use crate::syscalls::*;
+use crate::{os::task::limits::WasiResource, syscalls::*};
 
 /// ### `fd_filestat_set_size()`
 /// Change the size of an open file, zeroing out any new bytes
@@ -41,6 +41,17 @@ pub(crate) fn fd_filestat_set_size_internal(
     if !fd_entry.rights.contains(Rights::FD_FILESTAT_SET_SIZE) {
         return Err(Errno::Access);
     }
+    state
+        .fs
+        .check_inode_access(&inode, &env.process.credentials(), false, true)?;
+    if env
+        .process
+        .resource_limit(WasiResource::FileSize)
+        .exceeded_by(st_size)
+    {
+        env.thread.signal(Signal::Sigxfsz);
+        return Err(Errno::Fbig);
+    }
 
     {
         let mut guard = inode.write();
@@ -59,7 +70,10 @@ pub(crate) fn fd_filestat_set_size_internal(
             Kind::Socket { .. } => return Err(Errno::Badf),
             Kind::Pipe { .. } => return Err(Errno::Badf),
             Kind::Symlink { .. } => return Err(Errno::Badf),
-            Kind::EventNotifications { .. } | Kind::Epoll { .. } => return Err(Errno::Badf),
+            Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
+            | Kind::Epoll { .. } => return Err(Errno::Badf),
             Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
         }
     }
@@ -50,6 +50,9 @@ pub(crate) fn fd_filestat_set_times_internal(
     if !fd_entry.rights.contains(Rights::FD_FILESTAT_SET_TIMES) {
         return Err(Errno::Access);
     }
+    state
+        .fs
+        .check_inode_access(&fd_entry.inode, &env.process.credentials(), false, true)?;
 
     if (fst_flags.contains(Fstflags::SET_ATIM) && fst_flags.contains(Fstflags::SET_ATIM_NOW))
         || (fst_flags.contains(Fstflags::SET_MTIM) && fst_flags.contains(Fstflags::SET_MTIM_NOW))
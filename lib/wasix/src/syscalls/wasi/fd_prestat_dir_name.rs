@@ -41,6 +41,8 @@ pub fn fd_prestat_dir_name<M: MemorySize>(
         | Kind::Socket { .. }
         | Kind::Pipe { .. }
         | Kind::EventNotifications { .. }
+        | Kind::TimerFd { .. }
+        | Kind::SignalFd { .. }
         | Kind::Epoll { .. } => Errno::Notdir,
     }
 }
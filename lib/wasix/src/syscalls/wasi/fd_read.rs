@@ -4,7 +4,7 @@ use virtual_fs::{AsyncReadExt, DeviceFile, ReadBuf};
 
 use super::*;
 use crate::{
-    fs::NotificationInner,
+    fs::{signal_fd_siginfo, NotificationInner, SignalFdInner, SIGNALFD_SIGINFO_SIZE},
     journal::SnapshotTrigger,
     net::socket::TimeType,
     os::task::process::{MaybeCheckpointResult, WasiProcessCheckpoint, WasiProcessInner},
@@ -129,6 +129,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
     let state = env.state();
+    let _wait = env.thread.wait_on(WasiThreadWait::Fd { fd });
 
     let fd_entry = wasi_try_ok_ok!(state.fs.get_fd(fd));
     let is_stdio = fd_entry.is_stdio;
@@ -336,7 +337,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     // TODO: verify
                     return Ok(Err(Errno::Isdir));
                 }
-                Kind::EventNotifications { inner } => {
+                Kind::EventNotifications { inner } | Kind::TimerFd { inner, .. } => {
                     // Create a poller
                     struct NotifyPoller {
                         inner: Arc<NotificationInner>,
@@ -377,6 +378,69 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     let ret = wasi_try_ok_ok!(read_bytes(&reader[..], &memory, iovs_arr));
                     (ret, false)
                 }
+                Kind::SignalFd { signals, .. } => {
+                    // Every signal is read as a record of a fixed size, as
+                    // many signals are read as there are records that fit
+                    let max_signals = {
+                        let memory = unsafe { env.memory_view(ctx) };
+                        let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, iovs_len));
+                        let iovs_arr = wasi_try_mem_ok_ok!(iovs_arr.access());
+                        let buf_len: u64 = iovs_arr
+                            .iter()
+                            .map(|iovs| -> u64 { iovs.buf_len.into() })
+                            .sum();
+                        buf_len as usize / SIGNALFD_SIGINFO_SIZE
+                    };
+                    if max_signals == 0 {
+                        return Ok(Err(Errno::Inval));
+                    }
+
+                    // Create a poller
+                    struct SignalPoller {
+                        signals: Arc<SignalFdInner>,
+                        non_blocking: bool,
+                    }
+                    let poller = SignalPoller {
+                        signals: signals.clone(),
+                        non_blocking: fd_flags.contains(Fdflags::NONBLOCK),
+                    };
+                    let signals = signals.clone();
+
+                    drop(guard);
+
+                    // The poller will register itself for notifications and wait for
+                    // a signal to be queued
+                    impl Future for SignalPoller {
+                        type Output = Result<Signal, Errno>;
+                        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                            if self.non_blocking {
+                                Poll::Ready(self.signals.try_pop().ok_or(Errno::Again))
+                            } else {
+                                self.signals.pop(cx.waker()).map(Ok)
+                            }
+                        }
+                    }
+
+                    // Yield until a signal is received
+                    let res = __asyncify_light(env, None, poller)?.map_err(|err| match err {
+                        Errno::Timedout => Errno::Again,
+                        a => a,
+                    });
+                    let signal = wasi_try_ok_ok!(res);
+
+                    let mut reader = signal_fd_siginfo(signal).to_vec();
+                    while reader.len() < max_signals * SIGNALFD_SIGINFO_SIZE {
+                        match signals.try_pop() {
+                            Some(signal) => reader.extend(signal_fd_siginfo(signal)),
+                            None => break,
+                        }
+                    }
+
+                    let memory = unsafe { env.memory_view(ctx) };
+                    let iovs_arr = wasi_try_mem_ok_ok!(iovs.slice(&memory, iovs_len));
+                    let ret = wasi_try_ok_ok!(read_bytes(&reader[..], &memory, iovs_arr));
+                    (ret, false)
+                }
                 Kind::Symlink { .. } | Kind::Epoll { .. } => {
                     return Ok(Err(Errno::Notsup));
                 }
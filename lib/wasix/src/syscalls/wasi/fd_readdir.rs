@@ -98,6 +98,8 @@ pub fn fd_readdir<M: MemorySize>(
             | Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => return Errno::Notdir,
         }
     };
@@ -119,6 +119,8 @@ pub(crate) fn fd_seek_internal(
                 | Kind::Socket { .. }
                 | Kind::Pipe { .. }
                 | Kind::EventNotifications { .. }
+                | Kind::TimerFd { .. }
+                | Kind::SignalFd { .. }
                 | Kind::Epoll { .. } => {
                     // TODO: check this
                     return Ok(Err(Errno::Inval));
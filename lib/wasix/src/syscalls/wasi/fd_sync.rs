@@ -65,6 +65,8 @@ pub fn fd_sync(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno
             | Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => return Ok(Errno::Inval),
         }
     }
@@ -6,7 +6,7 @@ use crate::{
     journal::{JournalEffector, JournalEntry},
     utils::map_snapshot_err,
 };
-use crate::{net::socket::TimeType, syscalls::*};
+use crate::{net::socket::TimeType, os::task::limits::WasiResource, syscalls::*};
 
 /// ### `fd_write()`
 /// Write data to the file descriptor
@@ -128,6 +128,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
 ) -> Result<Result<usize, Errno>, WasiError> {
     let mut env = ctx.data();
     let state = env.state.clone();
+    let _wait = env.thread.wait_on(WasiThreadWait::Fd { fd });
 
     let fd_entry = wasi_try_ok_ok!(state.fs.get_fd(fd));
     let is_stdio = fd_entry.is_stdio;
@@ -136,6 +137,14 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
         if !is_stdio && !fd_entry.rights.contains(Rights::FD_WRITE) {
             return Ok(Err(Errno::Access));
         }
+        if !is_stdio && fd_entry.open_flags & Fd::ACCESS_CHECKED == 0 {
+            wasi_try_ok_ok!(state.fs.check_inode_access(
+                &fd_entry.inode,
+                &env.process.credentials(),
+                false,
+                true
+            ));
+        }
 
         let fd_flags = fd_entry.flags;
         let mut memory = unsafe { env.memory_view(&ctx) };
@@ -149,6 +158,12 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                         let handle = handle.clone();
                         drop(guard);
 
+                        let max_size = if is_stdio {
+                            None
+                        } else {
+                            env.process.resource_limit(WasiResource::FileSize).soft
+                        };
+
                         let res = __asyncify_light(
                             env,
                             if fd_entry.flags.contains(Fdflags::NONBLOCK) {
@@ -180,12 +195,24 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                                 .map_err(mem_error_to_wasi)?
                                                 .access()
                                                 .map_err(mem_error_to_wasi)?;
-                                            let local_written =
-                                                match handle.write(buf.as_ref()).await {
-                                                    Ok(s) => s,
-                                                    Err(_) if written > 0 => break,
-                                                    Err(err) => return Err(map_io_err(err)),
-                                                };
+                                            let mut buf = buf.as_ref();
+                                            if let Some(max_size) = max_size {
+                                                // Only what fits under the limit is written
+                                                let room = max_size
+                                                    .saturating_sub(offset + written as u64);
+                                                if room == 0 && !buf.is_empty() {
+                                                    if written > 0 {
+                                                        break;
+                                                    }
+                                                    return Err(Errno::Fbig);
+                                                }
+                                                buf = &buf[..(buf.len() as u64).min(room) as usize];
+                                            }
+                                            let local_written = match handle.write(buf).await {
+                                                Ok(s) => s,
+                                                Err(_) if written > 0 => break,
+                                                Err(err) => return Err(map_io_err(err)),
+                                            };
                                             written += local_written;
                                             if local_written != buf.len() {
                                                 break;
@@ -193,6 +220,11 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                         }
                                     }
                                     FdWriteSource::Buffer(data) => {
+                                        if max_size.map_or(false, |max_size| {
+                                            offset + data.len() as u64 > max_size
+                                        }) {
+                                            return Err(Errno::Fbig);
+                                        }
                                         handle.write_all(data).await?;
                                         written += data.len();
                                     }
@@ -204,7 +236,11 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                 Ok(written)
                             },
                         );
-                        let written = wasi_try_ok_ok!(res?.map_err(|err| match err {
+                        let res = res?;
+                        if res == Err(Errno::Fbig) {
+                            env.thread.signal(Signal::Sigxfsz);
+                        }
+                        let written = wasi_try_ok_ok!(res.map_err(|err| match err {
                             Errno::Timedout => Errno::Again,
                             a => a,
                         }));
@@ -359,7 +395,10 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
 
                     (written, false, true)
                 }
-                Kind::Symlink { .. } | Kind::Epoll { .. } => return Ok(Err(Errno::Inval)),
+                Kind::Symlink { .. }
+                | Kind::Epoll { .. }
+                | Kind::TimerFd { .. }
+                | Kind::SignalFd { .. } => return Ok(Err(Errno::Inval)),
                 Kind::Buffer { buffer } => {
                     let mut written = 0usize;
 
@@ -121,7 +121,17 @@ pub(crate) fn path_create_directory_internal(
                             return Err(Errno::Notdir);
                         }
                     } else {
+                        // Creating an entry requires write access to the directory
+                        state.fs.check_inode_access(
+                            &cur_dir_inode,
+                            &env.process.credentials(),
+                            false,
+                            true,
+                        )?;
                         state.fs_create_dir(&adjusted_path)?;
+                        state
+                            .fs
+                            .set_creator(&adjusted_path, &env.process.credentials(), 0o755);
                     }
                     let kind = Kind::Dir {
                         parent: cur_dir_inode.downgrade(),
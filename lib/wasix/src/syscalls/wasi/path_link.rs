@@ -110,6 +110,12 @@ pub(crate) fn path_link_internal(
         state
             .fs
             .get_parent_inode_at_path(inodes, new_fd, &target_path_arg, false)?;
+    state.fs.check_inode_access(
+        &target_parent_inode,
+        &env.process.credentials(),
+        false,
+        true,
+    )?;
 
     if source_inode.stat.write().unwrap().st_nlink == Linkcount::max_value() {
         return Err(Errno::Mlink);
@@ -130,6 +136,8 @@ pub(crate) fn path_link_internal(
             | Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => return Err(Errno::Notdir),
         }
     }
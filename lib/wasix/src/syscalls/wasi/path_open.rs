@@ -124,6 +124,7 @@ pub(crate) fn path_open_internal(
     let (memory, mut state, mut inodes) =
         unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
 
+    let credentials = env.process.credentials();
     let path_arg = std::path::PathBuf::from(&path);
     let maybe_inode = state.fs.get_inode_at_path(
         inodes,
@@ -217,6 +218,17 @@ pub(crate) fn path_open_internal(
                     assert!(handle.is_some());
                     return Ok(Ok(*special_fd));
                 }
+                if crate::os::tty::pty::is_ptmx(path) {
+                    // Every open of `/dev/ptmx` allocates a new pseudo-terminal
+                    let master = Box::new(env.process.compute.must_upgrade().ptys().allocate());
+                    return Ok(pty_create_fd(
+                        state,
+                        inodes,
+                        master,
+                        "ptmx".to_string(),
+                        fs_flags,
+                    ));
+                }
                 if o_flags.contains(Oflags::DIRECTORY) {
                     return Ok(Err(Errno::Notdir));
                 }
@@ -224,6 +236,17 @@ pub(crate) fn path_open_internal(
                     return Ok(Err(Errno::Exist));
                 }
 
+                // The user must be allowed the access it asked for
+                let wants_write = fs_rights_base.contains(Rights::FD_WRITE)
+                    || fs_flags.contains(Fdflags::APPEND)
+                    || o_flags.contains(Oflags::TRUNC);
+                wasi_try_ok_ok!(state.fs.check_access(
+                    path,
+                    &credentials,
+                    fs_rights_base.contains(Rights::FD_READ),
+                    wants_write
+                ));
+
                 let open_options = open_options
                     .write(minimum_rights.write)
                     .create(minimum_rights.create)
@@ -272,6 +295,8 @@ pub(crate) fn path_open_internal(
             | Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => {}
             Kind::Symlink {
                 base_po_dir,
@@ -299,6 +324,9 @@ pub(crate) fn path_open_internal(
                     &path_arg,
                     dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0
                 ));
+            wasi_try_ok_ok!(state
+                .fs
+                .check_inode_access(&parent_inode, &credentials, false, true));
             let new_file_host_path = {
                 let guard = parent_inode.read();
                 match guard.deref() {
@@ -342,6 +370,10 @@ pub(crate) fn path_open_internal(
                     .map_err(|e| { fs_error_into_wasi_err(e) })))
             };
 
+            state
+                .fs
+                .set_creator(&new_file_host_path, &credentials, 0o644);
+
             let new_inode = {
                 let kind = Kind::File {
                     handle: handle.map(|a| Arc::new(std::sync::RwLock::new(a))),
@@ -375,7 +407,7 @@ pub(crate) fn path_open_internal(
         adjusted_rights,
         fs_rights_inheriting,
         fs_flags,
-        open_flags,
+        open_flags | Fd::ACCESS_CHECKED,
         inode
     ));
 
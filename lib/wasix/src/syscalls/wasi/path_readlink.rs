@@ -49,25 +49,27 @@ pub fn path_readlink<M: MemorySize>(
 
     {
         let guard = inode.read();
-        if let Kind::Symlink { relative_path, .. } = guard.deref() {
-            let rel_path_str = relative_path.to_string_lossy();
-            let buf_len: u64 = buf_len.into();
-            let bytes = rel_path_str.bytes();
-            if bytes.len() as u64 >= buf_len {
-                return Errno::Overflow;
+        let rel_path_str = match guard.deref() {
+            Kind::Symlink { relative_path, .. } => relative_path.to_string_lossy().into_owned(),
+            // the entries of `/proc` that behave as symlinks hold their target
+            Kind::File { path, .. } if crate::fs::proc_fs::is_link(path) => {
+                wasi_try!(crate::fs::proc_fs::read_link(&state.fs.root_fs, path))
             }
-            let bytes: Vec<_> = bytes.collect();
+            _ => return Errno::Inval,
+        };
+        let buf_len: u64 = buf_len.into();
+        let bytes = rel_path_str.bytes();
+        if bytes.len() as u64 >= buf_len {
+            return Errno::Overflow;
+        }
+        let bytes: Vec<_> = bytes.collect();
 
-            let out = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(bytes.len()))));
-            wasi_try_mem!(out.write_slice(&bytes));
-            // should we null terminate this?
+        let out = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(bytes.len()))));
+        wasi_try_mem!(out.write_slice(&bytes));
+        // should we null terminate this?
 
-            let bytes_len: M::Offset =
-                wasi_try!(bytes.len().try_into().map_err(|_| Errno::Overflow));
-            wasi_try_mem!(buf_used.deref(&memory).write(bytes_len));
-        } else {
-            return Errno::Inval;
-        }
+        let bytes_len: M::Offset = wasi_try!(bytes.len().try_into().map_err(|_| Errno::Overflow));
+        wasi_try_mem!(buf_used.deref(&memory).write(bytes_len));
     }
 
     Errno::Success
@@ -54,6 +54,9 @@ pub(crate) fn path_remove_directory_internal(
         state
             .fs
             .get_parent_inode_at_path(inodes, fd, std::path::Path::new(&path), false)?;
+    state
+        .fs
+        .check_inode_access(&parent_inode, &env.process.credentials(), false, true)?;
 
     let host_path_to_remove = {
         let guard = inode.read();
@@ -89,7 +92,7 @@ pub(crate) fn path_remove_directory_internal(
 
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
-    if let Err(err) = state.fs_remove_dir(host_path_to_remove) {
+    if let Err(err) = state.fs_remove_dir(&host_path_to_remove) {
         // reinsert to prevent FS from being in bad state
         let mut guard = parent_inode.write();
         if let Kind::Dir {
@@ -100,6 +103,7 @@ pub(crate) fn path_remove_directory_internal(
         }
         return Err(err);
     }
+    state.fs.remove_ownership(&host_path_to_remove);
 
     Ok(())
 }
@@ -92,6 +92,15 @@ pub fn path_rename_internal(
         Path::new(target_path),
         true
     ));
+    // Moving an entry requires write access to both directories
+    let credentials = env.process.credentials();
+    wasi_try_ok!(state
+        .fs
+        .check_inode_access(&source_parent_inode, &credentials, false, true));
+    wasi_try_ok!(state
+        .fs
+        .check_inode_access(&target_parent_inode, &credentials, false, true));
+
     let mut need_create = true;
     let host_adjusted_target_path = {
         let guard = target_parent_inode.read();
@@ -108,6 +117,8 @@ pub fn path_rename_internal(
             Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => return Ok(Errno::Inval),
             Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
                 debug!("fatal internal logic error: parent of inode is not a directory");
@@ -126,6 +137,8 @@ pub fn path_rename_internal(
             Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => {
                 return Ok(Errno::Inval);
             }
@@ -162,6 +175,7 @@ pub fn path_rename_internal(
                     {
                         let mut guard = source_entry.write();
                         if let Kind::File { ref mut path, .. } = guard.deref_mut() {
+                            state.fs.rename_ownership(&path, &host_adjusted_target_path);
                             *path = host_adjusted_target_path;
                         } else {
                             unreachable!()
@@ -187,6 +201,7 @@ pub fn path_rename_internal(
                     drop(guard);
                     let mut guard = source_entry.write();
                     if let Kind::Dir { path, .. } = guard.deref_mut() {
+                        state.fs.rename_ownership(&path, &host_adjusted_target_path);
                         *path = host_adjusted_target_path;
                     }
                 }
@@ -197,6 +212,8 @@ pub fn path_rename_internal(
             Kind::Pipe { .. } => {}
             Kind::Epoll { .. } => {}
             Kind::EventNotifications { .. } => {}
+            Kind::TimerFd { .. } => {}
+            Kind::SignalFd { .. } => {}
             Kind::Root { .. } => unreachable!("The root can not be moved"),
         }
     }
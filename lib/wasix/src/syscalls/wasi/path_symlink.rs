@@ -85,6 +85,12 @@ pub fn path_symlink_internal(
         state
             .fs
             .get_parent_inode_at_path(inodes, fd, new_path_path, true)?;
+    state.fs.check_inode_access(
+        &target_parent_inode,
+        &env.process.credentials(),
+        false,
+        true,
+    )?;
 
     // short circuit if anything is wrong, before we create an inode
     {
@@ -99,6 +105,8 @@ pub fn path_symlink_internal(
             Kind::Socket { .. }
             | Kind::Pipe { .. }
             | Kind::EventNotifications { .. }
+            | Kind::TimerFd { .. }
+            | Kind::SignalFd { .. }
             | Kind::Epoll { .. } => return Err(Errno::Inval),
             Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => {
                 unreachable!("get_parent_inode_at_path returned something other than a Dir or Root")
@@ -65,6 +65,12 @@ pub(crate) fn path_unlink_file_internal(
         std::path::Path::new(path),
         false
     ));
+    wasi_try_ok!(state.fs.check_inode_access(
+        &parent_inode,
+        &env.process.credentials(),
+        false,
+        true
+    ));
 
     let removed_inode = {
         let mut guard = parent_inode.write();
@@ -95,6 +101,12 @@ pub(crate) fn path_unlink_file_internal(
             let mut guard = removed_inode.read();
             match guard.deref() {
                 Kind::File { handle, path, .. } => {
+                    // A UNIX socket that was bound to the file is not
+                    // reachable anymore
+                    if let Some(control_plane) = env.process.compute.upgrade() {
+                        control_plane.unix_sockets().unbind(path);
+                    }
+                    state.fs.remove_ownership(path);
                     if let Some(h) = handle {
                         let mut h = h.write().unwrap();
                         let state = state;
@@ -439,6 +439,7 @@ where
     }
 
     // We use asyncify with a deep sleep to wait on new IO events
+    let _wait = ctx.data().thread.wait_on(WasiThreadWait::Poll);
     let res = __asyncify_with_deep_sleep::<M, Result<Vec<EventResult>, Errno>, _>(
         ctx,
         Duration::from_millis(50),
@@ -201,6 +201,7 @@ pub fn epoll_wait<'a, M: MemorySize + 'static>(
     }
 
     // We use asyncify with a deep sleep to wait on new IO events
+    let _wait = ctx.data().thread.wait_on(WasiThreadWait::Poll);
     let res = __asyncify_with_deep_sleep::<M, Result<Vec<(EpollFd, EpollType)>, Errno>, _>(
         ctx,
         Duration::from_millis(50),
@@ -21,20 +21,54 @@ mod port_route_clear;
 mod port_route_list;
 mod port_route_remove;
 mod port_unbridge;
+mod proc_dup_fd;
 mod proc_exec;
 mod proc_fork;
+mod proc_getegid;
+mod proc_geteuid;
+mod proc_getgid;
+mod proc_getgroups;
+mod proc_getpgid;
+mod proc_getrlimit;
+mod proc_getsid;
+mod proc_getuid;
 mod proc_id;
 mod proc_join;
 mod proc_parent;
+mod proc_rusage;
+mod proc_setegid;
+mod proc_seteuid;
+mod proc_setgid;
+mod proc_setgroups;
+mod proc_setpgid;
+mod proc_setrlimit;
+mod proc_setsid;
+mod proc_setuid;
 mod proc_signal;
+mod proc_signal_group;
 mod proc_spawn;
+mod proc_timer_create;
+mod proc_timer_delete;
+mod proc_timer_getoverrun;
+mod proc_timer_gettime;
+mod proc_timer_settime;
+mod pty_get;
+mod pty_open;
+mod pty_open_slave;
+mod pty_set;
+mod pty_set_ctty;
 mod resolve;
 mod sched_yield;
+mod signalfd;
 mod sock_accept;
 mod sock_addr_local;
+mod sock_addr_local_unix;
 mod sock_addr_peer;
+mod sock_addr_peer_unix;
 mod sock_bind;
+mod sock_bind_unix;
 mod sock_connect;
+mod sock_connect_unix;
 mod sock_get_opt_flag;
 mod sock_get_opt_size;
 mod sock_get_opt_time;
@@ -44,11 +78,16 @@ mod sock_leave_multicast_v4;
 mod sock_leave_multicast_v6;
 mod sock_listen;
 mod sock_open;
+mod sock_pair;
 mod sock_recv;
+mod sock_recv_fds;
 mod sock_recv_from;
+mod sock_recv_from_unix;
 mod sock_send;
+mod sock_send_fds;
 mod sock_send_file;
 mod sock_send_to;
+mod sock_send_to_unix;
 mod sock_set_opt_flag;
 mod sock_set_opt_size;
 mod sock_set_opt_time;
@@ -63,8 +102,13 @@ mod thread_parallelism;
 mod thread_signal;
 mod thread_sleep;
 mod thread_spawn;
+mod timerfd_create;
+mod timerfd_gettime;
+mod timerfd_settime;
 mod tty_get;
+mod tty_get_pgrp;
 mod tty_set;
+mod tty_set_pgrp;
 
 pub use callback_signal::*;
 pub use chdir::*;
@@ -89,20 +133,54 @@ pub use port_route_clear::*;
 pub use port_route_list::*;
 pub use port_route_remove::*;
 pub use port_unbridge::*;
+pub use proc_dup_fd::*;
 pub use proc_exec::*;
 pub use proc_fork::*;
+pub use proc_getegid::*;
+pub use proc_geteuid::*;
+pub use proc_getgid::*;
+pub use proc_getgroups::*;
+pub use proc_getpgid::*;
+pub use proc_getrlimit::*;
+pub use proc_getsid::*;
+pub use proc_getuid::*;
 pub use proc_id::*;
 pub use proc_join::*;
 pub use proc_parent::*;
+pub use proc_rusage::*;
+pub use proc_setegid::*;
+pub use proc_seteuid::*;
+pub use proc_setgid::*;
+pub use proc_setgroups::*;
+pub use proc_setpgid::*;
+pub use proc_setrlimit::*;
+pub use proc_setsid::*;
+pub use proc_setuid::*;
 pub use proc_signal::*;
+pub use proc_signal_group::*;
 pub use proc_spawn::*;
+pub use proc_timer_create::*;
+pub use proc_timer_delete::*;
+pub use proc_timer_getoverrun::*;
+pub use proc_timer_gettime::*;
+pub use proc_timer_settime::*;
+pub use pty_get::*;
+pub use pty_open::*;
+pub use pty_open_slave::*;
+pub use pty_set::*;
+pub use pty_set_ctty::*;
 pub use resolve::*;
 pub use sched_yield::*;
+pub use signalfd::*;
 pub use sock_accept::*;
 pub use sock_addr_local::*;
+pub use sock_addr_local_unix::*;
 pub use sock_addr_peer::*;
+pub use sock_addr_peer_unix::*;
 pub use sock_bind::*;
+pub use sock_bind_unix::*;
 pub use sock_connect::*;
+pub use sock_connect_unix::*;
 pub use sock_get_opt_flag::*;
 pub use sock_get_opt_size::*;
 pub use sock_get_opt_time::*;
@@ -112,11 +190,16 @@ pub use sock_leave_multicast_v4::*;
 pub use sock_leave_multicast_v6::*;
 pub use sock_listen::*;
 pub use sock_open::*;
+pub use sock_pair::*;
 pub use sock_recv::*;
+pub use sock_recv_fds::*;
 pub use sock_recv_from::*;
+pub use sock_recv_from_unix::*;
 pub use sock_send::*;
+pub use sock_send_fds::*;
 pub use sock_send_file::*;
 pub use sock_send_to::*;
+pub use sock_send_to_unix::*;
 pub use sock_set_opt_flag::*;
 pub use sock_set_opt_size::*;
 pub use sock_set_opt_time::*;
@@ -131,7 +214,12 @@ pub use thread_parallelism::*;
 pub use thread_signal::*;
 pub use thread_sleep::*;
 pub use thread_spawn::*;
+pub use timerfd_create::*;
+pub use timerfd_gettime::*;
+pub use timerfd_settime::*;
 pub use tty_get::*;
+pub use tty_get_pgrp::*;
 pub use tty_set::*;
+pub use tty_set_pgrp::*;
 
 use tracing::{debug_span, field, instrument, trace_span, Span};
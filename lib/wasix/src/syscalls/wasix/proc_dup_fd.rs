@@ -0,0 +1,59 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_dup_fd()`
+/// Duplicates a file descriptor of a child process into this process.
+/// Note: This is similar to `pidfd_getfd` on Linux.
+///
+/// Both descriptors share the same file and offset.
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the child process that owns the file descriptor
+/// * `fd` - File descriptor of the child process to duplicate
+///
+/// ## Return
+///
+/// The new file descriptor of this process.
+#[instrument(level = "debug", skip_all, fields(%pid, %fd, ret_fd = field::Empty), ret)]
+pub fn proc_dup_fd<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    pid: Pid,
+    fd: WasiFd,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let pid: WasiProcessId = pid.into();
+
+    // only the descriptors of our own children can be taken
+    let child = {
+        let inner = env.process.lock();
+        inner
+            .children
+            .iter()
+            .find(|child| child.pid() == pid)
+            .cloned()
+    };
+    let child = match child {
+        Some(child) => child,
+        None if env.control_plane.get_process(pid).is_some() => return Ok(Errno::Perm),
+        None => return Ok(Errno::Srch),
+    };
+
+    let entry = wasi_try_ok!(child.fd(fd));
+    let new_fd = wasi_try_ok!(env.state.fs.insert_fd(entry.clone()));
+    Span::current().record("ret_fd", new_fd);
+
+    #[cfg(feature = "journal")]
+    if env.enable_journal {
+        JournalEffector::save_fd_receive(&mut ctx, new_fd, &entry).map_err(|err| {
+            tracing::error!("failed to save proc_dup_fd event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_fd.write(&memory, new_fd));
+    Ok(Errno::Success)
+}
@@ -0,0 +1,19 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getegid()`
+/// Returns the effective group ID of the calling process, which is
+/// the group that permission checks are made against
+#[instrument(level = "trace", skip_all, fields(egid = field::Empty), ret)]
+pub fn proc_getegid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_egid: WasmPtr<u32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let egid = env.process.credentials().egid;
+    Span::current().record("egid", egid);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_egid.write(&memory, egid));
+    Errno::Success
+}
@@ -0,0 +1,19 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_geteuid()`
+/// Returns the effective user ID of the calling process, which is
+/// the user that permission checks are made against
+#[instrument(level = "trace", skip_all, fields(euid = field::Empty), ret)]
+pub fn proc_geteuid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_euid: WasmPtr<u32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let euid = env.process.credentials().euid;
+    Span::current().record("euid", euid);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_euid.write(&memory, euid));
+    Errno::Success
+}
@@ -0,0 +1,18 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getgid()`
+/// Returns the real group ID of the calling process
+#[instrument(level = "trace", skip_all, fields(gid = field::Empty), ret)]
+pub fn proc_getgid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_gid: WasmPtr<u32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let gid = env.process.credentials().gid;
+    Span::current().record("gid", gid);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_gid.write(&memory, gid));
+    Errno::Success
+}
@@ -0,0 +1,40 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getgroups()`
+/// Returns the supplementary groups of the calling process
+///
+/// ## Parameters
+///
+/// * `groups` - Buffer that receives the group IDs
+/// * `groups_len` - Number of group IDs that fit in the buffer, when this
+///   is zero only the number of groups is returned
+///
+/// Fails with `Errno::Inval` if the buffer is too small to hold the groups
+#[instrument(level = "trace", skip_all, fields(%groups_len, count = field::Empty), ret)]
+pub fn proc_getgroups<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    groups: WasmPtr<u32, M>,
+    groups_len: M::Offset,
+    ret_count: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let env = ctx.data();
+    let credentials = env.process.credentials();
+    let count: M::Offset = wasi_try!(credentials
+        .groups
+        .len()
+        .try_into()
+        .map_err(|_| Errno::Overflow));
+    Span::current().record("count", credentials.groups.len());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    if groups_len != M::ZERO {
+        if groups_len < count {
+            return Errno::Inval;
+        }
+        let slice = wasi_try_mem!(groups.slice(&memory, count));
+        wasi_try_mem!(slice.write_slice(&credentials.groups));
+    }
+    wasi_try_mem!(ret_count.write(&memory, count));
+    Errno::Success
+}
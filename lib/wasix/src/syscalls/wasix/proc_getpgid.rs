@@ -0,0 +1,27 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getpgid()`
+/// Returns the process group of the supplied process
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the process (zero for the calling process)
+#[instrument(level = "trace", skip_all, fields(%pid, pgid = field::Empty), ret)]
+pub fn proc_getpgid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    pid: Pid,
+    ret_pgid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let pid = match pid {
+        0 => env.process.pid(),
+        pid => pid.into(),
+    };
+    let pgid = wasi_try!(env.control_plane.process_group(pid).ok_or(Errno::Srch));
+    Span::current().record("pgid", pgid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_pgid.write(&memory, pgid.raw() as Pid));
+    Errno::Success
+}
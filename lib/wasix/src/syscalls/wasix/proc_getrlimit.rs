@@ -0,0 +1,27 @@
+use super::*;
+use crate::{os::task::limits::WasiResource, syscalls::*};
+
+/// ### `proc_getrlimit()`
+/// Returns the limit on a resource that the calling process may consume
+///
+/// ## Parameters
+///
+/// * `resource` - The resource whose limit is returned
+/// * `ret_soft` - Receives the soft limit (`u64::MAX` means unlimited)
+/// * `ret_hard` - Receives the hard limit (`u64::MAX` means unlimited)
+#[instrument(level = "trace", skip_all, fields(%resource), ret)]
+pub fn proc_getrlimit<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    resource: u32,
+    ret_soft: WasmPtr<u64, M>,
+    ret_hard: WasmPtr<u64, M>,
+) -> Errno {
+    let env = ctx.data();
+    let resource = wasi_try!(WasiResource::try_from(resource));
+    let limit = env.process.resource_limit(resource);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_soft.write(&memory, limit.soft.unwrap_or(u64::MAX)));
+    wasi_try_mem!(ret_hard.write(&memory, limit.hard.unwrap_or(u64::MAX)));
+    Errno::Success
+}
@@ -0,0 +1,27 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getsid()`
+/// Returns the session of the supplied process
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the process (zero for the calling process)
+#[instrument(level = "trace", skip_all, fields(%pid, sid = field::Empty), ret)]
+pub fn proc_getsid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    pid: Pid,
+    ret_sid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let pid = match pid {
+        0 => env.process.pid(),
+        pid => pid.into(),
+    };
+    let sid = wasi_try!(env.control_plane.session(pid).ok_or(Errno::Srch));
+    Span::current().record("sid", sid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_sid.write(&memory, sid.raw() as Pid));
+    Errno::Success
+}
@@ -0,0 +1,18 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getuid()`
+/// Returns the real user ID of the calling process
+#[instrument(level = "trace", skip_all, fields(uid = field::Empty), ret)]
+pub fn proc_getuid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_uid: WasmPtr<u32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let uid = env.process.credentials().uid;
+    Span::current().record("uid", uid);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_uid.write(&memory, uid));
+    Errno::Success
+}
@@ -1,4 +1,4 @@
-use std::task::Waker;
+use std::{future::Future, task::Waker};
 
 use serde::{Deserialize, Serialize};
 use wasmer::FromToNativeWasmType;
@@ -11,9 +11,31 @@ use crate::{syscalls::*, WasiProcess};
 enum JoinStatusResult {
     Nothing,
     ExitNormal(WasiProcessId, ExitCode),
+    /// A child was stopped by a signal (as a raw `Signal`)
+    Stopped(WasiProcessId, u8),
     Err(Errno),
 }
 
+/// Waits for `join` to finish, unless `wake_stopped` is set and a child
+/// accepted by `filter` is stopped by a signal first
+async fn join_or_stopped(
+    parent: WasiProcess,
+    wake_stopped: bool,
+    filter: impl Fn(&WasiProcess) -> bool + Send + Sync + 'static,
+    join: impl Future<Output = JoinStatusResult>,
+) -> JoinStatusResult {
+    if !wake_stopped {
+        return join.await;
+    }
+    tokio::select! {
+        status = join => status,
+        (pid, signal) = parent.join_stopped_child(filter) => {
+            tracing::trace!(%pid, ?signal, "triggered stopped child join");
+            JoinStatusResult::Stopped(pid, signal as u8)
+        }
+    }
+}
+
 /// ### `proc_join()`
 /// Joins the child process, blocking this one until the other finishes
 ///
@@ -66,6 +88,20 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
                         },
                     }
                 }
+                JoinStatusResult::Stopped(pid, signal) => {
+                    let option_pid = OptionPid {
+                        tag: OptionTag::Some,
+                        pid: pid.raw() as Pid,
+                    };
+                    pid_ptr.write(&view, option_pid).ok();
+
+                    JoinStatus {
+                        tag: JoinStatusType::Stopped,
+                        u: JoinStatusUnion {
+                            stopped: Signal::try_from(signal).unwrap_or(Signal::Sigstop),
+                        },
+                    }
+                }
                 JoinStatusResult::Err(err) => {
                     ret = err;
                     JoinStatus {
@@ -112,10 +148,65 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
         }
     ));
 
+    // Callers that pass `WAKE_STOPPED` are also told about the children
+    // that are stopped by a signal (e.g. for a shell to put them in the
+    // background)
+    let wake_stopped = flags.contains(JoinFlags::WAKE_STOPPED);
+
+    // Waiting on a process group joins any of the children in that group
+    if flags.contains(JoinFlags::PROCESS_GROUP) {
+        let mut process = ctx.data().process.clone();
+        let pgid = match option_pid {
+            Some(pgid) => WasiProcessId::from(pgid),
+            None => wasi_try_ok!(ctx
+                .data()
+                .control_plane
+                .process_group(process.pid())
+                .ok_or(Errno::Srch)),
+        };
+
+        let in_group = move |child: &WasiProcess| child.process_group() == pgid;
+
+        if flags.contains(JoinFlags::NON_BLOCKING) {
+            let status = match process.try_join_child_in_group(pgid) {
+                Ok(Some((pid, exit_code))) => JoinStatusResult::ExitNormal(pid, exit_code),
+                Ok(None) => match process.try_join_stopped_child(in_group) {
+                    Some((pid, signal)) if wake_stopped => {
+                        JoinStatusResult::Stopped(pid, signal as u8)
+                    }
+                    _ => JoinStatusResult::Nothing,
+                },
+                Err(err) => JoinStatusResult::Err(err),
+            };
+            return ret_result(ctx, status);
+        }
+
+        let parent = process.clone();
+        let res =
+            __asyncify_with_deep_sleep::<M, _, _>(ctx, Duration::from_millis(50), async move {
+                let join = async move {
+                    match process.join_any_child_in_group(pgid).await {
+                        Ok(Some((pid, exit_code))) => {
+                            tracing::trace!(%pid, %pgid, %exit_code, "triggered group join");
+                            JoinStatusResult::ExitNormal(pid, exit_code)
+                        }
+                        Ok(None) => JoinStatusResult::Err(Errno::Child),
+                        Err(err) => JoinStatusResult::Err(err),
+                    }
+                };
+                join_or_stopped(parent, wake_stopped, in_group, join).await
+            })?;
+        return match res {
+            AsyncifyAction::Finish(ctx, result) => ret_result(ctx, result),
+            AsyncifyAction::Unwind => Ok(Errno::Success),
+        };
+    }
+
     // If the ID is maximum then it means wait for any of the children
     let pid = match option_pid {
         None => {
             let mut process = ctx.data_mut().process.clone();
+            let parent = process.clone();
 
             // We wait for any process to exit (if it takes too long
             // then we go into a deep sleep)
@@ -123,22 +214,25 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
                 ctx,
                 Duration::from_millis(50),
                 async move {
-                    let child_exit = process.join_any_child().await;
-                    match child_exit {
-                        Ok(Some((pid, exit_code))) => {
-                            tracing::trace!(%pid, %exit_code, "triggered child join");
-                            trace!(ret_id = pid.raw(), exit_code = exit_code.raw());
-                            JoinStatusResult::ExitNormal(pid, exit_code)
+                    let join = async move {
+                        let child_exit = process.join_any_child().await;
+                        match child_exit {
+                            Ok(Some((pid, exit_code))) => {
+                                tracing::trace!(%pid, %exit_code, "triggered child join");
+                                trace!(ret_id = pid.raw(), exit_code = exit_code.raw());
+                                JoinStatusResult::ExitNormal(pid, exit_code)
+                            }
+                            Ok(None) => {
+                                tracing::trace!("triggered child join (no child)");
+                                JoinStatusResult::Err(Errno::Child)
+                            }
+                            Err(err) => {
+                                tracing::trace!(%err, "error triggered on child join");
+                                JoinStatusResult::Err(err)
+                            }
                         }
-                        Ok(None) => {
-                            tracing::trace!("triggered child join (no child)");
-                            JoinStatusResult::Err(Errno::Child)
-                        }
-                        Err(err) => {
-                            tracing::trace!(%err, "error triggered on child join");
-                            JoinStatusResult::Err(err)
-                        }
-                    }
+                    };
+                    join_or_stopped(parent, wake_stopped, |_| true, join).await
                 },
             )?;
             return match res {
@@ -155,16 +249,15 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
     // Waiting for a process that is an explicit child will join it
     // meaning it will no longer be a sub-process of the main process
     let mut process = {
-        let mut inner = ctx.data().process.lock();
-        let process = inner
+        let inner = ctx.data().process.lock();
+        inner
             .children
             .iter()
             .filter(|c| c.pid == pid)
             .map(Clone::clone)
-            .next();
-        inner.children.retain(|c| c.pid != pid);
-        process
+            .next()
     };
+    let parent = process.as_ref().map(|_| ctx.data().process.clone());
 
     // Otherwise it could be the case that we are waiting for a process
     // that is not a child of this process but may still be running
@@ -182,12 +275,27 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
             }
         ));
 
+        // Only the parent is told that a process stopped
+        let wake_stopped = wake_stopped && parent.is_some();
+        let is_child = move |child: &WasiProcess| child.pid == pid;
+
         if flags.contains(JoinFlags::NON_BLOCKING) {
             if let Some(status) = process.try_join() {
+                if let Some(parent) = parent {
+                    parent.reap_child(&process);
+                }
                 let exit_code = status.unwrap_or_else(|_| Errno::Child.into());
                 ret_result(ctx, JoinStatusResult::ExitNormal(pid, exit_code))
             } else {
-                ret_result(ctx, JoinStatusResult::Nothing)
+                let stopped = parent
+                    .filter(|_| wake_stopped)
+                    .and_then(|parent| parent.try_join_stopped_child(is_child));
+                match stopped {
+                    Some((pid, signal)) => {
+                        ret_result(ctx, JoinStatusResult::Stopped(pid, signal as u8))
+                    }
+                    None => ret_result(ctx, JoinStatusResult::Nothing),
+                }
             }
         } else {
             // Wait for the process to finish
@@ -196,9 +304,20 @@ pub(super) fn proc_join_internal<M: MemorySize + 'static>(
                 ctx,
                 Duration::from_millis(50),
                 async move {
-                    let exit_code = process.join().await.unwrap_or_else(|_| Errno::Child.into());
-                    tracing::trace!(%exit_code, "triggered child join");
-                    JoinStatusResult::ExitNormal(pid, exit_code)
+                    let waiter = parent.clone();
+                    let join = async move {
+                        let exit_code =
+                            process.join().await.unwrap_or_else(|_| Errno::Child.into());
+                        if let Some(parent) = parent {
+                            parent.reap_child(&process);
+                        }
+                        tracing::trace!(%exit_code, "triggered child join");
+                        JoinStatusResult::ExitNormal(pid, exit_code)
+                    };
+                    match waiter {
+                        Some(parent) => join_or_stopped(parent, wake_stopped, is_child, join).await,
+                        None => join.await,
+                    }
                 },
             )?;
             match res {
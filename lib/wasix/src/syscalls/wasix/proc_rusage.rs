@@ -0,0 +1,42 @@
+use super::*;
+use crate::syscalls::*;
+
+/// Resource usage of the calling process
+const RUSAGE_SELF: i32 = 0;
+/// Resource usage of the children of the calling process that were joined
+const RUSAGE_CHILDREN: i32 = -1;
+/// Resource usage of the calling thread
+const RUSAGE_THREAD: i32 = 1;
+
+/// ### `proc_rusage()`
+/// Returns the CPU time that was consumed (like `getrusage`)
+///
+/// ## Parameters
+///
+/// * `who` - Whose usage to return, either the calling process (0), its
+///   children that were joined (-1) or the calling thread (1)
+///
+/// ## Return
+///
+/// The time spent running the guest and the time spent by the runtime on
+/// its behalf, the latter is not measured separately and is always zero
+#[instrument(level = "trace", skip_all, fields(%who), ret)]
+pub fn proc_rusage<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    who: i32,
+    ret_utime: WasmPtr<Timestamp, M>,
+    ret_stime: WasmPtr<Timestamp, M>,
+) -> Errno {
+    let env = ctx.data();
+    let utime = match who {
+        RUSAGE_SELF => env.process.cpu_time(),
+        RUSAGE_CHILDREN => env.process.children_cpu_time(),
+        RUSAGE_THREAD => env.thread.cpu_clock().elapsed(),
+        _ => return Errno::Inval,
+    };
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_utime.write(&memory, utime.as_nanos() as Timestamp));
+    wasi_try_mem!(ret_stime.write(&memory, 0));
+    Errno::Success
+}
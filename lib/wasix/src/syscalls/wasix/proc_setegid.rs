@@ -0,0 +1,18 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setegid()`
+/// Sets the effective group ID of the calling process
+///
+/// Fails with `Errno::Perm` if the process is not allowed to switch to the group
+///
+/// ## Parameters
+///
+/// * `egid` - The group ID to switch to
+#[instrument(level = "trace", skip_all, fields(%egid), ret)]
+pub fn proc_setegid(ctx: FunctionEnvMut<'_, WasiEnv>, egid: u32) -> Errno {
+    let env = ctx.data();
+    let mut credentials = env.process.credentials.write().unwrap();
+    wasi_try!(credentials.set_egid(egid));
+    Errno::Success
+}
@@ -0,0 +1,18 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_seteuid()`
+/// Sets the effective user ID of the calling process
+///
+/// Fails with `Errno::Perm` if the process is not allowed to switch to the user
+///
+/// ## Parameters
+///
+/// * `euid` - The user ID to switch to
+#[instrument(level = "trace", skip_all, fields(%euid), ret)]
+pub fn proc_seteuid(ctx: FunctionEnvMut<'_, WasiEnv>, euid: u32) -> Errno {
+    let env = ctx.data();
+    let mut credentials = env.process.credentials.write().unwrap();
+    wasi_try!(credentials.set_euid(euid));
+    Errno::Success
+}
@@ -0,0 +1,20 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setgid()`
+/// Sets the group ID of the calling process, privileged processes
+/// change the real, effective and saved group IDs while the others can only
+/// switch the effective group ID to their real or saved group ID
+///
+/// Fails with `Errno::Perm` if the process is not allowed to switch to the group
+///
+/// ## Parameters
+///
+/// * `gid` - The group ID to switch to
+#[instrument(level = "trace", skip_all, fields(%gid), ret)]
+pub fn proc_setgid(ctx: FunctionEnvMut<'_, WasiEnv>, gid: u32) -> Errno {
+    let env = ctx.data();
+    let mut credentials = env.process.credentials.write().unwrap();
+    wasi_try!(credentials.set_gid(gid));
+    Errno::Success
+}
@@ -0,0 +1,27 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setgroups()`
+/// Replaces the supplementary groups of the calling process
+///
+/// ## Parameters
+///
+/// * `groups` - Buffer that holds the group IDs
+/// * `groups_len` - Number of group IDs in the buffer
+///
+/// Fails with `Errno::Perm` if the process is not privileged
+#[instrument(level = "trace", skip_all, fields(%groups_len), ret)]
+pub fn proc_setgroups<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    groups: WasmPtr<u32, M>,
+    groups_len: M::Offset,
+) -> Errno {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let groups = wasi_try_mem!(groups.slice(&memory, groups_len));
+    let groups = wasi_try_mem!(groups.read_to_vec());
+
+    let mut credentials = env.process.credentials.write().unwrap();
+    wasi_try!(credentials.set_groups(groups));
+    Errno::Success
+}
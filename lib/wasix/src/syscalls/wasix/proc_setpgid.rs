@@ -0,0 +1,39 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setpgid()`
+/// Moves a process into a process group of the same session
+///
+/// ## Parameters
+///
+/// * `pid` - Handle of the process, either the calling process or one
+///   of its children (zero for the calling process)
+/// * `pgid` - Process group to join, when this is zero (or the ID of the
+///   process) a new group is created that is led by the process
+#[instrument(level = "trace", skip_all, fields(%pid, %pgid), ret)]
+pub fn proc_setpgid(ctx: FunctionEnvMut<'_, WasiEnv>, pid: Pid, pgid: Pid) -> Errno {
+    let env = ctx.data();
+    let pid = match pid {
+        0 => env.process.pid(),
+        pid => WasiProcessId::from(pid),
+    };
+    let pgid = match pgid {
+        0 => pid,
+        pgid => WasiProcessId::from(pgid),
+    };
+
+    // Only the process itself and its children can be moved
+    if pid != env.process.pid() {
+        let inner = env.process.lock();
+        if !inner.children.iter().any(|child| child.pid() == pid) {
+            return Errno::Srch;
+        }
+    }
+    // ...and only within the session of the caller
+    if env.control_plane.session(pid) != env.control_plane.session(env.process.pid()) {
+        return Errno::Perm;
+    }
+
+    wasi_try!(env.control_plane.set_process_group(pid, pgid));
+    Errno::Success
+}
@@ -0,0 +1,34 @@
+use super::*;
+use crate::{
+    os::task::limits::{WasiResource, WasiResourceLimit},
+    syscalls::*,
+};
+
+/// ### `proc_setrlimit()`
+/// Changes the limit on a resource that the calling process may consume,
+/// the soft limit may not be above the hard limit and only privileged
+/// processes may raise their hard limit
+///
+/// Fails with `Errno::Perm` if the process is not allowed to raise the limit
+///
+/// ## Parameters
+///
+/// * `resource` - The resource whose limit is changed
+/// * `soft` - The new soft limit (`u64::MAX` means unlimited)
+/// * `hard` - The new hard limit (`u64::MAX` means unlimited)
+#[instrument(level = "trace", skip_all, fields(%resource, %soft, %hard), ret)]
+pub fn proc_setrlimit(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    resource: u32,
+    soft: u64,
+    hard: u64,
+) -> Errno {
+    let env = ctx.data();
+    let resource = wasi_try!(WasiResource::try_from(resource));
+    let limit = WasiResourceLimit {
+        soft: Some(soft).filter(|soft| *soft != u64::MAX),
+        hard: Some(hard).filter(|hard| *hard != u64::MAX),
+    };
+    wasi_try!(env.process.set_resource_limit(env.tasks(), resource, limit));
+    Errno::Success
+}
@@ -0,0 +1,21 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setsid()`
+/// Creates a new session that is led by the calling process, the
+/// process also becomes the leader of a new process group
+///
+/// Fails with `Errno::Perm` if the process is already a group leader
+#[instrument(level = "trace", skip_all, fields(sid = field::Empty), ret)]
+pub fn proc_setsid<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_sid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let sid = wasi_try!(env.control_plane.new_session(env.process.pid()));
+    Span::current().record("sid", sid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_sid.write(&memory, sid.raw() as Pid));
+    Errno::Success
+}
@@ -0,0 +1,20 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setuid()`
+/// Sets the user ID of the calling process, privileged processes
+/// change the real, effective and saved user IDs while the others can only
+/// switch the effective user ID to their real or saved user ID
+///
+/// Fails with `Errno::Perm` if the process is not allowed to switch to the user
+///
+/// ## Parameters
+///
+/// * `uid` - The user ID to switch to
+#[instrument(level = "trace", skip_all, fields(%uid), ret)]
+pub fn proc_setuid(ctx: FunctionEnvMut<'_, WasiEnv>, uid: u32) -> Errno {
+    let env = ctx.data();
+    let mut credentials = env.process.credentials.write().unwrap();
+    wasi_try!(credentials.set_uid(uid));
+    Errno::Success
+}
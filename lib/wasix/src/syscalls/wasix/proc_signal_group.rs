@@ -0,0 +1,30 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_signal_group()`
+/// Sends a signal to all the processes of a process group
+///
+/// ## Parameters
+///
+/// * `pgid` - Process group to signal (zero for the group of the calling process)
+/// * `sig` - Signal to send to the processes
+#[instrument(level = "trace", skip_all, fields(%pgid, ?sig), ret)]
+pub fn proc_signal_group(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    pgid: Pid,
+    sig: Signal,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let pgid = match pgid {
+        0 => wasi_try_ok!(env
+            .control_plane
+            .process_group(env.process.pid())
+            .ok_or(Errno::Srch)),
+        pgid => WasiProcessId::from(pgid),
+    };
+    wasi_try_ok!(env.control_plane.signal_process_group(pgid, sig));
+
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    Ok(Errno::Success)
+}
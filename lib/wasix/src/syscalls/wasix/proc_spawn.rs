@@ -2,7 +2,7 @@ use virtual_fs::Pipe;
 use wasmer_wasix_types::wasi::ProcessHandles;
 
 use super::*;
-use crate::syscalls::*;
+use crate::{os::task::control_plane::ControlPlaneError, syscalls::*};
 
 /// Spawns a new process within the context of this machine
 ///
@@ -109,6 +109,7 @@ pub fn proc_spawn_internal(
     // Fork the current environment and set the new arguments
     let (mut child_env, handle) = match ctx.data().fork() {
         Ok(x) => x,
+        Err(ControlPlaneError::ProcessLimitReached { .. }) => return Ok(Err(Errno::Again)),
         Err(err) => {
             // TODO: evaluate the appropriate error code, document it in the spec.
             return Ok(Err(Errno::Access));
@@ -118,7 +119,7 @@ pub fn proc_spawn_internal(
     if let Some(args) = args {
         let mut child_state = env.state.fork();
         child_state.args = args;
-        child_env.state = Arc::new(child_state);
+        child_env.set_state(child_state);
     }
 
     // Take ownership of this child
@@ -0,0 +1,35 @@
+use super::*;
+use crate::{os::task::timer::WasiTimerClock, syscalls::*};
+
+/// ### `proc_timer_create()`
+/// Creates a timer that raises a signal on this process when it expires,
+/// the timer is disarmed until it is set with `proc_timer_settime`
+///
+/// ## Parameters
+///
+/// * `clock_id` - Clock that the timer measures its expiration against,
+///   the CPU time clocks measure the time that the guest has been running
+/// * `sig` - Signal raised when the timer expires
+#[instrument(level = "trace", skip_all, fields(?clock_id, ?sig, timer = field::Empty), ret)]
+pub fn proc_timer_create<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    clock_id: Snapshot0Clockid,
+    sig: Signal,
+    ret_timer: WasmPtr<u32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let clock = match clock_id {
+        Snapshot0Clockid::Realtime | Snapshot0Clockid::Monotonic => {
+            WasiTimerClock::System(clock_id)
+        }
+        Snapshot0Clockid::ProcessCputimeId => WasiTimerClock::Process,
+        Snapshot0Clockid::ThreadCputimeId => WasiTimerClock::Thread(env.thread.cpu_clock().clone()),
+        Snapshot0Clockid::Unknown => return Errno::Inval,
+    };
+    let timer = wasi_try!(env.process.create_timer(clock, sig));
+    Span::current().record("timer", timer);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_timer.write(&memory, timer));
+    Errno::Success
+}
@@ -0,0 +1,10 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_timer_delete()`
+/// Deletes a timer, disarming it
+#[instrument(level = "trace", skip_all, fields(%timer), ret)]
+pub fn proc_timer_delete(ctx: FunctionEnvMut<'_, WasiEnv>, timer: u32) -> Errno {
+    wasi_try!(ctx.data().process.delete_timer(timer));
+    Errno::Success
+}
@@ -0,0 +1,19 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_timer_getoverrun()`
+/// Returns the number of expirations of a periodic timer that were
+/// missed the last time it expired
+#[instrument(level = "trace", skip_all, fields(%timer), ret)]
+pub fn proc_timer_getoverrun<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    timer: u32,
+    ret_overrun: WasmPtr<u32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let overrun = wasi_try!(env.process.timer_overrun(timer));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_overrun.write(&memory, overrun));
+    Errno::Success
+}
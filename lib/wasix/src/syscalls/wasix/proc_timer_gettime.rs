@@ -0,0 +1,21 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_timer_gettime()`
+/// Returns the time left before a timer expires (zero when it is
+/// disarmed) and its interval
+#[instrument(level = "trace", skip_all, fields(%timer), ret)]
+pub fn proc_timer_gettime<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    timer: u32,
+    ret_value: WasmPtr<Timestamp, M>,
+    ret_interval: WasmPtr<Timestamp, M>,
+) -> Errno {
+    let env = ctx.data();
+    let (value, interval) = wasi_try!(env.process.get_timer(timer));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_value.write(&memory, value.as_nanos() as Timestamp));
+    wasi_try_mem!(ret_interval.write(&memory, interval.as_nanos() as Timestamp));
+    Errno::Success
+}
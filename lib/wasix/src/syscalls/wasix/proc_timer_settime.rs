@@ -0,0 +1,44 @@
+use wasmer_wasix_types::wasi::Subclockflags;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_timer_settime()`
+/// Arms (or disarms) a timer
+///
+/// ## Parameters
+///
+/// * `timer` - Timer created with `proc_timer_create`
+/// * `flags` - When `SUBSCRIPTION_CLOCK_ABSTIME` is set then `value` is
+///   the time on the clock of the timer at which it expires
+/// * `value` - Time after which the timer expires, zero disarms the timer
+/// * `interval` - Time between the following expirations of the timer,
+///   zero for a timer that only expires once
+///
+/// ## Return
+///
+/// The time that was left on the timer and its interval
+#[instrument(level = "trace", skip_all, fields(%timer, ?flags, %value, %interval), ret)]
+pub fn proc_timer_settime<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    timer: u32,
+    flags: Subclockflags,
+    value: Timestamp,
+    interval: Timestamp,
+    ret_old_value: WasmPtr<Timestamp, M>,
+    ret_old_interval: WasmPtr<Timestamp, M>,
+) -> Errno {
+    let env = ctx.data();
+    let (old_value, old_interval) = wasi_try!(env.process.set_timer(
+        env.tasks(),
+        timer,
+        Duration::from_nanos(value),
+        Duration::from_nanos(interval),
+        flags.contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME),
+    ));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_old_value.write(&memory, old_value.as_nanos() as Timestamp));
+    wasi_try_mem!(ret_old_interval.write(&memory, old_interval.as_nanos() as Timestamp));
+    Errno::Success
+}
@@ -0,0 +1,37 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `pty_get()`
+/// Retrieves the state of a pseudo-terminal (window size and line
+/// discipline) from either of its ends
+///
+/// ## Parameters
+///
+/// * `fd` - One of the ends of the pseudo-terminal
+#[instrument(level = "trace", skip_all, fields(%fd), ret)]
+pub fn pty_get<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    tty_state: WasmPtr<Tty, M>,
+) -> Errno {
+    let env = ctx.data();
+    let pty = wasi_try!(pty_of_fd(env, fd));
+
+    let state = pty.state();
+    let state = Tty {
+        cols: state.cols,
+        rows: state.rows,
+        width: state.width,
+        height: state.height,
+        stdin_tty: state.stdin_tty,
+        stdout_tty: state.stdout_tty,
+        stderr_tty: state.stderr_tty,
+        echo: state.echo,
+        line_buffered: state.line_buffered,
+    };
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(tty_state.write(&memory, state));
+
+    Errno::Success
+}
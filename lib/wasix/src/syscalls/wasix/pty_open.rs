@@ -0,0 +1,109 @@
+use std::sync::RwLock;
+
+use super::*;
+use crate::{os::tty::pty::Pty, syscalls::*, VirtualFile};
+
+/// ### `pty_open()`
+/// Allocates a new pseudo-terminal and opens both of its ends (`openpty`),
+/// the input that is written to the master end passes through the line
+/// discipline of the terminal before it can be read from the slave end
+///
+/// Output:
+/// - `Fd`
+///     Master end of the pseudo-terminal
+/// - `Fd`
+///     Slave end of the pseudo-terminal
+#[instrument(level = "trace", skip_all, fields(master = field::Empty, slave = field::Empty), ret)]
+pub fn pty_open<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_master: WasmPtr<WasiFd, M>,
+    ret_slave: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    let (master, slave) = wasi_try_ok!(pty_open_internal(&mut ctx));
+    Span::current()
+        .record("master", master)
+        .record("slave", slave);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_pty_open(&mut ctx, master, slave).map_err(|err| {
+            tracing::error!("failed to save pty_open event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_master.write(&memory, master));
+    wasi_try_mem_ok!(ret_slave.write(&memory, slave));
+    Ok(Errno::Success)
+}
+
+pub(crate) fn pty_open_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+) -> Result<(WasiFd, WasiFd), Errno> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let master = env.process.compute.must_upgrade().ptys().allocate();
+    let slave = master.open_slave().map_err(fs_error_into_wasi_err)?;
+    let name = format!("pts/{}", master.pty().index());
+
+    let master = pty_create_fd(
+        state,
+        inodes,
+        Box::new(master),
+        "ptmx".to_string(),
+        Fdflags::empty(),
+    )?;
+    let slave = pty_create_fd(state, inodes, Box::new(slave), name, Fdflags::empty())?;
+    Ok((master, slave))
+}
+
+/// Opens a file descriptor for one of the ends of a pseudo-terminal, the
+/// name is relative to `/dev`
+pub(crate) fn pty_create_fd(
+    state: &WasiState,
+    inodes: &WasiInodes,
+    file: Box<dyn VirtualFile + Send + Sync + 'static>,
+    name: String,
+    flags: Fdflags,
+) -> Result<WasiFd, Errno> {
+    let stat = Filestat {
+        st_filetype: Filetype::CharacterDevice,
+        ..Filestat::default()
+    };
+    let inode = state.fs.create_inode_with_stat(
+        inodes,
+        Kind::File {
+            handle: Some(Arc::new(RwLock::new(file))),
+            path: PathBuf::new(),
+            fd: None,
+        },
+        false,
+        name.into(),
+        stat,
+    );
+
+    let rights = Rights::FD_READ
+        | Rights::FD_WRITE
+        | Rights::FD_SYNC
+        | Rights::FD_DATASYNC
+        | Rights::POLL_FD_READWRITE
+        | Rights::FD_FILESTAT_GET
+        | Rights::FD_FDSTAT_SET_FLAGS;
+    state.fs.create_fd(rights, rights, flags, 0, inode)
+}
+
+/// Returns the pseudo-terminal that a file descriptor is one of the ends of
+pub(crate) fn pty_of_fd(env: &WasiEnv, fd: WasiFd) -> Result<Pty, Errno> {
+    let fd_entry = env.state.fs.get_fd(fd)?;
+    let guard = fd_entry.inode.read();
+    match guard.deref() {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => Pty::of(&**handle.read().unwrap()).ok_or(Errno::Notty),
+        _ => Err(Errno::Notty),
+    }
+}
@@ -0,0 +1,70 @@
+use super::*;
+use crate::{os::tty::pty::PtyMaster, syscalls::*};
+
+/// ### `pty_open_slave()`
+/// Opens the slave end of a pseudo-terminal from its master end, which is
+/// how the pseudo-terminals allocated by opening `/dev/ptmx` are used
+///
+/// Fails with `Errno::Notty` if the file descriptor is not the master end
+/// of a pseudo-terminal and with `Errno::Pipe` once the terminal hung up
+///
+/// ## Parameters
+///
+/// * `fd` - Master end of the pseudo-terminal
+#[instrument(level = "trace", skip_all, fields(%fd, slave = field::Empty), ret)]
+pub fn pty_open_slave<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    ret_slave: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    let slave = wasi_try_ok!(pty_open_slave_internal(&mut ctx, fd));
+    Span::current().record("slave", slave);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_pty_open_slave(&mut ctx, fd, slave).map_err(|err| {
+            tracing::error!("failed to save pty_open_slave event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_slave.write(&memory, slave));
+    Ok(Errno::Success)
+}
+
+pub(crate) fn pty_open_slave_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+) -> Result<WasiFd, Errno> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let (slave, index) = {
+        let fd_entry = state.fs.get_fd(fd)?;
+        let guard = fd_entry.inode.read();
+        let handle = match guard.deref() {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.clone(),
+            _ => return Err(Errno::Notty),
+        };
+        let handle = handle.read().unwrap();
+        let master = (**handle)
+            .upcast_any_ref()
+            .downcast_ref::<PtyMaster>()
+            .ok_or(Errno::Notty)?;
+        let slave = master.open_slave().map_err(fs_error_into_wasi_err)?;
+        (slave, master.pty().index())
+    };
+
+    pty_create_fd(
+        state,
+        inodes,
+        Box::new(slave),
+        format!("pts/{index}"),
+        Fdflags::empty(),
+    )
+}
@@ -0,0 +1,45 @@
+use super::*;
+use crate::{syscalls::*, WasiTtyState};
+
+/// ### `pty_set()`
+/// Updates the state of a pseudo-terminal from either of its ends, changing
+/// the window size raises `SIGWINCH` in the foreground process group of the
+/// session that the terminal controls
+///
+/// ## Parameters
+///
+/// * `fd` - One of the ends of the pseudo-terminal
+#[instrument(level = "trace", skip_all, fields(%fd), ret)]
+pub fn pty_set<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    tty_state: WasmPtr<Tty, M>,
+) -> Errno {
+    let env = ctx.data();
+    let pty = wasi_try!(pty_of_fd(env, fd));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    let state = wasi_try_mem!(tty_state.read(&memory));
+    debug!(
+        cols = state.cols,
+        rows = state.rows,
+        echo = state.echo,
+        line_buffered = state.line_buffered,
+    );
+
+    pty.set_state(WasiTtyState {
+        cols: state.cols,
+        rows: state.rows,
+        width: state.width,
+        height: state.height,
+        stdin_tty: state.stdin_tty,
+        stdout_tty: state.stdout_tty,
+        stderr_tty: state.stderr_tty,
+        echo: state.echo,
+        line_buffered: state.line_buffered,
+        // Not part of the guest facing state
+        line_feeds: pty.state().line_feeds,
+    });
+
+    Errno::Success
+}
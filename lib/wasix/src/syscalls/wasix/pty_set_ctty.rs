@@ -0,0 +1,28 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `pty_set_ctty()`
+/// Makes a pseudo-terminal the controlling terminal of the session led by
+/// the calling process (`TIOCSCTTY`), the signals raised by the terminal
+/// (e.g. Ctrl-C or a window resize) are then delivered to the foreground
+/// process group of that session
+///
+/// Fails with `Errno::Perm` if the calling process does not lead a session
+///
+/// ## Parameters
+///
+/// * `fd` - One of the ends of the pseudo-terminal
+#[instrument(level = "trace", skip_all, fields(%fd), ret)]
+pub fn pty_set_ctty(ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Errno {
+    let env = ctx.data();
+    let pty = wasi_try!(pty_of_fd(env, fd));
+
+    let pid = env.process.pid();
+    let sid = wasi_try!(env.control_plane.session(pid).ok_or(Errno::Srch));
+    if sid != pid {
+        return Errno::Perm;
+    }
+    pty.set_controlling_session(&env.control_plane, sid);
+
+    Errno::Success
+}
@@ -0,0 +1,75 @@
+use super::*;
+use crate::{
+    fs::{NotificationInner, SignalFdInner},
+    syscalls::*,
+};
+
+/// ### `signalfd()`
+/// Creates a file descriptor that receives the signals of this process in
+/// place of its threads (or changes the signals that an existing one
+/// receives), reading the descriptor returns a 128-byte record for every
+/// signal that starts with the number of the signal (`signalfd_siginfo`)
+///
+/// ## Parameters
+///
+/// * `fd` - Signal descriptor whose signals are changed, or `-1` to
+///   create a new descriptor
+/// * `mask` - Signals that are received, bit `n - 1` is set for signal `n`
+///   (`SIGKILL` and `SIGSTOP` are never received)
+/// * `flags` - Flags of a new descriptor (`NONBLOCK`)
+#[instrument(level = "trace", skip_all, fields(%fd, %mask, ret_fd = field::Empty), ret)]
+pub fn signalfd<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    mask: u64,
+    flags: Fdflags,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    let fd = wasi_try_ok!(signalfd_internal(&mut ctx, fd, mask, flags));
+    Span::current().record("ret_fd", fd);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_signalfd(&mut ctx, fd, mask, flags).map_err(|err| {
+            tracing::error!("failed to save signalfd event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_fd.write(&memory, fd));
+    Ok(Errno::Success)
+}
+
+pub(crate) fn signalfd_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    mask: u64,
+    flags: Fdflags,
+) -> Result<WasiFd, Errno> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    if fd != u32::MAX {
+        let fd_entry = state.fs.get_fd(fd)?;
+        let guard = fd_entry.inode.read();
+        match guard.deref() {
+            Kind::SignalFd { signals, .. } => signals.set_mask(mask),
+            _ => return Err(Errno::Inval),
+        }
+        return Ok(fd);
+    }
+
+    let inner = Arc::new(NotificationInner::new(0, true));
+    let signals = Arc::new(SignalFdInner::new(mask, inner.clone()));
+    env.process.add_signal_fd(&signals);
+    let kind = Kind::SignalFd { inner, signals };
+
+    let inode =
+        state
+            .fs
+            .create_inode_with_default_stat(inodes, kind, false, "signalfd".to_string().into());
+    let rights = Rights::FD_READ | Rights::POLL_FD_READWRITE | Rights::FD_FDSTAT_SET_FLAGS;
+    state.fs.create_fd(rights, rights, flags, 0, inode)
+}
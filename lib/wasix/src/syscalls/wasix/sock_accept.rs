@@ -68,8 +68,10 @@ pub fn sock_accept_v2<M: MemorySize>(
 
     let (fd, addr) = wasi_try_ok!(sock_accept_internal(env, sock, fd_flags, nonblocking)?);
 
+    // UNIX sockets only exist while the processes that bound them are
+    // running so they are not recorded in the journal
     #[cfg(feature = "journal")]
-    if ctx.data().enable_journal {
+    if let (true, Some(addr)) = (ctx.data().enable_journal, addr) {
         JournalEffector::save_sock_accepted(&mut ctx, sock, fd, addr, fd_flags, nonblocking)
             .map_err(|err| {
                 tracing::error!("failed to save sock_accepted event - {}", err);
@@ -80,12 +82,19 @@ pub fn sock_accept_v2<M: MemorySize>(
     let env = ctx.data();
     let (memory, state, _) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
     wasi_try_mem_ok!(ro_fd.write(&memory, fd));
-    wasi_try_ok!(crate::net::write_ip_port(
-        &memory,
-        ro_addr,
-        addr.ip(),
-        addr.port()
-    ));
+    match addr {
+        Some(addr) => {
+            wasi_try_ok!(crate::net::write_ip_port(
+                &memory,
+                ro_addr,
+                addr.ip(),
+                addr.port()
+            ));
+        }
+        None => {
+            wasi_try_ok!(write_unix_port(&memory, ro_addr));
+        }
+    }
 
     Ok(Errno::Success)
 }
@@ -95,7 +104,7 @@ pub(crate) fn sock_accept_internal(
     sock: WasiFd,
     mut fd_flags: Fdflags,
     mut nonblocking: bool,
-) -> Result<Result<(WasiFd, SocketAddr), Errno>, WasiError> {
+) -> Result<Result<(WasiFd, Option<SocketAddr>), Errno>, WasiError> {
     let state = env.state();
     let inodes = &state.inodes;
 
@@ -122,11 +131,7 @@ pub(crate) fn sock_accept_internal(
     ));
 
     let kind = Kind::Socket {
-        socket: InodeSocket::new(InodeSocketKind::TcpStream {
-            socket: child,
-            write_timeout: None,
-            read_timeout: None,
-        }),
+        socket: InodeSocket::new(child),
     };
     let inode = state
         .fs
@@ -0,0 +1,40 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_addr_local_unix()`
+/// Returns the path that a UNIX socket is bound to.
+///
+/// Note: This is similar to `getsockname` in POSIX using PF_UNIX
+///
+/// `path_len` holds the size of the buffer and is set to the length of
+/// the path, unnamed sockets have an empty path. If the path exceeds the
+/// size of the buffer then this function will return ERANGE
+///
+/// ## Parameters
+///
+/// * `fd` - Socket that the path is read from
+#[instrument(level = "debug", skip_all, fields(%sock, path = field::Empty), ret)]
+pub fn sock_addr_local_unix<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let addr = wasi_try!(__sock_actor(
+        &mut ctx,
+        sock,
+        Rights::empty(),
+        |socket, _| socket.addr_local_unix()
+    ));
+    Span::current().record("path", &format!("{:?}", addr));
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try!(crate::net::write_unix_path(
+        &memory,
+        path,
+        path_len,
+        addr.as_deref()
+    ));
+    Errno::Success
+}
@@ -0,0 +1,75 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_bind_unix()`
+/// Bind a UNIX socket to a path
+/// Note: This is similar to `bind` in POSIX using PF_UNIX
+///
+/// The path is created in the file system, binding fails with EADDRINUSE
+/// if something already exists at the path
+///
+/// ## Parameters
+///
+/// * `fd` - File descriptor of the socket to be bind
+/// * `path` - Path to bind the socket to
+#[instrument(level = "debug", skip_all, fields(%sock, path = field::Empty), ret)]
+pub fn sock_bind_unix<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    let current_dir = state.fs.current_dir.lock().unwrap().clone();
+    let path = wasi_try_ok!(crate::net::read_unix_path(
+        &memory,
+        path,
+        path_len,
+        &current_dir
+    ));
+    Span::current().record("path", &format!("{}", path.display()));
+
+    wasi_try_ok!(sock_bind_unix_internal(&mut ctx, sock, path.clone())?);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_sock_bind_unix(&mut ctx, sock, path).map_err(|err| {
+            tracing::error!("failed to save sock_bind_unix event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    Ok(Errno::Success)
+}
+
+pub(crate) fn sock_bind_unix_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    path: PathBuf,
+) -> Result<Result<(), Errno>, WasiError> {
+    let env = ctx.data();
+    let state = env.state.clone();
+    let control_plane = env.process.compute.must_upgrade();
+
+    wasi_try_ok_ok!(state
+        .fs_new_open_options()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map(drop)
+        .map_err(|err| match err {
+            FsError::AlreadyExists => Errno::Addrinuse,
+            err => fs_error_into_wasi_err(err),
+        }));
+
+    let ret = __sock_actor_mut(ctx, sock, Rights::SOCK_BIND, |socket, _| {
+        socket.bind_unix(control_plane.unix_sockets(), path.clone())
+    });
+    if let Err(err) = ret {
+        state.fs_remove_file(&path).ok();
+        return Ok(Err(err));
+    }
+
+    Ok(Ok(()))
+}
@@ -0,0 +1,95 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_connect_unix()`
+/// Initiate a connection on a UNIX socket to the socket bound to a path
+///
+/// Sockets bound by processes of the same control plane are connected to
+/// first, otherwise the path is looked up in the host UNIX sockets that the
+/// capabilities of the process bridge into the guest
+///
+/// Note: This is similar to `connect` in POSIX using PF_UNIX
+///
+/// ## Parameters
+///
+/// * `fd` - Socket descriptor
+/// * `path` - Path of the socket to connect to
+#[instrument(level = "debug", skip_all, fields(%sock, path = field::Empty), ret)]
+pub fn sock_connect_unix<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    let current_dir = state.fs.current_dir.lock().unwrap().clone();
+    let path = wasi_try_ok!(crate::net::read_unix_path(
+        &memory,
+        path,
+        path_len,
+        &current_dir
+    ));
+    Span::current().record("path", &format!("{}", path.display()));
+
+    wasi_try_ok!(sock_connect_unix_internal(&mut ctx, sock, path.clone())?);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_sock_connect_unix(&mut ctx, sock, path).map_err(|err| {
+            tracing::error!("failed to save sock_connect_unix event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    Ok(Errno::Success)
+}
+
+pub(crate) fn sock_connect_unix_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    path: PathBuf,
+) -> Result<Result<(), Errno>, WasiError> {
+    let env = ctx.data();
+    let net = env.net().clone();
+    let tasks = env.tasks().clone();
+    let state = env.state.clone();
+    let control_plane = env.process.compute.must_upgrade();
+    let bridge = env
+        .capabilities
+        .unix_sockets
+        .host_bridges
+        .get(&path)
+        .cloned();
+
+    let peer = path.clone();
+    let ret = __sock_upgrade(
+        ctx,
+        sock,
+        Rights::SOCK_CONNECT,
+        move |mut socket| async move {
+            socket
+                .connect_unix(
+                    tasks.deref(),
+                    net.deref(),
+                    control_plane.unix_sockets(),
+                    peer,
+                    bridge,
+                    None,
+                )
+                .await
+        },
+    );
+
+    // Nothing listens on the path, like on POSIX it depends on the
+    // path whether the connection was refused or the socket is missing
+    if let Err(Errno::Addrnotavail) = ret {
+        return Ok(Err(match state.fs.root_fs.metadata(&path) {
+            Ok(_) => Errno::Connrefused,
+            Err(_) => Errno::Noent,
+        }));
+    }
+    wasi_try_ok_ok!(ret);
+
+    Ok(Ok(()))
+}
@@ -45,11 +45,21 @@ pub(crate) fn sock_listen_internal(
     let env = ctx.data();
     let net = env.net().clone();
     let tasks = ctx.data().tasks().clone();
+    let control_plane = env.process.compute.must_upgrade();
     wasi_try_ok_ok!(__sock_upgrade(
         ctx,
         sock,
         Rights::SOCK_LISTEN,
-        |socket| async move { socket.listen(tasks.deref(), net.deref(), backlog).await }
+        |socket| async move {
+            socket
+                .listen(
+                    tasks.deref(),
+                    net.deref(),
+                    control_plane.unix_sockets(),
+                    backlog,
+                )
+                .await
+        }
     ));
 
     Ok(Ok(()))
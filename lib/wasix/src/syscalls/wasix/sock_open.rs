@@ -70,12 +70,18 @@ pub(crate) fn sock_open_internal(
     let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
 
     let kind = match ty {
+        Socktype::Dgram if af == Addressfamily::Unix => Kind::Socket {
+            socket: InodeSocket::new(InodeSocketKind::UnixDatagram {
+                socket: UnixDatagramSocket::new(),
+            }),
+        },
         Socktype::Stream | Socktype::Dgram => Kind::Socket {
             socket: InodeSocket::new(InodeSocketKind::PreSocket {
                 family: af,
                 ty,
                 pt,
                 addr: None,
+                unix_addr: None,
                 only_v6: false,
                 reuse_port: false,
                 reuse_addr: false,
@@ -0,0 +1,99 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_pair()`
+/// Create a pair of connected UNIX sockets.
+///
+/// Note: This is similar to `socketpair` in POSIX using PF_UNIX
+///
+/// ## Parameters
+///
+/// * `af` - Address family, only UNIX sockets are supported
+/// * `socktype` - Socket type, either datagram or stream
+/// * `sock_proto` - Socket protocol
+///
+/// ## Return
+///
+/// The file descriptors of the two ends of the connection.
+#[instrument(level = "debug", skip_all, fields(?af, ?ty, ?pt, sock1 = field::Empty, sock2 = field::Empty), ret)]
+pub fn sock_pair<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    af: Addressfamily,
+    ty: Socktype,
+    pt: SockProto,
+    ro_sock1: WasmPtr<WasiFd, M>,
+    ro_sock2: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    if af != Addressfamily::Unix {
+        return Ok(Errno::Afnosupport);
+    }
+    if !matches!(pt, SockProto::Ip) {
+        return Ok(Errno::Protonosupport);
+    }
+
+    let (fd1, fd2) = wasi_try_ok!(sock_pair_internal(&mut ctx, ty));
+    Span::current().record("sock1", fd1).record("sock2", fd2);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_sock_pair(&mut ctx, ty, fd1, fd2).map_err(|err| {
+            tracing::error!("failed to save sock_pair event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ro_sock1.write(&memory, fd1));
+    wasi_try_mem_ok!(ro_sock2.write(&memory, fd2));
+
+    Ok(Errno::Success)
+}
+
+pub(crate) fn sock_pair_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    ty: Socktype,
+) -> Result<(WasiFd, WasiFd), Errno> {
+    let (kind1, kind2) = match ty {
+        Socktype::Stream => {
+            let (socket1, socket2) = UnixPipeSocket::pair(None, None);
+            let kind = |socket: UnixPipeSocket| InodeSocketKind::UnixStream {
+                fds: Some(socket.fd_channel()),
+                socket: Box::new(socket),
+                local: None,
+                write_timeout: None,
+                read_timeout: None,
+            };
+            (kind(socket1), kind(socket2))
+        }
+        Socktype::Dgram => {
+            let (socket1, socket2) = UnixDatagramSocket::pair();
+            (
+                InodeSocketKind::UnixDatagram { socket: socket1 },
+                InodeSocketKind::UnixDatagram { socket: socket2 },
+            )
+        }
+        _ => return Err(Errno::Notsup),
+    };
+
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+    let rights = Rights::all_socket();
+    let create_fd = |kind| {
+        let kind = Kind::Socket {
+            socket: InodeSocket::new(kind),
+        };
+        let inode = state.fs.create_inode_with_default_stat(
+            inodes,
+            kind,
+            false,
+            "socket".to_string().into(),
+        );
+        state
+            .fs
+            .create_fd(rights, rights, Fdflags::empty(), 0, inode)
+    };
+    let fd1 = create_fd(kind1)?;
+    let fd2 = create_fd(kind2)?;
+    Ok((fd1, fd2))
+}
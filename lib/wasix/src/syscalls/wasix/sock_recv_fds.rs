@@ -0,0 +1,121 @@
+use std::mem::MaybeUninit;
+
+use super::*;
+use crate::{net::socket::TimeType, syscalls::*};
+
+/// ### `sock_recv_fds()`
+/// Receive a message and the file descriptors attached to it from a UNIX
+/// socket.
+/// Note: This is similar to `recvmsg` in POSIX with `SCM_RIGHTS`, though it
+/// also supports reading the data into multiple buffers in the manner of
+/// `readv`.
+///
+/// The descriptors are added to the file descriptor table of the process and
+/// their numbers are stored in `ro_fds`. Descriptors that do not fit in
+/// `ro_fds` are closed and `__WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED` is set in
+/// the output flags.
+///
+/// ## Parameters
+///
+/// * `ri_data` - List of scatter/gather vectors to which to store data.
+/// * `ri_flags` - Message flags.
+/// * `ro_fds` - Buffer the received file descriptors are stored in
+/// * `ro_fds_max` - Maximum number of file descriptors to receive
+///
+/// ## Return
+///
+/// Number of bytes stored in ri_data, message flags and the number of file
+/// descriptors stored in ro_fds.
+#[instrument(level = "trace", skip_all, fields(%sock, nread = field::Empty, nfds = field::Empty), ret)]
+pub fn sock_recv_fds<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    ri_data: WasmPtr<__wasi_iovec_t<M>, M>,
+    ri_data_len: M::Offset,
+    _ri_flags: RiFlags,
+    ro_data_len: WasmPtr<M::Offset, M>,
+    ro_flags: WasmPtr<RoFlags, M>,
+    ro_fds: WasmPtr<WasiFd, M>,
+    ro_fds_max: M::Offset,
+    ro_fds_len: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    let iovs_arr = wasi_try_mem_ok!(ri_data.slice(&memory, ri_data_len));
+    let ro_fds = wasi_try_mem_ok!(ro_fds.slice(&memory, ro_fds_max));
+
+    let max_size = {
+        let mut max_size = 0usize;
+        for iovs in iovs_arr.iter() {
+            let iovs = wasi_try_mem_ok!(iovs.read());
+            let buf_len: usize = wasi_try_ok!(iovs.buf_len.try_into().map_err(|_| Errno::Overflow));
+            max_size += buf_len;
+        }
+        max_size
+    };
+
+    let (data, mut fds) = wasi_try_ok!(__sock_asyncify(
+        env,
+        sock,
+        Rights::SOCK_RECV,
+        |socket, fd| async move {
+            let nonblocking = fd.flags.contains(Fdflags::NONBLOCK);
+            let timeout = socket
+                .opt_time(TimeType::ReadTimeout)
+                .ok()
+                .flatten()
+                .unwrap_or(Duration::from_secs(30));
+
+            let mut buf = vec![MaybeUninit::uninit(); max_size];
+            socket
+                .recv_fds(env.tasks().deref(), &mut buf, Some(timeout), nonblocking)
+                .await
+                .map(|(amt, fds)| {
+                    buf.truncate(amt);
+                    let buf: Vec<u8> = unsafe { std::mem::transmute(buf) };
+                    (buf, fds)
+                })
+        }
+    ));
+    Span::current()
+        .record("nread", data.len())
+        .record("nfds", fds.len());
+
+    let mut flags = 0;
+    if fds.len() as u64 > ro_fds.len() {
+        fds.truncate(ro_fds.len() as usize);
+        flags |= __WASI_SOCK_RECV_OUTPUT_FDS_TRUNCATED;
+    }
+
+    if !data.is_empty() {
+        wasi_try_ok!(read_bytes(&data[..], &memory, iovs_arr));
+    }
+
+    let mut received = Vec::with_capacity(fds.len());
+    for (idx, entry) in fds.into_iter().enumerate() {
+        let fd = wasi_try_ok!(state.fs.insert_fd(entry.clone()));
+        wasi_try_mem_ok!(ro_fds.write(idx as u64, fd));
+        received.push((fd, entry));
+    }
+    let nfds = received.len();
+
+    let bytes_read: M::Offset = wasi_try_ok!(data.len().try_into().map_err(|_| Errno::Overflow));
+    let nfds: M::Offset = wasi_try_ok!(nfds.try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem_ok!(ro_flags.write(&memory, flags));
+    wasi_try_mem_ok!(ro_data_len.write(&memory, bytes_read));
+    wasi_try_mem_ok!(ro_fds_len.write(&memory, nfds));
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        for (fd, entry) in received.iter() {
+            JournalEffector::save_fd_receive(&mut ctx, *fd, entry).map_err(|err| {
+                tracing::error!("failed to save sock_recv_fds event - {}", err);
+                WasiError::Exit(ExitCode::Errno(Errno::Fault))
+            })?;
+        }
+    }
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,94 @@
+use std::mem::MaybeUninit;
+
+use super::*;
+use crate::{net::socket::TimeType, syscalls::*};
+
+/// ### `sock_recv_from_unix()`
+/// Receive a message and the path of its sender from a UNIX socket.
+/// Note: This is similar to `recvfrom` in POSIX using PF_UNIX, though it also
+/// supports reading the data into multiple buffers in the manner of `readv`.
+///
+/// `ro_path_len` holds the size of the path buffer and is set to the length
+/// of the path, messages from unnamed sockets have an empty path. If the
+/// path exceeds the size of the buffer then this function will return ERANGE
+///
+/// ## Parameters
+///
+/// * `ri_data` - List of scatter/gather vectors to which to store data.
+/// * `ri_flags` - Message flags.
+///
+/// ## Return
+///
+/// Number of bytes stored in ri_data, message flags and the path of the
+/// sender.
+#[instrument(level = "trace", skip_all, fields(%sock, nread = field::Empty, peer = field::Empty), ret)]
+pub fn sock_recv_from_unix<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    ri_data: WasmPtr<__wasi_iovec_t<M>, M>,
+    ri_data_len: M::Offset,
+    _ri_flags: RiFlags,
+    ro_data_len: WasmPtr<M::Offset, M>,
+    ro_flags: WasmPtr<RoFlags, M>,
+    ro_path: WasmPtr<u8, M>,
+    ro_path_len: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let iovs_arr = wasi_try_mem_ok!(ri_data.slice(&memory, ri_data_len));
+
+    let max_size = {
+        let mut max_size = 0usize;
+        for iovs in iovs_arr.iter() {
+            let iovs = wasi_try_mem_ok!(iovs.read());
+            let buf_len: usize = wasi_try_ok!(iovs.buf_len.try_into().map_err(|_| Errno::Overflow));
+            max_size += buf_len;
+        }
+        max_size
+    };
+
+    let (data, peer) = wasi_try_ok!(__sock_asyncify(
+        env,
+        sock,
+        Rights::SOCK_RECV_FROM,
+        |socket, fd| async move {
+            let nonblocking = fd.flags.contains(Fdflags::NONBLOCK);
+            let timeout = socket
+                .opt_time(TimeType::ReadTimeout)
+                .ok()
+                .flatten()
+                .unwrap_or(Duration::from_secs(30));
+
+            let mut buf = vec![MaybeUninit::uninit(); max_size];
+            socket
+                .recv_from_unix(env.tasks().deref(), &mut buf, Some(timeout), nonblocking)
+                .await
+                .map(|(amt, peer)| {
+                    buf.truncate(amt);
+                    let buf: Vec<u8> = unsafe { std::mem::transmute(buf) };
+                    (buf, peer)
+                })
+        }
+    ));
+    Span::current()
+        .record("nread", data.len())
+        .record("peer", &format!("{:?}", peer));
+
+    if !data.is_empty() {
+        wasi_try_ok!(read_bytes(&data[..], &memory, iovs_arr));
+    }
+    wasi_try_ok!(crate::net::write_unix_path(
+        &memory,
+        ro_path,
+        ro_path_len,
+        peer.as_deref()
+    ));
+
+    let bytes_read: M::Offset = wasi_try_ok!(data.len().try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem_ok!(ro_flags.write(&memory, 0));
+    wasi_try_mem_ok!(ro_data_len.write(&memory, bytes_read));
+
+    Ok(Errno::Success)
+}
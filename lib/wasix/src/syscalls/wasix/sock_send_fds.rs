@@ -0,0 +1,104 @@
+use super::*;
+use crate::{
+    net::{socket::TimeType, unix::UNIX_MAX_FDS},
+    syscalls::*,
+};
+
+/// ### `sock_send_fds()`
+/// Send a message with file descriptors attached on a UNIX socket.
+/// Note: This is similar to `sendmsg` in POSIX with `SCM_RIGHTS`, though it
+/// also supports writing the data from multiple buffers in the manner of
+/// `writev`.
+///
+/// The receiver gets duplicates of the descriptors which share the file and
+/// the offset with the originals. At least one byte has to be sent along with
+/// the descriptors.
+///
+/// ## Parameters
+///
+/// * `si_data` - List of scatter/gather vectors to which to retrieve data
+/// * `si_flags` - Message flags.
+/// * `fds` - File descriptors to pass to the receiver
+///
+/// ## Return
+///
+/// Number of bytes transmitted.
+#[instrument(level = "trace", skip_all, fields(%sock, nfds = field::Empty, nsent = field::Empty), ret)]
+pub fn sock_send_fds<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    si_data: WasmPtr<__wasi_ciovec_t<M>, M>,
+    si_data_len: M::Offset,
+    si_flags: SiFlags,
+    fds: WasmPtr<WasiFd, M>,
+    fds_len: M::Offset,
+    ret_data_len: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+
+    let data = {
+        let iovs_arr = wasi_try_mem_ok!(si_data.slice(&memory, si_data_len));
+        let iovs_arr = wasi_try_mem_ok!(iovs_arr.access());
+
+        let mut data = Vec::new();
+        for iovs in iovs_arr.iter() {
+            let buf = wasi_try_mem_ok!(WasmPtr::<u8, M>::new(iovs.buf)
+                .slice(&memory, iovs.buf_len)
+                .and_then(|buf| buf.read_to_vec()));
+            data.extend_from_slice(&buf);
+        }
+        data
+    };
+
+    let fds = {
+        let fds = wasi_try_mem_ok!(fds.slice(&memory, fds_len));
+        let fds = wasi_try_mem_ok!(fds.read_to_vec());
+        if fds.len() > UNIX_MAX_FDS {
+            return Ok(Errno::Inval);
+        }
+        let mut entries = Vec::with_capacity(fds.len());
+        for fd in fds {
+            entries.push(wasi_try_ok!(state.fs.get_fd(fd)));
+        }
+        entries
+    };
+    Span::current().record("nfds", fds.len());
+
+    let bytes_written = wasi_try_ok!(__sock_asyncify(
+        env,
+        sock,
+        Rights::SOCK_SEND,
+        |socket, fd| async move {
+            let nonblocking = fd.flags.contains(Fdflags::NONBLOCK);
+            let timeout = socket
+                .opt_time(TimeType::WriteTimeout)
+                .ok()
+                .flatten()
+                .unwrap_or(Duration::from_secs(30));
+            socket
+                .send_fds(env.tasks().deref(), &data, fds, Some(timeout), nonblocking)
+                .await
+        },
+    ));
+    Span::current().record("nsent", bytes_written);
+
+    // Only the data is recorded, the descriptors that were passed with it
+    // can not be passed again when the journal is replayed
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_sock_send(&ctx, sock, bytes_written, si_data, si_data_len, si_flags)
+            .map_err(|err| {
+                tracing::error!("failed to save sock_send_fds event - {}", err);
+                WasiError::Exit(ExitCode::Errno(Errno::Fault))
+            })?;
+    }
+
+    let bytes_written: M::Offset =
+        wasi_try_ok!(bytes_written.try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem_ok!(ret_data_len.write(&memory, bytes_written));
+
+    Ok(Errno::Success)
+}
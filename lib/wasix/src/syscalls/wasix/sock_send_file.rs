@@ -177,7 +177,9 @@ pub(crate) fn sock_send_file_internal(
                             Kind::Dir { .. } | Kind::Root { .. } => {
                                 return Ok(Err(Errno::Isdir));
                             }
-                            Kind::EventNotifications { .. } => {
+                            Kind::EventNotifications { .. }
+                            | Kind::TimerFd { .. }
+                            | Kind::SignalFd { .. } => {
                                 return Ok(Err(Errno::Inval));
                             }
                             Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
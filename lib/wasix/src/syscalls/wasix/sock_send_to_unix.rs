@@ -0,0 +1,87 @@
+use super::*;
+use crate::{net::socket::TimeType, syscalls::*};
+
+/// ### `sock_send_to_unix()`
+/// Send a message on a UNIX datagram socket to the socket bound to a path.
+/// Note: This is similar to `sendto` in POSIX using PF_UNIX, though it also
+/// supports writing the data from multiple buffers in the manner of `writev`.
+///
+/// The buffers are sent as a single datagram
+///
+/// ## Parameters
+///
+/// * `si_data` - List of scatter/gather vectors to which to retrieve data
+/// * `si_flags` - Message flags.
+/// * `path` - Path of the socket to send message to
+///
+/// ## Return
+///
+/// Number of bytes transmitted.
+#[instrument(level = "trace", skip_all, fields(%sock, path = field::Empty, nsent = field::Empty), ret)]
+pub fn sock_send_to_unix<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    si_data: WasmPtr<__wasi_ciovec_t<M>, M>,
+    si_data_len: M::Offset,
+    _si_flags: SiFlags,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    ret_data_len: WasmPtr<M::Offset, M>,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    let current_dir = state.fs.current_dir.lock().unwrap().clone();
+    let path = wasi_try_ok!(crate::net::read_unix_path(
+        &memory,
+        path,
+        path_len,
+        &current_dir
+    ));
+    Span::current().record("path", &format!("{}", path.display()));
+
+    let data = {
+        let iovs_arr = wasi_try_mem_ok!(si_data.slice(&memory, si_data_len));
+        let iovs_arr = wasi_try_mem_ok!(iovs_arr.access());
+
+        let mut data = Vec::new();
+        for iovs in iovs_arr.iter() {
+            let buf = wasi_try_mem_ok!(WasmPtr::<u8, M>::new(iovs.buf)
+                .slice(&memory, iovs.buf_len)
+                .and_then(|buf| buf.read_to_vec()));
+            data.extend_from_slice(&buf);
+        }
+        data
+    };
+
+    let control_plane = env.process.compute.must_upgrade();
+    let bytes_written = wasi_try_ok!(__sock_asyncify(
+        env,
+        sock,
+        Rights::SOCK_SEND_TO,
+        |socket, fd| async move {
+            let nonblocking = fd.flags.contains(Fdflags::NONBLOCK);
+            let timeout = socket
+                .opt_time(TimeType::WriteTimeout)
+                .ok()
+                .flatten()
+                .unwrap_or(Duration::from_secs(30));
+            socket
+                .send_to_unix(
+                    env.tasks().deref(),
+                    control_plane.unix_sockets(),
+                    &data,
+                    &path,
+                    Some(timeout),
+                    nonblocking,
+                )
+                .await
+        },
+    ));
+    Span::current().record("nsent", bytes_written);
+
+    let bytes_written: M::Offset =
+        wasi_try_ok!(bytes_written.try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem_ok!(ret_data_len.write(&memory, bytes_written));
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,75 @@
+use super::*;
+use crate::{
+    fs::{NotificationInner, TimerFdInner},
+    syscalls::*,
+};
+
+/// ### `timerfd_create()`
+/// Creates a file descriptor for a timer that is disarmed until it is set
+/// with `timerfd_settime`, reading the descriptor returns the number of
+/// times that the timer expired since it was last read (as a 64-bit integer)
+///
+/// ## Parameters
+///
+/// * `clock_id` - Clock that the timer measures its expiration against
+///   (realtime or monotonic)
+/// * `flags` - Flags of the new descriptor (`NONBLOCK`)
+#[instrument(level = "trace", skip_all, fields(?clock_id, ret_fd = field::Empty), ret)]
+pub fn timerfd_create<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    clock_id: Snapshot0Clockid,
+    flags: Fdflags,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    let fd = wasi_try_ok!(timerfd_create_internal(&mut ctx, clock_id, flags));
+    Span::current().record("ret_fd", fd);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_timerfd_create(&mut ctx, fd, clock_id, flags).map_err(|err| {
+            tracing::error!("failed to save timerfd_create event - {}", err);
+            WasiError::Exit(ExitCode::Errno(Errno::Fault))
+        })?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_fd.write(&memory, fd));
+    Ok(Errno::Success)
+}
+
+pub(crate) fn timerfd_create_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    clock_id: Snapshot0Clockid,
+    flags: Fdflags,
+) -> Result<WasiFd, Errno> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    match clock_id {
+        Snapshot0Clockid::Realtime | Snapshot0Clockid::Monotonic => {}
+        _ => return Err(Errno::Inval),
+    }
+    let inner = Arc::new(NotificationInner::new(0, false));
+    let kind = Kind::TimerFd {
+        inner: inner.clone(),
+        timer: Arc::new(TimerFdInner::new(clock_id, inner)),
+    };
+
+    let inode =
+        state
+            .fs
+            .create_inode_with_default_stat(inodes, kind, false, "timerfd".to_string().into());
+    let rights = Rights::FD_READ | Rights::POLL_FD_READWRITE | Rights::FD_FDSTAT_SET_FLAGS;
+    state.fs.create_fd(rights, rights, flags, 0, inode)
+}
+
+/// Returns the timer of a timer descriptor
+pub(crate) fn timer_of_fd(env: &WasiEnv, fd: WasiFd) -> Result<Arc<TimerFdInner>, Errno> {
+    let fd_entry = env.state.fs.get_fd(fd)?;
+    let guard = fd_entry.inode.read();
+    match guard.deref() {
+        Kind::TimerFd { timer, .. } => Ok(timer.clone()),
+        _ => Err(Errno::Inval),
+    }
+}
@@ -0,0 +1,22 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `timerfd_gettime()`
+/// Returns the time left before the timer of a timer descriptor expires
+/// (zero when it is disarmed) and its interval
+#[instrument(level = "trace", skip_all, fields(%fd), ret)]
+pub fn timerfd_gettime<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    ret_value: WasmPtr<Timestamp, M>,
+    ret_interval: WasmPtr<Timestamp, M>,
+) -> Errno {
+    let env = ctx.data();
+    let timer = wasi_try!(timer_of_fd(env, fd));
+    let (value, interval) = wasi_try!(timer.get());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_value.write(&memory, value.as_nanos() as Timestamp));
+    wasi_try_mem!(ret_interval.write(&memory, interval.as_nanos() as Timestamp));
+    Errno::Success
+}
@@ -0,0 +1,69 @@
+use wasmer_wasix_types::wasi::Subclockflags;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `timerfd_settime()`
+/// Arms (or disarms) the timer of a timer descriptor, the expirations that
+/// were not read yet are discarded
+///
+/// ## Parameters
+///
+/// * `fd` - Descriptor created with `timerfd_create`
+/// * `flags` - When `SUBSCRIPTION_CLOCK_ABSTIME` is set then `value` is
+///   the time on the clock of the timer at which it expires
+/// * `value` - Time after which the timer expires, zero disarms the timer
+/// * `interval` - Time between the following expirations of the timer,
+///   zero for a timer that only expires once
+///
+/// ## Return
+///
+/// The time that was left on the timer and its interval
+#[instrument(level = "trace", skip_all, fields(%fd, ?flags, %value, %interval), ret)]
+pub fn timerfd_settime<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    flags: Subclockflags,
+    value: Timestamp,
+    interval: Timestamp,
+    ret_old_value: WasmPtr<Timestamp, M>,
+    ret_old_interval: WasmPtr<Timestamp, M>,
+) -> Result<Errno, WasiError> {
+    let absolute = flags.contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME);
+    let (old_value, old_interval) = wasi_try_ok!(timerfd_settime_internal(
+        &mut ctx, fd, value, interval, absolute
+    ));
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_timerfd_settime(&mut ctx, fd, value, interval, absolute).map_err(
+            |err| {
+                tracing::error!("failed to save timerfd_settime event - {}", err);
+                WasiError::Exit(ExitCode::Errno(Errno::Fault))
+            },
+        )?;
+    }
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_old_value.write(&memory, old_value.as_nanos() as Timestamp));
+    wasi_try_mem_ok!(ret_old_interval.write(&memory, old_interval.as_nanos() as Timestamp));
+    Ok(Errno::Success)
+}
+
+pub(crate) fn timerfd_settime_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    value: Timestamp,
+    interval: Timestamp,
+    absolute: bool,
+) -> Result<(Duration, Duration), Errno> {
+    let env = ctx.data();
+    let timer = timer_of_fd(env, fd)?;
+    timer.set(
+        env.tasks(),
+        Duration::from_nanos(value),
+        Duration::from_nanos(interval),
+        absolute,
+    )
+}
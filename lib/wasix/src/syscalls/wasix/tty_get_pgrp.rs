@@ -0,0 +1,23 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `tty_get_pgrp()`
+/// Returns the foreground process group of the terminal that controls
+/// the session of the calling process
+#[instrument(level = "trace", skip_all, fields(pgid = field::Empty), ret)]
+pub fn tty_get_pgrp<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_pgid: WasmPtr<Pid, M>,
+) -> Errno {
+    let env = ctx.data();
+    let sid = wasi_try!(env
+        .control_plane
+        .session(env.process.pid())
+        .ok_or(Errno::Srch));
+    let pgid = env.control_plane.foreground_group(sid);
+    Span::current().record("pgid", pgid.raw());
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_pgid.write(&memory, pgid.raw() as Pid));
+    Errno::Success
+}
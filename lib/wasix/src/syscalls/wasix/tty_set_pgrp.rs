@@ -0,0 +1,23 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `tty_set_pgrp()`
+/// Moves a process group into the foreground of the terminal that controls
+/// the session of the calling process, the foreground group is the one
+/// that receives the signals raised by the terminal (e.g. Ctrl-C)
+///
+/// ## Parameters
+///
+/// * `pgid` - Process group of the session to move into the foreground
+#[instrument(level = "trace", skip_all, fields(%pgid), ret)]
+pub fn tty_set_pgrp(ctx: FunctionEnvMut<'_, WasiEnv>, pgid: Pid) -> Errno {
+    let env = ctx.data();
+    let sid = wasi_try!(env
+        .control_plane
+        .session(env.process.pid())
+        .ok_or(Errno::Srch));
+    wasi_try!(env
+        .control_plane
+        .set_foreground_group(sid, WasiProcessId::from(pgid)));
+    Errno::Success
+}
@@ -0,0 +1,90 @@
+//! Fixture shared by the tests that run small modules, written in the
+//! WebAssembly text format, against the syscalls of WASIX
+#![allow(dead_code)]
+
+use wasmer::{wat2wasm, Instance, Module, Store, Value};
+use wasmer_wasix::{virtual_fs::TmpFileSystem, WasiEnv, WasiEnvBuilder, WasiFunctionEnv};
+
+/// Creates the runtime that the tests enter before they create a [`Guest`]
+pub fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Gives the guest a file system of its own whose root is preopened (for
+/// reading and writing) as descriptor 4
+pub fn with_root(mut builder: WasiEnvBuilder, fs: TmpFileSystem) -> WasiEnvBuilder {
+    builder.add_preopen_dir("/").unwrap();
+    builder.sandbox_fs(fs)
+}
+
+/// A module that was instantiated in a store of its own
+pub struct Guest {
+    pub store: Store,
+    pub instance: Instance,
+    pub env: WasiFunctionEnv,
+}
+
+impl Guest {
+    pub fn new(wat: &str, builder: WasiEnvBuilder) -> Self {
+        let wasm = wat2wasm(wat.as_bytes()).unwrap();
+        let mut store = Store::default();
+        let module = Module::new(&store, wasm).unwrap();
+        let (instance, env) = builder.instantiate(module, &mut store).unwrap();
+        Self {
+            store,
+            instance,
+            env,
+        }
+    }
+
+    /// Creates a guest with an empty file system (see [`with_root`])
+    pub fn with_tmp_fs(wat: &str, builder: WasiEnvBuilder) -> Self {
+        Self::new(wat, with_root(builder, TmpFileSystem::new()))
+    }
+
+    pub fn env(&self) -> &WasiEnv {
+        self.env.data(&self.store)
+    }
+
+    /// Calls a function that returns an errno
+    pub fn call(&mut self, func: &str, args: &[Value]) -> i32 {
+        let func = self.instance.exports.get_function(func).unwrap();
+        func.call(&mut self.store, args).unwrap()[0].unwrap_i32()
+    }
+
+    /// Calls a function that returns an errno, or the 32-bit number that it
+    /// stored at `offset` when it succeeded
+    pub fn call_u32(&mut self, func: &str, args: &[Value], offset: u64) -> Result<u32, i32> {
+        match self.call(func, args) {
+            0 => Ok(self.u32_at(offset)),
+            err => Err(err),
+        }
+    }
+
+    pub fn memory_read(&self, offset: u64, len: usize) -> Vec<u8> {
+        let memory = self.instance.exports.get_memory("memory").unwrap();
+        let mut data = vec![0u8; len];
+        memory.view(&self.store).read(offset, &mut data).unwrap();
+        data
+    }
+
+    pub fn memory_write(&self, offset: u64, data: &[u8]) {
+        let memory = self.instance.exports.get_memory("memory").unwrap();
+        memory.view(&self.store).write(offset, data).unwrap();
+    }
+
+    pub fn u32_at(&self, offset: u64) -> u32 {
+        u32::from_le_bytes(self.memory_read(offset, 4).try_into().unwrap())
+    }
+
+    pub fn u64_at(&self, offset: u64) -> u64 {
+        u64::from_le_bytes(self.memory_read(offset, 8).try_into().unwrap())
+    }
+
+    pub fn string_at(&self, offset: u64, len: usize) -> String {
+        String::from_utf8(self.memory_read(offset, len)).unwrap()
+    }
+}
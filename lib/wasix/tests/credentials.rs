@@ -0,0 +1,268 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use std::path::Path;
+
+use common::{runtime, with_root, Guest};
+use tokio::io::AsyncWriteExt;
+use wasmer::Value;
+use wasmer_wasix::{
+    virtual_fs::{FileSystem, TmpFileSystem},
+    wasmer_wasix_types::wasi::Errno,
+    WasiEnv,
+};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "proc_getuid" (func $proc_getuid (param i32) (result i32)))
+    (import "wasix_32v1" "proc_geteuid" (func $proc_geteuid (param i32) (result i32)))
+    (import "wasix_32v1" "proc_getgid" (func $proc_getgid (param i32) (result i32)))
+    (import "wasix_32v1" "proc_getgroups"
+        (func $proc_getgroups (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_setuid" (func $proc_setuid (param i32) (result i32)))
+    (import "wasix_32v1" "proc_seteuid" (func $proc_seteuid (param i32) (result i32)))
+    (import "wasix_32v1" "proc_setgroups" (func $proc_setgroups (param i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open"
+        (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_create_directory"
+        (func $path_create_directory (param i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_rename"
+        (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; The IDs are stored at 0
+    (func (export "getuid") (result i32) (call $proc_getuid (i32.const 0)))
+    (func (export "geteuid") (result i32) (call $proc_geteuid (i32.const 0)))
+    (func (export "getgid") (result i32) (call $proc_getgid (i32.const 0)))
+    (func (export "setuid") (param $uid i32) (result i32) (call $proc_setuid (local.get $uid)))
+    (func (export "seteuid") (param $uid i32) (result i32) (call $proc_seteuid (local.get $uid)))
+
+    ;; The groups are stored at 512 and their number at 0
+    (func (export "getgroups") (result i32)
+        (call $proc_getgroups (i32.const 512) (i32.const 16) (i32.const 0)))
+    (func (export "setgroups") (param $len i32) (result i32)
+        (call $proc_setgroups (i32.const 512) (local.get $len)))
+
+    ;; Opens the file whose path is stored at 256 (relative to the root that
+    ;; is preopened as descriptor 4) with some rights, the descriptor is
+    ;; stored at 0
+    (func (export "open") (param $len i32) (param $rights i64) (result i32)
+        (call $path_open (i32.const 4) (i32.const 0) (i32.const 256) (local.get $len)
+            (i32.const 0) (local.get $rights) (i64.const 0) (i32.const 0) (i32.const 0)))
+
+    ;; Creates the file whose path is stored at 256, the descriptor is
+    ;; stored at 0
+    (func (export "create") (param $len i32) (result i32)
+        (call $path_open (i32.const 4) (i32.const 0) (i32.const 256) (local.get $len)
+            (i32.const 1) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 0)))
+
+    ;; Creates the directory whose path is stored at 256
+    (func (export "mkdir") (param $len i32) (result i32)
+        (call $path_create_directory (i32.const 4) (i32.const 256) (local.get $len)))
+
+    ;; Moves the file whose path is stored at 256 to the path stored at 384
+    (func (export "rename") (param $len i32) (param $new_len i32) (result i32)
+        (call $path_rename (i32.const 4) (i32.const 256) (local.get $len)
+            (i32.const 4) (i32.const 384) (local.get $new_len)))
+
+    ;; Reads the file that was opened into 1024, the number of bytes read
+    ;; is stored at 8
+    (func (export "read") (result i32)
+        (i32.store (i32.const 16) (i32.const 1024))
+        (i32.store (i32.const 20) (i32.const 1024))
+        (call $fd_read (i32.load (i32.const 0)) (i32.const 16) (i32.const 1) (i32.const 8)))
+
+    (func (export "_start"))
+)
+"#;
+
+const FD_READ: i64 = 1 << 1;
+const FD_WRITE: i64 = 1 << 6;
+
+fn call(guest: &mut Guest, func: &str, args: &[Value]) -> Result<u32, i32> {
+    guest.call_u32(func, args, 0)
+}
+
+fn groups(guest: &mut Guest) -> Vec<u32> {
+    let count = call(guest, "getgroups", &[]).unwrap();
+    (0..count as u64)
+        .map(|i| guest.u32_at(512 + i * 4))
+        .collect()
+}
+
+fn set_groups(guest: &mut Guest, groups: &[u32]) -> Result<u32, i32> {
+    let data: Vec<u8> = groups.iter().flat_map(|g| g.to_le_bytes()).collect();
+    guest.memory_write(512, &data);
+    call(guest, "setgroups", &[Value::I32(groups.len() as i32)])
+}
+
+fn open(guest: &mut Guest, path: &str, rights: i64) -> Result<u32, i32> {
+    guest.memory_write(256, path.as_bytes());
+    call(
+        guest,
+        "open",
+        &[Value::I32(path.len() as i32), Value::I64(rights)],
+    )
+}
+
+fn path_call(guest: &mut Guest, func: &str, path: &str) -> i32 {
+    guest.memory_write(256, path.as_bytes());
+    guest.call(func, &[Value::I32(path.len() as i32)])
+}
+
+fn rename(guest: &mut Guest, from: &str, to: &str) -> i32 {
+    guest.memory_write(256, from.as_bytes());
+    guest.memory_write(384, to.as_bytes());
+    let len = |path: &str| Value::I32(path.len() as i32);
+    guest.call("rename", &[len(from), len(to)])
+}
+
+fn read_file(guest: &mut Guest, path: &str) -> String {
+    open(guest, path, FD_READ).unwrap();
+    assert_eq!(guest.call("read", &[]), 0);
+    let len = guest.u32_at(8) as usize;
+    guest.string_at(1024, len)
+}
+
+#[test]
+fn test_processes_run_as_root_by_default() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("credentials"));
+
+    assert_eq!(call(&mut guest, "getuid", &[]), Ok(0));
+    assert_eq!(call(&mut guest, "getgid", &[]), Ok(0));
+    assert_eq!(groups(&mut guest), Vec::<u32>::new());
+
+    // Root may change its groups and drop its privileges
+    set_groups(&mut guest, &[10, 20]).unwrap();
+    assert_eq!(groups(&mut guest), vec![10, 20]);
+    call(&mut guest, "seteuid", &[Value::I32(1000)]).unwrap();
+    assert_eq!(call(&mut guest, "geteuid", &[]), Ok(1000));
+    assert_eq!(call(&mut guest, "getuid", &[]), Ok(0));
+    call(&mut guest, "seteuid", &[Value::I32(0)]).unwrap();
+
+    assert_eq!(
+        read_file(&mut guest, "etc/passwd"),
+        "root:x:0:0:root:/root:/bin/sh\n"
+    );
+}
+
+#[test]
+fn test_unprivileged_user() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(
+        WAT,
+        WasiEnv::builder("credentials").user(1000, 100).groups([20]),
+    );
+
+    assert_eq!(call(&mut guest, "getuid", &[]), Ok(1000));
+    assert_eq!(call(&mut guest, "geteuid", &[]), Ok(1000));
+    assert_eq!(call(&mut guest, "getgid", &[]), Ok(100));
+    assert_eq!(groups(&mut guest), vec![20]);
+
+    // The user can not become someone else
+    assert_eq!(
+        call(&mut guest, "setuid", &[Value::I32(0)]),
+        Err(Errno::Perm as i32)
+    );
+    assert_eq!(set_groups(&mut guest, &[0]), Err(Errno::Perm as i32));
+    call(&mut guest, "setuid", &[Value::I32(1000)]).unwrap();
+
+    // The user database describes the user...
+    assert_eq!(
+        read_file(&mut guest, "etc/passwd"),
+        "root:x:0:0:root:/root:/bin/sh\nuser:x:1000:100:user:/home/user:/bin/sh\n"
+    );
+    assert_eq!(
+        read_file(&mut guest, "etc/group"),
+        "root:x:0:\ngroup20:x:20:user\ngroup100:x:100:user\n"
+    );
+
+    // ...but only root may change it
+    assert_eq!(
+        open(&mut guest, "etc/passwd", FD_READ | FD_WRITE),
+        Err(Errno::Access as i32)
+    );
+}
+
+#[test]
+fn test_created_files_are_owned_by_their_creator() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("credentials"));
+
+    assert_eq!(path_call(&mut guest, "create", "secret"), 0);
+    assert_eq!(path_call(&mut guest, "mkdir", "private"), 0);
+    call(&mut guest, "seteuid", &[Value::I32(1000)]).unwrap();
+
+    // Others may only read the files of root...
+    assert_eq!(
+        open(&mut guest, "secret", FD_READ | FD_WRITE),
+        Err(Errno::Access as i32)
+    );
+    open(&mut guest, "secret", FD_READ).unwrap();
+
+    // ...and can't change the directories of root
+    assert_eq!(
+        path_call(&mut guest, "create", "private/file"),
+        Errno::Access as i32
+    );
+    assert_eq!(
+        path_call(&mut guest, "mkdir", "etc/dir"),
+        Errno::Access as i32
+    );
+    assert_eq!(
+        rename(&mut guest, "etc/passwd", "passwd"),
+        Errno::Access as i32
+    );
+
+    // The owners move along with the files
+    call(&mut guest, "seteuid", &[Value::I32(0)]).unwrap();
+    assert_eq!(rename(&mut guest, "secret", "moved"), 0);
+    call(&mut guest, "seteuid", &[Value::I32(1000)]).unwrap();
+    assert_eq!(
+        open(&mut guest, "moved", FD_READ | FD_WRITE),
+        Err(Errno::Access as i32)
+    );
+}
+
+#[test]
+fn test_existing_user_database_keeps_its_owner() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let fs = TmpFileSystem::new();
+    fs.create_dir(Path::new("/etc")).unwrap();
+    let mut passwd = fs
+        .new_open_options()
+        .create_new(true)
+        .write(true)
+        .open(Path::new("/etc/passwd"))
+        .unwrap();
+    runtime
+        .block_on(passwd.write_all(b"admin:x:0:0:admin:/:/bin/sh\n"))
+        .unwrap();
+    let mut guest = Guest::new(
+        WAT,
+        with_root(WasiEnv::builder("credentials").user(1000, 100), fs),
+    );
+
+    // The existing files are kept as they are...
+    assert_eq!(
+        read_file(&mut guest, "etc/passwd"),
+        "admin:x:0:0:admin:/:/bin/sh\n"
+    );
+    open(&mut guest, "etc/passwd", FD_READ | FD_WRITE).unwrap();
+    assert_eq!(path_call(&mut guest, "mkdir", "etc/dir"), 0);
+
+    // ...and only the synthesized ones are owned by root
+    assert_eq!(
+        open(&mut guest, "etc/group", FD_READ | FD_WRITE),
+        Err(Errno::Access as i32)
+    );
+}
@@ -0,0 +1,204 @@
+#![cfg(feature = "sys-thread")]
+
+use std::time::Duration;
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::{types::Signal, wasmer_wasix_types::wasi::Errno, WasiEnv};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "timerfd_create"
+        (func $timerfd_create (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "timerfd_settime"
+        (func $timerfd_settime (param i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasix_32v1" "timerfd_gettime"
+        (func $timerfd_gettime (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "signalfd" (func $signalfd (param i32 i64 i32 i32) (result i32)))
+    (import "wasix_32v1" "epoll_create" (func $epoll_create (param i32) (result i32)))
+    (import "wasix_32v1" "epoll_ctl" (func $epoll_ctl (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "epoll_wait"
+        (func $epoll_wait (param i32 i32 i32 i64 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "poll_oneoff"
+        (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "proc_raise" (func $proc_raise (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; All of these store their first result at 0 and the second one at 8
+    (func (export "timerfd_create") (param $flags i32) (result i32)
+        (call $timerfd_create (i32.const 1) (local.get $flags) (i32.const 0)))
+    (func (export "timerfd_settime") (param $fd i32) (param $value i64) (param $interval i64)
+        (result i32)
+        (call $timerfd_settime (local.get $fd) (i32.const 0) (local.get $value)
+            (local.get $interval) (i32.const 0) (i32.const 8)))
+    (func (export "timerfd_gettime") (param $fd i32) (result i32)
+        (call $timerfd_gettime (local.get $fd) (i32.const 0) (i32.const 8)))
+    (func (export "signalfd") (param $fd i32) (param $mask i64) (param $flags i32) (result i32)
+        (call $signalfd (local.get $fd) (local.get $mask) (local.get $flags) (i32.const 0)))
+    (func (export "epoll_create") (result i32)
+        (call $epoll_create (i32.const 0)))
+    (func (export "raise") (param $sig i32) (result i32)
+        (call $proc_raise (local.get $sig)))
+
+    ;; Registers the descriptor for EPOLLIN with the event that is stored at 64
+    (func (export "epoll_add") (param $epfd i32) (param $fd i32) (result i32)
+        (i32.store (i32.const 64) (i32.const 1))
+        (i32.store (i32.const 76) (local.get $fd))
+        (call $epoll_ctl (local.get $epfd) (i32.const 0) (local.get $fd) (i32.const 64)))
+    ;; The events are stored at 128
+    (func (export "epoll_wait") (param $epfd i32) (param $timeout i64) (result i32)
+        (call $epoll_wait (local.get $epfd) (i32.const 128) (i32.const 4) (local.get $timeout)
+            (i32.const 0)))
+
+    ;; Waits for the descriptor to be readable with the subscription that is
+    ;; stored at 256, the event is stored at 320
+    (func (export "poll") (param $fd i32) (result i32)
+        (i64.store (i32.const 256) (i64.const 42))
+        (i32.store8 (i32.const 264) (i32.const 1))
+        (i32.store (i32.const 272) (local.get $fd))
+        (call $poll_oneoff (i32.const 256) (i32.const 320) (i32.const 1) (i32.const 0)))
+
+    ;; Reads into the buffer at 1024
+    (func (export "read") (param $fd i32) (param $len i32) (result i32)
+        (i32.store (i32.const 16) (i32.const 1024))
+        (i32.store (i32.const 20) (local.get $len))
+        (call $fd_read (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 0)))
+
+    (func (export "_start"))
+)
+"#;
+
+const NONBLOCK: i32 = 4;
+
+/// Reads from a descriptor, returning the number of bytes that were read
+fn read(guest: &mut Guest, fd: u32, len: u32) -> Result<u32, i32> {
+    guest.call_u32("read", &[Value::I32(fd as i32), Value::I32(len as i32)], 0)
+}
+
+fn settime(guest: &mut Guest, fd: u32, value: Duration, interval: Duration) -> (u64, u64) {
+    let args = [
+        Value::I32(fd as i32),
+        Value::I64(value.as_nanos() as i64),
+        Value::I64(interval.as_nanos() as i64),
+    ];
+    assert_eq!(guest.call("timerfd_settime", &args), 0);
+    (guest.u64_at(0), guest.u64_at(8))
+}
+
+#[test]
+fn test_timerfd() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::new(WAT, WasiEnv::builder("event-fds"));
+
+    assert_eq!(guest.call("timerfd_create", &[Value::I32(NONBLOCK)]), 0);
+    let fd = guest.u32_at(0);
+
+    // Timers start disarmed
+    assert_eq!(read(&mut guest, fd, 8), Err(Errno::Again as i32));
+    assert_eq!(guest.call("timerfd_gettime", &[Value::I32(fd as i32)]), 0);
+    assert_eq!((guest.u64_at(0), guest.u64_at(8)), (0, 0));
+
+    let value = Duration::from_millis(50);
+    assert_eq!(settime(&mut guest, fd, value, Duration::ZERO), (0, 0));
+    assert_eq!(guest.call("timerfd_gettime", &[Value::I32(fd as i32)]), 0);
+    let left = guest.u64_at(0);
+    assert!(left > 0 && left <= value.as_nanos() as u64);
+
+    // The descriptor becomes readable once the timer expired
+    assert_eq!(guest.call("poll", &[Value::I32(fd as i32)]), 0);
+    assert_eq!(guest.u32_at(0), 1);
+    assert_eq!(guest.u64_at(320), 42);
+    assert_eq!(guest.memory_read(328, 2), [0, 0]);
+    assert_eq!(guest.u64_at(336), 1);
+
+    assert_eq!(read(&mut guest, fd, 8), Ok(8));
+    assert_eq!(guest.u64_at(1024), 1);
+    assert_eq!(read(&mut guest, fd, 8), Err(Errno::Again as i32));
+
+    // Periodic timers count every expiration
+    let interval = Duration::from_millis(10);
+    settime(&mut guest, fd, interval, interval);
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(read(&mut guest, fd, 8), Ok(8));
+    assert!(guest.u64_at(1024) >= 2);
+
+    let (_, old_interval) = settime(&mut guest, fd, Duration::ZERO, Duration::ZERO);
+    assert_eq!(old_interval, interval.as_nanos() as u64);
+    assert_eq!(read(&mut guest, fd, 8), Err(Errno::Again as i32));
+}
+
+#[test]
+fn test_signalfd() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::new(WAT, WasiEnv::builder("event-fds"));
+
+    let mask = 1i64 << (Signal::Sigusr1 as i64 - 1);
+    let args = [Value::I32(-1), Value::I64(mask), Value::I32(NONBLOCK)];
+    assert_eq!(guest.call("signalfd", &args), 0);
+    let fd = guest.u32_at(0);
+    assert_eq!(read(&mut guest, fd, 128), Err(Errno::Again as i32));
+
+    // Only signal descriptors can have their signals changed
+    let args = [Value::I32(1), Value::I64(mask), Value::I32(0)];
+    assert_eq!(guest.call("signalfd", &args), Errno::Inval as i32);
+
+    assert_eq!(guest.call("epoll_create", &[]), 0);
+    let epfd = guest.u32_at(0);
+    assert_eq!(
+        guest.call(
+            "epoll_add",
+            &[Value::I32(epfd as i32), Value::I32(fd as i32)]
+        ),
+        0
+    );
+
+    // The descriptor is not readable until a signal is received
+    let timeout = Value::I64(Duration::from_millis(10).as_nanos() as i64);
+    assert_eq!(
+        guest.call("epoll_wait", &[Value::I32(epfd as i32), timeout.clone()]),
+        0
+    );
+    assert_eq!(guest.u32_at(0), 0);
+
+    // The signal is received by the descriptor instead of the thread
+    assert_eq!(
+        guest.call("raise", &[Value::I32(Signal::Sigusr1 as i32)]),
+        0
+    );
+    assert!(!guest.env().thread.has_signal(&[Signal::Sigusr1]));
+
+    assert_eq!(
+        guest.call("epoll_wait", &[Value::I32(epfd as i32), timeout]),
+        0
+    );
+    assert_eq!(guest.u32_at(0), 1);
+    assert_eq!(guest.u32_at(128), 1);
+    assert_eq!(guest.u32_at(140), fd);
+
+    // Every signal is read as a record that starts with its number
+    assert_eq!(read(&mut guest, fd, 64), Err(Errno::Inval as i32));
+    assert_eq!(read(&mut guest, fd, 256), Ok(128));
+    assert_eq!(guest.u32_at(1024), Signal::Sigusr1 as u32);
+    assert_eq!(read(&mut guest, fd, 128), Err(Errno::Again as i32));
+
+    // Signals sent to a particular thread are received as well
+    let tid = guest.env().thread.tid();
+    guest.env().process.signal_thread(&tid, Signal::Sigusr1);
+    assert!(!guest.env().thread.has_signal(&[Signal::Sigusr1]));
+    assert_eq!(read(&mut guest, fd, 128), Ok(128));
+    assert_eq!(guest.u32_at(1024), Signal::Sigusr1 as u32);
+
+    // A forked process receives its signals on the descriptors it inherits
+    let (child, _handle) = guest.env().fork().unwrap();
+    child.process.signal_process(Signal::Sigusr1);
+    assert!(!child.thread.has_signal(&[Signal::Sigusr1]));
+    assert_eq!(read(&mut guest, fd, 128), Ok(128));
+    assert_eq!(guest.u32_at(1024), Signal::Sigusr1 as u32);
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "sys-thread")]
+
+use std::io::Write;
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::{
+    fs::Kind,
+    types::wasi::{Fdflags, Rights},
+    Pipe, WasiEnv,
+};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "sock_pair"
+        (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_send_fds"
+        (func $sock_send_fds (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_recv_fds"
+        (func $sock_recv_fds (param i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+    (data (i32.const 64) "ping")
+
+    ;; Creates two pairs of connected UNIX stream sockets, the descriptors
+    ;; are stored at 0 and 4 for the first pair and at 8 and 12 for the
+    ;; second one
+    (func (export "pairs") (result i32)
+        (local $ret i32)
+        (local.set $ret (call $sock_pair (i32.const 3) (i32.const 1) (i32.const 0)
+            (i32.const 0) (i32.const 4)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (call $sock_pair (i32.const 3) (i32.const 1) (i32.const 0)
+            (i32.const 8) (i32.const 12)))
+
+    ;; Passes the descriptor stored at 8 over the first pair, the received
+    ;; descriptors are stored at 48 and their number at 52
+    (func (export "pass") (result i32)
+        (local $ret i32)
+        (i32.store (i32.const 16) (i32.const 64))
+        (i32.store (i32.const 20) (i32.const 1))
+        (local.set $ret (call $sock_send_fds (i32.load (i32.const 0)) (i32.const 16)
+            (i32.const 1) (i32.const 0) (i32.const 8) (i32.const 1) (i32.const 24)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (i32.store (i32.const 32) (i32.const 128))
+        (i32.store (i32.const 36) (i32.const 16))
+        (call $sock_recv_fds (i32.load (i32.const 4)) (i32.const 32) (i32.const 1)
+            (i32.const 0) (i32.const 40) (i32.const 44) (i32.const 48) (i32.const 1)
+            (i32.const 52)))
+
+    ;; Writes "ping" to a descriptor
+    (func (export "send") (param $fd i32) (result i32)
+        (i32.store (i32.const 16) (i32.const 64))
+        (i32.store (i32.const 20) (i32.const 4))
+        (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 24)))
+
+    ;; Reads up to 16 bytes at 128 from a descriptor
+    (func (export "recv") (param $fd i32) (result i32)
+        (i32.store (i32.const 32) (i32.const 128))
+        (i32.store (i32.const 36) (i32.const 16))
+        (call $fd_read (local.get $fd) (i32.const 32) (i32.const 1) (i32.const 40)))
+
+    (func (export "_start"))
+)
+"#;
+
+fn instantiate() -> Guest {
+    Guest::new(WAT, WasiEnv::builder("fd-passing"))
+}
+
+/// Sends "ping" on one descriptor and checks that it arrives on the other
+fn ping(guest: &mut Guest, from: Option<u32>, to: u32) {
+    if let Some(from) = from {
+        assert_eq!(guest.call("send", &[Value::I32(from as i32)]), 0);
+    }
+    assert_eq!(guest.call("recv", &[Value::I32(to as i32)]), 0);
+    assert_eq!(guest.u32_at(40), 4);
+    assert_eq!(guest.memory_read(128, 4), b"ping");
+}
+
+#[test]
+fn test_pass_fd_over_unix_socket() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = instantiate();
+    assert_eq!(guest.call("pairs", &[]), 0);
+    assert_eq!(guest.call("pass", &[]), 0);
+
+    // one byte of data and one descriptor were received
+    assert_eq!(guest.u32_at(40), 1);
+    assert_eq!(guest.u32_at(52), 1);
+    let passed = guest.u32_at(48);
+    assert_ne!(passed, guest.u32_at(8));
+
+    // the received descriptor is another end of the second pair
+    let peer = guest.u32_at(12);
+    ping(&mut guest, Some(passed), peer);
+}
+
+#[test]
+fn test_host_inserts_fds() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = instantiate();
+    assert_eq!(guest.call("pairs", &[]), 0);
+    let process = guest.env().process.clone();
+
+    // duplicate an existing descriptor of the guest
+    let fd = process.fd(guest.u32_at(0)).unwrap();
+    let dup = process.insert_fd(fd).unwrap();
+    let peer = guest.u32_at(4);
+    ping(&mut guest, Some(dup), peer);
+
+    // hand a pipe of the host over to the guest
+    let (mut host, pipe) = Pipe::channel();
+    let injected = process
+        .inject_fd(Kind::Pipe { pipe }, Rights::all(), Fdflags::empty())
+        .unwrap();
+    host.write_all(b"ping").unwrap();
+    ping(&mut guest, None, injected);
+}
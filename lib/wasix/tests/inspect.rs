@@ -0,0 +1,45 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer_wasix::{
+    os::task::inspect::WasiThreadActivity, types::Signal, WasiControlPlane, WasiEnv,
+};
+
+#[test]
+fn test_hosts_inspect_the_processes_of_their_control_plane() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+
+    let control_plane = WasiControlPlane::default();
+    let guest = Guest::new(
+        r#"(module (memory (export "memory") 1) (func (export "_start")))"#,
+        WasiEnv::builder("inspect")
+            .arg("--verbose")
+            .control_plane(control_plane.clone()),
+    );
+    let pid = guest.env().pid();
+
+    let processes = control_plane.processes();
+    assert_eq!(processes.len(), 1);
+    let process = &processes[0];
+    assert_eq!(process.pid, pid);
+    assert_eq!(process.name, "inspect");
+    assert_eq!(process.args, ["inspect", "--verbose"]);
+    assert_eq!(process.threads.len(), 1);
+    assert!(process.threads[0].is_main);
+    assert_eq!(process.threads[0].activity, WasiThreadActivity::Running);
+    assert_eq!(
+        process
+            .fds
+            .iter()
+            .map(|fd| fd.fd)
+            .take(3)
+            .collect::<Vec<_>>(),
+        [0, 1, 2]
+    );
+
+    control_plane.signal_process(pid, Signal::Sigusr1).unwrap();
+    assert!(guest.env().thread.has_signal(&[Signal::Sigusr1]));
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::{
+    wasmer_wasix_types::wasi::{Errno, JoinFlags, JoinStatusType, Signal},
+    WasiEnv,
+};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "proc_getpgid" (func $proc_getpgid (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_getsid" (func $proc_getsid (param i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_setsid" (func $proc_setsid (param i32) (result i32)))
+    (import "wasix_32v1" "proc_setpgid" (func $proc_setpgid (param i32 i32) (result i32)))
+    (import "wasix_32v1" "tty_get_pgrp" (func $tty_get_pgrp (param i32) (result i32)))
+    (import "wasix_32v1" "tty_set_pgrp" (func $tty_set_pgrp (param i32) (result i32)))
+    (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; Each of these store the returned ID at 0
+    (func (export "getpgid") (result i32)
+        (call $proc_getpgid (i32.const 0) (i32.const 0)))
+    (func (export "getsid") (result i32)
+        (call $proc_getsid (i32.const 0) (i32.const 0)))
+    (func (export "setsid") (result i32)
+        (call $proc_setsid (i32.const 0)))
+    (func (export "tcgetpgrp") (result i32)
+        (call $tty_get_pgrp (i32.const 0)))
+
+    (func (export "setpgid") (param $pid i32) (param $pgid i32) (result i32)
+        (call $proc_setpgid (local.get $pid) (local.get $pgid)))
+    (func (export "tcsetpgrp") (param $pgid i32) (result i32)
+        (call $tty_set_pgrp (local.get $pgid)))
+
+    ;; Joins a child (any child when the ID is 0), storing its ID at 0
+    ;; and its status at 8
+    (func (export "join") (param $pid i32) (param $flags i32) (result i32)
+        (i32.store (i32.const 0) (i32.ne (local.get $pid) (i32.const 0)))
+        (i32.store (i32.const 4) (local.get $pid))
+        (call $proc_join (i32.const 0) (local.get $flags) (i32.const 8)))
+
+    (func (export "_start"))
+)
+"#;
+
+#[test]
+fn test_job_control() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::new(WAT, WasiEnv::builder("job-control"));
+    let pid = guest.env().process.pid().raw();
+
+    // Each of these store the returned ID at 0
+    let mut call_id = |func: &str| guest.call_u32(func, &[], 0);
+
+    // The process was started on its own so it leads its group and session
+    assert_eq!(call_id("getpgid"), Ok(pid));
+    assert_eq!(call_id("getsid"), Ok(pid));
+    assert_eq!(call_id("tcgetpgrp"), Ok(pid));
+    assert_eq!(call_id("setsid"), Err(Errno::Perm as i32));
+
+    let id = |id: u32| Value::I32(id as i32);
+    assert_eq!(guest.call("setpgid", &[id(0), id(0)]), 0);
+    assert_eq!(
+        guest.call("setpgid", &[id(pid + 1000), id(0)]),
+        Errno::Srch as i32
+    );
+
+    assert_eq!(guest.call("tcsetpgrp", &[id(pid)]), 0);
+    assert_eq!(
+        guest.call("tcsetpgrp", &[id(pid + 1000)]),
+        Errno::Perm as i32
+    );
+}
+
+#[test]
+fn test_stopped_children() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::new(WAT, WasiEnv::builder("job-control"));
+    let (child, _handle) = guest.env().fork().unwrap();
+    let child = child.process;
+    guest.env().process.lock().children.push(child.clone());
+
+    let control_plane = guest.env().control_plane.clone();
+    control_plane
+        .signal_process(child.pid(), Signal::Sigstop)
+        .unwrap();
+    assert_eq!(child.stopped(), Some(Signal::Sigstop));
+
+    let args =
+        |pid: u32, flags: JoinFlags| [Value::I32(pid as i32), Value::I32(flags.bits() as i32)];
+    let pid = child.pid().raw();
+    let status = |guest: &Guest| guest.memory_read(8, 4);
+
+    // Stopped children are only reported to the callers that ask for it
+    assert_eq!(guest.call("join", &args(pid, JoinFlags::NON_BLOCKING)), 0);
+    assert_eq!(status(&guest)[0], JoinStatusType::Nothing as u8);
+
+    let wake_stopped = JoinFlags::WAKE_STOPPED | JoinFlags::NON_BLOCKING;
+    assert_eq!(guest.call("join", &args(pid, wake_stopped)), 0);
+    assert_eq!(guest.u32_at(4), pid);
+    assert_eq!(status(&guest)[0], JoinStatusType::Stopped as u8);
+    assert_eq!(status(&guest)[2], Signal::Sigstop as u8);
+
+    // ...and only once for every time that they stop
+    assert_eq!(guest.call("join", &args(pid, wake_stopped)), 0);
+    assert_eq!(status(&guest)[0], JoinStatusType::Nothing as u8);
+
+    control_plane
+        .signal_process(child.pid(), Signal::Sigcont)
+        .unwrap();
+    assert_eq!(child.stopped(), None);
+
+    // Blocking joins on any child return as soon as one stops
+    control_plane
+        .signal_process(child.pid(), Signal::Sigstop)
+        .unwrap();
+    assert_eq!(guest.call("join", &args(0, JoinFlags::WAKE_STOPPED)), 0);
+    assert_eq!(guest.u32_at(4), pid);
+    assert_eq!(status(&guest)[0], JoinStatusType::Stopped as u8);
+
+    // Killing a stopped process continues it so that it can exit
+    control_plane
+        .signal_process(child.pid(), Signal::Sigkill)
+        .unwrap();
+    assert_eq!(child.stopped(), None);
+}
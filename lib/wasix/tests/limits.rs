@@ -0,0 +1,154 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::{
+    capabilities::{Capabilities, CapabilityLimitsV1},
+    wasmer_wasix_types::wasi::Errno,
+    WasiEnv,
+};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "proc_getrlimit"
+        (func $proc_getrlimit (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_setrlimit"
+        (func $proc_setrlimit (param i32 i64 i64) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open"
+        (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; The soft limit is stored at 0 and the hard limit at 8
+    (func (export "getrlimit") (param $resource i32) (result i32)
+        (call $proc_getrlimit (local.get $resource) (i32.const 0) (i32.const 8)))
+    (func (export "setrlimit") (param $resource i32) (param $soft i64) (param $hard i64)
+        (result i32)
+        (call $proc_setrlimit (local.get $resource) (local.get $soft) (local.get $hard)))
+
+    ;; Creates (or opens) the file whose path is stored at 256 (relative to
+    ;; the root that is preopened as descriptor 4) for writing, the
+    ;; descriptor is stored at 0
+    (func (export "open") (param $len i32) (result i32)
+        (call $path_open (i32.const 4) (i32.const 0) (i32.const 256) (local.get $len)
+            (i32.const 1) (i64.const 0x42) (i64.const 0) (i32.const 0) (i32.const 0)))
+
+    ;; Writes the first bytes at 1024 to a descriptor, the number of bytes
+    ;; written is stored at 0
+    (func (export "write") (param $fd i32) (param $len i32) (result i32)
+        (i32.store (i32.const 16) (i32.const 1024))
+        (i32.store (i32.const 20) (local.get $len))
+        (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 0)))
+
+    ;; Returns the previous number of pages of the memory, or -1
+    (func (export "grow") (param $pages i32) (result i32)
+        (memory.grow (local.get $pages)))
+
+    (func (export "_start"))
+)
+"#;
+
+const RLIMIT_NOFILE: i32 = 0;
+const RLIMIT_FSIZE: i32 = 1;
+const RLIMIT_AS: i32 = 2;
+
+fn call(guest: &mut Guest, func: &str, args: &[Value]) -> Result<u32, i32> {
+    guest.call_u32(func, args, 0)
+}
+
+fn getrlimit(guest: &mut Guest, resource: i32) -> (u64, u64) {
+    call(guest, "getrlimit", &[Value::I32(resource)]).unwrap();
+    (guest.u64_at(0), guest.u64_at(8))
+}
+
+fn setrlimit(guest: &mut Guest, resource: i32, soft: u64, hard: u64) -> Result<u32, i32> {
+    call(
+        guest,
+        "setrlimit",
+        &[
+            Value::I32(resource),
+            Value::I64(soft as i64),
+            Value::I64(hard as i64),
+        ],
+    )
+}
+
+fn open(guest: &mut Guest, path: &str) -> Result<u32, i32> {
+    guest.memory_write(256, path.as_bytes());
+    call(guest, "open", &[Value::I32(path.len() as i32)])
+}
+
+fn write(guest: &mut Guest, fd: u32, len: usize) -> Result<u32, i32> {
+    call(
+        guest,
+        "write",
+        &[Value::I32(fd as i32), Value::I32(len as i32)],
+    )
+}
+
+fn grow(guest: &mut Guest, pages: i32) -> i32 {
+    guest.call("grow", &[Value::I32(pages)])
+}
+
+#[test]
+fn test_open_files_are_limited_by_the_host() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut capabilities = Capabilities::new();
+    capabilities.limits = CapabilityLimitsV1 {
+        max_open_files: Some(16),
+        ..Default::default()
+    };
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("limits").capabilities(capabilities));
+
+    assert_eq!(getrlimit(&mut guest, RLIMIT_NOFILE), (16, 16));
+
+    let opened = (0..16)
+        .take_while(|_| open(&mut guest, "file").is_ok())
+        .count();
+    assert!(opened > 0 && opened < 16);
+    assert_eq!(open(&mut guest, "file"), Err(Errno::Mfile as i32));
+}
+
+#[test]
+fn test_unprivileged_processes_only_lower_limits() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("limits").user(1000, 1000));
+
+    assert_eq!(getrlimit(&mut guest, RLIMIT_FSIZE), (u64::MAX, u64::MAX));
+
+    setrlimit(&mut guest, RLIMIT_FSIZE, 4, 8).unwrap();
+    assert_eq!(getrlimit(&mut guest, RLIMIT_FSIZE), (4, 8));
+    assert_eq!(
+        setrlimit(&mut guest, RLIMIT_FSIZE, 4, u64::MAX),
+        Err(Errno::Perm as i32)
+    );
+    assert_eq!(
+        setrlimit(&mut guest, RLIMIT_FSIZE, 8, 4),
+        Err(Errno::Inval as i32)
+    );
+
+    // Writes stop at the soft limit
+    let fd = open(&mut guest, "file").unwrap();
+    assert_eq!(write(&mut guest, fd, 6), Ok(4));
+    assert_eq!(write(&mut guest, fd, 6), Err(Errno::Fbig as i32));
+}
+
+#[test]
+fn test_memory_grows_up_to_the_soft_limit() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("limits"));
+
+    setrlimit(&mut guest, RLIMIT_AS, 3, u64::MAX).unwrap();
+    assert_eq!(grow(&mut guest, 2), 1);
+    assert_eq!(grow(&mut guest, 1), -1);
+
+    setrlimit(&mut guest, RLIMIT_AS, 4, u64::MAX).unwrap();
+    assert_eq!(grow(&mut guest, 1), 3);
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::runtime;
+use wasmer::{wat2wasm, Module, Store, TypedFunction};
+use wasmer_wasix::{PreInitError, PreInitializer, WasiEnv};
+
+const WAT: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "environ_sizes_get"
+        (func $environ_sizes_get (param i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+    (global $initialized (mut i32) (i32.const 0))
+
+    ;; Stores the number of environment variables at the start of the
+    ;; third page, which the initialization has to grow the memory for
+    (func (export "init")
+        (drop (memory.grow (i32.const 2)))
+        (drop (call $environ_sizes_get (i32.const 131072) (i32.const 131076)))
+        (global.set $initialized (i32.const 1)))
+
+    (func (export "env_count") (result i32)
+        (i32.load (i32.const 131072)))
+    (func (export "initialized") (result i32)
+        (global.get $initialized))
+    (func (export "_start"))
+)
+"#;
+
+#[test]
+fn test_pre_init_captures_state() {
+    let wasm = wat2wasm(WAT.as_bytes()).unwrap();
+
+    let mut store = Store::default();
+    let module = Module::new(&store, &wasm).unwrap();
+    let builder = WasiEnv::builder("pre-init").env("DOG", "1").env("CAT", "2");
+    let pre_initialized = PreInitializer::new("init")
+        .run(builder, &mut store, module, &wasm)
+        .unwrap();
+
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut store = Store::default();
+    let module = Module::new(&store, pre_initialized).unwrap();
+    assert!(module.exports().all(|export| export.name() != "init"));
+
+    let (instance, _env) = WasiEnv::builder("pre-init")
+        .instantiate(module, &mut store)
+        .unwrap();
+    let env_count: TypedFunction<(), i32> = instance
+        .exports
+        .get_typed_function(&store, "env_count")
+        .unwrap();
+    let initialized: TypedFunction<(), i32> = instance
+        .exports
+        .get_typed_function(&store, "initialized")
+        .unwrap();
+    assert_eq!(env_count.call(&mut store).unwrap(), 2);
+    assert_eq!(initialized.call(&mut store).unwrap(), 1);
+}
+
+#[test]
+fn test_pre_init_is_deterministic() {
+    let wasm = wat2wasm(WAT.as_bytes()).unwrap();
+    let pre_initialize = || {
+        let mut store = Store::default();
+        let module = Module::new(&store, &wasm).unwrap();
+        let builder = WasiEnv::builder("pre-init").env("DOG", "1");
+        PreInitializer::new("init")
+            .run(builder, &mut store, module, &wasm)
+            .unwrap()
+    };
+
+    assert_eq!(pre_initialize(), pre_initialize());
+}
+
+#[test]
+fn test_pre_init_missing_function() {
+    let wasm = wat2wasm(WAT.as_bytes()).unwrap();
+
+    let mut store = Store::default();
+    let module = Module::new(&store, &wasm).unwrap();
+    let error = PreInitializer::new("wizer.initialize")
+        .run(WasiEnv::builder("pre-init"), &mut store, module, &wasm)
+        .unwrap_err();
+    assert!(matches!(error, PreInitError::MissingInitFunction(name) if name == "wizer.initialize"));
+
+    // Reactors are initialized while they are instantiated, so their
+    // `_initialize` export has to be checked separately
+    let module = Module::new(&store, &wasm).unwrap();
+    let error = PreInitializer::new("_initialize")
+        .run(WasiEnv::builder("pre-init"), &mut store, module, &wasm)
+        .unwrap_err();
+    assert!(matches!(error, PreInitError::MissingInitFunction(name) if name == "_initialize"));
+}
@@ -0,0 +1,98 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::WasiEnv;
+
+const WAT: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "path_open"
+        (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_readlink"
+        (func $path_readlink (param i32 i32 i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; Reads the file whose path is stored at 256 (relative to the root
+    ;; that is preopened as descriptor 4) into 1024, the number of bytes
+    ;; read is stored at 8
+    (func (export "read") (param $len i32) (result i32)
+        (local $ret i32)
+        (local.set $ret (call $path_open (i32.const 4) (i32.const 0) (i32.const 256)
+            (local.get $len) (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0)
+            (i32.const 0)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (i32.store (i32.const 16) (i32.const 1024))
+        (i32.store (i32.const 20) (i32.const 1024))
+        (call $fd_read (i32.load (i32.const 0)) (i32.const 16) (i32.const 1) (i32.const 8)))
+
+    ;; Reads the link whose path is stored at 256 into 1024, its length is
+    ;; stored at 8
+    (func (export "readlink") (param $len i32) (result i32)
+        (call $path_readlink (i32.const 4) (i32.const 256) (local.get $len)
+            (i32.const 1024) (i32.const 1024) (i32.const 8)))
+
+    (func (export "grow") (param $pages i32) (result i32)
+        (memory.grow (local.get $pages)))
+
+    (func (export "_start"))
+)
+"#;
+
+/// Calls `read` or `readlink` with a path and returns what was read
+fn call(guest: &mut Guest, func: &str, path: &str) -> Result<String, i32> {
+    guest.memory_write(256, path.as_bytes());
+    let len = guest.call_u32(func, &[Value::I32(path.len() as i32)], 8)?;
+    Ok(guest.string_at(1024, len as usize))
+}
+
+#[test]
+fn test_proc_self() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("proc-fs").args(["--verbose"]));
+    let pid = guest.env().process.pid();
+
+    let status = call(&mut guest, "read", "proc/self/status").unwrap();
+    assert!(status.contains("Name:\tproc-fs\n"));
+    assert!(status.contains("State:\tR (running)\n"));
+    assert!(status.contains(&format!("Pid:\t{pid}\n")));
+    assert!(status.contains("Threads:\t1\n"));
+    assert!(status.contains("VmSize:\t      64 kB\n"));
+    assert_eq!(
+        call(&mut guest, "read", &format!("proc/{pid}/status")).unwrap(),
+        status
+    );
+
+    let cmdline = call(&mut guest, "read", "proc/self/cmdline").unwrap();
+    assert_eq!(cmdline, "proc-fs\0--verbose\0");
+
+    let exe = call(&mut guest, "readlink", "proc/self/exe").unwrap();
+    assert_eq!(exe, "proc-fs");
+    let cwd = call(&mut guest, "readlink", "proc/self/cwd").unwrap();
+    assert_eq!(cwd, "/");
+    let stdin = call(&mut guest, "readlink", "proc/self/fd/0").unwrap();
+    assert_eq!(stdin, "/dev/stdin");
+
+    let cpuinfo = call(&mut guest, "read", "proc/cpuinfo").unwrap();
+    assert!(cpuinfo.starts_with("processor\t: 0\n"));
+    let meminfo = call(&mut guest, "read", "proc/meminfo").unwrap();
+    assert!(meminfo.starts_with("MemTotal:"));
+
+    // The size of the memory is updated as it grows
+    assert_eq!(guest.call("grow", &[Value::I32(2)]), 1);
+    let status = call(&mut guest, "read", "proc/self/status").unwrap();
+    assert!(status.contains("VmSize:\t     192 kB\n"));
+}
+
+#[test]
+fn test_proc_fs_disabled() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::with_tmp_fs(WAT, WasiEnv::builder("proc-fs").proc_fs(false));
+    assert!(call(&mut guest, "read", "proc/self/status").is_err());
+}
@@ -0,0 +1,182 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::{runtime, with_root, Guest};
+use wasmer::Value;
+use wasmer_wasix::{
+    types::Signal, virtual_fs::RootFileSystemBuilder, wasmer_wasix_types::wasi::Errno, WasiEnv,
+};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "pty_open" (func $pty_open (param i32 i32) (result i32)))
+    (import "wasix_32v1" "pty_open_slave" (func $pty_open_slave (param i32 i32) (result i32)))
+    (import "wasix_32v1" "pty_get" (func $pty_get (param i32 i32) (result i32)))
+    (import "wasix_32v1" "pty_set" (func $pty_set (param i32 i32) (result i32)))
+    (import "wasix_32v1" "pty_set_ctty" (func $pty_set_ctty (param i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_open"
+        (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; Both of these store the master end at 0 and the slave end at 4
+    (func (export "openpty") (result i32)
+        (call $pty_open (i32.const 0) (i32.const 4)))
+    (func (export "openpt") (param $len i32) (result i32)
+        (local $ret i32)
+        ;; Opens the path that is stored at 256 relative to the root that is
+        ;; preopened as descriptor 4
+        (local.set $ret (call $path_open (i32.const 4) (i32.const 0) (i32.const 256)
+            (local.get $len) (i32.const 0) (i64.const 66) (i64.const 0) (i32.const 0)
+            (i32.const 0)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (call $pty_open_slave (i32.load (i32.const 0)) (i32.const 4)))
+    ;; Opens the path that is stored at 256 and stores the descriptor at 12
+    (func (export "open") (param $len i32) (result i32)
+        (call $path_open (i32.const 4) (i32.const 0) (i32.const 256) (local.get $len)
+            (i32.const 0) (i64.const 66) (i64.const 0) (i32.const 0) (i32.const 12)))
+
+    ;; The state of the terminal is stored at 512
+    (func (export "get") (param $fd i32) (result i32)
+        (call $pty_get (local.get $fd) (i32.const 512)))
+    (func (export "set") (param $fd i32) (result i32)
+        (call $pty_set (local.get $fd) (i32.const 512)))
+    (func (export "setctty") (param $fd i32) (result i32)
+        (call $pty_set_ctty (local.get $fd)))
+
+    ;; Both of these use the buffer at 1024, the number of bytes that were
+    ;; read or written is stored at 8
+    (func (export "read") (param $fd i32) (result i32)
+        (i32.store (i32.const 16) (i32.const 1024))
+        (i32.store (i32.const 20) (i32.const 1024))
+        (call $fd_read (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 8)))
+    (func (export "write") (param $fd i32) (param $len i32) (result i32)
+        (i32.store (i32.const 16) (i32.const 1024))
+        (i32.store (i32.const 20) (local.get $len))
+        (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 8)))
+
+    (func (export "_start"))
+)
+"#;
+
+fn instantiate() -> Guest {
+    let builder = with_root(
+        WasiEnv::builder("pty"),
+        RootFileSystemBuilder::new().build(),
+    );
+    Guest::new(WAT, builder)
+}
+
+fn call(guest: &mut Guest, func: &str, param: u32) -> i32 {
+    guest.call(func, &[Value::I32(param as i32)])
+}
+
+/// Returns the master and the slave end that were opened
+fn ends(guest: &Guest) -> (u32, u32) {
+    (guest.u32_at(0), guest.u32_at(4))
+}
+
+fn write(guest: &mut Guest, fd: u32, data: &str) {
+    guest.memory_write(1024, data.as_bytes());
+    let args = [Value::I32(fd as i32), Value::I32(data.len() as i32)];
+    assert_eq!(guest.call("write", &args), 0);
+}
+
+/// Reads until as many bytes as the expected output has were read
+fn read(guest: &mut Guest, fd: u32, expected: &str) -> String {
+    let mut data = Vec::new();
+    while data.len() < expected.len() {
+        assert_eq!(call(guest, "read", fd), 0);
+        let read = guest.u32_at(8);
+        assert_ne!(read, 0);
+        data.extend(guest.memory_read(1024, read as usize));
+    }
+    String::from_utf8(data).unwrap()
+}
+
+#[test]
+fn test_pty_line_discipline() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+
+    let mut guest = instantiate();
+    assert_eq!(guest.call("openpty", &[]), 0);
+    let (master, slave) = ends(&guest);
+
+    // The input reaches the slave end once the line is complete, the
+    // terminal echoes it back to the master end
+    write(&mut guest, master, "ls -x\u{7f}l\r");
+    assert_eq!(read(&mut guest, slave, "ls -l\n"), "ls -l\n");
+    assert_eq!(
+        read(&mut guest, master, "ls -x\u{8} \u{8}l\r\n"),
+        "ls -x\u{8} \u{8}l\r\n"
+    );
+
+    write(&mut guest, slave, "total 0\n");
+    assert_eq!(read(&mut guest, master, "total 0\r\n"), "total 0\r\n");
+
+    // Only the ends of pseudo-terminals have a terminal state
+    assert_eq!(call(&mut guest, "get", 1), Errno::Notty as i32);
+    assert_eq!(call(&mut guest, "setctty", 1), Errno::Notty as i32);
+}
+
+#[test]
+fn test_pty_window_size() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+
+    let mut guest = instantiate();
+    let path = "dev/ptmx";
+    guest.memory_write(256, path.as_bytes());
+    assert_eq!(call(&mut guest, "openpt", path.len() as u32), 0);
+    let (master, slave) = ends(&guest);
+    assert_ne!(master, slave);
+
+    // The process was started on its own so it leads its session and can
+    // make the terminal its controlling terminal
+    assert_eq!(call(&mut guest, "setctty", slave), 0);
+
+    // Both ends share the state of the terminal (the columns are stored
+    // first, followed by the rows)
+    assert_eq!(call(&mut guest, "get", slave), 0);
+    assert_eq!(guest.u32_at(512), 80);
+    assert_eq!(guest.u32_at(516), 25);
+
+    guest.memory_write(512, &120u32.to_le_bytes());
+    assert_eq!(call(&mut guest, "set", master), 0);
+    assert!(guest.env().thread.has_signal(&[Signal::Sigwinch]));
+
+    assert_eq!(call(&mut guest, "get", slave), 0);
+    assert_eq!(guest.memory_read(512, 4), 120u32.to_le_bytes());
+}
+
+#[test]
+fn test_pty_slave_is_listed_in_dev_pts() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+
+    let mut guest = instantiate();
+    assert_eq!(guest.call("openpty", &[]), 0);
+    let (master, _) = ends(&guest);
+
+    // The first pseudo-terminal of the control plane is `/dev/pts/0`
+    let path = "dev/pts/0";
+    guest.memory_write(256, path.as_bytes());
+    assert_eq!(call(&mut guest, "open", path.len() as u32), 0);
+    let slave = guest.u32_at(12);
+
+    write(&mut guest, slave, "hello\n");
+    assert_eq!(read(&mut guest, master, "hello\r\n"), "hello\r\n");
+
+    let path = "dev/pts/1";
+    guest.memory_write(256, path.as_bytes());
+    assert_eq!(
+        call(&mut guest, "open", path.len() as u32),
+        Errno::Noent as i32
+    );
+}
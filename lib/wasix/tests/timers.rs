@@ -0,0 +1,154 @@
+#![cfg(feature = "sys-thread")]
+
+use std::time::Duration;
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::{wasmer_wasix_types::wasi::Errno, WasiEnv};
+
+const WAT: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "clock_time_get"
+        (func $clock_time_get (param i32 i64 i32) (result i32)))
+    (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+    (import "wasix_32v1" "proc_rusage" (func $proc_rusage (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_timer_create"
+        (func $proc_timer_create (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_timer_settime"
+        (func $proc_timer_settime (param i32 i32 i64 i64 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_timer_gettime"
+        (func $proc_timer_gettime (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "proc_timer_delete" (func $proc_timer_delete (param i32) (result i32)))
+
+    (memory (export "memory") 1)
+
+    ;; Each of these store their first result at 0 and the second one at 8
+    (func (export "clock") (param $id i32) (result i32)
+        (call $clock_time_get (local.get $id) (i64.const 1) (i32.const 0)))
+    (func (export "rusage") (param $who i32) (result i32)
+        (call $proc_rusage (local.get $who) (i32.const 0) (i32.const 8)))
+    (func (export "timer_create") (param $id i32) (result i32)
+        (call $proc_timer_create (local.get $id) (i32.const 14) (i32.const 0)))
+    (func (export "timer_settime") (param $timer i32) (param $value i64) (param $interval i64)
+        (result i32)
+        (call $proc_timer_settime (local.get $timer) (i32.const 0) (local.get $value)
+            (local.get $interval) (i32.const 0) (i32.const 8)))
+    (func (export "timer_gettime") (param $timer i32) (result i32)
+        (call $proc_timer_gettime (local.get $timer) (i32.const 0) (i32.const 8)))
+    (func (export "timer_delete") (param $timer i32) (result i32)
+        (call $proc_timer_delete (local.get $timer)))
+
+    (func (export "sleep") (param $duration i64) (result i32)
+        (call $thread_sleep (local.get $duration)))
+
+    (func (export "_start"))
+)
+"#;
+
+const PROCESS_CPUTIME: i32 = 2;
+const THREAD_CPUTIME: i32 = 3;
+const MONOTONIC: i32 = 1;
+
+/// Calls a function and returns the two results that it stored
+fn call(guest: &mut Guest, func: &str, args: &[Value]) -> Result<(u64, u64), i32> {
+    match guest.call(func, args) {
+        0 => Ok((guest.u64_at(0), guest.u64_at(8))),
+        err => Err(err),
+    }
+}
+
+#[test]
+fn test_cpu_clocks_exclude_idle_time() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::new(WAT, WasiEnv::builder("timers"));
+
+    let sleep = Duration::from_millis(500);
+    call(&mut guest, "sleep", &[Value::I64(sleep.as_nanos() as i64)]).unwrap();
+
+    // The time the thread spent sleeping is not CPU time, and the process
+    // consumed at least the time of its thread (which is read first)
+    let (thread, _) = call(&mut guest, "clock", &[Value::I32(THREAD_CPUTIME)]).unwrap();
+    let (process, _) = call(&mut guest, "clock", &[Value::I32(PROCESS_CPUTIME)]).unwrap();
+    assert!(Duration::from_nanos(process) < sleep);
+    assert!(thread <= process);
+
+    let (utime, stime) = call(&mut guest, "rusage", &[Value::I32(0)]).unwrap();
+    assert!(utime >= process);
+    assert!(Duration::from_nanos(utime) < sleep);
+    assert_eq!(stime, 0);
+    assert_eq!(
+        call(&mut guest, "rusage", &[Value::I32(-1)]).unwrap(),
+        (0, 0)
+    );
+    assert_eq!(
+        call(&mut guest, "rusage", &[Value::I32(7)]),
+        Err(Errno::Inval as i32)
+    );
+}
+
+#[test]
+fn test_timers() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = Guest::new(WAT, WasiEnv::builder("timers"));
+
+    let (first, _) = call(&mut guest, "timer_create", &[Value::I32(MONOTONIC)]).unwrap();
+    let (second, _) = call(&mut guest, "timer_create", &[Value::I32(PROCESS_CPUTIME)]).unwrap();
+    assert_ne!(first, second);
+    let (first, second) = (Value::I32(first as i32), Value::I32(second as i32));
+
+    // Timers start disarmed
+    assert_eq!(
+        call(&mut guest, "timer_gettime", &[first.clone()]).unwrap(),
+        (0, 0)
+    );
+
+    let hour = Duration::from_secs(3600).as_nanos() as u64;
+    let minute = Duration::from_secs(60).as_nanos() as u64;
+    let nanos = |value: u64| Value::I64(value as i64);
+    assert_eq!(
+        call(
+            &mut guest,
+            "timer_settime",
+            &[first.clone(), nanos(hour), nanos(minute)]
+        )
+        .unwrap(),
+        (0, 0)
+    );
+    let (left, interval) = call(&mut guest, "timer_gettime", &[first.clone()]).unwrap();
+    assert!(left > 0 && left <= hour);
+    assert_eq!(interval, minute);
+
+    // The timers are independent of each other
+    assert_eq!(
+        call(&mut guest, "timer_gettime", &[second.clone()]).unwrap(),
+        (0, 0)
+    );
+
+    // Setting a timer returns its previous state and zero disarms it
+    let (left, interval) = call(
+        &mut guest,
+        "timer_settime",
+        &[first.clone(), nanos(0), nanos(0)],
+    )
+    .unwrap();
+    assert!(left > 0 && left <= hour);
+    assert_eq!(interval, minute);
+    assert_eq!(
+        call(&mut guest, "timer_gettime", &[first.clone()]).unwrap(),
+        (0, 0)
+    );
+
+    call(&mut guest, "timer_delete", &[first.clone()]).unwrap();
+    assert_eq!(
+        call(&mut guest, "timer_gettime", &[first.clone()]),
+        Err(Errno::Inval as i32)
+    );
+    assert_eq!(
+        call(&mut guest, "timer_delete", &[first.clone()]),
+        Err(Errno::Inval as i32)
+    );
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "sys-thread")]
+
+mod common;
+
+use common::{runtime, Guest};
+use wasmer::Value;
+use wasmer_wasix::{wasmer_wasix_types::wasi::Errno, WasiEnv};
+
+const WAT: &str = r#"
+(module
+    (import "wasix_32v1" "sock_pair"
+        (func $sock_pair (param i32 i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_open"
+        (func $sock_open (param i32 i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_bind_unix"
+        (func $sock_bind_unix (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_listen"
+        (func $sock_listen (param i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_connect_unix"
+        (func $sock_connect_unix (param i32 i32 i32) (result i32)))
+    (import "wasix_32v1" "sock_accept_v2"
+        (func $sock_accept_v2 (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "fd_read"
+        (func $fd_read (param i32 i32 i32 i32) (result i32)))
+    (import "wasi_snapshot_preview1" "path_unlink_file"
+        (func $path_unlink_file (param i32 i32 i32) (result i32)))
+
+    (memory (export "memory") 1)
+    (data (i32.const 64) "ping")
+    (data (i32.const 96) "/test.sock")
+
+    ;; Creates a pair of connected UNIX sockets, the descriptors are
+    ;; stored at 0 and 4
+    (func (export "pair") (param $ty i32) (result i32)
+        (call $sock_pair (i32.const 3) (local.get $ty) (i32.const 0)
+            (i32.const 0) (i32.const 4)))
+
+    ;; Connects a client (stored at 0) to a server listening on
+    ;; `/test.sock`, the accepted connection is stored at 4
+    (func (export "connect") (result i32)
+        (local $ret i32)
+        (local.set $ret (call $sock_open (i32.const 3) (i32.const 1) (i32.const 0) (i32.const 8)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (local.set $ret (call $sock_bind_unix (i32.load (i32.const 8)) (i32.const 96) (i32.const 10)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (local.set $ret (call $sock_listen (i32.load (i32.const 8)) (i32.const 1)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (local.set $ret (call $sock_open (i32.const 3) (i32.const 1) (i32.const 0) (i32.const 0)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (local.set $ret (call $sock_connect_unix (i32.load (i32.const 0)) (i32.const 96) (i32.const 10)))
+        (if (local.get $ret) (then (return (local.get $ret))))
+        (call $sock_accept_v2 (i32.load (i32.const 8)) (i32.const 0) (i32.const 4) (i32.const 160)))
+
+    ;; Writes "ping" to the descriptor stored at 0
+    (func (export "send") (result i32)
+        (i32.store (i32.const 16) (i32.const 64))
+        (i32.store (i32.const 20) (i32.const 4))
+        (call $fd_write (i32.load (i32.const 0)) (i32.const 16) (i32.const 1) (i32.const 24)))
+
+    ;; Reads up to 16 bytes at 128 from the descriptor stored at 4
+    (func (export "recv") (result i32)
+        (i32.store (i32.const 32) (i32.const 128))
+        (i32.store (i32.const 36) (i32.const 16))
+        (call $fd_read (i32.load (i32.const 4)) (i32.const 32) (i32.const 1) (i32.const 40)))
+
+    ;; Removes `test.sock` from the root that is preopened as descriptor 4
+    (func (export "unlink") (result i32)
+        (call $path_unlink_file (i32.const 4) (i32.const 97) (i32.const 9)))
+
+    (func (export "received") (result i32)
+        (i32.load (i32.const 40)))
+    (func (export "_start"))
+)
+"#;
+
+const SOCK_STREAM: i32 = 1;
+const SOCK_DGRAM: i32 = 2;
+
+fn instantiate() -> Guest {
+    Guest::with_tmp_fs(WAT, WasiEnv::builder("unix-sockets"))
+}
+
+fn ping(guest: &mut Guest) {
+    assert_eq!(guest.call("send", &[]), 0);
+    assert_eq!(guest.call("recv", &[]), 0);
+    assert_eq!(guest.call("received", &[]), 4);
+    assert_eq!(guest.memory_read(128, 4), b"ping");
+}
+
+#[test]
+fn test_unix_stream_pair() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = instantiate();
+    assert_eq!(guest.call("pair", &[Value::I32(SOCK_STREAM)]), 0);
+    ping(&mut guest);
+}
+
+#[test]
+fn test_unix_datagram_pair() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = instantiate();
+    assert_eq!(guest.call("pair", &[Value::I32(SOCK_DGRAM)]), 0);
+    ping(&mut guest);
+}
+
+#[test]
+fn test_unix_listener() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = instantiate();
+    assert_eq!(guest.call("connect", &[]), 0);
+    ping(&mut guest);
+}
+
+#[test]
+fn test_unix_listener_path_is_released_on_unlink() {
+    let runtime = runtime();
+    let _guard = runtime.enter();
+    let mut guest = instantiate();
+    assert_eq!(guest.call("connect", &[]), 0);
+
+    // The path stays taken while the listener lives, until it is removed
+    assert_eq!(guest.call("connect", &[]), Errno::Addrinuse as i32);
+    assert_eq!(guest.call("unlink", &[]), 0);
+    assert_eq!(guest.call("connect", &[]), 0);
+    ping(&mut guest);
+}
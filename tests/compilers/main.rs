@@ -14,6 +14,7 @@ mod middlewares;
 // mod multi_value_imports;
 mod artifact;
 mod serialize;
+mod stack_limit;
 mod traps;
 mod typed_functions;
 mod wasi;
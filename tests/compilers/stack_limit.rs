@@ -0,0 +1,72 @@
+use anyhow::Result;
+use wasmer_middlewares::stack_limit::{self, get_stack_usage, set_stack_usage};
+use wasmer_middlewares::StackLimit;
+
+use std::sync::Arc;
+use wasmer::*;
+use wasmer_types::TrapCode;
+
+fn instantiate(mut config: crate::Config, limit: u64) -> Result<(Store, Instance)> {
+    config
+        .middlewares
+        .push(Arc::new(StackLimit::new(limit, |_: &FunctionType| 1)));
+    let mut store = config.store();
+    let wat = r#"(module
+        (func $down (export "down") (param $n i32) (result i32)
+            (if (i32.eqz (local.get $n))
+                (then (return (i32.const 0))))
+            (i32.add
+                (call $down (i32.sub (local.get $n) (i32.const 1)))
+                (i32.const 1)))
+        (func $br_if (export "br_if") (param $n i32) (result i32)
+            (local.get $n)
+            (br_if 0 (local.get $n))
+            (drop)
+            (i32.const -1))
+        (func (export "trap") (param $n i32)
+            (drop (call $down (local.get $n)))
+            (unreachable))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let import_object = stack_limit::imports(&mut store);
+    let instance = Instance::new(&mut store, &module, &import_object)?;
+    Ok((store, instance))
+}
+
+#[compiler_test(stack_limit)]
+fn stack_limit_ok(config: crate::Config) -> Result<()> {
+    let (mut store, instance) = instantiate(config, 10)?;
+    let down: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "down")?;
+    let br_if: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "br_if")?;
+    assert_eq!(down.call(&mut store, 9)?, 9);
+    assert_eq!(br_if.call(&mut store, 3)?, 3);
+    assert_eq!(br_if.call(&mut store, 0)?, -1);
+    assert_eq!(get_stack_usage(&mut store, &instance), 0);
+    Ok(())
+}
+
+#[compiler_test(stack_limit)]
+fn stack_limit_exceeded(config: crate::Config) -> Result<()> {
+    let (mut store, instance) = instantiate(config, 10)?;
+    let down: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "down")?;
+    let error = down.call(&mut store, 10).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::StackLimitExceeded));
+    Ok(())
+}
+
+#[compiler_test(stack_limit)]
+fn stack_usage_is_kept_after_traps(config: crate::Config) -> Result<()> {
+    let (mut store, instance) = instantiate(config, 10)?;
+    let down: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "down")?;
+    let trap: TypedFunction<i32, ()> = instance.exports.get_typed_function(&store, "trap")?;
+
+    // The frame of `trap` isn't released by the trap.
+    let error = trap.call(&mut store, 3).unwrap_err();
+    assert_eq!(error.to_trap(), Some(TrapCode::UnreachableCodeReached));
+    assert_eq!(get_stack_usage(&mut store, &instance), 1);
+    assert!(down.call(&mut store, 9).is_err());
+
+    set_stack_usage(&mut store, &instance, 0);
+    assert_eq!(down.call(&mut store, 9)?, 9);
+    Ok(())
+}
@@ -402,6 +402,42 @@ fn run_invoke_works_with_nomain_wasi() {
     std::fs::remove_file(&module_file).unwrap();
 }
 
+#[test]
+fn run_trace_calls_prints_the_calls() {
+    let wat = r#"
+    (module
+        (func $double (param i32) (result i32)
+          local.get 0
+          i32.const 2
+          i32.mul)
+        (func $run (export "run") (param i32) (result i32)
+          local.get 0
+          call $double)
+      )
+    "#;
+
+    let temp = TempDir::new().unwrap();
+    let module_file = temp.path().join("trace.wat");
+    std::fs::write(&module_file, wat.as_bytes()).unwrap();
+
+    let assert = Command::new(get_wasmer_path())
+        .arg("run")
+        .arg("--trace-calls")
+        .arg("--invoke")
+        .arg("run")
+        .arg(&module_file)
+        .arg("21")
+        .assert()
+        .success();
+
+    assert
+        .stdout(contains("42"))
+        .stderr(contains("-> run(21)"))
+        .stderr(contains("  -> double(21)"))
+        .stderr(contains("  <- double = [42]"))
+        .stderr(contains("<- run = [42]"));
+}
+
 #[test]
 fn run_no_start_wasm_report_error() {
     let assert = Command::new(get_wasmer_path())